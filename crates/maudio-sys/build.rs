@@ -17,6 +17,14 @@ fn write_bindings(out_bindings: &std::path::Path) {
         builder = builder.clang_arg("-DMA_NO_VORBIS=1");
     }
 
+    // bindgen passes the target triple to clang, but the emscripten headers live in the emsdk sysroot
+    if is_emscripten() {
+        if let Some(sysroot) = emscripten_sysroot() {
+            builder = builder.clang_arg(format!("--sysroot={}", sysroot.display()));
+        }
+        builder = builder.clang_arg("-fvisibility=default");
+    }
+
     let bindings = builder.generate().expect("Unable to generate bindings");
     bindings
         .write_to_file(out_bindings)
@@ -76,7 +84,28 @@ fn backend_features(builder: &mut Build) {
     }
 }
 
+fn is_emscripten() -> bool {
+    env::var("CARGO_CFG_TARGET_OS").map_or(false, |os| os == "emscripten")
+}
+
+// Uses EMSCRIPTEN_SYSROOT if set, otherwise the sysroot of the emsdk pointed to by EMSDK.
+#[cfg(feature = "generate-bindings")]
+fn emscripten_sysroot() -> Option<PathBuf> {
+    println!("cargo:rerun-if-env-changed=EMSCRIPTEN_SYSROOT");
+    println!("cargo:rerun-if-env-changed=EMSDK");
+    if let Some(sysroot) = env::var_os("EMSCRIPTEN_SYSROOT") {
+        return Some(PathBuf::from(sysroot));
+    }
+    let emsdk = PathBuf::from(env::var_os("EMSDK")?);
+    Some(emsdk.join("upstream/emscripten/cache/sysroot"))
+}
+
 fn main() {
+    if is_emscripten() && !cfg!(feature = "generate-bindings") {
+        // The pre-generated bindings assume 64 bit pointers
+        panic!("target `wasm32-unknown-emscripten` requires the `generate-bindings` feature");
+    }
+
     if cfg!(feature = "generate-bindings") {
         let minor = rustc_minor().unwrap_or(0);
         if minor < 70 {
@@ -104,6 +133,11 @@ fn main() {
     // backend features
     backend_features(&mut cc_builder);
 
+    if is_emscripten() && cfg!(feature = "audio-worklets") {
+        // Also requires linking with -sAUDIO_WORKLET=1 -sWASM_WORKERS=1 -sASYNCIFY
+        cc_builder.define("MA_ENABLE_AUDIO_WORKLETS", "1");
+    }
+
     cc_builder
         .file("native/miniaudio_version_check.c")
         .file("native/miniaudio.c")
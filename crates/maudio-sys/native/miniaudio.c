@@ -8,3 +8,41 @@
     #undef STB_VORBIS_HEADER_ONLY
     #include "miniaudio/extras/stb_vorbis.c"
 #endif
+
+/*
+maudio extensions. They read decoder state miniaudio keeps private, so they must be built in the
+same translation unit as the implementation. Declared by hand in maudio-sys/src/lib.rs.
+*/
+
+/*
+Copies the PCM frame of each entry of an MP3 decoder's seek table to pPcmFrames, up to cap entries,
+and sets pSampleRate to the rate those frames are counted at. Returns the number of entries, which
+is 0 if the decoder does not decode MP3 or was created without seek points.
+*/
+MA_API ma_uint32 maudio_decoder_mp3_seek_points(const ma_decoder* pDecoder, ma_uint64* pPcmFrames, ma_uint32 cap, ma_uint32* pSampleRate)
+{
+#if defined(MA_HAS_MP3)
+    const ma_mp3* pMP3;
+    ma_uint32 iSeekPoint;
+
+    if (pDecoder == NULL || pDecoder->pBackend == NULL || pDecoder->pBackendVTable != &g_ma_decoding_backend_vtable_mp3) {
+        return 0;
+    }
+
+    pMP3 = (const ma_mp3*)pDecoder->pBackend;
+    if (pSampleRate != NULL) {
+        *pSampleRate = pMP3->dr.sampleRate;
+    }
+    for (iSeekPoint = 0; iSeekPoint < pMP3->seekPointCount && iSeekPoint < cap; iSeekPoint += 1) {
+        pPcmFrames[iSeekPoint] = pMP3->pSeekPoints[iSeekPoint].pcmFrameIndex;
+    }
+
+    return pMP3->seekPointCount;
+#else
+    (void)pDecoder;
+    (void)pPcmFrames;
+    (void)cap;
+    (void)pSampleRate;
+    return 0;
+#endif
+}
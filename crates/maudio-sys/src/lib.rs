@@ -14,9 +14,27 @@ pub mod ffi {
 #[cfg(not(feature = "generate-bindings"))]
 #[doc(hidden)]
 pub mod ffi {
-    #[cfg(unix)]
+    #[cfg(all(unix, not(target_os = "emscripten")))]
     include!("pregen_bindings/unix.rs");
 
     #[cfg(windows)]
     include!("pregen_bindings/windows.rs");
 }
+
+/// Functions defined in `native/miniaudio.c` next to the miniaudio implementation.
+///
+/// They are not part of miniaudio, so bindgen does not see them.
+#[doc(hidden)]
+pub mod ext {
+    use crate::ffi::{ma_decoder, ma_uint32, ma_uint64};
+
+    extern "C" {
+        /// Copies the PCM frame of each MP3 seek table entry, see `native/miniaudio.c`.
+        pub fn maudio_decoder_mp3_seek_points(
+            pDecoder: *const ma_decoder,
+            pPcmFrames: *mut ma_uint64,
+            cap: ma_uint32,
+            pSampleRate: *mut ma_uint32,
+        ) -> ma_uint32;
+    }
+}
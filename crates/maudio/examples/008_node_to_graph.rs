@@ -69,8 +69,8 @@ fn main() -> MaResult<()> {
 
     // Re-route the signal:
     // Sound -> low-pass filter -> graph endpoint
-    source_node.attach_output_bus(0, &mut lpf, 0)?;
-    lpf.attach_output_bus(0, &mut end_node, 0)?;
+    source_node.attach_output(0, &mut lpf, 0)?;
+    lpf.attach_output(0, &mut end_node, 0)?;
 
     source.play_sound()?;
     println!("Stopping in 5 seconds...");
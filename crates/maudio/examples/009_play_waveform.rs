@@ -34,7 +34,7 @@ fn main() -> MaResult<()> {
 
     // Connect a second source node to the endpoint. They will be mixed there
     let mut end_node = graph.endpoint();
-    src_node.attach_output_bus(0, &mut end_node, 0)?;
+    src_node.attach_output(0, &mut end_node, 0)?;
 
     // Start the sound
     // The source node added earlier will start feeding sound into the engine immediately.
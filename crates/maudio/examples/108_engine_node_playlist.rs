@@ -77,7 +77,7 @@ impl PlayList {
             DecoderBuilder::new_f32(graph.channels(), SampleRate::Sr44100).from_file(&path)?;
         let mut node = AttachedSourceNodeBuilder::new(graph, decoder).build()?;
         let mut endpoint = graph.endpoint();
-        node.attach_output_bus(0, &mut endpoint, 0)?;
+        node.attach_output(0, &mut endpoint, 0)?;
         self.current = Some(node);
 
         Ok(())
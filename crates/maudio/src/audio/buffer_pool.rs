@@ -0,0 +1,236 @@
+//! Reusable [`SampleBuffer`]s for applications reading audio block after block.
+//!
+//! Allocating a buffer for every block is cheap once, but a streaming application doing it
+//! hundreds of times per second for hours churns the allocator and fragments the heap. A
+//! [`BufferPool`] keeps the buffers it handed out once they are dropped, and hands them out
+//! again, so a steady stream of reads settles on a few allocations.
+//!
+//! A [`PooledBuffer`] derefs to a [`SampleBuffer`], and is filled with the
+//! `read_pcm_frames_into_buffer` methods of the engine reader and the sources.
+//!
+//! ```no_run
+//! # use maudio::audio::buffer_pool::BufferPool;
+//! # use maudio::engine::Engine;
+//! # fn main() -> maudio::MaResult<()> {
+//! # let engine = Engine::new()?;
+//! let pool = BufferPool::<f32>::for_engine(&engine)?;
+//! let mut reader = engine.try_acquire_reader()?;
+//!
+//! loop {
+//!     let mut block = pool.get();
+//!     reader.read_pcm_frames_into_buffer(&mut block, pool.frames() as u64)?;
+//!     // Send the block to another thread, it goes back to the pool when dropped
+//! #   break;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    audio::formats::SampleBuffer,
+    device::{device_type::DeviceType, DeviceOps},
+    engine::Engine,
+    pcm_frames::PcmFormat,
+    util::rt_check,
+    MaResult,
+};
+
+/// Number of buffers kept by a pool by default. Buffers dropped while it is full are freed.
+pub const DEFAULT_MAX_FREE: usize = 16;
+
+// Block size used for engines without a device, 10 ms like miniaudio's default period
+const NO_DEVICE_BLOCK_MILLIS: u32 = 10;
+
+/// Hands out reusable [`SampleBuffer`]s. See the [module docs](self).
+///
+/// Cloning the pool is cheap, and the clones share their buffers.
+pub struct BufferPool<F: PcmFormat> {
+    inner: Arc<PoolInner<F>>,
+}
+
+struct PoolInner<F: PcmFormat> {
+    frames: usize,
+    // Units of each buffer, see `SampleBuffer::required_len`
+    len: usize,
+    channels: u32,
+    max_free: usize,
+    free: Mutex<Vec<SampleBuffer<F>>>,
+}
+
+impl<F: PcmFormat> Clone for BufferPool<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<F: PcmFormat> BufferPool<F> {
+    /// Creates a pool of buffers holding `frames` frames of `channels` channels.
+    pub fn new(frames: usize, channels: u32) -> MaResult<Self> {
+        Self::with_max_free(frames, channels, DEFAULT_MAX_FREE)
+    }
+
+    /// Same as [`BufferPool::new`], keeping at most `max_free` unused buffers.
+    pub fn with_max_free(frames: usize, channels: u32, max_free: usize) -> MaResult<Self> {
+        let len = SampleBuffer::<F>::required_len(frames, channels, F::VEC_PCM_UNITS_PER_FRAME)?;
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                frames,
+                len,
+                channels,
+                max_free,
+                free: Mutex::new(Vec::with_capacity(max_free)),
+            }),
+        })
+    }
+
+    /// Creates a pool of buffers sized for one block of `engine`.
+    ///
+    /// A block is one period of the engine's device, or 10 ms without a device.
+    pub fn for_engine(engine: &Engine) -> MaResult<Self> {
+        let frames = match engine.device() {
+            Some(device) => device.timing(DeviceType::Playback)?.period_size_frames,
+            None => engine.sample_rate_u32() * NO_DEVICE_BLOCK_MILLIS / 1000,
+        };
+        Self::new(frames as usize, engine.channels())
+    }
+
+    /// Returns an empty buffer, reusing a free one if there is any.
+    pub fn get(&self) -> PooledBuffer<F> {
+        let reused = self.inner.lock_free().pop();
+        let buf = reused.unwrap_or_else(|| {
+            SampleBuffer::with_len_capacity(self.inner.len, self.inner.channels)
+        });
+        PooledBuffer {
+            buf: Some(buf),
+            pool: self.inner.clone(),
+        }
+    }
+
+    /// Number of frames each buffer holds without reallocating.
+    pub fn frames(&self) -> usize {
+        self.inner.frames
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.inner.channels
+    }
+
+    /// Number of buffers waiting to be reused.
+    pub fn free_count(&self) -> usize {
+        self.inner.lock_free().len()
+    }
+
+    /// Frees the buffers waiting to be reused.
+    pub fn shrink(&self) {
+        self.inner.lock_free().clear();
+    }
+}
+
+impl<F: PcmFormat> PoolInner<F> {
+    fn lock_free(&self) -> std::sync::MutexGuard<'_, Vec<SampleBuffer<F>>> {
+        rt_check::check("BufferPool");
+        // The list stays valid if a thread panicked while holding it
+        self.free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A [`SampleBuffer`] from a [`BufferPool`], returned to the pool when dropped.
+pub struct PooledBuffer<F: PcmFormat> {
+    // Only `None` after `into_inner`
+    buf: Option<SampleBuffer<F>>,
+    pool: Arc<PoolInner<F>>,
+}
+
+impl<F: PcmFormat> PooledBuffer<F> {
+    /// Takes the buffer out of the pool, it is not returned when dropped.
+    pub fn into_inner(mut self) -> SampleBuffer<F> {
+        self.buf.take().expect("buffer is only taken once")
+    }
+}
+
+impl<F: PcmFormat> Deref for PooledBuffer<F> {
+    type Target = SampleBuffer<F>;
+
+    fn deref(&self) -> &Self::Target {
+        self.buf.as_ref().expect("buffer is only taken once")
+    }
+}
+
+impl<F: PcmFormat> DerefMut for PooledBuffer<F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buf.as_mut().expect("buffer is only taken once")
+    }
+}
+
+impl<F: PcmFormat> Drop for PooledBuffer<F> {
+    fn drop(&mut self) {
+        let Some(mut buf) = self.buf.take() else {
+            return;
+        };
+        let mut free = self.pool.lock_free();
+        if free.len() < self.pool.max_free {
+            buf.clear();
+            free.push(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::waveform::{WaveFormBuilder, WaveFormOps},
+        engine::engine_builder::EngineBuilder,
+    };
+
+    #[test]
+    fn test_buffer_pool_reuses_buffers() {
+        let pool = BufferPool::<f32>::with_max_free(256, 2, 1).unwrap();
+        let mut wave = WaveFormBuilder::new_sine(SampleRate::Sr48000, 440.0)
+            .channels(2)
+            .build_f32()
+            .unwrap();
+
+        let mut first = pool.get();
+        wave.read_pcm_frames_into_buffer(&mut first, 256).unwrap();
+        assert_eq!(first.frames(), 256);
+        let ptr = first.data.as_ptr();
+        drop(first);
+        assert_eq!(pool.free_count(), 1);
+
+        let second = pool.get();
+        assert!(second.is_empty());
+        assert_eq!(second.frames(), 0);
+        assert_eq!(second.data.as_ptr(), ptr);
+
+        // Only one free buffer is kept
+        let third = pool.get();
+        drop(second);
+        drop(third);
+        assert_eq!(pool.free_count(), 1);
+
+        let owned = pool.get().into_inner();
+        assert_eq!(owned.data.capacity(), 512);
+        assert_eq!(pool.free_count(), 0);
+    }
+
+    #[test]
+    fn test_buffer_pool_for_engine_without_device() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let pool = BufferPool::<f32>::for_engine(&engine).unwrap();
+        assert_eq!(pool.frames(), 480);
+        assert_eq!(pool.channels(), 2);
+    }
+}
@@ -1,7 +1,9 @@
 //! Channel configuration and channel-related audio utilities.
+use std::ops::Deref;
+
 use maudio_sys::ffi as sys;
 
-use crate::{ErrorKinds, MaudioError};
+use crate::{ErrorKinds, MaResult, MaudioError};
 
 /// Channel mixing strategy used by the channel converter when a direct 1:1 channel-position mapping
 /// is not possible (or when channel counts differ).
@@ -352,6 +354,188 @@ impl From<ChannelPosition> for sys::ma_channel {
     }
 }
 
+/// Speaker position of every channel in an interleaved stream.
+///
+/// A channel map always holds between 1 and `MA_MAX_CHANNELS` channels. Maps built from
+/// user data are validated: every channel must be a known [`ChannelPosition`],
+/// [`ChannelPosition::Mono`] is only allowed in a single channel map, and a position can
+/// not be used twice. [`ChannelPosition::None`] may be repeated, it marks channels with
+/// no assigned position.
+///
+/// The standard layout constructors use miniaudio's default (Microsoft) ordering. Use
+/// [`ChannelLayout::standard`] for the ordering of other platforms or file formats.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChannelLayout(Vec<Channel>);
+
+impl ChannelLayout {
+    /// Creates a channel map from a list of channels.
+    pub fn new(channels: Vec<Channel>) -> MaResult<Self> {
+        Self::validate(&channels)?;
+        Ok(Self(channels))
+    }
+
+    /// Creates a channel map from a list of positions.
+    pub fn from_positions(positions: &[ChannelPosition]) -> MaResult<Self> {
+        Self::new(positions.iter().copied().map(Channel::from).collect())
+    }
+
+    /// Creates a channel map from raw `ma_channel` values.
+    pub fn from_raw(channels: &[sys::ma_channel]) -> MaResult<Self> {
+        Self::new(channels.iter().copied().map(Channel::from_raw).collect())
+    }
+
+    // Channel maps returned by miniaudio are used as is, only the channel count is checked
+    pub(crate) fn from_raw_count(raw: &[sys::ma_channel], channels: u32) -> MaResult<Self> {
+        let raw = raw
+            .get(..channels as usize)
+            .ok_or(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS))?;
+        Ok(Self::from_raw_unchecked(raw))
+    }
+
+    fn from_raw_unchecked(channels: &[sys::ma_channel]) -> Self {
+        Self(channels.iter().copied().map(Channel::from_raw).collect())
+    }
+
+    /// Creates the channel map used by `layout` for `channels` channels.
+    pub fn standard(layout: ChannelMap, channels: u32) -> MaResult<Self> {
+        if channels == 0 || channels > sys::MA_MAX_CHANNELS {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let mut raw = vec![0 as sys::ma_channel; channels as usize];
+        unsafe {
+            sys::ma_channel_map_init_standard(layout.into(), raw.as_mut_ptr(), raw.len(), channels)
+        };
+        Ok(Self::from_raw_unchecked(&raw))
+    }
+
+    /// `M`
+    pub fn mono() -> Self {
+        Self::from_known(&[ChannelPosition::Mono])
+    }
+
+    /// `FL, FR`
+    pub fn stereo() -> Self {
+        Self::from_known(&[ChannelPosition::FrontLeft, ChannelPosition::FrontRight])
+    }
+
+    /// `FL, FR, BL, BR`
+    pub fn quad() -> Self {
+        Self::from_known(&[
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontRight,
+            ChannelPosition::BackLeft,
+            ChannelPosition::BackRight,
+        ])
+    }
+
+    /// `FL, FR, FC, LFE, SL, SR`
+    pub fn surround_5_1() -> Self {
+        Self::from_known(&[
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontRight,
+            ChannelPosition::FrontCenter,
+            ChannelPosition::Lfe,
+            ChannelPosition::SideLeft,
+            ChannelPosition::SideRight,
+        ])
+    }
+
+    /// `FL, FR, FC, LFE, BL, BR, SL, SR`
+    pub fn surround_7_1() -> Self {
+        Self::from_known(&[
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontRight,
+            ChannelPosition::FrontCenter,
+            ChannelPosition::Lfe,
+            ChannelPosition::BackLeft,
+            ChannelPosition::BackRight,
+            ChannelPosition::SideLeft,
+            ChannelPosition::SideRight,
+        ])
+    }
+
+    /// Number of channels in the map.
+    pub fn channels(&self) -> u32 {
+        self.0.len() as u32
+    }
+
+    pub fn as_slice(&self) -> &[Channel] {
+        &self.0
+    }
+
+    /// Position of the channel at `index`, if the index is in range.
+    pub fn position(&self, index: usize) -> Option<ChannelPosition> {
+        self.0
+            .get(index)
+            .and_then(|c| ChannelPosition::try_from(*c).ok())
+    }
+
+    /// Index of the first channel at `position`.
+    pub fn find(&self, position: ChannelPosition) -> Option<usize> {
+        self.0.iter().position(|c| *c == Channel::from(position))
+    }
+
+    /// Returns `true` if a channel is at `position`.
+    pub fn contains(&self, position: ChannelPosition) -> bool {
+        self.find(position).is_some()
+    }
+
+    /// Copies the map into a `ma_channel` array.
+    pub fn to_raw(&self) -> Vec<sys::ma_channel> {
+        self.0.iter().map(|c| c.as_raw()).collect()
+    }
+
+    pub(crate) fn as_raw_ptr(&self) -> *const sys::ma_channel {
+        // Channel is repr(transparent) over ma_channel
+        self.0.as_ptr().cast()
+    }
+
+    fn from_known(positions: &[ChannelPosition]) -> Self {
+        Self(positions.iter().copied().map(Channel::from).collect())
+    }
+
+    fn validate(channels: &[Channel]) -> MaResult<()> {
+        if channels.is_empty() || channels.len() > sys::MA_MAX_CHANNELS as usize {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        for (i, channel) in channels.iter().enumerate() {
+            let position = ChannelPosition::try_from(*channel)?;
+            if position == ChannelPosition::None {
+                continue;
+            }
+            if position == ChannelPosition::Mono && channels.len() > 1 {
+                return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+            }
+            if channels[..i].contains(channel) {
+                return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<Channel>> for ChannelLayout {
+    type Error = MaudioError;
+
+    fn try_from(channels: Vec<Channel>) -> Result<Self, Self::Error> {
+        Self::new(channels)
+    }
+}
+
+impl From<ChannelLayout> for Vec<Channel> {
+    fn from(map: ChannelLayout) -> Self {
+        map.0
+    }
+}
+
+impl Deref for ChannelLayout {
+    type Target = [Channel];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 /// Controls how mono channels are expanded to multiple channels.
 ///
 /// Maps directly to `ma_mono_expansion_mode` in miniaudio.
@@ -541,6 +725,73 @@ mod tests {
         assert_eq!(err, MaError(sys::ma_result_MA_ERROR));
     }
 
+    #[test]
+    fn test_channel_layout_standard_layouts() {
+        assert_eq!(ChannelLayout::mono().channels(), 1);
+        assert_eq!(ChannelLayout::stereo().channels(), 2);
+        assert_eq!(ChannelLayout::quad().channels(), 4);
+        assert_eq!(ChannelLayout::surround_5_1().channels(), 6);
+        assert_eq!(ChannelLayout::surround_7_1().channels(), 8);
+
+        // Same ordering as miniaudio's default maps. Its 4 channel map is not quad.
+        for layout in [
+            ChannelLayout::mono(),
+            ChannelLayout::stereo(),
+            ChannelLayout::surround_5_1(),
+            ChannelLayout::surround_7_1(),
+        ] {
+            let standard = ChannelLayout::standard(ChannelMap::Default, layout.channels());
+            assert_eq!(standard.unwrap(), layout);
+        }
+
+        let map = ChannelLayout::surround_5_1();
+        assert_eq!(map.find(ChannelPosition::Lfe), Some(3));
+        assert_eq!(map.position(4), Some(ChannelPosition::SideLeft));
+        assert!(!map.contains(ChannelPosition::BackLeft));
+        assert_eq!(map.position(6), None);
+    }
+
+    #[test]
+    fn test_channel_layout_validation_and_raw_round_trip() {
+        let map = ChannelLayout::stereo();
+        let raw = map.to_raw();
+        assert_eq!(
+            raw,
+            [
+                sys::ma_channel::from(ChannelPosition::FrontLeft),
+                sys::ma_channel::from(ChannelPosition::FrontRight)
+            ]
+        );
+        assert_eq!(ChannelLayout::from_raw(&raw).unwrap(), map);
+
+        assert!(ChannelLayout::new(Vec::new()).is_err());
+        assert!(ChannelLayout::from_raw(&[200]).is_err());
+        assert!(ChannelLayout::from_positions(&[
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontLeft
+        ])
+        .is_err());
+        assert!(ChannelLayout::from_positions(&[
+            ChannelPosition::Mono,
+            ChannelPosition::FrontLeft
+        ])
+        .is_err());
+        assert!(
+            ChannelLayout::from_positions(&[ChannelPosition::None, ChannelPosition::None]).is_ok()
+        );
+        assert!(ChannelLayout::standard(ChannelMap::Alsa, 0).is_err());
+    }
+
+    #[test]
+    fn test_channel_layout_from_raw_count_checks_channels() {
+        let raw = [0 as sys::ma_channel; sys::MA_MAX_CHANNELS as usize];
+        assert_eq!(
+            ChannelLayout::from_raw_count(&raw, 2).unwrap().channels(),
+            2
+        );
+        assert!(ChannelLayout::from_raw_count(&raw, sys::MA_MAX_CHANNELS + 1).is_err());
+    }
+
     #[test]
     fn test_channel_mix_mode_from_rust_to_sys_variants() {
         assert_eq!(
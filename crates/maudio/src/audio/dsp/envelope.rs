@@ -0,0 +1,285 @@
+//! ADSR (attack, decay, sustain, release) envelope.
+use maudio_sys::ffi as sys;
+
+use crate::{audio::sample_rate::SampleRate, ErrorKinds, MaResult, MaudioError};
+
+/// The shape of an [`Envelope`].
+///
+/// - **attack** (ms): time to rise from silence to full level after a note on.
+/// - **decay** (ms): time to fall from full level to the sustain level.
+/// - **sustain**: level held while the note is on, between `0.0` and `1.0`.
+/// - **release** (ms): time to fall from the sustain level to silence after a note off.
+///
+/// The default is a 5 ms attack, 100 ms decay, a sustain level of `0.7` and a 200 ms release.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adsr {
+    pub attack_ms: f32,
+    pub decay_ms: f32,
+    pub sustain: f32,
+    pub release_ms: f32,
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self {
+            attack_ms: 5.0,
+            decay_ms: 100.0,
+            sustain: 0.7,
+            release_ms: 200.0,
+        }
+    }
+}
+
+impl Adsr {
+    pub fn new(attack_ms: f32, decay_ms: f32, sustain: f32, release_ms: f32) -> Self {
+        Self {
+            attack_ms,
+            decay_ms,
+            sustain,
+            release_ms,
+        }
+    }
+
+    /// Returns the length of the release in frames at `sample_rate`.
+    pub fn release_frames(&self, sample_rate: SampleRate) -> u64 {
+        ms_to_frames(self.release_ms, u32::from(sample_rate)) as u64
+    }
+
+    /// Returns `MA_INVALID_ARGS` unless the times are `>= 0` and the sustain level is
+    /// between `0.0` and `1.0`.
+    fn validate(&self) -> MaResult<()> {
+        let times_valid = [self.attack_ms, self.decay_ms, self.release_ms]
+            .iter()
+            .all(|t| t.is_finite() && *t >= 0.0);
+        if !times_valid || !(0.0..=1.0).contains(&self.sustain) {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeStage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// An ADSR envelope applied to interleaved `f32` PCM frames.
+///
+/// [`Envelope::note_on`] starts the attack from the current level, so retriggering a note
+/// that is still sounding does not click. [`Envelope::note_off`] starts the release from
+/// the current level. All segments are linear.
+///
+/// The voices of a [`Synth`](crate::engine::synth::Synth) use it to shape their waveform.
+pub struct Envelope {
+    channels: usize,
+    sample_rate: u32,
+    adsr: Adsr,
+    stage: EnvelopeStage,
+    level: f32,
+    step: f32,
+}
+
+impl Envelope {
+    pub fn new(channels: u32, sample_rate: SampleRate, adsr: Adsr) -> MaResult<Self> {
+        if channels == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        adsr.validate()?;
+        Ok(Self {
+            channels: channels as usize,
+            sample_rate: sample_rate.into(),
+            adsr,
+            stage: EnvelopeStage::Idle,
+            level: 0.0,
+            step: 0.0,
+        })
+    }
+
+    /// Starts the attack stage.
+    pub fn note_on(&mut self) {
+        self.enter(EnvelopeStage::Attack);
+    }
+
+    /// Starts the release stage, unless the envelope is already silent.
+    pub fn note_off(&mut self) {
+        if self.stage != EnvelopeStage::Idle {
+            self.enter(EnvelopeStage::Release);
+        }
+    }
+
+    /// Silences the envelope immediately.
+    pub fn reset(&mut self) {
+        self.stage = EnvelopeStage::Idle;
+        self.level = 0.0;
+        self.step = 0.0;
+    }
+
+    /// Changes the shape. Takes effect at the start of the next stage.
+    pub fn set_adsr(&mut self, adsr: Adsr) -> MaResult<()> {
+        adsr.validate()?;
+        self.adsr = adsr;
+        Ok(())
+    }
+
+    pub fn adsr(&self) -> Adsr {
+        self.adsr
+    }
+
+    pub fn stage(&self) -> EnvelopeStage {
+        self.stage
+    }
+
+    /// Returns the current level, between `0.0` and `1.0`.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Returns `true` once the release has finished, or before the first note on.
+    pub fn is_idle(&self) -> bool {
+        self.stage == EnvelopeStage::Idle
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels as u32
+    }
+
+    /// Multiplies interleaved frames by the envelope, in place.
+    pub fn process_in_place(&mut self, frames: &mut [f32]) -> MaResult<()> {
+        if frames.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+        for frame in frames.chunks_exact_mut(self.channels) {
+            let gain = self.next_level();
+            frame.iter_mut().for_each(|s| *s *= gain);
+        }
+        Ok(())
+    }
+
+    // Advances by one frame and returns the level to apply to it
+    fn next_level(&mut self) -> f32 {
+        match self.stage {
+            EnvelopeStage::Idle | EnvelopeStage::Sustain => {}
+            EnvelopeStage::Attack => {
+                self.level += self.step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.enter(EnvelopeStage::Decay);
+                }
+            }
+            EnvelopeStage::Decay => {
+                self.level -= self.step;
+                if self.level <= self.adsr.sustain {
+                    self.level = self.adsr.sustain;
+                    self.enter(EnvelopeStage::Sustain);
+                }
+            }
+            EnvelopeStage::Release => {
+                self.level -= self.step;
+                if self.level <= 0.0 {
+                    self.reset();
+                }
+            }
+        }
+        self.level
+    }
+
+    fn enter(&mut self, stage: EnvelopeStage) {
+        let (distance, ms) = match stage {
+            EnvelopeStage::Attack => (1.0 - self.level, self.adsr.attack_ms),
+            EnvelopeStage::Decay => (self.level - self.adsr.sustain, self.adsr.decay_ms),
+            EnvelopeStage::Release => (self.level, self.adsr.release_ms),
+            EnvelopeStage::Idle | EnvelopeStage::Sustain => (0.0, 0.0),
+        };
+        self.stage = stage;
+        let frames = ms_to_frames(ms, self.sample_rate);
+        // Zero length stages complete on the next frame
+        self.step = if frames == 0 {
+            f32::INFINITY
+        } else {
+            distance / frames as f32
+        };
+        match stage {
+            EnvelopeStage::Decay if self.level <= self.adsr.sustain => {
+                self.level = self.adsr.sustain;
+                self.stage = EnvelopeStage::Sustain;
+            }
+            EnvelopeStage::Release if self.level <= 0.0 => self.reset(),
+            _ => {}
+        }
+    }
+}
+
+fn ms_to_frames(ms: f32, sample_rate: u32) -> u32 {
+    (ms as f64 * sample_rate as f64 / 1000.0).round() as u32
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::{
+        dsp::envelope::{Adsr, Envelope, EnvelopeStage},
+        sample_rate::SampleRate,
+    };
+
+    fn render(envelope: &mut Envelope, frames: usize) -> Vec<f32> {
+        let mut out = vec![1.0f32; frames];
+        envelope.process_in_place(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_envelope_stages() {
+        // 10 frames attack, 10 frames decay, 10 frames release at 1000 Hz
+        let adsr = Adsr::new(10.0, 10.0, 0.5, 10.0);
+        let mut envelope = Envelope::new(1, SampleRate::Custom(1000), adsr).unwrap();
+        assert!(envelope.is_idle());
+        assert!(render(&mut envelope, 4).iter().all(|s| *s == 0.0));
+
+        envelope.note_on();
+        let out = render(&mut envelope, 30);
+        assert!((out[0] - 0.1).abs() < 1e-5);
+        assert!((out[9] - 1.0).abs() < 1e-5);
+        assert!((out[19] - 0.5).abs() < 1e-5);
+        assert!((out[29] - 0.5).abs() < 1e-5);
+        assert_eq!(envelope.stage(), EnvelopeStage::Sustain);
+
+        envelope.note_off();
+        let out = render(&mut envelope, 11);
+        assert!((out[0] - 0.45).abs() < 1e-5);
+        assert!(out[9].abs() < 1e-5);
+        assert_eq!(out[10], 0.0);
+        assert!(envelope.is_idle());
+    }
+
+    #[test]
+    fn test_envelope_retrigger_starts_from_current_level() {
+        let adsr = Adsr::new(10.0, 0.0, 1.0, 100.0);
+        let mut envelope = Envelope::new(1, SampleRate::Custom(1000), adsr).unwrap();
+        envelope.note_on();
+        render(&mut envelope, 20);
+        envelope.note_off();
+        render(&mut envelope, 50);
+        let level = envelope.level();
+        assert!(level > 0.4 && level < 0.6);
+
+        envelope.note_on();
+        let out = render(&mut envelope, 2);
+        assert!(out[0] > level && out[0] < level + 0.2);
+        assert!(out[1] > out[0]);
+    }
+
+    #[test]
+    fn test_envelope_invalid_args() {
+        let bad = Adsr::new(-1.0, 0.0, 0.5, 0.0);
+        assert!(Envelope::new(1, SampleRate::Sr48000, bad).is_err());
+        let bad = Adsr::new(0.0, 0.0, 1.5, 0.0);
+        assert!(Envelope::new(1, SampleRate::Sr48000, bad).is_err());
+        assert!(Envelope::new(0, SampleRate::Sr48000, Adsr::default()).is_err());
+    }
+}
@@ -1,14 +1,23 @@
+//! Linear volume fades.
 use std::{marker::PhantomData, mem::MaybeUninit};
 
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{formats::Format, sample_rate::SampleRate},
+    audio::{
+        formats::{Format, SampleBuffer},
+        sample_rate::SampleRate,
+    },
     data_source::DataFormat,
     pcm_frames::PcmFormat,
     Binding, MaResult,
 };
 
+/// Applies a linear volume fade to interleaved frames, the `ma_fader` used by miniaudio for
+/// sound and group fades.
+///
+/// Once a fade completes, the end volume is held until the next fade. For other fade
+/// shapes, see [`ShapedFader`](crate::audio::dsp::shaped_fader::ShapedFader).
 pub struct Fader<F: PcmFormat> {
     inner: *mut sys::ma_fader,
     format: Format,
@@ -52,6 +61,11 @@ impl<F: PcmFormat> Fader<F> {
         fader_ffi::ma_fader_process_pcm_frames(self, frames_out, frames_in)
     }
 
+    /// Fades a buffer in place. The buffer must have the channel count of the fader.
+    pub fn process_buffer(&mut self, buffer: &mut SampleBuffer<F>) -> MaResult<()> {
+        fader_ffi::ma_fader_process_buffer(self, buffer)
+    }
+
     pub fn get_data_format(&self) -> DataFormat {
         DataFormat {
             format: self.format,
@@ -75,9 +89,24 @@ impl<F: PcmFormat> Fader<F> {
         fader_ffi::ma_fader_set_fade_ex(self, vol_start, vol_end, length_frames, start_off_frames);
     }
 
+    /// Same as [`Fader::set_fade`], with the length in milliseconds.
+    pub fn set_fade_mili(&mut self, vol_start: f32, vol_end: f32, length_mili: u64) {
+        let sample_rate: u32 = self.sample_rate.into();
+        let length_frames = length_mili.saturating_mul(sample_rate as u64) / 1000;
+        self.set_fade(vol_start, vol_end, length_frames);
+    }
+
     pub fn current_volume(&self) -> f32 {
         fader_ffi::ma_fader_get_current_volume(self)
     }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
 }
 
 pub struct FaderBuilder {
@@ -101,8 +130,10 @@ mod fader_ffi {
     use maudio_sys::ffi as sys;
 
     use crate::{
-        audio::dsp::fader::Fader, data_source::DataFormat, pcm_frames::PcmFormat, Binding,
-        MaResult, MaudioError,
+        audio::{dsp::fader::Fader, formats::SampleBuffer},
+        data_source::DataFormat,
+        pcm_frames::PcmFormat,
+        Binding, ErrorKinds, MaResult, MaudioError,
     };
 
     #[inline]
@@ -133,6 +164,29 @@ mod fader_ffi {
         MaudioError::check(res)
     }
 
+    #[inline]
+    pub fn ma_fader_process_buffer<F: PcmFormat>(
+        fader: &mut Fader<F>,
+        buffer: &mut SampleBuffer<F>,
+    ) -> MaResult<()> {
+        if buffer.channels() != fader.channels {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "Fader::process_buffer channels",
+                expected: fader.channels as usize,
+                actual: buffer.channels() as usize,
+            }));
+        }
+        if !F::DIRECT_READ {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidFormat));
+        }
+        // Same layout as the miniaudio storage, processed in place
+        let ptr = buffer.as_mut().as_mut_ptr() as *mut core::ffi::c_void;
+        let res = unsafe {
+            sys::ma_fader_process_pcm_frames(fader.to_raw(), ptr, ptr, buffer.frames() as u64)
+        };
+        MaudioError::check(res)
+    }
+
     // Data is stored on the Fader struct instead.
     #[inline]
     #[allow(unused)]
@@ -351,6 +405,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fader_test_process_buffer_and_fade_mili() {
+        let mut fader = FaderBuilder::new(2, SampleRate::Sr8000)
+            .build_f32()
+            .unwrap();
+        assert_eq!(fader.channels(), 2);
+        assert_eq!(fader.sample_rate(), SampleRate::Sr8000);
+
+        // 1 ms at 8 kHz
+        fader.set_fade_mili(1.0, 0.0, 1);
+        let mut buffer = SampleBuffer::<f32>::from_storage(vec![1.0; 24], 12, 2).unwrap();
+        fader.process_buffer(&mut buffer).unwrap();
+
+        let samples = buffer.as_ref();
+        assert!(samples[0] > samples[14]);
+        assert!(samples[16..].iter().all(|s| *s == 0.0));
+        assert_approx_eq(fader.current_volume(), 0.0);
+
+        let mut mono = SampleBuffer::<f32>::from_storage(vec![1.0; 4], 4, 1).unwrap();
+        assert!(fader.process_buffer(&mut mono).is_err());
+    }
+
     #[test]
     fn fader_test_process_pcm_frames_only_processes_minimum_frame_count() {
         let mut builder = FaderBuilder::new(2, SampleRate::Sr44100);
@@ -87,6 +87,54 @@ impl<F: PcmFormat> Drop for Biquad<F> {
     }
 }
 
+// Matches MA_BIQUAD_FIXED_POINT_SHIFT in miniaudio
+const BIQUAD_FIXED_POINT_SHIFT: u32 = 14;
+
+/// Computes the linear magnitude response of a biquad at each frequency in `freqs` (Hz).
+///
+/// Shared by the `frequency_response` methods of the biquad based filter nodes.
+/// Values are computed from the current coefficients, so `1.0` means the frequency
+/// passes unchanged. Convert with `20.0 * g.log10()` for dB.
+///
+/// Coefficients are read from the initialized biquad and are already normalized by `a0`.
+pub(crate) fn biquad_frequency_response(
+    bq: &sys::ma_biquad,
+    sample_rate: u32,
+    freqs: &[f32],
+) -> Vec<f32> {
+    let coeff = |c: sys::ma_biquad_coefficient| -> f64 {
+        if bq.format == sys::ma_format_ma_format_f32 {
+            unsafe { c.f32_ as f64 }
+        } else {
+            unsafe { c.s32 as f64 / (1u32 << BIQUAD_FIXED_POINT_SHIFT) as f64 }
+        }
+    };
+    let (b0, b1, b2) = (coeff(bq.b0), coeff(bq.b1), coeff(bq.b2));
+    let (a1, a2) = (coeff(bq.a1), coeff(bq.a2));
+
+    freqs
+        .iter()
+        .map(|&freq| {
+            let w = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate as f64;
+            let (cos1, sin1) = (w.cos(), w.sin());
+            let (cos2, sin2) = ((2.0 * w).cos(), (2.0 * w).sin());
+
+            let num_re = b0 + b1 * cos1 + b2 * cos2;
+            let num_im = -(b1 * sin1 + b2 * sin2);
+            let den_re = 1.0 + a1 * cos1 + a2 * cos2;
+            let den_im = -(a1 * sin1 + a2 * sin2);
+
+            let num = (num_re * num_re + num_im * num_im).sqrt();
+            let den = (den_re * den_re + den_im * den_im).sqrt();
+            if den == 0.0 {
+                f32::INFINITY
+            } else {
+                (num / den) as f32
+            }
+        })
+        .collect()
+}
+
 pub(crate) mod biquad_ffi {
     use std::sync::Arc;
 
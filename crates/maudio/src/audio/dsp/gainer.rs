@@ -1,9 +1,18 @@
+//! Per-channel gain with smoothing.
 use std::{marker::PhantomData, mem::MaybeUninit};
 
 use maudio_sys::ffi as sys;
 
-use crate::{pcm_frames::PcmFormat, Binding, MaResult};
+use crate::{audio::sample_rate::SampleRate, pcm_frames::PcmFormat, Binding, MaResult};
 
+/// Applies a gain to each channel of interleaved PCM frames.
+///
+/// Gain changes are not applied instantly. The gainer moves linearly from the current gain
+/// to the new one over the smoothing time set on the [`GainerBuilder`], which avoids the
+/// clicks caused by jumping to a new volume in the middle of a buffer. A smoothing time of
+/// zero applies changes instantly, as does the first gain set after building.
+///
+/// This is the `ma_gainer` used by miniaudio for the volume of sounds and nodes.
 pub struct Gainer<F: PcmFormat> {
     inner: *mut sys::ma_gainer,
     channels: u32,
@@ -42,10 +51,19 @@ impl<F: PcmFormat> Gainer<F> {
         gainer_ffi::ma_gainer_process_pcm_frames(self, frames_out, frames_in)
     }
 
+    /// Processes interleaved frames in place.
+    pub fn process_in_place(&mut self, frames: &mut [F::StorageUnit]) -> MaResult<()> {
+        gainer_ffi::ma_gainer_process_in_place(self, frames)
+    }
+
+    /// Sets the same gain on every channel, reached after the smoothing time.
     pub fn set_gain(&mut self, gain: f32) -> MaResult<()> {
         gainer_ffi::ma_gainer_set_gain(self, gain)
     }
 
+    /// Sets the gain of each channel, reached after the smoothing time.
+    ///
+    /// `gains` must hold one gain per channel.
     pub fn set_gains(&mut self, gains: &[f32]) -> MaResult<()> {
         gainer_ffi::ma_gainer_set_gains(self, gains)
     }
@@ -54,9 +72,50 @@ impl<F: PcmFormat> Gainer<F> {
         gainer_ffi::ma_gainer_get_master_volume(self)
     }
 
+    /// Sets a volume applied on top of the channel gains. Changes are not smoothed.
     pub fn set_master_volume(&mut self, volume: f32) -> MaResult<()> {
         gainer_ffi::ma_gainer_set_master_volume(self, volume)
     }
+
+    /// Target gain of `channel`, as set by the last call to `set_gain` or `set_gains`.
+    pub fn gain(&self, channel: u32) -> Option<f32> {
+        self.gains().get(channel as usize).copied()
+    }
+
+    /// Target gain of every channel.
+    pub fn gains(&self) -> &[f32] {
+        // pNewGains holds one gain per channel for the lifetime of the gainer
+        unsafe { std::slice::from_raw_parts((*self.inner).pNewGains, self.channels as usize) }
+    }
+
+    /// Gain applied to `channel` on the next frame, part way through the smoothing.
+    pub fn current_gain(&self, channel: u32) -> Option<f32> {
+        if channel >= self.channels {
+            return None;
+        }
+        let inner = unsafe { &*self.inner };
+        let (old, new) = unsafe {
+            (
+                *inner.pOldGains.add(channel as usize),
+                *inner.pNewGains.add(channel as usize),
+            )
+        };
+        let smooth = inner.config.smoothTimeInFrames;
+        if inner.t >= smooth {
+            return Some(new);
+        }
+        let a = inner.t as f32 / smooth as f32;
+        Some(old + (new - old) * a)
+    }
+
+    /// Number of frames over which gain changes are spread.
+    pub fn smooth_time_pcm(&self) -> u32 {
+        unsafe { (*self.inner).config.smoothTimeInFrames }
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
 }
 
 pub struct GainerBuilder {
@@ -69,6 +128,20 @@ impl GainerBuilder {
         Self { config }
     }
 
+    /// Sets the smoothing time in PCM frames.
+    pub fn smooth_time_pcm(&mut self, frames: u32) -> &mut Self {
+        self.config.smoothTimeInFrames = frames;
+        self
+    }
+
+    /// Sets the smoothing time in milliseconds, for audio at `sample_rate`.
+    pub fn smooth_time_mili(&mut self, mili: u32, sample_rate: SampleRate) -> &mut Self {
+        let sample_rate: u32 = sample_rate.into();
+        let frames = mili as u64 * sample_rate as u64 / 1000;
+        self.config.smoothTimeInFrames = frames.min(u32::MAX as u64) as u32;
+        self
+    }
+
     pub fn build_f32(&self) -> MaResult<Gainer<f32>> {
         Gainer::build(&self.config)
     }
@@ -78,8 +151,8 @@ mod gainer_ffi {
     use std::sync::Arc;
 
     use crate::{
-        audio::dsp::volume_gainer::Gainer, engine::AllocationCallbacks, pcm_frames::PcmFormat,
-        AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
+        audio::dsp::gainer::Gainer, engine::AllocationCallbacks, pcm_frames::PcmFormat, AsRawRef,
+        Binding, ErrorKinds, MaResult, MaudioError,
     };
     use maudio_sys::ffi as sys;
 
@@ -113,15 +186,59 @@ mod gainer_ffi {
         let frame_in = frames_in.len() / channels;
         let frame_out = frames_out.len() / channels;
         let frames_proc = frame_in.min(frame_out);
-        let res = unsafe {
-            sys::ma_gainer_process_pcm_frames(
-                gainer.to_raw(),
-                frames_out.as_mut_ptr() as *mut _,
-                frames_in.as_ptr() as *const _,
-                frames_proc as u64,
-            )
-        };
-        MaudioError::check(res)
+        process_frames::<F>(
+            gainer,
+            frames_out.as_mut_ptr(),
+            frames_in.as_ptr(),
+            frames_proc,
+        )
+    }
+
+    #[inline]
+    pub fn ma_gainer_process_in_place<F: PcmFormat>(
+        gainer: &mut Gainer<F>,
+        frames: &mut [F::StorageUnit],
+    ) -> MaResult<()> {
+        let frame_count = frames.len() / gainer.channels as usize;
+        let ptr = frames.as_mut_ptr();
+        // ma_gainer supports the same buffer for input and output
+        process_frames::<F>(gainer, ptr, ptr, frame_count)
+    }
+
+    // ma_gainer interpolates every frame of a call that starts inside the smoothing time,
+    // overshooting the new gain when the call is longer than the remaining smoothing.
+    // Calls are split at the end of the smoothing time to avoid it.
+    fn process_frames<F: PcmFormat>(
+        gainer: &mut Gainer<F>,
+        frames_out: *mut F::StorageUnit,
+        frames_in: *const F::StorageUnit,
+        frame_count: usize,
+    ) -> MaResult<()> {
+        let channels = gainer.channels as usize;
+        let mut done = 0;
+        while done < frame_count {
+            let (t, smooth) = unsafe {
+                let inner = &*gainer.to_raw();
+                (inner.t, inner.config.smoothTimeInFrames)
+            };
+            let remaining = frame_count - done;
+            let chunk = if t < smooth {
+                remaining.min((smooth - t) as usize)
+            } else {
+                remaining
+            };
+            let res = unsafe {
+                sys::ma_gainer_process_pcm_frames(
+                    gainer.to_raw(),
+                    frames_out.add(done * channels) as *mut _,
+                    frames_in.add(done * channels) as *const _,
+                    chunk as u64,
+                )
+            };
+            MaudioError::check(res)?;
+            done += chunk;
+        }
+        Ok(())
     }
 
     #[inline]
@@ -267,6 +384,34 @@ mod tests {
         assert!(gainer.set_gains(&[1.0, 1.0, 1.0]).is_err());
     }
 
+    #[test]
+    fn gainer_test_smooths_gain_changes() {
+        let mut gainer = GainerBuilder::new(1, 0)
+            .smooth_time_mili(1, SampleRate::Sr8000)
+            .build_f32()
+            .unwrap();
+        assert_eq!(gainer.smooth_time_pcm(), 8);
+        assert_eq!(gainer.gains(), &[1.0]);
+
+        // The first gain is not smoothed
+        gainer.set_gain(1.0).unwrap();
+        assert_eq!(gainer.current_gain(0), Some(1.0));
+
+        gainer.set_gain(0.0).unwrap();
+        assert_eq!(gainer.gain(0), Some(0.0));
+        assert_eq!(gainer.current_gain(0), Some(1.0));
+
+        let mut frames = [1.0f32; 12];
+        gainer.process_in_place(&mut frames).unwrap();
+        // Ramps down over 8 frames instead of jumping to silence
+        assert!(frames[..8].windows(2).all(|w| w[0] > w[1]));
+        assert!(frames[3] > 0.0);
+        assert!(frames[8..].iter().all(|s| *s == 0.0), "{frames:?}");
+        assert_eq!(gainer.current_gain(0), Some(0.0));
+        assert_eq!(gainer.gain(1), None);
+        assert_eq!(gainer.current_gain(1), None);
+    }
+
     #[test]
     fn gainer_test_process_pcm_frames_accepts_in_place_processing() {
         let mut gainer = GainerBuilder::new(2, 0).build_f32().unwrap();
@@ -290,4 +435,14 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn gainer_test_volume_gainer_alias() {
+        let gainer: crate::audio::dsp::volume_gainer::Gainer<f32> =
+            crate::audio::dsp::volume_gainer::GainerBuilder::new(2, 0)
+                .build_f32()
+                .unwrap();
+        assert_eq!(gainer.channels(), 2);
+    }
 }
@@ -0,0 +1,521 @@
+//! Loudness measurement following ITU-R BS.1770-4 and EBU R128.
+//!
+//! [`LoudnessMeter`] reports the loudness of interleaved `f32` PCM in LUFS:
+//!
+//! - **momentary**: over the last 400 ms.
+//! - **short-term**: over the last 3 s.
+//! - **integrated**: over everything measured since the start or the last reset, with the
+//!   absolute (-70 LUFS) and relative (-10 LU) gates of BS.1770. Silence and quiet passages
+//!   do not pull it down, which makes it the value to normalize music to.
+//!
+//! It also reports the true peak in dBTP, the peak of the signal oversampled 4 times, which
+//! catches the peaks between samples that a sample peak meter misses.
+//!
+//! Whole files can be measured with [`LoudnessMeter::measure_decoder`] or
+//! [`LoudnessMeter::measure`]. To meter audio while it plays, use
+//! [`LoudnessNode`](crate::engine::node_graph::nodes::routing::loudness::LoudnessNode).
+//!
+//! ```no_run
+//! # use maudio::audio::{dsp::loudness::LoudnessMeter, sample_rate::SampleRate};
+//! # use maudio::data_source::sources::decoder::DecoderBuilder;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let mut decoder = DecoderBuilder::new_f32(2, SampleRate::Sr48000).from_file(Path::new("song.flac"))?;
+//! let loudness = LoudnessMeter::measure_decoder(&mut decoder)?;
+//!
+//! // Gain bringing the song to the -14 LUFS used by streaming services
+//! let gain_db = -14.0 - loudness.integrated;
+//! # Ok(())
+//! # }
+//! ```
+use std::f64::consts::PI;
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate},
+    data_source::sources::decoder::DecoderOps,
+    ErrorKinds, MaResult, MaResultCode, MaudioError,
+};
+
+// Gating blocks are 400 ms, computed every 100 ms from 4 sub-blocks
+const BLOCKS_MOMENTARY: usize = 4;
+const BLOCKS_SHORT_TERM: usize = 30;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+// Gating blocks are kept in a histogram with 0.01 LU bins from the absolute gate to +10 LUFS
+const HISTOGRAM_BINS_PER_LU: f64 = 100.0;
+const HISTOGRAM_BINS: usize = 8000;
+const MEASURE_BLOCK_FRAMES: usize = 4096;
+
+// 4x oversampling filter for the true peak, from BS.1770-4 Annex 2. One row per phase.
+const TRUE_PEAK_TAPS: usize = 12;
+const TRUE_PEAK_FILTER: [[f32; TRUE_PEAK_TAPS]; 4] = [
+    [
+        0.001_708_984_4,
+        0.010_986_328,
+        -0.019_653_32,
+        0.033_203_125,
+        -0.059_448_242,
+        0.137_329_1,
+        0.972_167_97,
+        -0.102_294_92,
+        0.047_607_422,
+        -0.026_611_328,
+        0.014_892_578,
+        -0.008_300_781,
+    ],
+    [
+        -0.029_174_805,
+        0.029_296_875,
+        -0.051_757_812,
+        0.089_111_33,
+        -0.166_503_9,
+        0.465_087_9,
+        0.779_785_16,
+        -0.200_317_38,
+        0.101_562_5,
+        -0.058_227_54,
+        0.033_081_055,
+        -0.018_920_898,
+    ],
+    [
+        -0.018_920_898,
+        0.033_081_055,
+        -0.058_227_54,
+        0.101_562_5,
+        -0.200_317_38,
+        0.779_785_16,
+        0.465_087_9,
+        -0.166_503_9,
+        0.089_111_33,
+        -0.051_757_812,
+        0.029_296_875,
+        -0.029_174_805,
+    ],
+    [
+        -0.008_300_781,
+        0.014_892_578,
+        -0.026_611_328,
+        0.047_607_422,
+        -0.102_294_92,
+        0.972_167_97,
+        0.137_329_1,
+        -0.059_448_242,
+        0.033_203_125,
+        -0.019_653_32,
+        0.010_986_328,
+        0.001_708_984_4,
+    ],
+];
+
+/// A loudness measurement, see the [module docs](self).
+///
+/// Loudness is in LUFS and the true peak in dBTP. Values are `f64::NEG_INFINITY` while
+/// nothing was measured, or when the window is not filled yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub momentary: f64,
+    pub short_term: f64,
+    pub integrated: f64,
+    pub true_peak: f64,
+}
+
+impl Default for Loudness {
+    fn default() -> Self {
+        Self {
+            momentary: f64::NEG_INFINITY,
+            short_term: f64::NEG_INFINITY,
+            integrated: f64::NEG_INFINITY,
+            true_peak: f64::NEG_INFINITY,
+        }
+    }
+}
+
+/// An EBU R128 loudness meter for interleaved `f32` PCM frames.
+///
+/// Channels are weighted as in BS.1770 for the usual channel orders: surround channels
+/// of 5.0, 5.1 and 7.1 count 1.5 dB more and the LFE of 5.1 and 7.1 is not measured. Use
+/// [`LoudnessMeter::set_channel_weights`] for other layouts.
+///
+/// [`LoudnessNode`](crate::engine::node_graph::nodes::routing::loudness::LoudnessNode)
+/// measures a node graph branch with it.
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>,
+    k_weighting: KWeighting,
+    // Filter state, per channel
+    filter_state: Vec<[f64; 4]>,
+    // Last input samples of each channel, for the true peak
+    peak_history: Vec<[f32; TRUE_PEAK_TAPS]>,
+    true_peak: f32,
+    sub_block_frames: usize,
+    sub_block_pos: usize,
+    sub_block_energy: f64,
+    // Mean square of the last sub-blocks, as a ring
+    sub_blocks: [f64; BLOCKS_SHORT_TERM],
+    sub_block_count: usize,
+    // Gating blocks above the absolute gate, as a count and a sum of powers per bin
+    histogram_count: Vec<u64>,
+    histogram_power: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    /// Creates a meter for audio with `channels` channels at `sample_rate`.
+    ///
+    /// Returns `MA_INVALID_ARGS` if `channels` is zero.
+    pub fn new(channels: u32, sample_rate: SampleRate) -> MaResult<Self> {
+        if channels == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let channels = channels as usize;
+        let weights = (0..channels)
+            .map(|channel| default_channel_weight(channels, channel))
+            .collect();
+        let sample_rate = sample_rate.hz() as f64;
+        Ok(Self {
+            channels,
+            weights,
+            k_weighting: KWeighting::new(sample_rate),
+            filter_state: vec![[0.0; 4]; channels],
+            peak_history: vec![[0.0; TRUE_PEAK_TAPS]; channels],
+            true_peak: 0.0,
+            sub_block_frames: ((sample_rate / 10.0).round() as usize).max(1),
+            sub_block_pos: 0,
+            sub_block_energy: 0.0,
+            sub_blocks: [0.0; BLOCKS_SHORT_TERM],
+            sub_block_count: 0,
+            histogram_count: vec![0; HISTOGRAM_BINS],
+            histogram_power: vec![0.0; HISTOGRAM_BINS],
+        })
+    }
+
+    /// Measures a whole sample buffer recorded at `sample_rate`.
+    pub fn measure(samples: &SampleBuffer<f32>, sample_rate: SampleRate) -> MaResult<Loudness> {
+        let mut meter = Self::new(samples.channels(), sample_rate)?;
+        meter.process(samples.as_ref())?;
+        Ok(meter.loudness())
+    }
+
+    /// Measures a decoder from its cursor to the end.
+    pub fn measure_decoder<D: DecoderOps<Format = f32>>(decoder: &mut D) -> MaResult<Loudness> {
+        let format = decoder.data_format()?;
+        let mut meter = Self::new(format.channels, format.sample_rate)?;
+        let mut block = vec![0.0f32; MEASURE_BLOCK_FRAMES * meter.channels];
+        loop {
+            let frames = match decoder.read_pcm_frames_into(&mut block) {
+                Ok(0) => break,
+                Ok(frames) => frames,
+                Err(e) if e.code() == MaResultCode::AtEnd => break,
+                Err(e) => return Err(e),
+            };
+            meter.process(&block[..frames * meter.channels])?;
+        }
+        Ok(meter.loudness())
+    }
+
+    /// Measures interleaved frames.
+    ///
+    /// `frames` must hold a whole number of frames.
+    pub fn process(&mut self, frames: &[f32]) -> MaResult<()> {
+        if frames.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+        for frame in frames.chunks_exact(self.channels) {
+            let mut energy = 0.0;
+            for (channel, sample) in frame.iter().enumerate() {
+                self.update_true_peak(channel, *sample);
+                let filtered = self
+                    .k_weighting
+                    .process(&mut self.filter_state[channel], *sample as f64);
+                energy += self.weights[channel] * filtered * filtered;
+            }
+            self.sub_block_energy += energy;
+            self.sub_block_pos += 1;
+            if self.sub_block_pos == self.sub_block_frames {
+                self.end_sub_block();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the current measurement.
+    pub fn loudness(&self) -> Loudness {
+        Loudness {
+            momentary: self.momentary(),
+            short_term: self.short_term(),
+            integrated: self.integrated(),
+            true_peak: self.true_peak(),
+        }
+    }
+
+    /// Loudness of the last 400 ms, in LUFS.
+    pub fn momentary(&self) -> f64 {
+        self.window_loudness(BLOCKS_MOMENTARY)
+    }
+
+    /// Loudness of the last 3 s, in LUFS.
+    pub fn short_term(&self) -> f64 {
+        self.window_loudness(BLOCKS_SHORT_TERM)
+    }
+
+    /// Gated loudness of everything measured, in LUFS.
+    pub fn integrated(&self) -> f64 {
+        let (count, power) = self.gated_sum(0);
+        if count == 0 {
+            return f64::NEG_INFINITY;
+        }
+        let threshold = power_to_lufs(power / count as f64) + RELATIVE_GATE;
+        let first_bin = ((threshold - ABSOLUTE_GATE) * HISTOGRAM_BINS_PER_LU)
+            .ceil()
+            .max(0.0) as usize;
+        let (count, power) = self.gated_sum(first_bin);
+        if count == 0 {
+            return f64::NEG_INFINITY;
+        }
+        power_to_lufs(power / count as f64)
+    }
+
+    /// Highest true peak measured, in dBTP.
+    pub fn true_peak(&self) -> f64 {
+        20.0 * (self.true_peak as f64).log10()
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels as u32
+    }
+
+    /// Sets the weight of each channel, `1.0` for front channels, `1.41` for surround
+    /// channels and `0.0` for channels that are not measured, like the LFE.
+    pub fn set_channel_weights(&mut self, weights: &[f32]) -> MaResult<()> {
+        if weights.len() != self.channels {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "LoudnessMeter::set_channel_weights",
+                expected: self.channels,
+                actual: weights.len(),
+            }));
+        }
+        for (weight, new) in self.weights.iter_mut().zip(weights) {
+            *weight = *new as f64;
+        }
+        Ok(())
+    }
+
+    /// Forgets everything measured.
+    pub fn reset(&mut self) {
+        self.filter_state.fill([0.0; 4]);
+        self.peak_history.fill([0.0; TRUE_PEAK_TAPS]);
+        self.true_peak = 0.0;
+        self.sub_block_pos = 0;
+        self.sub_block_energy = 0.0;
+        self.sub_blocks = [0.0; BLOCKS_SHORT_TERM];
+        self.sub_block_count = 0;
+        self.histogram_count.fill(0);
+        self.histogram_power.fill(0.0);
+    }
+
+    fn update_true_peak(&mut self, channel: usize, sample: f32) {
+        let history = &mut self.peak_history[channel];
+        history.copy_within(..TRUE_PEAK_TAPS - 1, 1);
+        history[0] = sample;
+        let mut peak = self.true_peak.max(sample.abs());
+        for phase in &TRUE_PEAK_FILTER {
+            let value: f32 = phase.iter().zip(history.iter()).map(|(h, x)| h * x).sum();
+            peak = peak.max(value.abs());
+        }
+        self.true_peak = peak;
+    }
+
+    fn end_sub_block(&mut self) {
+        let mean_square = self.sub_block_energy / self.sub_block_frames as f64;
+        self.sub_blocks[self.sub_block_count % BLOCKS_SHORT_TERM] = mean_square;
+        self.sub_block_count += 1;
+        self.sub_block_pos = 0;
+        self.sub_block_energy = 0.0;
+
+        if self.sub_block_count >= BLOCKS_MOMENTARY {
+            let power = self.window_power(BLOCKS_MOMENTARY);
+            let loudness = power_to_lufs(power);
+            if loudness > ABSOLUTE_GATE {
+                let bin = ((loudness - ABSOLUTE_GATE) * HISTOGRAM_BINS_PER_LU) as usize;
+                let bin = bin.min(HISTOGRAM_BINS - 1);
+                self.histogram_count[bin] += 1;
+                self.histogram_power[bin] += power;
+            }
+        }
+    }
+
+    fn window_power(&self, blocks: usize) -> f64 {
+        let sum: f64 = (1..=blocks)
+            .map(|i| self.sub_blocks[(self.sub_block_count - i) % BLOCKS_SHORT_TERM])
+            .sum();
+        sum / blocks as f64
+    }
+
+    fn window_loudness(&self, blocks: usize) -> f64 {
+        if self.sub_block_count < blocks {
+            return f64::NEG_INFINITY;
+        }
+        power_to_lufs(self.window_power(blocks))
+    }
+
+    fn gated_sum(&self, first_bin: usize) -> (u64, f64) {
+        let first_bin = first_bin.min(HISTOGRAM_BINS);
+        let count = self.histogram_count[first_bin..].iter().sum();
+        let power = self.histogram_power[first_bin..].iter().sum();
+        (count, power)
+    }
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+// BS.1770 weights for miniaudio's default channel maps
+fn default_channel_weight(channels: usize, channel: usize) -> f64 {
+    const SURROUND: f64 = 1.41;
+    match (channels, channel) {
+        // FL FR FC BL BR
+        (5, 3..=4) => SURROUND,
+        // FL FR FC LFE BL BR (SL SR)
+        (6 | 8, 3) => 0.0,
+        (6 | 8, 4..) => SURROUND,
+        _ => 1.0,
+    }
+}
+
+// The two biquads of the K-weighting: a high shelf modelling the head, then a high-pass.
+// Coefficients are derived for any sample rate as in libebur128.
+struct KWeighting {
+    shelf_b: [f64; 3],
+    shelf_a: [f64; 2],
+    highpass_b: [f64; 3],
+    highpass_a: [f64; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let f0 = 1_681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf_b = [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ];
+        let shelf_a = [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0];
+
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass_a = [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0];
+        Self {
+            shelf_b,
+            shelf_a,
+            highpass_b: [1.0, -2.0, 1.0],
+            highpass_a,
+        }
+    }
+
+    // Transposed direct form II, `state` holds two values per biquad
+    #[inline]
+    fn process(&self, state: &mut [f64; 4], x: f64) -> f64 {
+        let (b, a) = (&self.shelf_b, &self.shelf_a);
+        let y = b[0] * x + state[0];
+        state[0] = b[1] * x - a[0] * y + state[1];
+        state[1] = b[2] * x - a[1] * y;
+
+        let (b, a, x) = (&self.highpass_b, &self.highpass_a, y);
+        let y = b[0] * x + state[2];
+        state[2] = b[1] * x - a[0] * y + state[3];
+        state[3] = b[2] * x - a[1] * y;
+        y
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::{
+        dsp::loudness::{Loudness, LoudnessMeter},
+        sample_rate::SampleRate,
+    };
+
+    fn sine(channels: usize, amplitude: f32, seconds: f32) -> Vec<f32> {
+        let frames = (48_000.0 * seconds) as usize;
+        (0..frames)
+            .flat_map(|i| {
+                let s =
+                    amplitude * (2.0 * std::f32::consts::PI * 997.0 * i as f32 / 48_000.0).sin();
+                std::iter::repeat(s).take(channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_loudness_full_scale_sine() {
+        // BS.1770: a 0 dBFS 997 Hz sine on one channel reads -3.01 LUFS
+        let mut meter = LoudnessMeter::new(1, SampleRate::Sr48000).unwrap();
+        assert_eq!(meter.loudness(), Loudness::default());
+        meter.process(&sine(1, 1.0, 4.0)).unwrap();
+        let loudness = meter.loudness();
+        assert!((loudness.momentary + 3.01).abs() < 0.05, "{loudness:?}");
+        assert!((loudness.short_term + 3.01).abs() < 0.05, "{loudness:?}");
+        assert!((loudness.integrated + 3.01).abs() < 0.05, "{loudness:?}");
+        assert!(loudness.true_peak.abs() < 0.1, "{loudness:?}");
+
+        // On both channels of a stereo signal it reads 0 LUFS
+        let mut meter = LoudnessMeter::new(2, SampleRate::Sr48000).unwrap();
+        meter.process(&sine(2, 1.0, 1.0)).unwrap();
+        assert!(meter.integrated().abs() < 0.05);
+
+        meter.reset();
+        assert_eq!(meter.loudness(), Loudness::default());
+    }
+
+    #[test]
+    fn test_loudness_integrated_is_gated() {
+        let mut meter = LoudnessMeter::new(1, SampleRate::Sr48000).unwrap();
+        // -20 dBFS, then 20 dB quieter, then silence. Only the first part, and the blocks
+        // overlapping its end, are above the relative gate.
+        meter.process(&sine(1, 0.1, 10.0)).unwrap();
+        meter.process(&sine(1, 0.01, 5.0)).unwrap();
+        meter.process(&vec![0.0; 48_000 * 5]).unwrap();
+        let loudness = meter.loudness();
+        assert!((loudness.integrated + 23.01).abs() < 0.1, "{loudness:?}");
+        assert_eq!(loudness.momentary, f64::NEG_INFINITY);
+        assert!(loudness.true_peak < -19.9);
+    }
+
+    #[test]
+    fn test_loudness_true_peak_between_samples() {
+        // A quarter of the sample rate, sampled 45 degrees off its peaks
+        let samples: Vec<f32> = [
+            0.5f32.sqrt(),
+            0.5f32.sqrt(),
+            -(0.5f32.sqrt()),
+            -(0.5f32.sqrt()),
+        ]
+        .repeat(4800);
+        let buffer = crate::audio::formats::SampleBuffer::from_storage(samples, 19_200, 1).unwrap();
+        let loudness = LoudnessMeter::measure(&buffer, SampleRate::Sr48000).unwrap();
+        // The sample peak is at -3 dB
+        assert!(loudness.true_peak > -0.7, "{loudness:?}");
+        assert!(loudness.true_peak < 0.5, "{loudness:?}");
+    }
+
+    #[test]
+    fn test_loudness_invalid_args() {
+        assert!(LoudnessMeter::new(0, SampleRate::Sr48000).is_err());
+        let mut meter = LoudnessMeter::new(2, SampleRate::Sr44100).unwrap();
+        assert!(meter.process(&[0.0; 3]).is_err());
+        assert!(meter.set_channel_weights(&[1.0]).is_err());
+        meter.set_channel_weights(&[1.0, 0.0]).unwrap();
+    }
+}
@@ -1,14 +1,30 @@
 //! Digital signal processing primitives.
 //!
 //! This module contains reusable DSP types that operate directly on PCM frames,
-//! such as biquad, low-pass, high-pass, and band-pass filters.
+//! such as biquad, low-pass, high-pass, and band-pass filters, a noise gate, a pitch shifter,
+//! fades with a selectable curve, an ADSR envelope or a loudness meter.
 //!
 //! These types are independent of the engine and node graph. They can be used
 //! from device callbacks, custom nodes, offline processing code, or any other
 //! low-level audio pipeline.
+//!
+//! The noise gate, pitch shifter, shaped fader, envelope and loudness meter are written
+//! in Rust and do not call into miniaudio. They do not allocate while processing, so
+//! they can be driven directly from a device callback.
 pub mod delay_effect;
+pub mod envelope;
 pub mod fader;
 pub mod filters;
+pub mod gainer;
+pub mod loudness;
+pub mod noise_gate;
+pub mod pitch_shifter;
+pub mod shaped_fader;
 pub mod spatializer;
 pub mod stereo_panner;
-pub mod volume_gainer;
+
+/// Renamed to [`gainer`].
+#[deprecated(since = "0.1.6", note = "renamed to `audio::dsp::gainer`")]
+pub mod volume_gainer {
+    pub use super::gainer::{Gainer, GainerBuilder};
+}
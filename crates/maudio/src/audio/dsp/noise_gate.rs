@@ -0,0 +1,460 @@
+//! Noise gate with hysteresis, hold and optional lookahead.
+use crate::{audio::sample_rate::SampleRate, ErrorKinds, MaResult, MaudioError};
+
+/// A noise gate operating on interleaved `f32` PCM frames.
+///
+/// The gate mutes the signal while its level stays below a threshold and lets it through
+/// once the level rises above it. It is useful on drum buses, microphones and other noisy
+/// inputs where background noise should be removed between hits or phrases.
+///
+/// ## Parameters
+/// - **open threshold** (dB): level at which a closed gate opens.
+/// - **close threshold** (dB): level below which an open gate starts closing. Keeping this
+///   lower than the open threshold (hysteresis) prevents chattering on signals that hover
+///   around a single threshold.
+/// - **attack** (ms): time for the gain to ramp from fully closed to fully open.
+/// - **hold** (ms): how long the gate stays open after the level drops below the close threshold.
+/// - **release** (ms): time for the gain to ramp from fully open to fully closed.
+/// - **lookahead** (ms): delays the audio relative to the level detector, so the gate can
+///   open before a transient arrives. This adds the same amount of latency.
+///
+/// The level is detected per frame as the peak across all channels, so every channel
+/// is gated together.
+///
+/// To gate a node graph branch, use
+/// [`GateNode`](crate::engine::node_graph::nodes::effects::gate::GateNode).
+///
+/// Use [`NoiseGateBuilder`] to initialize.
+pub struct NoiseGate {
+    channels: usize,
+    sample_rate: u32,
+    open_threshold: f32,
+    close_threshold: f32,
+    attack_step: f32,
+    release_step: f32,
+    hold_frames: u32,
+    hold_counter: u32,
+    gain: f32,
+    open: bool,
+    delay_line: Vec<f32>,
+    delay_pos: usize,
+}
+
+impl NoiseGate {
+    /// Processes interleaved frames from `frames_in` into `frames_out`.
+    ///
+    /// Both slices must hold the same number of samples and a whole number of frames.
+    pub fn process_pcm_frames(
+        &mut self,
+        frames_out: &mut [f32],
+        frames_in: &[f32],
+    ) -> MaResult<()> {
+        if frames_out.len() != frames_in.len() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "NoiseGate::process_pcm_frames",
+                expected: frames_in.len(),
+                actual: frames_out.len(),
+            }));
+        }
+        if frames_in.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+
+        let channels = self.channels;
+        for (frame_in, frame_out) in frames_in
+            .chunks_exact(channels)
+            .zip(frames_out.chunks_exact_mut(channels))
+        {
+            let level = frame_in.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+            self.update_state(level);
+
+            if self.delay_line.is_empty() {
+                for (out, sample) in frame_out.iter_mut().zip(frame_in) {
+                    *out = sample * self.gain;
+                }
+            } else {
+                let slot = &mut self.delay_line[self.delay_pos..self.delay_pos + channels];
+                for ((out, delayed), sample) in
+                    frame_out.iter_mut().zip(slot.iter_mut()).zip(frame_in)
+                {
+                    *out = *delayed * self.gain;
+                    *delayed = *sample;
+                }
+                self.delay_pos += channels;
+                if self.delay_pos == self.delay_line.len() {
+                    self.delay_pos = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Processes interleaved frames in place.
+    pub fn process_in_place(&mut self, frames: &mut [f32]) -> MaResult<()> {
+        if frames.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+        let channels = self.channels;
+        for frame in frames.chunks_exact_mut(channels) {
+            let level = frame.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+            self.update_state(level);
+
+            if self.delay_line.is_empty() {
+                frame.iter_mut().for_each(|s| *s *= self.gain);
+            } else {
+                let slot = &mut self.delay_line[self.delay_pos..self.delay_pos + channels];
+                for (sample, delayed) in frame.iter_mut().zip(slot.iter_mut()) {
+                    let current = *sample;
+                    *sample = *delayed * self.gain;
+                    *delayed = current;
+                }
+                self.delay_pos += channels;
+                if self.delay_pos == self.delay_line.len() {
+                    self.delay_pos = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` while the gate is open (including the hold phase).
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Returns the current gain applied to the signal, between `0.0` and `1.0`.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Returns the latency introduced by the lookahead, in frames.
+    pub fn latency(&self) -> u32 {
+        (self.delay_line.len() / self.channels) as u32
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels as u32
+    }
+
+    /// Sets the open and close thresholds in dB.
+    ///
+    /// `close_db` is clamped so it is never above `open_db`.
+    pub fn set_thresholds_db(&mut self, open_db: f32, close_db: f32) {
+        self.open_threshold = db_to_linear(open_db);
+        self.close_threshold = db_to_linear(close_db.min(open_db));
+    }
+
+    /// Sets the attack time in milliseconds.
+    pub fn set_attack_ms(&mut self, attack_ms: f32) {
+        self.attack_step = ramp_step(attack_ms, self.sample_rate);
+    }
+
+    /// Sets the hold time in milliseconds.
+    pub fn set_hold_ms(&mut self, hold_ms: f32) {
+        self.hold_frames = ms_to_frames(hold_ms, self.sample_rate) as u32;
+    }
+
+    /// Sets the release time in milliseconds.
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        self.release_step = ramp_step(release_ms, self.sample_rate);
+    }
+
+    /// Clears the lookahead buffer and closes the gate.
+    pub fn reset(&mut self) {
+        self.delay_line.fill(0.0);
+        self.delay_pos = 0;
+        self.gain = 0.0;
+        self.open = false;
+        self.hold_counter = 0;
+    }
+
+    #[inline]
+    fn update_state(&mut self, level: f32) {
+        if self.open {
+            if level >= self.close_threshold {
+                self.hold_counter = self.hold_frames;
+            } else if self.hold_counter > 0 {
+                self.hold_counter -= 1;
+            } else {
+                self.open = false;
+            }
+        } else if level >= self.open_threshold {
+            self.open = true;
+            self.hold_counter = self.hold_frames;
+        }
+
+        if self.open {
+            self.gain = (self.gain + self.attack_step).min(1.0);
+        } else {
+            self.gain = (self.gain - self.release_step).max(0.0);
+        }
+    }
+}
+
+/// Builder for creating a [`NoiseGate`]
+pub struct NoiseGateBuilder {
+    channels: u32,
+    sample_rate: SampleRate,
+    open_db: f32,
+    close_db: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    lookahead_ms: f32,
+}
+
+impl NoiseGateBuilder {
+    /// Creates a builder with an open threshold of -40 dB, a close threshold of -50 dB,
+    /// 1 ms attack, 50 ms hold, 100 ms release and no lookahead.
+    pub fn new(channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            open_db: -40.0,
+            close_db: -50.0,
+            attack_ms: 1.0,
+            hold_ms: 50.0,
+            release_ms: 100.0,
+            lookahead_ms: 0.0,
+        }
+    }
+
+    /// Sets the level (dB) at which the gate opens.
+    pub fn open_threshold_db(&mut self, db: f32) -> &mut Self {
+        self.open_db = db;
+        self
+    }
+
+    /// Sets the level (dB) below which the gate starts closing.
+    ///
+    /// Clamped to the open threshold when building.
+    pub fn close_threshold_db(&mut self, db: f32) -> &mut Self {
+        self.close_db = db;
+        self
+    }
+
+    pub fn attack_ms(&mut self, ms: f32) -> &mut Self {
+        self.attack_ms = ms;
+        self
+    }
+
+    pub fn hold_ms(&mut self, ms: f32) -> &mut Self {
+        self.hold_ms = ms;
+        self
+    }
+
+    pub fn release_ms(&mut self, ms: f32) -> &mut Self {
+        self.release_ms = ms;
+        self
+    }
+
+    /// Sets the lookahead time in milliseconds. Adds the same amount of latency.
+    pub fn lookahead_ms(&mut self, ms: f32) -> &mut Self {
+        self.lookahead_ms = ms;
+        self
+    }
+
+    pub fn build(&self) -> MaResult<NoiseGate> {
+        let times = [
+            self.attack_ms,
+            self.hold_ms,
+            self.release_ms,
+            self.lookahead_ms,
+        ];
+        if self.channels == 0
+            || !self.open_db.is_finite()
+            || !self.close_db.is_finite()
+            || times.iter().any(|t| !t.is_finite() || *t < 0.0)
+        {
+            return Err(MaudioError::from_ma_result(
+                maudio_sys::ffi::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+
+        let sample_rate: u32 = self.sample_rate.into();
+        let channels = self.channels as usize;
+        let lookahead_frames = ms_to_frames(self.lookahead_ms, sample_rate);
+        let delay_len = lookahead_frames
+            .checked_mul(channels)
+            .ok_or(MaudioError::new_ma_error(ErrorKinds::IntegerOverflow {
+                op: "lookahead frames * channels",
+            }))?;
+
+        let mut gate = NoiseGate {
+            channels,
+            sample_rate,
+            open_threshold: 0.0,
+            close_threshold: 0.0,
+            attack_step: 0.0,
+            release_step: 0.0,
+            hold_frames: 0,
+            hold_counter: 0,
+            gain: 0.0,
+            open: false,
+            delay_line: vec![0.0; delay_len],
+            delay_pos: 0,
+        };
+        gate.set_thresholds_db(self.open_db, self.close_db);
+        gate.set_attack_ms(self.attack_ms);
+        gate.set_hold_ms(self.hold_ms);
+        gate.set_release_ms(self.release_ms);
+        Ok(gate)
+    }
+}
+
+#[inline]
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[inline]
+fn ms_to_frames(ms: f32, sample_rate: u32) -> usize {
+    (ms.max(0.0) as f64 * sample_rate as f64 / 1000.0).round() as usize
+}
+
+// Gain increment per frame for a full 0..1 ramp. Zero time jumps straight to the target.
+#[inline]
+fn ramp_step(ms: f32, sample_rate: u32) -> f32 {
+    let frames = ms_to_frames(ms, sample_rate);
+    if frames == 0 {
+        1.0
+    } else {
+        1.0 / frames as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: SampleRate = SampleRate::Sr48000;
+
+    #[test]
+    fn noise_gate_test_build_invalid_args() {
+        assert!(NoiseGateBuilder::new(0, SR).build().is_err());
+        assert!(NoiseGateBuilder::new(1, SR)
+            .attack_ms(-1.0)
+            .build()
+            .is_err());
+        assert!(NoiseGateBuilder::new(1, SR)
+            .open_threshold_db(f32::NAN)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn noise_gate_test_quiet_signal_is_muted() -> MaResult<()> {
+        let mut gate = NoiseGateBuilder::new(2, SR).build()?;
+
+        let frames_in = [0.001_f32; 64];
+        let mut frames_out = [1.0_f32; 64];
+        gate.process_pcm_frames(&mut frames_out, &frames_in)?;
+
+        assert!(!gate.is_open());
+        assert!(frames_out.iter().all(|s| *s == 0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn noise_gate_test_loud_signal_opens() -> MaResult<()> {
+        let mut gate = NoiseGateBuilder::new(1, SR).attack_ms(0.0).build()?;
+
+        let frames_in = [0.5_f32; 16];
+        let mut frames_out = [0.0_f32; 16];
+        gate.process_pcm_frames(&mut frames_out, &frames_in)?;
+
+        assert!(gate.is_open());
+        assert_eq!(frames_out, frames_in);
+        Ok(())
+    }
+
+    #[test]
+    fn noise_gate_test_hysteresis_keeps_gate_open() -> MaResult<()> {
+        // Opens at -20 dB (0.1), closes below -40 dB (0.01)
+        let mut gate = NoiseGateBuilder::new(1, SR)
+            .open_threshold_db(-20.0)
+            .close_threshold_db(-40.0)
+            .attack_ms(0.0)
+            .hold_ms(0.0)
+            .release_ms(0.0)
+            .build()?;
+
+        let mut frames = [0.05_f32; 8];
+        gate.process_in_place(&mut frames)?;
+        assert!(!gate.is_open());
+
+        let mut frames = [0.2_f32; 8];
+        gate.process_in_place(&mut frames)?;
+        assert!(gate.is_open());
+
+        // Between the thresholds, the gate stays open
+        let mut frames = [0.05_f32; 8];
+        gate.process_in_place(&mut frames)?;
+        assert!(gate.is_open());
+
+        let mut frames = [0.001_f32; 8];
+        gate.process_in_place(&mut frames)?;
+        assert!(!gate.is_open());
+        Ok(())
+    }
+
+    #[test]
+    fn noise_gate_test_hold_delays_close() -> MaResult<()> {
+        // 1 ms hold at 48 kHz = 48 frames
+        let mut gate = NoiseGateBuilder::new(1, SR)
+            .attack_ms(0.0)
+            .hold_ms(1.0)
+            .release_ms(0.0)
+            .build()?;
+
+        let mut frames = [0.5_f32; 4];
+        gate.process_in_place(&mut frames)?;
+
+        let mut frames = [0.0_f32; 40];
+        gate.process_in_place(&mut frames)?;
+        assert!(gate.is_open());
+
+        let mut frames = [0.0_f32; 16];
+        gate.process_in_place(&mut frames)?;
+        assert!(!gate.is_open());
+        Ok(())
+    }
+
+    #[test]
+    fn noise_gate_test_lookahead_delays_audio() -> MaResult<()> {
+        // 1 ms lookahead at 48 kHz = 48 frames of latency
+        let mut gate = NoiseGateBuilder::new(1, SR)
+            .attack_ms(0.0)
+            .lookahead_ms(1.0)
+            .build()?;
+        assert_eq!(gate.latency(), 48);
+
+        let mut frames_in = vec![0.0_f32; 96];
+        frames_in[10] = 0.9;
+        let mut frames_out = vec![0.0_f32; 96];
+        gate.process_pcm_frames(&mut frames_out, &frames_in)?;
+
+        // The transient comes out delayed and already at full gain
+        assert_eq!(frames_out[58], 0.9);
+        Ok(())
+    }
+
+    #[test]
+    fn noise_gate_test_mismatched_buffers_error() -> MaResult<()> {
+        let mut gate = NoiseGateBuilder::new(2, SR).build()?;
+
+        let frames_in = [0.0_f32; 8];
+        let mut frames_out = [0.0_f32; 6];
+        assert!(gate
+            .process_pcm_frames(&mut frames_out, &frames_in)
+            .is_err());
+
+        let mut odd = [0.0_f32; 7];
+        assert!(gate.process_in_place(&mut odd).is_err());
+        Ok(())
+    }
+}
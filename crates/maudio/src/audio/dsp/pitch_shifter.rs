@@ -0,0 +1,327 @@
+//! Pitch shifter that keeps the playback speed.
+use std::f32::consts::PI;
+
+use crate::{audio::sample_rate::SampleRate, ErrorKinds, MaResult, MaudioError};
+
+/// Largest shift accepted by [`PitchShifter::set_semitones`], in either direction.
+pub const MAX_SEMITONES: f32 = 24.0;
+
+/// A pitch shifter operating on interleaved `f32` PCM frames.
+///
+/// Unlike [`Sound::set_pitch`](crate::sound::Sound::set_pitch), which resamples the sound
+/// and so changes its speed, this changes the pitch while the duration stays the same.
+/// It is suited to voice and music.
+///
+/// ## How it works
+/// The input is written into a short delay line and read back by two taps whose delay
+/// sweeps across a window at a rate set by the pitch ratio. Reading a delay line that
+/// shrinks or grows resamples the audio, and the taps are half a window apart and
+/// crossfaded so that each one jumps back while it is silent.
+///
+/// Larger windows give a smoother result on low and sustained sounds, smaller windows
+/// suit speech and percussive material. The output is delayed by roughly half a window,
+/// see [`PitchShifter::latency`].
+///
+/// The node graph version is
+/// [`PitchShiftNode`](crate::engine::node_graph::nodes::effects::pitch_shift::PitchShiftNode).
+///
+/// Use [`PitchShifterBuilder`] to initialize.
+pub struct PitchShifter {
+    channels: usize,
+    semitones: f32,
+    // Phase step per frame, `(1 - ratio) / window`.
+    step: f32,
+    phase: f32,
+    window: f32,
+    delay_line: Vec<f32>,
+    // Length of the delay line in frames.
+    delay_frames: usize,
+    write_pos: usize,
+}
+
+impl PitchShifter {
+    /// Processes interleaved frames from `frames_in` into `frames_out`.
+    ///
+    /// Both slices must hold the same number of samples and a whole number of frames.
+    pub fn process_pcm_frames(
+        &mut self,
+        frames_out: &mut [f32],
+        frames_in: &[f32],
+    ) -> MaResult<()> {
+        if frames_out.len() != frames_in.len() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "PitchShifter::process_pcm_frames",
+                expected: frames_in.len(),
+                actual: frames_out.len(),
+            }));
+        }
+        if frames_in.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+
+        let channels = self.channels;
+        for (frame_in, frame_out) in frames_in
+            .chunks_exact(channels)
+            .zip(frames_out.chunks_exact_mut(channels))
+        {
+            self.push(frame_in);
+            self.render(frame_out);
+        }
+        Ok(())
+    }
+
+    /// Processes interleaved frames in place.
+    pub fn process_in_place(&mut self, frames: &mut [f32]) -> MaResult<()> {
+        if frames.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+        for frame in frames.chunks_exact_mut(self.channels) {
+            self.push(frame);
+            self.render(frame);
+        }
+        Ok(())
+    }
+
+    /// Returns the pitch shift in semitones.
+    pub fn semitones(&self) -> f32 {
+        self.semitones
+    }
+
+    /// Sets the pitch shift in semitones. Positive values raise the pitch.
+    ///
+    /// Clamped to ±[`MAX_SEMITONES`]. Non-finite values are ignored.
+    pub fn set_semitones(&mut self, semitones: f32) {
+        if !semitones.is_finite() {
+            return;
+        }
+        self.semitones = semitones.clamp(-MAX_SEMITONES, MAX_SEMITONES);
+        self.step = (1.0 - semitones_to_ratio(self.semitones)) / self.window;
+    }
+
+    /// Returns the frequency ratio, `2^(semitones / 12)`.
+    pub fn ratio(&self) -> f32 {
+        semitones_to_ratio(self.semitones)
+    }
+
+    /// Returns the average delay of the output, in frames.
+    pub fn latency(&self) -> u32 {
+        (self.window / 2.0) as u32
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels as u32
+    }
+
+    /// Clears the delay line.
+    pub fn reset(&mut self) {
+        self.delay_line.fill(0.0);
+        self.write_pos = 0;
+        self.phase = 0.0;
+    }
+
+    #[inline]
+    fn push(&mut self, frame: &[f32]) {
+        let base = self.write_pos * self.channels;
+        self.delay_line[base..base + self.channels].copy_from_slice(frame);
+    }
+
+    // Reads the taps for the last pushed frame and moves on to the next one.
+    #[inline]
+    fn render(&mut self, frame_out: &mut [f32]) {
+        let phase_b = (self.phase + 0.5).fract();
+        let (delay_a, delay_b) = (self.phase * self.window, phase_b * self.window);
+        // Hann weights, they always sum to 1 and each tap is silent when it wraps around
+        let gain_a = (PI * self.phase).sin().powi(2);
+        let gain_b = 1.0 - gain_a;
+
+        for (channel, out) in frame_out.iter_mut().enumerate() {
+            *out = gain_a * self.read(delay_a, channel) + gain_b * self.read(delay_b, channel);
+        }
+
+        self.phase = (self.phase + self.step).rem_euclid(1.0);
+        self.write_pos += 1;
+        if self.write_pos == self.delay_frames {
+            self.write_pos = 0;
+        }
+    }
+
+    // Reads `delay` frames behind the last written frame, with linear interpolation.
+    #[inline]
+    fn read(&self, delay: f32, channel: usize) -> f32 {
+        let whole = delay as usize;
+        let frac = delay - whole as f32;
+        let len = self.delay_frames;
+        let i0 = (self.write_pos + len - whole) % len;
+        let i1 = (i0 + len - 1) % len;
+        let s0 = self.delay_line[i0 * self.channels + channel];
+        let s1 = self.delay_line[i1 * self.channels + channel];
+        s0 + (s1 - s0) * frac
+    }
+}
+
+/// Builder for creating a [`PitchShifter`]
+pub struct PitchShifterBuilder {
+    channels: u32,
+    sample_rate: SampleRate,
+    semitones: f32,
+    window_ms: f32,
+}
+
+impl PitchShifterBuilder {
+    /// Creates a builder with no shift and a 50 ms window.
+    pub fn new(channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            semitones: 0.0,
+            window_ms: 50.0,
+        }
+    }
+
+    /// Sets the initial pitch shift in semitones.
+    pub fn semitones(&mut self, semitones: f32) -> &mut Self {
+        self.semitones = semitones;
+        self
+    }
+
+    /// Sets the window length in milliseconds. Must be between 5 and 500 ms.
+    pub fn window_ms(&mut self, ms: f32) -> &mut Self {
+        self.window_ms = ms;
+        self
+    }
+
+    pub fn build(&self) -> MaResult<PitchShifter> {
+        if self.channels == 0
+            || !self.semitones.is_finite()
+            || !(5.0..=500.0).contains(&self.window_ms)
+        {
+            return Err(MaudioError::from_ma_result(
+                maudio_sys::ffi::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+
+        let sample_rate: u32 = self.sample_rate.into();
+        let channels = self.channels as usize;
+        let window = (self.window_ms as f64 * sample_rate as f64 / 1000.0).round() as usize;
+        // Room for the longest delay plus the interpolated frame
+        let delay_frames = window + 2;
+        let delay_len = delay_frames
+            .checked_mul(channels)
+            .ok_or(MaudioError::new_ma_error(ErrorKinds::IntegerOverflow {
+                op: "window frames * channels",
+            }))?;
+
+        let mut shifter = PitchShifter {
+            channels,
+            semitones: 0.0,
+            step: 0.0,
+            phase: 0.0,
+            window: window as f32,
+            delay_line: vec![0.0; delay_len],
+            delay_frames,
+            write_pos: 0,
+        };
+        shifter.set_semitones(self.semitones);
+        Ok(shifter)
+    }
+}
+
+#[inline]
+fn semitones_to_ratio(semitones: f32) -> f32 {
+    2f32.powf(semitones / 12.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: SampleRate = SampleRate::Sr48000;
+
+    fn sine(frequency: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|i| (2.0 * PI * frequency * i as f32 / 48_000.0).sin() * 0.5)
+            .collect()
+    }
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn pitch_shifter_test_build_invalid_args() {
+        assert!(PitchShifterBuilder::new(0, SR).build().is_err());
+        assert!(PitchShifterBuilder::new(1, SR)
+            .window_ms(1.0)
+            .build()
+            .is_err());
+        assert!(PitchShifterBuilder::new(1, SR)
+            .semitones(f32::NAN)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn pitch_shifter_test_octave_up_doubles_frequency() -> MaResult<()> {
+        let mut shifter = PitchShifterBuilder::new(1, SR).semitones(12.0).build()?;
+        assert!((shifter.ratio() - 2.0).abs() < 1e-6);
+
+        let frames_in = sine(220.0, 48_000);
+        let mut frames_out = vec![0.0; frames_in.len()];
+        shifter.process_pcm_frames(&mut frames_out, &frames_in)?;
+
+        // Skip the start, while the delay line fills up
+        let crossings = zero_crossings(&frames_out[4800..]) as f32;
+        let expected = zero_crossings(&frames_in[4800..]) as f32 * 2.0;
+        assert!(
+            (crossings / expected - 1.0).abs() < 0.1,
+            "{crossings} vs {expected}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pitch_shifter_test_no_shift_delays_audio() -> MaResult<()> {
+        let mut shifter = PitchShifterBuilder::new(2, SR).window_ms(10.0).build()?;
+        let latency = shifter.latency() as usize;
+        assert_eq!(latency, 240);
+
+        let mut frames = vec![0.0; 1024 * 2];
+        frames[0] = 1.0;
+        frames[1] = -1.0;
+        shifter.process_in_place(&mut frames)?;
+
+        assert_eq!(frames[latency * 2], 1.0);
+        assert_eq!(frames[latency * 2 + 1], -1.0);
+        assert_eq!(frames.iter().filter(|s| **s != 0.0).count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn pitch_shifter_test_set_semitones_clamps() -> MaResult<()> {
+        let mut shifter = PitchShifterBuilder::new(1, SR).build()?;
+        shifter.set_semitones(-40.0);
+        assert_eq!(shifter.semitones(), -MAX_SEMITONES);
+        shifter.set_semitones(f32::INFINITY);
+        assert_eq!(shifter.semitones(), -MAX_SEMITONES);
+        Ok(())
+    }
+
+    #[test]
+    fn pitch_shifter_test_mismatched_buffers_error() -> MaResult<()> {
+        let mut shifter = PitchShifterBuilder::new(2, SR).build()?;
+        let frames_in = [0.0_f32; 8];
+        let mut frames_out = [0.0_f32; 6];
+        assert!(shifter
+            .process_pcm_frames(&mut frames_out, &frames_in)
+            .is_err());
+        assert!(shifter.process_in_place(&mut [0.0; 3]).is_err());
+        Ok(())
+    }
+}
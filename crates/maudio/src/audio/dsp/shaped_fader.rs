@@ -0,0 +1,241 @@
+//! Volume fades with a selectable curve.
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{ErrorKinds, MaResult, MaudioError};
+
+/// Shape of a fade between two volumes.
+///
+/// Curves are written for a rising fade, mapping the progress `t` (`0.0..=1.0`) to the
+/// fraction of the volume change applied so far. Falling fades use the curve mirrored in
+/// time, so a fade out sounds like the fade in played backwards. [`FadeCurve::Custom`] is
+/// used as given in both directions.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum FadeCurve {
+    /// Straight line, the curve used by miniaudio's own fades.
+    #[default]
+    Linear,
+    /// Keeps the summed power constant when a fade out overlaps a fade in of the same
+    /// length, which makes it the usual choice for crossfades.
+    EqualPower,
+    /// Changes at a constant rate in decibels over a 60 dB range, which sounds even to
+    /// the ear. Suited to long fade outs.
+    Exponential,
+    /// Smooth start and end (smoothstep).
+    SCurve,
+    /// User curve mapping `t` in `0.0..=1.0` to `0.0..=1.0`.
+    ///
+    /// Runs on the audio thread, so it must not block or allocate.
+    Custom(fn(f32) -> f32),
+}
+
+// Range covered by `FadeCurve::Exponential`, as a linear gain ratio (60 dB).
+const EXPONENTIAL_RANGE: f32 = 1000.0;
+
+impl FadeCurve {
+    /// Progress of a rising fade at `t`, clamped to `0.0..=1.0`.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let shaped = match self {
+            FadeCurve::Linear => t,
+            FadeCurve::EqualPower => (t * FRAC_PI_2).sin(),
+            FadeCurve::Exponential => (EXPONENTIAL_RANGE.powf(t) - 1.0) / (EXPONENTIAL_RANGE - 1.0),
+            FadeCurve::SCurve => t * t * (3.0 - 2.0 * t),
+            FadeCurve::Custom(f) => f(t),
+        };
+        shaped.clamp(0.0, 1.0)
+    }
+
+    // Progress at `t` for a fade from `start` to `end`
+    #[inline]
+    fn progress(&self, t: f32, start: f32, end: f32) -> f32 {
+        match self {
+            FadeCurve::Linear | FadeCurve::Custom(_) => self.apply(t),
+            _ if end >= start => self.apply(t),
+            _ => 1.0 - self.apply(1.0 - t),
+        }
+    }
+}
+
+/// Applies a volume fade with a [`FadeCurve`] to interleaved `f32` PCM frames.
+///
+/// Unlike miniaudio's fader, which only fades linearly, the curve is evaluated for every
+/// frame. Once a fade completes, the end volume is held until the next fade.
+///
+/// [`FadeNode`](crate::engine::node_graph::nodes::effects::fade::FadeNode) wraps it for use
+/// in a node graph.
+pub struct ShapedFader {
+    channels: usize,
+    volume_start: f32,
+    volume_end: f32,
+    curve: FadeCurve,
+    length: u64,
+    cursor: u64,
+    current: f32,
+}
+
+impl ShapedFader {
+    /// Creates a fader at full volume, with no fade in progress.
+    pub fn new(channels: u32) -> MaResult<Self> {
+        if channels == 0 {
+            return Err(MaudioError::from_ma_result(
+                maudio_sys::ffi::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+        Ok(Self {
+            channels: channels as usize,
+            volume_start: 1.0,
+            volume_end: 1.0,
+            curve: FadeCurve::Linear,
+            length: 0,
+            cursor: 0,
+            current: 1.0,
+        })
+    }
+
+    /// Starts a fade from `volume_start` to `volume_end` over `length_frames` frames.
+    ///
+    /// A negative `volume_start` starts from the current volume. A length of zero jumps
+    /// straight to `volume_end`.
+    pub fn set_fade(
+        &mut self,
+        volume_start: f32,
+        volume_end: f32,
+        length_frames: u64,
+        curve: FadeCurve,
+    ) {
+        self.volume_start = if volume_start < 0.0 {
+            self.current
+        } else {
+            volume_start
+        };
+        self.volume_end = volume_end;
+        self.curve = curve;
+        self.length = length_frames;
+        self.cursor = 0;
+        self.current = if length_frames == 0 {
+            volume_end
+        } else {
+            self.volume_start
+        };
+    }
+
+    /// Processes interleaved frames in place.
+    pub fn process_in_place(&mut self, frames: &mut [f32]) -> MaResult<()> {
+        if frames.len() % self.channels != 0 {
+            return Err(MaudioError::new_ma_error(
+                ErrorKinds::InvalidDecodedDataLength,
+            ));
+        }
+        for frame in frames.chunks_exact_mut(self.channels) {
+            let gain = self.next_gain();
+            frame.iter_mut().for_each(|s| *s *= gain);
+        }
+        Ok(())
+    }
+
+    /// Processes interleaved frames from `frames_in` into `frames_out`.
+    ///
+    /// Both slices must hold the same number of samples and a whole number of frames.
+    pub fn process_pcm_frames(
+        &mut self,
+        frames_out: &mut [f32],
+        frames_in: &[f32],
+    ) -> MaResult<()> {
+        if frames_out.len() != frames_in.len() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "ShapedFader::process_pcm_frames",
+                expected: frames_in.len(),
+                actual: frames_out.len(),
+            }));
+        }
+        frames_out.copy_from_slice(frames_in);
+        self.process_in_place(frames_out)
+    }
+
+    /// Volume applied to the last processed frame.
+    pub fn current_volume(&self) -> f32 {
+        self.current
+    }
+
+    /// Returns `true` while a fade is in progress.
+    pub fn is_fading(&self) -> bool {
+        self.cursor < self.length
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels as u32
+    }
+
+    #[inline]
+    fn next_gain(&mut self) -> f32 {
+        if self.cursor < self.length {
+            self.cursor += 1;
+            let t = self.cursor as f32 / self.length as f32;
+            let progress = self.curve.progress(t, self.volume_start, self.volume_end);
+            self.current = self.volume_start + (self.volume_end - self.volume_start) * progress;
+        }
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shaped_fader_test_curves_end_points() {
+        let curves = [
+            FadeCurve::Linear,
+            FadeCurve::EqualPower,
+            FadeCurve::Exponential,
+            FadeCurve::SCurve,
+            FadeCurve::Custom(|t| t * t),
+        ];
+        for curve in curves {
+            assert_eq!(curve.apply(0.0), 0.0, "{curve:?}");
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-6, "{curve:?}");
+        }
+        assert!((FadeCurve::EqualPower.apply(0.5) - 0.70710677).abs() < 1e-6);
+        assert!(FadeCurve::Exponential.apply(0.5) < 0.05);
+        assert_eq!(FadeCurve::SCurve.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn shaped_fader_test_equal_power_crossfade() {
+        // A fade out and a fade in of the same length keep the total power constant
+        let mut fade_out = ShapedFader::new(1).unwrap();
+        let mut fade_in = ShapedFader::new(1).unwrap();
+        fade_out.set_fade(1.0, 0.0, 100, FadeCurve::EqualPower);
+        fade_in.set_fade(0.0, 1.0, 100, FadeCurve::EqualPower);
+
+        let mut a = [1.0f32; 100];
+        let mut b = [1.0f32; 100];
+        fade_out.process_in_place(&mut a).unwrap();
+        fade_in.process_in_place(&mut b).unwrap();
+        for (a, b) in a.iter().zip(&b) {
+            assert!((a * a + b * b - 1.0).abs() < 1e-4);
+        }
+        assert_eq!(fade_out.current_volume(), 0.0);
+        assert_eq!(fade_in.current_volume(), 1.0);
+    }
+
+    #[test]
+    fn shaped_fader_test_holds_end_volume() {
+        let mut fader = ShapedFader::new(2).unwrap();
+        fader.set_fade(1.0, 0.25, 4, FadeCurve::Exponential);
+        assert!(fader.is_fading());
+
+        let mut frames = [1.0f32; 16];
+        fader.process_in_place(&mut frames).unwrap();
+        assert!(!fader.is_fading());
+        // Falling fades drop fastest at the start
+        assert!(frames[0] < 0.5);
+        assert!(frames[8..].iter().all(|s| *s == 0.25));
+
+        // Negative start continues from the current volume
+        fader.set_fade(-1.0, 1.0, 0, FadeCurve::Linear);
+        assert_eq!(fader.current_volume(), 1.0);
+        assert!(fader.process_in_place(&mut [0.0; 3]).is_err());
+        assert!(ShapedFader::new(0).is_err());
+    }
+}
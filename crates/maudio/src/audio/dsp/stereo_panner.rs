@@ -1,17 +1,23 @@
+//! Stereo panning and balance.
 use std::{marker::PhantomData, mem::MaybeUninit};
 
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{formats::Format, pan::PanMode},
+    audio::{
+        formats::{Format, SampleBuffer},
+        pan::PanMode,
+    },
     pcm_frames::{PcmFormat, S24Packed},
     Binding, MaResult,
 };
 
+/// Pans interleaved stereo frames, the `ma_panner` used by miniaudio for sound and group pan.
+///
+/// Only stereo is panned. Other channel counts are copied unchanged.
 pub struct Panner<F: PcmFormat> {
     inner: *mut sys::ma_panner,
     channels: u32,
-    #[allow(unused)]
     format: Format,
     _format: PhantomData<F>,
 }
@@ -49,6 +55,13 @@ impl<F: PcmFormat> Panner<F> {
         panner_ffi::ma_panner_process_pcm_frames(self, frames_out, frames_in)
     }
 
+    /// Pans a buffer in place. The buffer must have the channel count of the panner.
+    pub fn process_buffer(&mut self, buffer: &mut SampleBuffer<F>) -> MaResult<()> {
+        panner_ffi::ma_panner_process_buffer(self, buffer)
+    }
+
+    /// Sets the pan, from `-1.0` (left) to `1.0` (right). Takes effect on the next call to
+    /// a process function.
     pub fn set_pan(&mut self, pan: f32) {
         panner_ffi::ma_panner_set_pan(self, pan);
     }
@@ -64,6 +77,14 @@ impl<F: PcmFormat> Panner<F> {
     pub fn get_mode(&self) -> MaResult<PanMode> {
         panner_ffi::ma_panner_get_mode(self)
     }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
 }
 
 pub struct PannerBuilder {
@@ -76,6 +97,18 @@ impl PannerBuilder {
         Self { config }
     }
 
+    /// Initial pan, from `-1.0` (left) to `1.0` (right). Defaults to `0.0`.
+    pub fn pan(&mut self, pan: f32) -> &mut Self {
+        self.config.pan = pan;
+        self
+    }
+
+    /// Defaults to [`PanMode::Balance`].
+    pub fn mode(&mut self, mode: PanMode) -> &mut Self {
+        self.config.mode = mode.into();
+        self
+    }
+
     pub fn build_u8(&mut self) -> MaResult<Panner<u8>> {
         self.config.format = Format::U8.into();
         Panner::<u8>::build(&self.config, Format::U8)
@@ -106,9 +139,9 @@ mod panner_ffi {
     use maudio_sys::ffi as sys;
 
     use crate::{
-        audio::{dsp::stereo_panner::Panner, pan::PanMode},
+        audio::{dsp::stereo_panner::Panner, formats::SampleBuffer, pan::PanMode},
         pcm_frames::PcmFormat,
-        Binding, MaResult, MaudioError,
+        Binding, ErrorKinds, MaResult, MaudioError,
     };
 
     #[inline]
@@ -142,6 +175,29 @@ mod panner_ffi {
         MaudioError::check(res)
     }
 
+    #[inline]
+    pub fn ma_panner_process_buffer<F: PcmFormat>(
+        panner: &mut Panner<F>,
+        buffer: &mut SampleBuffer<F>,
+    ) -> MaResult<()> {
+        if buffer.channels() != panner.channels {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "Panner::process_buffer channels",
+                expected: panner.channels as usize,
+                actual: buffer.channels() as usize,
+            }));
+        }
+        if !F::DIRECT_READ {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidFormat));
+        }
+        // Same layout as the miniaudio storage, processed in place
+        let ptr = buffer.as_mut().as_mut_ptr() as *mut core::ffi::c_void;
+        let res = unsafe {
+            sys::ma_panner_process_pcm_frames(panner.to_raw(), ptr, ptr, buffer.frames() as u64)
+        };
+        MaudioError::check(res)
+    }
+
     #[inline]
     pub fn ma_panner_set_mode<F: PcmFormat>(panner: &mut Panner<F>, mode: PanMode) {
         unsafe {
@@ -256,6 +312,30 @@ mod tests {
         assert_approx_eq(panner.get_pan(), 0.75);
     }
 
+    #[test]
+    fn panner_test_builder_options_and_process_buffer() {
+        let mut panner = PannerBuilder::new(2)
+            .pan(1.0)
+            .mode(PanMode::Pan)
+            .build_f32()
+            .unwrap();
+        assert_approx_eq(panner.get_pan(), 1.0);
+        assert_eq!(panner.get_mode().unwrap(), PanMode::Pan);
+        assert_eq!(panner.channels(), 2);
+        assert_eq!(panner.format(), Format::F32);
+
+        let mut buffer = SampleBuffer::<f32>::from_storage(vec![0.5; 8], 4, 2).unwrap();
+        panner.process_buffer(&mut buffer).unwrap();
+        // A full right pan moves the left channel to the right
+        for frame in buffer.as_ref().chunks_exact(2) {
+            assert_approx_eq(frame[0], 0.0);
+            assert_approx_eq(frame[1], 1.0);
+        }
+
+        let mut mono = SampleBuffer::<f32>::from_storage(vec![0.5; 4], 4, 1).unwrap();
+        assert!(panner.process_buffer(&mut mono).is_err());
+    }
+
     #[test]
     fn panner_test_set_mode_updates_mode_value() {
         let mut builder = PannerBuilder::new(2);
@@ -1,9 +1,12 @@
+pub mod g711;
+
 use std::marker::PhantomData;
 
 use maudio_sys::ffi as sys;
 
 use crate::{
     pcm_frames::{PcmFormat, PcmFormatInternal},
+    util::rt_check,
     ErrorKinds, MaResult, MaudioError,
 };
 
@@ -58,6 +61,18 @@ impl From<SampleFormat> for sys::ma_format {
     }
 }
 
+impl Format {
+    /// Size in bytes of a single sample in this format.
+    pub const fn bytes_per_sample(self) -> usize {
+        match self {
+            Format::U8 => 1,
+            Format::S16 => 2,
+            Format::S24Packed => 3,
+            Format::S32 | Format::F32 => 4,
+        }
+    }
+}
+
 impl From<Format> for sys::ma_format {
     fn from(value: Format) -> Self {
         match value {
@@ -199,6 +214,7 @@ impl<F: PcmFormat> SampleBuffer<F> {
     /// The length is frames * channels and it's initialized with silence for the chosen sample format
     /// (e.g. 0.0 for f32, 0 for i16, 128 for u8).
     pub fn new_zeroed(frames: usize, channels: u32) -> MaResult<Vec<F::StorageUnit>> {
+        rt_check::check("SampleBuffer::new_zeroed");
         let len = Self::required_len(frames, channels, F::VEC_STORE_UNITS_PER_FRAME)?;
         Ok(vec![F::STORE_SILENCE; len])
     }
@@ -211,6 +227,7 @@ impl<F: PcmFormat> SampleBuffer<F> {
         frames_read: usize,
         channels: u32,
     ) -> MaResult<SampleBuffer<F>> {
+        rt_check::check("read_pcm_frames");
         let len = frames_read
             .checked_mul(channels as usize)
             .ok_or(MaudioError::new_ma_error(ErrorKinds::IntegerOverflow {
@@ -236,6 +253,64 @@ impl<F: PcmFormat> SampleBuffer<F> {
         })
     }
 
+    /// Creates an empty buffer that holds up to `frames` frames without reallocating.
+    ///
+    /// Pass it to the `read_pcm_frames_into_buffer` methods of the engine reader and the
+    /// sources, to read a block at a time without allocating on every call.
+    pub fn with_capacity(frames: usize, channels: u32) -> MaResult<Self> {
+        rt_check::check("SampleBuffer::with_capacity");
+        let len = Self::required_len(frames, channels, F::VEC_PCM_UNITS_PER_FRAME)?;
+        Ok(Self::with_len_capacity(len, channels))
+    }
+
+    /// Empty buffer with room for `len` units, see [`SampleBuffer::required_len`].
+    pub(crate) fn with_len_capacity(len: usize, channels: u32) -> Self {
+        SampleBuffer {
+            data: Vec::with_capacity(len),
+            channels,
+            frames: 0,
+            _pcm_format: PhantomData,
+        }
+    }
+
+    /// Reads up to `frame_count` frames with `read`, replacing the content of the buffer.
+    ///
+    /// `read` fills a slice of `frame_count` frames and returns the number of frames read.
+    /// The buffer only reallocates if it never held that many frames before.
+    pub(crate) fn read_with<R>(
+        &mut self,
+        frame_count: u64,
+        channels: u32,
+        read: R,
+    ) -> MaResult<usize>
+    where
+        R: FnOnce(&mut [F::PcmUnit]) -> MaResult<usize>,
+    {
+        let frames = usize::try_from(frame_count).map_err(|_| {
+            MaudioError::new_ma_error(ErrorKinds::IntegerOverflow {
+                op: "frame_count to usize",
+            })
+        })?;
+        let len = Self::required_len(frames, channels, F::VEC_PCM_UNITS_PER_FRAME)?;
+        self.channels = channels;
+        self.frames = 0;
+        self.data.resize(len, F::PCM_UNIT_SILENCE);
+
+        let result = read(&mut self.data);
+        let frames_read = match result {
+            Ok(frames_read) => frames_read.min(frames),
+            Err(e) => {
+                self.data.clear();
+                return Err(e);
+            }
+        };
+        // frames_read <= frames, so this cannot overflow
+        self.data
+            .truncate(frames_read * channels as usize * F::VEC_PCM_UNITS_PER_FRAME);
+        self.frames = frames_read;
+        Ok(frames_read)
+    }
+
     pub fn channels(&self) -> u32 {
         self.channels
     }
@@ -253,6 +328,19 @@ impl<F: PcmFormat> SampleBuffer<F> {
         self.data.len()
     }
 
+    /// Removes all the frames, keeping the allocation.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.frames = 0;
+    }
+
+    /// Fills the buffer with silence for its sample format (e.g. 0.0 for f32, 128 for u8).
+    ///
+    /// The frame count and channels are not changed.
+    pub fn silence(&mut self) {
+        slice_silence::<F>(&mut self.data);
+    }
+
     fn as_slice(&self) -> &[F::PcmUnit] {
         &self.data
     }
@@ -262,6 +350,106 @@ impl<F: PcmFormat> SampleBuffer<F> {
     }
 }
 
+/// Fills a slice of interleaved samples with silence for the sample format `F`.
+///
+/// Silence is not always zero. Unsigned 8-bit PCM is centered around 128, so zeroing a `u8`
+/// buffer produces a full negative DC offset instead of silence. Useful for clearing output
+/// buffers in custom node or device callbacks.
+///
+/// ```
+/// # use maudio::audio::formats::slice_silence;
+/// let mut out = [0u8; 8];
+/// slice_silence::<u8>(&mut out);
+/// assert!(out.iter().all(|&s| s == 128));
+///
+/// let mut out = [0.5f32; 8];
+/// slice_silence::<f32>(&mut out);
+/// assert!(out.iter().all(|&s| s == 0.0));
+/// ```
+pub fn slice_silence<F: PcmFormat>(buf: &mut [F::PcmUnit]) {
+    if !F::DIRECT_READ {
+        buf.fill(F::PCM_UNIT_SILENCE);
+        return;
+    }
+    // PcmUnit has the same layout as the miniaudio storage. Silence whole samples as one channel.
+    let samples = buf.len() / F::VEC_PCM_UNITS_PER_FRAME;
+    formats_ffi::ma_silence_pcm_frames::<F>(buf, samples as u64);
+    // Trailing bytes of an incomplete S24Packed sample
+    buf[samples * F::VEC_PCM_UNITS_PER_FRAME..].fill(F::PCM_UNIT_SILENCE);
+}
+
+/// Copies `frames` interleaved frames from `src` to the start of `dst`.
+///
+/// Both slices are checked against `frames * channels` samples before anything is copied.
+/// Either slice may be longer than needed, in which case the rest is left untouched.
+///
+/// Returns [`ErrorKinds::BufferSizeMismatch`] if either slice is too short, naming the slice in
+/// the error context, or `MA_INVALID_ARGS` if `channels` is zero.
+///
+/// ```
+/// # use maudio::audio::formats::copy_pcm_frames;
+/// # fn main() -> maudio::MaResult<()> {
+/// let src = [0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6];
+/// let mut dst = [0.0f32; 8];
+/// // 2 stereo frames
+/// copy_pcm_frames::<f32>(&mut dst, &src, 2, 2)?;
+/// assert_eq!(&dst[..4], &src[..4]);
+///
+/// // `dst` only holds 4 stereo frames
+/// assert!(copy_pcm_frames::<f32>(&mut dst, &src, 5, 2).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub fn copy_pcm_frames<F: PcmFormat>(
+    dst: &mut [F::PcmUnit],
+    src: &[F::PcmUnit],
+    frames: usize,
+    channels: u32,
+) -> MaResult<()> {
+    if channels == 0 {
+        return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+    }
+    let len = SampleBuffer::<F>::required_len(frames, channels, F::VEC_PCM_UNITS_PER_FRAME)?;
+    if src.len() < len {
+        return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+            context: "copy_pcm_frames: src",
+            expected: len,
+            actual: src.len(),
+        }));
+    }
+    if dst.len() < len {
+        return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+            context: "copy_pcm_frames: dst",
+            expected: len,
+            actual: dst.len(),
+        }));
+    }
+    dst[..len].copy_from_slice(&src[..len]);
+    Ok(())
+}
+
+pub(crate) mod formats_ffi {
+    use maudio_sys::ffi as sys;
+
+    use crate::pcm_frames::PcmFormat;
+
+    // `buf` must hold at least `samples` samples of `F::FORMAT`
+    #[inline]
+    pub fn ma_silence_pcm_frames<F: PcmFormat>(buf: &mut [F::PcmUnit], samples: u64) {
+        if samples == 0 {
+            return;
+        }
+        unsafe {
+            sys::ma_silence_pcm_frames(
+                buf.as_mut_ptr() as *mut core::ffi::c_void,
+                samples,
+                F::FORMAT.into(),
+                1,
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::MaError;
@@ -273,6 +461,94 @@ mod tests {
         MaError(sys::ma_result_MA_ERROR)
     }
 
+    #[test]
+    fn test_formats_slice_silence_per_format() {
+        let mut u = [7u8; 6];
+        slice_silence::<u8>(&mut u);
+        assert_eq!(u, [128u8; 6]);
+
+        let mut s = [-3i16; 6];
+        slice_silence::<i16>(&mut s);
+        assert_eq!(s, [0i16; 6]);
+
+        let mut s = [9i32; 6];
+        slice_silence::<i32>(&mut s);
+        assert_eq!(s, [0i32; 6]);
+
+        let mut s = [9i32; 6];
+        slice_silence::<crate::pcm_frames::S24>(&mut s);
+        assert_eq!(s, [0i32; 6]);
+
+        // Includes an incomplete trailing sample
+        let mut p = [0xAAu8; 7];
+        slice_silence::<crate::pcm_frames::S24Packed>(&mut p);
+        assert_eq!(p, [0u8; 7]);
+
+        let mut f = [0.25f32; 6];
+        slice_silence::<f32>(&mut f);
+        assert_eq!(f, [0.0f32; 6]);
+
+        let mut empty: [f32; 0] = [];
+        slice_silence::<f32>(&mut empty);
+    }
+
+    #[test]
+    fn test_formats_copy_pcm_frames_copies_whole_frames() {
+        let src: Vec<i16> = (0..12).collect();
+        let mut dst = [-1i16; 10];
+        copy_pcm_frames::<i16>(&mut dst, &src, 3, 2).unwrap();
+        assert_eq!(&dst[..6], &src[..6]);
+        assert!(dst[6..].iter().all(|&s| s == -1));
+
+        // S24Packed frames are 3 bytes per sample
+        let src = [1u8, 2, 3, 4, 5, 6];
+        let mut dst = [0u8; 6];
+        copy_pcm_frames::<crate::pcm_frames::S24Packed>(&mut dst, &src, 1, 2).unwrap();
+        assert_eq!(dst, src);
+
+        copy_pcm_frames::<f32>(&mut [], &[], 0, 2).unwrap();
+    }
+
+    #[test]
+    fn test_formats_copy_pcm_frames_rejects_bad_shapes() {
+        let src = [0.0f32; 6];
+        let mut dst = [0.0f32; 4];
+
+        let err = copy_pcm_frames::<f32>(&mut dst, &src, 3, 2).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            Some(&ErrorKinds::BufferSizeMismatch {
+                context: "copy_pcm_frames: dst",
+                expected: 6,
+                actual: 4,
+            })
+        );
+
+        let err = copy_pcm_frames::<f32>(&mut [0.0f32; 8], &src, 4, 2).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            Some(&ErrorKinds::BufferSizeMismatch {
+                context: "copy_pcm_frames: src",
+                expected: 8,
+                actual: 6,
+            })
+        );
+
+        assert!(copy_pcm_frames::<f32>(&mut dst, &src, 1, 0).is_err());
+        assert!(copy_pcm_frames::<f32>(&mut dst, &src, usize::MAX, 2).is_err());
+        assert_eq!(dst, [0.0f32; 4]);
+    }
+
+    #[test]
+    fn test_formats_sample_buffer_silence() {
+        let storage = vec![1u8, 2, 3, 4];
+        let mut buf = SampleBuffer::<u8>::from_storage(storage, 2, 2).unwrap();
+        buf.silence();
+        assert_eq!(buf.as_ref(), &[128u8; 4]);
+        assert_eq!(buf.frames(), 2);
+        assert_eq!(buf.channels(), 2);
+    }
+
     #[test]
     fn test_formats_format_into_sys_matches_expected_constants() {
         assert_eq!(
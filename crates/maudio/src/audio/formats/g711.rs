@@ -0,0 +1,248 @@
+//! G.711 μ-law and A-law companding.
+//!
+//! Telephony audio (SIP/RTP, PSTN gateways) is usually sent as 8-bit companded samples at
+//! 8 kHz. These functions convert between those bytes and [`SampleBuffer`]s, for example to
+//! decode received packets before pushing them to a
+//! [`PushSource`](crate::data_source::sources::push_source::PushSource).
+//!
+//! Decoding is exact. Encoding keeps the 13 (A-law) or 14 (μ-law) most significant bits of
+//! the magnitude, as specified by G.711, so a round trip is lossy.
+//!
+//! ```
+//! # use maudio::audio::formats::g711::{self, G711Law};
+//! # fn main() -> maudio::MaResult<()> {
+//! // One packet of a mono μ-law stream
+//! let packet = [0xFFu8, 0x80, 0x00, 0x6A];
+//! let samples = g711::decode_f32(G711Law::MuLaw, &packet, 1)?;
+//! assert_eq!(samples.frames(), 4);
+//!
+//! assert_eq!(g711::encode_f32(G711Law::MuLaw, &samples), packet);
+//! # Ok(())
+//! # }
+//! ```
+use maudio_sys::ffi as sys;
+
+use crate::{audio::formats::SampleBuffer, MaResult, MaudioError};
+
+/// The companding law of a G.711 stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum G711Law {
+    /// μ-law, used in North America and Japan (PCMU).
+    MuLaw,
+    /// A-law, used in Europe and most other regions (PCMA).
+    ALaw,
+}
+
+impl G711Law {
+    /// Decodes one companded sample.
+    pub fn decode(self, sample: u8) -> i16 {
+        match self {
+            G711Law::MuLaw => ulaw_to_i16(sample),
+            G711Law::ALaw => alaw_to_i16(sample),
+        }
+    }
+
+    /// Encodes one sample.
+    pub fn encode(self, sample: i16) -> u8 {
+        match self {
+            G711Law::MuLaw => i16_to_ulaw(sample),
+            G711Law::ALaw => i16_to_alaw(sample),
+        }
+    }
+}
+
+const ULAW_BIAS: i32 = 0x84;
+const ULAW_CLIP: i32 = 32635;
+
+/// Decodes a μ-law sample.
+pub fn ulaw_to_i16(sample: u8) -> i16 {
+    let sample = !sample;
+    let exponent = (sample >> 4) & 0x07;
+    let mantissa = (sample & 0x0F) as i32;
+    let magnitude = (((mantissa << 3) + ULAW_BIAS) << exponent) - ULAW_BIAS;
+    if sample & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+/// Encodes a sample with μ-law.
+pub fn i16_to_ulaw(sample: i16) -> u8 {
+    let sample = sample as i32;
+    let sign = if sample < 0 { 0x80 } else { 0x00 };
+    let magnitude = sample.abs().min(ULAW_CLIP) + ULAW_BIAS;
+    // Position of the highest set bit above the 7 low bits
+    let exponent = 7 - ((magnitude >> 7) as u8).leading_zeros() as u8;
+    let mantissa = ((magnitude >> (exponent + 3)) & 0x0F) as u8;
+    !(sign | (exponent << 4) | mantissa)
+}
+
+/// Decodes an A-law sample.
+pub fn alaw_to_i16(sample: u8) -> i16 {
+    let sample = sample ^ 0x55;
+    let segment = (sample & 0x70) >> 4;
+    let mut magnitude = ((sample & 0x0F) as i16) << 4;
+    match segment {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        _ => magnitude = (magnitude + 0x108) << (segment - 1),
+    }
+    if sample & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// Encodes a sample with A-law.
+pub fn i16_to_alaw(sample: i16) -> u8 {
+    // A-law works on 13 bit samples
+    let sample = (sample >> 3) as i32;
+    let (mask, magnitude) = if sample >= 0 {
+        (0xD5, sample)
+    } else {
+        (0x55, -sample - 1)
+    };
+    // Segments end at 0x1F, 0x3F, ..., 0xFFF
+    let segment = (32 - (magnitude >> 5).leading_zeros()).min(8) as u8;
+    if segment == 8 {
+        return 0x7F ^ mask;
+    }
+    let shift = if segment < 2 { 1 } else { segment };
+    let mantissa = ((magnitude >> shift) & 0x0F) as u8;
+    ((segment << 4) | mantissa) ^ mask
+}
+
+fn check_channels(channels: u32) -> MaResult<()> {
+    if channels == 0 {
+        return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+    }
+    Ok(())
+}
+
+/// Decodes interleaved companded samples to 16-bit PCM.
+///
+/// A trailing incomplete frame is ignored. Returns `MA_INVALID_ARGS` if `channels` is zero.
+pub fn decode_i16(law: G711Law, bytes: &[u8], channels: u32) -> MaResult<SampleBuffer<i16>> {
+    check_channels(channels)?;
+    let frames = bytes.len() / channels as usize;
+    let samples = bytes[..frames * channels as usize]
+        .iter()
+        .map(|b| law.decode(*b))
+        .collect();
+    SampleBuffer::from_storage(samples, frames, channels)
+}
+
+/// Decodes interleaved companded samples to `f32` PCM in `[-1.0, 1.0]`.
+///
+/// A trailing incomplete frame is ignored. Returns `MA_INVALID_ARGS` if `channels` is zero.
+pub fn decode_f32(law: G711Law, bytes: &[u8], channels: u32) -> MaResult<SampleBuffer<f32>> {
+    check_channels(channels)?;
+    let frames = bytes.len() / channels as usize;
+    let samples = bytes[..frames * channels as usize]
+        .iter()
+        .map(|b| law.decode(*b) as f32 / 32768.0)
+        .collect();
+    SampleBuffer::from_storage(samples, frames, channels)
+}
+
+/// Encodes 16-bit PCM to interleaved companded samples.
+pub fn encode_i16(law: G711Law, samples: &SampleBuffer<i16>) -> Vec<u8> {
+    encode_slice_i16(law, samples.as_ref())
+}
+
+/// Encodes `f32` PCM to interleaved companded samples. Samples outside `[-1.0, 1.0]` are
+/// clipped.
+pub fn encode_f32(law: G711Law, samples: &SampleBuffer<f32>) -> Vec<u8> {
+    encode_slice_f32(law, samples.as_ref())
+}
+
+/// Encodes a slice of 16-bit samples, like [`encode_i16`].
+pub fn encode_slice_i16(law: G711Law, samples: &[i16]) -> Vec<u8> {
+    samples.iter().map(|s| law.encode(*s)).collect()
+}
+
+/// Encodes a slice of `f32` samples, like [`encode_f32`].
+pub fn encode_slice_f32(law: G711Law, samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .map(|s| law.encode((s.clamp(-1.0, 1.0) * 32767.0).round() as i16))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::formats::g711::{self, G711Law};
+
+    #[test]
+    fn test_g711_known_values() {
+        // Silence, and the largest magnitudes
+        assert_eq!(g711::ulaw_to_i16(0xFF), 0);
+        assert_eq!(g711::ulaw_to_i16(0x7F), 0);
+        assert_eq!(g711::ulaw_to_i16(0x80), 32124);
+        assert_eq!(g711::ulaw_to_i16(0x00), -32124);
+        assert_eq!(g711::i16_to_ulaw(0), 0xFF);
+        assert_eq!(g711::i16_to_ulaw(i16::MAX), 0x80);
+        assert_eq!(g711::i16_to_ulaw(i16::MIN), 0x00);
+
+        assert_eq!(g711::alaw_to_i16(0xD5), 8);
+        assert_eq!(g711::alaw_to_i16(0x55), -8);
+        assert_eq!(g711::alaw_to_i16(0xAA), 32256);
+        assert_eq!(g711::alaw_to_i16(0x2A), -32256);
+        assert_eq!(g711::i16_to_alaw(0), 0xD5);
+        assert_eq!(g711::i16_to_alaw(i16::MAX), 0xAA);
+        assert_eq!(g711::i16_to_alaw(i16::MIN), 0x2A);
+    }
+
+    #[test]
+    fn test_g711_decode_encode_round_trip() {
+        // Every companded value decodes to a sample that encodes back to it, except μ-law's
+        // negative zero
+        for law in [G711Law::MuLaw, G711Law::ALaw] {
+            for byte in 0..=255u8 {
+                let decoded = law.decode(byte);
+                let expected = if law == G711Law::MuLaw && byte == 0x7F {
+                    0xFF
+                } else {
+                    byte
+                };
+                assert_eq!(law.encode(decoded), expected, "{law:?} {byte:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_g711_encoding_is_monotonic_and_close() {
+        for law in [G711Law::MuLaw, G711Law::ALaw] {
+            let mut previous = i16::MIN;
+            for sample in (i16::MIN..=i16::MAX).step_by(7) {
+                let decoded = law.decode(law.encode(sample));
+                assert!(decoded >= previous);
+                // Step size of the largest segment
+                assert!((decoded as i32 - sample as i32).abs() <= 1024);
+                previous = decoded;
+            }
+        }
+    }
+
+    #[test]
+    fn test_g711_sample_buffers() {
+        let bytes = [0xFF, 0x80, 0x00, 0x7F, 0x12];
+        let pcm = g711::decode_i16(G711Law::MuLaw, &bytes, 2).unwrap();
+        assert_eq!(pcm.frames(), 2);
+        assert_eq!(pcm.channels(), 2);
+        assert_eq!(pcm.as_ref(), &[0, 32124, -32124, 0]);
+        assert_eq!(
+            g711::encode_i16(G711Law::MuLaw, &pcm),
+            [0xFF, 0x80, 0x00, 0xFF]
+        );
+
+        let pcm = g711::decode_f32(G711Law::ALaw, &[0xD5, 0xAA], 1).unwrap();
+        assert_eq!(pcm.as_ref(), &[8.0 / 32768.0, 32256.0 / 32768.0]);
+        let clipped = g711::encode_slice_f32(G711Law::ALaw, &[2.0, -2.0]);
+        assert_eq!(clipped, [0xAA, 0x2A]);
+
+        assert!(g711::decode_f32(G711Law::ALaw, &bytes, 0).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 //! Audio-related types and utilities
+pub mod buffer_pool;
 pub mod channels;
 pub mod converters;
 pub mod dsp;
@@ -1,4 +1,4 @@
-//! PanMode type definition.
+//! PanMode and PanLaw type definitions.
 use maudio_sys::ffi as sys;
 
 use crate::{ErrorKinds, MaudioError};
@@ -33,3 +33,82 @@ impl TryFrom<sys::ma_pan_mode> for PanMode {
         }
     }
 }
+
+/// How a pan position maps to the gains of the left and right channels of a stereo signal.
+///
+/// miniaudio pans with a fixed law, see [`PanMode`]. A `PanLaw` set with
+/// [`Sound::set_pan_law`](crate::sound::Sound::set_pan_law) replaces it.
+#[derive(Debug, Clone, Copy)]
+pub enum PanLaw {
+    /// Gains change linearly. Both channels are at half volume (-6 dB) in the center, so
+    /// centered sounds are quieter than sounds panned to one side.
+    Linear,
+    /// Keeps the summed power constant. Both channels are at -3 dB in the center, so the
+    /// loudness does not dip as a sound moves across the stereo field.
+    ConstantPower,
+    /// User function mapping a pan in `-1.0..=1.0` to the `(left, right)` gains.
+    ///
+    /// Evaluated when the pan changes, on the thread changing it.
+    Custom(fn(f32) -> (f32, f32)),
+}
+
+impl PanLaw {
+    /// Returns the `(left, right)` gains at `pan`, clamped to `-1.0..=1.0`.
+    ///
+    /// Gains that are negative or not finite are returned as `0.0`.
+    pub fn gains(&self, pan: f32) -> (f32, f32) {
+        let pan = if pan.is_nan() {
+            0.0
+        } else {
+            pan.clamp(-1.0, 1.0)
+        };
+        // Position from 0.0 (left) to 1.0 (right)
+        let t = (pan + 1.0) / 2.0;
+        let (left, right) = match self {
+            PanLaw::Linear => (1.0 - t, t),
+            PanLaw::ConstantPower => {
+                let angle = t * std::f32::consts::FRAC_PI_2;
+                (angle.cos(), angle.sin())
+            }
+            PanLaw::Custom(f) => f(pan),
+        };
+        (sanitize(left), sanitize(right))
+    }
+}
+
+fn sanitize(gain: f32) -> f32 {
+    if gain.is_finite() {
+        gain.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audio::pan::PanLaw;
+
+    fn assert_gains(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-5 && (actual.1 - expected.1).abs() < 1e-5,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn test_pan_law_gains() {
+        assert_gains(PanLaw::Linear.gains(0.0), (0.5, 0.5));
+        assert_gains(PanLaw::Linear.gains(-1.0), (1.0, 0.0));
+        assert_gains(PanLaw::Linear.gains(2.0), (0.0, 1.0));
+
+        let center = std::f32::consts::FRAC_1_SQRT_2;
+        assert_gains(PanLaw::ConstantPower.gains(0.0), (center, center));
+        assert_gains(PanLaw::ConstantPower.gains(1.0), (0.0, 1.0));
+        let (left, right) = PanLaw::ConstantPower.gains(0.3);
+        assert!((left * left + right * right - 1.0).abs() < 1e-5);
+
+        let law = PanLaw::Custom(|pan| (f32::NAN, -pan));
+        assert_gains(law.gains(0.5), (0.0, 0.0));
+        assert_gains(law.gains(-0.5), (0.0, 0.5));
+    }
+}
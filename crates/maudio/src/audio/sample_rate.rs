@@ -1,6 +1,8 @@
 //! Sample rate definitions and conversion utilities.
 
-use crate::{ErrorKinds, MaudioError};
+use maudio_sys::ffi as sys;
+
+use crate::{ErrorKinds, MaResult, MaudioError};
 
 /// Common standard audio sample rates.
 ///
@@ -16,7 +18,12 @@ use crate::{ErrorKinds, MaudioError};
 /// - `48_000 Hz` and `44_100 Hz` are the most commonly used rates.
 /// - Lower and higher rates are included for compatibility with legacy,
 ///   low-power, or high-resolution audio pipelines.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// - Other rates, used by some hardware and network streams, are represented by
+///   [`SampleRate::Custom`]. Use [`SampleRate::custom`] to check them against the range
+///   miniaudio supports.
+///
+/// Rates compare by their value, so `SampleRate::Custom(48_000) == SampleRate::Sr48000`.
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub enum SampleRate {
     Sr48000,
@@ -36,6 +43,51 @@ pub enum SampleRate {
     Custom(u32),
 }
 
+impl SampleRate {
+    /// Lowest rate miniaudio supports, in Hz.
+    pub const MIN_HZ: u32 = sys::ma_standard_sample_rate_ma_standard_sample_rate_min;
+    /// Highest rate miniaudio supports, in Hz.
+    pub const MAX_HZ: u32 = sys::ma_standard_sample_rate_ma_standard_sample_rate_max;
+
+    /// Returns the sample rate for `hz`.
+    ///
+    /// Standard rates return their own variant, other rates [`SampleRate::Custom`].
+    /// Returns an error if `hz` is outside [`SampleRate::MIN_HZ`]..=[`SampleRate::MAX_HZ`].
+    pub fn custom(hz: u32) -> MaResult<Self> {
+        if !(Self::MIN_HZ..=Self::MAX_HZ).contains(&hz) {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        Self::try_from(hz)
+    }
+
+    /// The rate in Hz.
+    pub fn hz(self) -> u32 {
+        self.into()
+    }
+
+    /// Returns `true` for the rates with their own variant.
+    pub fn is_standard(self) -> bool {
+        !matches!(
+            Self::try_from(self.hz()),
+            Ok(SampleRate::Custom(_)) | Err(_)
+        )
+    }
+}
+
+impl PartialEq for SampleRate {
+    fn eq(&self, other: &Self) -> bool {
+        self.hz() == other.hz()
+    }
+}
+
+impl Eq for SampleRate {}
+
+impl core::hash::Hash for SampleRate {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.hz().hash(state);
+    }
+}
+
 impl From<SampleRate> for i32 {
     fn from(value: SampleRate) -> Self {
         match value {
@@ -147,7 +199,6 @@ mod tests {
     use crate::MaError;
 
     use super::*;
-    use maudio_sys::ffi as sys;
 
     fn ma_error() -> MaError {
         MaError(sys::ma_result_MA_ERROR)
@@ -335,10 +386,45 @@ mod tests {
 
             // u32 roundtrip
             let rust_u32 = SampleRate::try_from(v).unwrap();
-            assert_eq!(v, rust_u32.into());
+            let back_u32: u32 = rust_u32.into();
+            assert_eq!(back_u32, v);
         }
     }
 
+    #[test]
+    fn test_sample_rate_custom_is_validated() {
+        assert_eq!(
+            SampleRate::custom(12_345).unwrap(),
+            SampleRate::Custom(12_345)
+        );
+        assert!(matches!(
+            SampleRate::custom(48_000).unwrap(),
+            SampleRate::Sr48000
+        ));
+        assert!(SampleRate::custom(SampleRate::MIN_HZ).is_ok());
+        assert!(SampleRate::custom(SampleRate::MAX_HZ).is_ok());
+        assert!(SampleRate::custom(0).is_err());
+        assert!(SampleRate::custom(SampleRate::MIN_HZ - 1).is_err());
+        assert!(SampleRate::custom(SampleRate::MAX_HZ + 1).is_err());
+    }
+
+    #[test]
+    fn test_sample_rate_custom_compares_by_value() {
+        use std::collections::HashSet;
+
+        assert_eq!(SampleRate::Custom(44_100), SampleRate::Sr44100);
+        assert_ne!(SampleRate::Custom(44_101), SampleRate::Sr44100);
+        assert_eq!(SampleRate::Custom(12_345).hz(), 12_345);
+        assert!(SampleRate::Sr8000.is_standard());
+        assert!(SampleRate::Custom(8_000).is_standard());
+        assert!(!SampleRate::Custom(12_345).is_standard());
+
+        let rates: HashSet<SampleRate> = [SampleRate::Sr96000, SampleRate::Custom(96_000)]
+            .into_iter()
+            .collect();
+        assert_eq!(rates.len(), 1);
+    }
+
     #[test]
     fn test_sample_rate_min_max_are_aliases() {
         // These are intentionally aliases in miniaudio.
@@ -0,0 +1,260 @@
+//! Integration with the [bevy](https://bevyengine.org) game engine.
+//!
+//! Enabled with the `bevy` feature. [`MaudioPlugin`] makes an [`Engine`] available to systems
+//! as the [`AudioEngine`] resource, and keeps the sounds and listeners in the world in sync
+//! with the transforms of their entities:
+//!
+//! - [`AudioSource`] owns a [`Sound`]. The sound is dropped, and stops, with the entity.
+//! - [`SpatialEmitter`] places the sound of the entity at its [`GlobalTransform`], facing
+//!   the same way.
+//! - [`AudioListener`] places one of the engine's listeners at its [`GlobalTransform`].
+//!
+//! The transforms are copied in [`PostUpdate`], after bevy has propagated them. Bevy uses the
+//! same right-handed, Y-up, -Z forward coordinate system as miniaudio, so no conversion is
+//! needed.
+//!
+//! ```no_run
+//! # use bevy_app::App;
+//! # use bevy_ecs::prelude::*;
+//! # use bevy_transform::components::Transform;
+//! # use maudio::bevy::{AudioEngine, AudioListener, AudioSource, MaudioPlugin, SpatialEmitter};
+//! # use maudio::engine::Engine;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! App::new()
+//!     .add_plugins(MaudioPlugin::new(Engine::new()?))
+//!     .add_systems(bevy_app::Startup, setup)
+//!     .run();
+//! # Ok(())
+//! # }
+//!
+//! fn setup(mut commands: Commands, engine: Res<AudioEngine>) {
+//!     commands.spawn((Transform::default(), AudioListener::default()));
+//!
+//!     let mut sound = engine.new_sound_from_file(Path::new("engine_hum.ogg")).unwrap();
+//!     sound.play_sound().unwrap();
+//!     commands.spawn((
+//!         Transform::from_xyz(4.0, 0.0, -2.0),
+//!         AudioSource::new(sound),
+//!         SpatialEmitter,
+//!     ));
+//! }
+//! ```
+use std::{
+    ops::Deref,
+    sync::{Mutex, MutexGuard},
+};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::{
+    change_detection::DetectChangesMut,
+    component::Component,
+    query::{Added, Changed, Or, With},
+    resource::Resource,
+    schedule::IntoScheduleConfigs,
+    system::{Query, Res},
+};
+use bevy_transform::{components::GlobalTransform, TransformSystems};
+
+use crate::{audio::math::vec3::Vec3, engine::Engine, sound::Sound};
+
+/// Adds the [`AudioEngine`] resource and the systems syncing transforms to maudio.
+pub struct MaudioPlugin {
+    // Moved into the world when the plugin is built
+    engine: Mutex<Option<Engine>>,
+}
+
+impl MaudioPlugin {
+    /// Creates a plugin that makes `engine` the [`AudioEngine`] of the app.
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine: Mutex::new(Some(engine)),
+        }
+    }
+}
+
+impl Plugin for MaudioPlugin {
+    fn build(&self, app: &mut App) {
+        let engine = self.engine.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(engine) = engine {
+            app.insert_resource(AudioEngine(engine));
+        }
+        app.add_systems(
+            PostUpdate,
+            (sync_listeners, sync_emitters).after(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// The engine used by the app, added by [`MaudioPlugin`].
+#[derive(Resource)]
+pub struct AudioEngine(pub Engine);
+
+impl Deref for AudioEngine {
+    type Target = Engine;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A [`Sound`] owned by an entity.
+///
+/// The sound is dropped when the component is removed or the entity despawned. Add a
+/// [`SpatialEmitter`] to place it at the entity's transform.
+#[derive(Component)]
+pub struct AudioSource {
+    // Sound is not Sync, components must be
+    sound: Mutex<Sound>,
+}
+
+impl AudioSource {
+    pub fn new(sound: Sound) -> Self {
+        Self {
+            sound: Mutex::new(sound),
+        }
+    }
+
+    /// Returns the sound, from a system with mutable access to the component.
+    pub fn sound_mut(&mut self) -> &mut Sound {
+        self.sound.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the sound, from a system with shared access to the component.
+    pub fn lock(&self) -> MutexGuard<'_, Sound> {
+        self.sound.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn into_inner(self) -> Sound {
+        self.sound.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl From<Sound> for AudioSource {
+    fn from(sound: Sound) -> Self {
+        Self::new(sound)
+    }
+}
+
+/// Places the [`AudioSource`] of the entity at its [`GlobalTransform`], facing the same way.
+///
+/// The sound is only moved. Whether it is spatialized and how it is attenuated are set on
+/// the [`Sound`].
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct SpatialEmitter;
+
+/// Places one of the engine's listeners at the entity's [`GlobalTransform`].
+///
+/// The default uses listener `0`. Indices the engine does not have are ignored.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AudioListener {
+    index: u32,
+}
+
+impl AudioListener {
+    pub fn new(index: u32) -> Self {
+        Self { index }
+    }
+
+    /// Returns the index of the engine listener driven by this entity.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+// Listeners that moved, or were added
+type ListenerChanged = Or<(Changed<GlobalTransform>, Changed<AudioListener>)>;
+// Emitters that moved, or were added
+type EmitterChanged = (
+    With<SpatialEmitter>,
+    Or<(
+        Changed<GlobalTransform>,
+        Added<SpatialEmitter>,
+        Added<AudioSource>,
+    )>,
+);
+
+fn to_vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+/// Copies the transforms of [`AudioListener`] entities to the engine's listeners.
+///
+/// Added to [`PostUpdate`] by [`MaudioPlugin`].
+pub fn sync_listeners(
+    engine: Res<AudioEngine>,
+    listeners: Query<(&AudioListener, &GlobalTransform), ListenerChanged>,
+) {
+    for (listener, transform) in &listeners {
+        let Ok(listener) = engine.listener(listener.index) else {
+            continue;
+        };
+        listener.set_position(to_vec3(transform.translation().to_array()));
+        listener.set_direction(to_vec3(transform.forward().to_array()));
+        listener.set_world_up(to_vec3(transform.up().to_array()));
+    }
+}
+
+/// Copies the transforms of [`SpatialEmitter`] entities to their sounds.
+///
+/// Added to [`PostUpdate`] by [`MaudioPlugin`].
+pub fn sync_emitters(mut emitters: Query<(&mut AudioSource, &GlobalTransform), EmitterChanged>) {
+    for (mut source, transform) in &mut emitters {
+        // Would mark the source as changed for every other system
+        let sound = source.bypass_change_detection().sound_mut();
+        sound.set_position(to_vec3(transform.translation().to_array()));
+        sound.set_direction(to_vec3(transform.forward().to_array()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy_app::App;
+    use bevy_transform::components::{GlobalTransform, Transform};
+
+    use crate::{
+        audio::{math::vec3::Vec3, sample_rate::SampleRate},
+        bevy::{AudioEngine, AudioListener, AudioSource, MaudioPlugin, SpatialEmitter},
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::engine_builder::EngineBuilder,
+    };
+
+    #[test]
+    fn test_bevy_syncs_transforms() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 256]).unwrap();
+        let sound = engine.new_sound_from_source(&buffer).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(MaudioPlugin::new(engine));
+        app.world_mut().spawn((
+            GlobalTransform::from(Transform::from_xyz(0.0, 1.0, 2.0)),
+            AudioListener::default(),
+        ));
+        let emitter = app
+            .world_mut()
+            .spawn((
+                GlobalTransform::from(Transform::from_xyz(4.0, 0.0, -2.0)),
+                AudioSource::new(sound),
+                SpatialEmitter,
+            ))
+            .id();
+        app.update();
+
+        let engine = app.world().resource::<AudioEngine>();
+        let listener = engine.listener(0).unwrap();
+        assert_eq!(listener.position(), Vec3::new(0.0, 1.0, 2.0));
+        assert_eq!(listener.direction(), Vec3::new(0.0, 0.0, -1.0));
+
+        *app.world_mut().get_mut::<GlobalTransform>(emitter).unwrap() =
+            GlobalTransform::from(Transform::from_xyz(-1.0, 0.0, 0.0));
+        app.update();
+        let mut entity = app.world_mut().entity_mut(emitter);
+        let mut source = entity.get_mut::<AudioSource>().unwrap();
+        assert_eq!(source.sound_mut().position(), Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(source.sound_mut().direction(), Vec3::new(0.0, 0.0, -1.0));
+    }
+}
@@ -0,0 +1,394 @@
+//! C API for embedding maudio in non-Rust hosts.
+//!
+//! Enabled with the `capi` feature. The functions here are a small `extern "C"` facade over
+//! [`Engine`] and [`Sound`], for hosts written in C, C++ or C# that want the safe Rust layer
+//! instead of calling miniaudio directly. The types are cbindgen-friendly: engines and sounds
+//! are opaque pointers, and every fallible function returns a miniaudio result code
+//! (`MA_SUCCESS`, `MA_INVALID_ARGS`, ...), see [`MaResultCode`].
+//!
+//! Link it from a crate that depends on `maudio` with the `capi` feature and builds as a
+//! `staticlib` or `cdylib`, and generate the header with `cbindgen`.
+//!
+//! ```c
+//! MaudioEngine *engine = NULL;
+//! MaudioSound *sound = NULL;
+//! if (maudio_engine_new(&engine) != 0) { /* handle the error */ }
+//! if (maudio_sound_new_from_file(engine, "music.ogg", &sound) == 0) {
+//!     maudio_sound_set_volume(sound, 0.5f);
+//!     maudio_sound_play(sound);
+//! }
+//! /* ... */
+//! maudio_sound_free(sound);
+//! maudio_engine_free(engine);
+//! ```
+//!
+//! Sounds keep their engine alive, so engines and sounds can be freed in any order. A panic
+//! is never unwound into the host, the function returns `MA_ERROR` instead.
+use std::{
+    ffi::{c_char, c_int, CStr},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{math::vec3::Vec3, sample_rate::SampleRate},
+    engine::{engine_builder::EngineBuilder, Engine},
+    sound::Sound,
+    MaResult, MaResultCode,
+};
+
+/// Opaque handle to an [`Engine`].
+pub struct MaudioEngine {
+    engine: Engine,
+}
+
+/// Opaque handle to a [`Sound`].
+pub struct MaudioSound {
+    sound: Sound,
+}
+
+fn to_code(result: MaResult<()>) -> c_int {
+    match result {
+        Ok(()) => success(),
+        Err(e) => e.ma_result() as c_int,
+    }
+}
+
+fn success() -> c_int {
+    sys::ma_result_MA_SUCCESS as c_int
+}
+
+fn invalid_args() -> c_int {
+    MaResultCode::InvalidArgs.to_raw() as c_int
+}
+
+// Runs `f`, turning a panic into MA_ERROR
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(sys::ma_result_MA_ERROR as c_int)
+}
+
+// Boxes the engine into `out`
+unsafe fn new_engine(out_engine: *mut *mut MaudioEngine, engine: MaResult<Engine>) -> c_int {
+    match engine {
+        Ok(engine) => {
+            *out_engine = Box::into_raw(Box::new(MaudioEngine { engine }));
+            success()
+        }
+        Err(e) => to_code(Err(e)),
+    }
+}
+
+/// Creates an engine playing to the default device.
+///
+/// On success, `*out_engine` is set to an engine to free with [`maudio_engine_free`].
+///
+/// # Safety
+///
+/// `out_engine` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_engine_new(out_engine: *mut *mut MaudioEngine) -> c_int {
+    if out_engine.is_null() {
+        return invalid_args();
+    }
+    guard(|| new_engine(out_engine, Engine::new()))
+}
+
+/// Creates an engine without a device, for hosts that pull the output themselves.
+///
+/// `sample_rate` is in Hz and must not be `0`.
+///
+/// # Safety
+///
+/// `out_engine` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_engine_new_no_device(
+    channels: u32,
+    sample_rate: u32,
+    out_engine: *mut *mut MaudioEngine,
+) -> c_int {
+    if out_engine.is_null() || channels == 0 {
+        return invalid_args();
+    }
+    let sample_rate = match SampleRate::try_from(sample_rate) {
+        Ok(rate) => rate,
+        Err(_) => return invalid_args(),
+    };
+    guard(|| {
+        let engine = EngineBuilder::new()
+            .no_device(channels, sample_rate)
+            .build();
+        new_engine(out_engine, engine)
+    })
+}
+
+/// Frees an engine created by [`maudio_engine_new`] or [`maudio_engine_new_no_device`].
+///
+/// Does nothing if `engine` is null.
+///
+/// # Safety
+///
+/// `engine` must be null or an engine that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_engine_free(engine: *mut MaudioEngine) {
+    if !engine.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(engine))));
+    }
+}
+
+/// Sets the master volume of the engine.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_engine_set_volume(engine: *mut MaudioEngine, volume: f32) -> c_int {
+    match engine.as_ref() {
+        Some(engine) => guard(|| to_code(engine.engine.set_volume(volume))),
+        None => invalid_args(),
+    }
+}
+
+/// Loads a sound from a file.
+///
+/// `path` is a nul-terminated UTF-8 string. On success, `*out_sound` is set to a stopped
+/// sound to free with [`maudio_sound_free`].
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine, `path` null or a nul-terminated string, and
+/// `out_sound` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_new_from_file(
+    engine: *mut MaudioEngine,
+    path: *const c_char,
+    out_sound: *mut *mut MaudioSound,
+) -> c_int {
+    let engine = match engine.as_ref() {
+        Some(engine) => engine,
+        None => return invalid_args(),
+    };
+    if path.is_null() || out_sound.is_null() {
+        return invalid_args();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => Path::new(path),
+        Err(_) => return invalid_args(),
+    };
+    guard(|| match engine.engine.new_sound_from_file(path) {
+        Ok(sound) => {
+            *out_sound = Box::into_raw(Box::new(MaudioSound { sound }));
+            success()
+        }
+        Err(e) => to_code(Err(e)),
+    })
+}
+
+/// Frees a sound created by [`maudio_sound_new_from_file`], stopping it.
+///
+/// Does nothing if `sound` is null.
+///
+/// # Safety
+///
+/// `sound` must be null or a sound that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_free(sound: *mut MaudioSound) {
+    if !sound.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(sound))));
+    }
+}
+
+// Runs `f` on a live sound, or returns MA_INVALID_ARGS for null
+unsafe fn with_sound(sound: *mut MaudioSound, f: impl FnOnce(&mut Sound) -> c_int) -> c_int {
+    match sound.as_mut() {
+        Some(sound) => guard(|| f(&mut sound.sound)),
+        None => invalid_args(),
+    }
+}
+
+/// Starts playback.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_play(sound: *mut MaudioSound) -> c_int {
+    with_sound(sound, |s| to_code(s.play_sound()))
+}
+
+/// Stops playback.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_stop(sound: *mut MaudioSound) -> c_int {
+    with_sound(sound, |s| to_code(s.stop_sound()))
+}
+
+/// Sets the sound volume.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_set_volume(sound: *mut MaudioSound, volume: f32) -> c_int {
+    with_sound(sound, |s| {
+        s.set_volume(volume);
+        success()
+    })
+}
+
+/// Sets the pitch multiplier.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_set_pitch(sound: *mut MaudioSound, pitch: f32) -> c_int {
+    with_sound(sound, |s| {
+        s.set_pitch(pitch);
+        success()
+    })
+}
+
+/// Sets the pan, from `-1.0` (left) to `1.0` (right).
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_set_pan(sound: *mut MaudioSound, pan: f32) -> c_int {
+    with_sound(sound, |s| {
+        s.set_pan(pan);
+        success()
+    })
+}
+
+/// Enables or disables looping.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_set_looping(sound: *mut MaudioSound, looping: bool) -> c_int {
+    with_sound(sound, |s| {
+        s.set_looping(looping);
+        success()
+    })
+}
+
+/// Sets the position of the sound in world space.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_set_position(
+    sound: *mut MaudioSound,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> c_int {
+    with_sound(sound, |s| {
+        s.set_position(Vec3::new(x, y, z));
+        success()
+    })
+}
+
+/// Returns `true` if the sound is playing, and `false` if it is not or `sound` is null.
+///
+/// # Safety
+///
+/// `sound` must be null or a live sound.
+#[no_mangle]
+pub unsafe extern "C" fn maudio_sound_is_playing(sound: *const MaudioSound) -> bool {
+    match sound.as_ref() {
+        Some(sound) => catch_unwind(AssertUnwindSafe(|| sound.sound.is_playing())).unwrap_or(false),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{ffi::CString, ptr};
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        capi::*,
+        test_assets::{
+            temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le,
+        },
+        MaResultCode,
+    };
+
+    fn code(code: MaResultCode) -> c_int {
+        code.to_raw() as c_int
+    }
+
+    #[test]
+    fn test_capi_plays_sound_from_file() {
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(
+            guard.path(),
+            wav_i16_le(1, SampleRate::Sr48000, &[8000i16; 4800]),
+        )
+        .unwrap();
+        let path = CString::new(guard.path().to_str().unwrap()).unwrap();
+
+        unsafe {
+            let mut engine = ptr::null_mut();
+            assert_eq!(maudio_engine_new_no_device(1, 48000, &mut engine), 0);
+            let mut sound = ptr::null_mut();
+            assert_eq!(
+                maudio_sound_new_from_file(engine, path.as_ptr(), &mut sound),
+                0
+            );
+            assert!(!maudio_sound_is_playing(sound));
+            assert_eq!(maudio_sound_set_volume(sound, 0.5), 0);
+            assert_eq!(maudio_sound_play(sound), 0);
+            assert!(maudio_sound_is_playing(sound));
+
+            let mut reader = (*engine).engine.try_acquire_reader().unwrap();
+            let out = reader.read_pcm_frames(256).unwrap();
+            drop(reader);
+            assert!(out.as_ref().iter().any(|s| s.abs() > 0.05));
+
+            // Sounds keep the engine alive
+            maudio_engine_free(engine);
+            assert_eq!(maudio_sound_stop(sound), 0);
+            maudio_sound_free(sound);
+        }
+    }
+
+    #[test]
+    fn test_capi_reports_errors() {
+        let missing = CString::new("maudio_capi_missing.wav").unwrap();
+        unsafe {
+            let mut engine = ptr::null_mut();
+            assert_eq!(
+                maudio_engine_new_no_device(1, 0, &mut engine),
+                code(MaResultCode::InvalidArgs)
+            );
+            assert!(engine.is_null());
+            assert_eq!(maudio_engine_new_no_device(1, 48000, &mut engine), 0);
+
+            let mut sound = ptr::null_mut();
+            assert_eq!(
+                maudio_sound_new_from_file(engine, missing.as_ptr(), &mut sound),
+                code(MaResultCode::DoesNotExist)
+            );
+            assert!(sound.is_null());
+            assert_eq!(
+                maudio_sound_new_from_file(engine, ptr::null(), &mut sound),
+                code(MaResultCode::InvalidArgs)
+            );
+            assert_eq!(maudio_sound_play(sound), code(MaResultCode::InvalidArgs));
+            assert!(!maudio_sound_is_playing(sound));
+            maudio_sound_free(sound);
+            maudio_engine_free(engine);
+        }
+    }
+}
@@ -29,26 +29,25 @@
 //! [`Context`] is the owning handle to the backend context, while [`ContextRef`] is a borrowed
 //! view. Both implement [`ContextOps`].
 use core::slice;
-use std::{
-    marker::PhantomData,
-    mem::MaybeUninit,
-    panic::{catch_unwind, AssertUnwindSafe},
-    sync::Arc,
-};
+use std::{marker::PhantomData, mem::MaybeUninit, sync::Arc};
 
 use maudio_sys::ffi as sys;
 
 use crate::{
     backend::Backend,
+    context::coreaudio::{SessionCategory, SessionOptions},
     device::{
         device_id::DeviceId,
         device_info::{DeviceBasicInfo, DeviceInfo, Devices},
         device_type::DeviceType,
     },
     engine::AllocationCallbacks,
+    util::callback_panic,
     AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
 };
 
+pub mod coreaudio;
+
 /// An owning handle to a miniaudio context.
 ///
 /// A context is the entry point for backend-level audio operations such as:
@@ -266,6 +265,19 @@ pub trait ContextOps: AsContextPtr {
         context_ffi::ma_context_get_device_info(self, device_type, device_id)
     }
 
+    /// Looks up a device by a previously stored ID.
+    ///
+    /// Returns `None` if no device of `device_type` currently has this ID, for example
+    /// because it was unplugged. See [`DeviceId::to_bytes`] for storing an ID between runs.
+    fn find_device(
+        &self,
+        device_type: DeviceType,
+        device_id: &DeviceId,
+    ) -> MaResult<Option<DeviceInfo>> {
+        let devices = self.get_devices()?;
+        Ok(devices.find(device_type, device_id).copied())
+    }
+
     /// Returns whether the active backend configuration supports loopback devices.
     ///
     /// Loopback support is backend and platform specific.
@@ -537,6 +549,39 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets the category of the iOS audio session. Only used by CoreAudio on iOS.
+    ///
+    /// See [`coreaudio`] for how it affects mixing with other applications.
+    pub fn coreaudio_session_category(&mut self, category: SessionCategory) -> &mut Self {
+        self.inner.coreaudio.sessionCategory = category.into();
+        self
+    }
+
+    /// Sets the options of the iOS audio session. Removes any existing ones.
+    ///
+    /// Only used by CoreAudio on iOS, and ignored unless a session category other than
+    /// [`SessionCategory::Default`] and [`SessionCategory::None`] is set.
+    pub fn coreaudio_session_options(&mut self, options: SessionOptions) -> &mut Self {
+        self.inner.coreaudio.sessionCategoryOptions = options.bits();
+        self
+    }
+
+    /// Controls whether the iOS audio session is activated when the context is created.
+    ///
+    /// Enabled by default. Disable it if the application manages the session itself.
+    pub fn coreaudio_activate_session(&mut self, yes: bool) -> &mut Self {
+        self.inner.coreaudio.noAudioSessionActivate = (!yes) as u32;
+        self
+    }
+
+    /// Controls whether the iOS audio session is deactivated when the context is dropped.
+    ///
+    /// Enabled by default.
+    pub fn coreaudio_deactivate_session(&mut self, yes: bool) -> &mut Self {
+        self.inner.coreaudio.noAudioSessionDeactivate = (!yes) as u32;
+        self
+    }
+
     pub fn build(&self) -> MaResult<Context> {
         let ctx = Context::new_with_config(self)?;
         Ok(ctx)
@@ -594,13 +639,13 @@ where
         return sys::MA_FALSE;
     };
 
-    let decision = catch_unwind(AssertUnwindSafe(|| (state.f)(device_type, basic)));
+    let decision = callback_panic::guard(|| (state.f)(device_type, basic));
 
     match decision {
         Ok(EnumerateControl::Continue) => sys::MA_TRUE,
         Ok(EnumerateControl::Stop) => sys::MA_FALSE,
-        Err(_) => {
-            state.err = Some(MaudioError::from_ma_result(sys::ma_result_MA_ERROR));
+        Err(e) => {
+            state.err = Some(e);
             sys::MA_FALSE
         }
     }
@@ -655,7 +700,10 @@ impl TryFrom<sys::ma_thread_priority> for ThreadPriority {
 
 #[cfg(test)]
 mod test {
-    use crate::context::{ContextBuilder, ContextOps, EnumerateControl};
+    use crate::{
+        context::{ContextBuilder, ContextOps, EnumerateControl},
+        device::device_id::DeviceId,
+    };
 
     #[test]
     fn test_context_basic_init() {
@@ -663,6 +711,37 @@ mod test {
         drop(ctx);
     }
 
+    #[test]
+    fn test_context_builder_coreaudio_options() {
+        use crate::{
+            context::coreaudio::{SessionCategory, SessionOptions},
+            sys, AsRawRef,
+        };
+
+        let mut builder = ContextBuilder::new();
+        builder
+            .coreaudio_session_category(SessionCategory::Ambient)
+            .coreaudio_session_options(
+                SessionOptions::MIX_WITH_OTHERS | SessionOptions::DEFAULT_TO_SPEAKER,
+            )
+            .coreaudio_deactivate_session(false);
+
+        let raw = builder.as_raw();
+        assert_eq!(
+            raw.coreaudio.sessionCategory,
+            sys::ma_ios_session_category_ma_ios_session_category_ambient
+        );
+        let options = SessionOptions::from_bits(raw.coreaudio.sessionCategoryOptions);
+        assert!(options.contains(SessionOptions::MIX_WITH_OTHERS));
+        assert!(options.contains(SessionOptions::DEFAULT_TO_SPEAKER));
+        assert!(!options.contains(SessionOptions::DUCK_OTHERS));
+        assert_eq!(raw.coreaudio.noAudioSessionActivate, 0);
+        assert_eq!(raw.coreaudio.noAudioSessionDeactivate, 1);
+
+        let ctx = builder.build().unwrap();
+        drop(ctx);
+    }
+
     #[test]
     fn test_context_get_device_info_owned() {
         let ctx = ContextBuilder::new().build().unwrap();
@@ -707,6 +786,17 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_context_find_device_by_id() {
+        let ctx = ContextBuilder::new().build().unwrap();
+        let devices = ctx.get_devices().unwrap();
+        for (device_type, info) in devices.iter() {
+            let id = DeviceId::from_bytes(&info.device_id().to_bytes()).unwrap();
+            let found = ctx.find_device(device_type, &id).unwrap();
+            assert_eq!(found.map(|d| d.device_id()), Some(info.device_id()));
+        }
+    }
+
     #[test]
     fn text_context_send_to_thread() {
         let ctx = ContextBuilder::new().build().unwrap();
@@ -0,0 +1,147 @@
+//! CoreAudio audio session options, used on iOS.
+//!
+//! On iOS, the audio session decides how the application's audio mixes with other
+//! applications and how it reacts to interruptions. miniaudio configures the session when
+//! the context is created, from the options set on
+//! [`ContextBuilder`](crate::context::ContextBuilder). They are ignored by every other backend,
+//! including CoreAudio on macOS.
+//!
+//! ```no_run
+//! # use maudio::context::ContextBuilder;
+//! # use maudio::context::coreaudio::{SessionCategory, SessionOptions};
+//! # use maudio::engine::engine_builder::EngineBuilder;
+//! # fn main() -> maudio::MaResult<()> {
+//! // Play along with music from other applications
+//! let context = ContextBuilder::new()
+//!     .coreaudio_session_category(SessionCategory::Ambient)
+//!     .coreaudio_session_options(SessionOptions::MIX_WITH_OTHERS)
+//!     .build()?;
+//!
+//! let engine = EngineBuilder::new().context(&context).build()?;
+//! # drop(engine);
+//! # Ok(())
+//! # }
+//! ```
+use maudio_sys::ffi as sys;
+
+/// Category of the iOS audio session (`AVAudioSessionCategory*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionCategory {
+    /// Uses miniaudio's default, which is [`SessionCategory::Playback`] with
+    /// [`SessionOptions::DEFAULT_TO_SPEAKER`] for playback devices.
+    #[default]
+    Default,
+    /// Leaves the session category unchanged.
+    None,
+    /// Audio mixes with other applications and is silenced by the ring/silent switch.
+    Ambient,
+    /// Audio interrupts other applications and is silenced by the ring/silent switch.
+    SoloAmbient,
+    /// Audio keeps playing when the ring/silent switch is set to silent.
+    Playback,
+    Record,
+    PlayAndRecord,
+    MultiRoute,
+}
+
+impl From<SessionCategory> for sys::ma_ios_session_category {
+    fn from(value: SessionCategory) -> Self {
+        match value {
+            SessionCategory::Default => {
+                sys::ma_ios_session_category_ma_ios_session_category_default
+            }
+            SessionCategory::None => sys::ma_ios_session_category_ma_ios_session_category_none,
+            SessionCategory::Ambient => {
+                sys::ma_ios_session_category_ma_ios_session_category_ambient
+            }
+            SessionCategory::SoloAmbient => {
+                sys::ma_ios_session_category_ma_ios_session_category_solo_ambient
+            }
+            SessionCategory::Playback => {
+                sys::ma_ios_session_category_ma_ios_session_category_playback
+            }
+            SessionCategory::Record => sys::ma_ios_session_category_ma_ios_session_category_record,
+            SessionCategory::PlayAndRecord => {
+                sys::ma_ios_session_category_ma_ios_session_category_play_and_record
+            }
+            SessionCategory::MultiRoute => {
+                sys::ma_ios_session_category_ma_ios_session_category_multi_route
+            }
+        }
+    }
+}
+
+type SessionOptionsRaw = sys::ma_ios_session_category_option;
+
+/// Options of the iOS audio session (`AVAudioSessionCategoryOptions`).
+#[repr(transparent)]
+#[derive(Debug, PartialEq, Clone, Copy, Hash, Eq)]
+pub struct SessionOptions(SessionOptionsRaw);
+
+impl SessionOptions {
+    pub const NONE: Self = Self(0);
+    /// Mix with audio from other applications instead of interrupting it.
+    pub const MIX_WITH_OTHERS: Self =
+        Self(sys::ma_ios_session_category_option_ma_ios_session_category_option_mix_with_others);
+    /// Lower the volume of other applications while this session is active.
+    pub const DUCK_OTHERS: Self =
+        Self(sys::ma_ios_session_category_option_ma_ios_session_category_option_duck_others);
+    pub const ALLOW_BLUETOOTH: Self =
+        Self(sys::ma_ios_session_category_option_ma_ios_session_category_option_allow_bluetooth);
+    /// Route audio to the speaker instead of the receiver when no other output is connected.
+    pub const DEFAULT_TO_SPEAKER: Self =
+        Self(sys::ma_ios_session_category_option_ma_ios_session_category_option_default_to_speaker);
+    /// Interrupt spoken audio from other applications, and mix with the rest.
+    pub const INTERRUPT_SPOKEN_AUDIO_AND_MIX_WITH_OTHERS: Self = Self(
+        sys::ma_ios_session_category_option_ma_ios_session_category_option_interrupt_spoken_audio_and_mix_with_others,
+    );
+    pub const ALLOW_BLUETOOTH_A2DP: Self = Self(
+        sys::ma_ios_session_category_option_ma_ios_session_category_option_allow_bluetooth_a2dp,
+    );
+    pub const ALLOW_AIR_PLAY: Self =
+        Self(sys::ma_ios_session_category_option_ma_ios_session_category_option_allow_air_play);
+
+    #[inline]
+    #[allow(clippy::useless_conversion)]
+    #[allow(clippy::unnecessary_cast)]
+    pub fn bits(self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Create SessionOptions from a u32 bitmask
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits as SessionOptionsRaw)
+    }
+
+    /// Check if all the bits in other are set
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    #[inline]
+    pub const fn insert(&mut self, other: Self) {
+        self.0 |= other.0
+    }
+
+    #[inline]
+    pub const fn remove(&mut self, other: Self) {
+        self.0 &= !other.0
+    }
+}
+
+impl core::ops::BitOr for SessionOptions {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for SessionOptions {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
@@ -8,7 +8,7 @@ use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{
-        channels::Channel,
+        channels::ChannelLayout,
         formats::{Format, SampleBuffer},
         sample_rate::SampleRate,
     },
@@ -36,8 +36,8 @@ pub struct DataFormat {
     pub channels: u32,
     /// Sample rate in Hz.
     pub sample_rate: SampleRate,
-    /// Channel order/map for each channel, length == channels (when available).
-    pub channel_map: Option<Vec<Channel>>,
+    /// Position of each channel, `channel_map.channels() == channels` (when available).
+    pub channel_map: Option<ChannelLayout>,
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +51,9 @@ pub struct DataSource<F: PcmFormat, P: PcmSource<F>> {
     inner: *mut DataSourceInner<F, P>,
 }
 
+// The handle owns its allocation and is not Sync. Moving it only moves the PcmSource with it.
+unsafe impl<F: PcmFormat, P: PcmSource<F> + Send> Send for DataSource<F, P> {}
+
 #[repr(C)]
 struct DataSourceInner<F: PcmFormat, P: PcmSource<F>> {
     inner: sys::ma_data_source_base,
@@ -126,6 +129,14 @@ impl<F: PcmFormat, P: PcmSource<F>> DataSource<F, P> {
         data_source_ffi::ma_data_source_get_range_in_pcm_frames(self)
     }
 
+    /// Limits reading to the frames in `begin..end`.
+    ///
+    /// Frame positions (cursor, length, seeking and loop points) become relative to `begin`.
+    /// Use `u64::MAX` as `end` for no upper limit.
+    pub fn set_range_in_pcm_frames(&mut self, begin: u64, end: u64) -> MaResult<()> {
+        data_source_ffi::ma_data_source_set_range_in_pcm_frames(self, begin, end)
+    }
+
     pub fn set_loop_point_in_pcm_frames(&mut self, begin: u64, end: u64) -> MaResult<()> {
         data_source_ffi::ma_data_source_set_loop_point_in_pcm_frames(self, begin, end)
     }
@@ -171,10 +182,14 @@ pub(crate) mod private_data_source {
         data_source::{
             data_source_chain::ChainSource,
             sources::{
-                buffer::{AudioBuffer, AudioBufferBase},
+                buffer::{progressive::ProgressiveBuffer, AudioBuffer, AudioBufferBase},
+                constant::{Constant, Silence},
                 decoder::{custom_decoder::CustomDecoder, Decoder, DecoderOps},
+                jitter_buffer::JitterBuffer,
                 noise::Noise,
                 pulsewave::{PulseWave, PulseWaveOps},
+                push_source::PushSource,
+                sweep::Sweep,
                 waveform::{WaveForm, WaveFormOps},
             },
         },
@@ -208,6 +223,12 @@ pub(crate) mod private_data_source {
     pub struct ResourceManagerBufferProvider;
     pub struct ResourceManagerStreamProvider;
     pub struct ChainSourceProvider;
+    pub struct ProgressiveBufferProvider;
+    pub struct SweepProvider;
+    pub struct ConstantProvider;
+    pub struct SilenceProvider;
+    pub struct PushSourceProvider;
+    pub struct JitterBufferProvider;
 
     impl<F: PcmFormat, P: PcmSource<F>> DataSourcePtrProvider<DataSource<F, P>> for DataSourceProvider {
         #[inline]
@@ -313,6 +334,48 @@ pub(crate) mod private_data_source {
         }
     }
 
+    impl DataSourcePtrProvider<ProgressiveBuffer> for ProgressiveBufferProvider {
+        #[inline]
+        fn as_source_ptr(t: &ProgressiveBuffer) -> *mut sys::ma_data_source {
+            t.as_source_ref().to_raw()
+        }
+    }
+
+    impl DataSourcePtrProvider<Sweep> for SweepProvider {
+        #[inline]
+        fn as_source_ptr(t: &Sweep) -> *mut sys::ma_data_source {
+            t.as_source_ref().to_raw()
+        }
+    }
+
+    impl DataSourcePtrProvider<Constant> for ConstantProvider {
+        #[inline]
+        fn as_source_ptr(t: &Constant) -> *mut sys::ma_data_source {
+            t.as_source_ref().to_raw()
+        }
+    }
+
+    impl DataSourcePtrProvider<Silence> for SilenceProvider {
+        #[inline]
+        fn as_source_ptr(t: &Silence) -> *mut sys::ma_data_source {
+            t.as_source_ref().to_raw()
+        }
+    }
+
+    impl DataSourcePtrProvider<PushSource> for PushSourceProvider {
+        #[inline]
+        fn as_source_ptr(t: &PushSource) -> *mut sys::ma_data_source {
+            t.as_source_ref().to_raw()
+        }
+    }
+
+    impl DataSourcePtrProvider<JitterBuffer> for JitterBufferProvider {
+        #[inline]
+        fn as_source_ptr(t: &JitterBuffer) -> *mut sys::ma_data_source {
+            t.as_source_ref().to_raw()
+        }
+    }
+
     pub fn source_ptr<T: AsSourcePtr + ?Sized>(t: &T) -> *mut sys::ma_data_source {
         <T as AsSourcePtr>::__PtrProvider::as_source_ptr(t)
     }
@@ -430,6 +493,14 @@ pub trait DataSourceOps: AsSourcePtr + SharedSource {
         data_source_ffi::ma_data_source_get_range_in_pcm_frames(self)
     }
 
+    /// Limits reading to the frames in `begin..end`.
+    ///
+    /// Frame positions (cursor, length, seeking and loop points) become relative to `begin`.
+    /// Use `u64::MAX` as `end` for no upper limit.
+    fn set_range_in_pcm_frames(&mut self, begin: u64, end: u64) -> MaResult<()> {
+        data_source_ffi::ma_data_source_set_range_in_pcm_frames(self, begin, end)
+    }
+
     fn set_loop_point_in_pcm_frames(&mut self, begin: u64, end: u64) -> MaResult<()> {
         data_source_ffi::ma_data_source_set_loop_point_in_pcm_frames(self, begin, end)
     }
@@ -446,7 +517,7 @@ pub(crate) mod data_source_ffi {
     use maudio_sys::ffi as sys;
 
     use crate::{
-        audio::{channels::Channel, formats::SampleBuffer},
+        audio::{channels::ChannelLayout, formats::SampleBuffer},
         data_source::{
             data_source_builder::DataSourceBuilder, private_data_source, AsSourcePtr, DataFormat,
             DataSourceRef, GetNextCallback,
@@ -611,6 +682,22 @@ pub(crate) mod data_source_ffi {
     pub fn ma_data_source_get_data_format<S: AsSourcePtr + ?Sized>(
         source: &S,
     ) -> MaResult<DataFormat> {
+        let (format_raw, channels, sample_rate, channel_map) =
+            ma_data_source_get_data_format_raw(source)?;
+
+        Ok(DataFormat {
+            format: format_raw.try_into()?,
+            channels,
+            sample_rate: sample_rate.try_into()?,
+            channel_map: Some(channel_map),
+        })
+    }
+
+    /// Same as [`ma_data_source_get_data_format`], but keeps the raw format and sample rate.
+    /// Some sources, like `ma_audio_buffer`, report a sample rate of 0.
+    pub fn ma_data_source_get_data_format_raw<S: AsSourcePtr + ?Sized>(
+        source: &S,
+    ) -> MaResult<(sys::ma_format, u32, u32, ChannelLayout)> {
         let mut format_raw: sys::ma_format = sys::ma_format_ma_format_unknown;
         let mut channels: u32 = 0;
         let mut sample_rate: u32 = 0;
@@ -626,17 +713,8 @@ pub(crate) mod data_source_ffi {
             )
         };
         MaudioError::check(res)?;
-        // Could cast when passing the ptr to miniaudio, but copying should be fine here
-        let mut channel_map: Vec<Channel> =
-            channel_map_raw.into_iter().map(Channel::from_raw).collect();
-        channel_map.truncate(channels as usize);
-
-        Ok(DataFormat {
-            format: format_raw.try_into()?,
-            channels,
-            sample_rate: sample_rate.try_into()?,
-            channel_map: Some(channel_map),
-        })
+        let channel_map = ChannelLayout::from_raw_count(&channel_map_raw, channels)?;
+        Ok((format_raw, channels, sample_rate, channel_map))
     }
 
     #[inline]
@@ -719,18 +797,17 @@ pub(crate) mod data_source_ffi {
         res == 1
     }
 
-    // TODO
     #[inline]
-    #[allow(dead_code)]
     pub fn ma_data_source_set_range_in_pcm_frames<S: AsSourcePtr + ?Sized>(
-        source: &S,
-        range: core::ops::Range<u64>,
+        source: &mut S,
+        begin: u64,
+        end: u64,
     ) -> MaResult<()> {
         let res = unsafe {
             sys::ma_data_source_set_range_in_pcm_frames(
                 private_data_source::source_ptr(source),
-                range.start,
-                range.end,
+                begin,
+                end,
             )
         };
         MaudioError::check(res)
@@ -968,4 +1045,40 @@ mod test {
 
         assert_eq!(out.data.len(), 40);
     }
+
+    #[test]
+    fn test_custom_data_source_reads_advance_cursor_once() {
+        let data: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let mut ds = DataSourceBuilder::new(1, SampleRate::Sr44100)
+            .build_f32(data)
+            .unwrap();
+
+        let first = ds.read_pcm_frames(10).unwrap();
+        assert_eq!(ds.cursor_in_pcm_frames().unwrap(), 10);
+        let second = ds.read_pcm_frames(10).unwrap();
+        assert_eq!(ds.cursor_in_pcm_frames().unwrap(), 20);
+
+        assert_eq!(first.as_ref()[0], 0.0);
+        assert_eq!(second.as_ref()[0], 10.0);
+    }
+
+    #[test]
+    fn test_custom_data_source_range_and_loop_point() {
+        let data: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let mut ds = DataSourceBuilder::new(1, SampleRate::Sr44100)
+            .build_f32(data)
+            .unwrap();
+
+        ds.set_range_in_pcm_frames(20, 60).unwrap();
+        assert_eq!(ds.range_in_pcm_frames(), 20..60);
+        assert_eq!(ds.length_in_pcm_frames().unwrap(), 40);
+
+        // Loop points are relative to the range
+        ds.set_loop_point_in_pcm_frames(10, 20).unwrap();
+        ds.set_looping(true).unwrap();
+        let out = ds.read_pcm_frames(25).unwrap();
+        assert_eq!(out.as_ref()[0], 20.0);
+        assert_eq!(out.as_ref()[19], 39.0);
+        assert_eq!(out.as_ref()[20], 30.0);
+    }
 }
@@ -3,7 +3,7 @@ use std::{marker::PhantomData, mem::MaybeUninit};
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{channels::Channel, formats::Format, sample_rate::SampleRate},
+    audio::{channels::ChannelLayout, formats::Format, sample_rate::SampleRate},
     data_source::{
         data_source_ffi, data_source_vtable::data_source_vtable, pcm_source::PcmSource, DataFormat,
         DataSource, DataSourceInner, SourceContext,
@@ -16,7 +16,7 @@ pub struct DataSourceBuilder {
     pub(crate) inner: sys::ma_data_source_config,
     sample_rate: SampleRate,
     channels: u32,
-    channel_map: Option<Vec<Channel>>,
+    channel_map: Option<ChannelLayout>,
     pub(crate) no_looping: bool,
     pub(crate) no_length: bool,
     pub(crate) no_seek: bool,
@@ -46,7 +46,11 @@ impl DataSourceBuilder {
         }
     }
 
-    pub fn channel_map(&mut self, map: Vec<Channel>) -> &mut Self {
+    /// Sets the position of each channel reported by the data source.
+    ///
+    /// Also updates the channel count to match the map.
+    pub fn channel_map(&mut self, map: ChannelLayout) -> &mut Self {
+        self.channels = map.channels();
         self.channel_map = Some(map);
         self
     }
@@ -73,6 +73,27 @@
 //! The same data source may only be added to a chain once. If the same sound
 //! should appear multiple times, create a separate data source instance for
 //! each entry.
+//!
+//! # Intro and loop
+//!
+//! A looping chain goes back to the head after the final source by default.
+//! [`ChainSource::set_loop_start`] changes where it goes back to, so a chain can
+//! play one or more sources once and then loop the rest. The common case of a
+//! music track with an intro followed by a looping body is covered by
+//! [`ChainSource::intro_loop`].
+//!
+//! ```no_run
+//! # use maudio::data_source::{data_source_chain::ChainSource, data_source_builder::DataSourceBuilder};
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # fn main() -> maudio::MaResult<()> {
+//! # let intro = DataSourceBuilder::new(2, SampleRate::Sr44100).build_f32(vec![0.0f32; 200])?;
+//! # let body = DataSourceBuilder::new(2, SampleRate::Sr44100).build_f32(vec![0.0f32; 200])?;
+//! // intro -> body -> body -> body ...
+//! let chain = ChainSource::intro_loop(intro.as_source_ref(), body.as_source_ref())?;
+//! # let _ = chain;
+//! # Ok(())
+//! # }
+//! ```
 
 use crate::{
     data_source::{data_source_ffi, private_data_source, AsSourcePtr, DataSourceRef},
@@ -85,6 +106,8 @@ pub struct ChainSource<'a, F: PcmFormat> {
     tail: Vec<DataSourceRef<'a, F>>,
     unlinked: Vec<DataSourceRef<'a, F>>,
     looping: bool,
+    // Where a looping chain goes back to. `None` is the head.
+    loop_start: Option<DataSourceRef<'a, F>>,
 }
 
 impl<'a, F: PcmFormat> ChainSource<'a, F> {
@@ -97,9 +120,41 @@ impl<'a, F: PcmFormat> ChainSource<'a, F> {
             tail: Vec::new(),
             unlinked: Vec::new(),
             looping,
+            loop_start: None,
         }
     }
 
+    /// Creates a looping chain that plays `intro` once and then loops `body`.
+    ///
+    /// Returns `InvalidOperation` if `intro` and `body` are the same source.
+    pub fn intro_loop(intro: DataSourceRef<'a, F>, body: DataSourceRef<'a, F>) -> MaResult<Self> {
+        let mut chain = Self::new(intro, true);
+        chain.insert(body)?;
+        chain.set_loop_start(body)?;
+        Ok(chain)
+    }
+
+    /// Returns the source a looping chain goes back to after the final source.
+    pub fn loop_start(&self) -> DataSourceRef<'a, F> {
+        self.loop_start.unwrap_or(self.head)
+    }
+
+    /// Sets the source a looping chain goes back to after the final source.
+    ///
+    /// Sources before `src` are only played once. The default is the head.
+    /// Unlinking `src` or clearing the tail resets it to the head.
+    ///
+    /// Returns `InvalidOperation` if the source is not in the chain.
+    pub fn set_loop_start(&mut self, src: DataSourceRef<'a, F>) -> MaResult<()> {
+        if !self.exists(src) {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "Data source does not exist",
+            )));
+        }
+        self.loop_start = if self.head == src { None } else { Some(src) };
+        self.relink()
+    }
+
     /// Returns `true` if the chain loops back to the head after the final source.
     pub fn is_looping(&self) -> bool {
         self.looping
@@ -126,6 +181,7 @@ impl<'a, F: PcmFormat> ChainSource<'a, F> {
     /// Removes all sources after the head.
     pub fn clear_tail(&mut self) -> MaResult<()> {
         self.tail.clear();
+        self.loop_start = None;
         self.relink()
     }
 
@@ -161,6 +217,9 @@ impl<'a, F: PcmFormat> ChainSource<'a, F> {
         if let Some(index) = self.tail.iter().position(|c| c == &src) {
             let old = self.tail.remove(index);
             self.unlinked.push(old);
+            if self.loop_start == Some(old) {
+                self.loop_start = None;
+            }
 
             self.relink()?;
             Ok(true)
@@ -271,7 +330,7 @@ impl<'a, F: PcmFormat> ChainSource<'a, F> {
         }
 
         if self.looping {
-            self.set_next_at(prev, Some(self.head))?;
+            self.set_next_at(prev, Some(self.loop_start()))?;
         } else {
             self.set_next_at(prev, None)?;
         }
@@ -616,4 +675,73 @@ mod tests {
 
         assert_eq!(chain.get_next_at(src2.as_source_ref()), None);
     }
+
+    #[test]
+    fn test_data_chain_intro_loop_links_body_to_itself() {
+        let data1 = vec![0.0f32; 200];
+        let data2 = vec![1.0f32; 200];
+
+        let intro = buffer_source(&data1);
+        let body = buffer_source(&data2);
+
+        let chain = ChainSource::intro_loop(intro.as_source_ref(), body.as_source_ref()).unwrap();
+
+        assert!(chain.is_looping());
+        assert_eq!(chain.loop_start(), body.as_source_ref());
+        assert_eq!(chain.get_next(), Some(body.as_source_ref()));
+        assert_eq!(
+            chain.get_next_at(body.as_source_ref()),
+            Some(body.as_source_ref())
+        );
+    }
+
+    #[test]
+    fn test_data_chain_intro_loop_plays_intro_once() {
+        let data1 = vec![1.0f32; 200];
+        let data2 = vec![2.0f32; 100];
+
+        let intro = buffer_source(&data1);
+        let body = buffer_source(&data2);
+
+        let mut chain =
+            ChainSource::intro_loop(intro.as_source_ref(), body.as_source_ref()).unwrap();
+
+        // 100 intro frames, then the 50 frame body three times
+        let out =
+            data_source_ffi::ma_data_source_read_pcm_frames::<f32, _>(&mut chain, 250, 2).unwrap();
+        assert_eq!(out.frames(), 250);
+        assert!(out.as_ref()[..200].iter().all(|&s| s == 1.0));
+        assert!(out.as_ref()[200..].iter().all(|&s| s == 2.0));
+    }
+
+    #[test]
+    fn test_data_chain_set_loop_start_rejects_missing_and_resets_on_unlink() {
+        let data1 = vec![0.0f32; 200];
+        let data2 = vec![1.0f32; 200];
+        let data3 = vec![2.0f32; 200];
+
+        let src1 = buffer_source(&data1);
+        let src2 = buffer_source(&data2);
+        let src3 = buffer_source(&data3);
+
+        let mut chain = ChainSource::new(src1.as_source_ref(), true);
+        chain.insert(src2.as_source_ref()).unwrap();
+
+        assert!(chain.set_loop_start(src3.as_source_ref()).is_err());
+        assert_eq!(chain.loop_start(), src1.as_source_ref());
+
+        chain.insert(src3.as_source_ref()).unwrap();
+        chain.set_loop_start(src3.as_source_ref()).unwrap();
+        assert_eq!(
+            chain.get_next_at(src3.as_source_ref()),
+            Some(src3.as_source_ref())
+        );
+
+        chain.unlink(src3.as_source_ref()).unwrap();
+        assert_eq!(chain.loop_start(), src1.as_source_ref());
+        assert_eq!(
+            chain.get_next_at(src2.as_source_ref()),
+            Some(src1.as_source_ref())
+        );
+    }
 }
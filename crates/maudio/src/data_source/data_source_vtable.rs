@@ -1,8 +1,7 @@
-use std::panic::{catch_unwind, AssertUnwindSafe};
-
 use crate::{
     data_source::{data_source_builder::DataSourceBuilder, pcm_source::PcmSource, DataSourceInner},
     pcm_frames::PcmFormat,
+    util::callback_panic,
     ErrorKinds,
 };
 
@@ -67,16 +66,14 @@ unsafe extern "C" fn data_source_read_proc<F: PcmFormat, P: PcmSource<F>>(
     // PcmUnit and StorageUnit always the same layout, size, and alignment
     let out = core::slice::from_raw_parts_mut::<F::PcmUnit>(frames_out.cast(), slice_len);
 
-    let res = catch_unwind(AssertUnwindSafe(|| {
-        ds.source.fill_pcm_frames(out, &mut ds.context)
-    }));
+    let res = callback_panic::guard(|| ds.source.fill_pcm_frames(out, &mut ds.context));
     match res {
         Ok(Ok(frames)) => {
             if !frames_read.is_null() {
                 *frames_read = frames as u64;
             }
 
-            ds.context.cursor = ds.context.cursor.saturating_add(frames as u64);
+            // The cursor is advanced by the PcmSource, which also handles wrapping when looping
 
             if frames == 0 {
                 sys::ma_result_MA_AT_END
@@ -101,9 +98,7 @@ unsafe extern "C" fn data_source_seek_proc<F: PcmFormat, P: PcmSource<F>>(
 
     let ds = &mut *(data_source).cast::<DataSourceInner<F, P>>();
 
-    let res = catch_unwind(AssertUnwindSafe(|| {
-        ds.source.seek_to_pcm_frame(frame_index, &mut ds.context)
-    }));
+    let res = callback_panic::guard(|| ds.source.seek_to_pcm_frame(frame_index, &mut ds.context));
     match res {
         Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
         Ok(Err(e)) if e.kind() == Some(&ErrorKinds::NotImplemented) => {
@@ -174,9 +169,7 @@ unsafe extern "C" fn data_source_get_len_proc<F: PcmFormat, P: PcmSource<F>>(
 
     let ds = &mut *(data_source).cast::<DataSourceInner<F, P>>();
 
-    let res = catch_unwind(AssertUnwindSafe(|| {
-        ds.source.length_in_pcm_frames(&ds.context)
-    }));
+    let res = callback_panic::guard(|| ds.source.length_in_pcm_frames(&ds.context));
     let len = match res {
         Ok(Some(l)) => l,
         _ => 0,
@@ -195,9 +188,7 @@ unsafe extern "C" fn data_source_set_looping_proc<F: PcmFormat, P: PcmSource<F>>
 
     let ds = &mut *(data_source).cast::<DataSourceInner<F, P>>();
 
-    let res = catch_unwind(AssertUnwindSafe(|| {
-        ds.source.set_looping(is_looping == 1, &mut ds.context)
-    }));
+    let res = callback_panic::guard(|| ds.source.set_looping(is_looping == 1, &mut ds.context));
     match res {
         Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
         _ => sys::ma_result_MA_NOT_IMPLEMENTED,
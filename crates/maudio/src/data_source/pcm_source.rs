@@ -3,6 +3,10 @@ use std::sync::{Arc, Mutex};
 use crate::{data_source::SourceContext, pcm_frames::PcmFormat, ErrorKinds, MaResult, MaudioError};
 
 pub trait PcmSource<F: PcmFormat> {
+    /// Fills `out` with interleaved frames and returns the number of frames written.
+    ///
+    /// Implementations advance `ctx.cursor` by the frames they read, and wrap it when
+    /// `ctx.looping` is set. The data source does not move the cursor itself.
     fn fill_pcm_frames(
         &mut self,
         out: &mut [F::PcmUnit],
@@ -3,19 +3,35 @@
 //! An `AudioBuffer` stores decoded audio samples and can be read, seeked, or
 //! used as a [`DataSource`](crate::data_source::DataSource) by the engine.
 //!
-//! Use the builder helpers (`build_*` / `build_*_ref`) to construct a buffer from existing PCM data.
+//! Use the builder helpers (`build_*` / `build_*_ref`) to construct a buffer from existing PCM data,
+//! or [`AudioBufferBuilder::from_encoded_bytes`] to decode an encoded file into one.
 use std::{marker::PhantomData, mem::MaybeUninit, sync::Arc};
 
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::formats::{Format, SampleBuffer},
-    data_source::{private_data_source, AsSourcePtr, DataSourceRef},
+    audio::{
+        formats::{Format, SampleBuffer},
+        sample_rate::SampleRate,
+    },
+    data_source::{
+        private_data_source,
+        sources::{
+            buffer::progressive::{DecodeProgress, ProgressiveBuffer},
+            decoder::{Decoder, DecoderBuilder, DecoderOps},
+        },
+        AsSourcePtr, DataSourceRef,
+    },
     engine::AllocationCallbacks,
     pcm_frames::{PcmFormat, PcmFormatInternal, S24Packed, S24},
-    AsRawRef, Binding, MaResult,
+    AsRawRef, Binding, MaResult, MaResultCode,
 };
 
+pub mod progressive;
+
+// Frames decoded per read by `AudioBufferBuilder::from_encoded_bytes`.
+const DECODE_BLOCK_FRAMES: usize = 4096;
+
 /// Owned in-memory PCM audio buffer.
 ///
 /// This type owns the underlying buffer allocation
@@ -140,6 +156,14 @@ impl<F: PcmFormat> AudioBuffer<F> {
         buffer_ffi::ma_audio_buffer_get_available_frames(self)
     }
 
+    /// Copies the whole buffer into a [`SampleBuffer`] of format `T`, converting the samples
+    /// if the formats differ.
+    ///
+    /// The cursor is neither used nor moved.
+    pub fn to_sample_buffer<T: PcmFormat>(&self) -> MaResult<SampleBuffer<T>> {
+        buffer_ffi::ma_audio_buffer_to_sample_buffer(self)
+    }
+
     /// Returns a [`DataSourceRef`] view of this buffer.
     pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, F> {
         debug_assert!(!self.to_raw().is_null());
@@ -233,7 +257,7 @@ impl<F: PcmFormat> AudioBuffer<F> {
 
 pub(crate) mod buffer_ffi {
     use crate::{
-        audio::formats::{Format, SampleBuffer},
+        audio::formats::{Dither, Format, SampleBuffer},
         data_source::sources::buffer::{
             AudioBuffer, AudioBufferBase, AudioBufferBuilder, AudioBufferRef,
         },
@@ -390,6 +414,34 @@ pub(crate) mod buffer_ffi {
         MaudioError::check(res)
     }
 
+    pub fn ma_audio_buffer_to_sample_buffer<F: PcmFormat, T: PcmFormat>(
+        audio_buffer: &AudioBuffer<F>,
+    ) -> MaResult<SampleBuffer<T>> {
+        let (data, format, frames) = unsafe {
+            let buffer_ref = &(*audio_buffer.to_raw()).ref_;
+            (
+                buffer_ref.pData,
+                buffer_ref.format,
+                buffer_ref.sizeInFrames as usize,
+            )
+        };
+        let channels = audio_buffer.channels;
+        let mut storage = SampleBuffer::<T>::new_zeroed(frames, channels)?;
+        if frames > 0 && !data.is_null() {
+            unsafe {
+                sys::ma_pcm_convert(
+                    storage.as_mut_ptr() as *mut core::ffi::c_void,
+                    T::FORMAT.into(),
+                    data,
+                    format,
+                    (frames * channels as usize) as u64,
+                    Dither::None.into(),
+                )
+            };
+        }
+        SampleBuffer::from_storage(storage, frames, channels)
+    }
+
     #[inline]
     pub fn ma_audio_buffer_at_end<F: PcmFormat>(audio_buffer: &AudioBuffer<F>) -> bool {
         let res = unsafe { sys::ma_audio_buffer_at_end(audio_buffer.to_raw() as *const _) };
@@ -729,6 +781,70 @@ impl<'a> AudioBufferBuilder<'a> {
         })
     }
 
+    /// Decodes `decoder` on a background thread into a buffer that can be played before it
+    /// is fully decoded.
+    ///
+    /// `progress` is called from the worker thread after each decoded block. See
+    /// [`progressive`] for how playback behaves while decoding.
+    pub fn from_decoder_async<S, C>(
+        decoder: Decoder<f32, S>,
+        progress: C,
+    ) -> MaResult<ProgressiveBuffer>
+    where
+        S: Send + 'static,
+        C: FnMut(DecodeProgress) + Send + 'static,
+    {
+        ProgressiveBuffer::spawn(decoder, progress)
+    }
+
+    /// Decodes encoded audio, like a WAV, FLAC or MP3 file, into an `f32` buffer in one step.
+    ///
+    /// The buffer keeps the channel count and sample rate of the encoded audio. Use a
+    /// [`Decoder`] to convert them while decoding.
+    pub fn from_encoded_bytes(data: &[u8]) -> MaResult<AudioBuffer<f32>> {
+        let info = Decoder::probe_memory(data)?;
+        let mut decoder =
+            DecoderBuilder::new_f32(info.channels, info.sample_rate).from_memory(data)?;
+        let channels = info.channels as usize;
+
+        let mut samples = Vec::with_capacity(info.length_frames.unwrap_or(0) as usize * channels);
+        let mut block = vec![0.0f32; DECODE_BLOCK_FRAMES * channels];
+        loop {
+            let frames = match decoder.read_pcm_frames_into(&mut block) {
+                Ok(0) => break,
+                Ok(frames) => frames,
+                Err(e) if e.code() == MaResultCode::AtEnd => break,
+                Err(e) => return Err(e),
+            };
+            samples.extend_from_slice(&block[..frames * channels]);
+        }
+
+        Self::build_f32_with_sample_rate(info.channels, info.sample_rate, &samples)
+    }
+
+    /// Same as [`AudioBufferBuilder::build_f32`], but the buffer reports `sample_rate` as a
+    /// data source, so sounds resample it to the engine rate.
+    pub(crate) fn build_f32_with_sample_rate(
+        channels: u32,
+        sample_rate: SampleRate,
+        data: &[f32],
+    ) -> MaResult<AudioBuffer<f32>> {
+        if channels == 0 {
+            return Err(crate::MaudioError::from_ma_result(
+                sys::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+        let mut builder = Self::init(
+            Format::F32,
+            channels,
+            (data.len() / channels as usize) as u64,
+            data.as_ptr() as *const _,
+            None,
+        );
+        builder.inner.sampleRate = sample_rate.into();
+        AudioBuffer::copy_with_cfg_internal(&builder)
+    }
+
     pub fn build_u8(channels: u32, data: &[u8]) -> MaResult<AudioBuffer<u8>> {
         if channels == 0 {
             return Err(crate::MaudioError::from_ma_result(
@@ -921,8 +1037,10 @@ pub(crate) mod buffer_config_ffi {
 #[cfg(test)]
 mod test {
     use crate::{
-        audio::formats::SampleBuffer, data_source::sources::buffer::AudioBufferBuilder,
+        audio::{formats::SampleBuffer, sample_rate::SampleRate},
+        data_source::sources::buffer::AudioBufferBuilder,
         pcm_frames::PcmFormat,
+        test_assets::wav_i16_le,
     };
 
     fn ramp_f32_interleaved(channels: u32, frames: u64) -> Vec<f32> {
@@ -1170,4 +1288,47 @@ mod test {
             let _ref_buffer = base.bind(&data).unwrap();
         }
     }
+
+    #[test]
+    fn test_audio_buffer_from_encoded_bytes() {
+        let samples: Vec<i16> = (0..2000).map(|i| (i * 16) as i16).collect();
+        let wav = wav_i16_le(2, SampleRate::Sr44100, &samples);
+
+        let buffer = AudioBufferBuilder::from_encoded_bytes(&wav).unwrap();
+        assert_eq!(buffer.length_pcm().unwrap(), 1000);
+        let format = buffer.as_source_ref().data_format().unwrap();
+        assert_eq!(format.channels, 2);
+        assert_eq!(format.sample_rate, SampleRate::Sr44100);
+
+        let decoded = buffer.to_sample_buffer::<f32>().unwrap();
+        let expected: Vec<f32> = samples.iter().map(|s| *s as f32 / 32768.0).collect();
+        assert_eq!(decoded.as_ref(), expected.as_slice());
+
+        assert!(AudioBufferBuilder::from_encoded_bytes(b"not audio").is_err());
+    }
+
+    #[test]
+    fn test_audio_buffer_to_sample_buffer_converts_and_keeps_cursor() {
+        let data = ramp_f32_interleaved(2, 64)
+            .iter()
+            .map(|s| s / 1000.0)
+            .collect::<Vec<_>>();
+        let mut buffer = AudioBufferBuilder::build_f32(2, &data).unwrap();
+        buffer.seek_to_pcm(10).unwrap();
+
+        let same = buffer.to_sample_buffer::<f32>().unwrap();
+        assert_eq!(same.frames(), 64);
+        assert_eq!(same.channels(), 2);
+        assert_eq!(same.as_ref(), data.as_slice());
+
+        let converted = buffer.to_sample_buffer::<i16>().unwrap();
+        assert_eq!(converted.frames(), 64);
+        let expected: Vec<i16> = data.iter().map(|s| (s * 32767.0) as i16).collect();
+        assert!(converted
+            .as_ref()
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| (a - b).abs() <= 1));
+        assert_eq!(buffer.cursor_pcm().unwrap(), 10);
+    }
 }
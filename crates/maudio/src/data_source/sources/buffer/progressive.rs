@@ -0,0 +1,508 @@
+//! Buffer filled by a background decoder, playable while it is still being decoded.
+//!
+//! [`AudioBufferBuilder::from_decoder_async`] moves a decoder to a worker thread that decodes
+//! it block by block into a growing in-memory buffer. The returned [`ProgressiveBuffer`] is a
+//! data source like any other, so a sound can start playing it right away.
+//!
+//! When playback catches up with the decoder, the buffer outputs silence and keeps its
+//! cursor where it is until more frames are decoded. Once decoding is done the buffer
+//! behaves like a fully decoded [`AudioBuffer`], and can be seeked and looped.
+//!
+//! ```no_run
+//! # use maudio::data_source::sources::{buffer::AudioBufferBuilder, decoder::DecoderBuilder};
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use maudio::engine::Engine;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let decoder = DecoderBuilder::new_f32(2, SampleRate::Sr48000)
+//!     .from_file(Path::new("long_track.flac"))?;
+//!
+//! let buffer = AudioBufferBuilder::from_decoder_async(decoder, |progress| {
+//!     if let Some(fraction) = progress.fraction() {
+//!         println!("decoded {:.0}%", fraction * 100.0);
+//!     }
+//! })?;
+//!
+//! // Starts playing before the whole file is decoded
+//! let mut sound = engine.new_sound_from_source(&buffer)?;
+//! sound.play_sound()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`AudioBufferBuilder::from_decoder_async`]: crate::data_source::sources::buffer::AudioBufferBuilder::from_decoder_async
+//! [`AudioBuffer`]: crate::data_source::sources::buffer::AudioBuffer
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, TryLockError,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{
+    audio::formats::SampleBuffer,
+    data_source::{
+        data_source_builder::DataSourceBuilder,
+        pcm_source::PcmSource,
+        private_data_source,
+        sources::decoder::{Decoder, DecoderOps},
+        AsSourcePtr, DataSource, DataSourceRef, SourceContext,
+    },
+    ErrorKinds, MaResult, MaResultCode, MaudioError,
+};
+
+// Frames the worker decodes before making them available to the buffer, the size of a chunk.
+const DECODE_BLOCK_FRAMES: usize = 4096;
+
+/// How far a [`ProgressiveBuffer`] has been decoded, passed to the progress callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeProgress {
+    /// Frames decoded so far.
+    pub decoded_frames: u64,
+    /// Length of the decoded audio, if the decoder knows it.
+    pub total_frames: Option<u64>,
+}
+
+impl DecodeProgress {
+    /// Returns the decoded fraction between `0.0` and `1.0`, if the length is known.
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total_frames {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.decoded_frames as f64 / total as f64).min(1.0) as f32),
+            None => None,
+        }
+    }
+}
+
+/// A buffer decoded on a background thread, created with
+/// [`AudioBufferBuilder::from_decoder_async`].
+///
+/// Dropping the buffer stops the worker thread and waits for it. Sounds playing the buffer
+/// must be dropped first.
+///
+/// [`AudioBufferBuilder::from_decoder_async`]: crate::data_source::sources::buffer::AudioBufferBuilder::from_decoder_async
+pub struct ProgressiveBuffer {
+    source: DataSource<f32, ProgressiveSource>,
+    state: Arc<ProgressiveState>,
+    worker: Option<JoinHandle<MaResult<()>>>,
+}
+
+#[doc(hidden)]
+impl AsSourcePtr for ProgressiveBuffer {
+    type Format = f32;
+    type __PtrProvider = private_data_source::ProgressiveBufferProvider;
+}
+
+impl ProgressiveBuffer {
+    pub(crate) fn spawn<S, C>(mut decoder: Decoder<f32, S>, mut progress: C) -> MaResult<Self>
+    where
+        S: Send + 'static,
+        C: FnMut(DecodeProgress) + Send + 'static,
+    {
+        let format = decoder.data_format()?;
+        let total_frames = match decoder.length_pcm() {
+            Ok(0) | Err(_) => None,
+            Ok(frames) => Some(frames),
+        };
+        let state = Arc::new(ProgressiveState {
+            chunks: Mutex::new(Vec::with_capacity(
+                total_frames.unwrap_or(0) as usize / DECODE_BLOCK_FRAMES + 1,
+            )),
+            chunk_frames: DECODE_BLOCK_FRAMES,
+            channels: format.channels as usize,
+            total_frames,
+            decoded_frames: AtomicU64::new(0),
+            complete: AtomicBool::new(false),
+            cancel: AtomicBool::new(false),
+        });
+
+        let mut builder = DataSourceBuilder::new(format.channels, format.sample_rate);
+        if let Some(map) = format.channel_map {
+            builder.channel_map(map);
+        }
+        let source = builder.build_f32(ProgressiveSource {
+            state: state.clone(),
+        })?;
+
+        let worker_state = state.clone();
+        let worker = std::thread::Builder::new()
+            .name("maudio-progressive-decode".into())
+            .spawn(move || {
+                let res = decode_loop(&mut decoder, &worker_state, &mut progress);
+                // Also set on errors and cancellation, so playback ends instead of waiting
+                worker_state.complete.store(true, Ordering::Release);
+                res
+            })?;
+
+        Ok(Self {
+            source,
+            state,
+            worker: Some(worker),
+        })
+    }
+
+    /// Number of frames decoded so far.
+    pub fn decoded_frames(&self) -> u64 {
+        self.state.decoded_frames.load(Ordering::Acquire)
+    }
+
+    /// Length of the decoded audio, if the decoder reported it.
+    pub fn total_frames(&self) -> Option<u64> {
+        self.state.total_frames
+    }
+
+    /// Returns `true` once the worker has stopped decoding, because it reached the end, was
+    /// cancelled or failed.
+    pub fn is_complete(&self) -> bool {
+        self.state.complete.load(Ordering::Acquire)
+    }
+
+    /// Blocks until the whole source is decoded.
+    ///
+    /// Returns the error that stopped the decoder, if any. Calling it again returns `Ok(())`.
+    pub fn wait(&mut self) -> MaResult<()> {
+        let Some(worker) = self.worker.take() else {
+            return Ok(());
+        };
+        worker.join().map_err(|_| {
+            MaudioError::new_ma_error(ErrorKinds::Other("progressive decode thread panicked"))
+        })?
+    }
+
+    /// Stops decoding and waits for the worker thread.
+    ///
+    /// The frames decoded so far stay in the buffer, and playback ends after them.
+    pub fn cancel(&mut self) -> MaResult<()> {
+        self.state.cancel.store(true, Ordering::Release);
+        self.wait()
+    }
+
+    /// Reads PCM frames into `dst`, returning the number of frames read.
+    ///
+    /// Frames that are not decoded yet read as silence.
+    pub fn read_pcm_frames_into(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        self.source.read_pcm_frames_into(dst)
+    }
+
+    /// Allocates and reads `frame_count` PCM frames.
+    pub fn read_pcm_frames(&mut self, frame_count: u64) -> MaResult<SampleBuffer<f32>> {
+        self.source.read_pcm_frames(frame_count)
+    }
+
+    /// Seeks to an absolute PCM frame index.
+    ///
+    /// Frames past the decoded ones can be seeked to when the length is known.
+    pub fn seek_to_pcm(&mut self, frame_index: u64) -> MaResult<()> {
+        self.source.seek_to_pcm_frame(frame_index)
+    }
+
+    pub fn cursor_pcm(&self) -> MaResult<u64> {
+        self.source.cursor_in_pcm_frames()
+    }
+
+    /// Returns a [`DataSourceRef`] view of this buffer.
+    pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, f32> {
+        self.source.as_source_ref()
+    }
+}
+
+impl Drop for ProgressiveBuffer {
+    fn drop(&mut self) {
+        let _ = self.cancel();
+    }
+}
+
+// State shared between the buffer, the audio thread and the worker thread.
+struct ProgressiveState {
+    // Decoded samples, in chunks of `chunk_frames` frames. Only the last chunk can be shorter.
+    // A chunk is never moved once added, so the lock is only held to push a pointer, and the
+    // audio thread never waits on the whole buffer being reallocated.
+    chunks: Mutex<Vec<Box<[f32]>>>,
+    chunk_frames: usize,
+    channels: usize,
+    total_frames: Option<u64>,
+    decoded_frames: AtomicU64,
+    complete: AtomicBool,
+    cancel: AtomicBool,
+}
+
+impl ProgressiveState {
+    fn push_chunk(&self, chunk: Box<[f32]>) -> u64 {
+        let mut chunks = self.chunks.lock().unwrap_or_else(|e| e.into_inner());
+        chunks.push(chunk);
+        self.frames_in(&chunks)
+    }
+
+    fn frames_in(&self, chunks: &[Box<[f32]>]) -> u64 {
+        match chunks.last() {
+            Some(last) => {
+                ((chunks.len() - 1) * self.chunk_frames + last.len() / self.channels) as u64
+            }
+            None => 0,
+        }
+    }
+}
+
+fn decode_loop<S, C: FnMut(DecodeProgress)>(
+    decoder: &mut Decoder<f32, S>,
+    state: &ProgressiveState,
+    progress: &mut C,
+) -> MaResult<()> {
+    let chunk_len = state.chunk_frames * state.channels;
+    let mut block = vec![0.0f32; chunk_len];
+    let mut filled = 0;
+    let res = loop {
+        if state.cancel.load(Ordering::Acquire) {
+            break Ok(());
+        }
+        let frames = match decoder.read_pcm_frames_into(&mut block[filled..]) {
+            Ok(0) => break Ok(()),
+            Ok(frames) => frames,
+            Err(e) if e.code() == MaResultCode::AtEnd => break Ok(()),
+            Err(e) => break Err(e),
+        };
+        filled += frames * state.channels;
+        if filled < chunk_len {
+            continue;
+        }
+        let chunk = std::mem::replace(&mut block, vec![0.0f32; chunk_len]);
+        filled = 0;
+        let decoded_frames = state.push_chunk(chunk.into_boxed_slice());
+        state
+            .decoded_frames
+            .store(decoded_frames, Ordering::Release);
+        progress(DecodeProgress {
+            decoded_frames,
+            total_frames: state.total_frames,
+        });
+    };
+
+    // The last chunk, also kept when decoding stopped early
+    if filled > 0 {
+        block.truncate(filled);
+        let decoded_frames = state.push_chunk(block.into_boxed_slice());
+        state
+            .decoded_frames
+            .store(decoded_frames, Ordering::Release);
+        progress(DecodeProgress {
+            decoded_frames,
+            total_frames: state.total_frames,
+        });
+    }
+    res
+}
+
+// The `PcmSource` behind a `ProgressiveBuffer`
+struct ProgressiveSource {
+    state: Arc<ProgressiveState>,
+}
+
+impl PcmSource<f32> for ProgressiveSource {
+    fn fill_pcm_frames(&mut self, out: &mut [f32], ctx: &mut SourceContext) -> MaResult<usize> {
+        let channels = self.state.channels;
+        let chunk_frames = self.state.chunk_frames as u64;
+        // Read before the samples, so a complete buffer is never missing its last block
+        let complete = self.state.complete.load(Ordering::Acquire);
+        let chunks = match self.state.chunks.try_lock() {
+            Ok(chunks) => chunks,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                // The worker is adding a chunk. Play silence and wait at the same position.
+                out.fill(0.0);
+                return Ok(out.len() / channels);
+            }
+        };
+        let decoded = self.state.frames_in(&chunks);
+
+        let mut written = 0;
+        loop {
+            if ctx.cursor < decoded {
+                let chunk = &chunks[(ctx.cursor / chunk_frames) as usize];
+                let offset = (ctx.cursor % chunk_frames) as usize * channels;
+                let count = (chunk.len() - offset).min(out.len() - written) / channels * channels;
+                out[written..written + count].copy_from_slice(&chunk[offset..offset + count]);
+                written += count;
+                ctx.cursor += (count / channels) as u64;
+                if written == out.len() {
+                    break;
+                }
+                continue;
+            }
+
+            if !complete {
+                // Caught up with the decoder. Play silence and wait at the same position.
+                out[written..].fill(0.0);
+                return Ok(out.len() / channels);
+            }
+            if ctx.looping && decoded > 0 {
+                ctx.cursor = 0;
+                continue;
+            }
+            out[written..].fill(0.0);
+            break;
+        }
+        Ok(written / channels)
+    }
+
+    fn seek_to_pcm_frame(&mut self, frame_index: u64, ctx: &mut SourceContext) -> MaResult<()> {
+        let limit = match self.state.total_frames {
+            Some(total) => total,
+            None => self.state.decoded_frames.load(Ordering::Acquire),
+        };
+        if frame_index > limit {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "Trying to seek too far",
+            )));
+        }
+        ctx.cursor = frame_index;
+        Ok(())
+    }
+
+    fn cursor_in_pcm_frames(&self, ctx: &SourceContext) -> Option<u64> {
+        Some(ctx.cursor)
+    }
+
+    fn length_in_pcm_frames(&self, _ctx: &SourceContext) -> Option<u64> {
+        match self.state.complete.load(Ordering::Acquire) {
+            true => Some(self.state.decoded_frames.load(Ordering::Acquire)),
+            false => self.state.total_frames,
+        }
+    }
+
+    fn set_looping(&self, looping: bool, ctx: &mut SourceContext) -> MaResult<()> {
+        ctx.looping = looping;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+
+    use super::{ProgressiveSource, ProgressiveState};
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::{
+            data_source_builder::DataSourceBuilder,
+            sources::{buffer::AudioBufferBuilder, decoder::DecoderBuilder},
+        },
+        engine::engine_builder::EngineBuilder,
+        test_assets::wav_i16_le,
+    };
+
+    fn ramp(frames: usize) -> Vec<i16> {
+        (0..frames).map(|i| (i % 1000) as i16 * 16).collect()
+    }
+
+    #[test]
+    fn test_progressive_buffer_decodes_everything() {
+        let samples = ramp(10_000);
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &samples);
+        let decoder = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .copy_memory(wav)
+            .unwrap();
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let progress = reported.clone();
+        let mut buffer = AudioBufferBuilder::from_decoder_async(decoder, move |p| {
+            progress.lock().unwrap().push(p);
+        })
+        .unwrap();
+        assert_eq!(buffer.total_frames(), Some(10_000));
+
+        buffer.wait().unwrap();
+        assert!(buffer.is_complete());
+        assert_eq!(buffer.decoded_frames(), 10_000);
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.last().unwrap().decoded_frames, 10_000);
+        assert_eq!(reported.last().unwrap().fraction(), Some(1.0));
+        assert!(reported
+            .windows(2)
+            .all(|w| w[0].decoded_frames < w[1].decoded_frames));
+
+        let out = buffer.read_pcm_frames(10_000).unwrap();
+        let expected: Vec<f32> = samples.iter().map(|s| *s as f32 / 32768.0).collect();
+        assert_eq!(out.as_ref(), expected.as_slice());
+
+        buffer.seek_to_pcm(9_990).unwrap();
+        assert_eq!(buffer.cursor_pcm().unwrap(), 9_990);
+        assert!(buffer.seek_to_pcm(10_001).is_err());
+    }
+
+    #[test]
+    fn test_progressive_buffer_plays_through_a_sound() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let samples = ramp(4_096);
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &samples);
+        let decoder = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .copy_memory(wav)
+            .unwrap();
+        let mut buffer = AudioBufferBuilder::from_decoder_async(decoder, |_| {}).unwrap();
+        buffer.wait().unwrap();
+
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        let expected: Vec<f32> = samples[..256].iter().map(|s| *s as f32 / 32768.0).collect();
+        // The sound's resampler delays its output by one frame
+        for (a, b) in out.as_ref()[1..].iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_progressive_source_waits_for_decoder() {
+        let state = Arc::new(ProgressiveState {
+            chunks: Mutex::new(vec![vec![0.5; 4].into_boxed_slice()]),
+            chunk_frames: 4,
+            channels: 1,
+            total_frames: Some(8),
+            decoded_frames: AtomicU64::new(4),
+            complete: AtomicBool::new(false),
+            cancel: AtomicBool::new(false),
+        });
+        let mut source = DataSourceBuilder::new(1, SampleRate::Sr48000)
+            .build_f32(ProgressiveSource {
+                state: state.clone(),
+            })
+            .unwrap();
+
+        // Runs out of decoded frames: silence, and the cursor waits
+        let out = source.read_pcm_frames(6).unwrap();
+        assert_eq!(out.as_ref(), &[0.5, 0.5, 0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(source.cursor_in_pcm_frames().unwrap(), 4);
+
+        // The worker holds the lock: silence, without waiting for it
+        let held = state.chunks.lock().unwrap();
+        let out = source.read_pcm_frames(2).unwrap();
+        assert_eq!(out.as_ref(), &[0.0, 0.0]);
+        assert_eq!(source.cursor_in_pcm_frames().unwrap(), 4);
+        drop(held);
+
+        state.push_chunk(vec![0.25; 4].into_boxed_slice());
+        state.decoded_frames.store(8, Ordering::Release);
+        state.complete.store(true, Ordering::Release);
+        let out = source.read_pcm_frames(6).unwrap();
+        assert_eq!(out.as_ref(), &[0.25; 4]);
+
+        source.set_looping(true).unwrap();
+        source.seek_to_pcm_frame(6).unwrap();
+        let out = source.read_pcm_frames(4).unwrap();
+        assert_eq!(out.as_ref(), &[0.25, 0.25, 0.5, 0.5]);
+
+        // Reads span chunks
+        source.seek_to_pcm_frame(2).unwrap();
+        let out = source.read_pcm_frames(4).unwrap();
+        assert_eq!(out.as_ref(), &[0.5, 0.5, 0.25, 0.25]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Silence and constant-level (DC) data sources.
+//!
+//! [`Silence`] is a placeholder source for sounds and nodes that need one before the real
+//! audio is available. [`Constant`] outputs the same value on every sample, which makes the
+//! effect of a node easy to check: a gain shows up as a different level, and the latency of a
+//! graph as the number of frames before the level appears at the output.
+//!
+//! Both run forever unless they are built with a length.
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate},
+    data_source::{
+        data_source_builder::DataSourceBuilder, pcm_source::PcmSource, private_data_source,
+        AsSourcePtr, DataSource, DataSourceRef, SourceContext,
+    },
+    engine::node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
+    ErrorKinds, MaResult, MaudioError,
+};
+
+/// A data source that outputs `level` on every channel.
+///
+/// The level can be changed while the source plays.
+pub struct Constant {
+    source: DataSource<f32, ConstantSource>,
+    level: Arc<AtomicU32>,
+}
+
+#[doc(hidden)]
+impl AsSourcePtr for Constant {
+    type Format = f32;
+    type __PtrProvider = private_data_source::ConstantProvider;
+}
+
+impl Constant {
+    /// Creates a source that outputs `level` forever.
+    pub fn new(channels: u32, sample_rate: SampleRate, level: f32) -> MaResult<Self> {
+        Self::build(channels, sample_rate, level, None)
+    }
+
+    /// Creates a source that outputs `level` for `frames` frames, then ends.
+    pub fn with_length(
+        channels: u32,
+        sample_rate: SampleRate,
+        level: f32,
+        frames: u64,
+    ) -> MaResult<Self> {
+        Self::build(channels, sample_rate, level, Some(frames))
+    }
+
+    fn build(
+        channels: u32,
+        sample_rate: SampleRate,
+        level: f32,
+        length: Option<u64>,
+    ) -> MaResult<Self> {
+        let level = Arc::new(AtomicU32::new(level.to_bits()));
+        let source = DataSourceBuilder::new(channels, sample_rate).build_f32(ConstantSource {
+            level: level.clone(),
+            length,
+        })?;
+        Ok(Self { source, level })
+    }
+
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Changes the output level, also while the source is playing.
+    pub fn set_level(&self, level: f32) {
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Reads PCM frames into `dst`, returning the number of frames read.
+    pub fn read_pcm_frames_into(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        self.source.read_pcm_frames_into(dst)
+    }
+
+    /// Allocates and reads `frame_count` PCM frames.
+    pub fn read_pcm_frames(&mut self, frame_count: u64) -> MaResult<SampleBuffer<f32>> {
+        self.source.read_pcm_frames(frame_count)
+    }
+
+    /// Seeks to an absolute PCM frame index, at most the length if there is one.
+    pub fn seek_to_pcm(&mut self, frame_index: u64) -> MaResult<()> {
+        self.source.seek_to_pcm_frame(frame_index)
+    }
+
+    pub fn cursor_pcm(&self) -> MaResult<u64> {
+        self.source.cursor_in_pcm_frames()
+    }
+
+    /// Returns a [`DataSourceRef`] view of this source.
+    pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, f32> {
+        self.source.as_source_ref()
+    }
+
+    /// Turns the source into a source node of `node_graph`. Its output is not attached yet.
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
+/// A data source that outputs silence.
+pub struct Silence {
+    inner: Constant,
+}
+
+#[doc(hidden)]
+impl AsSourcePtr for Silence {
+    type Format = f32;
+    type __PtrProvider = private_data_source::SilenceProvider;
+}
+
+impl Silence {
+    /// Creates a source that is silent forever.
+    pub fn new(channels: u32, sample_rate: SampleRate) -> MaResult<Self> {
+        Ok(Self {
+            inner: Constant::new(channels, sample_rate, 0.0)?,
+        })
+    }
+
+    /// Creates a source that is silent for `frames` frames, then ends.
+    pub fn with_length(channels: u32, sample_rate: SampleRate, frames: u64) -> MaResult<Self> {
+        Ok(Self {
+            inner: Constant::with_length(channels, sample_rate, 0.0, frames)?,
+        })
+    }
+
+    /// Reads PCM frames into `dst`, returning the number of frames read.
+    pub fn read_pcm_frames_into(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        self.inner.read_pcm_frames_into(dst)
+    }
+
+    /// Allocates and reads `frame_count` PCM frames.
+    pub fn read_pcm_frames(&mut self, frame_count: u64) -> MaResult<SampleBuffer<f32>> {
+        self.inner.read_pcm_frames(frame_count)
+    }
+
+    /// Seeks to an absolute PCM frame index, at most the length if there is one.
+    pub fn seek_to_pcm(&mut self, frame_index: u64) -> MaResult<()> {
+        self.inner.seek_to_pcm(frame_index)
+    }
+
+    pub fn cursor_pcm(&self) -> MaResult<u64> {
+        self.inner.cursor_pcm()
+    }
+
+    /// Returns a [`DataSourceRef`] view of this source.
+    pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, f32> {
+        self.inner.as_source_ref()
+    }
+
+    /// Turns the source into a source node of `node_graph`. Its output is not attached yet.
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
+// The `PcmSource` behind `Constant` and `Silence`
+struct ConstantSource {
+    level: Arc<AtomicU32>,
+    length: Option<u64>,
+}
+
+impl PcmSource<f32> for ConstantSource {
+    fn fill_pcm_frames(&mut self, out: &mut [f32], ctx: &mut SourceContext) -> MaResult<usize> {
+        let channels = ctx.data_format.channels as usize;
+        let requested = (out.len() / channels) as u64;
+        let mut frames = 0;
+        loop {
+            let available = match self.length {
+                Some(length) => length.saturating_sub(ctx.cursor),
+                None => requested,
+            };
+            let count = (requested - frames).min(available);
+            frames += count;
+            ctx.cursor += count;
+            if frames == requested {
+                break;
+            }
+            match self.length {
+                Some(length) if ctx.looping && length > 0 => ctx.cursor = 0,
+                _ => break,
+            }
+        }
+
+        let samples = frames as usize * channels;
+        out[..samples].fill(f32::from_bits(self.level.load(Ordering::Relaxed)));
+        out[samples..].fill(0.0);
+        Ok(frames as usize)
+    }
+
+    fn seek_to_pcm_frame(&mut self, frame_index: u64, ctx: &mut SourceContext) -> MaResult<()> {
+        if self.length.map_or(false, |length| frame_index > length) {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "Trying to seek too far",
+            )));
+        }
+        ctx.cursor = frame_index;
+        Ok(())
+    }
+
+    fn cursor_in_pcm_frames(&self, ctx: &SourceContext) -> Option<u64> {
+        Some(ctx.cursor)
+    }
+
+    fn length_in_pcm_frames(&self, _ctx: &SourceContext) -> Option<u64> {
+        self.length
+    }
+
+    fn set_looping(&self, looping: bool, ctx: &mut SourceContext) -> MaResult<()> {
+        ctx.looping = looping;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::constant::{Constant, Silence},
+        engine::engine_builder::EngineBuilder,
+    };
+
+    #[test]
+    fn test_constant_outputs_its_level() {
+        let mut constant = Constant::new(2, SampleRate::Sr48000, 0.25).unwrap();
+        let out = constant.read_pcm_frames(512).unwrap();
+        assert_eq!(out.frames(), 512);
+        assert!(out.as_ref().iter().all(|s| *s == 0.25));
+
+        constant.set_level(-0.5);
+        assert_eq!(constant.level(), -0.5);
+        let out = constant.read_pcm_frames(64).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == -0.5));
+        assert_eq!(constant.cursor_pcm().unwrap(), 576);
+    }
+
+    #[test]
+    fn test_silence_with_length_ends() {
+        let mut silence = Silence::with_length(1, SampleRate::Sr48000, 100).unwrap();
+        assert_eq!(silence.as_source_ref().length_in_pcm_frames().unwrap(), 100);
+        let out = silence.read_pcm_frames(160).unwrap();
+        assert_eq!(out.frames(), 100);
+        assert!(out.as_ref().iter().all(|s| *s == 0.0));
+        assert!(silence.seek_to_pcm(101).is_err());
+        silence.seek_to_pcm(40).unwrap();
+        assert_eq!(silence.read_pcm_frames(100).unwrap().frames(), 60);
+    }
+
+    #[test]
+    fn test_constant_measures_graph_latency() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let constant = Constant::new(1, SampleRate::Sr48000, 0.5).unwrap();
+        let mut sound = engine.new_sound_from_source(&constant).unwrap();
+        sound.set_spatialization(false);
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        // Sounds start one frame late
+        let latency = out.as_ref().iter().position(|s| *s == 0.5).unwrap();
+        assert_eq!(latency, 1);
+        assert!(out.as_ref()[latency..].iter().all(|s| *s == 0.5));
+    }
+}
@@ -8,24 +8,41 @@
 //!
 //! A decoder implements [`DataSource`](crate::data_source::DataSource), allowing it to be used directly by
 //! sounds and node graphs.
-use std::{marker::PhantomData, mem::MaybeUninit, path::Path, sync::Arc};
+use std::{
+    marker::PhantomData,
+    mem::MaybeUninit,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{
+        channels::ChannelLayout,
         formats::{Format, SampleBuffer},
         sample_rate::SampleRate,
     },
     data_source::{data_source_ffi, private_data_source, AsSourcePtr, DataFormat, DataSourceRef},
     device::device_builder::Unknown,
     pcm_frames::{PcmFormat, S24Packed},
-    AsRawRef, Binding, MaResult,
+    util::callback_panic,
+    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError, ResultContext,
 };
 
+#[cfg(feature = "memmap2")]
+use crate::util::mapped_file::MappedFile;
+use custom_decoder::CustomDecoderBuilder;
+use decoding_backend::DecodingBackend;
+use seek::{SeekIndex, SeekMode};
+use wav_metadata::{Marker, SampleLoop, WavMetadata};
+
 pub mod custom_decoder;
 mod decoder_vtable;
 pub mod decoding_backend;
+pub mod seek;
+pub mod wav_metadata;
 
 /// Streaming audio decoder.
 ///
@@ -116,6 +133,21 @@ pub struct Decoder<F: PcmFormat, S> {
     user_data: Option<DecoderUserDataDestructor>,
     _sample_format: PhantomData<F>,
     source_data: S,
+    metadata: WavMetadata,
+    // MP3 seek table entries, read on the first fast seek
+    seek_index: Option<SeekIndex>,
+    // Kept to reopen the decoder in `build_seek_index`
+    config: sys::ma_decoder_config,
+    channel_map: Option<ChannelLayout>,
+    origin: Origin,
+}
+
+// Where the encoded data is read from
+enum Origin {
+    // Kept alive by the decoder's source data
+    Memory(*const u8, usize),
+    File(PathBuf),
+    Reader,
 }
 
 unsafe impl<F: PcmFormat, S> Send for Decoder<F, S> {}
@@ -123,13 +155,21 @@ unsafe impl<F: PcmFormat, S> Send for Decoder<F, S> {}
 type DecoderUserDataDestructor = (*mut core::ffi::c_void, fn(*mut core::ffi::c_void));
 
 /// Borrowed in-memory audio data used as a decoder source.
+#[derive(Clone, Copy)]
 #[allow(unused)]
 pub struct Borrowed<'a>(&'a [u8]);
 
 /// Owned in-memory audio data used as a decoder source.
+#[derive(Clone)]
 #[allow(unused)]
 pub struct Owned(Arc<[u8]>);
+/// Memory-mapped audio data used as a decoder source.
+#[cfg(feature = "memmap2")]
+#[derive(Clone)]
+#[allow(unused)]
+pub struct Mapped(MappedFile);
 /// Data source or destination is in a filesystem (e.g., file path) managed by miniaudio.
+#[derive(Clone, Copy)]
 pub struct Fs;
 /// Data source or destination is a callback (reader or writer)
 pub struct Cb;
@@ -149,7 +189,13 @@ impl<F: PcmFormat, S> Decoder<F, S> {
         config: &DecoderBuilder<F>,
         format: Format,
         source_data: S,
+        origin: Origin,
     ) -> Self {
+        let channel_map = config.channel_map.clone();
+        let mut raw_config = config.inner;
+        if let Some(map) = &channel_map {
+            raw_config.pChannelMap = map.as_raw_ptr() as *mut _;
+        }
         Self {
             inner,
             channels: config.channels,
@@ -158,7 +204,76 @@ impl<F: PcmFormat, S> Decoder<F, S> {
             user_data: None,
             _sample_format: PhantomData,
             source_data,
+            metadata: WavMetadata::default(),
+            seek_index: None,
+            config: raw_config,
+            channel_map,
+            origin,
+        }
+    }
+
+    /// Seeks to `time`, at the closest output frame.
+    ///
+    /// Same as [`Decoder::seek_to_time_with`] with [`SeekMode::Accurate`]. Seeking in a long
+    /// MP3 file is much faster with a seek table, see [`seek`].
+    pub fn seek_to_time(&mut self, time: Duration) -> MaResult<()> {
+        self.seek_to_time_with(time, SeekMode::Accurate)
+    }
+
+    /// Seeks to `time`, using `mode` to pick the frame.
+    ///
+    /// [`SeekMode::Fast`] lands on an entry of the MP3 seek table at or before `time`, see
+    /// [`seek`] for details.
+    pub fn seek_to_time_with(&mut self, time: Duration, mode: SeekMode) -> MaResult<()> {
+        let sample_rate: u32 = decoder_ffi::ma_decoder_get_data_format(self)?
+            .sample_rate
+            .into();
+        let target = seek::time_to_frame(time, sample_rate)?;
+        let frame = match mode {
+            SeekMode::Accurate => target,
+            SeekMode::Fast => {
+                let inner = self.inner;
+                self.seek_index
+                    .get_or_insert_with(|| SeekIndex::read(inner, sample_rate))
+                    .resolve(target)
+            }
+        };
+        decoder_ffi::ma_decoder_seek_to_pcm_frame(self, frame)
+    }
+
+    /// Returns the markers read from the `cue ` chunk of a WAV file, sorted by position.
+    ///
+    /// Positions are in output frames, so they already account for any resampling done by
+    /// the decoder. Empty for other formats.
+    pub fn markers(&self) -> &[Marker] {
+        &self.metadata.markers
+    }
+
+    /// Returns the loops read from the `smpl` chunk of a WAV file, in output frames.
+    ///
+    /// Empty for other formats.
+    pub fn sample_loops(&self) -> &[SampleLoop] {
+        &self.metadata.loops
+    }
+
+    fn with_metadata(
+        mut self,
+        metadata: WavMetadata,
+        config: &DecoderBuilder<F>,
+    ) -> MaResult<Self> {
+        let rate = decoder_ffi::ma_decoder_get_data_format(&self)
+            .map(|f| f.sample_rate.into())
+            .unwrap_or(0);
+        self.metadata = metadata.rescaled(rate);
+        if config.file_loop_points {
+            if let Some(l) = self.metadata.loops.first() {
+                let (start, end) = (l.start, l.end);
+                data_source_ffi::ma_data_source_set_loop_point_in_pcm_frames(
+                    &mut self, start, end,
+                )?;
+            }
         }
+        Ok(self)
     }
 
     fn init_from_memory<'a>(
@@ -175,7 +290,15 @@ impl<F: PcmFormat, S> Decoder<F, S> {
         )?;
 
         let inner: *mut sys::ma_decoder = Box::into_raw(mem) as *mut sys::ma_decoder;
-        Ok(Decoder::new(inner, config, config.format, Borrowed(data)))
+        let metadata = WavMetadata::read(&mut std::io::Cursor::new(data));
+        Decoder::new(
+            inner,
+            config,
+            config.format,
+            Borrowed(data),
+            Origin::Memory(data.as_ptr(), data.len()),
+        )
+        .with_metadata(metadata, config)
     }
 
     fn init_copy<D: Into<Arc<[u8]>>>(
@@ -193,22 +316,46 @@ impl<F: PcmFormat, S> Decoder<F, S> {
         )?;
 
         let inner: *mut sys::ma_decoder = Box::into_raw(mem) as *mut sys::ma_decoder;
-        Ok(Decoder::new(inner, config, config.format, Owned(data_arc)))
+        let metadata = WavMetadata::read(&mut std::io::Cursor::new(&data_arc[..]));
+        let origin = Origin::Memory(data_arc.as_ptr(), data_arc.len());
+        Decoder::new(inner, config, config.format, Owned(data_arc), origin)
+            .with_metadata(metadata, config)
+    }
+
+    #[cfg(feature = "memmap2")]
+    fn init_mapped(file: &MappedFile, config: &DecoderBuilder<F>) -> MaResult<Decoder<F, Mapped>> {
+        let mut mem: Box<std::mem::MaybeUninit<sys::ma_decoder>> = Box::new(MaybeUninit::uninit());
+
+        decoder_ffi::ma_decoder_init_memory(
+            file.as_ptr() as *const _,
+            file.len(),
+            config.as_raw_ptr(),
+            mem.as_mut_ptr(),
+        )?;
+
+        let inner: *mut sys::ma_decoder = Box::into_raw(mem) as *mut sys::ma_decoder;
+        let metadata = WavMetadata::read(&mut std::io::Cursor::new(file.as_bytes()));
+        let origin = Origin::Memory(file.as_ptr(), file.len());
+        Decoder::new(inner, config, config.format, Mapped(file.clone()), origin)
+            .with_metadata(metadata, config)
     }
 
     fn init_file(path: &Path, config: &DecoderBuilder<F>) -> MaResult<Decoder<F, Fs>> {
         let mut mem: Box<std::mem::MaybeUninit<sys::ma_decoder>> = Box::new(MaybeUninit::uninit());
 
-        Decoder::<F, S>::init_from_file_internal(path, config, mem.as_mut_ptr())?;
+        Decoder::<F, S>::init_from_file_internal(path, config.as_raw_ptr(), mem.as_mut_ptr())?;
 
         let inner: *mut sys::ma_decoder = Box::into_raw(mem) as *mut sys::ma_decoder;
-        Ok(Decoder::new(inner, config, config.format, Fs))
+        let metadata = WavMetadata::read_file(path);
+        let origin = Origin::File(path.to_path_buf());
+        Decoder::new(inner, config, config.format, Fs, origin).with_metadata(metadata, config)
     }
 
     fn init_from_reader<R: SeekRead>(
-        reader: R,
+        mut reader: R,
         config: &DecoderBuilder<F>,
     ) -> MaResult<Decoder<F, Cb>> {
+        let metadata = read_reader_metadata(&mut reader)?;
         let mut mem: Box<std::mem::MaybeUninit<sys::ma_decoder>> = Box::new(MaybeUninit::uninit());
 
         let user_data = Box::new(DecoderUserData { reader });
@@ -227,7 +374,8 @@ impl<F: PcmFormat, S> Decoder<F, S> {
         }
 
         let inner: *mut sys::ma_decoder = Box::into_raw(mem) as *mut sys::ma_decoder;
-        let mut decoder = Decoder::new(inner, config, config.format, Cb);
+        let mut decoder = Decoder::new(inner, config, config.format, Cb, Origin::Reader)
+            .with_metadata(metadata, config)?;
         decoder.user_data = Some((user_data_ptr, encoder_user_data_drop::<R>));
 
         Ok(decoder)
@@ -235,7 +383,7 @@ impl<F: PcmFormat, S> Decoder<F, S> {
 
     fn init_from_file_internal(
         path: &Path,
-        config: &DecoderBuilder<F>,
+        config: *const sys::ma_decoder_config,
         decoder: *mut sys::ma_decoder,
     ) -> MaResult<()> {
         #[cfg(unix)]
@@ -243,7 +391,7 @@ impl<F: PcmFormat, S> Decoder<F, S> {
             use crate::engine::cstring_from_path;
 
             let path = cstring_from_path(path)?;
-            decoder_ffi::ma_decoder_init_file(path, config.as_raw_ptr(), decoder)
+            decoder_ffi::ma_decoder_init_file(path, config, decoder)
         }
 
         #[cfg(windows)]
@@ -252,7 +400,7 @@ impl<F: PcmFormat, S> Decoder<F, S> {
 
             let path = wide_null_terminated(path);
 
-            decoder_ffi::ma_decoder_init_file_w(&path, config.as_raw_ptr(), decoder)
+            decoder_ffi::ma_decoder_init_file_w(&path, config, decoder)
         }
 
         #[cfg(not(any(unix, windows)))]
@@ -260,6 +408,83 @@ impl<F: PcmFormat, S> Decoder<F, S> {
     }
 }
 
+/// Basic information about an encoded audio stream, as returned by [`Decoder::probe`].
+///
+/// All values describe the stream as it is stored, before any conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInfo {
+    /// Native sample format of the stream.
+    pub format: Format,
+    /// Number of channels in the stream.
+    pub channels: u32,
+    /// Native sample rate of the stream.
+    pub sample_rate: SampleRate,
+    /// Total length in PCM frames, or `None` if the backend cannot report it.
+    pub length_frames: Option<u64>,
+    /// Total play time, or `None` if the length is unknown.
+    pub duration: Option<Duration>,
+    /// Markers of a WAV file, see [`Decoder::markers`].
+    pub markers: Vec<Marker>,
+    /// Loops of a WAV file, see [`Decoder::sample_loops`].
+    pub loops: Vec<SampleLoop>,
+}
+
+impl AudioInfo {
+    fn from_decoder<F: PcmFormat, S>(decoder: &Decoder<F, S>) -> MaResult<Self> {
+        let data_format = decoder_ffi::ma_decoder_get_data_format(decoder)?;
+        // Some backends report 0 (or fail) when the length is unknown
+        let length_frames = decoder_ffi::ma_decoder_get_length_in_pcm_frames(decoder)
+            .ok()
+            .filter(|len| *len > 0);
+        let rate: u32 = data_format.sample_rate.into();
+        let duration = length_frames
+            .filter(|_| rate > 0)
+            .map(|len| Duration::from_secs_f64(len as f64 / rate as f64));
+
+        Ok(Self {
+            format: data_format.format,
+            channels: data_format.channels,
+            sample_rate: data_format.sample_rate,
+            length_frames,
+            duration,
+            markers: decoder.metadata.markers.clone(),
+            loops: decoder.metadata.loops.clone(),
+        })
+    }
+}
+
+impl Decoder<f32, Fs> {
+    /// Reads the format and length of an audio file without decoding its frames.
+    ///
+    /// The file is opened in its native format, the headers and length are queried,
+    /// and the file is closed again. Useful for validating assets or displaying file
+    /// information cheaply.
+    ///
+    /// For some formats (e.g. MP3) the length is found by scanning the frame headers,
+    /// which still avoids decoding any audio.
+    ///
+    /// ```no_run
+    /// # use maudio::data_source::sources::decoder::Decoder;
+    /// # fn main() -> maudio::MaResult<()> {
+    /// let info = Decoder::probe(std::path::Path::new("music.flac"))?;
+    /// println!("{} ch @ {:?}, {:?}", info.channels, info.sample_rate, info.duration);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn probe(path: &Path) -> MaResult<AudioInfo> {
+        let builder = DecoderBuilder::new_native();
+        let decoder = Decoder::<f32, Fs>::init_file(path, &builder).with_path(path)?;
+        AudioInfo::from_decoder(&decoder)
+    }
+
+    /// Same as [`Decoder::probe`], but reads the encoded audio from memory.
+    pub fn probe_memory(data: &[u8]) -> MaResult<AudioInfo> {
+        let builder = DecoderBuilder::new_native();
+        let decoder = Decoder::<f32, Borrowed>::init_from_memory(data, &builder)?;
+        AudioInfo::from_decoder(&decoder)
+    }
+}
+
 /// Trait alias for types that implement both [`std::io::Read`] and [`std::io::Seek`].
 ///
 /// This is used by [`DecoderBuilder::from_reader`] to accept custom input
@@ -267,6 +492,18 @@ impl<F: PcmFormat, S> Decoder<F, S> {
 pub trait SeekRead: std::io::Read + std::io::Seek {}
 impl<T: std::io::Read + std::io::Seek> SeekRead for T {}
 
+// Reads the WAV metadata, leaving the reader where it was for the decoder.
+// User readers may panic, like in the read and seek callbacks.
+fn read_reader_metadata<R: SeekRead>(reader: &mut R) -> MaResult<WavMetadata> {
+    callback_panic::guard(|| {
+        let start = reader.stream_position()?;
+        let metadata = WavMetadata::read(reader);
+        reader.seek(std::io::SeekFrom::Start(start))?;
+        Ok(metadata)
+    })?
+    .map_err(|_: std::io::Error| MaudioError::from_ma_result(sys::ma_result_MA_IO_ERROR))
+}
+
 struct DecoderUserData<R> {
     reader: R,
 }
@@ -288,16 +525,16 @@ unsafe extern "C" fn decoder_read_proc<R: SeekRead>(
 
     let slice = core::slice::from_raw_parts_mut(buffer_out as _, bytes_to_read);
 
-    match user_data.reader.read(slice) {
+    match callback_panic::guard(|| user_data.reader.read(slice)) {
         // If the number of bytes actually read is less than the number of bytes
         // requested (bytes_to_read), miniaudio will treat
         // it as if the end of the file has been reached
-        Ok(0) => sys::ma_result_MA_AT_END,
-        Ok(n) => {
+        Ok(Ok(0)) => sys::ma_result_MA_AT_END,
+        Ok(Ok(n)) => {
             *bytes_read = n;
             sys::ma_result_MA_SUCCESS
         }
-        Err(_) => sys::ma_result_MA_ERROR,
+        Ok(Err(_)) | Err(_) => sys::ma_result_MA_ERROR,
     }
 }
 
@@ -324,9 +561,9 @@ unsafe extern "C" fn decoder_seek_proc<R: SeekRead>(
         _ => return sys::ma_result_MA_INVALID_ARGS,
     };
 
-    match user_data.reader.seek(pos) {
-        Ok(_) => sys::ma_result_MA_SUCCESS,
-        Err(_) => sys::ma_result_MA_ERROR,
+    match callback_panic::guard(|| user_data.reader.seek(pos)) {
+        Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
+        Ok(Err(_)) | Err(_) => sys::ma_result_MA_ERROR,
     }
 }
 
@@ -422,6 +659,17 @@ pub trait DecoderOps: AsDecoderPtr + AsSourcePtr {
         decoder_ffi::ma_decoder_read_pcm_frames(self, frame_count)
     }
 
+    /// Same as [`DecoderOps::read_pcm_frames`], but reads into `buf`, reusing its
+    /// allocation. Returns the number of frames read.
+    fn read_pcm_frames_into_buffer(
+        &mut self,
+        buf: &mut SampleBuffer<Self::Format>,
+        frame_count: u64,
+    ) -> MaResult<usize> {
+        let channels = self.channels();
+        buf.read_with(frame_count, channels, |dst| self.read_pcm_frames_into(dst))
+    }
+
     /// Seeks to an absolute PCM frame index.
     fn seek_to_pcm_frame(&mut self, frame_index: u64) -> MaResult<()> {
         decoder_ffi::ma_decoder_seek_to_pcm_frame(self, frame_index)
@@ -466,7 +714,7 @@ pub trait DecoderOps: AsDecoderPtr + AsSourcePtr {
 pub(crate) mod decoder_ffi {
     use maudio_sys::ffi as sys;
 
-    use crate::audio::{channels::Channel, formats::SampleBuffer};
+    use crate::audio::{channels::ChannelLayout, formats::SampleBuffer};
     use crate::data_source::{
         sources::decoder::{private_decoder, AsDecoderPtr},
         DataFormat,
@@ -642,9 +890,7 @@ pub(crate) mod decoder_ffi {
         MaudioError::check(res)?;
 
         // Could cast when passing the ptr to miniaudio, but copying should be fine here
-        let mut channel_map: Vec<Channel> =
-            channel_map_raw.into_iter().map(Channel::from_raw).collect();
-        channel_map.truncate(channels as usize);
+        let channel_map = ChannelLayout::from_raw_count(&channel_map_raw, channels)?;
 
         Ok(DataFormat {
             format: format_raw.try_into()?,
@@ -720,6 +966,88 @@ fn encoder_user_data_drop<R: SeekRead>(ptr: *mut core::ffi::c_void) {
     drop(unsafe { Box::from_raw(ptr as *mut DecoderUserData<R>) });
 }
 
+impl<F: PcmFormat, S: Clone> Decoder<F, S> {
+    /// Scans the file once to build a seek table of `points` entries, and returns a new
+    /// decoder using it. Later seeks to any frame of the new decoder are fast and accurate.
+    ///
+    /// This is the same table as [`DecoderBuilder::seek_points`], built after the decoder was
+    /// created, for example in the background once a long podcast started playing. The new
+    /// decoder starts at the cursor of this one, with the same range and loop points.
+    /// This decoder is left untouched, so sounds reading it keep playing; switch them to the
+    /// new decoder when it is ready.
+    ///
+    /// Only MP3 decoding uses the table, Vorbis and FLAC already seek without decoding from
+    /// the start. miniaudio keeps the table inside its MP3 decoder, so it can't be saved and
+    /// must be built again in each session.
+    ///
+    /// Decoders created with [`DecoderBuilder::from_reader`] can't be reopened, and don't
+    /// have this method.
+    pub fn build_seek_index(&self, points: u32) -> MaResult<Self> {
+        let cursor = decoder_ffi::ma_decoder_get_cursor_in_pcm_frames(self)?;
+        let range = data_source_ffi::ma_data_source_get_range_in_pcm_frames(self);
+        let loop_points = data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(self);
+        let looping = data_source_ffi::ma_data_source_is_looping(self);
+
+        let channel_map = self.channel_map.clone();
+        let mut config = self.config;
+        config.seekPointCount = points;
+        if let Some(map) = &channel_map {
+            config.pChannelMap = map.as_raw_ptr() as *mut _;
+        }
+        let mut mem: Box<MaybeUninit<sys::ma_decoder>> = Box::new(MaybeUninit::uninit());
+        let origin = match &self.origin {
+            Origin::Memory(data, len) => {
+                decoder_ffi::ma_decoder_init_memory(
+                    *data as *const _,
+                    *len,
+                    &config,
+                    mem.as_mut_ptr(),
+                )?;
+                // The new decoder keeps the data alive with its clone of the source data
+                Origin::Memory(*data, *len)
+            }
+            Origin::File(path) => {
+                Decoder::<F, S>::init_from_file_internal(path, &config, mem.as_mut_ptr())
+                    .with_path(path)?;
+                Origin::File(path.clone())
+            }
+            Origin::Reader => {
+                return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                    "build_seek_index needs a decoder created from memory or a file",
+                )))
+            }
+        };
+
+        let mut decoder = Self {
+            inner: Box::into_raw(mem) as *mut sys::ma_decoder,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            format: self.format,
+            user_data: None,
+            _sample_format: PhantomData,
+            source_data: self.source_data.clone(),
+            metadata: self.metadata.clone(),
+            seek_index: None,
+            config,
+            channel_map,
+            origin,
+        };
+        data_source_ffi::ma_data_source_set_range_in_pcm_frames(
+            &mut decoder,
+            range.start,
+            range.end,
+        )?;
+        data_source_ffi::ma_data_source_set_loop_point_in_pcm_frames(
+            &mut decoder,
+            loop_points.start,
+            loop_points.end,
+        )?;
+        data_source_ffi::ma_data_source_set_looping(&mut decoder, looping)?;
+        decoder_ffi::ma_decoder_seek_to_pcm_frame(&mut decoder, cursor)?;
+        Ok(decoder)
+    }
+}
+
 impl<F: PcmFormat, S> Drop for Decoder<F, S> {
     fn drop(&mut self) {
         let _ = decoder_ffi::ma_decoder_uninit(self);
@@ -735,6 +1063,8 @@ pub struct DecoderBuilder<F = Unknown> {
     format: Format,
     channels: u32,
     sample_rate: SampleRate,
+    channel_map: Option<ChannelLayout>,
+    file_loop_points: bool,
     _format: PhantomData<F>,
 }
 
@@ -755,6 +1085,22 @@ impl DecoderBuilder<Unknown> {
         unsafe { sys::ma_decoder_config_init(format.into(), out_channels, out_sample_rate.into()) }
     }
 
+    /// Config that keeps the native format, channels and sample rate of the stream.
+    ///
+    /// Only used for probing, the decoder is never read from.
+    fn new_native() -> DecoderBuilder<f32> {
+        let inner = unsafe { sys::ma_decoder_config_init(sys::ma_format_ma_format_unknown, 0, 0) };
+        DecoderBuilder {
+            inner,
+            format: Format::F32,
+            channels: 0,
+            sample_rate: SampleRate::Custom(0),
+            channel_map: None,
+            file_loop_points: false,
+            _format: PhantomData,
+        }
+    }
+
     pub fn new_u8(out_channels: u32, out_sample_rate: SampleRate) -> DecoderBuilder<u8> {
         let inner = DecoderBuilder::new_inner(out_channels, out_sample_rate, Format::U8);
         DecoderBuilder {
@@ -762,6 +1108,8 @@ impl DecoderBuilder<Unknown> {
             format: Format::U8,
             channels: out_channels,
             sample_rate: out_sample_rate,
+            channel_map: None,
+            file_loop_points: false,
             _format: PhantomData,
         }
     }
@@ -773,6 +1121,8 @@ impl DecoderBuilder<Unknown> {
             format: Format::S16,
             channels: out_channels,
             sample_rate: out_sample_rate,
+            channel_map: None,
+            file_loop_points: false,
             _format: PhantomData,
         }
     }
@@ -784,6 +1134,8 @@ impl DecoderBuilder<Unknown> {
             format: Format::S32,
             channels: out_channels,
             sample_rate: out_sample_rate,
+            channel_map: None,
+            file_loop_points: false,
             _format: PhantomData,
         }
     }
@@ -798,6 +1150,8 @@ impl DecoderBuilder<Unknown> {
             format: Format::S24Packed,
             channels: out_channels,
             sample_rate: out_sample_rate,
+            channel_map: None,
+            file_loop_points: false,
             _format: PhantomData,
         }
     }
@@ -809,12 +1163,62 @@ impl DecoderBuilder<Unknown> {
             format: Format::F32,
             channels: out_channels,
             sample_rate: out_sample_rate,
+            channel_map: None,
+            file_loop_points: false,
             _format: PhantomData,
         }
     }
 }
 
 impl<F: PcmFormat> DecoderBuilder<F> {
+    /// Registers a custom [`DecodingBackend`], turning this into a [`CustomDecoderBuilder`]
+    /// with the same output format.
+    ///
+    /// More backends can be added with [`CustomDecoderBuilder::backend`]. Data that none of
+    /// the custom backends accept is decoded by miniaudio's built-in decoders.
+    pub fn backend<B: DecodingBackend<Format = F>>(&self) -> CustomDecoderBuilder<F> {
+        let mut builder = CustomDecoderBuilder::with_output(self.channels, self.sample_rate);
+        if let Some(map) = self.channel_map.clone() {
+            builder.output_channel_map(map);
+        }
+        builder.backend::<B>();
+        builder
+    }
+
+    /// Sets the position of each output channel.
+    ///
+    /// Also updates the output channel count to match the map. Decoded audio is converted
+    /// to this layout.
+    pub fn output_channel_map(&mut self, map: ChannelLayout) -> &mut Self {
+        self.channels = map.channels();
+        self.inner.channels = map.channels();
+        // The map is heap allocated, the pointer stays valid when the builder moves
+        self.inner.pChannelMap = map.as_raw_ptr() as *mut _;
+        self.channel_map = Some(map);
+        self
+    }
+
+    /// Uses the first loop stored in the `smpl` chunk of a WAV file as the decoder's loop
+    /// points, in output frames.
+    ///
+    /// Loop points only apply when the decoder is read as a data source, for example by a
+    /// sound, and looping still has to be enabled on it. Files without a loop keep the
+    /// default loop points. See [`Decoder::sample_loops`].
+    pub fn file_loop_points(&mut self, yes: bool) -> &mut Self {
+        self.file_loop_points = yes;
+        self
+    }
+
+    /// Builds a seek table of `count` entries when the decoder is created.
+    ///
+    /// Only MP3 decoding uses the table. It costs a scan of the file on creation, and makes
+    /// seeking much faster on long files. `0`, the default, builds no table. Also sets the
+    /// entries [`SeekMode::Fast`] lands on, see [`seek`].
+    pub fn seek_points(&mut self, count: u32) -> &mut Self {
+        self.inner.seekPointCount = count;
+        self
+    }
+
     /// Creates a decoder from borrowed in-memory audio data.
     ///
     /// This uses `ma_decoder_init_memory`.
@@ -847,7 +1251,16 @@ impl<F: PcmFormat> DecoderBuilder<F> {
     /// This is usually the most convenient option when decoding from a normal
     /// file on disk.
     pub fn from_file(&self, path: &Path) -> MaResult<Decoder<F, Fs>> {
-        Decoder::<F, Fs>::init_file(path, self)
+        Decoder::<F, Fs>::init_file(path, self).with_path(path)
+    }
+
+    /// Creates a decoder from a memory-mapped file.
+    ///
+    /// Like [`DecoderBuilder::copy_memory`], the decoder keeps the data alive, but the file
+    /// is never copied into memory. The decoder holds a clone of the mapping.
+    #[cfg(feature = "memmap2")]
+    pub fn from_mapped(&self, file: &MappedFile) -> MaResult<Decoder<F, Mapped>> {
+        Decoder::<F, Mapped>::init_mapped(file, self)
     }
 
     /// Creates a decoder from a custom Rust reader.
@@ -883,8 +1296,9 @@ impl<F: PcmFormat> DecoderBuilder<F> {
 #[cfg(test)]
 mod tests {
     use crate::test_assets::{
+        silent_mp3,
         temp_file::{unique_tmp_path, TempFileGuard},
-        wav_i16_le,
+        wav_i16_le, wav_with_metadata,
     };
 
     use super::*;
@@ -897,6 +1311,95 @@ mod tests {
         wav_i16_le(1, SampleRate::Sr48000, &samples)
     }
 
+    #[test]
+    fn test_decoder_seek_to_time() {
+        let wav = tiny_test_wav_mono(96_000);
+        let mut dec = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .seek_points(4)
+            .copy_memory(wav)
+            .unwrap();
+
+        dec.seek_to_time(Duration::from_millis(700)).unwrap();
+        assert_eq!(dec.cursor_pcm().unwrap(), 33_600);
+        dec.seek_to_time(Duration::from_micros(10)).unwrap();
+        assert_eq!(dec.cursor_pcm().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_decoder_seek_fast_lands_on_mp3_seek_point() {
+        // 100 MP3 frames of 1152 samples, the table splits them in 5 parts of 23040 frames
+        let mp3 = silent_mp3(100);
+        let mut dec = DecoderBuilder::new_f32(1, SampleRate::Sr44100)
+            .seek_points(4)
+            .copy_memory(mp3)
+            .unwrap();
+
+        dec.seek_to_time_with(Duration::from_millis(1200), SeekMode::Fast)
+            .unwrap();
+        assert_eq!(dec.cursor_pcm().unwrap(), 46_080);
+        // Before the first entry, the start of the stream
+        dec.seek_to_time_with(Duration::from_millis(300), SeekMode::Fast)
+            .unwrap();
+        assert_eq!(dec.cursor_pcm().unwrap(), 0);
+
+        dec.seek_to_time_with(Duration::from_millis(1200), SeekMode::Accurate)
+            .unwrap();
+        assert_eq!(dec.cursor_pcm().unwrap(), 52_920);
+    }
+
+    #[test]
+    fn test_decoder_seek_fast_without_seek_table_is_accurate() {
+        let mut mp3 = DecoderBuilder::new_f32(1, SampleRate::Sr44100)
+            .copy_memory(silent_mp3(100))
+            .unwrap();
+        mp3.seek_to_time_with(Duration::from_millis(1200), SeekMode::Fast)
+            .unwrap();
+        assert_eq!(mp3.cursor_pcm().unwrap(), 52_920);
+
+        // Only MP3 has a seek table
+        let mut wav = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .seek_points(4)
+            .copy_memory(tiny_test_wav_mono(96_000))
+            .unwrap();
+        wav.seek_to_time_with(Duration::from_millis(700), SeekMode::Fast)
+            .unwrap();
+        assert_eq!(wav.cursor_pcm().unwrap(), 33_600);
+    }
+
+    #[test]
+    fn test_decoder_build_seek_index_keeps_state() {
+        let wav = tiny_test_wav_mono(4_800);
+        let mut dec = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .copy_memory(wav.clone())
+            .unwrap();
+        let mut reference = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .copy_memory(wav)
+            .unwrap();
+        data_source_ffi::ma_data_source_set_loop_point_in_pcm_frames(&mut dec, 100, 2_000).unwrap();
+        dec.seek_to_pcm_frame(1_000).unwrap();
+        reference.seek_to_pcm_frame(1_000).unwrap();
+
+        let before = dec.to_raw();
+        let mut indexed = dec.build_seek_index(16).unwrap();
+        // The previous decoder is untouched
+        assert_eq!(dec.to_raw(), before);
+        assert_eq!(dec.cursor_pcm().unwrap(), 1_000);
+
+        assert_eq!(indexed.cursor_pcm().unwrap(), 1_000);
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&indexed),
+            100..2_000
+        );
+        let read = indexed.read_pcm_frames(64).unwrap();
+        assert_eq!(
+            read.as_ref(),
+            reference.read_pcm_frames(64).unwrap().as_ref()
+        );
+        // The source data is shared, and outlives the previous decoder
+        drop(dec);
+        assert_eq!(indexed.read_pcm_frames(64).unwrap().frames(), 64);
+    }
+
     #[test]
     fn test_decoder_from_memory_f32_read_seek_cursor_length_available() {
         let frames_total: usize = 64;
@@ -940,6 +1443,24 @@ mod tests {
         assert_eq!(dec.cursor_pcm().unwrap(), 7);
     }
 
+    #[test]
+    fn test_decoder_output_channel_map() {
+        let wav = tiny_test_wav_mono(32);
+
+        let mut builder = DecoderBuilder::new_f32(1, SampleRate::Sr48000);
+        builder.output_channel_map(ChannelLayout::stereo());
+        let mut dec = builder.from_memory(&wav).unwrap();
+
+        let df = dec.data_format().unwrap();
+        assert_eq!(df.channels, 2);
+
+        // Mono is copied to both channels
+        let buf = dec.read_pcm_frames(8).unwrap();
+        assert_eq!(buf.len(), 16);
+        let samples = buf.as_ref();
+        assert!(samples.chunks_exact(2).all(|f| f[0] == f[1]));
+    }
+
     #[test]
     fn test_decoder_ref_from_memory_decodes() {
         let frames_total: usize = 32;
@@ -974,6 +1495,130 @@ mod tests {
         assert_eq!(buf.frames(), frames_total);
     }
 
+    #[test]
+    fn test_decoder_probe_file() {
+        let samples = vec![0i16; 2 * 4800];
+        let wav = wav_i16_le(2, SampleRate::Sr48000, &samples);
+
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(guard.path(), &wav).unwrap();
+
+        let info = Decoder::probe(guard.path()).unwrap();
+        assert_eq!(info.format, Format::S16);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, SampleRate::Sr48000);
+        assert_eq!(info.length_frames, Some(4800));
+        assert_eq!(info.duration, Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_decoder_probe_memory_keeps_native_format() {
+        let wav = wav_i16_le(1, SampleRate::Sr22050, &[0i16; 100]);
+
+        let info = Decoder::probe_memory(&wav).unwrap();
+        assert_eq!(info.format, Format::S16);
+        assert_eq!(info.channels, 1);
+        assert_eq!(info.sample_rate, SampleRate::Sr22050);
+        assert_eq!(info.length_frames, Some(100));
+    }
+
+    #[test]
+    fn test_decoder_from_file_error_names_path() {
+        let path = Path::new("does/not/exist.wav");
+        let Err(err) = DecoderBuilder::new_f32(2, SampleRate::Sr48000).from_file(path) else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            err.context(),
+            Some(&crate::ErrorContext::Path(path.to_path_buf()))
+        );
+    }
+
+    #[test]
+    fn test_decoder_reads_wav_markers() {
+        let wav = wav_with_metadata(
+            &wav_i16_le(1, SampleRate::Sr24000, &[0i16; 1000]),
+            &[(7, 300, Some("drop")), (3, 100, None)],
+            &[(1, 200, 599)],
+        );
+
+        let info = Decoder::probe_memory(&wav).unwrap();
+        assert_eq!(info.markers.len(), 2);
+        assert_eq!(info.markers[0].position, 100);
+        assert_eq!(info.markers[1].label.as_deref(), Some("drop"));
+        assert_eq!(info.loops[0].end, 600);
+
+        // Positions follow the decoder's output rate
+        let dec = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .from_memory(&wav)
+            .unwrap();
+        assert_eq!(dec.markers()[1].position, 600);
+        assert_eq!(dec.sample_loops()[0].start, 400);
+
+        let dec = DecoderBuilder::new_f32(1, SampleRate::Sr24000)
+            .from_reader(std::io::Cursor::new(wav.clone()))
+            .unwrap();
+        assert_eq!(dec.markers(), info.markers.as_slice());
+        let plain = tiny_test_wav_mono(16);
+        let dec = DecoderBuilder::new_f32(1, SampleRate::Sr24000)
+            .from_memory(&plain)
+            .unwrap();
+        assert!(dec.markers().is_empty());
+    }
+
+    #[test]
+    fn test_decoder_file_loop_points() {
+        let wav = wav_with_metadata(
+            &wav_i16_le(1, SampleRate::Sr24000, &[0i16; 1000]),
+            &[],
+            &[(1, 100, 399)],
+        );
+
+        let dec = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .file_loop_points(true)
+            .from_memory(&wav)
+            .unwrap();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&dec),
+            200..800
+        );
+
+        let dec = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .from_memory(&wav)
+            .unwrap();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&dec),
+            0..u64::MAX
+        );
+    }
+
+    #[test]
+    fn test_decoder_probe_rejects_invalid_data() {
+        assert!(Decoder::probe_memory(&[0u8; 64]).is_err());
+        assert!(Decoder::probe(Path::new("does/not/exist.wav")).is_err());
+    }
+
+    #[test]
+    fn test_decoder_reader_panic_is_caught() {
+        struct PanicReader;
+        impl std::io::Read for PanicReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                panic!("reader panicked");
+            }
+        }
+        impl std::io::Seek for PanicReader {
+            fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                panic!("reader panicked");
+            }
+        }
+
+        let before = crate::util::callback_panic::panic_count();
+        assert!(DecoderBuilder::new_f32(2, SampleRate::Sr48000)
+            .from_reader(PanicReader)
+            .is_err());
+        assert!(crate::util::callback_panic::panic_count() > before);
+    }
+
     #[test]
     fn test_decoder_read_u8_memory() {
         let frames_total: usize = 16;
@@ -1,7 +1,7 @@
 use std::{marker::PhantomData, mem::MaybeUninit, path::Path, sync::Arc};
 
 use crate::{
-    audio::{channels::Channel, formats::Format, sample_rate::SampleRate},
+    audio::{channels::ChannelLayout, formats::Format, sample_rate::SampleRate},
     data_source::{
         data_source_ffi, private_data_source,
         sources::decoder::{
@@ -14,7 +14,7 @@ use crate::{
     },
     device::device_builder::Unknown,
     pcm_frames::{PcmFormat, S24Packed},
-    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
+    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError, ResultContext,
 };
 
 use maudio_sys::ffi as sys;
@@ -26,7 +26,7 @@ pub struct CustomDecoder<F: PcmFormat, S> {
     sample_rate: SampleRate,
     format: Format,
     user_data: Option<DecoderUserDataDestructor>,
-    backend_reg: *mut BackendRegistration<F>,
+    backend_reg: *mut BackendRegistration,
     _source_data: S,                                                 // keep alive
     _decoder_vtables: Box<[*const sys::ma_decoding_backend_vtable]>, // keep alive
     _sample_format: PhantomData<F>,
@@ -39,15 +39,11 @@ unsafe impl<F: PcmFormat, S> Send for CustomDecoder<F, S> {}
 /// This object contains any data / config that can be used inside the init function
 /// to create the data source and (potentially) as config for the `DecodingBackend` trait
 ///
-/// The information in this object will be available to all the vtables (backends)
-pub(crate) struct BackendRegistration<F>
-where
-    F: PcmFormat,
-{
+/// The information in this object will be available to all the vtables (backends).
+/// It does not depend on the sample format, so backends with different formats can share it.
+pub(crate) struct BackendRegistration {
     pub(crate) channels: u32,
     pub(crate) sample_rate: SampleRate,
-    pub(crate) format: Format,
-    _format: PhantomData<F>,
 }
 
 /// The Data Source create inside the onInit
@@ -126,7 +122,7 @@ impl<F: PcmFormat, S> CustomDecoder<F, S> {
         config: &CustomDecoderBuilder<F>,
         format: Format,
         vtables: Box<[*const sys::ma_decoding_backend_vtable]>,
-        reg: *mut BackendRegistration<F>,
+        reg: *mut BackendRegistration,
         source_data: S,
     ) -> Self {
         Self {
@@ -280,8 +276,7 @@ pub struct CustomDecoderBuilder<F = Unknown> {
     sample_rate: SampleRate,
     channels: u32,
     format: Format,
-    #[allow(unused)]
-    channel_map: Option<Vec<Channel>>,
+    channel_map: Option<ChannelLayout>,
     // user_data: Option<U>,
     _format: PhantomData<F>,
 }
@@ -379,12 +374,24 @@ impl CustomDecoderBuilder<Unknown> {
 }
 
 impl<F: PcmFormat> CustomDecoderBuilder<F> {
-    fn set_backend_registration(&mut self) -> *mut BackendRegistration<F> {
-        let registration: BackendRegistration<F> = BackendRegistration {
+    pub(crate) fn with_output(out_channels: u32, out_sample_rate: SampleRate) -> Self {
+        let inner = CustomDecoderBuilder::new_inner(out_channels, out_sample_rate, F::FORMAT);
+        CustomDecoderBuilder {
+            inner,
+            vtables: Vec::new(),
+            sample_rate: out_sample_rate,
+            channels: out_channels,
+            format: F::FORMAT,
+            channel_map: None,
+            // user_data: None,
+            _format: PhantomData,
+        }
+    }
+
+    fn set_backend_registration(&mut self) -> *mut BackendRegistration {
+        let registration = BackendRegistration {
             channels: self.channels,
             sample_rate: self.sample_rate,
-            format: self.format,
-            _format: PhantomData,
         };
         let ptr = Box::into_raw(Box::new(registration));
         self.inner.pCustomBackendUserData = ptr.cast();
@@ -415,6 +422,18 @@ impl<F: PcmFormat> CustomDecoderBuilder<F> {
         self
     }
 
+    /// Sets the position of each output channel.
+    ///
+    /// Also updates the output channel count to match the map.
+    pub fn output_channel_map(&mut self, map: ChannelLayout) -> &mut Self {
+        self.channels = map.channels();
+        self.inner.channels = map.channels();
+        // The map is heap allocated, the pointer stays valid when the builder moves
+        self.inner.pChannelMap = map.as_raw_ptr() as *mut _;
+        self.channel_map = Some(map);
+        self
+    }
+
     /// Creates a decoder from borrowed in-memory audio data.
     ///
     /// This uses `ma_decoder_init_memory`.
@@ -450,7 +469,7 @@ impl<F: PcmFormat> CustomDecoderBuilder<F> {
     /// This is usually the most convenient option when decoding from a normal
     /// file on disk.
     pub fn from_file(&mut self, path: &Path) -> MaResult<CustomDecoder<F, Fs>> {
-        CustomDecoder::<F, Fs>::init_file(path, self)
+        CustomDecoder::<F, Fs>::init_file(path, self).with_path(path)
     }
 
     /// Creates a decoder from a custom Rust reader.
@@ -510,9 +529,14 @@ mod test {
             sources::decoder::{
                 decoding_backend::DecodingBackend, Cb, Decoder, DecoderBuilder, DecoderOps,
             },
+            DataSourceOps,
+        },
+        engine::resource::{
+            rm_builder::ResourceManagerBuilder, rm_source_flags::RmSourceFlags, RmOps,
         },
         MaResult,
     };
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     use super::*;
 
@@ -579,6 +603,75 @@ mod test {
         }
     }
 
+    static COUNTING_INITS: AtomicUsize = AtomicUsize::new(0);
+
+    // Same as `TestCbDecoder`, but records how many times miniaudio used it
+    struct CountingBackend;
+
+    impl DecodingBackend for CountingBackend {
+        type Format = f32;
+
+        type Decoder = TestCbDecoder;
+
+        fn init_decoder<R: std::io::prelude::Read + std::io::prelude::Seek>(
+            stream: R,
+        ) -> MaResult<Self::Decoder> {
+            COUNTING_INITS.fetch_add(1, Ordering::SeqCst);
+            TestCbDecoder::init_decoder(stream)
+        }
+    }
+
+    #[test]
+    fn test_custom_decoder_from_decoder_builder() {
+        let frames_total: usize = 24;
+        let wav = tiny_test_wav_mono(frames_total);
+
+        let mut dec = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .backend::<TestCbDecoder>()
+            .from_memory(&wav)
+            .unwrap();
+
+        assert_eq!(dec.data_format().unwrap().format, Format::F32);
+        assert_eq!(dec.read_pcm_frames(100).unwrap().frames(), frames_total);
+    }
+
+    #[test]
+    fn test_custom_decoder_resource_manager_backend() {
+        let frames_total: usize = 48;
+        let wav = tiny_test_wav_mono(frames_total);
+
+        let rm = ResourceManagerBuilder::new()
+            .channels(1)
+            .sample_rate(SampleRate::Sr48000)
+            .decoding_backend::<CountingBackend>()
+            .build_f32()
+            .unwrap();
+
+        let before = COUNTING_INITS.load(Ordering::SeqCst);
+        let guard = rm.register_encoded("custom:wav", &wav).unwrap();
+        let mut buf = guard
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .unwrap();
+
+        assert!(COUNTING_INITS.load(Ordering::SeqCst) > before);
+        assert_eq!(
+            DataSourceOps::read_pcm_frames(&mut buf, 100)
+                .unwrap()
+                .frames(),
+            frames_total
+        );
+    }
+
+    #[test]
+    fn test_custom_decoder_resource_manager_backend_requires_format() {
+        let res = ResourceManagerBuilder::new()
+            .decoding_backend::<CountingBackend>()
+            .build_f32();
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_custom_decoder_from_memory_f32_read_seek_cursor_length_available() {
         let frames_total: usize = 64;
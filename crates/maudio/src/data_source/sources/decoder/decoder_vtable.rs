@@ -4,7 +4,6 @@ use std::{
     io::{Cursor, Read, Seek},
     marker::PhantomData,
     mem::MaybeUninit,
-    panic::AssertUnwindSafe,
     path::PathBuf,
 };
 
@@ -24,6 +23,7 @@ use crate::{
         DataFormat, SourceContext,
     },
     pcm_frames::PcmFormat,
+    util::callback_panic,
     MaResult, MaudioError,
 };
 
@@ -68,10 +68,8 @@ unsafe extern "C" fn decoder_on_init<F: PcmFormat, D: DecodingBackend<Format = F
         backend.write(core::ptr::null_mut());
     }
 
-    let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
-        // Keep the registratio, but remove the backend
-        // Also, the vtable should be used?
-        let registration = unsafe { &mut *backend_user_data.cast::<BackendRegistration<F>>() };
+    let res = callback_panic::guard(|| {
+        let registration = unsafe { &*backend_user_data.cast::<BackendRegistration>() };
 
         let decoder_stream = DecoderStream {
             on_read,
@@ -87,7 +85,7 @@ unsafe extern "C" fn decoder_on_init<F: PcmFormat, D: DecodingBackend<Format = F
         backend.write(inner_ptr.cast::<sys::ma_data_source>());
 
         Ok::<_, MaudioError>(())
-    }));
+    });
 
     match res {
         Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
@@ -112,7 +110,7 @@ unsafe extern "C" fn decoder_on_init_file<F: PcmFormat, D: DecodingBackend<Forma
         backend.write(core::ptr::null_mut());
     }
 
-    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+    let result = callback_panic::guard(|| {
         let path = unsafe { CStr::from_ptr(path) };
         let path = PathBuf::from(path.to_str().unwrap_or(""));
 
@@ -120,8 +118,8 @@ unsafe extern "C" fn decoder_on_init_file<F: PcmFormat, D: DecodingBackend<Forma
 
         let decoder_stream = DecoderFileStream { file };
 
-        let registration: &BackendRegistration<F> =
-            unsafe { &*backend_user_data.cast::<BackendRegistration<F>>() };
+        let registration: &BackendRegistration =
+            unsafe { &*backend_user_data.cast::<BackendRegistration>() };
 
         let inner_ptr =
             create_data_source::<F, D, DecoderFileStream>(decoder_stream, registration)?;
@@ -129,7 +127,7 @@ unsafe extern "C" fn decoder_on_init_file<F: PcmFormat, D: DecodingBackend<Forma
         backend.write(inner_ptr.cast::<sys::ma_data_source>());
 
         Ok::<_, MaudioError>(())
-    }));
+    });
 
     match result {
         Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
@@ -158,7 +156,7 @@ unsafe extern "C" fn decoder_on_init_file_w<F: PcmFormat, D: DecodingBackend<For
         backend.write(core::ptr::null_mut());
     }
 
-    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+    let result = callback_panic::guard(|| {
         let mut len = 0;
         while unsafe { *path.add(len) } != 0 {
             len += 1;
@@ -171,8 +169,8 @@ unsafe extern "C" fn decoder_on_init_file_w<F: PcmFormat, D: DecodingBackend<For
 
         let decoder_stream = DecoderFileStream { file };
 
-        let registration: &BackendRegistration<F> =
-            unsafe { &*backend_user_data.cast::<BackendRegistration<F>>() };
+        let registration: &BackendRegistration =
+            unsafe { &*backend_user_data.cast::<BackendRegistration>() };
 
         let inner_ptr =
             create_data_source::<F, D, DecoderFileStream>(decoder_stream, registration)?;
@@ -180,7 +178,7 @@ unsafe extern "C" fn decoder_on_init_file_w<F: PcmFormat, D: DecodingBackend<For
         backend.write(inner_ptr.cast::<sys::ma_data_source>());
 
         Ok::<_, MaudioError>(())
-    }));
+    });
 
     match result {
         Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
@@ -206,7 +204,7 @@ unsafe extern "C" fn decoder_on_init_memory<F: PcmFormat, D: DecodingBackend<For
         backend.write(core::ptr::null_mut());
     }
 
-    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+    let result = callback_panic::guard(|| {
         // This slice is kept alive by the `CustomDecoder`
         // struct created by `CustomDecoderBuilder::from_memory`
         let slice: &[u8] = std::slice::from_raw_parts(data.cast(), data_size);
@@ -215,8 +213,8 @@ unsafe extern "C" fn decoder_on_init_memory<F: PcmFormat, D: DecodingBackend<For
             bytes: Cursor::new(slice),
         };
 
-        let registration: &BackendRegistration<F> =
-            unsafe { &*backend_user_data.cast::<BackendRegistration<F>>() };
+        let registration: &BackendRegistration =
+            unsafe { &*backend_user_data.cast::<BackendRegistration>() };
 
         let inner_ptr =
             create_data_source::<F, D, DecoderByteStream>(decoder_stream, registration)?;
@@ -224,7 +222,7 @@ unsafe extern "C" fn decoder_on_init_memory<F: PcmFormat, D: DecodingBackend<For
         backend.write(inner_ptr.cast::<sys::ma_data_source>());
 
         Ok::<_, MaudioError>(())
-    }));
+    });
 
     match result {
         Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
@@ -243,7 +241,7 @@ unsafe extern "C" fn decoder_on_uninit<F: PcmFormat, D: DecodingBackend<Format =
 
 fn create_data_source<F: PcmFormat, D: DecodingBackend<Format = F>, R: Read + Seek>(
     decoder_stream: R,
-    registration: &BackendRegistration<F>,
+    registration: &BackendRegistration,
 ) -> MaResult<*mut BackendDataSource<F, D>> {
     let decoder = D::init_decoder(decoder_stream)?;
 
@@ -253,7 +251,7 @@ fn create_data_source<F: PcmFormat, D: DecodingBackend<Format = F>, R: Read + Se
 
     let src_ctx = SourceContext {
         data_format: DataFormat {
-            format: registration.format,
+            format: F::FORMAT,
             channels: registration.channels,
             sample_rate: registration.sample_rate,
             channel_map: None,
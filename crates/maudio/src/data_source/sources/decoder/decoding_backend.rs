@@ -4,18 +4,94 @@ use std::{
     io::{Cursor, SeekFrom},
 };
 
-use crate::{data_source::pcm_source::PcmSource, pcm_frames::PcmFormat, MaResult};
+use crate::{
+    audio::sample_rate::SampleRate,
+    data_source::{
+        pcm_source::PcmSource,
+        sources::decoder::{custom_decoder::BackendRegistration, decoder_vtable::decoder_vtable},
+    },
+    pcm_frames::PcmFormat,
+    MaResult,
+};
 
 use maudio_sys::ffi as sys;
 
+/// A decoder implemented in Rust, that miniaudio can use alongside its built-in decoders.
+///
+/// A backend is registered by type, either on a
+/// [`CustomDecoderBuilder`](crate::data_source::sources::decoder::custom_decoder::CustomDecoderBuilder)
+/// (or [`DecoderBuilder::backend`](crate::data_source::sources::decoder::DecoderBuilder::backend))
+/// or on a [`ResourceManagerBuilder`](crate::engine::resource::rm_builder::ResourceManagerBuilder).
+///
+/// When miniaudio opens some encoded data, it tries the custom backends first, in the order
+/// they were registered. A backend rejects data it cannot decode by returning an error from
+/// [`DecodingBackend::init_decoder`], after which the next backend (or miniaudio's own) is tried.
+///
+/// The decoder must produce frames with the channel count and sample rate that the
+/// builder it was registered on was configured with. Reading, seeking and the length are
+/// handled by the [`PcmSource`] implementation of [`DecodingBackend::Decoder`].
 pub trait DecodingBackend: Send + 'static {
     type Format: PcmFormat;
 
     type Decoder: PcmSource<Self::Format>;
 
+    /// Creates a decoder that reads the encoded data from `stream`.
     fn init_decoder<R: Read + Seek>(stream: R) -> MaResult<Self::Decoder>;
 }
 
+pub(crate) type BackendVTableFn = fn() -> *const sys::ma_decoding_backend_vtable;
+
+/// Returns the function that allocates the miniaudio vtable for `B`.
+///
+/// The vtable is only allocated once the owner that frees it is created.
+pub(crate) fn backend_vtable_fn<B: DecodingBackend>() -> BackendVTableFn {
+    decoder_vtable::<B::Format, B>
+}
+
+/// Custom backends registered with a resource manager.
+///
+/// miniaudio only copies the array of vtable pointers, so the vtables and the
+/// shared registration must outlive the resource manager.
+#[derive(Debug)]
+pub(crate) struct RegisteredBackends {
+    vtables: Box<[*const sys::ma_decoding_backend_vtable]>,
+    registration: *mut BackendRegistration,
+}
+
+impl RegisteredBackends {
+    pub(crate) fn new(
+        backends: &[BackendVTableFn],
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> Self {
+        let vtables = backends.iter().map(|init| init()).collect();
+        let registration = Box::into_raw(Box::new(BackendRegistration {
+            channels,
+            sample_rate,
+        }));
+        Self {
+            vtables,
+            registration,
+        }
+    }
+
+    pub(crate) fn apply(&mut self, config: &mut sys::ma_resource_manager_config) {
+        config.ppCustomDecodingBackendVTables =
+            self.vtables.as_mut_ptr() as *mut *mut sys::ma_decoding_backend_vtable;
+        config.customDecodingBackendCount = self.vtables.len() as u32;
+        config.pCustomDecodingBackendUserData = self.registration.cast();
+    }
+}
+
+impl Drop for RegisteredBackends {
+    fn drop(&mut self) {
+        for vtable in self.vtables.iter() {
+            drop(unsafe { Box::from_raw(*vtable as *mut sys::ma_decoding_backend_vtable) });
+        }
+        drop(unsafe { Box::from_raw(self.registration) });
+    }
+}
+
 pub(crate) struct DecoderStream {
     pub(crate) on_read: sys::ma_read_proc,
     pub(crate) on_seek: sys::ma_seek_proc,
@@ -92,7 +168,6 @@ impl std::io::Seek for DecoderStream {
         // fseek only returns sucess or fail.
 
         let on_tell = self.on_tell.ok_or_else(|| {
-            println!("on_tell called and failed");
             std::io::Error::new(
                 std::io::ErrorKind::Unsupported,
                 "decoder stream has no callback for cursor position",
@@ -109,7 +184,6 @@ impl std::io::Seek for DecoderStream {
             ));
         }
 
-        println!("seek ran correctly: {cursor}");
         u64::try_from(cursor).map_err(|_| {
             std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
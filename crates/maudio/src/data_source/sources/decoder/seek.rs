@@ -0,0 +1,113 @@
+//! Time based seeking for [`Decoder`](super::Decoder).
+//!
+//! Seeking to an exact frame is cheap for PCM formats like WAV, but MP3 has no frame index.
+//! Without a seek table, miniaudio finds an MP3 frame by decoding from the start of the file
+//! (or from the cursor, when seeking forward), which takes a while on long files.
+//!
+//! [`DecoderBuilder::seek_points`](super::DecoderBuilder::seek_points) asks miniaudio to
+//! build a seek table when the decoder is created, and
+//! [`Decoder::build_seek_index`](super::Decoder::build_seek_index) builds one later. MP3
+//! seeks then start from the closest table entry instead of the start of the file, and
+//! still land on the exact frame.
+//!
+//! [`SeekMode::Fast`] goes one step further and lands on the table entry itself, so only the
+//! few frames miniaudio needs to prime the decoder are thrown away. The entries are read
+//! from miniaudio on the first fast seek. Without a table, fast seeks are accurate.
+use std::time::Duration;
+
+use maudio_sys::{ext as sys_ext, ffi as sys};
+
+use crate::{MaResult, MaudioError};
+
+/// How [`Decoder::seek_to_time_with`](super::Decoder::seek_to_time_with) picks the frame
+/// it seeks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekMode {
+    /// Seeks to the frame closest to the target.
+    Accurate,
+    /// Seeks to the last seek table entry at or before the target, or the start of the
+    /// stream when the target comes before the first entry. Seeks accurately if the decoder
+    /// has no seek table.
+    Fast,
+}
+
+/// The entries of a decoder's MP3 seek table, in output frames.
+pub(crate) struct SeekIndex {
+    frames: Vec<u64>,
+}
+
+impl SeekIndex {
+    /// Reads the seek table of `decoder`, which outputs frames at `sample_rate`.
+    pub(crate) fn read(decoder: *const sys::ma_decoder, sample_rate: u32) -> Self {
+        let mut native_rate = 0;
+        let count = unsafe {
+            sys_ext::maudio_decoder_mp3_seek_points(
+                decoder,
+                core::ptr::null_mut(),
+                0,
+                &mut native_rate,
+            )
+        };
+        let mut frames = vec![0u64; count as usize];
+        unsafe {
+            sys_ext::maudio_decoder_mp3_seek_points(
+                decoder,
+                frames.as_mut_ptr(),
+                count,
+                &mut native_rate,
+            )
+        };
+        // The table counts frames before the decoder resamples them
+        if native_rate != 0 && native_rate != sample_rate {
+            for frame in &mut frames {
+                *frame = (*frame as u128 * sample_rate as u128 / native_rate as u128) as u64;
+            }
+        }
+        Self { frames }
+    }
+
+    /// Returns the frame to seek to for `target`.
+    pub(crate) fn resolve(&self, target: u64) -> u64 {
+        if self.frames.is_empty() {
+            return target;
+        }
+        // The start of the stream acts as an entry before the first one
+        match self.frames.partition_point(|&frame| frame <= target) {
+            0 => 0,
+            i => self.frames[i - 1],
+        }
+    }
+}
+
+/// Converts `time` to a frame index at `sample_rate`, rounded to the closest frame.
+pub(crate) fn time_to_frame(time: Duration, sample_rate: u32) -> MaResult<u64> {
+    if sample_rate == 0 {
+        return Err(MaudioError::from_ma_result(
+            sys::ma_result_MA_INVALID_OPERATION,
+        ));
+    }
+    let frame = (time.as_secs_f64() * sample_rate as f64).round();
+    if frame >= u64::MAX as f64 {
+        return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+    }
+    Ok(frame as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::SeekIndex;
+
+    #[test]
+    fn test_seek_index_resolve() {
+        let index = SeekIndex {
+            frames: vec![1_000, 2_000, 3_000],
+        };
+        assert_eq!(index.resolve(500), 0);
+        assert_eq!(index.resolve(2_000), 2_000);
+        assert_eq!(index.resolve(2_999), 2_000);
+        assert_eq!(index.resolve(9_000), 3_000);
+
+        let empty = SeekIndex { frames: Vec::new() };
+        assert_eq!(empty.resolve(1_234), 1_234);
+    }
+}
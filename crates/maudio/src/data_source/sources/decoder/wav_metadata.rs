@@ -0,0 +1,299 @@
+//! Markers and loop points stored in WAV files.
+//!
+//! Audio editors save cue points in the `cue ` chunk, their names in a `LIST`/`adtl`
+//! chunk, and sampler loops in the `smpl` chunk. miniaudio skips these chunks, so they are
+//! read separately here. Only the chunk headers and these chunks are read, never the audio.
+//!
+//! Files that are not WAV, or whose metadata is malformed, simply report no markers.
+use std::io::{Read, Seek, SeekFrom};
+
+/// A named position in a WAV file, read from its `cue ` chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    /// Cue point identifier, unique within the file.
+    pub id: u32,
+    /// Position in PCM frames.
+    pub position: u64,
+    /// Name from the `labl` chunk, if the file has one for this cue point.
+    pub label: Option<String>,
+}
+
+/// How a sampler plays a [`SampleLoop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopKind {
+    Forward,
+    PingPong,
+    Backward,
+    /// A manufacturer specific loop type.
+    Other(u32),
+}
+
+impl From<u32> for LoopKind {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => LoopKind::Forward,
+            1 => LoopKind::PingPong,
+            2 => LoopKind::Backward,
+            other => LoopKind::Other(other),
+        }
+    }
+}
+
+/// A loop region read from the `smpl` chunk of a WAV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleLoop {
+    /// Identifier of the loop, usually matching a cue point.
+    pub id: u32,
+    pub kind: LoopKind,
+    /// First frame of the loop.
+    pub start: u64,
+    /// Frame after the last frame of the loop.
+    ///
+    /// The `smpl` chunk stores the last frame itself. It is converted here to match the
+    /// loop points used everywhere else in the crate.
+    pub end: u64,
+    /// Number of times to play the loop, `0` meaning forever.
+    pub play_count: u32,
+}
+
+/// Metadata of a WAV file, with positions in frames at `sample_rate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct WavMetadata {
+    pub(crate) sample_rate: u32,
+    pub(crate) markers: Vec<Marker>,
+    pub(crate) loops: Vec<SampleLoop>,
+}
+
+impl WavMetadata {
+    /// Reads the metadata from the start of a WAV stream.
+    ///
+    /// Returns empty metadata when the stream is not a WAV file. Reading stops at the first
+    /// malformed chunk, keeping what was read before it.
+    pub(crate) fn read<R: Read + Seek>(reader: &mut R) -> Self {
+        let mut meta = WavMetadata::default();
+        let mut labels = Vec::new();
+        let _ = meta.read_chunks(reader, &mut labels);
+        for (id, label) in labels {
+            if let Some(marker) = meta.markers.iter_mut().find(|m| m.id == id) {
+                marker.label = Some(label);
+            }
+        }
+        meta.markers.sort_by_key(|m| m.position);
+        meta
+    }
+
+    /// Same as [`WavMetadata::read`], from a file on disk.
+    pub(crate) fn read_file(path: &std::path::Path) -> Self {
+        match std::fs::File::open(path) {
+            Ok(file) => WavMetadata::read(&mut std::io::BufReader::new(file)),
+            Err(_) => WavMetadata::default(),
+        }
+    }
+
+    /// Converts every position to frames at `sample_rate`.
+    ///
+    /// Decoders and sounds may resample the file, and their cursors count output frames.
+    pub(crate) fn rescaled(mut self, sample_rate: u32) -> Self {
+        if self.sample_rate == 0 || sample_rate == 0 || self.sample_rate == sample_rate {
+            return self;
+        }
+        let scale =
+            |pos: u64| (pos as u128 * sample_rate as u128 / self.sample_rate as u128) as u64;
+        for marker in &mut self.markers {
+            marker.position = scale(marker.position);
+        }
+        for l in &mut self.loops {
+            l.start = scale(l.start);
+            l.end = scale(l.end);
+        }
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    fn read_chunks<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        labels: &mut Vec<(u32, String)>,
+    ) -> std::io::Result<()> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Ok(());
+        }
+
+        loop {
+            let mut chunk = [0u8; 8];
+            if reader.read_exact(&mut chunk).is_err() {
+                return Ok(());
+            }
+            let size = u32_at(&chunk, 4) as u64;
+            // Chunks are padded to an even size
+            let padded = size + (size & 1);
+            match &chunk[0..4] {
+                b"fmt " | b"cue " | b"LIST" | b"smpl" => {
+                    let body = read_body(reader, size)?;
+                    if padded > size {
+                        reader.seek(SeekFrom::Current(1))?;
+                    }
+                    match &chunk[0..4] {
+                        b"fmt " if body.len() >= 8 => self.sample_rate = u32_at(&body, 4),
+                        b"cue " => self.read_cue(&body),
+                        b"LIST" => read_labels(&body, labels),
+                        b"smpl" => self.read_smpl(&body),
+                        _ => {}
+                    }
+                }
+                _ => {
+                    reader.seek(SeekFrom::Current(padded as i64))?;
+                }
+            }
+        }
+    }
+
+    fn read_cue(&mut self, body: &[u8]) {
+        let Some(count) = body.get(0..4).map(|b| u32_at(b, 0)) else {
+            return;
+        };
+        for point in body[4..].chunks_exact(24).take(count as usize) {
+            self.markers.push(Marker {
+                id: u32_at(point, 0),
+                position: u32_at(point, 20) as u64,
+                label: None,
+            });
+        }
+    }
+
+    fn read_smpl(&mut self, body: &[u8]) {
+        if body.len() < 36 {
+            return;
+        }
+        let count = u32_at(body, 28);
+        for l in body[36..].chunks_exact(24).take(count as usize) {
+            let (start, last) = (u32_at(l, 8) as u64, u32_at(l, 12) as u64);
+            if last < start {
+                continue;
+            }
+            self.loops.push(SampleLoop {
+                id: u32_at(l, 0),
+                kind: LoopKind::from(u32_at(l, 4)),
+                start,
+                end: last + 1,
+                play_count: u32_at(l, 20),
+            });
+        }
+    }
+}
+
+// Reads the `labl` entries of a `LIST` chunk of type `adtl`
+fn read_labels(body: &[u8], labels: &mut Vec<(u32, String)>) {
+    if body.get(0..4) != Some(b"adtl".as_slice()) {
+        return;
+    }
+    let mut rest = &body[4..];
+    while rest.len() >= 8 {
+        let size = u32_at(rest, 4) as usize;
+        // A corrupt size can overflow usize on 32 bit targets
+        let Some(end) = size.checked_add(8) else {
+            return;
+        };
+        let Some(data) = rest.get(8..end) else {
+            return;
+        };
+        if &rest[0..4] == b"labl" && data.len() >= 4 {
+            let text = &data[4..];
+            let text = text.split(|b| *b == 0).next().unwrap_or(text);
+            labels.push((u32_at(data, 0), String::from_utf8_lossy(text).into_owned()));
+        }
+        let Some(next) = end.checked_add(size & 1) else {
+            return;
+        };
+        rest = &rest[next.min(rest.len())..];
+    }
+}
+
+fn read_body<R: Read>(reader: &mut R, size: u64) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    // `take` keeps a corrupt size from allocating more than the file holds
+    reader.take(size).read_to_end(&mut body)?;
+    if (body.len() as u64) < size {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(body)
+}
+
+#[inline]
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::decoder::wav_metadata::{read_labels, LoopKind, WavMetadata},
+        test_assets::{wav_i16_le, wav_with_metadata},
+    };
+
+    #[test]
+    fn test_wav_metadata_reads_cues_labels_and_loops() {
+        let wav = wav_with_metadata(
+            &wav_i16_le(1, SampleRate::Sr48000, &[0; 1000]),
+            &[(1, 100, Some("intro")), (2, 50, None)],
+            &[(1, 200, 799)],
+        );
+        let meta = WavMetadata::read(&mut Cursor::new(&wav));
+
+        assert_eq!(meta.sample_rate, 48000);
+        assert_eq!(meta.markers.len(), 2);
+        // Sorted by position
+        assert_eq!(meta.markers[0].id, 2);
+        assert_eq!(meta.markers[0].label, None);
+        assert_eq!(meta.markers[1].position, 100);
+        assert_eq!(meta.markers[1].label.as_deref(), Some("intro"));
+        assert_eq!(meta.loops.len(), 1);
+        assert_eq!(meta.loops[0].kind, LoopKind::Forward);
+        assert_eq!(meta.loops[0].start, 200);
+        assert_eq!(meta.loops[0].end, 800);
+
+        let meta = meta.rescaled(24000);
+        assert_eq!(meta.markers[1].position, 50);
+        assert_eq!(meta.loops[0].end, 400);
+    }
+
+    #[test]
+    fn test_wav_metadata_ignores_other_files() {
+        let plain = wav_i16_le(1, SampleRate::Sr48000, &[0; 16]);
+        let meta = WavMetadata::read(&mut Cursor::new(&plain));
+        assert!(meta.markers.is_empty());
+        assert!(meta.loops.is_empty());
+
+        let meta = WavMetadata::read(&mut Cursor::new(b"OggS not a wav file"));
+        assert_eq!(meta, WavMetadata::default());
+
+        // Truncated cue chunk
+        let mut wav = wav_with_metadata(&plain, &[(1, 4, None)], &[]);
+        wav.truncate(wav.len() - 10);
+        let meta = WavMetadata::read(&mut Cursor::new(&wav));
+        assert!(meta.markers.is_empty());
+    }
+
+    #[test]
+    fn test_wav_metadata_labels_stop_at_corrupt_size() {
+        let mut body = b"adtl".to_vec();
+        body.extend_from_slice(b"labl");
+        body.extend_from_slice(&u32::MAX.to_le_bytes());
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(b"intro\0");
+
+        let mut labels = Vec::new();
+        read_labels(&body, &mut labels);
+        assert!(labels.is_empty());
+    }
+}
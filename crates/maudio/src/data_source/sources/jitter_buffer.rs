@@ -0,0 +1,615 @@
+//! Jitter buffer for network audio, built on the [`PushSource`](super::push_source) ring buffer.
+//!
+//! Network packets arrive in bursts and sometimes not at all. A [`JitterBuffer`] holds back
+//! playback until its target latency is buffered, so that late packets are still played in
+//! time, and conceals the gaps it cannot avoid:
+//!
+//! - When it runs out of audio, the missing frames are concealed, the target latency grows by
+//!   the growth step (up to the maximum), and playback waits until the new target is buffered.
+//! - Packets known to be lost are reported with [`JitterSender::push_lost`]. They are
+//!   concealed at their place in the stream, instead of the next packets playing early.
+//! - When more than the maximum latency is buffered, the oldest frames are skipped.
+//!
+//! Concealment is done by a [`Concealer`]. [`Concealment`] provides silence, repetition of the
+//! last frames, and a repetition that fades out. Implement [`Concealer`] for anything more
+//! elaborate.
+//!
+//! ```no_run
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use maudio::data_source::sources::jitter_buffer::{Concealment, JitterBufferBuilder};
+//! # use maudio::engine::Engine;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let (sender, jitter) = JitterBufferBuilder::new(1, SampleRate::Sr48000)
+//!     .target_latency(2400)
+//!     .concealment(Concealment::Fade { frames: 480 })
+//!     .build()?;
+//!
+//! let mut sound = engine.new_sound_from_source(&jitter)?;
+//! sound.play_sound()?;
+//!
+//! // On the network thread, for each packet in sequence order
+//! # let (packet, lost) = ([0.0f32; 480], false);
+//! if lost {
+//!     sender.push_lost(480);
+//! } else {
+//!     sender.push(&packet)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate},
+    data_source::{
+        data_source_builder::DataSourceBuilder,
+        pcm_source::PcmSource,
+        private_data_source,
+        sources::{
+            pcm_ring_buffer::{PcmRbRecv, PcmRingBuffer},
+            push_source::PushSender,
+        },
+        AsSourcePtr, DataSource, DataSourceRef, SourceContext,
+    },
+    engine::node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
+    ErrorKinds, MaResult, MaudioError,
+};
+
+/// Fills the frames a [`JitterBuffer`] has no audio for.
+///
+/// Called from the audio thread, so it should not block or allocate.
+pub trait Concealer: Send + 'static {
+    /// Fills `out`, interleaved `channels` samples per frame.
+    ///
+    /// `history` holds the last frames that were played, oldest first, and may be empty.
+    /// `gap_offset` is the number of frames of the current gap that were concealed before
+    /// `out`, so that a concealment can fade out as the gap grows.
+    fn conceal(&mut self, history: &[f32], channels: usize, gap_offset: u64, out: &mut [f32]);
+}
+
+/// Built-in packet-loss concealment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Concealment {
+    /// Play silence.
+    Silence,
+    /// Loop the last played frames.
+    Repeat,
+    /// Loop the last played frames while fading them out over `frames`, then play silence.
+    Fade { frames: u64 },
+}
+
+impl Concealer for Concealment {
+    fn conceal(&mut self, history: &[f32], channels: usize, gap_offset: u64, out: &mut [f32]) {
+        let history_frames = history.len() / channels;
+        if *self == Concealment::Silence || history_frames == 0 {
+            out.fill(0.0);
+            return;
+        }
+        for (i, frame) in out.chunks_exact_mut(channels).enumerate() {
+            let position = gap_offset + i as u64;
+            let gain = match *self {
+                Concealment::Fade { frames } if position >= frames => 0.0,
+                Concealment::Fade { frames } => 1.0 - position as f32 / frames as f32,
+                _ => 1.0,
+            };
+            let src = (position % history_frames as u64) as usize * channels;
+            for (out, sample) in frame.iter_mut().zip(&history[src..src + channels]) {
+                *out = sample * gain;
+            }
+        }
+    }
+}
+
+/// Builder for a [`JitterBuffer`].
+///
+/// Latencies are in frames. The defaults are a target of 40 ms, growing by 10 ms per
+/// underrun up to 200 ms, and a 10 ms [`Concealment::Fade`] of the last 10 ms played.
+pub struct JitterBufferBuilder {
+    channels: u32,
+    sample_rate: SampleRate,
+    target_latency: u32,
+    max_latency: u32,
+    growth: u32,
+    history: u32,
+    concealer: Option<Box<dyn Concealer>>,
+}
+
+impl JitterBufferBuilder {
+    pub fn new(channels: u32, sample_rate: SampleRate) -> Self {
+        let ms = sample_rate.hz() / 1000;
+        Self {
+            channels,
+            sample_rate,
+            target_latency: 40 * ms,
+            max_latency: 200 * ms,
+            growth: 10 * ms,
+            history: 10 * ms,
+            concealer: None,
+        }
+    }
+
+    /// Frames buffered before playback starts, and again after each underrun.
+    pub fn target_latency(&mut self, frames: u32) -> &mut Self {
+        self.target_latency = frames;
+        self
+    }
+
+    /// Upper bound of the target latency. Frames buffered past it are skipped.
+    pub fn max_latency(&mut self, frames: u32) -> &mut Self {
+        self.max_latency = frames;
+        self
+    }
+
+    /// Frames added to the target latency after each underrun. `0` keeps it fixed.
+    pub fn growth_step(&mut self, frames: u32) -> &mut Self {
+        self.growth = frames;
+        self
+    }
+
+    /// Number of played frames kept for the concealment.
+    pub fn history(&mut self, frames: u32) -> &mut Self {
+        self.history = frames;
+        self
+    }
+
+    pub fn concealment(&mut self, concealment: Concealment) -> &mut Self {
+        self.concealer = Some(Box::new(concealment));
+        self
+    }
+
+    /// Uses a custom [`Concealer`] for the gaps.
+    pub fn concealer<C: Concealer>(&mut self, concealer: C) -> &mut Self {
+        self.concealer = Some(Box::new(concealer));
+        self
+    }
+
+    /// Builds the jitter buffer and the sender feeding it.
+    ///
+    /// Fails if the target latency is zero or above the maximum latency. Building takes the
+    /// custom concealer, later builds use the default one.
+    pub fn build(&mut self) -> MaResult<(JitterSender, JitterBuffer)> {
+        if self.target_latency == 0 || self.target_latency > self.max_latency {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "the target latency must be between 1 and the maximum latency",
+            )));
+        }
+        let channels = self.channels as usize;
+        // Room for the maximum latency plus a burst of the same size
+        let capacity = self.max_latency.saturating_mul(2);
+        let (mut tx, rx) = PcmRingBuffer::new_f32(capacity, self.channels)?;
+        tx.set_sample_rate(self.sample_rate);
+
+        let shared = Arc::new(JitterShared {
+            lost: Mutex::new(VecDeque::new()),
+            target_latency: AtomicU32::new(self.target_latency),
+            underruns: AtomicU64::new(0),
+            concealed_frames: AtomicU64::new(0),
+            skipped_frames: AtomicU64::new(0),
+        });
+        let concealer = self.concealer.take().unwrap_or_else(|| {
+            Box::new(Concealment::Fade {
+                frames: self.history as u64,
+            })
+        });
+        let source = JitterSource {
+            rx,
+            channels,
+            shared: shared.clone(),
+            max_latency: self.max_latency,
+            growth: self.growth,
+            concealer,
+            history: VecDeque::with_capacity(self.history as usize * channels),
+            history_len: self.history as usize * channels,
+            history_flat: vec![0.0; self.history as usize * channels],
+            buffering: true,
+            read_position: 0,
+            lost_pending: 0,
+            gap_offset: 0,
+        };
+        let source = DataSourceBuilder::new(self.channels, self.sample_rate)
+            .no_seek(true)
+            .no_length(true)
+            .build_f32(source)?;
+
+        let sender = JitterSender {
+            sender: PushSender::new(tx, self.channels),
+            shared: shared.clone(),
+        };
+        Ok((sender, JitterBuffer { source, shared }))
+    }
+}
+
+// State shared between the sender, the buffer and the audio thread.
+struct JitterShared {
+    // Lost packets as (stream position, frames), in stream order
+    lost: Mutex<VecDeque<(u64, u64)>>,
+    target_latency: AtomicU32,
+    underruns: AtomicU64,
+    concealed_frames: AtomicU64,
+    skipped_frames: AtomicU64,
+}
+
+/// Counters of a [`JitterBuffer`], see [`JitterBuffer::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JitterStats {
+    /// Current target latency in frames, after the growth.
+    pub target_latency: u32,
+    /// Number of times playback ran out of audio.
+    pub underruns: u64,
+    /// Frames filled by the concealer, for underruns and lost packets.
+    pub concealed_frames: u64,
+    /// Frames skipped because more than the maximum latency was buffered.
+    pub skipped_frames: u64,
+}
+
+/// Pushes packets to a [`JitterBuffer`]. Cheap to clone, and usable from any thread.
+///
+/// Push the packets and report the lost ones in sequence order, from one thread at a time.
+#[derive(Clone)]
+pub struct JitterSender {
+    sender: PushSender,
+    shared: Arc<JitterShared>,
+}
+
+impl JitterSender {
+    /// Pushes the interleaved frames of a packet, returning the number of frames pushed.
+    ///
+    /// Frames only fail to fit when twice the maximum latency is buffered. The rest of the
+    /// packet is left to the caller to drop.
+    pub fn push(&self, samples: &[f32]) -> MaResult<usize> {
+        self.sender.push(samples)
+    }
+
+    /// Reports a lost packet of `frames` frames, concealed after the frames pushed so far.
+    pub fn push_lost(&self, frames: u64) {
+        if frames == 0 {
+            return;
+        }
+        self.sender.with_position(|position| {
+            let mut lost = self.shared.lost.lock().unwrap_or_else(|e| e.into_inner());
+            match lost.back_mut() {
+                Some((at, len)) if *at == position => *len += frames,
+                _ => lost.push_back((position, frames)),
+            }
+        });
+    }
+
+    /// Number of pushed frames that were not played yet.
+    pub fn queued_frames(&self) -> u32 {
+        self.sender.queued_frames()
+    }
+}
+
+/// A data source playing packets pushed with a [`JitterSender`]. See the [module docs](self).
+pub struct JitterBuffer {
+    source: DataSource<f32, JitterSource>,
+    shared: Arc<JitterShared>,
+}
+
+#[doc(hidden)]
+impl AsSourcePtr for JitterBuffer {
+    type Format = f32;
+    type __PtrProvider = private_data_source::JitterBufferProvider;
+}
+
+impl JitterBuffer {
+    pub fn stats(&self) -> JitterStats {
+        JitterStats {
+            target_latency: self.shared.target_latency.load(Ordering::Relaxed),
+            underruns: self.shared.underruns.load(Ordering::Relaxed),
+            concealed_frames: self.shared.concealed_frames.load(Ordering::Relaxed),
+            skipped_frames: self.shared.skipped_frames.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reads PCM frames into `dst`, returning the number of frames read.
+    pub fn read_pcm_frames_into(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        self.source.read_pcm_frames_into(dst)
+    }
+
+    /// Allocates and reads `frame_count` PCM frames.
+    pub fn read_pcm_frames(&mut self, frame_count: u64) -> MaResult<SampleBuffer<f32>> {
+        self.source.read_pcm_frames(frame_count)
+    }
+
+    /// Returns a [`DataSourceRef`] view of this buffer.
+    pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, f32> {
+        self.source.as_source_ref()
+    }
+
+    /// Turns the buffer into a source node of `node_graph`. Its output is not attached yet.
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
+// The `PcmSource` behind a `JitterBuffer`
+struct JitterSource {
+    rx: PcmRbRecv<f32>,
+    channels: usize,
+    shared: Arc<JitterShared>,
+    max_latency: u32,
+    growth: u32,
+    concealer: Box<dyn Concealer>,
+    // Last played samples, and a contiguous copy handed to the concealer
+    history: VecDeque<f32>,
+    history_len: usize,
+    history_flat: Vec<f32>,
+    // Waiting for the target latency to be buffered
+    buffering: bool,
+    // Position in the pushed stream of the next frame read from the ring buffer
+    read_position: u64,
+    // Frames of a lost packet still to conceal
+    lost_pending: u64,
+    // Frames concealed since real audio was last played
+    gap_offset: u64,
+}
+
+impl JitterSource {
+    fn conceal(&mut self, out: &mut [f32]) {
+        let flat = self.history.len();
+        for (dst, src) in self.history_flat.iter_mut().zip(&self.history) {
+            *dst = *src;
+        }
+        self.concealer.conceal(
+            &self.history_flat[..flat],
+            self.channels,
+            self.gap_offset,
+            out,
+        );
+        let frames = (out.len() / self.channels) as u64;
+        self.gap_offset += frames;
+        self.shared
+            .concealed_frames
+            .fetch_add(frames, Ordering::Relaxed);
+    }
+
+    fn remember(&mut self, played: &[f32]) {
+        let keep = played.len().min(self.history_len);
+        let excess = (self.history.len() + keep).saturating_sub(self.history_len);
+        self.history.drain(..excess);
+        self.history.extend(&played[played.len() - keep..]);
+    }
+
+    // Skips the oldest frames when more than the maximum latency is buffered
+    fn limit_latency(&mut self) -> MaResult<()> {
+        let available = self.rx.available_read();
+        if available <= self.max_latency {
+            return Ok(());
+        }
+        let skip = available - self.shared.target_latency.load(Ordering::Relaxed);
+        self.rx.seek_read(skip)?;
+        self.read_position += skip as u64;
+        self.shared
+            .skipped_frames
+            .fetch_add(skip as u64, Ordering::Relaxed);
+        // Lost packets that were skipped over are not concealed
+        let mut lost = self.shared.lost.lock().unwrap_or_else(|e| e.into_inner());
+        while lost
+            .front()
+            .map_or(false, |(at, _)| *at < self.read_position)
+        {
+            lost.pop_front();
+        }
+        Ok(())
+    }
+
+    // Takes the lost packets at the read position, returns the frames to the next one
+    fn next_lost(&mut self) -> u64 {
+        let mut lost = self.shared.lost.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(&(at, frames)) = lost.front() {
+            if at > self.read_position {
+                return at - self.read_position;
+            }
+            self.lost_pending += frames;
+            lost.pop_front();
+        }
+        u64::MAX
+    }
+}
+
+impl PcmSource<f32> for JitterSource {
+    fn fill_pcm_frames(&mut self, out: &mut [f32], ctx: &mut SourceContext) -> MaResult<usize> {
+        let channels = self.channels;
+        let requested = out.len() / channels;
+        ctx.cursor += requested as u64;
+        self.limit_latency()?;
+
+        let mut written = 0;
+        while written < requested {
+            let to_next_lost = self.next_lost();
+            if self.lost_pending > 0 {
+                let n = self.lost_pending.min((requested - written) as u64) as usize;
+                self.conceal(&mut out[written * channels..(written + n) * channels]);
+                self.lost_pending -= n as u64;
+                written += n;
+                continue;
+            }
+
+            let target = self.shared.target_latency.load(Ordering::Relaxed);
+            if self.buffering && self.rx.available_read() < target {
+                self.conceal(&mut out[written * channels..]);
+                break;
+            }
+            self.buffering = false;
+
+            let want = to_next_lost.min((requested - written) as u64) as usize;
+            let mut read = 0;
+            // The readable region can wrap around the end of the buffer
+            while read < want {
+                let dst = &mut out[(written + read) * channels..(written + want) * channels];
+                let n = self.rx.read(dst)?;
+                if n == 0 {
+                    break;
+                }
+                read += n;
+            }
+            if read > 0 {
+                let (start, end) = (written * channels, (written + read) * channels);
+                self.remember(&out[start..end]);
+                self.read_position += read as u64;
+                self.gap_offset = 0;
+                written += read;
+            }
+            if read < want {
+                // Out of audio: grow the target and wait until it is buffered again
+                self.shared.underruns.fetch_add(1, Ordering::Relaxed);
+                let grown = target.saturating_add(self.growth).min(self.max_latency);
+                self.shared.target_latency.store(grown, Ordering::Relaxed);
+                self.buffering = true;
+                self.conceal(&mut out[written * channels..]);
+                break;
+            }
+        }
+        Ok(requested)
+    }
+
+    fn seek_to_pcm_frame(&mut self, _frame_index: u64, _ctx: &mut SourceContext) -> MaResult<()> {
+        Err(MaudioError::new_ma_error(ErrorKinds::NotImplemented))
+    }
+
+    fn cursor_in_pcm_frames(&self, ctx: &SourceContext) -> Option<u64> {
+        Some(ctx.cursor)
+    }
+
+    fn length_in_pcm_frames(&self, _ctx: &SourceContext) -> Option<u64> {
+        None
+    }
+
+    fn set_looping(&self, _looping: bool, _ctx: &mut SourceContext) -> MaResult<()> {
+        // The stream has no end
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::jitter_buffer::{Concealer, Concealment, JitterBufferBuilder},
+    };
+
+    #[test]
+    fn test_jitter_buffer_waits_for_target_latency() {
+        let (sender, mut jitter) = JitterBufferBuilder::new(1, SampleRate::Sr48000)
+            .target_latency(100)
+            .max_latency(400)
+            .concealment(Concealment::Silence)
+            .build()
+            .unwrap();
+
+        sender.push(&[0.5; 60]).unwrap();
+        let out = jitter.read_pcm_frames(50).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.0));
+        assert_eq!(sender.queued_frames(), 60);
+
+        sender.push(&[0.5; 60]).unwrap();
+        let out = jitter.read_pcm_frames(50).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.5));
+        assert_eq!(sender.queued_frames(), 70);
+        assert_eq!(jitter.stats().underruns, 0);
+    }
+
+    #[test]
+    fn test_jitter_buffer_underrun_grows_target_and_conceals() {
+        let (sender, mut jitter) = JitterBufferBuilder::new(1, SampleRate::Sr48000)
+            .target_latency(16)
+            .max_latency(40)
+            .growth_step(16)
+            .history(4)
+            .concealment(Concealment::Fade { frames: 4 })
+            .build()
+            .unwrap();
+
+        sender.push(&[0.1, 0.2, 0.3, 0.4].repeat(4)).unwrap();
+        let out = jitter.read_pcm_frames(24).unwrap();
+        assert_eq!(&out.as_ref()[12..16], &[0.1, 0.2, 0.3, 0.4]);
+        // The last 4 frames repeat while fading out, then silence
+        assert_eq!(&out.as_ref()[16..20], &[0.1, 0.15, 0.15, 0.1]);
+        assert!(out.as_ref()[20..].iter().all(|s| *s == 0.0));
+
+        let stats = jitter.stats();
+        assert_eq!(stats.underruns, 1);
+        assert_eq!(stats.target_latency, 32);
+        assert_eq!(stats.concealed_frames, 8);
+
+        // Waits for the grown target, up to the maximum
+        sender.push(&[0.5; 16]).unwrap();
+        let out = jitter.read_pcm_frames(8).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.0));
+        sender.push(&[0.5; 16]).unwrap();
+        let out = jitter.read_pcm_frames(8).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.5));
+    }
+
+    #[test]
+    fn test_jitter_buffer_conceals_lost_packets_in_place() {
+        let (sender, mut jitter) = JitterBufferBuilder::new(2, SampleRate::Sr48000)
+            .target_latency(8)
+            .max_latency(64)
+            .concealment(Concealment::Repeat)
+            .history(1)
+            .build()
+            .unwrap();
+
+        sender.push(&[0.25, -0.25].repeat(4)).unwrap();
+        sender.push_lost(2);
+        sender.push_lost(1);
+        sender.push(&[0.5, -0.5].repeat(4)).unwrap();
+
+        let out = jitter.read_pcm_frames(11).unwrap();
+        let expected: Vec<f32> = [0.25, -0.25]
+            .repeat(7)
+            .into_iter()
+            .chain([0.5, -0.5].repeat(4))
+            .collect();
+        assert_eq!(out.as_ref(), expected.as_slice());
+        assert_eq!(jitter.stats().concealed_frames, 3);
+        assert_eq!(jitter.stats().underruns, 0);
+    }
+
+    #[test]
+    fn test_jitter_buffer_skips_past_max_latency() {
+        let (sender, mut jitter) = JitterBufferBuilder::new(1, SampleRate::Sr48000)
+            .target_latency(8)
+            .max_latency(16)
+            .build()
+            .unwrap();
+        let samples: Vec<f32> = (0..32).map(|i| i as f32).collect();
+        assert_eq!(sender.push(&samples).unwrap(), 32);
+
+        // Back down to the target latency, dropping the oldest frames
+        let out = jitter.read_pcm_frames(8).unwrap();
+        assert_eq!(out.as_ref(), &samples[24..]);
+        assert_eq!(jitter.stats().skipped_frames, 24);
+    }
+
+    #[test]
+    fn test_jitter_buffer_custom_concealer() {
+        struct Marker;
+        impl Concealer for Marker {
+            fn conceal(&mut self, _: &[f32], _: usize, gap_offset: u64, out: &mut [f32]) {
+                for (i, s) in out.iter_mut().enumerate() {
+                    *s = (gap_offset + i as u64) as f32;
+                }
+            }
+        }
+        let (_sender, mut jitter) = JitterBufferBuilder::new(1, SampleRate::Sr48000)
+            .concealer(Marker)
+            .build()
+            .unwrap();
+        jitter.read_pcm_frames(3).unwrap();
+        let out = jitter.read_pcm_frames(3).unwrap();
+        assert_eq!(out.as_ref(), &[3.0, 4.0, 5.0]);
+    }
+}
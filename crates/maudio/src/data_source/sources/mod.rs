@@ -1,7 +1,11 @@
 //! Built-in audio data source implementations.
 pub mod buffer;
+pub mod constant;
 pub mod decoder;
+pub mod jitter_buffer;
 pub mod noise;
 pub mod pcm_ring_buffer;
 pub mod pulsewave;
+pub mod push_source;
+pub mod sweep;
 pub mod waveform;
@@ -6,7 +6,10 @@ use maudio_sys::ffi as sys;
 use crate::{
     audio::formats::{Format, SampleBuffer},
     data_source::{private_data_source, AsSourcePtr, DataSourceRef},
-    engine::AllocationCallbacks,
+    engine::{
+        node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
+        AllocationCallbacks,
+    },
     pcm_frames::{PcmFormat, S24Packed, S24},
     AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
 };
@@ -45,6 +48,17 @@ impl<F: PcmFormat> Noise<F> {
         noise_ffi::ma_noise_read_pcm_frames(self, frames)
     }
 
+    /// Same as [`Noise::read_pcm_frames`], but generates into `buf`, reusing its allocation.
+    /// Returns the number of frames written.
+    pub fn read_pcm_frames_into_buffer(
+        &mut self,
+        buf: &mut SampleBuffer<F>,
+        frames: u64,
+    ) -> MaResult<usize> {
+        let channels = self.channels;
+        buf.read_with(frames, channels, |dst| self.read_pcm_frames_into(dst))
+    }
+
     /// Sets the output amplitude of the noise generator.
     ///
     /// Larger values produce louder noise. The exact practical range depends on
@@ -69,6 +83,20 @@ impl<F: PcmFormat> Noise<F> {
     }
 }
 
+impl Noise<f32> {
+    /// Turns the noise generator into a source node of `node_graph`.
+    ///
+    /// The node owns the noise generator and plays it into whatever it is attached to, so it can be
+    /// routed through effects without creating a `Sound`. Its output is not attached yet.
+    /// Change the noise generator while it plays with [`AttachedSourceNode::source_mut`].
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
 mod noise_ffi {
     use std::sync::Arc;
 
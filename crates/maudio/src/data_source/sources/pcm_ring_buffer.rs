@@ -36,6 +36,39 @@
 //! });
 //! # Ok(()) }
 //! ```
+//!
+//! ## Example: feeding the engine from a producer thread
+//!
+//! The receive endpoint can be turned into a [`PcmRbSource`], which is a [`PcmSource`] and can
+//! be used to build a data source. The [`UnderrunPolicy`] decides what is played when the
+//! producer falls behind.
+//!
+//! ```no_run
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use maudio::data_source::data_source_builder::DataSourceBuilder;
+//! # use maudio::data_source::sources::pcm_ring_buffer::{PcmRingBuffer, UnderrunPolicy};
+//! # use maudio::engine::Engine;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let (mut tx, rx) = PcmRingBuffer::new_f32(4096, 2)?;
+//!
+//! let source = rx.into_source(UnderrunPolicy::Silence);
+//! let underruns = source.underrun_notifier();
+//! let mut data_source = DataSourceBuilder::new(2, SampleRate::Sr48000)
+//!     .no_seek(true)
+//!     .no_length(true)
+//!     .build_f32(source)?;
+//! let mut sound = engine.new_sound_from_source(&mut data_source)?;
+//! sound.play_sound()?;
+//!
+//! std::thread::spawn(move || loop {
+//!     let block = [0.0f32; 512];
+//!     let _ = tx.write(&block);
+//!     std::thread::sleep(std::time::Duration::from_millis(5));
+//! });
+//! # let _ = underruns.take_delta();
+//! # Ok(()) }
+//! ```
 use std::{
     cell::Cell,
     marker::PhantomData,
@@ -47,12 +80,17 @@ use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{formats::Format, sample_rate::SampleRate},
-    data_source::sources::pcm_ring_buffer::private_pcm_db::{
-        PcmRbPtrImplementation, PcmRbRecvProvider, PcmRbSendProvider,
+    data_source::{
+        pcm_source::PcmSource,
+        sources::pcm_ring_buffer::private_pcm_db::{
+            PcmRbPtrImplementation, PcmRbRecvProvider, PcmRbSendProvider,
+        },
+        SourceContext,
     },
     engine::AllocationCallbacks,
     pcm_frames::{PcmFormat, PcmFormatInternal, S24Packed, S24},
-    MaResult,
+    util::proc_notif::ProcFramesNotif,
+    ErrorKinds, MaResult, MaudioError,
 };
 
 /// Type for creating a typed single-producer / single-consumer PCM ring buffer.
@@ -303,6 +341,152 @@ impl<F: PcmFormat> PcmRbRecv<F> {
     pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
         pcm_rb_ffi::ma_pcm_rb_set_sample_rate(self, sample_rate);
     }
+
+    /// Turns the receive endpoint into a [`PcmSource`], so it can be read as a data source.
+    pub fn into_source(self, policy: UnderrunPolicy) -> PcmRbSource<F> {
+        PcmRbSource::new(self, policy)
+    }
+}
+
+/// What a [`PcmRbSource`] outputs when the ring buffer does not have enough frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderrunPolicy {
+    /// Fill the missing frames with silence.
+    Silence,
+    /// Repeat the last frame that was read. Silence if nothing was read yet.
+    RepeatLast,
+    /// Stretch the frames that were available over the whole read by repeating them, so the
+    /// audio slows down briefly instead of dropping out. Silence if no frame was available.
+    Stretch,
+    /// Fill the missing frames with silence and report an error to miniaudio.
+    Error,
+}
+
+/// A [`PcmSource`] reading from the receive endpoint of a ring buffer.
+///
+/// Created with [`PcmRbRecv::into_source`]. A ring buffer has no end, so reads always return
+/// the requested number of frames. Missing frames are handled by the [`UnderrunPolicy`].
+///
+/// Seeking is not supported and there is no length, so the data source should be built with
+/// [`no_seek`](crate::data_source::data_source_builder::DataSourceBuilder::no_seek) and
+/// [`no_length`](crate::data_source::data_source_builder::DataSourceBuilder::no_length).
+pub struct PcmRbSource<F: PcmFormat> {
+    rx: PcmRbRecv<F>,
+    policy: UnderrunPolicy,
+    last_frame: Vec<F::PcmUnit>,
+    has_last: bool,
+    underruns: ProcFramesNotif,
+}
+
+impl<F: PcmFormat> PcmRbSource<F> {
+    fn new(rx: PcmRbRecv<F>, policy: UnderrunPolicy) -> Self {
+        let frame_len = rx.channels * F::VEC_PCM_UNITS_PER_FRAME;
+        Self {
+            rx,
+            policy,
+            last_frame: vec![F::PCM_UNIT_SILENCE; frame_len],
+            has_last: false,
+            underruns: ProcFramesNotif::default(),
+        }
+    }
+
+    pub fn policy(&self) -> UnderrunPolicy {
+        self.policy
+    }
+
+    /// Returns a [`ProcFramesNotif`] counting the frames that were missing from the ring buffer.
+    ///
+    /// Retrieve it before building the data source, as the source is moved into it.
+    pub fn underrun_notifier(&self) -> ProcFramesNotif {
+        self.underruns.clone()
+    }
+
+    /// Returns the receive endpoint.
+    pub fn into_inner(self) -> PcmRbRecv<F> {
+        self.rx
+    }
+}
+
+impl<F: PcmFormat> PcmSource<F> for PcmRbSource<F> {
+    fn fill_pcm_frames(
+        &mut self,
+        out: &mut [F::PcmUnit],
+        ctx: &mut SourceContext,
+    ) -> MaResult<usize> {
+        let frame_len = self.last_frame.len();
+        if frame_len == 0 {
+            return Ok(0);
+        }
+        let requested = out.len() / frame_len;
+
+        // The readable region can wrap around the end of the buffer, so read until nothing is left
+        let mut read = 0;
+        while read < requested {
+            let n = self
+                .rx
+                .read(&mut out[read * frame_len..requested * frame_len])?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+
+        if read > 0 {
+            self.last_frame
+                .copy_from_slice(&out[(read - 1) * frame_len..read * frame_len]);
+            self.has_last = true;
+        }
+        ctx.cursor += requested as u64;
+
+        let missing = requested - read;
+        if missing == 0 {
+            return Ok(requested);
+        }
+        self.underruns.add_frames(missing as u64);
+
+        if self.policy == UnderrunPolicy::Stretch && read > 0 {
+            // Back to front, every frame is copied from one at or before it
+            for i in (0..requested).rev() {
+                let src = i * read / requested;
+                out.copy_within(src * frame_len..(src + 1) * frame_len, i * frame_len);
+            }
+            return Ok(requested);
+        }
+
+        let rest = &mut out[read * frame_len..];
+        match self.policy {
+            UnderrunPolicy::RepeatLast if self.has_last => {
+                for frame in rest.chunks_exact_mut(frame_len) {
+                    frame.copy_from_slice(&self.last_frame);
+                }
+            }
+            _ => rest.fill(F::PCM_UNIT_SILENCE),
+        }
+
+        if self.policy == UnderrunPolicy::Error {
+            return Err(MaudioError::from_ma_result(
+                sys::ma_result_MA_NO_DATA_AVAILABLE,
+            ));
+        }
+        Ok(requested)
+    }
+
+    fn seek_to_pcm_frame(&mut self, _frame_index: u64, _ctx: &mut SourceContext) -> MaResult<()> {
+        Err(MaudioError::new_ma_error(ErrorKinds::NotImplemented))
+    }
+
+    fn cursor_in_pcm_frames(&self, ctx: &SourceContext) -> Option<u64> {
+        Some(ctx.cursor)
+    }
+
+    fn length_in_pcm_frames(&self, _ctx: &SourceContext) -> Option<u64> {
+        None
+    }
+
+    fn set_looping(&self, _looping: bool, _ctx: &mut SourceContext) -> MaResult<()> {
+        // A ring buffer has no end
+        Ok(())
+    }
 }
 
 impl PcmRingBuffer {
@@ -597,8 +781,11 @@ impl<'a, T, F: PcmFormat> RbWriteGuard<'a, T, F> {
         let n = self.capacity_frames() as usize * F::VEC_STORE_UNITS_PER_FRAME * self.channels;
         // Non-zero slice length requires a valid pointer
         debug_assert!(n == 0 || !self.ptr.is_null());
-        // Byte capacity must match whole T items
-        debug_assert_eq!(n % core::mem::size_of::<T>(), 0);
+        // The T items must cover exactly the bytes miniaudio handed out
+        debug_assert_eq!(
+            n * core::mem::size_of::<T>(),
+            self.capacity_frames() as usize * F::FORMAT.bytes_per_sample() * self.channels
+        );
         // Pointer must satisfy T's alignment before forming &mut [T]
         debug_assert!(n == 0 || (self.ptr as usize) % core::mem::align_of::<T>() == 0);
         // SAFETY:
@@ -930,3 +1117,121 @@ impl Drop for PcmRbInner {
         drop(unsafe { Box::from_raw(self.inner) });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::{
+            data_source_builder::DataSourceBuilder,
+            sources::pcm_ring_buffer::{PcmRingBuffer, UnderrunPolicy},
+        },
+    };
+
+    #[test]
+    fn test_pcm_rb_source_reads_written_frames() {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(64, 2).unwrap();
+        let src = rx.into_source(UnderrunPolicy::Silence);
+        let underruns = src.underrun_notifier();
+        let mut ds = DataSourceBuilder::new(2, SampleRate::Sr48000)
+            .no_seek(true)
+            .no_length(true)
+            .build_f32(src)
+            .unwrap();
+
+        let input: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        assert_eq!(tx.write(&input).unwrap(), 10);
+
+        let out = ds.read_pcm_frames(10).unwrap();
+        assert_eq!(out.data, input);
+        assert_eq!(underruns.take_delta(), 0);
+    }
+
+    #[test]
+    fn test_pcm_rb_source_underrun_silence() {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(64, 1).unwrap();
+        let src = rx.into_source(UnderrunPolicy::Silence);
+        let underruns = src.underrun_notifier();
+        let mut ds = DataSourceBuilder::new(1, SampleRate::Sr48000)
+            .build_f32(src)
+            .unwrap();
+
+        tx.write(&[0.5, 0.5]).unwrap();
+        let out = ds.read_pcm_frames(4).unwrap();
+        assert_eq!(out.data, vec![0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(underruns.take_delta(), 2);
+
+        // Never reaches an end
+        let out = ds.read_pcm_frames(4).unwrap();
+        assert_eq!(out.data, vec![0.0; 4]);
+        assert_eq!(underruns.take_delta(), 4);
+    }
+
+    #[test]
+    fn test_pcm_rb_source_underrun_repeat_last() {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(64, 2).unwrap();
+        let src = rx.into_source(UnderrunPolicy::RepeatLast);
+        let mut ds = DataSourceBuilder::new(2, SampleRate::Sr48000)
+            .build_f32(src)
+            .unwrap();
+
+        // Nothing read yet, so silence
+        let out = ds.read_pcm_frames(1).unwrap();
+        assert_eq!(out.data, vec![0.0, 0.0]);
+
+        tx.write(&[0.1, 0.2, 0.3, 0.4]).unwrap();
+        let out = ds.read_pcm_frames(4).unwrap();
+        assert_eq!(out.data, vec![0.1, 0.2, 0.3, 0.4, 0.3, 0.4, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_pcm_rb_source_underrun_stretch() {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(64, 2).unwrap();
+        let src = rx.into_source(UnderrunPolicy::Stretch);
+        let underruns = src.underrun_notifier();
+        let mut ds = DataSourceBuilder::new(2, SampleRate::Sr48000)
+            .build_f32(src)
+            .unwrap();
+
+        tx.write(&[0.1, 0.2, 0.3, 0.4]).unwrap();
+        let out = ds.read_pcm_frames(4).unwrap();
+        assert_eq!(out.data, vec![0.1, 0.2, 0.1, 0.2, 0.3, 0.4, 0.3, 0.4]);
+        assert_eq!(underruns.take_delta(), 2);
+
+        // Nothing to stretch
+        let out = ds.read_pcm_frames(2).unwrap();
+        assert_eq!(out.data, vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_pcm_rb_source_underrun_error() {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(64, 1).unwrap();
+        let src = rx.into_source(UnderrunPolicy::Error);
+        let mut ds = DataSourceBuilder::new(1, SampleRate::Sr48000)
+            .build_f32(src)
+            .unwrap();
+
+        tx.write(&[0.5; 4]).unwrap();
+        assert!(ds.read_pcm_frames(4).is_ok());
+        assert!(ds.read_pcm_frames(4).is_err());
+    }
+
+    #[test]
+    fn test_pcm_rb_source_wraps_around() {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(8, 1).unwrap();
+        let src = rx.into_source(UnderrunPolicy::Silence);
+        let mut ds = DataSourceBuilder::new(1, SampleRate::Sr48000)
+            .build_f32(src)
+            .unwrap();
+
+        tx.write(&[1.0; 6]).unwrap();
+        ds.read_pcm_frames(6).unwrap();
+        // The next write wraps around the end of the buffer, which takes two writes
+        let mut written = 0;
+        while written < 6 {
+            written += tx.write(&[2.0; 6][written..]).unwrap();
+        }
+        let out = ds.read_pcm_frames(6).unwrap();
+        assert_eq!(out.data, vec![2.0; 6]);
+    }
+}
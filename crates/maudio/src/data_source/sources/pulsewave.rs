@@ -17,6 +17,7 @@ use crate::{
         sample_rate::SampleRate,
     },
     data_source::{private_data_source, AsSourcePtr, DataSourceRef},
+    engine::node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
     pcm_frames::{PcmFormat, S24Packed, S24},
     AsRawRef, Binding, MaResult,
 };
@@ -97,6 +98,20 @@ mod private_pulsew {
 
 impl<F: PcmFormat> PulseWaveOps for PulseWave<F> {}
 
+impl PulseWave<f32> {
+    /// Turns the pulse wave into a source node of `node_graph`.
+    ///
+    /// The node owns the pulse wave and plays it into whatever it is attached to, so it can be
+    /// routed through effects without creating a `Sound`. Its output is not attached yet.
+    /// Change the pulse wave while it plays with [`AttachedSourceNode::source_mut`].
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
 pub trait PulseWaveOps: AsPulseWavePtr + AsSourcePtr {
     /// Generates PCM frames into `dst`, returning the number of frames written.
     fn read_pcm_frames_into(
@@ -111,6 +126,17 @@ pub trait PulseWaveOps: AsPulseWavePtr + AsSourcePtr {
         pulsewave_ffi::ma_pulsewave_read_pcm_frames(self, frames)
     }
 
+    /// Same as [`PulseWaveOps::read_pcm_frames`], but generates into `buf`, reusing its
+    /// allocation. Returns the number of frames written.
+    fn read_pcm_frames_into_buffer(
+        &mut self,
+        buf: &mut SampleBuffer<Self::Format>,
+        frames: u64,
+    ) -> MaResult<usize> {
+        let channels = self.channels();
+        buf.read_with(frames, channels, |dst| self.read_pcm_frames_into(dst))
+    }
+
     /// Seeks to an absolute PCM frame position.
     fn seek_to_pcm_frame(&mut self, frame_index: u64) -> MaResult<()> {
         pulsewave_ffi::ma_pulsewave_seek_to_pcm_frame(self, frame_index)
@@ -0,0 +1,255 @@
+//! Data source fed with audio the application generates or receives, like network audio.
+//!
+//! [`PushSource::new`] returns a [`PushSender`] and a [`PushSource`] sharing a
+//! [`PcmRingBuffer`]. The application pushes interleaved `f32` frames with the sender, and
+//! the engine pulls them by playing the source. The sender can be cloned, so frames can be
+//! pushed from several threads.
+//!
+//! When the application falls behind, the [`UnderrunPolicy`] decides what is played instead:
+//! silence, or the frames that did arrive stretched over the gap.
+//!
+//! ```no_run
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use maudio::data_source::sources::{pcm_ring_buffer::UnderrunPolicy, push_source::PushSource};
+//! # use maudio::engine::Engine;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let (sender, source) = PushSource::new(1, SampleRate::Sr48000, 4800, UnderrunPolicy::Stretch)?;
+//!
+//! let mut sound = engine.new_sound_from_source(&source)?;
+//! sound.play_sound()?;
+//!
+//! std::thread::spawn(move || loop {
+//!     // e.g. a decoded network packet
+//!     let packet = [0.0f32; 480];
+//!     let _ = sender.push(&packet);
+//!     std::thread::sleep(std::time::Duration::from_millis(10));
+//! });
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`PcmRingBuffer`]: crate::data_source::sources::pcm_ring_buffer::PcmRingBuffer
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate},
+    data_source::{
+        data_source_builder::DataSourceBuilder,
+        private_data_source,
+        sources::pcm_ring_buffer::{PcmRbSend, PcmRbSource, PcmRingBuffer, UnderrunPolicy},
+        AsSourcePtr, DataSource, DataSourceRef,
+    },
+    engine::node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
+    util::proc_notif::ProcFramesNotif,
+    MaResult,
+};
+
+/// A data source playing the frames pushed with its [`PushSender`].
+///
+/// It never ends: it plays according to its [`UnderrunPolicy`] while no frames are pushed.
+pub struct PushSource {
+    source: DataSource<f32, PcmRbSource<f32>>,
+    underruns: ProcFramesNotif,
+}
+
+#[doc(hidden)]
+impl AsSourcePtr for PushSource {
+    type Format = f32;
+    type __PtrProvider = private_data_source::PushSourceProvider;
+}
+
+impl PushSource {
+    /// Creates a source that can hold `capacity_frames` pushed frames that were not played yet.
+    ///
+    /// The capacity is the maximum latency between pushing a frame and hearing it. It should
+    /// be a few times larger than the blocks the application pushes, to absorb jitter.
+    pub fn new(
+        channels: u32,
+        sample_rate: SampleRate,
+        capacity_frames: u32,
+        policy: UnderrunPolicy,
+    ) -> MaResult<(PushSender, PushSource)> {
+        let (mut tx, rx) = PcmRingBuffer::new_f32(capacity_frames, channels)?;
+        tx.set_sample_rate(sample_rate);
+        let source = rx.into_source(policy);
+        let underruns = source.underrun_notifier();
+        let source = DataSourceBuilder::new(channels, sample_rate)
+            .no_seek(true)
+            .no_length(true)
+            .build_f32(source)?;
+
+        Ok((
+            PushSender::new(tx, channels),
+            PushSource { source, underruns },
+        ))
+    }
+
+    /// Returns a [`ProcFramesNotif`] counting the frames that were missing when the source
+    /// was read.
+    pub fn underrun_notifier(&self) -> ProcFramesNotif {
+        self.underruns.clone()
+    }
+
+    /// Reads PCM frames into `dst`, returning the number of frames read.
+    pub fn read_pcm_frames_into(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        self.source.read_pcm_frames_into(dst)
+    }
+
+    /// Allocates and reads `frame_count` PCM frames.
+    pub fn read_pcm_frames(&mut self, frame_count: u64) -> MaResult<SampleBuffer<f32>> {
+        self.source.read_pcm_frames(frame_count)
+    }
+
+    /// Returns a [`DataSourceRef`] view of this source.
+    pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, f32> {
+        self.source.as_source_ref()
+    }
+
+    /// Turns the source into a source node of `node_graph`. Its output is not attached yet.
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
+/// Pushes frames to a [`PushSource`]. Cheap to clone, and usable from any thread.
+#[derive(Clone)]
+pub struct PushSender {
+    tx: Arc<Mutex<SendState>>,
+    channels: usize,
+}
+
+struct SendState {
+    tx: PcmRbSend<f32>,
+    pushed: u64,
+}
+
+impl PushSender {
+    pub(crate) fn new(tx: PcmRbSend<f32>, channels: u32) -> Self {
+        Self {
+            tx: Arc::new(Mutex::new(SendState { tx, pushed: 0 })),
+            channels: channels as usize,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SendState> {
+        self.tx.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pushes interleaved frames, returning the number of frames pushed.
+    ///
+    /// Only the frames that fit in the buffer are pushed, the rest is left to the caller to
+    /// push again or drop.
+    pub fn push(&self, samples: &[f32]) -> MaResult<usize> {
+        let frames = samples.len() / self.channels;
+        let mut state = self.lock();
+        // The free region can wrap around the end of the buffer
+        let mut pushed = 0;
+        while pushed < frames {
+            let n = state
+                .tx
+                .write(&samples[pushed * self.channels..frames * self.channels])?;
+            if n == 0 {
+                break;
+            }
+            pushed += n;
+        }
+        state.pushed += pushed as u64;
+        Ok(pushed)
+    }
+
+    /// Number of pushed frames that were not played yet.
+    pub fn queued_frames(&self) -> u32 {
+        self.lock().tx.available_read()
+    }
+
+    /// Number of frames that can be pushed before the buffer is full.
+    pub fn free_frames(&self) -> u32 {
+        self.lock().tx.available_write()
+    }
+
+    /// Total number of frames pushed since the source was created.
+    pub fn pushed_frames(&self) -> u64 {
+        self.lock().pushed
+    }
+
+    // Runs `f` with the push position, while no other thread can push.
+    pub(crate) fn with_position<R>(&self, f: impl FnOnce(u64) -> R) -> R {
+        f(self.lock().pushed)
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::{pcm_ring_buffer::UnderrunPolicy, push_source::PushSource},
+        engine::engine_builder::EngineBuilder,
+    };
+
+    #[test]
+    fn test_push_source_from_several_threads() {
+        let (sender, mut source) =
+            PushSource::new(2, SampleRate::Sr48000, 256, UnderrunPolicy::Silence).unwrap();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let sender = sender.clone();
+                s.spawn(move || assert_eq!(sender.push(&[0.5; 32]).unwrap(), 16));
+            }
+        });
+        assert_eq!(sender.queued_frames(), 64);
+        assert_eq!(sender.free_frames(), 192);
+        assert_eq!(sender.pushed_frames(), 64);
+
+        let out = source.read_pcm_frames(80).unwrap();
+        assert!(out.as_ref()[..128].iter().all(|s| *s == 0.5));
+        assert!(out.as_ref()[128..].iter().all(|s| *s == 0.0));
+        assert_eq!(source.underrun_notifier().take_delta(), 16);
+        assert_eq!(sender.queued_frames(), 0);
+    }
+
+    #[test]
+    fn test_push_source_push_stops_when_full() {
+        let (sender, mut source) =
+            PushSource::new(1, SampleRate::Sr48000, 64, UnderrunPolicy::Silence).unwrap();
+        sender.push(&[0.5; 48]).unwrap();
+        source.read_pcm_frames(48).unwrap();
+        // The free space wraps around the end of the buffer
+        assert_eq!(sender.push(&[0.25; 100]).unwrap(), 64);
+        assert_eq!(sender.push(&[0.25; 8]).unwrap(), 0);
+        let out = source.read_pcm_frames(64).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.25));
+    }
+
+    #[test]
+    fn test_push_source_played_by_engine() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let (sender, source) =
+            PushSource::new(1, SampleRate::Sr48000, 1024, UnderrunPolicy::Silence).unwrap();
+        let mut sound = engine.new_sound_from_source(&source).unwrap();
+        sound.set_spatialization(false);
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+
+        sender.push(&[0.5; 128]).unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        // Sounds start one frame late
+        assert!(out.as_ref()[1..129].iter().all(|s| *s == 0.5));
+        assert!(out.as_ref()[129..].iter().all(|s| *s == 0.0));
+
+        // Keeps playing what is pushed later
+        sender.push(&[0.25; 64]).unwrap();
+        let out = reader.read_pcm_frames(64).unwrap();
+        assert!(out.as_ref()[1..].iter().all(|s| *s == 0.25));
+    }
+}
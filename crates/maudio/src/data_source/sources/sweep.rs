@@ -0,0 +1,377 @@
+//! Frequency sweep (chirp) generator.
+//!
+//! A [`Sweep`] plays a waveform whose frequency moves from a start to an end frequency over a
+//! fixed number of frames, either linearly or logarithmically. It is the usual test signal for
+//! filters: play it through a node and the output level at each point of the sweep shows the
+//! response at the frequency returned by [`Sweep::frequency_at`].
+//!
+//! The phase is computed from the cursor, so the sweep can be seeked and looped like any
+//! other data source, and stays phase-continuous across reads.
+//!
+//! ```no_run
+//! # use maudio::data_source::sources::sweep::{SweepBuilder, SweepMode};
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use maudio::engine::Engine;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! // 20 Hz to 20 kHz over five seconds
+//! let sweep = SweepBuilder::new(1, SampleRate::Sr48000, 20.0, 20_000.0, 5 * 48_000)
+//!     .mode(SweepMode::Logarithmic)
+//!     .amplitude(0.5)
+//!     .build()?;
+//!
+//! let mut sound = engine.new_sound_from_source(&sweep)?;
+//! sound.play_sound()?;
+//! # Ok(())
+//! # }
+//! ```
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate, wave_shape::WaveFormType},
+    data_source::{
+        data_source_builder::DataSourceBuilder, pcm_source::PcmSource, private_data_source,
+        AsSourcePtr, DataSource, DataSourceRef, SourceContext,
+    },
+    engine::node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
+    ErrorKinds, MaResult, MaudioError,
+};
+
+/// How the frequency of a [`Sweep`] moves between its start and end frequency.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SweepMode {
+    /// The frequency changes by the same number of Hz every frame.
+    #[default]
+    Linear,
+    /// The frequency changes by the same ratio every frame, so every octave takes the same
+    /// time. Also called an exponential sweep.
+    Logarithmic,
+}
+
+/// Builder for a [`Sweep`].
+pub struct SweepBuilder {
+    channels: u32,
+    sample_rate: SampleRate,
+    start_hz: f64,
+    end_hz: f64,
+    duration_frames: u64,
+    mode: SweepMode,
+    wave_type: WaveFormType,
+    amplitude: f64,
+}
+
+impl SweepBuilder {
+    /// Creates a builder for a sweep from `start_hz` to `end_hz` lasting `duration_frames`.
+    ///
+    /// Defaults to a linear sine sweep with an amplitude of `1.0`. The end frequency can be
+    /// lower than the start frequency to sweep downwards.
+    pub fn new(
+        channels: u32,
+        sample_rate: SampleRate,
+        start_hz: f64,
+        end_hz: f64,
+        duration_frames: u64,
+    ) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            start_hz,
+            end_hz,
+            duration_frames,
+            mode: SweepMode::Linear,
+            wave_type: WaveFormType::Sine,
+            amplitude: 1.0,
+        }
+    }
+
+    pub fn mode(&mut self, mode: SweepMode) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn wave_type(&mut self, wave_type: WaveFormType) -> &mut Self {
+        self.wave_type = wave_type;
+        self
+    }
+
+    pub fn amplitude(&mut self, amplitude: f64) -> &mut Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Builds the sweep.
+    ///
+    /// Fails if a frequency is not positive and finite, or if the duration or channel count
+    /// is zero.
+    pub fn build(&self) -> MaResult<Sweep> {
+        let valid_hz = |hz: f64| hz.is_finite() && hz > 0.0;
+        if !valid_hz(self.start_hz) || !valid_hz(self.end_hz) {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "sweep frequencies must be positive and finite",
+            )));
+        }
+        if self.duration_frames == 0 || self.channels == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+
+        let curve = SweepCurve {
+            sample_rate: self.sample_rate.hz() as f64,
+            start_hz: self.start_hz,
+            end_hz: self.end_hz,
+            duration_frames: self.duration_frames,
+            mode: self.mode,
+        };
+        let source = SweepSource {
+            curve,
+            channels: self.channels as usize,
+            wave_type: self.wave_type,
+            amplitude: self.amplitude as f32,
+        };
+        let source = DataSourceBuilder::new(self.channels, self.sample_rate).build_f32(source)?;
+        Ok(Sweep { source, curve })
+    }
+}
+
+/// A waveform sweeping between two frequencies, built with [`SweepBuilder`].
+///
+/// The sweep ends after its duration unless it is looping.
+pub struct Sweep {
+    source: DataSource<f32, SweepSource>,
+    curve: SweepCurve,
+}
+
+#[doc(hidden)]
+impl AsSourcePtr for Sweep {
+    type Format = f32;
+    type __PtrProvider = private_data_source::SweepProvider;
+}
+
+impl Sweep {
+    /// Instantaneous frequency in Hz at `frame`, clamped to the duration of the sweep.
+    pub fn frequency_at(&self, frame: u64) -> f64 {
+        self.curve.frequency_at(frame)
+    }
+
+    /// Length of the sweep in frames.
+    pub fn duration_frames(&self) -> u64 {
+        self.curve.duration_frames
+    }
+
+    /// Generates PCM frames into `dst`, returning the number of frames written.
+    pub fn read_pcm_frames_into(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        self.source.read_pcm_frames_into(dst)
+    }
+
+    /// Allocates and generates `frame_count` PCM frames.
+    pub fn read_pcm_frames(&mut self, frame_count: u64) -> MaResult<SampleBuffer<f32>> {
+        self.source.read_pcm_frames(frame_count)
+    }
+
+    /// Seeks to an absolute PCM frame index, at most the duration of the sweep.
+    pub fn seek_to_pcm(&mut self, frame_index: u64) -> MaResult<()> {
+        self.source.seek_to_pcm_frame(frame_index)
+    }
+
+    pub fn cursor_pcm(&self) -> MaResult<u64> {
+        self.source.cursor_in_pcm_frames()
+    }
+
+    /// Returns a [`DataSourceRef`] view of this sweep.
+    pub fn as_source_ref<'a>(&'a self) -> DataSourceRef<'a, f32> {
+        self.source.as_source_ref()
+    }
+
+    /// Turns the sweep into a source node of `node_graph`, for feeding it straight into the
+    /// nodes under test. Its output is not attached yet.
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SweepCurve {
+    sample_rate: f64,
+    start_hz: f64,
+    end_hz: f64,
+    duration_frames: u64,
+    mode: SweepMode,
+}
+
+impl SweepCurve {
+    // Position in the sweep between 0.0 and 1.0
+    fn progress(&self, frame: u64) -> f64 {
+        frame.min(self.duration_frames) as f64 / self.duration_frames as f64
+    }
+
+    fn frequency_at(&self, frame: u64) -> f64 {
+        let x = self.progress(frame);
+        match self.mode {
+            SweepMode::Linear => self.start_hz + (self.end_hz - self.start_hz) * x,
+            SweepMode::Logarithmic => self.start_hz * (self.end_hz / self.start_hz).powf(x),
+        }
+    }
+
+    // Number of cycles played before `frame`, the integral of the frequency. Computed from the
+    // frame rather than accumulated, so seeking lands on the same phase as playing.
+    fn cycles_at(&self, frame: u64) -> f64 {
+        let seconds = self.duration_frames as f64 / self.sample_rate;
+        let x = self.progress(frame);
+        let ratio = self.end_hz / self.start_hz;
+        match self.mode {
+            SweepMode::Logarithmic if ratio != 1.0 => {
+                self.start_hz * seconds / ratio.ln() * (ratio.powf(x) - 1.0)
+            }
+            _ => seconds * x * (self.start_hz + (self.end_hz - self.start_hz) * x / 2.0),
+        }
+    }
+}
+
+// The `PcmSource` behind a `Sweep`
+struct SweepSource {
+    curve: SweepCurve,
+    channels: usize,
+    wave_type: WaveFormType,
+    amplitude: f32,
+}
+
+impl SweepSource {
+    // Same shapes as `ma_waveform`
+    fn sample(&self, cycles: f64) -> f32 {
+        let t = cycles - cycles.floor();
+        let value = match self.wave_type {
+            WaveFormType::Sine => (t * core::f64::consts::TAU).sin(),
+            WaveFormType::Square if t < 0.5 => 1.0,
+            WaveFormType::Square => -1.0,
+            WaveFormType::Triangle => 2.0 * (2.0 * (t - 0.5)).abs() - 1.0,
+            WaveFormType::Sawtooth => 2.0 * (t - 0.5),
+        };
+        value as f32 * self.amplitude
+    }
+}
+
+impl PcmSource<f32> for SweepSource {
+    fn fill_pcm_frames(&mut self, out: &mut [f32], ctx: &mut SourceContext) -> MaResult<usize> {
+        let mut frames = 0;
+        for frame in out.chunks_exact_mut(self.channels) {
+            if ctx.cursor >= self.curve.duration_frames {
+                if !ctx.looping {
+                    break;
+                }
+                ctx.cursor = 0;
+            }
+            frame.fill(self.sample(self.curve.cycles_at(ctx.cursor)));
+            ctx.cursor += 1;
+            frames += 1;
+        }
+        out[frames * self.channels..].fill(0.0);
+        Ok(frames)
+    }
+
+    fn seek_to_pcm_frame(&mut self, frame_index: u64, ctx: &mut SourceContext) -> MaResult<()> {
+        if frame_index > self.curve.duration_frames {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "Trying to seek too far",
+            )));
+        }
+        ctx.cursor = frame_index;
+        Ok(())
+    }
+
+    fn cursor_in_pcm_frames(&self, ctx: &SourceContext) -> Option<u64> {
+        Some(ctx.cursor)
+    }
+
+    fn length_in_pcm_frames(&self, _ctx: &SourceContext) -> Option<u64> {
+        Some(self.curve.duration_frames)
+    }
+
+    fn set_looping(&self, looping: bool, ctx: &mut SourceContext) -> MaResult<()> {
+        ctx.looping = looping;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::{sample_rate::SampleRate, wave_shape::WaveFormType},
+        data_source::sources::sweep::{Sweep, SweepBuilder, SweepMode},
+    };
+
+    // Estimates the frequency around `frame` from the zero crossings in a window of `window`
+    // frames centered on it.
+    fn measured_hz(sweep: &mut Sweep, frame: u64, window: u64) -> f64 {
+        sweep.seek_to_pcm(frame - window / 2).unwrap();
+        let out = sweep.read_pcm_frames(window).unwrap();
+        let crossings = out
+            .as_ref()
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        crossings as f64 / 2.0 / (window as f64 / 48_000.0)
+    }
+
+    #[test]
+    fn test_sweep_linear_frequencies() {
+        let mut sweep = SweepBuilder::new(1, SampleRate::Sr48000, 100.0, 2100.0, 48_000)
+            .build()
+            .unwrap();
+        assert_eq!(sweep.frequency_at(0), 100.0);
+        assert_eq!(sweep.frequency_at(24_000), 1100.0);
+        assert_eq!(sweep.frequency_at(48_000), 2100.0);
+        assert_eq!(sweep.frequency_at(96_000), 2100.0);
+
+        let start = measured_hz(&mut sweep, 4800, 4800);
+        let middle = measured_hz(&mut sweep, 24_000, 4800);
+        assert!((start - sweep.frequency_at(4800)).abs() < 15.0, "{start}");
+        assert!((middle - 1100.0).abs() < 15.0, "{middle}");
+    }
+
+    #[test]
+    fn test_sweep_logarithmic_midpoint_is_geometric_mean() {
+        let mut sweep = SweepBuilder::new(1, SampleRate::Sr48000, 100.0, 6400.0, 48_000)
+            .mode(SweepMode::Logarithmic)
+            .build()
+            .unwrap();
+        assert!((sweep.frequency_at(24_000) - 800.0).abs() < 1e-9);
+
+        let middle = measured_hz(&mut sweep, 24_000, 2400);
+        assert!((middle - 800.0).abs() < 25.0, "{middle}");
+    }
+
+    #[test]
+    fn test_sweep_ends_and_seeks_to_the_same_phase() {
+        let mut sweep = SweepBuilder::new(2, SampleRate::Sr48000, 200.0, 50.0, 1000)
+            .wave_type(WaveFormType::Triangle)
+            .amplitude(0.5)
+            .build()
+            .unwrap();
+        assert_eq!(sweep.duration_frames(), 1000);
+        assert_eq!(sweep.as_source_ref().length_in_pcm_frames().unwrap(), 1000);
+
+        let all = sweep.read_pcm_frames(1200).unwrap();
+        assert_eq!(all.frames(), 1000);
+        assert!(all.as_ref().iter().all(|s| s.abs() <= 0.5));
+        // Both channels carry the same signal
+        assert!(all.as_ref().chunks(2).all(|f| f[0] == f[1]));
+
+        sweep.seek_to_pcm(600).unwrap();
+        let tail = sweep.read_pcm_frames(400).unwrap();
+        assert_eq!(tail.as_ref(), &all.as_ref()[1200..]);
+        assert!(sweep.seek_to_pcm(1001).is_err());
+    }
+
+    #[test]
+    fn test_sweep_rejects_invalid_parameters() {
+        let sr = SampleRate::Sr48000;
+        assert!(SweepBuilder::new(1, sr, 0.0, 100.0, 100).build().is_err());
+        assert!(SweepBuilder::new(1, sr, 100.0, f64::NAN, 100)
+            .build()
+            .is_err());
+        assert!(SweepBuilder::new(1, sr, 100.0, 200.0, 0).build().is_err());
+        assert!(SweepBuilder::new(0, sr, 100.0, 200.0, 100).build().is_err());
+    }
+}
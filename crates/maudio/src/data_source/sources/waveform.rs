@@ -23,6 +23,7 @@ use crate::{
         private_data_source, sources::waveform::private_wave::WaveFormPtrProvider, AsSourcePtr,
         DataSourceRef,
     },
+    engine::node_graph::{nodes::source::source_node::AttachedSourceNode, AsNodeGraphPtr},
     pcm_frames::{PcmFormat, S24Packed, S24},
     AsRawRef, Binding, MaResult,
 };
@@ -105,6 +106,20 @@ mod private_wave {
 
 impl<F: PcmFormat> WaveFormOps for WaveForm<F> {}
 
+impl WaveForm<f32> {
+    /// Turns the waveform into a source node of `node_graph`.
+    ///
+    /// The node owns the waveform and plays it into whatever it is attached to, so it can be
+    /// routed through effects without creating a `Sound`. Its output is not attached yet.
+    /// Change the waveform while it plays with [`AttachedSourceNode::source_mut`].
+    pub fn into_node<N: AsNodeGraphPtr>(
+        self,
+        node_graph: &N,
+    ) -> MaResult<AttachedSourceNode<Self>> {
+        AttachedSourceNode::from_source(node_graph, self)
+    }
+}
+
 pub trait WaveFormOps: AsWaveFormPtr + AsSourcePtr {
     /// Generates PCM frames into `dst`, returning the number of frames written.
     fn read_pcm_frames_into(
@@ -119,6 +134,17 @@ pub trait WaveFormOps: AsWaveFormPtr + AsSourcePtr {
         waveform_ffi::ma_waveform_read_pcm_frames(self, frames)
     }
 
+    /// Same as [`WaveFormOps::read_pcm_frames`], but generates into `buf`, reusing its
+    /// allocation. Returns the number of frames written.
+    fn read_pcm_frames_into_buffer(
+        &mut self,
+        buf: &mut SampleBuffer<Self::Format>,
+        frames: u64,
+    ) -> MaResult<usize> {
+        let channels = self.channels();
+        buf.read_with(frames, channels, |dst| self.read_pcm_frames_into(dst))
+    }
+
     /// Seeks to an absolute PCM frame position.
     fn seek_to_pcm_frame(&mut self, frame_index: u64) -> MaResult<()> {
         waveform_ffi::ma_waveform_seek_to_pcm_frame(self, frame_index)
@@ -746,6 +772,35 @@ mod tests {
         assert_eq!(buf.len(), (frames_read * w.channels() as u64) as usize);
     }
 
+    #[test]
+    fn test_waveform_read_pcm_frames_into_buffer_reuses_allocation() {
+        let mut w = WaveFormBuilder::new_sine(SampleRate::Sr48000, 440.0)
+            .channels(2)
+            .build_f32()
+            .unwrap();
+        let mut expected = WaveFormBuilder::new_sine(SampleRate::Sr48000, 440.0)
+            .channels(2)
+            .build_f32()
+            .unwrap();
+
+        let mut buf = SampleBuffer::with_capacity(256, 2).unwrap();
+        let ptr = buf.data.as_ptr();
+        for _ in 0..4 {
+            let frames = w.read_pcm_frames_into_buffer(&mut buf, 256).unwrap();
+            assert_eq!(frames, 256);
+            assert_eq!(buf.frames(), 256);
+            assert_eq!(buf.len(), 512);
+            assert_eq!(
+                buf.as_ref(),
+                expected.read_pcm_frames(256).unwrap().as_ref()
+            );
+        }
+        // Shorter reads shrink the buffer without reallocating
+        w.read_pcm_frames_into_buffer(&mut buf, 100).unwrap();
+        assert_eq!(buf.frames(), 100);
+        assert_eq!(buf.data.as_ptr(), ptr);
+    }
+
     #[test]
     fn test_waveform_read_pcm_frames_i16_len_matches_frames_read_times_channels() {
         let mut w = WaveFormBuilder::new_sine(SampleRate::Sr48000, 440.0)
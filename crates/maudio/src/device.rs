@@ -19,18 +19,23 @@ use crate::{
         device_info::DeviceInfo,
         device_state::DeviceState,
         device_type::DeviceType,
+        stream_format::StreamFormat,
+        timing::DeviceTiming,
     },
     pcm_frames::PcmFormat,
     util::{device_notif::DeviceStateNotifier, proc_notif::ProcFramesNotif},
-    Binding, MaResult,
+    Binding, MaResult, MaudioError,
 };
 
+pub mod android;
 pub mod device_builder;
 pub(crate) mod device_cb_notif;
 pub mod device_id;
 pub mod device_info;
 pub mod device_state;
 pub mod device_type;
+pub mod stream_format;
+pub mod timing;
 
 /// Owned audio device.
 ///
@@ -199,6 +204,54 @@ pub trait DeviceOps: AsDevicePtr {
         unsafe { (*private_device::device_ptr(self)).capture.channels }
     }
 
+    /// Retrieves the negotiated playback format. See [`StreamFormat`]
+    ///
+    /// Returns an error if the device is not setup for playback
+    fn stream_format_playback(&self) -> MaResult<StreamFormat> {
+        let device = unsafe { &*private_device::device_ptr(self) };
+        if device.playback.channels == 0 {
+            return Err(MaudioError::from_ma_result(
+                sys::ma_result_MA_INVALID_OPERATION,
+            ));
+        }
+        StreamFormat::from_playback(device)
+    }
+
+    /// Retrieves the negotiated capture format. See [`StreamFormat`]
+    ///
+    /// Returns an error if the device is not setup for capture
+    fn stream_format_capture(&self) -> MaResult<StreamFormat> {
+        let device = unsafe { &*private_device::device_ptr(self) };
+        if device.capture.channels == 0 {
+            return Err(MaudioError::from_ma_result(
+                sys::ma_result_MA_INVALID_OPERATION,
+            ));
+        }
+        StreamFormat::from_capture(device)
+    }
+
+    /// Retrieves the buffering and estimated latency of the device. See [`DeviceTiming`]
+    ///
+    /// `device_type` selects the playback or capture side. [`DeviceType::Loopback`] is the
+    /// capture side, and [`DeviceType::Duplex`] is not accepted.
+    ///
+    /// Returns an error if the device is not setup for that side
+    fn timing(&self, device_type: DeviceType) -> MaResult<DeviceTiming> {
+        let device = unsafe { &*private_device::device_ptr(self) };
+        match device_type {
+            DeviceType::Playback if device.playback.channels != 0 => {
+                Ok(DeviceTiming::from_playback(device))
+            }
+            DeviceType::Capture | DeviceType::Loopback if device.capture.channels != 0 => {
+                Ok(DeviceTiming::from_capture(device))
+            }
+            DeviceType::Duplex => Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS)),
+            _ => Err(MaudioError::from_ma_result(
+                sys::ma_result_MA_INVALID_OPERATION,
+            )),
+        }
+    }
+
     /// Returns the associated context, if available.
     fn get_context(&self) -> Option<ContextRef<'_>>
     where
@@ -343,7 +396,7 @@ pub(crate) mod device_ffi {
             device_info::DeviceInfo,
             device_state::DeviceState,
             device_type::DeviceType,
-            private_device, AsDevicePtr, Device, DeviceInner,
+            private_device, AsDevicePtr, DeviceInner,
         },
         pcm_frames::PcmFormat,
         AsRawRef, Binding, MaResult, MaudioError,
@@ -469,16 +522,16 @@ pub(crate) mod device_ffi {
     // Callback: not safe
     // Theadsafe: SAFE
     #[inline]
-    pub fn ma_device_start<F: PcmFormat>(device: &mut Device<F>) -> MaResult<()> {
-        let res = unsafe { sys::ma_device_start(device.to_raw()) };
+    pub fn ma_device_start<D: AsDevicePtr + ?Sized>(device: &D) -> MaResult<()> {
+        let res = unsafe { sys::ma_device_start(private_device::device_ptr(device)) };
         MaudioError::check(res)
     }
 
     // Callback: not safe
     // Theadsafe: SAFE
     #[inline]
-    pub fn ma_device_stop<F: PcmFormat>(device: &mut Device<F>) -> MaResult<()> {
-        let res = unsafe { sys::ma_device_stop(device.to_raw()) };
+    pub fn ma_device_stop<D: AsDevicePtr + ?Sized>(device: &D) -> MaResult<()> {
+        let res = unsafe { sys::ma_device_stop(private_device::device_ptr(device)) };
         MaudioError::check(res)
     }
 
@@ -0,0 +1,304 @@
+//! Android specific device options, for the AAudio and OpenSL|ES backends.
+//!
+//! These are set on a device with the `aaudio_*` and `opensl_*` methods of
+//! [`DeviceBuilderOps`](crate::device::device_builder::DeviceBuilderOps), and are ignored by
+//! every other backend. Setting them on other platforms is harmless, so they can be left in
+//! cross-platform code.
+//!
+//! The AAudio performance mode follows the device
+//! [`PerformanceProfile`](crate::audio::performance::PerformanceProfile):
+//! `LowLatency` requests `AAUDIO_PERFORMANCE_MODE_LOW_LATENCY`.
+//!
+//! miniaudio opens AAudio and OpenSL|ES through the NDK, so no JNI setup is needed before
+//! creating a device. The engine creates its own device without these options. To use them
+//! with an engine, create the device with [`DeviceBuilder`](crate::device::device_builder::DeviceBuilder).
+//!
+//! ```no_run
+//! # use maudio::device::device_builder::{DeviceBuilder, DeviceBuilderOps};
+//! # use maudio::device::android::{AAudioContentType, AAudioUsage};
+//! # use maudio::audio::performance::PerformanceProfile;
+//! # fn main() -> maudio::MaResult<()> {
+//! let device = DeviceBuilder::playback()
+//!     .f32()
+//!     .performance_profile(PerformanceProfile::LowLatency)
+//!     .aaudio_usage(AAudioUsage::Game)
+//!     .aaudio_content_type(AAudioContentType::Sonification)
+//!     .with_callback(|_device, output| output.fill(0.0))?;
+//! # drop(device);
+//! # Ok(())
+//! # }
+//! ```
+use maudio_sys::ffi as sys;
+
+/// How the stream is used, for AAudio (`AAUDIO_USAGE_*`).
+///
+/// Android uses it to route the stream and to decide how it is affected by focus changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AAudioUsage {
+    #[default]
+    /// Leaves the usage unset.
+    Default,
+    Media,
+    VoiceCommunication,
+    VoiceCommunicationSignalling,
+    Alarm,
+    Notification,
+    NotificationRingtone,
+    NotificationEvent,
+    AssistanceAccessibility,
+    AssistanceNavigationGuidance,
+    AssistanceSonification,
+    Game,
+    Assistant,
+    /// System usage.
+    Emergency,
+    /// System usage.
+    Safety,
+    /// System usage.
+    VehicleStatus,
+    /// System usage.
+    Announcement,
+}
+
+impl From<AAudioUsage> for sys::ma_aaudio_usage {
+    fn from(value: AAudioUsage) -> Self {
+        match value {
+            AAudioUsage::Default => sys::ma_aaudio_usage_ma_aaudio_usage_default,
+            AAudioUsage::Media => sys::ma_aaudio_usage_ma_aaudio_usage_media,
+            AAudioUsage::VoiceCommunication => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_voice_communication
+            }
+            AAudioUsage::VoiceCommunicationSignalling => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_voice_communication_signalling
+            }
+            AAudioUsage::Alarm => sys::ma_aaudio_usage_ma_aaudio_usage_alarm,
+            AAudioUsage::Notification => sys::ma_aaudio_usage_ma_aaudio_usage_notification,
+            AAudioUsage::NotificationRingtone => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_notification_ringtone
+            }
+            AAudioUsage::NotificationEvent => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_notification_event
+            }
+            AAudioUsage::AssistanceAccessibility => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_assistance_accessibility
+            }
+            AAudioUsage::AssistanceNavigationGuidance => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_assistance_navigation_guidance
+            }
+            AAudioUsage::AssistanceSonification => {
+                sys::ma_aaudio_usage_ma_aaudio_usage_assistance_sonification
+            }
+            AAudioUsage::Game => sys::ma_aaudio_usage_ma_aaudio_usage_game,
+            AAudioUsage::Assistant => sys::ma_aaudio_usage_ma_aaudio_usage_assitant,
+            AAudioUsage::Emergency => sys::ma_aaudio_usage_ma_aaudio_usage_emergency,
+            AAudioUsage::Safety => sys::ma_aaudio_usage_ma_aaudio_usage_safety,
+            AAudioUsage::VehicleStatus => sys::ma_aaudio_usage_ma_aaudio_usage_vehicle_status,
+            AAudioUsage::Announcement => sys::ma_aaudio_usage_ma_aaudio_usage_announcement,
+        }
+    }
+}
+
+/// Type of content in the stream, for AAudio (`AAUDIO_CONTENT_TYPE_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AAudioContentType {
+    #[default]
+    /// Leaves the content type unset.
+    Default,
+    Speech,
+    Music,
+    Movie,
+    Sonification,
+}
+
+impl From<AAudioContentType> for sys::ma_aaudio_content_type {
+    fn from(value: AAudioContentType) -> Self {
+        match value {
+            AAudioContentType::Default => {
+                sys::ma_aaudio_content_type_ma_aaudio_content_type_default
+            }
+            AAudioContentType::Speech => sys::ma_aaudio_content_type_ma_aaudio_content_type_speech,
+            AAudioContentType::Music => sys::ma_aaudio_content_type_ma_aaudio_content_type_music,
+            AAudioContentType::Movie => sys::ma_aaudio_content_type_ma_aaudio_content_type_movie,
+            AAudioContentType::Sonification => {
+                sys::ma_aaudio_content_type_ma_aaudio_content_type_sonification
+            }
+        }
+    }
+}
+
+/// Processing applied to captured audio, for AAudio (`AAUDIO_INPUT_PRESET_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AAudioInputPreset {
+    #[default]
+    /// Leaves the input preset unset.
+    Default,
+    Generic,
+    Camcorder,
+    VoiceRecognition,
+    VoiceCommunication,
+    Unprocessed,
+    VoicePerformance,
+}
+
+impl From<AAudioInputPreset> for sys::ma_aaudio_input_preset {
+    fn from(value: AAudioInputPreset) -> Self {
+        match value {
+            AAudioInputPreset::Default => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_default
+            }
+            AAudioInputPreset::Generic => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_generic
+            }
+            AAudioInputPreset::Camcorder => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_camcorder
+            }
+            AAudioInputPreset::VoiceRecognition => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_voice_recognition
+            }
+            AAudioInputPreset::VoiceCommunication => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_voice_communication
+            }
+            AAudioInputPreset::Unprocessed => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_unprocessed
+            }
+            AAudioInputPreset::VoicePerformance => {
+                sys::ma_aaudio_input_preset_ma_aaudio_input_preset_voice_performance
+            }
+        }
+    }
+}
+
+/// Whether other applications may capture the output of a playback stream, for AAudio (`AAUDIO_ALLOW_CAPTURE_BY_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AAudioCapturePolicy {
+    #[default]
+    /// Leaves the policy unset.
+    Default,
+    All,
+    System,
+    None,
+}
+
+impl From<AAudioCapturePolicy> for sys::ma_aaudio_allowed_capture_policy {
+    fn from(value: AAudioCapturePolicy) -> Self {
+        match value {
+            AAudioCapturePolicy::Default => {
+                sys::ma_aaudio_allowed_capture_policy_ma_aaudio_allow_capture_default
+            }
+            AAudioCapturePolicy::All => {
+                sys::ma_aaudio_allowed_capture_policy_ma_aaudio_allow_capture_by_all
+            }
+            AAudioCapturePolicy::System => {
+                sys::ma_aaudio_allowed_capture_policy_ma_aaudio_allow_capture_by_system
+            }
+            AAudioCapturePolicy::None => {
+                sys::ma_aaudio_allowed_capture_policy_ma_aaudio_allow_capture_by_none
+            }
+        }
+    }
+}
+
+/// Stream type of a playback stream, for OpenSL|ES (`SL_ANDROID_STREAM_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenSlStreamType {
+    #[default]
+    /// Leaves the stream type unset.
+    Default,
+    Voice,
+    System,
+    Ring,
+    Media,
+    Alarm,
+    Notification,
+}
+
+impl From<OpenSlStreamType> for sys::ma_opensl_stream_type {
+    fn from(value: OpenSlStreamType) -> Self {
+        match value {
+            OpenSlStreamType::Default => sys::ma_opensl_stream_type_ma_opensl_stream_type_default,
+            OpenSlStreamType::Voice => sys::ma_opensl_stream_type_ma_opensl_stream_type_voice,
+            OpenSlStreamType::System => sys::ma_opensl_stream_type_ma_opensl_stream_type_system,
+            OpenSlStreamType::Ring => sys::ma_opensl_stream_type_ma_opensl_stream_type_ring,
+            OpenSlStreamType::Media => sys::ma_opensl_stream_type_ma_opensl_stream_type_media,
+            OpenSlStreamType::Alarm => sys::ma_opensl_stream_type_ma_opensl_stream_type_alarm,
+            OpenSlStreamType::Notification => {
+                sys::ma_opensl_stream_type_ma_opensl_stream_type_notification
+            }
+        }
+    }
+}
+
+/// Processing applied to captured audio, for OpenSL|ES (`SL_ANDROID_RECORDING_PRESET_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenSlRecordingPreset {
+    #[default]
+    /// Leaves the recording preset unset.
+    Default,
+    Generic,
+    Camcorder,
+    VoiceRecognition,
+    VoiceCommunication,
+    Unprocessed,
+}
+
+impl From<OpenSlRecordingPreset> for sys::ma_opensl_recording_preset {
+    fn from(value: OpenSlRecordingPreset) -> Self {
+        match value {
+            OpenSlRecordingPreset::Default => {
+                sys::ma_opensl_recording_preset_ma_opensl_recording_preset_default
+            }
+            OpenSlRecordingPreset::Generic => {
+                sys::ma_opensl_recording_preset_ma_opensl_recording_preset_generic
+            }
+            OpenSlRecordingPreset::Camcorder => {
+                sys::ma_opensl_recording_preset_ma_opensl_recording_preset_camcorder
+            }
+            OpenSlRecordingPreset::VoiceRecognition => {
+                sys::ma_opensl_recording_preset_ma_opensl_recording_preset_voice_recognition
+            }
+            OpenSlRecordingPreset::VoiceCommunication => {
+                sys::ma_opensl_recording_preset_ma_opensl_recording_preset_voice_communication
+            }
+            OpenSlRecordingPreset::Unprocessed => {
+                sys::ma_opensl_recording_preset_ma_opensl_recording_preset_voice_unprocessed
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_android_enums_default_is_unset() {
+        assert_eq!(
+            sys::ma_aaudio_usage::from(AAudioUsage::default()),
+            sys::ma_aaudio_usage_ma_aaudio_usage_default
+        );
+        assert_eq!(
+            sys::ma_aaudio_content_type::from(AAudioContentType::default()),
+            sys::ma_aaudio_content_type_ma_aaudio_content_type_default
+        );
+        assert_eq!(
+            sys::ma_opensl_stream_type::from(OpenSlStreamType::default()),
+            sys::ma_opensl_stream_type_ma_opensl_stream_type_default
+        );
+    }
+
+    #[test]
+    fn test_android_enums_to_sys() {
+        assert_eq!(
+            sys::ma_aaudio_usage::from(AAudioUsage::Game),
+            sys::ma_aaudio_usage_ma_aaudio_usage_game
+        );
+        assert_eq!(
+            sys::ma_aaudio_allowed_capture_policy::from(AAudioCapturePolicy::None),
+            sys::ma_aaudio_allowed_capture_policy_ma_aaudio_allow_capture_by_none
+        );
+        assert_eq!(
+            sys::ma_opensl_recording_preset::from(OpenSlRecordingPreset::Unprocessed),
+            sys::ma_opensl_recording_preset_ma_opensl_recording_preset_voice_unprocessed
+        );
+    }
+}
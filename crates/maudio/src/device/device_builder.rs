@@ -53,7 +53,6 @@
 use std::{
     cell::UnsafeCell,
     marker::PhantomData,
-    panic::{catch_unwind, AssertUnwindSafe},
     slice,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -65,13 +64,17 @@ use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{
-        channels::{Channel, ChannelMixMode},
+        channels::{ChannelLayout, ChannelMixMode},
         performance::PerformanceProfile,
         sample_rate::SampleRate,
     },
     backend::Backend,
     context::ContextBuilder,
     device::{
+        android::{
+            AAudioCapturePolicy, AAudioContentType, AAudioInputPreset, AAudioUsage,
+            OpenSlRecordingPreset, OpenSlStreamType,
+        },
         device_cb_notif::{
             device_notification_capture_callback, device_notification_duplex_callback,
             device_notification_loopback_callback, device_notification_playback_callback,
@@ -81,6 +84,7 @@ use crate::{
         CallBackDevice, Device,
     },
     pcm_frames::{PcmFormat, S24Packed},
+    util::{callback_panic, rt_check},
     util::{device_notif::DeviceStateNotifier, proc_notif::ProcFramesNotif},
     AsRawRef, MaResult,
 };
@@ -768,13 +772,13 @@ pub trait DeviceBuilderOps<'a>: AsDeviceBuilder<'a> {
 
     /// Sets the playback channel map.
     ///
-    /// Also updates the channel count to match the map length.
-    fn playback_channel_map(&mut self, map: &[Channel]) -> &mut Self
+    /// Also updates the channel count to match the map.
+    fn playback_channel_map(&mut self, map: &'a ChannelLayout) -> &mut Self
     where
         Self: private_device_b::SupportsPlayback,
     {
-        private_device_b::inner(self).playback.pChannelMap = map.as_ptr() as *mut _;
-        private_device_b::inner(self).playback.channels = map.len() as _;
+        private_device_b::inner(self).playback.pChannelMap = map.as_raw_ptr() as *mut _;
+        private_device_b::inner(self).playback.channels = map.channels();
         self
     }
 
@@ -820,13 +824,13 @@ pub trait DeviceBuilderOps<'a>: AsDeviceBuilder<'a> {
 
     /// Sets the capture channel map.
     ///
-    /// Also updates the channel count to match the map length.
-    fn capture_channel_map(&mut self, map: &[Channel]) -> &mut Self
+    /// Also updates the channel count to match the map.
+    fn capture_channel_map(&mut self, map: &'a ChannelLayout) -> &mut Self
     where
         Self: private_device_b::SupportsCapture,
     {
-        private_device_b::inner(self).capture.pChannelMap = map.as_ptr() as *mut _;
-        private_device_b::inner(self).capture.channels = map.len() as _;
+        private_device_b::inner(self).capture.pChannelMap = map.as_raw_ptr() as *mut _;
+        private_device_b::inner(self).capture.channels = map.channels();
         self
     }
 
@@ -848,6 +852,83 @@ pub trait DeviceBuilderOps<'a>: AsDeviceBuilder<'a> {
         self
     }
 
+    /// Sets the AAudio usage of the playback stream. Only used on Android.
+    fn aaudio_usage(&mut self, usage: AAudioUsage) -> &mut Self
+    where
+        Self: private_device_b::SupportsPlayback,
+    {
+        private_device_b::inner(self).aaudio.usage = usage.into();
+        self
+    }
+
+    /// Sets the AAudio content type of the playback stream. Only used on Android.
+    fn aaudio_content_type(&mut self, content_type: AAudioContentType) -> &mut Self
+    where
+        Self: private_device_b::SupportsPlayback,
+    {
+        private_device_b::inner(self).aaudio.contentType = content_type.into();
+        self
+    }
+
+    /// Sets whether other applications can capture the playback stream. Only used on Android.
+    fn aaudio_allowed_capture_policy(&mut self, policy: AAudioCapturePolicy) -> &mut Self
+    where
+        Self: private_device_b::SupportsPlayback,
+    {
+        private_device_b::inner(self).aaudio.allowedCapturePolicy = policy.into();
+        self
+    }
+
+    /// Sets the AAudio input preset of the capture stream. Only used on Android.
+    fn aaudio_input_preset(&mut self, preset: AAudioInputPreset) -> &mut Self
+    where
+        Self: private_device_b::SupportsCapture,
+    {
+        private_device_b::inner(self).aaudio.inputPreset = preset.into();
+        self
+    }
+
+    /// Controls whether an AAudio device is restarted after it is rerouted,
+    /// for example when headphones are plugged in.
+    ///
+    /// Enabled by default.
+    fn aaudio_auto_start_after_reroute(&mut self, yes: bool) -> &mut Self {
+        private_device_b::inner(self).aaudio.noAutoStartAfterReroute = (!yes) as u32;
+        self
+    }
+
+    /// Sets the OpenSL|ES stream type of the playback stream. Only used on Android.
+    fn opensl_stream_type(&mut self, stream_type: OpenSlStreamType) -> &mut Self
+    where
+        Self: private_device_b::SupportsPlayback,
+    {
+        private_device_b::inner(self).opensl.streamType = stream_type.into();
+        self
+    }
+
+    /// Sets the OpenSL|ES recording preset of the capture stream. Only used on Android.
+    fn opensl_recording_preset(&mut self, preset: OpenSlRecordingPreset) -> &mut Self
+    where
+        Self: private_device_b::SupportsCapture,
+    {
+        private_device_b::inner(self).opensl.recordingPreset = preset.into();
+        self
+    }
+
+    /// Enables miniaudio's workarounds for known issues in the AAudio and OpenSL|ES
+    /// implementations of some devices.
+    ///
+    /// Disabled by default.
+    fn android_compatibility_workarounds(&mut self, yes: bool) -> &mut Self {
+        private_device_b::inner(self)
+            .aaudio
+            .enableCompatibilityWorkarounds = yes as u32;
+        private_device_b::inner(self)
+            .opensl
+            .enableCompatibilityWorkarounds = yes as u32;
+        self
+    }
+
     /// Enables unclipped floating-point playback output.
     ///
     /// When enabled, miniaudio will not clip the playback output buffer after the
@@ -1058,7 +1139,8 @@ impl<'a, F: PcmFormat> PlaybackDeviceBuilder<'a, F> {
     /// ## Panics
     ///
     /// Panics are trapped to avoid unwinding across the FFI boundary. After a
-    /// panic, the callback is poisoned and will no longer run user code.
+    /// panic, the callback is poisoned and will no longer run user code, unless the
+    /// [`callback_panic`](crate::util::callback_panic) policy aborts the process.
     pub fn with_callback<C>(&mut self, f: C) -> MaResult<Device<F>>
     where
         C: FnMut(CallBackDevice, &mut [F::StorageUnit]) + Send + 'static,
@@ -1144,7 +1226,8 @@ impl<'a, F: PcmFormat> CaptureDeviceBuilder<'a, F> {
     /// ## Panics
     ///
     /// Panics are trapped to avoid unwinding across the FFI boundary. After a
-    /// panic, the callback is poisoned and will no longer run user code.
+    /// panic, the callback is poisoned and will no longer run user code, unless the
+    /// [`callback_panic`](crate::util::callback_panic) policy aborts the process.
     pub fn with_callback<C>(&mut self, f: C) -> MaResult<Device<F>>
     where
         C: FnMut(CallBackDevice, &[F::StorageUnit]) + Send + 'static,
@@ -1233,7 +1316,8 @@ impl<'a, F: PcmFormat> DuplexDeviceBuilder<'a, F> {
     /// ## Panics
     ///
     /// Panics are trapped to avoid unwinding across the FFI boundary. After a
-    /// panic, the callback is poisoned and will no longer run user code.
+    /// panic, the callback is poisoned and will no longer run user code, unless the
+    /// [`callback_panic`](crate::util::callback_panic) policy aborts the process.
     pub fn with_callback<C>(&mut self, f: C) -> MaResult<Device<F>>
     where
         C: FnMut(CallBackDevice, &mut [F::StorageUnit], &[F::StorageUnit]) + Send + 'static,
@@ -1325,7 +1409,8 @@ impl<'a, F: PcmFormat> LoopbackDeviceBuilder<'a, F> {
     /// ## Panics
     ///
     /// Panics are trapped to avoid unwinding across the FFI boundary. After a
-    /// panic, the callback is poisoned and will no longer run user code.
+    /// panic, the callback is poisoned and will no longer run user code, unless the
+    /// [`callback_panic`](crate::util::callback_panic) policy aborts the process.
     pub fn with_callback<C>(&mut self, f: C) -> MaResult<Device<F>>
     where
         C: FnMut(CallBackDevice, &[F::StorageUnit]) + Send + 'static,
@@ -1450,7 +1535,8 @@ unsafe extern "C" fn device_data_playback_callback<F: PcmFormat, C>(
 
     // Run the callback
     let cb = &mut *state.f.get();
-    let res = catch_unwind(AssertUnwindSafe(|| (cb)(cb_device, slice)));
+    let _rt = rt_check::enter();
+    let res = callback_panic::guard(|| (cb)(cb_device, slice));
     if res.is_err() {
         // The callback is now poisoned
         state.panic_flag.store(true, Ordering::Release);
@@ -1503,7 +1589,8 @@ unsafe extern "C" fn device_data_capture_callback<F: PcmFormat, C>(
 
     // Run the callback
     let cb = &mut *state.f.get();
-    let res = catch_unwind(AssertUnwindSafe(|| (cb)(cb_device, slice)));
+    let _rt = rt_check::enter();
+    let res = callback_panic::guard(|| (cb)(cb_device, slice));
     if res.is_err() {
         // The callback is now poisoned
         state.panic_flag.store(true, Ordering::Release);
@@ -1558,7 +1645,8 @@ unsafe extern "C" fn device_data_duplex_callback<F: PcmFormat, C>(
 
     // Run the callback
     let cb = &mut *state.f.get();
-    let res = catch_unwind(AssertUnwindSafe(|| (cb)(cb_device, out_slice, in_slice)));
+    let _rt = rt_check::enter();
+    let res = callback_panic::guard(|| (cb)(cb_device, out_slice, in_slice));
     if res.is_err() {
         // The callback is now poisoned
         state.panic_flag.store(true, Ordering::Release);
@@ -1611,7 +1699,8 @@ unsafe extern "C" fn device_data_loopback_callback<F: PcmFormat, C>(
 
     // Run the callback
     let cb = &mut *state.f.get();
-    let res = catch_unwind(AssertUnwindSafe(|| (cb)(cb_device, slice)));
+    let _rt = rt_check::enter();
+    let res = callback_panic::guard(|| (cb)(cb_device, slice));
     if res.is_err() {
         // The callback is now poisoned
         state.panic_flag.store(true, Ordering::Release);
@@ -1792,4 +1881,98 @@ mod test {
         assert!(notif.contains(DeviceNotificationType::Started));
         device.device_stop().unwrap();
     }
+
+    #[test]
+    fn test_device_builder_android_options_set_config() {
+        use crate::device::{
+            android::{AAudioInputPreset, AAudioUsage, OpenSlStreamType},
+            device_builder::{DeviceBuilder, DeviceBuilderOps},
+        };
+        use crate::{sys, AsRawRef};
+
+        let mut builder = DeviceBuilder::duplex();
+        let mut builder = builder.f32();
+        builder
+            .aaudio_usage(AAudioUsage::Game)
+            .aaudio_input_preset(AAudioInputPreset::Unprocessed)
+            .aaudio_auto_start_after_reroute(false)
+            .opensl_stream_type(OpenSlStreamType::Media)
+            .android_compatibility_workarounds(true);
+
+        let raw = builder.as_raw();
+        assert_eq!(raw.aaudio.usage, sys::ma_aaudio_usage_ma_aaudio_usage_game);
+        assert_eq!(
+            raw.aaudio.inputPreset,
+            sys::ma_aaudio_input_preset_ma_aaudio_input_preset_unprocessed
+        );
+        assert_eq!(raw.aaudio.noAutoStartAfterReroute, 1);
+        assert_eq!(
+            raw.opensl.streamType,
+            sys::ma_opensl_stream_type_ma_opensl_stream_type_media
+        );
+        assert_eq!(raw.aaudio.enableCompatibilityWorkarounds, 1);
+        assert_eq!(raw.opensl.enableCompatibilityWorkarounds, 1);
+    }
+
+    #[test]
+    fn test_device_builder_playback_stream_format() {
+        use crate::{
+            audio::{formats::Format, sample_rate::SampleRate},
+            backend::Backend,
+            context::ContextBuilder,
+            device::{
+                device_builder::{DeviceBuilder, DeviceBuilderOps},
+                device_type::DeviceShareMode,
+                DeviceOps,
+            },
+        };
+
+        let mut ctx = ContextBuilder::new();
+        ctx.preferred_backends(&[Backend::Null]);
+        let device = DeviceBuilder::playback()
+            .i16()
+            .playback_channels(2)
+            .sample_rate(SampleRate::Sr44100)
+            .context(&ctx)
+            .with_callback(|_a, b| b.fill(0))
+            .unwrap();
+
+        let format = device.stream_format_playback().unwrap();
+        assert_eq!(format.share_mode, DeviceShareMode::Shared);
+        assert_eq!(format.format, Format::S16);
+        assert_eq!(format.channels, 2);
+        assert_eq!(format.sample_rate, SampleRate::Sr44100);
+        assert!(format.internal_channels > 0);
+        assert!(device.stream_format_capture().is_err());
+    }
+
+    #[test]
+    fn test_device_builder_playback_timing() {
+        use crate::{
+            backend::Backend,
+            context::ContextBuilder,
+            device::{
+                device_builder::{DeviceBuilder, DeviceBuilderOps},
+                device_type::DeviceType,
+                DeviceOps,
+            },
+        };
+
+        let mut ctx = ContextBuilder::new();
+        ctx.preferred_backends(&[Backend::Null]);
+        let device = DeviceBuilder::playback()
+            .f32()
+            .playback_channels(2)
+            .period_size_frames(256)
+            .context(&ctx)
+            .with_callback(|_a, b| b.fill(0.0))
+            .unwrap();
+
+        let timing = device.timing(DeviceType::Playback).unwrap();
+        assert!(timing.period_size_frames > 0);
+        assert!(timing.periods > 0);
+        assert!(timing.latency_frames() >= timing.buffer_frames());
+        assert!(device.timing(DeviceType::Capture).is_err());
+        assert!(device.timing(DeviceType::Duplex).is_err());
+    }
 }
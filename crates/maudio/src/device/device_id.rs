@@ -1,9 +1,13 @@
 //! Audio device identifier definitions.
-use std::sync::Arc;
+use std::{
+    hash::{Hash, Hasher},
+    mem::size_of,
+    sync::Arc,
+};
 
 use maudio_sys::ffi as sys;
 
-use crate::AsRawRef;
+use crate::{AsRawRef, ErrorKinds, MaResult, MaudioError};
 
 /// Identifies an audio device reported by [`Context`](crate::context::Context) enumeration.
 ///
@@ -12,6 +16,32 @@ use crate::AsRawRef;
 ///
 /// This is a thin value wrapper over miniaudio's `ma_device_id`. It does not own any external
 /// resources and can be cheaply copied.
+///
+/// ## Remembering a device
+///
+/// Unlike the position of a device in the enumeration, or its display name, the ID is assigned
+/// by the backend and stays the same when devices are added or removed. Use [`DeviceId::to_bytes`]
+/// to store it, and [`DeviceId::from_bytes`] to restore it on the next run. An ID is only
+/// meaningful to the backend that reported it.
+///
+/// ```no_run
+/// # use maudio::context::{ContextBuilder, ContextOps};
+/// # use maudio::device::{device_id::DeviceId, device_type::DeviceType};
+/// # use maudio::engine::engine_builder::EngineBuilder;
+/// # fn main() -> maudio::MaResult<()> {
+/// # let saved: Vec<u8> = Vec::new();
+/// let ctx = ContextBuilder::new().build()?;
+/// let id = DeviceId::from_bytes(&saved)?;
+///
+/// let mut builder = EngineBuilder::new();
+/// // Fall back to the default device if the saved one is no longer available
+/// if ctx.find_device(DeviceType::Playback, &id)?.is_some() {
+///     builder.device_id(&id);
+/// }
+/// let engine = builder.build()?;
+/// # Ok(())
+/// # }
+/// ```
 #[repr(transparent)]
 #[derive(Clone)]
 pub struct DeviceId {
@@ -39,6 +69,63 @@ impl DeviceId {
             inner: Arc::new(DeviceIdInner { inner: *id }),
         }
     }
+
+    /// Returns the raw bytes of the ID, for storage.
+    ///
+    /// Trailing zero bytes are not included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bytes = self.raw_bytes();
+        let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        bytes[..len].to_vec()
+    }
+
+    /// Restores an ID from bytes returned by [`DeviceId::to_bytes`].
+    ///
+    /// Returns an error if `bytes` is longer than a `ma_device_id`.
+    pub fn from_bytes(bytes: &[u8]) -> MaResult<Self> {
+        if bytes.len() > size_of::<sys::ma_device_id>() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "DeviceId::from_bytes",
+                expected: size_of::<sys::ma_device_id>(),
+                actual: bytes.len(),
+            }));
+        }
+        let mut id: sys::ma_device_id = unsafe { std::mem::zeroed() };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                (&mut id as *mut sys::ma_device_id).cast::<u8>(),
+                bytes.len(),
+            );
+        }
+        Ok(Self::from_raw(&id))
+    }
+
+    fn raw_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self.as_raw_ptr().cast::<u8>(),
+                size_of::<sys::ma_device_id>(),
+            )
+        }
+    }
+}
+
+impl core::fmt::Debug for DeviceId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "DeviceId(")?;
+        for b in self.to_bytes() {
+            write!(f, "{b:02x}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+// Consistent with `ma_device_id_equal`, which compares every byte.
+impl Hash for DeviceId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw_bytes().hash(state);
+    }
 }
 
 impl PartialEq for DeviceId {
@@ -47,3 +134,37 @@ impl PartialEq for DeviceId {
     }
 }
 impl Eq for DeviceId {}
+
+#[cfg(test)]
+mod test {
+    use std::mem::size_of;
+
+    use maudio_sys::ffi as sys;
+
+    use crate::device::device_id::DeviceId;
+
+    #[test]
+    fn test_device_id_bytes_roundtrip() {
+        let mut raw: sys::ma_device_id = unsafe { std::mem::zeroed() };
+        let name = b"hw:1,0";
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                name.as_ptr(),
+                (&mut raw as *mut sys::ma_device_id).cast::<u8>(),
+                name.len(),
+            );
+        }
+        let id = DeviceId::from_raw(&raw);
+
+        let bytes = id.to_bytes();
+        assert_eq!(bytes, name);
+        let restored = DeviceId::from_bytes(&bytes).unwrap();
+        assert_eq!(restored, id);
+    }
+
+    #[test]
+    fn test_device_id_from_bytes_too_long() {
+        let bytes = vec![1u8; size_of::<sys::ma_device_id>() + 1];
+        assert!(DeviceId::from_bytes(&bytes).is_err());
+    }
+}
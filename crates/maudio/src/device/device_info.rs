@@ -157,6 +157,18 @@ impl Devices {
     pub fn iter(&self) -> impl Iterator<Item = (DeviceType, &DeviceInfo)> {
         self.playback().chain(self.capture())
     }
+
+    /// Finds a device of `device_type` by its ID.
+    ///
+    /// Only playback and capture devices are listed. Any other `device_type` returns `None`.
+    pub fn find(&self, device_type: DeviceType, device_id: &DeviceId) -> Option<&DeviceInfo> {
+        let list = match device_type {
+            DeviceType::Playback => &self.playback,
+            DeviceType::Capture => &self.capture,
+            _ => return None,
+        };
+        list.iter().find(|d| d.device_id() == *device_id)
+    }
 }
 
 /// A single native format reported by a device during enumeration.
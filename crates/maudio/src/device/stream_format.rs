@@ -0,0 +1,65 @@
+//! Format negotiated between a device and its backend.
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{formats::Format, sample_rate::SampleRate},
+    device::device_type::DeviceShareMode,
+    MaResult,
+};
+
+/// Format of one direction (playback or capture) of an initialized device.
+///
+/// The `format`, `channels` and `sample_rate` fields describe the data seen by the
+/// callback. The `internal_*` fields are what the backend actually opened. When they
+/// differ, miniaudio converts between the two, which adds processing to the callback.
+/// Requested values are hints, so for low-latency or exclusive mode output, check
+/// [`is_converted`](Self::is_converted) after the device is created.
+///
+/// For an [`Engine`](crate::engine::Engine), use the device returned by
+/// [`Engine::device`](crate::engine::Engine::device).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamFormat {
+    /// Share mode the stream was opened with.
+    pub share_mode: DeviceShareMode,
+    pub format: Format,
+    pub channels: u32,
+    pub sample_rate: SampleRate,
+    pub internal_format: Format,
+    pub internal_channels: u32,
+    pub internal_sample_rate: SampleRate,
+}
+
+impl StreamFormat {
+    /// Returns `true` if miniaudio converts between the callback format and the backend format.
+    pub fn is_converted(&self) -> bool {
+        self.format != self.internal_format
+            || self.channels != self.internal_channels
+            || self.sample_rate != self.internal_sample_rate
+    }
+
+    pub(crate) fn from_playback(device: &sys::ma_device) -> MaResult<Self> {
+        let p = &device.playback;
+        Ok(Self {
+            share_mode: p.shareMode.try_into()?,
+            format: p.format.try_into()?,
+            channels: p.channels,
+            sample_rate: device.sampleRate.try_into()?,
+            internal_format: p.internalFormat.try_into()?,
+            internal_channels: p.internalChannels,
+            internal_sample_rate: p.internalSampleRate.try_into()?,
+        })
+    }
+
+    pub(crate) fn from_capture(device: &sys::ma_device) -> MaResult<Self> {
+        let c = &device.capture;
+        Ok(Self {
+            share_mode: c.shareMode.try_into()?,
+            format: c.format.try_into()?,
+            channels: c.channels,
+            sample_rate: device.sampleRate.try_into()?,
+            internal_format: c.internalFormat.try_into()?,
+            internal_channels: c.internalChannels,
+            internal_sample_rate: c.internalSampleRate.try_into()?,
+        })
+    }
+}
@@ -0,0 +1,113 @@
+//! Buffering and latency of an initialized device.
+use std::time::Duration;
+
+use maudio_sys::ffi as sys;
+
+/// Timing of one direction (playback or capture) of an initialized device.
+///
+/// miniaudio does not report the latency of the driver or the hardware. The latency here
+/// is an estimate made from the buffers the backend was opened with, plus the delay added
+/// by resampling when the callback and the backend run at different sample rates. It is
+/// a lower bound, but stable, which makes it suitable for A/V sync: delay video by
+/// [`latency`](Self::latency), or schedule events that much earlier.
+///
+/// All frame counts are at the sample rate of the callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceTiming {
+    /// Sample rate of the callback.
+    pub sample_rate: u32,
+    /// Size of one backend period.
+    pub period_size_frames: u32,
+    /// Number of periods in the backend buffer.
+    pub periods: u32,
+    /// Delay added by the data conversion (resampling) between the callback and the backend.
+    pub conversion_latency_frames: u64,
+}
+
+impl DeviceTiming {
+    /// Size of the backend buffer, in frames.
+    pub fn buffer_frames(&self) -> u64 {
+        self.period_size_frames as u64 * self.periods.max(1) as u64
+    }
+
+    /// Estimated latency, in frames.
+    pub fn latency_frames(&self) -> u64 {
+        self.buffer_frames() + self.conversion_latency_frames
+    }
+
+    /// Estimated latency.
+    pub fn latency(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.latency_frames() as f64 / self.sample_rate as f64)
+    }
+
+    pub(crate) fn from_playback(device: &sys::ma_device) -> Self {
+        let p = &device.playback;
+        // Playback converts from the callback to the backend, so the input side is in callback frames
+        let conversion = unsafe { sys::ma_data_converter_get_input_latency(&p.converter) };
+        Self::new(
+            device.sampleRate,
+            p.internalSampleRate,
+            p.internalPeriodSizeInFrames,
+            p.internalPeriods,
+            conversion,
+        )
+    }
+
+    pub(crate) fn from_capture(device: &sys::ma_device) -> Self {
+        let c = &device.capture;
+        let conversion = unsafe { sys::ma_data_converter_get_output_latency(&c.converter) };
+        Self::new(
+            device.sampleRate,
+            c.internalSampleRate,
+            c.internalPeriodSizeInFrames,
+            c.internalPeriods,
+            conversion,
+        )
+    }
+
+    fn new(
+        sample_rate: u32,
+        internal_sample_rate: u32,
+        internal_period_size: u32,
+        periods: u32,
+        conversion_latency_frames: u64,
+    ) -> Self {
+        let period_size_frames = if internal_sample_rate == 0 || internal_sample_rate == sample_rate
+        {
+            internal_period_size
+        } else {
+            let frames = internal_period_size as u64 * sample_rate as u64;
+            ((frames + internal_sample_rate as u64 - 1) / internal_sample_rate as u64) as u32
+        };
+        Self {
+            sample_rate,
+            period_size_frames,
+            periods,
+            conversion_latency_frames,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_device_timing_latency() {
+        let timing = DeviceTiming::new(48_000, 48_000, 480, 3, 0);
+        assert_eq!(timing.buffer_frames(), 1440);
+        assert_eq!(timing.latency_frames(), 1440);
+        assert_eq!(timing.latency(), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_device_timing_converts_period_to_callback_rate() {
+        // 441 frames at 44.1 kHz is 10 ms, which is 480 frames at 48 kHz
+        let timing = DeviceTiming::new(48_000, 44_100, 441, 2, 16);
+        assert_eq!(timing.period_size_frames, 480);
+        assert_eq!(timing.latency_frames(), 976);
+    }
+}
@@ -9,7 +9,8 @@ use crate::{
     device::device_builder::Unknown,
     engine::AllocationCallbacks,
     pcm_frames::{PcmFormat, S24Packed},
-    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
+    util::callback_panic,
+    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError, ResultContext,
 };
 
 /// Writes PCM audio frames into an encoded output destination.
@@ -250,12 +251,12 @@ unsafe extern "C" fn encoder_write_proc<W: WriteSeek>(
 
     let slice = core::slice::from_raw_parts(buffer_in as _, bytes_to_write);
 
-    match user_data.writer.write_all(slice) {
-        Ok(()) => {
+    match callback_panic::guard(|| user_data.writer.write_all(slice)) {
+        Ok(Ok(())) => {
             *bytes_written = bytes_to_write;
             sys::ma_result_MA_SUCCESS
         }
-        Err(_) => sys::ma_result_MA_ERROR,
+        Ok(Err(_)) | Err(_) => sys::ma_result_MA_ERROR,
     }
 }
 
@@ -282,9 +283,9 @@ unsafe extern "C" fn encoder_seek_proc<W: WriteSeek>(
         _ => return sys::ma_result_MA_INVALID_ARGS,
     };
 
-    match user_data.writer.seek(pos) {
-        Ok(_) => sys::ma_result_MA_SUCCESS,
-        Err(_) => sys::ma_result_MA_ERROR,
+    match callback_panic::guard(|| user_data.writer.seek(pos)) {
+        Ok(Ok(_)) => sys::ma_result_MA_SUCCESS,
+        Ok(Err(_)) | Err(_) => sys::ma_result_MA_ERROR,
     }
 }
 
@@ -573,7 +574,7 @@ impl<F: PcmFormat> EncoderBuilder<F, Unknown> {
 
 impl<F: PcmFormat, E: CodecFormat> EncoderBuilder<F, E> {
     pub fn build_path(&self, path: &Path) -> MaResult<Encoder<F, E, Fs>> {
-        Encoder::<F, E, Fs>::init_from_file(self, path)
+        Encoder::<F, E, Fs>::init_from_file(self, path).with_path(path)
     }
 
     pub fn build_writer<W: WriteSeek>(&self, writer: W) -> MaResult<Encoder<F, E, Cb>> {
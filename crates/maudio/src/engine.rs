@@ -79,43 +79,76 @@ use std::{
     mem::MaybeUninit,
     path::Path,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU32, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 use crate::{
     audio::{
-        formats::SampleBuffer, math::vec3::Vec3, sample_rate::SampleRate, spatial::cone::Cone,
+        dsp::loudness::Loudness,
+        formats::{Dither, SampleBuffer},
+        math::vec3::Vec3,
+        sample_rate::SampleRate,
     },
+    context::Context,
     data_source::AsSourcePtr,
-    device::{device_id::DeviceId, DeviceInner, DeviceRef},
+    device::{device_id::DeviceId, device_type::DeviceType, DeviceInner, DeviceOps, DeviceRef},
     engine::{
+        block_events::BlockSubscription,
         engine_builder::EngineBuilder,
         engine_cb_notif::engine_notification_callback,
+        engine_stats::{EngineStats, StatsCounters},
+        listener::Listener,
+        loudness_cache::LoudnessCache,
+        mix_snapshot::{MixSnapshot, MixSnapshots},
         node_graph::{nodes::NodeRef, NodeGraphRef},
-        process_cb::ProcessState,
-        resource::{ResourceManager, ResourceManagerRef},
+        one_shot::{OneShots, PlayOptions},
+        process_cb::{on_process_callback, ProcessState},
+        resource::{rm_key, rm_registry::ResourceUse, ResourceManager, ResourceManagerRef, RmOps},
+        sound_registry::SoundRegistry,
+        start_policy::StartPolicy,
+        time_scale::ScaledClock,
+        timeline::{CallbackId, Timeline, TimelineEvents},
     },
+    pcm_frames::PcmFormat,
     sound::{
         sound_builder::SoundBuilder,
         sound_ffi,
         sound_flags::SoundFlags,
         sound_group::{SoundGroup, SoundGroupBuilder},
+        sound_params::SoundParams,
         Sound,
     },
     util::{device_notif::DeviceStateNotifier, fence::Fence, proc_notif::ProcFramesNotif},
-    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
+    AsRawRef, Binding, ErrorContext, ErrorKinds, MaResult, MaudioError, ResultContext,
 };
 
 use maudio_sys::ffi as sys;
 
+pub mod block_events;
+pub mod device_router;
 pub mod engine_builder;
+pub mod engine_set;
 
 pub(crate) mod engine_cb_notif;
+pub mod engine_stats;
+pub mod listener;
+pub(crate) mod loudness_cache;
+pub mod mix_preset;
+pub mod mix_snapshot;
 pub mod node_graph;
+pub mod one_shot;
+pub mod output_tap;
 pub(crate) mod process_cb;
 pub mod resource;
+pub(crate) mod sound_registry;
+pub mod start_policy;
+pub mod synth;
+pub mod test_engine;
+pub mod time_scale;
+pub mod timeline;
 
 /// High-level audio engine.
 ///
@@ -136,16 +169,36 @@ pub struct EngineInner {
     _playback_device_id: Option<DeviceId>,  // keep alive
     _device: Option<Arc<DeviceInner<f32>>>, // keep alive
     _resource_manager: Option<ResourceManager<f32>>, // keep alive
+    _resource_manager_owner: Option<Arc<EngineInner>>, // keep alive
+    _context: Option<Context>,              // keep alive
     process_data_ptr: Option<*mut ProcessState>, // userdata (self.inner.pProcessUserData)
     process_data_panic: Option<Arc<AtomicBool>>, // true = callback panicked and is now poisoned
     process_data_notif: Option<ProcFramesNotif>,
     state_notifier: Option<DeviceStateNotifier>,
     reader_exists: Arc<AtomicBool>,
+    stats: Option<Arc<StatsCounters>>,
+    // Sounds created from this engine that are still alive
+    pub(crate) sound_count: AtomicU32,
+    // Sounds started with `play_buffer`
+    pub(crate) one_shots: OneShots,
+    // Files measured for `SoundBuilder::normalize_loudness`
+    pub(crate) loudness_cache: LoudnessCache,
+    // Named mixer snapshots and the running transition
+    pub(crate) mix_snapshots: MixSnapshots,
+    // Sounds created from this engine, for `pause_all_except` and `set_time_scale`
+    pub(crate) sounds: SoundRegistry,
+    // Slows down the engine clock for `set_time_scale`
+    pub(crate) clock: ScaledClock,
+    // Callbacks scheduled with `schedule_callback_at_pcm`
+    pub(crate) timeline: Timeline,
 }
 
 unsafe impl Send for EngineInner {}
 unsafe impl Sync for EngineInner {}
 
+// Block size used by `Engine::render_offline`
+const OFFLINE_BLOCK_FRAMES: u64 = 1024;
+
 impl Binding for Engine {
     type Raw = *mut sys::ma_engine;
 
@@ -193,6 +246,70 @@ impl EngineReader {
         }
         engine_ffi::ma_engine_read_pcm_frames(self, frame_count)
     }
+
+    /// Same as [`EngineReader::read_pcm_frames`], but reads into `buf`, reusing its
+    /// allocation. Returns the number of frames read.
+    ///
+    /// Create the buffer once with [`SampleBuffer::with_capacity`] to pull blocks from the
+    /// engine without allocating on every call.
+    pub fn read_pcm_frames_into_buffer(
+        &mut self,
+        buf: &mut SampleBuffer<f32>,
+        frame_count: u64,
+    ) -> MaResult<usize> {
+        let channels = engine_ffi::ma_engine_get_channels(self);
+        buf.read_with(frame_count, channels, |dst| self.read_pcm_frames_into(dst))
+    }
+
+    /// Same as [`EngineReader::read_pcm_frames`], but converts the output to the PCM format `F`.
+    ///
+    /// The engine always renders `f32` internally. This performs the conversion with
+    /// miniaudio's format converter, using `dither` where it applies.
+    ///
+    /// Useful when writing to sinks that expect integer samples, such as encoders or
+    /// network streams.
+    pub fn read_pcm_frames_as<F: PcmFormat>(
+        &mut self,
+        frame_count: u64,
+        dither: Dither,
+    ) -> MaResult<SampleBuffer<F>> {
+        if engine_ffi::ma_engine_get_device(self).is_some() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "read_pcm_frames is not allowed when engine has a device",
+            )));
+        }
+        engine_ffi::ma_engine_read_pcm_frames_as(self, frame_count, dither)
+    }
+
+    /// Same as [`EngineReader::read_pcm_frames_into`], but converts the output to the PCM format `F`.
+    ///
+    /// Returns the number of frames read.
+    pub fn read_pcm_frames_into_as<F: PcmFormat>(
+        &mut self,
+        dst: &mut [F::PcmUnit],
+        dither: Dither,
+    ) -> MaResult<usize> {
+        if engine_ffi::ma_engine_get_device(self).is_some() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "read_pcm_frames is not allowed when engine has a device",
+            )));
+        }
+        engine_ffi::ma_engine_read_pcm_frames_into_as::<F>(self, dst, dither)
+    }
+
+    /// Reads PCM frames converted to `i16`, without dithering.
+    ///
+    /// See [`EngineReader::read_pcm_frames_as`].
+    pub fn read_pcm_frames_i16(&mut self, frame_count: u64) -> MaResult<SampleBuffer<i16>> {
+        self.read_pcm_frames_as::<i16>(frame_count, Dither::None)
+    }
+
+    /// Reads PCM frames converted to `i32`.
+    ///
+    /// See [`EngineReader::read_pcm_frames_as`].
+    pub fn read_pcm_frames_i32(&mut self, frame_count: u64) -> MaResult<SampleBuffer<i32>> {
+        self.read_pcm_frames_as::<i32>(frame_count, Dither::None)
+    }
 }
 
 pub(crate) mod private_engine {
@@ -248,7 +365,7 @@ impl Engine {
     ///
     /// Most applications should start with this method.
     pub fn new() -> MaResult<Self> {
-        Self::new_with_config(None)
+        EngineBuilder::new().build()
     }
 
     /// Retrieves a [`ProcFramesNotif`] if one is present.
@@ -266,6 +383,34 @@ impl Engine {
         }
     }
 
+    /// Takes a snapshot of the engine statistics.
+    ///
+    /// Returns `None` unless the engine was built with [`EngineBuilder::with_stats()`].
+    pub fn stats(&self) -> Option<EngineStats> {
+        let stats = self.0.stats.as_ref()?;
+        let sounds = self.0.sound_count.load(Ordering::Relaxed);
+        Some(stats.snapshot(sounds, engine_ffi::rm_jobs_pending(self)))
+    }
+
+    /// Subscribes to the blocks processed by the engine, see
+    /// [`block_events`].
+    ///
+    /// Up to `capacity` blocks are kept until they are received, later blocks are dropped.
+    /// Returns an error unless the engine was built with
+    /// [`EngineBuilder::with_block_events()`].
+    pub fn subscribe_blocks(&self, capacity: usize) -> MaResult<BlockSubscription> {
+        let blocks = self
+            .0
+            .process_data_ptr
+            .and_then(|state| unsafe { (*state).blocks() });
+        match blocks {
+            Some(blocks) => blocks.subscribe(capacity),
+            None => Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "block events require an engine built with with_block_events",
+            ))),
+        }
+    }
+
     /// Retrieves a [`DeviceStateNotifier`] if one is present, that fires when the state of the device is changed
     ///
     /// `DeviceStateNotifier` is cheap to clone, and this function can be safely called multiple times
@@ -273,35 +418,11 @@ impl Engine {
         self.0.state_notifier.clone()
     }
 
-    fn new_with_config(config: Option<&EngineBuilder>) -> MaResult<Self> {
-        let (device, rm, dev_id) = config.map_or((None, None, None), |c| {
-            (
-                c.device.clone(),
-                c.resource_manager.clone(),
-                c.playback_device_id.clone(),
-            )
-        });
-        let mut mem: Box<MaybeUninit<sys::ma_engine>> = Box::new(MaybeUninit::uninit());
-        engine_ffi::engine_init(config, mem.as_mut_ptr())?;
-
-        let inner: *mut sys::ma_engine = Box::into_raw(mem) as *mut sys::ma_engine;
-        Ok(Self(Arc::new(EngineInner {
-            inner,
-            _playback_device_id: dev_id,
-            _device: device,
-            _resource_manager: rm,
-            process_data_ptr: None,
-            process_data_panic: None,
-            process_data_notif: None,
-            state_notifier: None,
-            reader_exists: Arc::new(AtomicBool::new(false)),
-        })))
-    }
-
     fn new_with_process_data(
         config: &mut EngineBuilder,
         data_notif: Option<ProcFramesNotif>,
     ) -> MaResult<Self> {
+        let backends_context = config.backends_context()?;
         let state_notif = if config.inner.noDevice == 0 && config.process_data.state_notif_exists {
             config.inner.notificationCallback = Some(engine_notification_callback);
             config.process_data.state_notif.take()
@@ -309,21 +430,69 @@ impl Engine {
             None
         };
 
+        let stats = config.process_data.stats.take();
+        let times_blocks = stats.is_some() || config.process_data.block_events_enabled;
+        // The device callback is wrapped to time it, which must happen before it starts
+        let no_auto_start = config.inner.noAutoStart;
+        if times_blocks {
+            config.inner.noAutoStart = 1;
+        }
+        // Also applies the output channel gains
+        if config.process_data.process_data_ptr.is_some() {
+            config.inner.onProcess = Some(on_process_callback);
+        }
+
+        let p_context = config.inner.pContext;
+        if let Some(context) = &backends_context {
+            config.inner.pContext = context.to_raw();
+        }
+
         let mut mem: Box<MaybeUninit<sys::ma_engine>> = Box::new(MaybeUninit::uninit());
-        engine_ffi::engine_init(Some(config), mem.as_mut_ptr())?;
+        let res = engine_ffi::engine_init(Some(config), mem.as_mut_ptr());
+        config.inner.noAutoStart = no_auto_start;
+        config.inner.pContext = p_context;
+        res?;
 
         let inner: *mut sys::ma_engine = Box::into_raw(mem) as *mut sys::ma_engine;
-        Ok(Self(Arc::new(EngineInner {
+        if let Some(state) = config.process_data.process_data_ptr {
+            // The config may leave the channel count to the device
+            let channels = unsafe { sys::ma_engine_get_channels(inner) };
+            unsafe { (*state).set_channels(channels) };
+            if let Some(blocks) = unsafe { (*state).blocks() } {
+                blocks.set_engine(inner);
+            }
+
+            let device = unsafe { sys::ma_engine_get_device(inner) };
+            if unsafe { (*state).times_blocks() && !device.is_null() && (*inner).ownsDevice != 0 } {
+                // Safe: the engine was initialized without starting its device
+                unsafe { (*state).wrap_device_callback(device) };
+            }
+        }
+        let engine = Self(Arc::new(EngineInner {
             inner,
             _playback_device_id: config.playback_device_id.take(),
             _device: config.device.take(),
             _resource_manager: config.resource_manager.take(),
+            _resource_manager_owner: config.resource_manager_owner.take(),
+            _context: backends_context.or_else(|| config.context.clone()),
             process_data_ptr: config.process_data.process_data_ptr,
             process_data_panic: config.process_data.process_data_panic.take(),
             process_data_notif: data_notif,
             state_notifier: state_notif,
             reader_exists: Arc::new(AtomicBool::new(false)),
-        })))
+            stats: stats.clone(),
+            sound_count: AtomicU32::new(0),
+            one_shots: OneShots::default(),
+            loudness_cache: LoudnessCache::default(),
+            mix_snapshots: MixSnapshots::default(),
+            sounds: SoundRegistry::default(),
+            clock: ScaledClock::default(),
+            timeline: Timeline::default(),
+        }));
+        if times_blocks && no_auto_start == 0 && engine.device().is_some() {
+            engine.start()?;
+        }
+        Ok(engine)
     }
 
     /// Equivalent to calling [`SoundBuilder::new()`]
@@ -342,16 +511,254 @@ impl Engine {
 
     pub fn new_sound_from_file(&self, path: &Path) -> MaResult<Sound> {
         self.new_sound_with_file_internal(path, SoundFlags::NONE, None, None)
+            .with_path(path)
     }
 
     pub fn new_sound_from_source<D: AsSourcePtr + ?Sized>(&self, source: &D) -> MaResult<Sound> {
         self.new_sound_with_source_internal(SoundFlags::NONE, None, source)
     }
 
+    /// Creates a sound from data registered with the engine's resource manager.
+    ///
+    /// `name` is the name used when registering the data (see [`Engine::resource_manager`]).
+    /// This allows playing audio embedded in the binary (e.g. with `include_bytes!`)
+    /// without writing it to a file first.
+    ///
+    /// The lookup covers the names registered with a live
+    /// [`ResourceGuard`](crate::engine::resource::ResourceGuard), including files registered
+    /// under their path. Unlike [`Engine::new_sound_from_file`], a name that is not registered
+    /// is not loaded from the file system, and an error is returned.
+    ///
+    /// The buffer is always read from memory, so [`SoundFlags::STREAM`] is ignored.
+    pub fn new_sound_from_registered(&self, name: &str, flags: SoundFlags) -> MaResult<Sound> {
+        let rm = self.resource_manager().ok_or(MaudioError::from_ma_result(
+            sys::ma_result_MA_INVALID_OPERATION,
+        ))?;
+        if !rm.is_registered(name) {
+            return Err(
+                MaudioError::from_ma_result(sys::ma_result_MA_DOES_NOT_EXIST)
+                    .with_context(ErrorContext::Resource(name.to_owned())),
+            );
+        }
+        let mut flags = flags;
+        flags.remove(SoundFlags::STREAM);
+        self.new_sound_with_file_internal(Path::new(name), flags, None, None)
+            .with_resource(name)
+    }
+
     pub fn clone_sound(&self, sound: &Sound, flags: SoundFlags) -> MaResult<Sound> {
         self.new_sound_instance_internal(sound, flags, None)
     }
 
+    /// Plays a copy of `samples` and forgets about it.
+    ///
+    /// The engine owns the sound, which is freed after it ends. The samples are played
+    /// without spatialization, at the engine's sample rate unless
+    /// [`PlayOptions::sample_rate`] is set. Playing an empty buffer does nothing.
+    ///
+    /// Use [`Engine::new_sound_from_source`] with an
+    /// [`AudioBuffer`](crate::data_source::sources::buffer::AudioBuffer) to control the
+    /// sound after it starts.
+    pub fn play_buffer(&self, samples: &SampleBuffer<f32>, options: &PlayOptions) -> MaResult<()> {
+        one_shot::play_buffer(self, samples, options)
+    }
+
+    /// Number of sounds started with [`Engine::play_buffer`] that are still playing.
+    pub fn playing_buffer_count(&self) -> usize {
+        self.0.one_shots.reap()
+    }
+
+    /// Stops every playing sound of the engine, except the sounds playing through one of the
+    /// `exempt` groups, and returns how many were stopped.
+    ///
+    /// A sound is exempt if its output reaches one of the groups, directly, through effects
+    /// inserted after it, or through a child group. This is typically used to keep the UI
+    /// sounds of a pause menu playing. The stopped sounds keep their cursor, and
+    /// [`Engine::resume_all`] starts them again. Sounds that were not playing are left alone.
+    ///
+    /// Sounds started with [`Engine::play_buffer`] are stopped too. Calling this again while
+    /// paused adds the sounds started since then.
+    pub fn pause_all_except(&self, exempt: &[&SoundGroup]) -> MaResult<usize> {
+        self.0.sounds.pause_except(&self.0.one_shots, exempt)
+    }
+
+    /// Same as [`Engine::pause_all_except`] with no exempt group.
+    pub fn pause_all(&self) -> MaResult<usize> {
+        self.pause_all_except(&[])
+    }
+
+    /// Starts again the sounds stopped by [`Engine::pause_all_except`], and returns how many
+    /// were started.
+    ///
+    /// Sounds that were stopped by hand, started again or dropped since the pause are left
+    /// as they are.
+    pub fn resume_all(&self) -> MaResult<usize> {
+        self.0.sounds.resume(&self.0.one_shots)
+    }
+
+    /// Number of sounds stopped by [`Engine::pause_all_except`] that [`Engine::resume_all`]
+    /// would start again.
+    pub fn paused_sound_count(&self) -> usize {
+        self.0.sounds.paused_count()
+    }
+
+    /// Returns the policy set with [`Engine::set_start_policy`].
+    pub fn start_policy(&self) -> Option<StartPolicy> {
+        self.0.sounds.start_policy()
+    }
+
+    /// Limits the number of playing sounds, by holding back the starts of low priority
+    /// sounds. See [`start_policy`]. `None`, the default, starts every sound.
+    ///
+    /// Removing the policy, or switching to
+    /// [`Overflow::Drop`](start_policy::Overflow::Drop), drops the deferred starts.
+    pub fn set_start_policy(&self, policy: Option<StartPolicy>) {
+        self.0.sounds.set_start_policy(policy)
+    }
+
+    /// Starts the sounds deferred by the start policy while fewer than
+    /// [`StartPolicy::max_playing`] sounds are playing, and returns how many were started.
+    ///
+    /// Sounds do not report when they stop, so call this regularly, for example once per
+    /// game frame.
+    pub fn start_deferred_sounds(&self) -> MaResult<usize> {
+        self.0.sounds.start_deferred(&self.0.one_shots)
+    }
+
+    /// Number of sounds waiting in [`Engine::start_deferred_sounds`].
+    pub fn deferred_sound_count(&self) -> usize {
+        self.0.sounds.deferred_count()
+    }
+
+    /// Scales time for the whole engine, for slow-motion effects. See
+    /// [`time_scale`](crate::engine::time_scale).
+    ///
+    /// The pitch of every sound that is not [exempt](Sound::set_time_scale_exempt) is
+    /// multiplied by `scale`, including sounds created later, and the engine clock advances
+    /// `scale` times as fast. `1.0` is normal speed.
+    ///
+    /// Returns `MA_INVALID_ARGS` if `scale` is not a positive, finite number.
+    pub fn set_time_scale(&self, scale: f32) -> MaResult<()> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        self.0.clock.set_scale(self, scale)?;
+        self.0.sounds.set_time_scale(&self.0.one_shots, scale);
+        Ok(())
+    }
+
+    /// Returns the scale set with [`Engine::set_time_scale`], `1.0` by default.
+    pub fn time_scale(&self) -> f32 {
+        self.0.sounds.time_scale()
+    }
+
+    /// Schedules `callback` for when the engine clock reaches `frame`. See
+    /// [`timeline`](crate::engine::timeline).
+    ///
+    /// The callback does not run on the audio thread. Once the frame is reached it is handed
+    /// to the [`TimelineEvents`] receiver, and runs with the frame it fired at when the
+    /// receiver is drained. A frame in the past fires on the next processing callback.
+    pub fn schedule_callback_at_pcm<F>(&self, frame: u64, callback: F) -> MaResult<CallbackId>
+    where
+        F: FnOnce(u64) + Send + 'static,
+    {
+        self.0.timeline.schedule(self, frame, Box::new(callback))
+    }
+
+    /// Removes a callback scheduled with [`Engine::schedule_callback_at_pcm`].
+    ///
+    /// Returns `false` if it already fired or was cancelled.
+    pub fn cancel_scheduled_callback(&self, id: CallbackId) -> bool {
+        self.0.timeline.cancel(id)
+    }
+
+    /// Returns the number of scheduled callbacks that have not fired yet.
+    pub fn scheduled_callback_count(&self) -> usize {
+        self.0.timeline.scheduled_count()
+    }
+
+    /// Returns the receiver for the callbacks scheduled with
+    /// [`Engine::schedule_callback_at_pcm`].
+    pub fn timeline_events(&self) -> TimelineEvents {
+        self.0.timeline.events()
+    }
+
+    /// Returns the loudness measured for the file at `path`, if a sound was loaded from it
+    /// with [`SoundBuilder::normalize_loudness`](crate::sound::sound_builder::SoundBuilder::normalize_loudness).
+    pub fn cached_loudness(&self, path: &Path) -> Option<Loudness> {
+        self.0.loudness_cache.get(path)
+    }
+
+    /// Forgets the loudness measured for normalization, so files are measured again the next
+    /// time they are loaded. Useful after a file changed on disk.
+    pub fn clear_loudness_cache(&self) {
+        self.0.loudness_cache.clear();
+    }
+
+    /// Registers `snapshot` under `name`, replacing any snapshot with the same name.
+    ///
+    /// See [`mix_snapshot`](crate::engine::mix_snapshot).
+    pub fn define_snapshot(&self, name: &str, snapshot: MixSnapshot) {
+        self.0.mix_snapshots.define(name, snapshot);
+    }
+
+    /// Returns a copy of the snapshot registered under `name`.
+    pub fn snapshot(&self, name: &str) -> Option<MixSnapshot> {
+        self.0.mix_snapshots.get(name)
+    }
+
+    /// Removes the snapshot registered under `name`.
+    pub fn remove_snapshot(&self, name: &str) -> Option<MixSnapshot> {
+        self.0.mix_snapshots.remove(name)
+    }
+
+    /// Moves the volume of every group in `snapshot` to its value in the snapshot, over
+    /// `duration`.
+    ///
+    /// The volumes are interpolated on the audio thread. A transition that is still running
+    /// is replaced, starting from the volumes it reached. A zero duration applies the
+    /// snapshot immediately.
+    pub fn transition_to(&self, snapshot: &MixSnapshot, duration: Duration) -> MaResult<()> {
+        self.0.mix_snapshots.transition_to(self, snapshot, duration)
+    }
+
+    /// Same as [`Engine::transition_to`], with a snapshot registered by
+    /// [`Engine::define_snapshot`].
+    ///
+    /// Returns `MA_DOES_NOT_EXIST` if there is no snapshot called `name`.
+    pub fn transition_to_named(&self, name: &str, duration: Duration) -> MaResult<()> {
+        let snapshot = self.snapshot(name).ok_or(MaudioError::from_ma_result(
+            sys::ma_result_MA_DOES_NOT_EXIST,
+        ))?;
+        self.transition_to(&snapshot, duration)
+    }
+
+    /// Returns `true` while a transition started by [`Engine::transition_to`] is running.
+    pub fn is_transitioning(&self) -> bool {
+        self.0.mix_snapshots.is_transitioning()
+    }
+
+    /// Applies parameters to many sounds in one pass.
+    ///
+    /// Every sound must belong to this engine. The batch stops at the first sound that
+    /// does not, and returns an error. The sounds before it have already been updated.
+    ///
+    /// See [`SoundParams`].
+    pub fn apply_batch<'a, I>(&self, batch: I) -> MaResult<()>
+    where
+        I: IntoIterator<Item = (&'a mut Sound, &'a SoundParams)>,
+    {
+        for (sound, params) in batch {
+            if !Arc::ptr_eq(sound.engine_inner(), &self.0) {
+                return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                    "sound belongs to another engine",
+                )));
+            }
+            sound.apply(params);
+        }
+        Ok(())
+    }
+
     // Thread-safe
     /// Manually starts the engine
     ///
@@ -371,6 +778,7 @@ impl Engine {
         engine_ffi::ma_engine_stop(self)
     }
 
+    /// The sound starts with the group's [`SoundGroup::spatial_defaults`].
     pub fn new_sound_from_file_with_group(
         &self,
         path: &Path,
@@ -417,69 +825,102 @@ impl Engine {
         engine_ffi::ma_engine_get_gain_db(self)
     }
 
-    /// Returns the number of listeners.
-    pub fn listener_count(&self) -> u32 {
-        engine_ffi::ma_engine_get_listener_count(self)
+    // Thread-safe
+    /// Sets the volume of the engine's output device, separately from [`Engine::set_volume`].
+    ///
+    /// The device volume is applied by miniaudio as the device hands the mixed audio to the
+    /// backend, after the engine's own gain. miniaudio does not change the volume of the
+    /// operating system mixer.
+    ///
+    /// Returns an error if the engine has no device.
+    pub fn set_device_volume(&self, volume: f32) -> MaResult<()> {
+        let device = engine_ffi::ma_engine_get_device(self).ok_or(MaudioError::new_ma_error(
+            ErrorKinds::InvalidOperation("engine has no device"),
+        ))?;
+        device.set_master_volume(volume)
     }
 
-    /// Returns the index of the closest listener to `position`.
-    pub fn closest_listener(&self, position: Vec3) -> u32 {
-        engine_ffi::ma_engine_find_closest_listener(self, position)
+    /// Returns the volume of the engine's output device, or `None` if the engine has no device.
+    pub fn device_volume(&self) -> Option<f32> {
+        engine_ffi::ma_engine_get_device(self).and_then(|device| device.master_volume().ok())
     }
 
     // Thread-safe
-    /// Sets the position of `listener`.
-    pub fn set_position(&self, listener: u32, position: Vec3) {
-        engine_ffi::ma_engine_listener_set_position(self, listener, position);
+    /// Sets the overall output volume, the one a volume slider in the application controls.
+    ///
+    /// Uses the device volume when the engine has a device, which leaves the engine volume to
+    /// the application's own mixing. Without a device, sets the engine volume.
+    pub fn set_master_volume(&self, volume: f32) -> MaResult<()> {
+        match engine_ffi::ma_engine_get_device(self) {
+            Some(device) => device.set_master_volume(volume),
+            None => self.set_volume(volume),
+        }
     }
 
-    /// Returns the position of `listener`.
-    pub fn position(&self, listener: u32) -> Vec3 {
-        engine_ffi::ma_engine_listener_get_position(self, listener)
+    /// Returns the volume set with [`Engine::set_master_volume`].
+    pub fn master_volume(&self) -> f32 {
+        self.device_volume().unwrap_or_else(|| self.volume())
     }
 
     // Thread-safe
-    /// Sets the facing direction of `listener`.
-    pub fn set_direction(&self, listener: u32, direction: Vec3) {
-        engine_ffi::ma_engine_listener_set_direction(self, listener, direction);
-    }
-
-    /// Returns the facing direction of `listener`.
-    pub fn direction(&self, listener: u32) -> Vec3 {
-        engine_ffi::ma_engine_listener_get_direction(self, listener)
+    /// Sets the linear gain of one channel of the engine's output, for example `0.0` on
+    /// channel `0` to mute the left speaker.
+    ///
+    /// Applied to the final mix, before the realtime callback and the output tap see it.
+    /// Per-sound gains are set with
+    /// [`Sound::set_channel_gain`](crate::sound::Sound::set_channel_gain).
+    ///
+    /// Returns an error if the engine has no such channel, or if `gain` is negative or not
+    /// finite.
+    pub fn set_output_channel_gain(&self, channel: u32, gain: f32) -> MaResult<()> {
+        if channel >= self.channels() {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let state = self.process_state()?;
+        state.output_gains.set(channel, gain)
     }
 
-    // Thread-safe
-    /// Sets the velocity of `listener`.
-    pub fn set_velocity(&self, listener: u32, position: Vec3) {
-        engine_ffi::ma_engine_listener_set_velocity(self, listener, position);
+    /// Returns the gain of output `channel`, or `None` if the engine has no such channel.
+    pub fn output_channel_gain(&self, channel: u32) -> Option<f32> {
+        if channel >= self.channels() {
+            return None;
+        }
+        self.process_state().ok()?.output_gains.get(channel)
     }
 
-    /// Returns the velocity of `listener`.
-    pub fn velocity(&self, listener: u32) -> Vec3 {
-        engine_ffi::ma_engine_listener_get_velocity(self, listener)
+    fn process_state(&self) -> MaResult<&ProcessState> {
+        match self.0.process_data_ptr {
+            // Freed with the engine
+            Some(state) => Ok(unsafe { &*state }),
+            None => Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "engine has no process state",
+            ))),
+        }
     }
 
-    // Thread-safe
-    /// Sets the directional cone of `listener`.
-    pub fn set_cone(&self, listener: u32, cone: Cone) {
-        engine_ffi::ma_engine_listener_set_cone(self, listener, cone);
+    /// Returns the number of listeners.
+    pub fn listener_count(&self) -> u32 {
+        engine_ffi::ma_engine_get_listener_count(self)
     }
 
-    /// Returns the directional cone of `listener`.
-    pub fn cone(&self, listener: u32) -> Cone {
-        engine_ffi::ma_engine_listener_get_cone(self, listener)
+    /// Returns a handle to the listener at `index`.
+    ///
+    /// Returns an error if `index` is not below [`Engine::listener_count`].
+    pub fn listener(&self, index: u32) -> MaResult<Listener<'_>> {
+        Listener::new(self, index)
     }
 
-    // Thread-safe
-    /// Sets the world-up vector of `listener`.
-    pub fn set_world_up(&self, listener: u32, up_direction: Vec3) {
-        engine_ffi::ma_engine_listener_set_world_up(self, listener, up_direction);
+    /// Returns an iterator over all the listeners of the engine.
+    pub fn listeners(&self) -> impl Iterator<Item = Listener<'_>> {
+        (0..self.listener_count()).map(move |index| Listener::from_index(self, index))
     }
 
-    /// Returns the world-up vector of `listener`.
-    pub fn get_world_up(&self, listener: u32) -> Vec3 {
-        engine_ffi::ma_engine_listener_get_world_up(self, listener)
+    /// Returns the closest listener to `position`.
+    ///
+    /// Disabled listeners are skipped.
+    pub fn closest_listener(&self, position: Vec3) -> Listener<'_> {
+        let index = engine_ffi::ma_engine_find_closest_listener(self, position);
+        Listener::from_index(self, index)
     }
 
     pub fn try_acquire_reader(&self) -> MaResult<EngineReader> {
@@ -494,15 +935,75 @@ impl Engine {
         }
     }
 
-    // Thread-safe
-    /// Enables or disables `listener`.
-    pub fn toggle_listener(&self, listener: u32, enabled: bool) {
-        engine_ffi::ma_engine_listener_set_enabled(self, listener, enabled);
+    /// Renders `frame_count` frames as fast as possible, passing each block of interleaved
+    /// samples to `sink`.
+    ///
+    /// This is meant for engines without a device (see
+    /// [`EngineBuilder::no_device`](crate::engine::engine_builder::EngineBuilder::no_device)),
+    /// for bouncing a timeline to a file or rendering deterministic output in tests.
+    /// Rendering advances the engine clock, so sounds scheduled with a start or stop
+    /// time play when the clock reaches it.
+    ///
+    /// Exactly `frame_count` frames are passed to `sink`, in blocks of at most 1024 frames.
+    /// When nothing is playing, silence is rendered and the clock still moves forward.
+    ///
+    /// miniaudio applies start and stop times at block boundaries: a sound starts with the
+    /// first block that begins at or after its start time, and stops with the block that
+    /// would reach its stop time. Use [`Engine::render_offline_with_block_size`] for finer
+    /// timing.
+    ///
+    /// Fails if the engine has a device, or if an [`EngineReader`] already exists.
+    pub fn render_offline<S>(&self, frame_count: u64, sink: S) -> MaResult<()>
+    where
+        S: FnMut(&[f32]),
+    {
+        self.render_offline_with_block_size(frame_count, OFFLINE_BLOCK_FRAMES, sink)
     }
 
-    /// Returns `true` if `listener` is enabled.
-    pub fn listener_enabled(&self, listener: u32) -> bool {
-        engine_ffi::ma_engine_listener_is_enabled(self, listener)
+    /// Same as [`Engine::render_offline`], rendering blocks of at most `block_frames` frames.
+    ///
+    /// Smaller blocks make scheduled start and stop times more precise, at the cost of
+    /// more processing overhead.
+    pub fn render_offline_with_block_size<S>(
+        &self,
+        frame_count: u64,
+        block_frames: u64,
+        mut sink: S,
+    ) -> MaResult<()>
+    where
+        S: FnMut(&[f32]),
+    {
+        if block_frames == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        if self.device().is_some() {
+            // The device callback would read the engine at the same time
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "render_offline is not allowed when engine has a device",
+            )));
+        }
+        let mut reader = self.try_acquire_reader()?;
+        let channels = self.channels() as usize;
+        let block_frames = block_frames.min(frame_count.max(1));
+        let mut block = vec![0.0f32; block_frames as usize * channels];
+
+        let mut remaining = frame_count;
+        while remaining > 0 {
+            let frames = remaining.min(block_frames) as usize;
+            let out = &mut block[..frames * channels];
+            let start = self.time_pcm();
+
+            let read = reader.read_pcm_frames_into(out)?;
+            if read < frames {
+                // The node graph does not advance when nothing is attached to the endpoint
+                out[read * channels..].fill(0.0);
+                self.set_time_pcm(start + frames as u64);
+            }
+
+            sink(out);
+            remaining -= frames as u64;
+        }
+        Ok(())
     }
 
     /// Returns the engine's internal node graph.
@@ -510,7 +1011,29 @@ impl Engine {
         engine_ffi::ma_engine_get_node_graph(self)
     }
 
-    /// Returns the engine's internal resource manager, if available.
+    /// Returns the engine's resource manager, if available.
+    ///
+    /// This is either the resource manager passed to [`EngineBuilder::resource_manager`], or the
+    /// one created by the engine. Both decode to `f32`.
+    ///
+    /// Data registered with it can be loaded by the engine by name, with
+    /// [`Engine::new_sound_from_registered`] or as if it was a file path:
+    ///
+    /// ```no_run
+    /// # use maudio::engine::Engine;
+    /// # use maudio::engine::resource::RmOps;
+    /// # use maudio::sound::sound_flags::SoundFlags;
+    /// # fn main() -> maudio::MaResult<()> {
+    /// # let bytes: Vec<u8> = Vec::new();
+    /// let engine = Engine::new()?;
+    /// let rm = engine.resource_manager().unwrap();
+    /// let _guard = rm.register_encoded("jump.wav", &bytes)?;
+    ///
+    /// let mut sound = engine.new_sound_from_registered("jump.wav", SoundFlags::NONE)?;
+    /// sound.play_sound()?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn resource_manager(&self) -> Option<ResourceManagerRef<'_, f32>> {
         engine_ffi::ma_engine_get_resource_manager(self)
     }
@@ -520,6 +1043,19 @@ impl Engine {
         engine_ffi::ma_engine_get_device(self)
     }
 
+    /// Estimated output latency of the engine's device, in frames at the engine's sample rate.
+    ///
+    /// Returns `None` if the engine has no device. See
+    /// [`DeviceTiming`](crate::device::timing::DeviceTiming) for what the
+    /// estimate includes.
+    pub fn output_latency_frames(&self) -> Option<u64> {
+        let device = self.device()?;
+        device
+            .timing(DeviceType::Playback)
+            .ok()
+            .map(|timing| timing.latency_frames())
+    }
+
     /// Returns the engine’s **endpoint node**.
     ///
     /// The endpoint node is the final node in the engine’s internal node graph.
@@ -581,7 +1117,22 @@ impl Engine {
         sound_ffi::ma_sound_init_copy(self, sound, flags, sound_group, mem.as_mut_ptr())?;
 
         let inner: *mut sys::ma_sound = Box::into_raw(mem) as *mut sys::ma_sound;
-        Ok(Sound::new_sound(inner, self.0.clone(), None, None))
+        let mut copy = Sound::new_sound(inner, self.0.clone(), None, None);
+        copy.copy_markers_from(sound);
+        // The copy reads the same registered data
+        copy.resource = sound.resource.clone();
+        copy.resource_use = sound.resource_use.clone();
+        Ok(copy)
+    }
+
+    // Counts a sound loading `path` through the resource manager as a user of the resource
+    // registered under that name, if any. Streams read the file themselves.
+    pub(crate) fn resource_use(&self, path: &Path, flags: SoundFlags) -> Option<ResourceUse> {
+        if flags.contains(SoundFlags::STREAM) {
+            return None;
+        }
+        let rm = self.resource_manager()?;
+        ResourceUse::new(rm_key(&rm), &path.to_string_lossy())
     }
 
     pub(crate) fn sample_rate_u32(&self) -> u32 {
@@ -635,7 +1186,11 @@ impl Engine {
         )?;
 
         let inner: *mut sys::ma_sound = Box::into_raw(mem) as *mut sys::ma_sound;
-        Ok(Sound::new_sound(inner, self.0.clone(), None, None))
+        let mut sound = Sound::new_sound(inner, self.0.clone(), None, None);
+        if let Some(group) = sound_group {
+            group.spatial_defaults().apply_to(&mut sound);
+        }
+        Ok(sound)
     }
 
     pub(crate) fn new_sound_with_file_internal(
@@ -647,6 +1202,7 @@ impl Engine {
     ) -> MaResult<Sound> {
         let mut mem: Box<MaybeUninit<sys::ma_sound>> = Box::new(MaybeUninit::uninit());
 
+        let resource_use = self.resource_use(path, flags);
         Sound::init_from_file_internal(
             mem.as_mut_ptr(),
             self,
@@ -657,12 +1213,22 @@ impl Engine {
         )?;
 
         let inner: *mut sys::ma_sound = Box::into_raw(mem) as *mut sys::ma_sound;
-        Ok(Sound::new_sound(inner, self.0.clone(), None, None))
+        let mut sound = Sound::new_sound(inner, self.0.clone(), None, None);
+        sound.resource_use = resource_use;
+        sound.load_wav_metadata(path);
+        if let Some(group) = sound_group {
+            group.spatial_defaults().apply_to(&mut sound);
+        }
+        Ok(sound)
     }
 }
 
 impl Drop for EngineInner {
     fn drop(&mut self) {
+        self.one_shots.clear();
+        self.mix_snapshots.clear();
+        self.clock.clear();
+        self.timeline.clear();
         engine_ffi::engine_uninit(self);
         if let Some(proc_data_ptr) = self.process_data_ptr {
             drop(unsafe { Box::from_raw(proc_data_ptr) });
@@ -726,10 +1292,19 @@ impl AsRawRef for AllocationCallbacks {
 }
 
 pub(crate) mod engine_ffi {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Instant,
+    };
+
     use maudio_sys::ffi as sys;
 
     use crate::{
-        audio::{formats::SampleBuffer, math::vec3::Vec3, spatial::cone::Cone},
+        audio::{
+            formats::{Dither, Format, SampleBuffer},
+            math::vec3::Vec3,
+            spatial::cone::Cone,
+        },
         device::DeviceRef,
         engine::{
             engine_builder::EngineBuilder,
@@ -739,7 +1314,8 @@ pub(crate) mod engine_ffi {
             resource::ResourceManagerRef,
             AsEnginePtr, Binding, Engine, EngineInner, EngineReader,
         },
-        AsRawRef, MaResult, MaudioError,
+        pcm_frames::{PcmFormat, PcmFormatInternal},
+        AsRawRef, ErrorKinds, MaResult, MaudioError,
     };
 
     #[inline]
@@ -756,6 +1332,11 @@ pub(crate) mod engine_ffi {
     #[inline]
     pub fn engine_uninit(engine: &mut EngineInner) {
         unsafe {
+            if (*engine.inner).ownsResourceManager != 0 {
+                crate::engine::resource::rm_registry::forget(
+                    (*engine.inner).pResourceManager as usize,
+                );
+            }
             sys::ma_engine_uninit(engine.inner);
         }
     }
@@ -775,18 +1356,51 @@ pub(crate) mod engine_ffi {
         // May truncate, and that is desired
         let frame_count = len / channels as u64;
 
+        let frames_read = read_pcm_frames_raw(engine, dst.as_mut_ptr(), frame_count)?;
+        Ok(frames_read as usize)
+    }
+
+    // Reads from the engine, timing the read if stats are enabled
+    fn read_pcm_frames_raw(
+        engine: &EngineReader,
+        dst: *mut f32,
+        frame_count: u64,
+    ) -> MaResult<u64> {
+        let start = engine.0.stats.as_ref().map(|_| Instant::now());
+        if let Some(state) = engine.0.process_data_ptr {
+            if let Some(blocks) = unsafe { (*state).blocks() } {
+                blocks.block_started();
+            }
+        }
         let mut frames_read = 0;
         let res = unsafe {
             sys::ma_engine_read_pcm_frames(
                 engine.to_raw(),
-                dst.as_mut_ptr() as *mut std::ffi::c_void,
+                dst as *mut std::ffi::c_void,
                 frame_count,
                 &mut frames_read,
             )
         };
+        if let (Some(stats), Some(start)) = (&engine.0.stats, start) {
+            stats.record_block(start.elapsed(), frame_count as u32, None);
+        }
         MaudioError::check(res)?;
+        Ok(frames_read)
+    }
 
-        Ok(frames_read as usize)
+    /// Number of jobs waiting in the job queue of the engine's resource manager.
+    pub fn rm_jobs_pending(engine: &Engine) -> Option<u32> {
+        let rm = unsafe { sys::ma_engine_get_resource_manager(engine.to_raw()) };
+        if rm.is_null() {
+            return None;
+        }
+        // The queue always holds a dummy job at its head. The count is updated atomically
+        // by miniaudio.
+        let count = unsafe {
+            let count = core::ptr::addr_of!((*rm).jobQueue.allocator.count);
+            (*count.cast::<AtomicU32>()).load(Ordering::Relaxed)
+        };
+        Some(count.saturating_sub(1))
     }
 
     #[inline]
@@ -796,17 +1410,75 @@ pub(crate) mod engine_ffi {
     ) -> MaResult<SampleBuffer<f32>> {
         let channels = engine_ffi::ma_engine_get_channels(engine);
         let mut buffer = vec![0.0f32; (frame_count * channels as u64) as usize];
-        let mut frames_read = 0;
-        let res = unsafe {
-            sys::ma_engine_read_pcm_frames(
-                engine.to_raw(),
-                buffer.as_mut_ptr() as *mut std::ffi::c_void,
-                frame_count,
-                &mut frames_read,
+        let frames_read = read_pcm_frames_raw(engine, buffer.as_mut_ptr(), frame_count)?;
+        SampleBuffer::<f32>::from_storage(buffer, frames_read as usize, channels)
+    }
+
+    pub fn ma_engine_read_pcm_frames_as<F: PcmFormat>(
+        engine: &EngineReader,
+        frame_count: u64,
+        dither: Dither,
+    ) -> MaResult<SampleBuffer<F>> {
+        let rendered = ma_engine_read_pcm_frames(engine, frame_count)?;
+        let channels = rendered.channels();
+        let frames_read = rendered.frames();
+
+        let mut storage = SampleBuffer::<F>::new_zeroed(frames_read, channels)?;
+        convert_from_f32::<F>(&mut storage, rendered.as_ref(), dither)?;
+        SampleBuffer::<F>::from_storage(storage, frames_read, channels)
+    }
+
+    pub fn ma_engine_read_pcm_frames_into_as<F: PcmFormat>(
+        engine: &EngineReader,
+        dst: &mut [F::PcmUnit],
+        dither: Dither,
+    ) -> MaResult<usize> {
+        let channels = engine_ffi::ma_engine_get_channels(engine);
+        if channels == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+
+        // May truncate, and that is desired
+        let frame_count = dst.len() / (channels as usize * F::VEC_PCM_UNITS_PER_FRAME);
+
+        let mut rendered = vec![0.0f32; frame_count * channels as usize];
+        let frames_read = ma_engine_read_pcm_frames_into(engine, &mut rendered)?;
+        rendered.truncate(frames_read * channels as usize);
+
+        let mut storage = SampleBuffer::<F>::new_zeroed(frames_read, channels)?;
+        convert_from_f32::<F>(&mut storage, &rendered, dither)?;
+        <F as PcmFormatInternal>::read_from_storage_internal(
+            &storage,
+            dst,
+            frames_read,
+            channels as usize,
+        )
+    }
+
+    // `src` holds interleaved f32 samples. `dst` must have room for the same number of samples in `F`
+    fn convert_from_f32<F: PcmFormat>(
+        dst: &mut [F::StorageUnit],
+        src: &[f32],
+        dither: Dither,
+    ) -> MaResult<()> {
+        if dst.len() != src.len() * F::VEC_STORE_UNITS_PER_FRAME {
+            return Err(MaudioError::new_ma_error(ErrorKinds::BufferSizeMismatch {
+                context: "engine read_pcm_frames format conversion",
+                expected: src.len() * F::VEC_STORE_UNITS_PER_FRAME,
+                actual: dst.len(),
+            }));
+        }
+        unsafe {
+            sys::ma_pcm_convert(
+                dst.as_mut_ptr() as *mut std::ffi::c_void,
+                F::FORMAT.into(),
+                src.as_ptr() as *const std::ffi::c_void,
+                Format::F32.into(),
+                src.len() as u64,
+                dither.into(),
             )
         };
-        MaudioError::check(res)?;
-        SampleBuffer::<f32>::from_storage(buffer, frames_read as usize, channels)
+        Ok(())
     }
 
     #[inline]
@@ -824,10 +1496,15 @@ pub(crate) mod engine_ffi {
     ) -> Option<ResourceManagerRef<'a, f32>> {
         let ptr = unsafe { sys::ma_engine_get_resource_manager(engine.to_raw()) };
         if ptr.is_null() {
-            None
-        } else {
-            Some(ResourceManagerRef::from_ptr(ptr))
+            return None;
+        }
+        // The engine always decodes to f32. Guard against a mismatched format anyway,
+        // as the format of the reference is not checked again.
+        let format = unsafe { (*ptr).config.decodedFormat };
+        if format != sys::ma_format_ma_format_f32 {
+            return None;
         }
+        Some(ResourceManagerRef::from_ptr(ptr, engine.0.clone()))
     }
 
     // AsEnginePtr
@@ -1088,7 +1765,128 @@ mod test {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_engine_output_latency_frames() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert!(engine.output_latency_frames().is_none());
+
+        let engine = EngineBuilder::new()
+            .backends(&[crate::backend::Backend::Null])
+            .build()
+            .unwrap();
+        assert!(engine.output_latency_frames().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_engine_read_pcm_frames_into_buffer() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(2, &[0.5f32; 2048]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let mut buf = SampleBuffer::with_capacity(256, 2).unwrap();
+        assert!(buf.is_empty());
+        let frames = reader.read_pcm_frames_into_buffer(&mut buf, 256).unwrap();
+        assert_eq!(frames, 256);
+        assert_eq!(buf.channels(), 2);
+        assert_eq!(buf.len(), 512);
+        assert!(buf.as_ref()[16..].iter().all(|s| *s > 0.0));
+    }
+
+    #[test]
+    fn test_engine_subscribe_blocks() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .with_block_events()
+            .build()
+            .unwrap();
+        let blocks = engine.subscribe_blocks(4).unwrap();
+        // Nothing is rendered while nothing is attached to the endpoint
+        let buf = AudioBufferBuilder::build_f32(2, &[0.5f32; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buf).unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(512).unwrap();
+        reader.read_pcm_frames(256).unwrap();
+
+        let first = blocks.try_recv().unwrap();
+        assert_eq!(first.frames, 512);
+        assert_eq!(first.engine_time, 512);
+        assert!(first.duration.is_some());
+        let second = blocks.try_recv().unwrap();
+        assert_eq!(second.engine_time, 768);
+        assert!(second.timestamp >= first.timestamp);
+        assert!(blocks.try_recv().is_none());
+
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert!(engine.subscribe_blocks(4).is_err());
+    }
+
+    #[test]
+    fn test_engine_stats_without_device() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .with_stats()
+            .build()
+            .unwrap();
+        let stats = engine.stats().unwrap();
+        assert_eq!(stats.frames_rendered, 0);
+        assert_eq!(stats.active_sounds, 0);
+        assert_eq!(stats.rm_jobs_pending, Some(0));
+
+        let buffer = AudioBufferBuilder::build_f32(2, &[0.5f32; 1024]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.play_sound().unwrap();
+        engine.render_offline(256, |_| {}).unwrap();
+
+        let stats = engine.stats().unwrap();
+        assert_eq!(stats.frames_rendered, 256);
+        assert_eq!(stats.callback_duration.total(), 1);
+        assert_eq!(stats.underruns, 0);
+        assert_eq!(stats.active_sounds, 1);
+
+        drop(sound);
+        assert_eq!(engine.stats().unwrap().active_sounds, 0);
+
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert!(engine.stats().is_none());
+    }
+
+    #[test]
+    fn test_engine_stats_times_device_callback() {
+        let engine = EngineBuilder::new()
+            .backends(&[crate::backend::Backend::Null])
+            .with_stats()
+            .build()
+            .unwrap();
+        assert!(engine.device().is_some());
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while engine.stats().unwrap().callback_duration.total() == 0
+            && std::time::Instant::now() < deadline
+        {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let stats = engine.stats().unwrap();
+        assert!(stats.callback_duration.total() > 0);
+    }
+
     use super::*;
+    use crate::{audio::spatial::cone::Cone, data_source::sources::buffer::AudioBufferBuilder};
 
     fn assert_f32_eq(a: f32, b: f32) {
         assert!(
@@ -1126,6 +1924,65 @@ mod test {
         assert_f32_eq(engine.volume(), 1.0);
     }
 
+    #[test]
+    fn test_engine_master_volume_without_device() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert!(engine.device_volume().is_none());
+        assert!(engine.set_device_volume(0.5).is_err());
+
+        engine.set_master_volume(0.5).unwrap();
+        assert_f32_eq(engine.volume(), 0.5);
+        assert_f32_eq(engine.master_volume(), 0.5);
+    }
+
+    #[test]
+    fn test_engine_master_volume_uses_device() {
+        let engine = EngineBuilder::new()
+            .backends(&[crate::backend::Backend::Null])
+            .build()
+            .unwrap();
+        assert_f32_eq(engine.device_volume().unwrap(), 1.0);
+
+        engine.set_master_volume(0.5).unwrap();
+        assert_f32_eq(engine.device_volume().unwrap(), 0.5);
+        assert_f32_eq(engine.master_volume(), 0.5);
+        // The engine gain is left alone
+        assert_f32_eq(engine.volume(), 1.0);
+
+        engine.set_device_volume(0.25).unwrap();
+        assert_f32_eq(engine.master_volume(), 0.25);
+        assert!(engine.set_device_volume(-1.0).is_err());
+    }
+
+    #[test]
+    fn test_engine_output_channel_gain() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert_eq!(engine.output_channel_gain(1), Some(1.0));
+        assert_eq!(engine.output_channel_gain(2), None);
+        assert!(engine.set_output_channel_gain(2, 0.5).is_err());
+        assert!(engine.set_output_channel_gain(0, f32::INFINITY).is_err());
+
+        engine.set_output_channel_gain(1, 0.5).unwrap();
+        assert_eq!(engine.output_channel_gain(1), Some(0.5));
+
+        let buffer = AudioBufferBuilder::build_f32(2, &[0.4f32; 2048]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        for frame in out.as_ref().chunks_exact(2).skip(1) {
+            assert!((frame[0] - 0.4).abs() < 1e-5);
+            assert!((frame[1] - 0.2).abs() < 1e-5);
+        }
+    }
+
     #[test]
     fn test_engine_gain_db_roundtrip() {
         let engine = Engine::new_for_tests().unwrap();
@@ -1145,11 +2002,12 @@ mod test {
         assert!(n >= 1, "engine should have at least 1 listener");
 
         // Toggle first listener (should always exist if n>=1).
-        engine.toggle_listener(0, false);
-        assert!(!engine.listener_enabled(0));
+        let listener = engine.listener(0).unwrap();
+        listener.set_enabled(false);
+        assert!(!listener.is_enabled());
 
-        engine.toggle_listener(0, true);
-        assert!(engine.listener_enabled(0));
+        listener.set_enabled(true);
+        assert!(listener.is_enabled());
     }
 
     #[test]
@@ -1161,9 +2019,10 @@ mod test {
             y: 2.0,
             z: 3.0,
         };
-        engine.set_position(0, p);
+        let listener = engine.listener(0).unwrap();
+        listener.set_position(p);
 
-        let got = engine.position(0);
+        let got = listener.position();
         assert_vec3_eq(got, p);
     }
 
@@ -1176,9 +2035,10 @@ mod test {
             y: 0.5,
             z: 10.0,
         };
-        engine.set_velocity(0, v);
+        let listener = engine.listener(0).unwrap();
+        listener.set_velocity(v);
 
-        let got = engine.velocity(0);
+        let got = listener.velocity();
         assert_vec3_eq(got, v);
     }
 
@@ -1191,9 +2051,10 @@ mod test {
             y: 1.0,
             z: 0.0,
         };
-        engine.set_world_up(0, up);
+        let listener = engine.listener(0).unwrap();
+        listener.set_world_up(up);
 
-        let got = engine.get_world_up(0);
+        let got = listener.world_up();
         assert_vec3_eq(got, up);
     }
 
@@ -1208,8 +2069,9 @@ mod test {
             outer_gain: 0.25,
         };
 
-        engine.set_cone(0, cone);
-        let got = engine.cone(0);
+        let listener = engine.listener(0).unwrap();
+        listener.set_cone(cone);
+        let got = listener.cone();
 
         assert_f32_eq(got.inner_angle_rad, cone.inner_angle_rad);
         assert_f32_eq(got.outer_angle_rad, cone.outer_angle_rad);
@@ -1223,46 +2085,40 @@ mod test {
         // If only 1 listener, the only valid answer is 0.
         let n = engine.listener_count();
         if n < 2 {
-            let idx = engine.closest_listener(Vec3 {
+            let closest = engine.closest_listener(Vec3 {
                 x: 100.0,
                 y: 0.0,
                 z: 0.0,
             });
-            assert_eq!(idx, 0);
+            assert_eq!(closest.index(), 0);
             return;
         }
 
         // If >=2 listeners, we can make a meaningful test.
-        engine.set_position(
-            0,
-            Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-        );
-        engine.set_position(
-            1,
-            Vec3 {
-                x: 1000.0,
-                y: 0.0,
-                z: 0.0,
-            },
-        );
+        engine.listener(0).unwrap().set_position(Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        });
+        engine.listener(1).unwrap().set_position(Vec3 {
+            x: 1000.0,
+            y: 0.0,
+            z: 0.0,
+        });
 
-        let idx = engine.closest_listener(Vec3 {
+        let closest = engine.closest_listener(Vec3 {
             x: 0.1,
             y: 0.0,
             z: 0.0,
         });
-        assert_eq!(idx, 0);
+        assert_eq!(closest.index(), 0);
 
-        let idx = engine.closest_listener(Vec3 {
+        let closest = engine.closest_listener(Vec3 {
             x: 999.9,
             y: 0.0,
             z: 0.0,
         });
-        assert_eq!(idx, 1);
+        assert_eq!(closest.index(), 1);
     }
 
     #[test]
@@ -1294,6 +2150,81 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_engine_read_pcm_frames_i16_shapes_output() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr44100)
+            .build()
+            .unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let buffer = reader.read_pcm_frames_i16(128).unwrap();
+
+        assert!(buffer.frames() <= 128);
+        assert_eq!(buffer.as_ref().len(), buffer.frames() * 2);
+        // Nothing is playing
+        assert!(buffer.as_ref().iter().all(|s| *s == 0));
+    }
+
+    // Renders `frames` of a looping ramp through a fresh engine, converted to `F`
+    fn render_ramp_as<F: PcmFormat>(frames: u64) -> SampleBuffer<F> {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr44100)
+            .build()
+            .unwrap();
+        let data: Vec<f32> = (0..128).map(|i| (i as f32 / 128.0) - 0.5).collect();
+        let buf = AudioBufferBuilder::build_f32(2, &data).unwrap();
+        let src = buf.as_source_ref();
+        let mut sound = engine.new_sound_from_source(&src).unwrap();
+        sound.set_spatialization(false);
+        sound.set_looping(true);
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader
+            .read_pcm_frames_as::<F>(frames, Dither::None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_engine_read_pcm_frames_as_matches_f32() {
+        let reference = render_ramp_as::<f32>(64);
+        let converted = render_ramp_as::<i16>(64);
+
+        assert_eq!(reference.frames(), converted.frames());
+        assert!(reference.as_ref().iter().any(|s| *s != 0.0));
+        for (f, i) in reference.as_ref().iter().zip(converted.as_ref()) {
+            let expected = (f * 32767.0) as i32;
+            assert!((expected - *i as i32).abs() <= 1, "{f} -> {i}");
+        }
+    }
+
+    #[test]
+    fn test_engine_read_pcm_frames_into_as_s24() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr44100)
+            .build()
+            .unwrap();
+        let data = vec![0.25f32; 64];
+        let buf = AudioBufferBuilder::build_f32(2, &data).unwrap();
+        let src = buf.as_source_ref();
+        let mut sound = engine.new_sound_from_source(&src).unwrap();
+        sound.set_spatialization(false);
+        sound.set_looping(true);
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        // 10 frames of stereo, plus one sample that does not make a full frame
+        let mut dst = vec![1i32; 21];
+        let frames = reader
+            .read_pcm_frames_into_as::<crate::pcm_frames::S24>(&mut dst, Dither::None)
+            .unwrap();
+
+        assert_eq!(frames, 10);
+        assert!(dst[..20].iter().all(|s| *s != 1));
+        assert_eq!(dst[20], 1);
+    }
+
     #[test]
     fn test_engine_time_pcm_set_get() {
         let engine = Engine::new_for_tests().unwrap();
@@ -1337,9 +2268,157 @@ mod test {
             y: 0.0,
             z: -1.0,
         };
-        engine.set_direction(0, dir);
+        let listener = engine.listener(0).unwrap();
+        listener.set_direction(dir);
 
-        let got = engine.direction(0);
+        let got = listener.direction();
         assert_vec3_eq(got, dir);
     }
+
+    #[test]
+    fn test_engine_resource_manager_register_and_load_by_name() {
+        use crate::{audio::formats::Format, engine::resource::RmOps, test_assets::wav_i16_le};
+
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        assert_eq!(rm.decoded_format().unwrap(), Format::F32);
+
+        let samples: Vec<i16> = (0..200).map(|i| (i * 100) as i16).collect();
+        let wav = wav_i16_le(1, SampleRate::Sr44100, &samples);
+        let _guard = rm.register_encoded("engine_rm_test.wav", &wav).unwrap();
+
+        let sound = engine
+            .new_sound_from_file(Path::new("engine_rm_test.wav"))
+            .unwrap();
+        drop(sound);
+        assert!(engine
+            .new_sound_from_file(Path::new("engine_rm_missing.wav"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_engine_new_sound_from_registered() {
+        use crate::{engine::resource::RmOps, test_assets::wav_i16_le};
+
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+
+        let samples: Vec<i16> = (0..200).map(|i| (i * 100) as i16).collect();
+        let wav = wav_i16_le(1, SampleRate::Sr44100, &samples);
+        let guard = rm.register_encoded("engine_registered.wav", &wav).unwrap();
+
+        let sound = engine
+            .new_sound_from_registered("engine_registered.wav", SoundFlags::DECODE)
+            .unwrap();
+        assert!(sound.length_pcm().unwrap() > 0);
+
+        // The stream flag is ignored for registered data
+        let sound_stream = engine
+            .new_sound_from_registered("engine_registered.wav", SoundFlags::STREAM)
+            .unwrap();
+        drop(sound_stream);
+        drop(sound);
+
+        assert!(engine
+            .new_sound_from_registered("engine_not_registered.wav", SoundFlags::NONE)
+            .is_err());
+        drop(guard);
+        assert!(engine
+            .new_sound_from_registered("engine_registered.wav", SoundFlags::NONE)
+            .is_err());
+    }
+
+    #[test]
+    fn test_engine_render_offline_honors_schedule() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buf = AudioBufferBuilder::build_f32(1, &[0.5f32; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buf).unwrap();
+        sound.set_start_time_pcm(1024);
+        sound.set_stop_time_pcm(2100);
+        sound.play_sound().unwrap();
+
+        let mut out = Vec::new();
+        let mut blocks = 0;
+        engine
+            .render_offline(3000, |block| {
+                blocks += 1;
+                out.extend_from_slice(block);
+            })
+            .unwrap();
+
+        assert_eq!(out.len(), 3000);
+        assert_eq!(blocks, 3);
+        assert_eq!(engine.time_pcm(), 3000);
+        assert!(out[..1024].iter().all(|s| *s == 0.0));
+        assert!(out[1100..2000].iter().all(|s| *s != 0.0));
+        assert!(out[2048..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn test_engine_render_offline_small_blocks() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buf = AudioBufferBuilder::build_f32(1, &[0.5f32; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buf).unwrap();
+        sound.set_start_time_pcm(1500);
+        sound.set_stop_time_pcm(2500);
+        sound.play_sound().unwrap();
+
+        let mut out = Vec::new();
+        engine
+            .render_offline_with_block_size(3000, 100, |block| out.extend_from_slice(block))
+            .unwrap();
+
+        assert_eq!(out.len(), 3000);
+        assert!(out[..1500].iter().all(|s| *s == 0.0));
+        assert!(out[1600..2400].iter().all(|s| *s != 0.0));
+        assert!(out[2500..].iter().all(|s| *s == 0.0));
+        assert!(engine
+            .render_offline_with_block_size(10, 0, |_| {})
+            .is_err());
+    }
+
+    #[test]
+    fn test_engine_render_offline_empty_graph_advances_time() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+
+        let mut frames = 0;
+        engine
+            .render_offline(2000, |block| {
+                assert!(block.iter().all(|s| *s == 0.0));
+                frames += block.len() / 2;
+            })
+            .unwrap();
+        assert_eq!(frames, 2000);
+        assert_eq!(engine.time_pcm(), 2000);
+
+        let _reader = engine.try_acquire_reader().unwrap();
+        assert!(engine.render_offline(10, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_engine_render_offline_fails_with_device() {
+        let engine = EngineBuilder::new()
+            .backends(&[crate::backend::Backend::Null])
+            .no_auto_start(true)
+            .build()
+            .unwrap();
+        let mut called = false;
+        let res = engine.render_offline(256, |_| called = true);
+        assert!(matches!(
+            res,
+            Err(e) if matches!(e.kind(), Some(ErrorKinds::InvalidOperation(_)))
+        ));
+        assert!(!called);
+        // No reader was left behind
+        assert!(engine.try_acquire_reader().is_ok());
+    }
 }
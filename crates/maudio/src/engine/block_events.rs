@@ -0,0 +1,240 @@
+//! Notifications for each block processed by the engine.
+//!
+//! Block events are enabled with
+//! [`EngineBuilder::with_block_events`](crate::engine::engine_builder::EngineBuilder::with_block_events).
+//! [`Engine::subscribe_blocks`](crate::engine::Engine::subscribe_blocks) then returns a
+//! [`BlockSubscription`], receiving a [`BlockInfo`] for every block the engine renders.
+//!
+//! Unlike [`ProcFramesNotif`](crate::util::proc_notif::ProcFramesNotif), which only adds up
+//! frames, each block is reported on its own with its timing. This is enough to drive
+//! visuals in step with the audio, or to notice stalls when a block took longer to render
+//! than the audio it holds.
+//!
+//! The audio thread never waits on a subscriber. Each subscription has a bounded channel,
+//! and blocks arriving while it is full are dropped and counted by
+//! [`BlockSubscription::dropped`].
+//!
+//! ```no_run
+//! # use maudio::engine::engine_builder::EngineBuilder;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = EngineBuilder::new().with_block_events().build()?;
+//! let sample_rate = engine.sample_rate()?.into();
+//! let blocks = engine.subscribe_blocks(64)?;
+//!
+//! loop {
+//!     for block in blocks.drain() {
+//!         if block.is_late(sample_rate) {
+//!             println!("block of {} frames took {:?}", block.frames, block.duration);
+//!         }
+//!     }
+//!     std::thread::sleep(std::time::Duration::from_millis(16));
+//! }
+//! # }
+//! ```
+use std::{
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{util::rt_check, MaResult, MaudioError};
+
+/// A block rendered by the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Number of frames in the block.
+    pub frames: u32,
+    /// Engine time, in frames, at the end of the block.
+    pub engine_time: u64,
+    /// Time spent rendering the block.
+    ///
+    /// `None` when the engine is driven by a user supplied device, which is not timed.
+    pub duration: Option<Duration>,
+    /// When the block finished rendering.
+    pub timestamp: Instant,
+}
+
+impl BlockInfo {
+    /// Returns `true` if rendering the block took longer than the audio it holds, at
+    /// `sample_rate`. Such a block is likely heard as a glitch.
+    pub fn is_late(&self, sample_rate: u32) -> bool {
+        let Some(duration) = self.duration else {
+            return false;
+        };
+        if sample_rate == 0 {
+            return false;
+        }
+        duration > Duration::from_secs_f64(self.frames as f64 / sample_rate as f64)
+    }
+}
+
+/// Receives the blocks processed by an engine. See the [module docs](self).
+///
+/// Dropping the subscription unsubscribes it.
+pub struct BlockSubscription {
+    rx: Receiver<BlockInfo>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl BlockSubscription {
+    /// Returns the oldest block not received yet, without waiting.
+    pub fn try_recv(&self) -> Option<BlockInfo> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next block.
+    ///
+    /// Returns `None` on timeout, or once the engine is dropped.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<BlockInfo> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+
+    /// Returns the blocks received so far, oldest first, without waiting.
+    pub fn drain(&self) -> impl Iterator<Item = BlockInfo> + '_ {
+        self.rx.try_iter()
+    }
+
+    /// Number of blocks dropped because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+struct Subscriber {
+    tx: SyncSender<BlockInfo>,
+    // Shared with the subscription, only this side holds it once the subscription is dropped
+    dropped: Arc<AtomicU64>,
+}
+
+/// Subscribers and block timing, kept in the engine's `ProcessState`.
+pub(crate) struct BlockEvents {
+    base: Instant,
+    // Nanoseconds since `base` when the current block started, `NO_START` if not timed
+    start_ns: AtomicU64,
+    engine: AtomicPtr<sys::ma_engine>,
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+const NO_START: u64 = u64::MAX;
+
+impl BlockEvents {
+    pub(crate) fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            start_ns: AtomicU64::new(NO_START),
+            engine: AtomicPtr::new(ptr::null_mut()),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Sets the engine whose time is reported, once it is initialized.
+    pub(crate) fn set_engine(&self, engine: *mut sys::ma_engine) {
+        self.engine.store(engine, Ordering::Release);
+    }
+
+    /// Marks the start of a block, called before the engine renders it.
+    pub(crate) fn block_started(&self) {
+        let ns = self.base.elapsed().as_nanos() as u64;
+        self.start_ns.store(ns, Ordering::Relaxed);
+    }
+
+    pub(crate) fn subscribe(&self, capacity: usize) -> MaResult<BlockSubscription> {
+        if capacity == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        rt_check::check("Engine::subscribe_blocks");
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Senders of dropped subscriptions are freed here, not on the audio thread
+        subscribers.retain(|s| Arc::strong_count(&s.dropped) > 1);
+        subscribers.push(Subscriber {
+            tx,
+            dropped: dropped.clone(),
+        });
+        Ok(BlockSubscription { rx, dropped })
+    }
+
+    /// Sends a block to every subscriber. Called from the audio thread, never blocks.
+    pub(crate) fn publish(&self, frames: u32) {
+        let timestamp = Instant::now();
+        let start = self.start_ns.swap(NO_START, Ordering::Relaxed);
+        let duration = (start != NO_START).then(|| {
+            let end = timestamp.duration_since(self.base).as_nanos() as u64;
+            Duration::from_nanos(end.saturating_sub(start))
+        });
+        let engine = self.engine.load(Ordering::Acquire);
+        let engine_time = if engine.is_null() {
+            0
+        } else {
+            unsafe { sys::ma_engine_get_time_in_pcm_frames(engine) }
+        };
+        let info = BlockInfo {
+            frames,
+            engine_time,
+            duration,
+            timestamp,
+        };
+
+        // A subscriber is being added, skip this block rather than wait
+        let Ok(subscribers) = self.subscribers.try_lock() else {
+            return;
+        };
+        for subscriber in subscribers.iter() {
+            if let Err(TrySendError::Full(_)) = subscriber.tx.try_send(info) {
+                subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_block_events_bounded_channel() {
+        let events = BlockEvents::new();
+        assert!(events.subscribe(0).is_err());
+        let blocks = events.subscribe(2).unwrap();
+
+        events.block_started();
+        events.publish(480);
+        events.publish(480);
+        events.publish(480);
+        assert_eq!(blocks.dropped(), 1);
+
+        let received: Vec<_> = blocks.drain().collect();
+        assert_eq!(received.len(), 2);
+        assert!(received[0].duration.is_some());
+        // Only the first block was timed
+        assert!(received[1].duration.is_none());
+        assert!(blocks.try_recv().is_none());
+
+        drop(blocks);
+        let _blocks = events.subscribe(1).unwrap();
+        assert_eq!(events.subscribers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_block_info_is_late() {
+        let block = BlockInfo {
+            frames: 480,
+            engine_time: 0,
+            duration: Some(Duration::from_millis(12)),
+            timestamp: Instant::now(),
+        };
+        // 480 frames at 48 kHz is 10 ms of audio
+        assert!(block.is_late(48_000));
+        assert!(!block.is_late(24_000));
+    }
+}
@@ -0,0 +1,275 @@
+//! Routing of sound groups to the engines of an [`EngineSet`].
+//!
+//! A [`DeviceRouter`] gives names to routes ("main", "cue", ...) and plays each route on one
+//! of the engines, through a [`SoundGroup`] created on that engine. Since every engine drives
+//! its own output device, this picks the device a sound is heard on.
+//!
+//! A playing sound can be moved to another route with [`DeviceRouter::migrate`]: the sound is
+//! copied on the target engine, seeked to the same position and started there, then stopped
+//! on its previous engine. This is how a DJ previews a track on the headphones and then
+//! moves it to the main output without restarting it.
+//!
+//! ```no_run
+//! # use maudio::engine::{device_router::DeviceRouter, engine_builder::EngineBuilder, engine_set::EngineSet};
+//! # use maudio::engine::resource::rm_builder::ResourceManagerBuilder;
+//! # use maudio::sound::sound_flags::SoundFlags;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let rm = ResourceManagerBuilder::new().build_f32()?;
+//! let mut set = EngineSet::new(&rm);
+//! set.add(&mut EngineBuilder::new())?; // main output
+//! set.add(&mut EngineBuilder::new())?; // headphones
+//!
+//! let mut router = DeviceRouter::new(set);
+//! router.assign("main", 0)?;
+//! router.assign("cue", 1)?;
+//!
+//! let mut track = router.new_sound("cue", Path::new("track.mp3"), SoundFlags::DECODE)?;
+//! track.play_sound()?;
+//! // Later, without restarting the track
+//! router.migrate(&mut track, "main")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only sounds loaded through the resource manager can be migrated, and streamed sounds
+//! cannot, since miniaudio only copies sounds reading a decoded or encoded buffer.
+use std::path::Path;
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::{engine_set::EngineSet, Engine},
+    sound::{
+        sound_flags::SoundFlags,
+        sound_group::{SoundGroup, SoundGroupBuilder},
+        Sound,
+    },
+    ErrorContext, MaResult, MaudioError, ResultContext,
+};
+
+struct Route {
+    name: String,
+    engine: usize,
+    group: SoundGroup,
+}
+
+/// Plays named routes on the engines of an [`EngineSet`]. See the [module docs](self).
+pub struct DeviceRouter {
+    engines: EngineSet,
+    routes: Vec<Route>,
+}
+
+impl DeviceRouter {
+    pub fn new(engines: EngineSet) -> Self {
+        Self {
+            engines,
+            routes: Vec::new(),
+        }
+    }
+
+    pub fn engines(&self) -> &EngineSet {
+        &self.engines
+    }
+
+    /// Plays `route` on the engine at index `engine` of the set, creating the route if needed.
+    ///
+    /// Moving an existing route to another engine replaces its group. Sounds still playing
+    /// in the previous group are no longer heard, [`DeviceRouter::migrate`] them first.
+    pub fn assign(&mut self, route: &str, engine: usize) -> MaResult<()> {
+        let target = self
+            .engines
+            .get(engine)
+            .ok_or(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS))?;
+        if let Some(existing) = self.routes.iter().find(|r| r.name == route) {
+            if existing.engine == engine {
+                return Ok(());
+            }
+        }
+        let group = SoundGroupBuilder::new(target).build()?;
+        match self.routes.iter_mut().find(|r| r.name == route) {
+            Some(existing) => {
+                existing.engine = engine;
+                existing.group = group;
+            }
+            None => self.routes.push(Route {
+                name: route.to_owned(),
+                engine,
+                group,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Removes `route`. Sounds playing in it are no longer heard.
+    pub fn remove(&mut self, route: &str) -> bool {
+        let len = self.routes.len();
+        self.routes.retain(|r| r.name != route);
+        self.routes.len() != len
+    }
+
+    /// Returns the index of the engine playing `route`.
+    pub fn route_engine(&self, route: &str) -> Option<usize> {
+        self.find(route).ok().map(|r| r.engine)
+    }
+
+    /// Returns the group of `route`, to set its volume or effects.
+    pub fn group(&self, route: &str) -> Option<&SoundGroup> {
+        self.find(route).ok().map(|r| &r.group)
+    }
+
+    pub fn group_mut(&mut self, route: &str) -> Option<&mut SoundGroup> {
+        self.routes
+            .iter_mut()
+            .find(|r| r.name == route)
+            .map(|r| &mut r.group)
+    }
+
+    /// Loads a sound from a file and plays it in `route`.
+    pub fn new_sound(&self, route: &str, path: &Path, flags: SoundFlags) -> MaResult<Sound> {
+        let route = self.find(route)?;
+        self.engine(route)
+            .new_sound_with_file_internal(path, flags, Some(&route.group), None)
+            .with_path(path)
+    }
+
+    /// Moves `sound` to `route`, keeping its position, volume, pan, pitch and looping.
+    ///
+    /// If the sound is playing, it is started on the target engine and stopped on its
+    /// previous engine. `sound` is replaced by the copy, so notifiers and effects set on the
+    /// previous sound are dropped.
+    pub fn migrate(&mut self, sound: &mut Sound, route: &str) -> MaResult<()> {
+        let index = self
+            .routes
+            .iter()
+            .position(|r| r.name == route)
+            .ok_or_else(|| Self::missing(route))?;
+        let engine = &self.engines.engines()[self.routes[index].engine];
+
+        let playing = sound.is_playing();
+        let cursor = sound.cursor_pcm()?;
+        let mut copy = engine.new_sound_instance_internal(
+            sound,
+            SoundFlags::NONE,
+            Some(&mut self.routes[index].group),
+        )?;
+        copy.set_volume(sound.volume());
+        copy.set_pan(sound.pan());
+        copy.set_pitch(sound.pitch());
+        copy.set_looping(sound.looping());
+        copy.seek_to_frame(cursor)?;
+        if playing {
+            copy.play_sound()?;
+            sound.stop_sound()?;
+        }
+        *sound = copy;
+        Ok(())
+    }
+
+    fn find(&self, route: &str) -> MaResult<&Route> {
+        self.routes
+            .iter()
+            .find(|r| r.name == route)
+            .ok_or_else(|| Self::missing(route))
+    }
+
+    fn engine(&self, route: &Route) -> &Engine {
+        // The set is not exposed mutably, so the index stays valid
+        &self.engines.engines()[route.engine]
+    }
+
+    fn missing(route: &str) -> MaudioError {
+        MaudioError::from_ma_result(sys::ma_result_MA_DOES_NOT_EXIST)
+            .with_context(ErrorContext::Resource(route.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeviceRouter;
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{
+            engine_builder::EngineBuilder,
+            engine_set::EngineSet,
+            resource::{rm_builder::ResourceManagerBuilder, RmOps},
+        },
+        sound::sound_flags::SoundFlags,
+        test_assets::wav_i16_le,
+    };
+    use std::path::Path;
+
+    fn router() -> DeviceRouter {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let mut set = EngineSet::new(&rm);
+        set.add(EngineBuilder::new().no_device(1, SampleRate::Sr48000))
+            .unwrap();
+        set.add(EngineBuilder::new().no_device(1, SampleRate::Sr48000))
+            .unwrap();
+        let mut router = DeviceRouter::new(set);
+        router.assign("main", 0).unwrap();
+        router.assign("cue", 1).unwrap();
+        router
+    }
+
+    #[test]
+    fn test_device_router_migrate_keeps_position() {
+        let mut router = router();
+        let rm = router.engines().resource_manager().clone();
+
+        let samples = vec![8000i16; 4800];
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &samples);
+        let _guard = rm.register_encoded("router.wav", &wav).unwrap();
+
+        let mut sound = router
+            .new_sound("cue", Path::new("router.wav"), SoundFlags::DECODE)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let cue = &router.engines().engines()[1];
+        let mut reader = cue.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(1000).unwrap();
+        assert!(out.as_ref()[16..].iter().all(|s| *s > 0.0));
+        drop(reader);
+
+        let cursor = sound.cursor_pcm().unwrap();
+        assert!(cursor > 0);
+        router.migrate(&mut sound, "main").unwrap();
+        assert!(sound.is_playing());
+        assert_eq!(sound.cursor_pcm().unwrap(), cursor);
+
+        let main = &router.engines().engines()[0];
+        let out = main
+            .try_acquire_reader()
+            .unwrap()
+            .read_pcm_frames(1000)
+            .unwrap();
+        assert!(out.as_ref()[16..].iter().all(|s| *s > 0.0));
+        assert!(sound.cursor_pcm().unwrap() > cursor);
+
+        // The cue engine no longer plays the sound
+        let cue = &router.engines().engines()[1];
+        let out = cue
+            .try_acquire_reader()
+            .unwrap()
+            .read_pcm_frames(1000)
+            .unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn test_device_router_routes() {
+        let mut router = router();
+        assert_eq!(router.route_engine("cue"), Some(1));
+        router.assign("cue", 0).unwrap();
+        assert_eq!(router.route_engine("cue"), Some(0));
+
+        assert!(router.assign("zone", 5).is_err());
+        assert!(router
+            .new_sound("zone", Path::new("router.wav"), SoundFlags::NONE)
+            .is_err());
+        assert!(router.remove("cue"));
+        assert!(!router.remove("cue"));
+        assert!(router.group("cue").is_none());
+    }
+}
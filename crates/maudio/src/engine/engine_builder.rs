@@ -5,12 +5,18 @@ use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{channels::MonoExpansionMode, sample_rate::SampleRate},
+    backend::Backend,
+    context::{Context, ContextBuilder},
+    data_source::sources::pcm_ring_buffer::PcmRingBuffer,
     device::{device_id::DeviceId, Device, DeviceInner},
     engine::{
+        block_events::BlockEvents,
         engine_cb_notif::engine_notification_callback,
+        engine_stats::StatsCounters,
+        output_tap::OutputTap,
         process_cb::{on_process_callback, EngineProcessCallback, ProcessState},
-        resource::{private_rm, ResourceManager},
-        Engine,
+        resource::{private_rm, ResourceManager, ResourceManagerRef},
+        Engine, EngineInner,
     },
     util::{device_notif::DeviceStateNotifier, proc_notif::ProcFramesNotif},
     AsRawRef, Binding, MaResult,
@@ -21,6 +27,9 @@ pub struct EngineBuilder {
     pub(crate) playback_device_id: Option<DeviceId>,
     pub(crate) device: Option<Arc<DeviceInner<f32>>>, // a ref count, not ownership
     pub(crate) resource_manager: Option<ResourceManager<f32>>, // a ref count, not ownership
+    pub(crate) resource_manager_owner: Option<Arc<EngineInner>>, // engine owning a shared resource manager
+    pub(crate) context: Option<Context>,                         // a ref count, not ownership
+    pub(crate) backends: Option<Vec<Backend>>,
     pub(crate) process_data: EngineProcessCbData,
 }
 
@@ -29,6 +38,9 @@ pub(crate) struct EngineProcessCbData {
     pub(crate) process_data_panic: Option<Arc<AtomicBool>>,
     pub(crate) state_notif_exists: bool,
     pub(crate) state_notif: Option<DeviceStateNotifier>, // Always set by set_process_notifier. Dropped if state_notif_exists is false
+    pub(crate) stats_enabled: bool,
+    pub(crate) stats: Option<Arc<StatsCounters>>, // Set by set_process_notifier if stats_enabled
+    pub(crate) block_events_enabled: bool,
 }
 
 unsafe impl Send for EngineBuilder {}
@@ -51,11 +63,17 @@ impl EngineBuilder {
             playback_device_id: None,
             device: None,
             resource_manager: None,
+            resource_manager_owner: None,
+            context: None,
+            backends: None,
             process_data: EngineProcessCbData {
                 process_data_ptr: None,
                 process_data_panic: None,
                 state_notif_exists: false,
                 state_notif: None,
+                stats_enabled: false,
+                stats: None,
+                block_events_enabled: false,
             },
         }
     }
@@ -70,12 +88,72 @@ impl EngineBuilder {
     pub fn resource_manager(&mut self, manager: &ResourceManager<f32>) -> &mut Self {
         self.inner.pResourceManager = private_rm::rm_ptr(manager);
         self.resource_manager = Some(manager.clone());
+        self.resource_manager_owner = None;
         self
     }
 
+    /// Shares the resource manager of another engine, returned by
+    /// [`Engine::resource_manager`].
+    ///
+    /// Sounds loaded by both engines from the same file or registered name then share their
+    /// decoded data, instead of decoding it twice. This is how several output devices, like
+    /// a DJ cue bus or the zones of an installation, play the same assets. The engine owning
+    /// the resource manager is kept alive until the new engine is dropped.
+    ///
+    /// See [`EngineSet`](crate::engine::engine_set::EngineSet) to manage several engines.
+    pub fn resource_manager_ref(&mut self, manager: &ResourceManagerRef<'_, f32>) -> &mut Self {
+        self.inner.pResourceManager = private_rm::rm_ptr(manager);
+        self.resource_manager = None;
+        self.resource_manager_owner = Some(manager.engine_inner().clone());
+        self
+    }
+
+    /// Sets the [`Context`] used to create the engine's device.
+    ///
+    /// Use it to apply backend options, such as the iOS audio session, that are set on
+    /// [`ContextBuilder`](crate::context::ContextBuilder). Ignored if a device is set with
+    /// [`device`](Self::device) or the engine has no device.
+    pub fn context(&mut self, context: &Context) -> &mut Self {
+        self.inner.pContext = context.to_raw();
+        self.context = Some(context.clone());
+        self
+    }
+
+    /// Sets the backends the engine's device can use, in order of priority.
+    ///
+    /// The first backend that can be initialized is used, and building fails if none of them
+    /// are available. An empty list uses miniaudio's default order.
+    ///
+    /// Ignored if a context is set with [`context`](Self::context), in which case the backends
+    /// are set with [`ContextBuilder::preferred_backends`](crate::context::ContextBuilder::preferred_backends).
+    /// Also ignored if a device is set or the engine has no device.
+    pub fn backends(&mut self, backends: &[Backend]) -> &mut Self {
+        self.backends = Some(backends.to_vec());
+        self
+    }
+
+    // Creates the context used to restrict the backends, unless one is not needed.
+    // The builder is left unchanged so that it can be reused.
+    pub(crate) fn backends_context(&self) -> MaResult<Option<Context>> {
+        let Some(backends) = self.backends.as_deref() else {
+            return Ok(None);
+        };
+        if self.context.is_some() || self.device.is_some() || self.inner.noDevice != 0 {
+            return Ok(None);
+        }
+        ContextBuilder::new()
+            .preferred_backends(backends)
+            .build()
+            .map(Some)
+    }
+
     /// Sets how many listeners the engine will create.
     ///
-    /// The default is `1` listener (index `0`).
+    /// The default is `1` listener (index `0`). At most
+    /// [`MA_ENGINE_MAX_LISTENERS`](maudio_sys::ffi::MA_ENGINE_MAX_LISTENERS) listeners are supported.
+    /// A count of `0` is treated as `1`, and building the engine fails with a higher count.
+    ///
+    /// Listeners are accessed with [`Engine::listener`].
     pub fn listener_count(&mut self, count: u32) -> &mut Self {
         self.inner.listenerCount = count;
         self
@@ -93,6 +171,10 @@ impl EngineBuilder {
         self
     }
 
+    /// Controls how mono sounds are expanded to the engine's channel count.
+    ///
+    /// This is the default for sounds created by the engine. Each sound can override it
+    /// with [`SoundBuilder::mono_expansion_mode`](crate::sound::sound_builder::SoundBuilder::mono_expansion_mode).
     pub fn mono_expansion_mode(&mut self, mode: MonoExpansionMode) -> &mut Self {
         self.inner.monoExpansionMode = mode.into();
         self
@@ -111,6 +193,9 @@ impl EngineBuilder {
     ///
     /// If not set, the default system device is used.
     ///
+    /// The ID can come from enumeration, or be restored from a previous run with
+    /// [`DeviceId::from_bytes`].
+    ///
     /// This is ignored if you are also passing in a device via [`EngineBuilder::device`]
     /// In that scenario, you should pass the `DeviceId` to that custom `Device` instead.
     pub fn device_id(&mut self, playback_id: &DeviceId) -> &mut Self {
@@ -120,29 +205,55 @@ impl EngineBuilder {
     }
 
     /// Sets the gain smoothing time in frames.
+    ///
+    /// Smooths the gain changes applied by spatialization, such as a sound moving
+    /// around the listener. Takes precedence over [`EngineBuilder::gain_smooth_millis`].
     pub fn gain_smooth_frames(&mut self, frames: u32) -> &mut Self {
         self.inner.gainSmoothTimeInFrames = frames;
         self
     }
 
     /// Sets the gain smoothing time in milliseconds.
+    ///
+    /// Only used when [`EngineBuilder::gain_smooth_frames`] is `0`.
     pub fn gain_smooth_millis(&mut self, millis: u32) -> &mut Self {
         self.inner.gainSmoothTimeInMilliseconds = millis;
         self
     }
 
     /// Sets the device period size (buffer size) in frames.
-    pub fn period_time_frames(&mut self, frames: u32) -> &mut Self {
+    ///
+    /// Takes precedence over [`EngineBuilder::period_size_millis`]. If both are `0`,
+    /// miniaudio picks a default.
+    ///
+    /// This is a hint and may be adjusted by the backend. It is ignored when the engine
+    /// has no device, or when a device is passed in with [`EngineBuilder::device`].
+    pub fn period_size_frames(&mut self, frames: u32) -> &mut Self {
         self.inner.periodSizeInFrames = frames;
         self
     }
 
     /// Sets the device period size (buffer size) in milliseconds.
-    pub fn period_time_millis(&mut self, millis: u32) -> &mut Self {
+    ///
+    /// Only used when [`EngineBuilder::period_size_frames`] is `0`. Smaller values reduce
+    /// latency but increase how often the device callback runs.
+    ///
+    /// This is a hint and may be adjusted by the backend.
+    pub fn period_size_millis(&mut self, millis: u32) -> &mut Self {
         self.inner.periodSizeInMilliseconds = millis;
         self
     }
 
+    #[deprecated(since = "0.1.6", note = "renamed to `period_size_frames`")]
+    pub fn period_time_frames(&mut self, frames: u32) -> &mut Self {
+        self.period_size_frames(frames)
+    }
+
+    #[deprecated(since = "0.1.6", note = "renamed to `period_size_millis`")]
+    pub fn period_time_millis(&mut self, millis: u32) -> &mut Self {
+        self.period_size_millis(millis)
+    }
+
     /// Sets the internal stack size used during node graph processing.
     ///
     /// Smaller values limit the maximum depth of the graph.
@@ -176,7 +287,15 @@ impl EngineBuilder {
 
     fn set_process_notifier(&mut self, f: Option<Box<EngineProcessCallback>>) -> ProcFramesNotif {
         let channels = self.inner.channels; // engine is init with 2 channels by default
-        let state = ProcessState::new(channels, f);
+        let stats = self
+            .process_data
+            .stats_enabled
+            .then(|| Arc::new(StatsCounters::default()));
+        let blocks = self
+            .process_data
+            .block_events_enabled
+            .then(BlockEvents::new);
+        let state = ProcessState::new(channels, f, stats.clone(), blocks);
 
         let proc_notif = state.clone_proc_notif();
         let proc_data_panic = state.clone_panic_flag();
@@ -190,6 +309,7 @@ impl EngineBuilder {
         self.process_data.process_data_ptr = Some(state_ptr);
         self.process_data.process_data_panic = Some(proc_data_panic);
         self.process_data.state_notif = Some(state_notif);
+        self.process_data.stats = stats;
 
         proc_notif
     }
@@ -272,6 +392,7 @@ impl EngineBuilder {
     ///   It is **only valid for the duration of the callback** and must not be stored
     ///   or referenced after the callback returns.
     /// - The callback should not panic. If it does panic, the callback will be poisoned
+    ///   (see [`callback_panic`](crate::util::callback_panic)).
     ///   Use [`Engine::data_callback_panicked()`] to check if the callback is poisoned
     /// - This also installs a [`ProcFramesNotifier`](crate::util::proc_notif::ProcFramesNotif).
     ///   It can be retrieved by calling [`Engine::get_data_notifier()`] after building the `Engine`.
@@ -286,6 +407,41 @@ impl EngineBuilder {
         Engine::new_with_process_data(self, Some(notifier))
     }
 
+    /// Builds an [`Engine`] that copies its final mixed output into an [`OutputTap`].
+    ///
+    /// The output still goes to the device as usual. Each processed block is also written,
+    /// from the engine's `onProcess` callback, into a ring buffer holding up to `size_frames`
+    /// frames, which the returned tap reads from another thread. When the ring buffer is
+    /// full, new frames are dropped and counted by [`OutputTap::overrun_notifier`].
+    ///
+    /// This also installs a [`ProcFramesNotif`], see [`EngineBuilder::with_process_notifier()`].
+    pub fn with_output_tap(&mut self, size_frames: u32) -> MaResult<(Engine, OutputTap)> {
+        // The tap needs the channel count, which may only be known once the engine exists.
+        // Keep the engine stopped until the callback is installed.
+        let no_auto_start = self.inner.noAutoStart;
+        self.inner.noAutoStart = 1;
+
+        let notifier = self.set_process_notifier(None);
+        self.inner.onProcess = Some(on_process_callback);
+        let engine = Engine::new_with_process_data(self, Some(notifier));
+        self.inner.noAutoStart = no_auto_start;
+        let engine = engine?;
+
+        let (mut tx, rx) = PcmRingBuffer::new_f32(size_frames, engine.channels())?;
+        tx.set_sample_rate(engine.sample_rate()?);
+        let (tap, writer) = OutputTap::new(tx, rx);
+
+        if let Some(state) = engine.0.process_data_ptr {
+            // Safe: the engine is not started and no reader was handed out yet
+            unsafe { (*state).set_callback(writer) };
+        }
+
+        if no_auto_start == 0 && self.inner.noDevice == 0 {
+            engine.start()?;
+        }
+        Ok((engine, tap))
+    }
+
     pub fn build(&mut self) -> MaResult<Engine> {
         let _ = self.set_process_notifier(None);
 
@@ -304,6 +460,25 @@ impl EngineBuilder {
         self
     }
 
+    /// Collects [`EngineStats`](crate::engine::engine_stats::EngineStats) while the engine runs.
+    ///
+    /// They can be retrieved by calling [`Engine::stats()`] after building the `Engine`.
+    /// The counters are updated with atomics from the audio thread, so the overhead is
+    /// small, but it is not zero.
+    pub fn with_stats(&mut self) -> &mut Self {
+        self.process_data.stats_enabled = true;
+        self
+    }
+
+    /// Reports each processed block to the subscriptions returned by
+    /// [`Engine::subscribe_blocks()`], see [`block_events`](crate::engine::block_events).
+    ///
+    /// Like [`EngineBuilder::with_stats()`], this times every block on the audio thread.
+    pub fn with_block_events(&mut self) -> &mut Self {
+        self.process_data.block_events_enabled = true;
+        self
+    }
+
     #[allow(dead_code)]
     pub(crate) fn build_for_tests(&mut self) -> MaResult<Engine> {
         if cfg!(feature = "ci-tests") {
@@ -443,6 +618,54 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_engine_builder_listener_count_is_reported() -> MaResult<()> {
+        let mut b = EngineBuilder::new();
+        b.listener_count(3);
+
+        let engine = build_ci_engine(b)?;
+        assert_eq!(engine.listener_count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_builder_listener_count_limits() {
+        let mut b = EngineBuilder::new();
+        b.listener_count(0);
+        assert_eq!(build_ci_engine(b).unwrap().listener_count(), 1);
+
+        let mut b = EngineBuilder::new();
+        b.listener_count(sys::MA_ENGINE_MAX_LISTENERS + 1);
+        assert!(build_ci_engine(b).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_engine_builder_deprecated_period_setters_forward() {
+        let mut b = EngineBuilder::new();
+        b.period_time_frames(512).period_time_millis(20);
+        assert_eq!(b.inner.periodSizeInFrames, 512);
+        assert_eq!(b.inner.periodSizeInMilliseconds, 20);
+    }
+
+    #[test]
+    fn test_engine_builder_period_and_smoothing_are_applied() -> MaResult<()> {
+        let mut b = EngineBuilder::new();
+        b.period_size_frames(256)
+            .period_size_millis(10)
+            .gain_smooth_frames(128)
+            .gain_smooth_millis(5)
+            .mono_expansion_mode(MonoExpansionMode::StereoOnly);
+        assert_eq!(b.inner.periodSizeInFrames, 256);
+        assert_eq!(b.inner.periodSizeInMilliseconds, 10);
+        assert_eq!(b.inner.gainSmoothTimeInFrames, 128);
+        assert_eq!(b.inner.gainSmoothTimeInMilliseconds, 5);
+
+        let engine = build_ci_engine(b)?;
+        drop(engine);
+        Ok(())
+    }
+
     #[test]
     fn test_engine_builder_no_auto_start_is_applied() -> MaResult<()> {
         let mut b = EngineBuilder::new();
@@ -553,6 +776,62 @@ mod test {
         let _rm_ref = engine.resource_manager().unwrap();
     }
 
+    #[test]
+    fn test_engine_builder_context_outlives_handle() {
+        let context = ContextBuilder::new()
+            .preferred_backends(&[Backend::Null])
+            .build()
+            .unwrap();
+        let engine = EngineBuilder::new()
+            .context(&context)
+            .build_for_tests()
+            .unwrap();
+        drop(context);
+        assert!(engine.channels() > 0);
+    }
+
+    #[cfg(not(feature = "ci-tests"))]
+    #[test]
+    fn test_engine_builder_backends_selects_backend() {
+        let engine = EngineBuilder::new()
+            .backends(&[Backend::Null])
+            .build()
+            .unwrap();
+        let device = engine.device().unwrap();
+        let backend = unsafe { (*(*device.to_raw()).pContext).backend };
+        assert_eq!(backend, sys::ma_backend_ma_backend_null);
+    }
+
+    #[cfg(not(feature = "ci-tests"))]
+    #[test]
+    fn test_engine_builder_backends_leave_builder_reusable() {
+        let mut builder = EngineBuilder::new();
+        builder.backends(&[Backend::Null]).no_auto_start(true);
+        let first = builder.build().unwrap();
+        // The context made for the backends belongs to the engine, not the builder
+        assert!(builder.context.is_none());
+        assert!(builder.inner.pContext.is_null());
+
+        builder.backends(&[Backend::Null]);
+        let second = builder.build().unwrap();
+        for engine in [&first, &second] {
+            let device = engine.device().unwrap();
+            let backend = unsafe { (*(*device.to_raw()).pContext).backend };
+            assert_eq!(backend, sys::ma_backend_ma_backend_null);
+        }
+    }
+
+    #[test]
+    fn test_engine_builder_backends_ignored_without_device() {
+        let mut builder = EngineBuilder::new();
+        builder
+            .no_device(2, SampleRate::Sr48000)
+            .backends(&[Backend::Null]);
+        let engine = builder.build().unwrap();
+        assert!(engine.device().is_none());
+        assert!(builder.context.is_none());
+    }
+
     #[test]
     fn test_engine_builder_many_with_one_resource_manager() {
         let rm = ResourceManagerBuilder::new().build_f32().unwrap();
@@ -589,4 +868,34 @@ mod test {
         let _rm_ref = engine6.resource_manager().unwrap();
         drop(rm); // safe
     }
+
+    #[test]
+    fn test_engine_builder_resource_manager_ref_outlives_owner() {
+        use crate::{
+            audio::sample_rate::SampleRate, engine::resource::RmOps,
+            sound::sound_flags::SoundFlags, test_assets::wav_i16_le,
+        };
+
+        let engine1 = Engine::new_for_tests().unwrap();
+        let rm = engine1.resource_manager().unwrap();
+        let engine2 = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .resource_manager_ref(&rm)
+            .build()
+            .unwrap();
+
+        drop(rm);
+        drop(engine1);
+
+        // engine2 keeps the resource manager alive
+        let rm = engine2.resource_manager().unwrap();
+        let samples: Vec<i16> = (0..200).map(|i| (i * 100) as i16).collect();
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &samples);
+        let _guard = rm.register_encoded("engine_rm_ref.wav", &wav).unwrap();
+        let sound = engine2
+            .new_sound_from_registered("engine_rm_ref.wav", SoundFlags::DECODE)
+            .unwrap();
+        assert!(sound.length_pcm().unwrap() > 0);
+        assert_eq!(rm.memory_stats().resources.len(), 1);
+    }
 }
@@ -0,0 +1,146 @@
+//! Several engines sharing one [`ResourceManager`].
+//!
+//! Each engine drives a single output device, so installations with several outputs (a DJ
+//! cue bus next to the main mix, the zones of a dual-zone system) need one engine per
+//! device. When the engines share their resource manager, a file loaded by all of them is
+//! decoded once and its data is shared, instead of being decoded once per engine.
+//!
+//! [`EngineSet`] builds the engines with a shared resource manager and keeps them together.
+//! To share the resource manager of an existing engine instead, use
+//! [`EngineBuilder::resource_manager_ref`].
+//!
+//! ```no_run
+//! # use maudio::engine::{engine_builder::EngineBuilder, engine_set::EngineSet};
+//! # use maudio::engine::resource::rm_builder::ResourceManagerBuilder;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let rm = ResourceManagerBuilder::new().build_f32()?;
+//! let mut set = EngineSet::new(&rm);
+//! set.add(&mut EngineBuilder::new())?;
+//! set.add(&mut EngineBuilder::new())?;
+//!
+//! // Decoded once, played on both outputs
+//! for engine in set.engines() {
+//!     let mut sound = engine.new_sound_from_file(Path::new("track.mp3"))?;
+//!     sound.play_sound()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    engine::{engine_builder::EngineBuilder, resource::ResourceManager, Engine},
+    MaResult,
+};
+
+/// Engines sharing one resource manager. See the [module docs](self).
+pub struct EngineSet {
+    resource_manager: ResourceManager<f32>,
+    engines: Vec<Engine>,
+}
+
+impl EngineSet {
+    pub fn new(resource_manager: &ResourceManager<f32>) -> Self {
+        Self {
+            resource_manager: resource_manager.clone(),
+            engines: Vec::new(),
+        }
+    }
+
+    /// Builds an engine using the shared resource manager, and adds it to the set.
+    ///
+    /// Any resource manager set on `builder` is replaced.
+    pub fn add(&mut self, builder: &mut EngineBuilder) -> MaResult<&Engine> {
+        let engine = builder.resource_manager(&self.resource_manager).build()?;
+        self.engines.push(engine);
+        Ok(&self.engines[self.engines.len() - 1])
+    }
+
+    /// Removes the engine at `index` from the set.
+    pub fn remove(&mut self, index: usize) -> Option<Engine> {
+        if index < self.engines.len() {
+            Some(self.engines.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Engine> {
+        self.engines.get(index)
+    }
+
+    pub fn engines(&self) -> &[Engine] {
+        &self.engines
+    }
+
+    pub fn len(&self) -> usize {
+        self.engines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.engines.is_empty()
+    }
+
+    pub fn resource_manager(&self) -> &ResourceManager<f32> {
+        &self.resource_manager
+    }
+
+    /// Starts every engine. Stops at the first error.
+    pub fn start_all(&self) -> MaResult<()> {
+        self.engines.iter().try_for_each(|engine| engine.start())
+    }
+
+    /// Stops every engine. Stops at the first error.
+    pub fn stop_all(&self) -> MaResult<()> {
+        self.engines.iter().try_for_each(|engine| engine.stop())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EngineSet;
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{
+            engine_builder::EngineBuilder,
+            resource::{rm_builder::ResourceManagerBuilder, RmOps},
+        },
+        sound::sound_flags::SoundFlags,
+        test_assets::wav_i16_le,
+    };
+
+    #[test]
+    fn test_engine_set_shares_decoded_data() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let mut set = EngineSet::new(&rm);
+        set.add(EngineBuilder::new().no_device(1, SampleRate::Sr48000))
+            .unwrap();
+        set.add(EngineBuilder::new().no_device(2, SampleRate::Sr48000))
+            .unwrap();
+        assert_eq!(set.len(), 2);
+
+        let samples: Vec<i16> = (0..200).map(|i| (i * 100) as i16).collect();
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &samples);
+        let _guard = rm.register_encoded("engine_set.wav", &wav).unwrap();
+        let registered = rm.memory_stats().resources[0].ref_count;
+
+        let sounds: Vec<_> = set
+            .engines()
+            .iter()
+            .map(|engine| {
+                engine
+                    .new_sound_from_registered("engine_set.wav", SoundFlags::DECODE)
+                    .unwrap()
+            })
+            .collect();
+
+        let stats = rm.memory_stats();
+        assert_eq!(stats.resources.len(), 1);
+        // One decoded copy, referenced by both sounds
+        assert_eq!(stats.resources[0].ref_count, registered + 2);
+        drop(sounds);
+
+        assert!(set.remove(1).is_some());
+        assert!(set.remove(1).is_none());
+        assert_eq!(set.len(), 1);
+    }
+}
@@ -0,0 +1,156 @@
+//! Engine statistics for profiling and debug overlays.
+//!
+//! Statistics are enabled with
+//! [`EngineBuilder::with_stats`](crate::engine::engine_builder::EngineBuilder::with_stats).
+//! The counters are updated with atomics from the audio path, and
+//! [`Engine::stats`](crate::engine::Engine::stats) takes a snapshot from any thread.
+//!
+//! ```no_run
+//! # use maudio::engine::engine_builder::EngineBuilder;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = EngineBuilder::new().with_stats().build()?;
+//!
+//! loop {
+//!     if let Some(stats) = engine.stats() {
+//!         println!(
+//!             "{} frames, {} underruns, {} sounds, max callback {:?}",
+//!             stats.frames_rendered,
+//!             stats.underruns,
+//!             stats.active_sounds,
+//!             stats.callback_duration.max,
+//!         );
+//!     }
+//!     std::thread::sleep(std::time::Duration::from_secs(1));
+//! }
+//! # }
+//! ```
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Upper bounds (exclusive) of the callback duration buckets, in microseconds.
+///
+/// The last bucket counts every callback that took at least the last bound.
+pub const CALLBACK_DURATION_BOUNDS_US: [u64; 7] = [100, 250, 500, 1_000, 2_000, 5_000, 10_000];
+
+const BUCKETS: usize = CALLBACK_DURATION_BOUNDS_US.len() + 1;
+
+/// Histogram of the time spent rendering each block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DurationHistogram {
+    /// Number of callbacks in each bucket, see [`CALLBACK_DURATION_BOUNDS_US`].
+    pub counts: [u64; BUCKETS],
+    /// Longest callback seen.
+    pub max: Duration,
+}
+
+impl DurationHistogram {
+    /// Total number of callbacks measured.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Snapshot of the engine statistics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EngineStats {
+    /// Frames rendered by the engine since it was created.
+    ///
+    /// Nothing is rendered while nothing is attached to the endpoint.
+    pub frames_rendered: u64,
+    /// Time spent rendering each block.
+    ///
+    /// Measured around the device callback when the engine owns its device, and around
+    /// each read for an engine without a device. Not measured when the engine is driven
+    /// by a user supplied device.
+    pub callback_duration: DurationHistogram,
+    /// Device callbacks that took longer than the audio they rendered.
+    ///
+    /// Each of these is likely heard as a glitch.
+    pub underruns: u64,
+    /// Number of sounds created from the engine that are still alive.
+    pub active_sounds: u32,
+    /// Jobs waiting in the resource manager job queue.
+    ///
+    /// `None` if the engine has no resource manager.
+    pub rm_jobs_pending: Option<u32>,
+}
+
+/// Counters written from the audio path.
+#[derive(Default)]
+pub(crate) struct StatsCounters {
+    frames_rendered: AtomicU64,
+    durations: [AtomicU64; BUCKETS],
+    max_duration_ns: AtomicU64,
+    underruns: AtomicU64,
+}
+
+impl StatsCounters {
+    pub(crate) fn add_frames(&self, frames: u64) {
+        self.frames_rendered.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// Records a block of `frames` frames that took `elapsed` to render.
+    ///
+    /// `sample_rate` is only known for device callbacks, and enables underrun detection.
+    pub(crate) fn record_block(&self, elapsed: Duration, frames: u32, sample_rate: Option<u32>) {
+        let micros = elapsed.as_micros() as u64;
+        let bucket = CALLBACK_DURATION_BOUNDS_US
+            .iter()
+            .position(|bound| micros < *bound)
+            .unwrap_or(BUCKETS - 1);
+        self.durations[bucket].fetch_add(1, Ordering::Relaxed);
+        self.max_duration_ns
+            .fetch_max(elapsed.as_nanos() as u64, Ordering::Relaxed);
+
+        if let Some(sample_rate) = sample_rate.filter(|sr| *sr > 0) {
+            let budget = Duration::from_secs_f64(frames as f64 / sample_rate as f64);
+            if elapsed > budget {
+                self.underruns.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub(crate) fn snapshot(&self, active_sounds: u32, rm_jobs_pending: Option<u32>) -> EngineStats {
+        let mut counts = [0; BUCKETS];
+        for (count, bucket) in counts.iter_mut().zip(&self.durations) {
+            *count = bucket.load(Ordering::Relaxed);
+        }
+        EngineStats {
+            frames_rendered: self.frames_rendered.load(Ordering::Relaxed),
+            callback_duration: DurationHistogram {
+                counts,
+                max: Duration::from_nanos(self.max_duration_ns.load(Ordering::Relaxed)),
+            },
+            underruns: self.underruns.load(Ordering::Relaxed),
+            active_sounds,
+            rm_jobs_pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stats_counters_buckets_and_underruns() {
+        let counters = StatsCounters::default();
+        counters.add_frames(480);
+        counters.record_block(Duration::from_micros(50), 480, Some(48_000));
+        counters.record_block(Duration::from_micros(700), 480, None);
+        // 480 frames at 48 kHz is 10 ms of audio
+        counters.record_block(Duration::from_millis(12), 480, Some(48_000));
+
+        let stats = counters.snapshot(3, None);
+        assert_eq!(stats.frames_rendered, 480);
+        assert_eq!(stats.callback_duration.counts[0], 1);
+        assert_eq!(stats.callback_duration.counts[3], 1);
+        assert_eq!(stats.callback_duration.counts[BUCKETS - 1], 1);
+        assert_eq!(stats.callback_duration.total(), 3);
+        assert_eq!(stats.callback_duration.max, Duration::from_millis(12));
+        assert_eq!(stats.underruns, 1);
+        assert_eq!(stats.active_sounds, 3);
+    }
+}
@@ -0,0 +1,149 @@
+//! Engine listeners.
+//!
+//! A listener is the point in 3D space that spatialized sounds are heard from, usually the
+//! player or camera. An engine has at least one listener. The number of listeners is set with
+//! [`EngineBuilder::listener_count`](crate::engine::engine_builder::EngineBuilder::listener_count).
+//!
+//! ```no_run
+//! # use maudio::engine::Engine;
+//! # use maudio::audio::math::vec3::Vec3;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let listener = engine.listener(0)?;
+//! listener.set_position(Vec3 { x: 0.0, y: 1.8, z: 0.0 });
+//! listener.set_direction(Vec3 { x: 0.0, y: 0.0, z: -1.0 });
+//! # Ok(())
+//! # }
+//! ```
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{math::vec3::Vec3, spatial::cone::Cone},
+    engine::{engine_ffi, Engine},
+    MaResult, MaudioError,
+};
+
+/// A handle to one of the engine's listeners.
+///
+/// Obtained from [`Engine::listener`], which checks the index once. The handle borrows the
+/// engine, so it can not outlive it.
+#[derive(Clone, Copy)]
+pub struct Listener<'a> {
+    engine: &'a Engine,
+    index: u32,
+}
+
+impl<'a> Listener<'a> {
+    pub(crate) fn new(engine: &'a Engine, index: u32) -> MaResult<Self> {
+        if index >= engine.listener_count() {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        Ok(Self::from_index(engine, index))
+    }
+
+    // `index` must already be known to be in range
+    pub(crate) fn from_index(engine: &'a Engine, index: u32) -> Self {
+        Self { engine, index }
+    }
+
+    /// Returns the index of this listener in the engine.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    // Thread-safe
+    /// Sets the position of the listener.
+    pub fn set_position(&self, position: Vec3) {
+        engine_ffi::ma_engine_listener_set_position(self.engine, self.index, position);
+    }
+
+    /// Returns the position of the listener.
+    pub fn position(&self) -> Vec3 {
+        engine_ffi::ma_engine_listener_get_position(self.engine, self.index)
+    }
+
+    // Thread-safe
+    /// Sets the facing direction of the listener.
+    pub fn set_direction(&self, direction: Vec3) {
+        engine_ffi::ma_engine_listener_set_direction(self.engine, self.index, direction);
+    }
+
+    /// Returns the facing direction of the listener.
+    pub fn direction(&self) -> Vec3 {
+        engine_ffi::ma_engine_listener_get_direction(self.engine, self.index)
+    }
+
+    // Thread-safe
+    /// Sets the velocity of the listener. Used for doppler.
+    pub fn set_velocity(&self, velocity: Vec3) {
+        engine_ffi::ma_engine_listener_set_velocity(self.engine, self.index, velocity);
+    }
+
+    /// Returns the velocity of the listener.
+    pub fn velocity(&self) -> Vec3 {
+        engine_ffi::ma_engine_listener_get_velocity(self.engine, self.index)
+    }
+
+    // Thread-safe
+    /// Sets the directional cone of the listener.
+    pub fn set_cone(&self, cone: Cone) {
+        engine_ffi::ma_engine_listener_set_cone(self.engine, self.index, cone);
+    }
+
+    /// Returns the directional cone of the listener.
+    pub fn cone(&self) -> Cone {
+        engine_ffi::ma_engine_listener_get_cone(self.engine, self.index)
+    }
+
+    // Thread-safe
+    /// Sets the world-up vector of the listener.
+    pub fn set_world_up(&self, up_direction: Vec3) {
+        engine_ffi::ma_engine_listener_set_world_up(self.engine, self.index, up_direction);
+    }
+
+    /// Returns the world-up vector of the listener.
+    pub fn world_up(&self) -> Vec3 {
+        engine_ffi::ma_engine_listener_get_world_up(self.engine, self.index)
+    }
+
+    // Thread-safe
+    /// Enables or disables the listener.
+    ///
+    /// Disabled listeners are skipped when finding the closest listener to a sound.
+    pub fn set_enabled(&self, enabled: bool) {
+        engine_ffi::ma_engine_listener_set_enabled(self.engine, self.index, enabled);
+    }
+
+    /// Returns `true` if the listener is enabled.
+    pub fn is_enabled(&self) -> bool {
+        engine_ffi::ma_engine_listener_is_enabled(self.engine, self.index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::{engine_builder::EngineBuilder, Engine};
+
+    #[test]
+    fn test_listener_index_is_validated() {
+        let engine = Engine::new_for_tests().unwrap();
+        let count = engine.listener_count();
+
+        assert_eq!(engine.listener(0).unwrap().index(), 0);
+        assert!(engine.listener(count).is_err());
+        assert!(engine.listener(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_listener_multiple_listeners_are_independent() {
+        let engine = EngineBuilder::new().listener_count(2).build().unwrap();
+        assert_eq!(engine.listeners().count(), 2);
+
+        let first = engine.listener(0).unwrap();
+        let second = engine.listener(1).unwrap();
+        second.set_enabled(false);
+
+        assert!(first.is_enabled());
+        assert!(!second.is_enabled());
+    }
+}
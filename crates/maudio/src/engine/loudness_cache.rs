@@ -0,0 +1,66 @@
+//! Loudness of the files played with [`SoundBuilder::normalize_loudness`], measured once per
+//! file.
+//!
+//! [`SoundBuilder::normalize_loudness`]: crate::sound::sound_builder::SoundBuilder::normalize_loudness
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    audio::dsp::loudness::{Loudness, LoudnessMeter},
+    data_source::sources::decoder::{Decoder, DecoderBuilder},
+    MaResult,
+};
+
+// Headroom kept below 0 dBTP when a quiet file is turned up
+const PEAK_CEILING_DBTP: f64 = -1.0;
+
+#[derive(Default)]
+pub(crate) struct LoudnessCache {
+    measured: Mutex<HashMap<PathBuf, Loudness>>,
+}
+
+impl LoudnessCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, Loudness>> {
+        self.measured.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn get(&self, path: &Path) -> Option<Loudness> {
+        self.lock().get(path).copied()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Returns the gain in dB bringing the file at `path` to `target_lufs`, measuring the
+    /// file the first time.
+    ///
+    /// The gain is lowered if needed to keep the true peak under -1 dBTP. Silent files get
+    /// no gain.
+    pub(crate) fn gain_db(&self, path: &Path, target_lufs: f32) -> MaResult<f32> {
+        let loudness = match self.get(path) {
+            Some(loudness) => loudness,
+            None => {
+                // Measured without holding the lock, two threads may measure the same file
+                let loudness = measure_file(path)?;
+                self.lock().insert(path.to_path_buf(), loudness);
+                loudness
+            }
+        };
+        if !loudness.integrated.is_finite() {
+            return Ok(0.0);
+        }
+        let gain = target_lufs as f64 - loudness.integrated;
+        let headroom = PEAK_CEILING_DBTP - loudness.true_peak;
+        Ok(gain.min(headroom.max(0.0)) as f32)
+    }
+}
+
+fn measure_file(path: &Path) -> MaResult<Loudness> {
+    let info = Decoder::probe(path)?;
+    let mut decoder = DecoderBuilder::new_f32(info.channels, info.sample_rate).from_file(path)?;
+    LoudnessMeter::measure_decoder(&mut decoder)
+}
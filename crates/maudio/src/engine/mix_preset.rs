@@ -0,0 +1,590 @@
+//! Mixer configurations that can be saved and rebuilt.
+//!
+//! A [`MixPreset`] describes a tree of sound groups, with their volume, pan and pitch,
+//! and a chain of filters on the output of each group. [`Mix::new`] builds it on an engine,
+//! and [`Mix::snapshot`] reads the current values back into a preset.
+//!
+//! With the `serde` feature, presets can be serialized. This allows user editable mix presets,
+//! or switching between two mixes with [`Mix::apply`] for A/B testing.
+//!
+//! ```no_run
+//! # use maudio::engine::Engine;
+//! # use maudio::engine::mix_preset::{FilterPreset, GroupPreset, Mix, MixPreset};
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let preset = MixPreset {
+//!     groups: vec![
+//!         GroupPreset::new("master"),
+//!         GroupPreset {
+//!             parent: Some("master".to_string()),
+//!             volume: 0.8,
+//!             filters: vec![FilterPreset::LowPass { cutoff: 8000.0, order: 2 }],
+//!             ..GroupPreset::new("music")
+//!         },
+//!     ],
+//! };
+//! let mut mix = Mix::new(&engine, &preset)?;
+//!
+//! let music = mix.group("music").unwrap();
+//! let mut sound = engine.sound_config().sound_group(music).build()?;
+//! # let _ = sound.play_sound();
+//!
+//! mix.group_mut("music").unwrap().set_volume(0.5);
+//! let saved = mix.snapshot();
+//! # let _ = saved;
+//! # Ok(())
+//! # }
+//! ```
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::sample_rate::SampleRate,
+    engine::{
+        mix_snapshot::MixSnapshot,
+        node_graph::nodes::{
+            filters::{
+                hishelf::{HiShelfNode, HiShelfNodeBuilder},
+                hpf::{HpfNode, HpfNodeBuilder},
+                loshelf::{LoShelfNode, LoShelfNodeBuilder},
+                lpf::{LpfNode, LpfNodeBuilder},
+                notch::{NotchNode, NotchNodeBuilder},
+                peak::{PeakNode, PeakNodeBuilder},
+            },
+            NodeOps, NodeRef,
+        },
+        Engine,
+    },
+    sound::sound_group::{SoundGroup, SoundGroupBuilder},
+    ErrorKinds, MaResult, MaudioError,
+};
+
+/// A tree of sound groups and their filters.
+///
+/// Groups are created in order. A group's parent must appear before it.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MixPreset {
+    pub groups: Vec<GroupPreset>,
+}
+
+/// A sound group in a [`MixPreset`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupPreset {
+    pub name: String,
+    /// Group the output is sent to. `None` sends it to the engine endpoint.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parent: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default = "default_one"))]
+    pub volume: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pan: f32,
+    #[cfg_attr(feature = "serde", serde(default = "default_one"))]
+    pub pitch: f32,
+    /// Filters applied to the output of the group, in order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filters: Vec<FilterPreset>,
+}
+
+#[cfg(feature = "serde")]
+fn default_one() -> f32 {
+    1.0
+}
+
+impl GroupPreset {
+    /// A group sent to the engine endpoint, with no filters and default values.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            parent: None,
+            volume: 1.0,
+            pan: 0.0,
+            pitch: 1.0,
+            filters: Vec::new(),
+        }
+    }
+}
+
+/// A filter node and its parameters. Frequencies are in Hz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterPreset {
+    LowPass {
+        cutoff: f64,
+        order: u32,
+    },
+    HighPass {
+        cutoff: f64,
+        order: u32,
+    },
+    Peak {
+        gain_db: f64,
+        q: f64,
+        frequency: f64,
+    },
+    LowShelf {
+        gain_db: f64,
+        slope: f64,
+        frequency: f64,
+    },
+    HighShelf {
+        gain_db: f64,
+        slope: f64,
+        frequency: f64,
+    },
+    Notch {
+        q: f64,
+        frequency: f64,
+    },
+}
+
+impl FilterPreset {
+    // Parameters that can not change without rebuilding the node
+    fn same_node(&self, other: &FilterPreset) -> bool {
+        match (self, other) {
+            (Self::LowPass { order: a, .. }, Self::LowPass { order: b, .. })
+            | (Self::HighPass { order: a, .. }, Self::HighPass { order: b, .. }) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// A [`MixPreset`] built on an engine.
+///
+/// Owns the sound groups and filter nodes. Dropping the mix removes them from the graph.
+pub struct Mix {
+    groups: Vec<MixGroup>,
+    sample_rate: SampleRate,
+}
+
+struct MixGroup {
+    name: String,
+    parent: Option<String>,
+    filters: Vec<(FilterPreset, FilterNode)>,
+    // Declared last, so the group is dropped after the filters attached to it
+    group: SoundGroup,
+}
+
+enum FilterNode {
+    LowPass(LpfNode),
+    HighPass(HpfNode),
+    Peak(PeakNode),
+    LowShelf(LoShelfNode),
+    HighShelf(HiShelfNode),
+    Notch(NotchNode),
+}
+
+impl Mix {
+    /// Builds every group and filter of `preset`.
+    ///
+    /// Returns an error if two groups have the same name, a parent is not defined
+    /// before its children, or a filter has invalid parameters.
+    pub fn new(engine: &Engine, preset: &MixPreset) -> MaResult<Self> {
+        let sample_rate = engine.sample_rate()?;
+        let channels = engine.channels();
+        let mut groups: Vec<MixGroup> = Vec::with_capacity(preset.groups.len());
+
+        for def in &preset.groups {
+            if groups.iter().any(|g| g.name == def.name) {
+                return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                    "duplicate group name in mix preset",
+                )));
+            }
+
+            let mut group = SoundGroupBuilder::new(engine)
+                .no_default_attachment()
+                .build()?;
+            group.set_volume(def.volume);
+            group.set_pan(def.pan);
+            group.set_pitch(def.pitch);
+
+            let filters = def
+                .filters
+                .iter()
+                .map(|f| Ok((*f, FilterNode::new(engine, channels, sample_rate, f)?)))
+                .collect::<MaResult<Vec<_>>>()?;
+
+            // group -> filters... -> parent or endpoint
+            let mut prev = group.as_node();
+            for (_, filter) in &filters {
+                let mut node = filter.as_node();
+                prev.attach_output(0, &mut node, 0)?;
+                prev = node;
+            }
+            match &def.parent {
+                Some(parent) => {
+                    let parent = groups.iter().find(|g| &g.name == parent).ok_or(
+                        MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                            "parent group must be defined before its children",
+                        )),
+                    )?;
+                    prev.attach_output(0, &mut parent.group.as_node(), 0)?;
+                }
+                None => prev.attach_output(0, &mut engine.endpoint(), 0)?,
+            }
+
+            groups.push(MixGroup {
+                name: def.name.clone(),
+                parent: def.parent.clone(),
+                filters,
+                group,
+            });
+        }
+
+        Ok(Self {
+            groups,
+            sample_rate,
+        })
+    }
+
+    pub fn group(&self, name: &str) -> Option<&SoundGroup> {
+        self.find(name).map(|g| &g.group)
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut SoundGroup> {
+        self.groups
+            .iter_mut()
+            .find(|g| g.name == name)
+            .map(|g| &mut g.group)
+    }
+
+    /// Returns the filters of a group, in order.
+    pub fn filters(&self, group: &str) -> Option<Vec<FilterPreset>> {
+        self.find(group)
+            .map(|g| g.filters.iter().map(|(p, _)| *p).collect())
+    }
+
+    /// Changes the parameters of a filter.
+    ///
+    /// The filter kind (and order, for low and high pass filters) can not change.
+    pub fn set_filter(&mut self, group: &str, index: usize, filter: FilterPreset) -> MaResult<()> {
+        let sample_rate = self.sample_rate;
+        let (current, node) = self
+            .groups
+            .iter_mut()
+            .find(|g| g.name == group)
+            .and_then(|g| g.filters.get_mut(index))
+            .ok_or(MaudioError::from_ma_result(
+                sys::ma_result_MA_DOES_NOT_EXIST,
+            ))?;
+        if !current.same_node(&filter) {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "filter kind or order differs",
+            )));
+        }
+        node.reinit(sample_rate, &filter)?;
+        *current = filter;
+        Ok(())
+    }
+
+    /// Reads the current state of the mix.
+    ///
+    /// Volume, pan and pitch are read from the groups, so changes made through
+    /// [`Mix::group_mut`] are included.
+    pub fn snapshot(&self) -> MixPreset {
+        MixPreset {
+            groups: self
+                .groups
+                .iter()
+                .map(|g| GroupPreset {
+                    name: g.name.clone(),
+                    parent: g.parent.clone(),
+                    volume: g.group.volume(),
+                    pan: g.group.pan(),
+                    pitch: g.group.pitch(),
+                    filters: g.filters.iter().map(|(p, _)| *p).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Applies the values of a preset with the same structure, without rebuilding the mix.
+    ///
+    /// The structure is the group names, parents, and filter kinds. If it differs,
+    /// an error is returned and nothing is changed. Use [`Mix::new`] to build a different mix.
+    pub fn apply(&mut self, preset: &MixPreset) -> MaResult<()> {
+        let same_structure = self.groups.len() == preset.groups.len()
+            && self.groups.iter().zip(&preset.groups).all(|(g, p)| {
+                g.name == p.name
+                    && g.parent == p.parent
+                    && g.filters.len() == p.filters.len()
+                    && g.filters
+                        .iter()
+                        .zip(&p.filters)
+                        .all(|((current, _), new)| current.same_node(new))
+            });
+        if !same_structure {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "mix preset structure differs",
+            )));
+        }
+
+        for (g, p) in self.groups.iter_mut().zip(&preset.groups) {
+            g.group.set_volume(p.volume);
+            g.group.set_pan(p.pan);
+            g.group.set_pitch(p.pitch);
+            for ((current, node), new) in g.filters.iter_mut().zip(&p.filters) {
+                node.reinit(self.sample_rate, new)?;
+                *current = *new;
+            }
+        }
+        Ok(())
+    }
+
+    /// Captures the current volume of every group, see [`MixSnapshot`].
+    pub fn capture_snapshot(&self) -> MixSnapshot {
+        let groups = self.groups.iter().map(|g| &g.group).collect::<Vec<_>>();
+        MixSnapshot::capture(&groups)
+    }
+
+    fn find(&self, name: &str) -> Option<&MixGroup> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+}
+
+impl FilterNode {
+    fn new(
+        engine: &Engine,
+        channels: u32,
+        sample_rate: SampleRate,
+        preset: &FilterPreset,
+    ) -> MaResult<Self> {
+        let graph = engine.as_node_graph();
+        let node = match *preset {
+            FilterPreset::LowPass { cutoff, order } => FilterNode::LowPass(
+                LpfNodeBuilder::new(&graph, channels, sample_rate, cutoff, order).build()?,
+            ),
+            FilterPreset::HighPass { cutoff, order } => FilterNode::HighPass(
+                HpfNodeBuilder::new(&graph, channels, sample_rate, cutoff, order).build()?,
+            ),
+            FilterPreset::Peak {
+                gain_db,
+                q,
+                frequency,
+            } => FilterNode::Peak(
+                PeakNodeBuilder::new(&graph, channels, sample_rate, gain_db, q, frequency)
+                    .build()?,
+            ),
+            FilterPreset::LowShelf {
+                gain_db,
+                slope,
+                frequency,
+            } => FilterNode::LowShelf(
+                LoShelfNodeBuilder::new(&graph, channels, sample_rate, gain_db, slope, frequency)
+                    .build()?,
+            ),
+            FilterPreset::HighShelf {
+                gain_db,
+                slope,
+                frequency,
+            } => FilterNode::HighShelf(
+                HiShelfNodeBuilder::new(&graph, channels, sample_rate, gain_db, slope, frequency)
+                    .build()?,
+            ),
+            FilterPreset::Notch { q, frequency } => FilterNode::Notch(
+                NotchNodeBuilder::new(&graph, channels, sample_rate, q, frequency).build()?,
+            ),
+        };
+        Ok(node)
+    }
+
+    fn as_node(&self) -> NodeRef<'_> {
+        match self {
+            FilterNode::LowPass(n) => n.as_node(),
+            FilterNode::HighPass(n) => n.as_node(),
+            FilterNode::Peak(n) => n.as_node(),
+            FilterNode::LowShelf(n) => n.as_node(),
+            FilterNode::HighShelf(n) => n.as_node(),
+            FilterNode::Notch(n) => n.as_node(),
+        }
+    }
+
+    // The caller checks that `preset` matches the node
+    fn reinit(&mut self, sample_rate: SampleRate, preset: &FilterPreset) -> MaResult<()> {
+        match (self, *preset) {
+            (FilterNode::LowPass(n), FilterPreset::LowPass { cutoff, .. }) => {
+                n.reinit(sample_rate, cutoff)
+            }
+            (FilterNode::HighPass(n), FilterPreset::HighPass { cutoff, .. }) => {
+                n.reinit(sample_rate, cutoff)
+            }
+            (
+                FilterNode::Peak(n),
+                FilterPreset::Peak {
+                    gain_db,
+                    q,
+                    frequency,
+                },
+            ) => n.reinit(gain_db, q, frequency),
+            (
+                FilterNode::LowShelf(n),
+                FilterPreset::LowShelf {
+                    gain_db,
+                    slope,
+                    frequency,
+                },
+            ) => n.reinit(sample_rate, gain_db, slope, frequency),
+            (
+                FilterNode::HighShelf(n),
+                FilterPreset::HighShelf {
+                    gain_db,
+                    slope,
+                    frequency,
+                },
+            ) => n.reinit(sample_rate, gain_db, slope, frequency),
+            (FilterNode::Notch(n), FilterPreset::Notch { q, frequency }) => n.reinit(q, frequency),
+            _ => Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::{
+        mix_preset::{FilterPreset, GroupPreset, Mix, MixPreset},
+        Engine,
+    };
+
+    fn preset() -> MixPreset {
+        MixPreset {
+            groups: vec![
+                GroupPreset {
+                    volume: 0.9,
+                    ..GroupPreset::new("master")
+                },
+                GroupPreset {
+                    parent: Some("master".to_string()),
+                    volume: 0.5,
+                    pan: -0.25,
+                    filters: vec![
+                        FilterPreset::LowPass {
+                            cutoff: 4000.0,
+                            order: 2,
+                        },
+                        FilterPreset::Peak {
+                            gain_db: 3.0,
+                            q: 0.7,
+                            frequency: 1000.0,
+                        },
+                    ],
+                    ..GroupPreset::new("music")
+                },
+                GroupPreset {
+                    parent: Some("master".to_string()),
+                    filters: vec![FilterPreset::HighPass {
+                        cutoff: 200.0,
+                        order: 2,
+                    }],
+                    ..GroupPreset::new("sfx")
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_mix_preset_build_and_snapshot() {
+        let engine = Engine::new_for_tests().unwrap();
+        let preset = preset();
+        let mut mix = Mix::new(&engine, &preset).unwrap();
+        assert_eq!(mix.snapshot(), preset);
+
+        mix.group_mut("sfx").unwrap().set_volume(0.25);
+        let snapshot = mix.snapshot();
+        assert_eq!(snapshot.groups[2].volume, 0.25);
+        let volumes = mix.capture_snapshot();
+        assert_eq!(volumes.len(), 3);
+        assert_eq!(volumes.volume(mix.group("sfx").unwrap()), Some(0.25));
+
+        // Rebuild on another engine
+        let other = Engine::new_for_tests().unwrap();
+        let rebuilt = Mix::new(&other, &snapshot).unwrap();
+        assert_eq!(rebuilt.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_mix_preset_apply_and_set_filter() {
+        let engine = Engine::new_for_tests().unwrap();
+        let mut mix = Mix::new(&engine, &preset()).unwrap();
+
+        let mut b = preset();
+        b.groups[1].volume = 0.1;
+        b.groups[1].filters[0] = FilterPreset::LowPass {
+            cutoff: 1000.0,
+            order: 2,
+        };
+        mix.apply(&b).unwrap();
+        assert_eq!(mix.snapshot(), b);
+
+        mix.set_filter(
+            "sfx",
+            0,
+            FilterPreset::HighPass {
+                cutoff: 400.0,
+                order: 2,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            mix.filters("sfx").unwrap(),
+            vec![FilterPreset::HighPass {
+                cutoff: 400.0,
+                order: 2
+            }]
+        );
+
+        // Changing the structure is not allowed
+        assert!(mix
+            .set_filter(
+                "sfx",
+                0,
+                FilterPreset::Notch {
+                    q: 1.0,
+                    frequency: 50.0
+                }
+            )
+            .is_err());
+        let mut c = preset();
+        c.groups.pop();
+        assert!(mix.apply(&c).is_err());
+
+        // Failed changes leave the mix as it was
+        b.groups[2].filters[0] = FilterPreset::HighPass {
+            cutoff: 400.0,
+            order: 2,
+        };
+        assert_eq!(mix.snapshot(), b);
+    }
+
+    #[test]
+    fn test_mix_preset_invalid() {
+        let engine = Engine::new_for_tests().unwrap();
+
+        let mut orphan = preset();
+        orphan.groups.swap(0, 1);
+        assert!(Mix::new(&engine, &orphan).is_err());
+
+        let mut duplicate = preset();
+        duplicate.groups[2].name = "music".to_string();
+        assert!(Mix::new(&engine, &duplicate).is_err());
+
+        let mut bad_filter = preset();
+        bad_filter.groups[2].filters[0] = FilterPreset::HighPass {
+            cutoff: -1.0,
+            order: 2,
+        };
+        assert!(Mix::new(&engine, &bad_filter).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mix_preset_serde_roundtrip() {
+        let preset = preset();
+        let json = serde_json::to_string(&preset).unwrap();
+        let back: MixPreset = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, preset);
+
+        let minimal: MixPreset =
+            serde_json::from_str(r#"{ "groups": [{ "name": "master" }] }"#).unwrap();
+        assert_eq!(minimal.groups[0], GroupPreset::new("master"));
+    }
+}
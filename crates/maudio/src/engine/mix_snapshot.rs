@@ -0,0 +1,410 @@
+//! Mixer snapshots and timed transitions between them.
+//!
+//! A [`MixSnapshot`] holds a target volume for a set of sound groups. Games usually define
+//! one per state, such as exploring, combat or the pause menu, and register it on the engine
+//! by name with [`Engine::define_snapshot`]. [`Engine::transition_to`] then moves each group
+//! of the snapshot from its current volume to the snapshot's volume over a given duration.
+//!
+//! The volumes are interpolated on the audio thread, by a silent node that the engine
+//! attaches to its endpoint the first time a transition starts. A transition advances as the
+//! engine processes audio, and starting a new one takes over from the current volumes, so
+//! switching states halfway through a transition does not jump.
+//!
+//! Groups that are not in the snapshot are left alone, and groups that were dropped are
+//! skipped. Calling [`SoundGroup::set_volume`] on a group that is part of a running
+//! transition is overwritten on the next processing callback.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use maudio::engine::{Engine, mix_snapshot::MixSnapshot};
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let music = engine.new_sound_group()?;
+//! let sfx = engine.new_sound_group()?;
+//!
+//! engine.define_snapshot("explore", MixSnapshot::capture(&[&music, &sfx]));
+//! let mut combat = MixSnapshot::new();
+//! combat.set_volume(&music, 0.3).set_volume(&sfx, 1.0);
+//! engine.define_snapshot("combat", combat);
+//!
+//! // When a fight starts
+//! engine.transition_to_named("combat", Duration::from_millis(500))?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Source, SourceCallback},
+            nodes::{Node, NodeOps},
+        },
+        Engine,
+    },
+    sound::sound_group::SoundGroup,
+    util::rt_check,
+    Binding, MaResult,
+};
+
+static NEXT_GROUP_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Returns a new identifier for a sound group, never reused by another group.
+pub(crate) fn next_group_id() -> u64 {
+    NEXT_GROUP_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Target volumes for a set of sound groups. See the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MixSnapshot {
+    // Group id and linear volume
+    volumes: Vec<(u64, f32)>,
+}
+
+impl MixSnapshot {
+    /// Creates an empty snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a snapshot with the current volume of each group.
+    pub fn capture(groups: &[&SoundGroup]) -> Self {
+        let mut snapshot = Self::new();
+        for group in groups {
+            snapshot.set_volume(group, group.volume());
+        }
+        snapshot
+    }
+
+    /// Sets the linear volume of `group` in the snapshot, replacing any previous value.
+    pub fn set_volume(&mut self, group: &SoundGroup, volume: f32) -> &mut Self {
+        let id = group.id();
+        match self.volumes.iter_mut().find(|(g, _)| *g == id) {
+            Some(entry) => entry.1 = volume,
+            None => self.volumes.push((id, volume)),
+        }
+        self
+    }
+
+    /// Returns the volume of `group` in the snapshot, if it is part of it.
+    pub fn volume(&self, group: &SoundGroup) -> Option<f32> {
+        let id = group.id();
+        self.volumes.iter().find(|(g, _)| *g == id).map(|(_, v)| *v)
+    }
+
+    /// Removes `group` from the snapshot and returns its volume.
+    pub fn remove(&mut self, group: &SoundGroup) -> Option<f32> {
+        let id = group.id();
+        let index = self.volumes.iter().position(|(g, _)| *g == id)?;
+        Some(self.volumes.remove(index).1)
+    }
+
+    /// Number of groups in the snapshot.
+    pub fn len(&self) -> usize {
+        self.volumes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.volumes.is_empty()
+    }
+}
+
+// A `ma_sound_group` owned by a `SoundGroup`, which removes it before freeing it.
+#[derive(Clone, Copy)]
+struct GroupPtr(*mut sys::ma_sound_group);
+
+unsafe impl Send for GroupPtr {}
+
+struct Ramp {
+    group: u64,
+    ptr: GroupPtr,
+    from: f32,
+    to: f32,
+}
+
+struct Transition {
+    ramps: Vec<Ramp>,
+    elapsed: u64,
+    length: u64,
+}
+
+#[derive(Default)]
+struct MixerState {
+    // The live groups of the engine, by id
+    groups: HashMap<u64, GroupPtr>,
+    transition: Option<Transition>,
+}
+
+// State shared between the control thread and the audio thread.
+// The audio thread only takes the lock while a transition runs, and never waits for it.
+#[derive(Default)]
+struct MixerShared {
+    state: Mutex<MixerState>,
+    active: AtomicBool,
+}
+
+impl MixerShared {
+    fn lock(&self) -> MutexGuard<'_, MixerState> {
+        rt_check::check("engine mixer snapshots");
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn advance(&self, frames: u64) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+        // Retried on the next callback if the control thread holds the lock
+        let Ok(mut state) = self.state.try_lock() else {
+            return;
+        };
+        let Some(transition) = state.transition.as_mut() else {
+            return;
+        };
+        transition.elapsed = (transition.elapsed + frames).min(transition.length);
+        let t = transition.elapsed as f32 / transition.length as f32;
+        for ramp in &transition.ramps {
+            let volume = ramp.from + (ramp.to - ramp.from) * t;
+            // SAFETY: groups remove themselves from the transition, under the lock, before
+            // they are freed
+            unsafe { sys::ma_sound_group_set_volume(ramp.ptr.0, volume) };
+        }
+        if transition.elapsed == transition.length {
+            state.transition = None;
+            self.active.store(false, Ordering::Release);
+        }
+    }
+}
+
+// Silent node attached to the endpoint, so it runs once per processing callback.
+struct TransitionDriver {
+    shared: Arc<MixerShared>,
+    channels: usize,
+}
+
+impl SourceCallback for TransitionDriver {
+    fn on_audio(&mut self, output: &mut [f32]) -> MaResult<u32> {
+        output.fill(0.0);
+        let frames = output.len() / self.channels;
+        self.shared.advance(frames as u64);
+        Ok(frames as u32)
+    }
+}
+
+/// The snapshots and the current transition of an engine.
+#[derive(Default)]
+pub(crate) struct MixSnapshots {
+    named: Mutex<HashMap<String, MixSnapshot>>,
+    shared: Arc<MixerShared>,
+    driver: Mutex<Option<Node<Source<TransitionDriver>>>>,
+}
+
+impl MixSnapshots {
+    fn named(&self) -> MutexGuard<'_, HashMap<String, MixSnapshot>> {
+        self.named.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn register(&self, group: &SoundGroup) {
+        self.shared
+            .lock()
+            .groups
+            .insert(group.id(), GroupPtr(group.to_raw()));
+    }
+
+    /// Removes a group that is about to be freed, including from the current transition.
+    pub(crate) fn unregister(&self, group: &SoundGroup) {
+        let id = group.id();
+        let mut state = self.shared.lock();
+        state.groups.remove(&id);
+        if let Some(transition) = state.transition.as_mut() {
+            transition.ramps.retain(|r| r.group != id);
+        }
+    }
+
+    pub(crate) fn define(&self, name: &str, snapshot: MixSnapshot) {
+        self.named().insert(name.to_string(), snapshot);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<MixSnapshot> {
+        self.named().get(name).cloned()
+    }
+
+    pub(crate) fn remove(&self, name: &str) -> Option<MixSnapshot> {
+        self.named().remove(name)
+    }
+
+    pub(crate) fn is_transitioning(&self) -> bool {
+        self.shared.active.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn transition_to(
+        &self,
+        engine: &Engine,
+        snapshot: &MixSnapshot,
+        duration: Duration,
+    ) -> MaResult<()> {
+        let sample_rate = u32::from(engine.sample_rate()?) as f64;
+        let length = (duration.as_secs_f64() * sample_rate).round() as u64;
+        if length > 0 {
+            self.start_driver(engine)?;
+        }
+
+        let mut state = self.shared.lock();
+        let ramps = snapshot
+            .volumes
+            .iter()
+            .filter_map(|(id, to)| {
+                let ptr = *state.groups.get(id)?;
+                let from = unsafe { sys::ma_sound_group_get_volume(ptr.0) };
+                Some(Ramp {
+                    group: *id,
+                    ptr,
+                    from,
+                    to: *to,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if length == 0 {
+            for ramp in &ramps {
+                unsafe { sys::ma_sound_group_set_volume(ramp.ptr.0, ramp.to) };
+            }
+            state.transition = None;
+            self.shared.active.store(false, Ordering::Release);
+        } else {
+            state.transition = Some(Transition {
+                ramps,
+                elapsed: 0,
+                length,
+            });
+            self.shared.active.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    fn start_driver(&self, engine: &Engine) -> MaResult<()> {
+        let mut driver = self.driver.lock().unwrap_or_else(|e| e.into_inner());
+        if driver.is_some() {
+            return Ok(());
+        }
+        let channels = engine.channels();
+        let processor = TransitionDriver {
+            shared: self.shared.clone(),
+            channels: channels as usize,
+        };
+        let node = NodeBuilder::source()
+            .output_channel_count(channels)
+            .build(&engine.as_node_graph(), processor)?;
+        node.as_node().attach_output(0, &mut engine.endpoint(), 0)?;
+        *driver = Some(node);
+        Ok(())
+    }
+
+    /// Stops the transition and removes the driver node. Called before the engine is freed.
+    pub(crate) fn clear(&self) {
+        {
+            let mut state = self.shared.lock();
+            state.transition = None;
+            self.shared.active.store(false, Ordering::Release);
+        }
+        drop(self.driver.lock().unwrap_or_else(|e| e.into_inner()).take());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{engine_builder::EngineBuilder, mix_snapshot::MixSnapshot, Engine},
+    };
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.02, "expected ~={b}, got {a}");
+    }
+
+    #[test]
+    fn test_mix_snapshot_capture_and_edit() {
+        let engine = Engine::new_for_tests().unwrap();
+        let mut music = engine.new_sound_group().unwrap();
+        let sfx = engine.new_sound_group().unwrap();
+        music.set_volume(0.5);
+
+        let mut snapshot = MixSnapshot::capture(&[&music, &sfx]);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.volume(&music), Some(0.5));
+        assert_eq!(snapshot.volume(&sfx), Some(1.0));
+
+        snapshot.set_volume(&sfx, 0.25);
+        assert_eq!(snapshot.volume(&sfx), Some(0.25));
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.remove(&music), Some(0.5));
+        assert_eq!(snapshot.volume(&music), None);
+
+        engine.define_snapshot("pause", snapshot.clone());
+        assert_eq!(engine.snapshot("pause"), Some(snapshot));
+        assert!(engine.remove_snapshot("pause").is_some());
+        assert!(engine
+            .transition_to_named("pause", Duration::from_millis(10))
+            .is_err());
+    }
+
+    #[test]
+    fn test_mix_snapshot_transition_on_audio_thread() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let music = engine.new_sound_group().unwrap();
+        let sfx = engine.new_sound_group().unwrap();
+        let ambience = engine.new_sound_group().unwrap();
+
+        let mut combat = MixSnapshot::new();
+        combat.set_volume(&music, 0.0).set_volume(&sfx, 0.5);
+        engine.define_snapshot("combat", combat);
+        engine
+            .transition_to_named("combat", Duration::from_millis(100))
+            .unwrap();
+        assert!(engine.is_transitioning());
+        // Nothing changes until the engine processes audio
+        assert_eq!(music.volume(), 1.0);
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(2400).unwrap();
+        assert_close(music.volume(), 0.5);
+        assert_close(sfx.volume(), 0.75);
+
+        reader.read_pcm_frames(2400).unwrap();
+        assert_eq!(music.volume(), 0.0);
+        assert_eq!(sfx.volume(), 0.5);
+        assert_eq!(ambience.volume(), 1.0);
+        assert!(!engine.is_transitioning());
+
+        // A new transition starts from the current volumes, and skips dropped groups
+        let explore = MixSnapshot::capture(&[&music, &sfx]);
+        let mut back = MixSnapshot::new();
+        back.set_volume(&music, 1.0).set_volume(&sfx, 1.0);
+        engine
+            .transition_to(&back, Duration::from_millis(100))
+            .unwrap();
+        reader.read_pcm_frames(2400).unwrap();
+        assert_close(music.volume(), 0.5);
+        drop(sfx);
+        reader.read_pcm_frames(2400).unwrap();
+        assert_eq!(music.volume(), 1.0);
+
+        engine.transition_to(&explore, Duration::ZERO).unwrap();
+        assert!(!engine.is_transitioning());
+        assert_eq!(music.volume(), 0.0);
+    }
+}
@@ -8,6 +8,7 @@ use std::{
 };
 
 pub mod node_builder;
+pub(crate) mod node_bypass;
 pub(crate) mod node_flags;
 pub mod node_graph_builder;
 pub mod node_on_process;
@@ -0,0 +1,408 @@
+//! Bypass for any node, behind `NodeOps::set_bypassed`.
+//!
+//! miniaudio calls a node through the vtable pointer stored in its `ma_node_base`, and reads
+//! it without synchronization on the audio thread. The first time a node is bypassed, it is
+//! detached from the graph, its vtable pointer is replaced by a wrapper and its outputs are
+//! attached again. miniaudio waits for the audio thread to finish with the node before a
+//! detach returns, so the pointer is never written while it is read.
+//!
+//! After that, bypassing only flips a flag in the wrapper. The wrapper keeps the flags of the
+//! original vtable and calls the original callbacks while the flag is off. It is freed when
+//! the node is uninitialized, see [`release`].
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{ErrorKinds, MaResult, MaudioError};
+
+#[repr(C)]
+struct BypassVtable {
+    // Must be first, the node's vtable pointer points here
+    vtable: sys::ma_node_vtable,
+    original: *const sys::ma_node_vtable,
+    bypassed: AtomicBool,
+}
+
+// Only held while a wrapper is installed, so two threads can not install one on the same node
+static INSTALL_LOCK: Mutex<()> = Mutex::new(());
+
+pub(crate) fn is_bypassed(node: *mut sys::ma_node) -> bool {
+    match installed(node) {
+        Some(bypass) => bypass.bypassed.load(Ordering::Acquire),
+        None => false,
+    }
+}
+
+pub(crate) fn set_bypassed(node: *mut sys::ma_node, bypassed: bool) -> MaResult<()> {
+    if let Some(bypass) = installed(node) {
+        bypass.bypassed.store(bypassed, Ordering::Release);
+        return Ok(());
+    }
+    if !bypassed {
+        return Ok(());
+    }
+    check_bypassable(node)?;
+
+    let _guard = INSTALL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    // Another thread may have installed it while we were waiting
+    if let Some(bypass) = installed(node) {
+        bypass.bypassed.store(true, Ordering::Release);
+        return Ok(());
+    }
+    install(node);
+    Ok(())
+}
+
+/// Frees the bypass wrapper of a node, if it has one.
+///
+/// Must be called after the node is uninitialized and before its memory is freed. The
+/// original vtable is put back, so code that frees the vtable still sees its own.
+pub(crate) fn release(node: *mut sys::ma_node) {
+    if installed(node).is_none() {
+        return;
+    }
+    let base = node.cast::<sys::ma_node_base>();
+    unsafe {
+        let bypass = (*base).vtable as *mut BypassVtable;
+        (*base).vtable = (*bypass).original;
+        drop(Box::from_raw(bypass));
+    }
+}
+
+fn check_bypassable(node: *mut sys::ma_node) -> MaResult<()> {
+    let (inputs, outputs) = unsafe {
+        (
+            sys::ma_node_get_input_bus_count(node),
+            sys::ma_node_get_output_bus_count(node),
+        )
+    };
+    if inputs == 0 || outputs == 0 {
+        return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+            "only nodes with an input and an output bus can be bypassed",
+        )));
+    }
+    // The graph reads the endpoint directly, it can not be detached to install the wrapper
+    let graph = unsafe { (*node.cast::<sys::ma_node_base>()).pNodeGraph };
+    if !graph.is_null() && unsafe { sys::ma_node_graph_get_endpoint(graph) } == node {
+        return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+            "the endpoint of a node graph can not be bypassed",
+        )));
+    }
+    for bus in 0..outputs {
+        let in_channels = unsafe { sys::ma_node_get_input_channels(node, input_bus(bus, inputs)) };
+        let out_channels = unsafe { sys::ma_node_get_output_channels(node, bus) };
+        if in_channels != out_channels {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "bypassed nodes need the same channel count on each output bus and the input bus feeding it",
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Output buses past the last input bus are fed from the first input bus
+fn input_bus(output_bus: u32, input_count: u32) -> u32 {
+    if output_bus < input_count {
+        output_bus
+    } else {
+        0
+    }
+}
+
+// Returns the wrapper the node is using, if one was installed
+fn installed<'a>(node: *mut sys::ma_node) -> Option<&'a BypassVtable> {
+    // SAFETY: every node is a ma_node_base. The vtable pointer only changes in `install`,
+    // while the node is detached, and in `release`, after the node is uninitialized.
+    let vtable = unsafe { (*node.cast::<sys::ma_node_base>()).vtable };
+    if vtable.is_null() {
+        return None;
+    }
+    let on_process = unsafe { (*vtable).onProcess }?;
+    match on_process as *const () == bypass_on_process as *const () {
+        true => Some(unsafe { &*(vtable as *const BypassVtable) }),
+        false => None,
+    }
+}
+
+fn install(node: *mut sys::ma_node) {
+    let base = node.cast::<sys::ma_node_base>();
+    let original = unsafe { (*base).vtable };
+    let bypass = Box::new(BypassVtable {
+        vtable: sys::ma_node_vtable {
+            onProcess: Some(bypass_on_process),
+            onGetRequiredInputFrameCount: Some(bypass_on_get_required_input_frame_count),
+            ..unsafe { *original }
+        },
+        original,
+        bypassed: AtomicBool::new(true),
+    });
+
+    // Remember where the outputs go, then detach them so the audio thread stops reading the node
+    let outputs = unsafe { sys::ma_node_get_output_bus_count(node) };
+    let targets: Vec<(*mut sys::ma_node, u32)> = (0..outputs)
+        .map(|bus| unsafe {
+            let output = &*(*base).pOutputBuses.add(bus as usize);
+            (output.pInputNode, output.inputNodeInputBusIndex as u32)
+        })
+        .collect();
+    unsafe { sys::ma_node_detach_all_output_buses(node) };
+
+    // SAFETY: the node is detached, nothing reads its vtable until it is attached again
+    unsafe { (*base).vtable = Box::into_raw(bypass).cast::<sys::ma_node_vtable>() };
+
+    for (bus, (target, target_bus)) in targets.into_iter().enumerate() {
+        if !target.is_null() {
+            unsafe { sys::ma_node_attach_output_bus(node, bus as u32, target, target_bus) };
+        }
+    }
+}
+
+unsafe extern "C" fn bypass_on_get_required_input_frame_count(
+    node: *mut sys::ma_node,
+    output_frame_count: u32,
+    input_frame_count: *mut u32,
+) -> sys::ma_result {
+    let bypass = &*((*node.cast::<sys::ma_node_base>()).vtable as *const BypassVtable);
+    if bypass.bypassed.load(Ordering::Acquire) {
+        *input_frame_count = output_frame_count;
+        return sys::ma_result_MA_SUCCESS;
+    }
+    match (*bypass.original).onGetRequiredInputFrameCount {
+        Some(f) => f(node, output_frame_count, input_frame_count),
+        // miniaudio already set the default
+        None => sys::ma_result_MA_SUCCESS,
+    }
+}
+
+unsafe extern "C" fn bypass_on_process(
+    node: *mut sys::ma_node,
+    frames_in: *mut *const f32,
+    frame_count_in: *mut u32,
+    frames_out: *mut *mut f32,
+    frame_count_out: *mut u32,
+) {
+    if node.is_null() || frames_out.is_null() || frame_count_out.is_null() {
+        return;
+    }
+    let bypass = &*((*node.cast::<sys::ma_node_base>()).vtable as *const BypassVtable);
+    if !bypass.bypassed.load(Ordering::Acquire) {
+        if let Some(f) = (*bypass.original).onProcess {
+            f(node, frames_in, frame_count_in, frames_out, frame_count_out);
+        }
+        return;
+    }
+
+    let frames = match frame_count_in.is_null() {
+        true => 0,
+        false => (*frame_count_in).min(*frame_count_out),
+    };
+    let inputs = sys::ma_node_get_input_bus_count(node);
+
+    for bus in 0..sys::ma_node_get_output_bus_count(node) {
+        let out = *frames_out.add(bus as usize);
+        if out.is_null() {
+            continue;
+        }
+        let channels = sys::ma_node_get_output_channels(node, bus) as usize;
+        let input = match frames_in.is_null() {
+            true => core::ptr::null(),
+            false => *frames_in.add(input_bus(bus, inputs) as usize),
+        };
+        let len = frames as usize * channels;
+        if input.is_null() {
+            core::slice::from_raw_parts_mut(out, len).fill(0.0);
+        } else if !core::ptr::eq(input, out) {
+            // Passthrough nodes read their input straight into the output
+            core::ptr::copy_nonoverlapping(input, out, len);
+        }
+    }
+    if !frame_count_in.is_null() {
+        *frame_count_in = frames;
+    }
+    *frame_count_out = frames;
+}
+
+#[cfg(test)]
+mod test {
+    use maudio_sys::ffi as sys;
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{
+                    effects::{bitcrusher::BitcrusherNodeBuilder, delay::DelayNodeBuilder},
+                    filters::hpf::HpfNodeBuilder,
+                    routing::splitter::SplitterNodeBuilder,
+                    NodeOps,
+                },
+                NodeGraphOps,
+            },
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_bypass_passes_input_through_and_restores_processing() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let crusher = BitcrusherNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .bits(2)
+            .build()
+            .unwrap();
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.3; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut crusher.as_node(), 0)
+            .unwrap();
+        crusher
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+
+        // Two bits round 0.3 up to 0.5
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref()[8..].iter().all(|s| *s == 0.5));
+
+        assert!(!crusher.as_node().is_bypassed());
+        crusher.as_node().set_bypassed(true).unwrap();
+        assert!(crusher.as_node().is_bypassed());
+        // Already bypassed
+        crusher.as_node().set_bypassed(true).unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref().iter().all(|s| (*s - 0.3).abs() < 1e-6));
+
+        crusher.as_node().set_bypassed(false).unwrap();
+        assert!(!crusher.as_node().is_bypassed());
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref().iter().all(|s| *s == 0.5));
+    }
+
+    #[test]
+    fn test_bypass_builtin_filter() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut hpf = HpfNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000, 1000.0, 2)
+            .build()
+            .unwrap();
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5; 8192]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut hpf.as_node(), 0)
+            .unwrap();
+        hpf.as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+
+        // A high-pass filter removes DC once it settles
+        let out = reader.read_pcm_frames(2048).unwrap();
+        assert!(out.as_ref()[1024..].iter().all(|s| s.abs() < 0.01));
+
+        hpf.as_node().set_bypassed(true).unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref().iter().all(|s| (*s - 0.5).abs() < 1e-6));
+        // The filter still works after the bypass is removed
+        hpf.as_node().set_bypassed(false).unwrap();
+        hpf.reinit(SampleRate::Sr48000, 2000.0).unwrap();
+    }
+
+    #[test]
+    fn test_bypass_needs_an_input_bus() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5; 64]).unwrap();
+        let sound = engine.new_sound_from_source(&buffer).unwrap();
+
+        // A sound playing a data source has no input bus
+        assert!(sound.as_node().set_bypassed(true).is_err());
+        assert!(!sound.as_node().is_bypassed());
+        sound.as_node().set_bypassed(false).unwrap();
+    }
+
+    #[test]
+    fn test_bypass_feeds_extra_output_buses() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let splitter = SplitterNodeBuilder::new(&node_graph, 1)
+            .output_bus_count(2)
+            .build()
+            .unwrap();
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut splitter.as_node(), 0)
+            .unwrap();
+        for bus in 0..2 {
+            splitter
+                .as_node()
+                .attach_output(bus, &mut node_graph.endpoint(), 0)
+                .unwrap();
+        }
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+
+        // Skip the first frame, which is silent while the sound starts
+        reader.read_pcm_frames(8).unwrap();
+        // Both outputs still reach the endpoint, which mixes them
+        splitter.as_node().set_bypassed(true).unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref().iter().all(|s| (*s - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_bypass_keeps_vtable_flags() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let delay = DelayNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000, 480, 0.5)
+            .build()
+            .unwrap();
+
+        delay.as_node().set_bypassed(true).unwrap();
+        let node = crate::engine::node_graph::nodes::private_node::node_ptr(&delay.as_node());
+        let flags = unsafe { (*(*node.cast::<sys::ma_node_base>()).vtable).flags };
+        assert_eq!(flags, sys::ma_node_flags_MA_NODE_FLAG_CONTINUOUS_PROCESSING);
+
+        delay.as_node().set_bypassed(false).unwrap();
+        assert!(!delay.as_node().is_bypassed());
+        // The wrapper is freed with the node
+        drop(delay);
+    }
+
+    #[test]
+    fn test_bypass_rejects_endpoint() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        assert!(node_graph.endpoint().set_bypassed(true).is_err());
+        assert!(!node_graph.endpoint().is_bypassed());
+    }
+}
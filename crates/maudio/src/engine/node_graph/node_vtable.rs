@@ -1,5 +1,3 @@
-use std::panic::AssertUnwindSafe;
-
 use maudio_sys::ffi as sys;
 
 use crate::engine::node_graph::{
@@ -8,6 +6,7 @@ use crate::engine::node_graph::{
     node_on_process::{CustomNode, InputBusses, OutputBusses, ReqFramesNode},
     nodes::NodeInner,
 };
+use crate::util::{callback_panic, rt_check};
 
 pub(crate) fn node_vtable<C: CustomNode>(
     in_bus: u8,
@@ -54,6 +53,7 @@ unsafe extern "C" fn node_on_process<C: CustomNode>(
 
     let node = &mut *(node).cast::<NodeInner<C>>();
     let flags = NodeFlags::from_bits((*node.vtable).flags);
+    let _rt = rt_check::enter();
 
     match node.op {
         NodeFunction::Source => {
@@ -63,10 +63,10 @@ unsafe extern "C" fn node_on_process<C: CustomNode>(
             let mut output =
                 OutputBusses::from_raw(frames_out, *frame_count_out as usize, &node.busses.outputs);
             // We do not need to update frame_count_in or frame_count_out. We can ignore the output.
-            let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let res = callback_panic::guard(|| {
                 node.custom
                     .process_frames(&InputBusses::zeroed(), &mut output)
-            }));
+            });
             match res {
                 Ok(Ok(frames)) => {
                     let frames_output = frames.frames_out_written;
@@ -97,10 +97,10 @@ unsafe extern "C" fn node_on_process<C: CustomNode>(
                 InputBusses::from_raw(frames_in, *frame_count_in as usize, &node.busses.inputs);
 
             // We do not need to update frame_count_in or frame_count_out. We can ignore the output.
-            let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = callback_panic::guard(|| {
                 node.custom
                     .process_frames(&input, &mut OutputBusses::zeroed())
-            }));
+            });
         }
         NodeFunction::Process => {
             if frames_out.is_null() || frame_count_out.is_null() {
@@ -134,9 +134,7 @@ unsafe extern "C" fn node_on_process<C: CustomNode>(
                 &node.busses.outputs,
             );
 
-            let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
-                node.custom.process_frames(&input, &mut output)
-            }));
+            let res = callback_panic::guard(|| node.custom.process_frames(&input, &mut output));
             match res {
                 Ok(Ok(frames)) => {
                     // The 2 fields inside ProcessResult are equal
@@ -181,9 +179,7 @@ unsafe extern "C" fn node_on_process<C: CustomNode>(
 
             let mut output =
                 OutputBusses::from_raw(frames_out, *frame_count_out as usize, &node.busses.outputs);
-            let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
-                node.custom.process_frames(&input, &mut output)
-            }));
+            let res = callback_panic::guard(|| node.custom.process_frames(&input, &mut output));
             match res {
                 Ok(Ok(frames)) => {
                     // The 2 fields inside ProcessResult are equal
@@ -217,9 +213,7 @@ unsafe extern "C" fn node_on_required_input_frame_count<C: CustomNode + ReqFrame
 
     let node = &mut *(node).cast::<NodeInner<C>>();
 
-    let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
-        node.custom.get_required_frames(out_frame_count)
-    }));
+    let res = callback_panic::guard(|| node.custom.get_required_frames(out_frame_count));
 
     match res {
         Ok(Ok(frames)) => {
@@ -61,6 +61,7 @@ use crate::{
     engine::{
         node_graph::{
             node_builder::NodeFunction,
+            node_bypass,
             node_flags::NodeFlags,
             node_on_process::{CustomNode, ReqFramesNode},
             node_vtable::{node_vtable, node_vtable_req_frames},
@@ -68,7 +69,7 @@ use crate::{
         },
         Engine,
     },
-    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
+    AsRawRef, Binding, ErrorContext, ErrorKinds, MaResult, MaudioError, ResultContext,
 };
 
 pub mod effects;
@@ -114,6 +115,10 @@ pub struct Node<C> {
     _not_sync: PhantomData<Cell<()>>,
 }
 
+// The node only owns its heap allocation, which the audio thread reaches through the graph
+// whichever thread holds the handle. Moving it is fine as long as the callback state is Send.
+unsafe impl<C: Send> Send for Node<C> {}
+
 #[repr(C)]
 pub(crate) struct NodeInner<C> {
     pub(crate) base: sys::ma_node_base,
@@ -415,12 +420,18 @@ pub(crate) mod private_node {
     use crate::{
         data_source::AsSourcePtr,
         engine::node_graph::nodes::{
-            effects::delay::DelayNode,
+            effects::{
+                bitcrusher::BitcrusherNode, channel_gain::ChannelGainNode, delay::DelayNode,
+                fade::FadeNode, gate::GateNode, pitch_shift::PitchShiftNode, ring_mod::RingModNode,
+            },
             filters::{
                 biquad::BiquadNode, hishelf::HiShelfNode, hpf::HpfNode, loshelf::LoShelfNode,
                 lpf::LpfNode, notch::NotchNode, peak::PeakNode,
             },
-            routing::splitter::SplitterNode,
+            routing::{
+                capture::CaptureNode, loudness::LoudnessNode, recorder::RecorderNode,
+                splitter::SplitterNode,
+            },
             source::source_node::{AttachedSourceNode, SourceNode},
         },
     };
@@ -435,6 +446,12 @@ pub(crate) mod private_node {
     pub struct NodeProvider;
     pub struct NodeRefProvider;
     pub struct DelayNodeProvider;
+    pub struct FadeNodeProvider;
+    pub struct GateNodeProvider;
+    pub struct PitchShiftNodeProvider;
+    pub struct BitcrusherNodeProvider;
+    pub struct ChannelGainNodeProvider;
+    pub struct RingModNodeProvider;
     pub struct BiquadNodeProvider;
     pub struct HiShelfNodeProvider;
     pub struct HpfNodeProvider;
@@ -442,6 +459,9 @@ pub(crate) mod private_node {
     pub struct LpfNodeProvider;
     pub struct NotchNodeProvider;
     pub struct PeakNodeProvider;
+    pub struct CaptureNodeProvider;
+    pub struct LoudnessNodeProvider;
+    pub struct RecorderNodeProvider;
     pub struct SplitterNodeProvider;
     pub struct SourceNodeProvider;
     pub struct AttachedSourceNodeProvider;
@@ -467,6 +487,48 @@ pub(crate) mod private_node {
         }
     }
 
+    impl NodePtrProvider<FadeNode> for FadeNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &FadeNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<GateNode> for GateNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &GateNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<PitchShiftNode> for PitchShiftNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &PitchShiftNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<BitcrusherNode> for BitcrusherNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &BitcrusherNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<ChannelGainNode> for ChannelGainNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &ChannelGainNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<RingModNode> for RingModNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &RingModNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
     impl NodePtrProvider<BiquadNode> for BiquadNodeProvider {
         #[inline]
         fn as_node_ptr(t: &BiquadNode) -> *mut sys::ma_node {
@@ -516,6 +578,27 @@ pub(crate) mod private_node {
         }
     }
 
+    impl NodePtrProvider<CaptureNode> for CaptureNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &CaptureNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<LoudnessNode> for LoudnessNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &LoudnessNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
+    impl NodePtrProvider<RecorderNode> for RecorderNodeProvider {
+        #[inline]
+        fn as_node_ptr(t: &RecorderNode) -> *mut sys::ma_node {
+            t.as_node().to_raw()
+        }
+    }
+
     impl NodePtrProvider<SplitterNode> for SplitterNodeProvider {
         #[inline]
         fn as_node_ptr(t: &SplitterNode) -> *mut sys::ma_node {
@@ -561,19 +644,83 @@ impl<T: AsNodePtr + ?Sized> NodeOps for T {}
 
 /// NodeOps trait contains shared methods for `Node` and [`NodeRef`]
 pub trait NodeOps: AsNodePtr {
-    /// Attaches `output_bus` of this node to `other_node_input_bus` of `other_node`.
+    /// Attaches `bus` (an output bus of this node) to `target_bus` (an input bus of `target`).
+    ///
+    /// If `bus` is already attached, it is detached first.
+    ///
+    /// The bus indices and the resulting graph are checked before attaching, instead of
+    /// failing later when the graph is processed:
+    /// - [`ErrorKinds::BusIndexOutOfRange`] if either bus does not exist
+    /// - [`ErrorKinds::NodeGraphCycle`] if `target` already feeds into this node (or is this node)
+    fn attach_output<P: AsNodePtr + ?Sized>(
+        &mut self,
+        bus: u32,
+        target: &mut P,
+        target_bus: u32,
+    ) -> MaResult<()> {
+        check_bus_index("output bus", bus, self.out_bus_count()).with_node(self.node_id())?;
+        check_bus_index("input bus", target_bus, target.in_bus_count())
+            .with_node(target.node_id())?;
+        if node_ffi::node_feeds_into(target, self) {
+            return Err(MaudioError::new_ma_error(ErrorKinds::NodeGraphCycle)
+                .with_context(ErrorContext::Node(self.node_id())));
+        }
+        node_ffi::ma_node_attach_output_bus(self, bus, target, target_bus).with_node(self.node_id())
+    }
+
+    /// Detaches `bus` (an output bus of this node) from the input bus it is attached to.
+    ///
+    /// Detaching a bus that is not attached does nothing.
+    /// Returns [`ErrorKinds::BusIndexOutOfRange`] if the bus does not exist.
+    fn detach_output(&mut self, bus: u32) -> MaResult<()> {
+        check_bus_index("output bus", bus, self.out_bus_count()).with_node(self.node_id())?;
+        node_ffi::ma_node_detach_output_bus(self, bus)
+    }
+
+    #[deprecated(since = "0.1.6", note = "renamed to `attach_output`")]
     fn attach_output_bus<P: AsNodePtr + ?Sized>(
         &mut self,
         output_bus: u32,
         other_node: &mut P,
         other_node_input_bus: u32,
     ) -> MaResult<()> {
-        node_ffi::ma_node_attach_output_bus(self, output_bus, other_node, other_node_input_bus)
+        self.attach_output(output_bus, other_node, other_node_input_bus)
     }
 
-    /// Detaches the specified output bus from its connected input bus.
+    #[deprecated(since = "0.1.6", note = "renamed to `detach_output`")]
     fn detach_output_bus(&mut self, output_bus: u32) -> MaResult<()> {
-        node_ffi::ma_node_detach_output_bus(self, output_bus)
+        self.detach_output(output_bus)
+    }
+
+    /// Identifies the node in an [`ErrorContext::Node`].
+    ///
+    /// This is the address of the underlying `ma_node`, which does not change while the
+    /// node is alive. It may be reused by a node created after this one is dropped.
+    fn node_id(&self) -> usize {
+        private_node::node_ptr(self) as usize
+    }
+
+    /// Bypasses the node, or turns the bypass off again.
+    ///
+    /// A bypassed node copies each input bus to the output bus with the same index unchanged.
+    /// Output buses past the last input bus get a copy of the first input bus. Its connections
+    /// stay the same and its own state is kept, so an effect can be toggled from a UI without
+    /// rewiring the graph.
+    ///
+    /// The first bypass of a node detaches and reattaches its outputs, which can drop one
+    /// period of its output if it is playing. Later calls only flip a flag and are safe to make
+    /// at any time.
+    ///
+    /// Works for every node, built in or custom. Returns [`ErrorKinds::InvalidOperation`] for
+    /// the endpoint of a node graph, if the node has no input or output bus, or if an output
+    /// bus has a different channel count than the input bus feeding it.
+    fn set_bypassed(&mut self, bypassed: bool) -> MaResult<()> {
+        node_bypass::set_bypassed(private_node::node_ptr(self), bypassed).with_node(self.node_id())
+    }
+
+    /// Returns `true` if the node is bypassed, see [`NodeOps::set_bypassed`].
+    fn is_bypassed(&self) -> bool {
+        node_bypass::is_bypassed(private_node::node_ptr(self))
     }
 
     /// Detaches all output buses from their connected input buses.
@@ -601,13 +748,24 @@ pub trait NodeOps: AsNodePtr {
         node_ffi::ma_node_get_output_channels(self, out_bus_index)
     }
 
-    /// Returns the volume for the given output bus.
-    fn output_bus_volume(&mut self, out_bus_index: u32) -> f32 {
-        node_ffi::ma_node_get_output_bus_volume(self, out_bus_index)
+    /// Returns the linear volume of the given output bus.
+    ///
+    /// Returns [`ErrorKinds::BusIndexOutOfRange`] if the bus does not exist.
+    fn output_bus_volume(&self, out_bus_index: u32) -> MaResult<f32> {
+        check_bus_index("output bus", out_bus_index, self.out_bus_count())?;
+        Ok(node_ffi::ma_node_get_output_bus_volume(self, out_bus_index))
     }
 
-    /// Sets the volume for the given output bus.
+    /// Sets the linear volume of the given output bus.
+    ///
+    /// The volume is applied to everything leaving the bus, so it can be used as a
+    /// submix level for a branch of the graph without inserting a gain node.
+    /// `1.0` leaves the signal unchanged. Use [`sound_volume_db_to_linear`](crate::sound::sound_volume_db_to_linear)
+    /// to convert from decibels.
+    ///
+    /// Returns [`ErrorKinds::BusIndexOutOfRange`] if the bus does not exist.
     fn set_output_bus_volume(&mut self, out_bus_index: u32, volume: f32) -> MaResult<()> {
+        check_bus_index("output bus", out_bus_index, self.out_bus_count())?;
         node_ffi::ma_node_set_output_bus_volume(self, out_bus_index, volume)
     }
 
@@ -624,17 +782,53 @@ pub trait NodeOps: AsNodePtr {
         node_ffi::ma_node_set_state(self, state)
     }
 
-    /// Sets the current node state.
+    /// Returns the global time (in PCM frames) at which `state` becomes active.
     fn state_time(&self, state: NodeState) -> u64 {
         node_ffi::ma_node_get_state_time(self, state)
     }
 
-    /// Returns the global time (in PCM frames) at which `state` becomes active.
+    /// Sets the global time (in PCM frames) at which `state` becomes active.
+    ///
+    /// The global time is the time of the node graph (see [`Engine::time_pcm`]).
+    /// By default a node is started at time `0` and never stopped.
+    ///
+    /// Scheduled times only apply while the node state is [`NodeState::Started`].
+    /// A node set to [`NodeState::Stopped`] does not process, regardless of the times.
     fn set_state_time(&mut self, state: NodeState, global_time: u64) -> MaResult<()> {
         node_ffi::ma_node_set_state_time(self, state, global_time)
     }
 
-    /// Sets the global time (in PCM frames) at which `state` becomes active.
+    /// Schedules the node to start processing at `global_time` (in PCM frames).
+    ///
+    /// The node outputs silence until then. Also sets the node state to
+    /// [`NodeState::Started`], so it only needs to be called once.
+    ///
+    /// ```no_run
+    /// # use maudio::engine::{Engine, node_graph::nodes::NodeOps};
+    /// # fn main() -> maudio::MaResult<()> {
+    /// # let engine = Engine::new()?;
+    /// # let sound = engine.new_sound()?;
+    /// let mut node = sound.as_node();
+    /// // Bring the node online on the next bar (120 bpm, 4/4)
+    /// let bar = u32::from(engine.sample_rate()?) as u64 * 2;
+    /// let next_bar = (engine.time_pcm() / bar + 1) * bar;
+    /// node.set_start_time_pcm(next_bar)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn set_start_time_pcm(&mut self, global_time: u64) -> MaResult<()> {
+        node_ffi::ma_node_set_state_time(self, NodeState::Started, global_time)?;
+        node_ffi::ma_node_set_state(self, NodeState::Started)
+    }
+
+    /// Schedules the node to stop processing at `global_time` (in PCM frames).
+    ///
+    /// Use `u64::MAX` to cancel a scheduled stop.
+    fn set_stop_time_pcm(&mut self, global_time: u64) -> MaResult<()> {
+        node_ffi::ma_node_set_state_time(self, NodeState::Stopped, global_time)
+    }
+
+    /// Returns the node state at `global_time`, taking the scheduled times into account.
     fn state_by_time(&self, global_time: u64) -> MaResult<NodeState> {
         node_ffi::ma_node_get_state_by_time(self, global_time)
     }
@@ -659,7 +853,18 @@ pub trait NodeOps: AsNodePtr {
     }
 }
 
-pub(super) mod node_ffi {
+fn check_bus_index(context: &'static str, bus: u32, bus_count: u32) -> MaResult<()> {
+    if bus >= bus_count {
+        return Err(MaudioError::new_ma_error(ErrorKinds::BusIndexOutOfRange {
+            context,
+            bus,
+            bus_count,
+        }));
+    }
+    Ok(())
+}
+
+pub(crate) mod node_ffi {
     use std::sync::Arc;
 
     use maudio_sys::ffi as sys;
@@ -788,6 +993,67 @@ pub(super) mod node_ffi {
         }
     }
 
+    /// Returns `true` if audio from `from` reaches `to` by following attached output buses,
+    /// or if both are the same node.
+    pub(crate) fn node_feeds_into<P: AsNodePtr + ?Sized, Q: AsNodePtr + ?Sized>(
+        from: &P,
+        to: &Q,
+    ) -> bool {
+        let target = private_node::node_ptr(to);
+        let mut stack = vec![private_node::node_ptr(from)];
+        let mut visited = Vec::new();
+        while let Some(node) = stack.pop() {
+            if node == target {
+                return true;
+            }
+            if node.is_null() || visited.contains(&node) {
+                continue;
+            }
+            visited.push(node);
+            stack.extend(attached_output_nodes(node));
+        }
+        false
+    }
+
+    // Nodes that the output buses of `node` are attached to
+    fn attached_output_nodes(node: *mut sys::ma_node) -> Vec<*mut sys::ma_node> {
+        // SAFETY: every node is a ma_node_base. pOutputBuses holds outputBusCount buses.
+        // pInputNode is updated atomically by miniaudio, so it is read as volatile.
+        unsafe {
+            let base = &*(node as *const sys::ma_node_base);
+            (0..base.outputBusCount as usize)
+                .map(|i| {
+                    let bus = base.pOutputBuses.add(i);
+                    core::ptr::read_volatile(core::ptr::addr_of!((*bus).pInputNode))
+                })
+                .filter(|n| !n.is_null())
+                .collect()
+        }
+    }
+
+    /// Returns the node and input bus that output bus `output_bus_index` of `node` is
+    /// attached to, if any.
+    pub(crate) fn output_attachment<P: AsNodePtr + ?Sized>(
+        node: &P,
+        output_bus_index: u32,
+    ) -> Option<(*mut sys::ma_node, u32)> {
+        let node = private_node::node_ptr(node);
+        // SAFETY: every node is a ma_node_base. The bus index is checked against
+        // outputBusCount. pInputNode is updated atomically by miniaudio.
+        unsafe {
+            let base = &*(node as *const sys::ma_node_base);
+            if output_bus_index >= base.outputBusCount {
+                return None;
+            }
+            let bus = base.pOutputBuses.add(output_bus_index as usize);
+            let input_node = core::ptr::read_volatile(core::ptr::addr_of!((*bus).pInputNode));
+            if input_node.is_null() {
+                return None;
+            }
+            Some((input_node, (*bus).inputNodeInputBusIndex as u32))
+        }
+    }
+
     #[inline]
     pub(crate) fn ma_node_detach_output_bus<P: AsNodePtr + ?Sized>(
         node: &mut P,
@@ -825,11 +1091,14 @@ pub(super) mod node_ffi {
 
     #[inline]
     pub(crate) fn ma_node_get_output_bus_volume<P: AsNodePtr + ?Sized>(
-        node: &mut P,
+        node: &P,
         output_bus_index: sys::ma_uint32,
     ) -> f32 {
         unsafe {
-            sys::ma_node_get_output_bus_volume(private_node::node_ptr(node), output_bus_index)
+            sys::ma_node_get_output_bus_volume(
+                private_node::node_ptr(node) as *const _,
+                output_bus_index,
+            )
         }
     }
 
@@ -912,6 +1181,7 @@ pub(super) mod node_ffi {
 impl<C> Drop for Node<C> {
     fn drop(&mut self) {
         node_ffi::ma_node_uninit(self, None);
+        node_bypass::release(self.inner.cast());
         drop(unsafe { Box::from_raw((*self.inner).vtable as *mut sys::ma_node_vtable) });
         drop(unsafe { Box::from_raw(self.inner) });
     }
@@ -0,0 +1,333 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::{
+    audio::sample_rate::SampleRate,
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult, MaudioError,
+};
+
+/// Lowest supported bit depth.
+pub const MIN_BITS: u32 = 1;
+/// Highest supported bit depth. At this depth the quantization is inaudible.
+pub const MAX_BITS: u32 = 24;
+
+/// A node that reduces the bit depth and sample rate of an audio signal.
+///
+/// Each sample is rounded to one of `2^(bits - 1)` steps per polarity, and frames are
+/// sampled and held at a lower rate than the node runs at, which folds high frequencies
+/// back down as aliasing. Together they give the gritty, lo-fi sound of early samplers and
+/// game consoles.
+///
+/// The processing is done entirely in Rust inside the node callback, which makes this node
+/// a small, self-contained example of the custom node API. Both parameters can be changed
+/// at any time from the control thread and are picked up on the next processing callback.
+///
+/// Use [`BitcrusherNodeBuilder`] to initialize
+pub struct BitcrusherNode {
+    node: Node<Effect<BitcrusherProcessor>>,
+    params: Arc<BitcrusherParams>,
+    channels: u32,
+    sample_rate: SampleRate,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for BitcrusherNode {
+    type __PtrProvider = private_node::BitcrusherNodeProvider;
+}
+
+impl BitcrusherNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Returns the bit depth.
+    pub fn bits(&self) -> u32 {
+        self.params.bits.load(Ordering::Relaxed)
+    }
+
+    /// Sets the bit depth. Clamped to [`MIN_BITS`]..=[`MAX_BITS`].
+    pub fn set_bits(&mut self, bits: u32) {
+        self.params
+            .bits
+            .store(bits.clamp(MIN_BITS, MAX_BITS), Ordering::Relaxed);
+    }
+
+    /// Returns the rate, in Hz, at which new frames are sampled.
+    pub fn target_rate(&self) -> f32 {
+        f32::from_bits(self.params.target_rate.load(Ordering::Relaxed))
+    }
+
+    /// Sets the rate, in Hz, at which new frames are sampled. Frames in between repeat the
+    /// last sampled one.
+    ///
+    /// Clamped to the node's sample rate, which disables the reduction. Values that are not
+    /// finite or not positive are ignored.
+    pub fn set_target_rate(&mut self, rate: f32) {
+        if !rate.is_finite() || rate <= 0.0 {
+            return;
+        }
+        let rate = rate.min(u32::from(self.sample_rate) as f32);
+        self.params
+            .target_rate
+            .store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+struct BitcrusherParams {
+    bits: AtomicU32,
+    target_rate: AtomicU32,
+}
+
+struct BitcrusherProcessor {
+    params: Arc<BitcrusherParams>,
+    channels: usize,
+    sample_rate: f32,
+    // Position between two sampled frames. A new frame is sampled once it reaches 1.0.
+    hold_phase: f32,
+    held: Vec<f32>,
+}
+
+impl EffectCallback for BitcrusherProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        let bits = self.params.bits.load(Ordering::Relaxed);
+        let target_rate = f32::from_bits(self.params.target_rate.load(Ordering::Relaxed));
+        let levels = (1u32 << (bits - 1)) as f32;
+        let step = target_rate / self.sample_rate;
+
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+
+        for (frame_in, frame_out) in frames_in
+            .chunks_exact(self.channels)
+            .zip(frames_out.chunks_exact_mut(self.channels))
+        {
+            if self.hold_phase >= 1.0 {
+                self.hold_phase -= 1.0;
+                for (held, sample) in self.held.iter_mut().zip(frame_in) {
+                    *held = (sample * levels).round() / levels;
+                }
+            }
+            self.hold_phase += step;
+            frame_out.copy_from_slice(&self.held);
+        }
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`BitcrusherNode`]
+pub struct BitcrusherNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    sample_rate: SampleRate,
+    bits: u32,
+    target_rate: Option<f32>,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> BitcrusherNodeBuilder<'a, N> {
+    /// Creates a builder with a bit depth of 8 and no sample rate reduction.
+    pub fn new(node_graph: &'a N, channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            bits: 8,
+            target_rate: None,
+            node_graph,
+        }
+    }
+
+    /// Sets the initial bit depth. Must be between [`MIN_BITS`] and [`MAX_BITS`].
+    pub fn bits(&mut self, bits: u32) -> &mut Self {
+        self.bits = bits;
+        self
+    }
+
+    /// Sets the initial rate, in Hz, at which new frames are sampled. Must be positive and
+    /// no higher than the node's sample rate.
+    pub fn target_rate(&mut self, rate: f32) -> &mut Self {
+        self.target_rate = Some(rate);
+        self
+    }
+
+    pub fn build(&self) -> MaResult<BitcrusherNode> {
+        let sample_rate = u32::from(self.sample_rate) as f32;
+        let target_rate = self.target_rate.unwrap_or(sample_rate);
+        if self.channels == 0
+            || !(MIN_BITS..=MAX_BITS).contains(&self.bits)
+            || !target_rate.is_finite()
+            || target_rate <= 0.0
+            || target_rate > sample_rate
+        {
+            return Err(MaudioError::from_ma_result(
+                maudio_sys::ffi::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+
+        let params = Arc::new(BitcrusherParams {
+            bits: AtomicU32::new(self.bits),
+            target_rate: AtomicU32::new(target_rate.to_bits()),
+        });
+
+        let processor = BitcrusherProcessor {
+            params: params.clone(),
+            channels: self.channels as usize,
+            sample_rate,
+            hold_phase: 1.0,
+            held: vec![0.0; self.channels as usize],
+        };
+
+        let mut builder = NodeBuilder::effect();
+        builder
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels);
+        let node = builder.build(self.node_graph, processor)?;
+
+        Ok(BitcrusherNode {
+            node,
+            params,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{effects::bitcrusher::BitcrusherNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_bitcrusher_node_basic_init() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut node = BitcrusherNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+            .bits(4)
+            .target_rate(8000.0)
+            .build()
+            .unwrap();
+
+        assert_eq!(node.channels(), 2);
+        assert_eq!(node.bits(), 4);
+        assert_eq!(node.target_rate(), 8000.0);
+        assert_eq!(node.as_node().in_bus_count(), 1);
+        assert_eq!(node.as_node().out_bus_count(), 1);
+
+        node.set_bits(0);
+        assert_eq!(node.bits(), 1);
+        node.set_target_rate(96000.0);
+        assert_eq!(node.target_rate(), 48000.0);
+        node.set_target_rate(-1.0);
+        assert_eq!(node.target_rate(), 48000.0);
+
+        assert!(
+            BitcrusherNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+                .bits(25)
+                .build()
+                .is_err()
+        );
+        assert!(
+            BitcrusherNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+                .target_rate(0.0)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_bitcrusher_node_quantizes_and_holds() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let crusher = BitcrusherNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .bits(2)
+            .target_rate(12000.0)
+            .build()
+            .unwrap();
+
+        let data: Vec<f32> = (0..1024).map(|i| (i % 8) as f32 / 8.0).collect();
+        let buffer = AudioBufferBuilder::build_f32(1, &data).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut crusher.as_node(), 0)
+            .unwrap();
+        crusher
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(512).unwrap();
+        let out = out.as_ref();
+
+        // Two bits leave the levels -1, -0.5, 0, 0.5 and 1.
+        assert!(out.iter().all(|s| [-1.0, -0.5, 0.0, 0.5, 1.0].contains(s)));
+        // A quarter of the sample rate holds each sampled frame for four frames.
+        for hold in out[8..].chunks_exact(4) {
+            assert!(hold.iter().all(|s| *s == hold[0]));
+        }
+        assert!(out.iter().any(|s| *s != 0.0));
+    }
+}
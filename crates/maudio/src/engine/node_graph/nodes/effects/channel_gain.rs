@@ -0,0 +1,261 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult, MaudioError,
+};
+
+/// A gain node with a separate gain for each channel.
+///
+/// miniaudio's volumes apply to all the channels of a sound or a node. A `ChannelGainNode`
+/// can mute or lower single channels instead, for example the left channel of a stereo
+/// signal, or balance a signal beyond what the pan law allows.
+///
+/// Gains are linear, where `1.0` is unchanged, and are picked up on the next processing
+/// callback. [`Sound::set_channel_gain`](crate::sound::Sound::set_channel_gain) inserts one of
+/// these nodes after a sound.
+///
+/// Use [`ChannelGainNodeBuilder`] to initialize
+pub struct ChannelGainNode {
+    node: Node<Effect<ChannelGainProcessor>>,
+    gains: Arc<ChannelGains>,
+    channels: u32,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for ChannelGainNode {
+    type __PtrProvider = private_node::ChannelGainNodeProvider;
+}
+
+impl ChannelGainNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Returns the gain of `channel`, or `None` if the node has no such channel.
+    pub fn gain(&self, channel: u32) -> Option<f32> {
+        self.gains.get(channel)
+    }
+
+    /// Sets the linear gain of `channel`.
+    ///
+    /// Returns an error if the node has no such channel, or if `gain` is negative or not finite.
+    pub fn set_gain(&mut self, channel: u32, gain: f32) -> MaResult<()> {
+        self.gains.set(channel, gain)
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+/// Per-channel gains shared with the audio thread.
+///
+/// Also used by the engine for the gains of its output, see
+/// [`Engine::set_output_channel_gain`](crate::engine::Engine::set_output_channel_gain).
+pub(crate) struct ChannelGains {
+    gains: Box<[AtomicU32]>,
+    // Cleared while every gain is 1.0, so the audio thread can skip the processing
+    active: AtomicBool,
+}
+
+impl ChannelGains {
+    pub(crate) fn new(channels: u32) -> Self {
+        Self {
+            gains: (0..channels)
+                .map(|_| AtomicU32::new(1f32.to_bits()))
+                .collect(),
+            active: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn get(&self, channel: u32) -> Option<f32> {
+        self.gains
+            .get(channel as usize)
+            .map(|g| f32::from_bits(g.load(Ordering::Relaxed)))
+    }
+
+    pub(crate) fn set(&self, channel: u32, gain: f32) -> MaResult<()> {
+        let Some(slot) = self.gains.get(channel as usize) else {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        };
+        if !gain.is_finite() || gain < 0.0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        slot.store(gain.to_bits(), Ordering::Relaxed);
+        let active = self
+            .gains
+            .iter()
+            .any(|g| f32::from_bits(g.load(Ordering::Relaxed)) != 1.0);
+        self.active.store(active, Ordering::Release);
+        Ok(())
+    }
+
+    /// Multiplies interleaved frames of `channels` channels by the gains, in place.
+    pub(crate) fn apply(&self, frames: &mut [f32], channels: usize) {
+        if !self.active.load(Ordering::Acquire) || channels == 0 || channels > self.gains.len() {
+            return;
+        }
+        for frame in frames.chunks_exact_mut(channels) {
+            for (sample, gain) in frame.iter_mut().zip(self.gains.iter()) {
+                *sample *= f32::from_bits(gain.load(Ordering::Relaxed));
+            }
+        }
+    }
+}
+
+// As many channels as miniaudio supports, for gains set before the channel count is known
+impl Default for ChannelGains {
+    fn default() -> Self {
+        Self::new(sys::MA_MAX_CHANNELS)
+    }
+}
+
+struct ChannelGainProcessor {
+    gains: Arc<ChannelGains>,
+    channels: usize,
+}
+
+impl EffectCallback for ChannelGainProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        frames_out.copy_from_slice(frames_in);
+        self.gains.apply(frames_out, self.channels);
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`ChannelGainNode`]
+pub struct ChannelGainNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> ChannelGainNodeBuilder<'a, N> {
+    /// Creates a builder for a node with every gain at `1.0`.
+    pub fn new(node_graph: &'a N, channels: u32) -> Self {
+        Self {
+            channels,
+            node_graph,
+        }
+    }
+
+    pub fn build(&self) -> MaResult<ChannelGainNode> {
+        if self.channels == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let gains = Arc::new(ChannelGains::new(self.channels));
+        let processor = ChannelGainProcessor {
+            gains: gains.clone(),
+            channels: self.channels as usize,
+        };
+
+        let node = NodeBuilder::effect()
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels)
+            .build(self.node_graph, processor)?;
+
+        Ok(ChannelGainNode {
+            node,
+            gains,
+            channels: self.channels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{effects::channel_gain::ChannelGainNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+        },
+    };
+
+    #[test]
+    fn test_channel_gain_node_scales_each_channel() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut node = ChannelGainNodeBuilder::new(&node_graph, 2).build().unwrap();
+        assert_eq!(node.gain(1), Some(1.0));
+        assert_eq!(node.gain(2), None);
+
+        node.set_gain(0, 0.0).unwrap();
+        node.set_gain(1, 0.5).unwrap();
+        assert!(node.set_gain(2, 1.0).is_err());
+        assert!(node.set_gain(0, -1.0).is_err());
+        assert!(node.set_gain(0, f32::NAN).is_err());
+        assert_eq!(node.gain(0), Some(0.0));
+
+        let buffer = AudioBufferBuilder::build_f32(2, &[0.8f32; 2048]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut node.as_node(), 0)
+            .unwrap();
+        node.as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        for frame in out.as_ref().chunks_exact(2).skip(1) {
+            assert_eq!(frame[0], 0.0);
+            assert!((frame[1] - 0.4).abs() < 1e-5);
+        }
+    }
+}
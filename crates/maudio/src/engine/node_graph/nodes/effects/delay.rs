@@ -223,6 +223,7 @@ pub(crate) mod n_delay_ffi {
 impl Drop for DelayNode {
     fn drop(&mut self) {
         n_delay_ffi::ma_delay_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -0,0 +1,266 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use crate::{
+    audio::dsp::shaped_fader::{FadeCurve, ShapedFader},
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult,
+};
+
+/// A gain node that fades its input with a [`FadeCurve`].
+///
+/// miniaudio's fades on sounds and groups are always linear. Routing audio through a
+/// `FadeNode` gives equal power, exponential, s-curve or custom fades instead, which
+/// sound more natural for fade outs and crossfades.
+///
+/// Fades are started from the control thread with [`FadeNode::set_fade_pcm`] and begin on
+/// the next processing callback. The processing is done by [`ShapedFader`].
+///
+/// Use [`FadeNodeBuilder`] to initialize
+pub struct FadeNode {
+    node: Node<Effect<FadeProcessor>>,
+    shared: Arc<FadeShared>,
+    channels: u32,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for FadeNode {
+    type __PtrProvider = private_node::FadeNodeProvider;
+}
+
+impl FadeNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Starts a fade from `vol_start` to `vol_end` over `fade_length_frames` PCM frames.
+    ///
+    /// A negative `vol_start` starts from the current volume. A new fade replaces the one
+    /// in progress.
+    pub fn set_fade_pcm(
+        &mut self,
+        vol_start: f32,
+        vol_end: f32,
+        fade_length_frames: u64,
+        curve: FadeCurve,
+    ) {
+        let mut pending = self
+            .shared
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *pending = Some(FadeCommand {
+            vol_start,
+            vol_end,
+            length: fade_length_frames,
+            curve,
+        });
+        self.shared.version.fetch_add(1, Ordering::Release);
+    }
+
+    /// Returns the volume applied by the node at the end of the last processing callback.
+    pub fn current_volume(&self) -> f32 {
+        f32::from_bits(self.shared.current.load(Ordering::Relaxed))
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct FadeCommand {
+    vol_start: f32,
+    vol_end: f32,
+    length: u64,
+    curve: FadeCurve,
+}
+
+// State shared between the control thread and the audio thread.
+// The audio thread only takes the lock when `version` changed, and never waits for it.
+struct FadeShared {
+    pending: Mutex<Option<FadeCommand>>,
+    version: AtomicU32,
+    current: AtomicU32,
+}
+
+struct FadeProcessor {
+    fader: ShapedFader,
+    shared: Arc<FadeShared>,
+    version: u32,
+}
+
+impl FadeProcessor {
+    fn apply_pending(&mut self) {
+        let version = self.shared.version.load(Ordering::Acquire);
+        if version == self.version {
+            return;
+        }
+        // Retried on the next callback if the control thread holds the lock
+        let Ok(mut pending) = self.shared.pending.try_lock() else {
+            return;
+        };
+        self.version = version;
+        if let Some(cmd) = pending.take() {
+            self.fader
+                .set_fade(cmd.vol_start, cmd.vol_end, cmd.length, cmd.curve);
+        }
+    }
+}
+
+impl EffectCallback for FadeProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        self.apply_pending();
+
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        self.fader.process_pcm_frames(frames_out, frames_in)?;
+        self.shared
+            .current
+            .store(self.fader.current_volume().to_bits(), Ordering::Relaxed);
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`FadeNode`]
+pub struct FadeNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> FadeNodeBuilder<'a, N> {
+    /// Creates a builder for a node at full volume.
+    pub fn new(node_graph: &'a N, channels: u32) -> Self {
+        Self {
+            channels,
+            node_graph,
+        }
+    }
+
+    pub fn build(&self) -> MaResult<FadeNode> {
+        let fader = ShapedFader::new(self.channels)?;
+        let shared = Arc::new(FadeShared {
+            pending: Mutex::new(None),
+            version: AtomicU32::new(0),
+            current: AtomicU32::new(fader.current_volume().to_bits()),
+        });
+
+        let processor = FadeProcessor {
+            fader,
+            shared: shared.clone(),
+            version: 0,
+        };
+
+        let node = NodeBuilder::effect()
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels)
+            .build(self.node_graph, processor)?;
+
+        Ok(FadeNode {
+            node,
+            shared,
+            channels: self.channels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::{dsp::shaped_fader::FadeCurve, sample_rate::SampleRate},
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{effects::fade::FadeNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+        },
+    };
+
+    #[test]
+    fn test_fade_node_equal_power_fade_out() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut fade = FadeNodeBuilder::new(&node_graph, 1).build().unwrap();
+        assert_eq!(fade.current_volume(), 1.0);
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[1.0f32; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut fade.as_node(), 0)
+            .unwrap();
+        fade.as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        fade.set_fade_pcm(1.0, 0.0, 1000, FadeCurve::EqualPower);
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(1200).unwrap();
+        let out = out.as_ref();
+
+        // cos(pi / 4) half way through
+        assert!((out[499] - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01);
+        assert!(out[1000..].iter().all(|s| *s == 0.0));
+        assert_eq!(fade.current_volume(), 0.0);
+    }
+
+    #[test]
+    fn test_fade_node_basic_init() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let node = FadeNodeBuilder::new(&node_graph, 2).build().unwrap();
+        assert_eq!(node.channels(), 2);
+        assert_eq!(node.as_node().in_bus_count(), 1);
+        assert!(FadeNodeBuilder::new(&node_graph, 0).build().is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::{
+    audio::{
+        dsp::noise_gate::{NoiseGate, NoiseGateBuilder},
+        sample_rate::SampleRate,
+    },
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult,
+};
+
+/// A node that applies a noise gate to an audio signal.
+///
+/// The gate mutes its input while the level stays below the open threshold and lets it
+/// through once the level rises above it. A separate, lower close threshold adds
+/// hysteresis, and attack, hold and release times shape how the gate opens and closes.
+/// An optional lookahead lets the gate open ahead of transients, at the cost of latency.
+///
+/// Typical uses are drum buses and noisy inputs such as microphones or line recordings.
+///
+/// The processing is done by [`NoiseGate`]. Parameters can be changed at any time from the
+/// control thread and are picked up by the audio thread on the next processing callback.
+/// The lookahead is fixed when the node is built.
+///
+/// Use [`GateNodeBuilder`] to initialize
+pub struct GateNode {
+    node: Node<Effect<GateProcessor>>,
+    params: Arc<GateParams>,
+    channels: u32,
+    sample_rate: SampleRate,
+    latency: u32,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for GateNode {
+    type __PtrProvider = private_node::GateNodeProvider;
+}
+
+impl GateNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Returns `true` while the gate is open.
+    ///
+    /// Updated by the audio thread at the end of each processing callback.
+    /// Useful for metering.
+    pub fn is_open(&self) -> bool {
+        self.params.is_open.load(Ordering::Relaxed)
+    }
+
+    /// Returns the open threshold in dB.
+    pub fn open_threshold_db(&self) -> f32 {
+        load_f32(&self.params.open_db)
+    }
+
+    /// Returns the close threshold in dB.
+    pub fn close_threshold_db(&self) -> f32 {
+        load_f32(&self.params.close_db)
+    }
+
+    /// Sets the open and close thresholds in dB.
+    ///
+    /// `close_db` is clamped so it is never above `open_db`.
+    pub fn set_thresholds_db(&mut self, open_db: f32, close_db: f32) {
+        store_f32(&self.params.open_db, open_db);
+        store_f32(&self.params.close_db, close_db.min(open_db));
+        self.params.bump();
+    }
+
+    pub fn attack_ms(&self) -> f32 {
+        load_f32(&self.params.attack_ms)
+    }
+
+    /// Sets the time for the gate to fully open, in milliseconds.
+    pub fn set_attack_ms(&mut self, ms: f32) {
+        store_f32(&self.params.attack_ms, ms.max(0.0));
+        self.params.bump();
+    }
+
+    pub fn hold_ms(&self) -> f32 {
+        load_f32(&self.params.hold_ms)
+    }
+
+    /// Sets how long the gate stays open after the level drops below the close threshold.
+    pub fn set_hold_ms(&mut self, ms: f32) {
+        store_f32(&self.params.hold_ms, ms.max(0.0));
+        self.params.bump();
+    }
+
+    pub fn release_ms(&self) -> f32 {
+        load_f32(&self.params.release_ms)
+    }
+
+    /// Sets the time for the gate to fully close, in milliseconds.
+    pub fn set_release_ms(&mut self, ms: f32) {
+        store_f32(&self.params.release_ms, ms.max(0.0));
+        self.params.bump();
+    }
+
+    /// Returns the latency introduced by the lookahead, in frames.
+    pub fn latency(&self) -> u32 {
+        self.latency
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+// Parameters shared between the control thread and the audio thread.
+// `version` is bumped on every change so the processor only re-applies them when needed.
+struct GateParams {
+    open_db: AtomicU32,
+    close_db: AtomicU32,
+    attack_ms: AtomicU32,
+    hold_ms: AtomicU32,
+    release_ms: AtomicU32,
+    version: AtomicU32,
+    is_open: AtomicBool,
+}
+
+impl GateParams {
+    #[inline]
+    fn bump(&self) {
+        self.version.fetch_add(1, Ordering::Release);
+    }
+}
+
+#[inline]
+fn load_f32(v: &AtomicU32) -> f32 {
+    f32::from_bits(v.load(Ordering::Relaxed))
+}
+
+#[inline]
+fn store_f32(v: &AtomicU32, value: f32) {
+    v.store(value.to_bits(), Ordering::Relaxed);
+}
+
+struct GateProcessor {
+    gate: NoiseGate,
+    params: Arc<GateParams>,
+    version: u32,
+}
+
+impl GateProcessor {
+    fn apply_params(&mut self) {
+        let version = self.params.version.load(Ordering::Acquire);
+        if version == self.version {
+            return;
+        }
+        self.version = version;
+        self.gate.set_thresholds_db(
+            load_f32(&self.params.open_db),
+            load_f32(&self.params.close_db),
+        );
+        self.gate.set_attack_ms(load_f32(&self.params.attack_ms));
+        self.gate.set_hold_ms(load_f32(&self.params.hold_ms));
+        self.gate.set_release_ms(load_f32(&self.params.release_ms));
+    }
+}
+
+impl EffectCallback for GateProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        self.apply_params();
+
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        self.gate.process_pcm_frames(frames_out, frames_in)?;
+        self.params
+            .is_open
+            .store(self.gate.is_open(), Ordering::Relaxed);
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`GateNode`]
+pub struct GateNodeBuilder<'a, N: AsNodeGraphPtr> {
+    gate: NoiseGateBuilder,
+    channels: u32,
+    sample_rate: SampleRate,
+    open_db: f32,
+    close_db: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> GateNodeBuilder<'a, N> {
+    /// Creates a builder with an open threshold of -40 dB, a close threshold of -50 dB,
+    /// 1 ms attack, 50 ms hold, 100 ms release and no lookahead.
+    pub fn new(node_graph: &'a N, channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            gate: NoiseGateBuilder::new(channels, sample_rate),
+            channels,
+            sample_rate,
+            open_db: -40.0,
+            close_db: -50.0,
+            attack_ms: 1.0,
+            hold_ms: 50.0,
+            release_ms: 100.0,
+            node_graph,
+        }
+    }
+
+    /// Sets the level (dB) at which the gate opens.
+    pub fn open_threshold_db(&mut self, db: f32) -> &mut Self {
+        self.open_db = db;
+        self.gate.open_threshold_db(db);
+        self
+    }
+
+    /// Sets the level (dB) below which the gate starts closing.
+    pub fn close_threshold_db(&mut self, db: f32) -> &mut Self {
+        self.close_db = db;
+        self.gate.close_threshold_db(db);
+        self
+    }
+
+    pub fn attack_ms(&mut self, ms: f32) -> &mut Self {
+        self.attack_ms = ms;
+        self.gate.attack_ms(ms);
+        self
+    }
+
+    pub fn hold_ms(&mut self, ms: f32) -> &mut Self {
+        self.hold_ms = ms;
+        self.gate.hold_ms(ms);
+        self
+    }
+
+    pub fn release_ms(&mut self, ms: f32) -> &mut Self {
+        self.release_ms = ms;
+        self.gate.release_ms(ms);
+        self
+    }
+
+    /// Sets the lookahead time in milliseconds. Adds the same amount of latency.
+    pub fn lookahead_ms(&mut self, ms: f32) -> &mut Self {
+        self.gate.lookahead_ms(ms);
+        self
+    }
+
+    pub fn build(&self) -> MaResult<GateNode> {
+        let gate = self.gate.build()?;
+        let latency = gate.latency();
+
+        let params = Arc::new(GateParams {
+            open_db: AtomicU32::new(self.open_db.to_bits()),
+            close_db: AtomicU32::new(self.close_db.min(self.open_db).to_bits()),
+            attack_ms: AtomicU32::new(self.attack_ms.to_bits()),
+            hold_ms: AtomicU32::new(self.hold_ms.to_bits()),
+            release_ms: AtomicU32::new(self.release_ms.to_bits()),
+            version: AtomicU32::new(0),
+            is_open: AtomicBool::new(false),
+        });
+
+        let processor = GateProcessor {
+            gate,
+            params: params.clone(),
+            version: 0,
+        };
+
+        let mut builder = NodeBuilder::effect();
+        builder
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels);
+        // Keeps the lookahead buffer draining after the input stops.
+        if latency > 0 {
+            builder.continuous_processing();
+        }
+        let node = builder.build(self.node_graph, processor)?;
+
+        Ok(GateNode {
+            node,
+            params,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            latency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{
+            node_graph::nodes::{effects::gate::GateNodeBuilder, NodeOps},
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_gate_node_basic_init() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let node = GateNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+            .lookahead_ms(1.0)
+            .build()
+            .unwrap();
+
+        assert_eq!(node.channels(), 2);
+        assert_eq!(node.latency(), 48);
+        assert!(!node.is_open());
+        assert_eq!(node.as_node().in_bus_count(), 1);
+        assert_eq!(node.as_node().out_bus_count(), 1);
+    }
+
+    #[test]
+    fn test_gate_node_setters_roundtrip() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut node = GateNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+
+        node.set_thresholds_db(-30.0, -20.0);
+        assert_eq!(node.open_threshold_db(), -30.0);
+        // Close threshold is clamped to the open threshold
+        assert_eq!(node.close_threshold_db(), -30.0);
+
+        node.set_attack_ms(2.0);
+        node.set_hold_ms(10.0);
+        node.set_release_ms(-5.0);
+        assert_eq!(node.attack_ms(), 2.0);
+        assert_eq!(node.hold_ms(), 10.0);
+        assert_eq!(node.release_ms(), 0.0);
+    }
+
+    #[test]
+    fn test_gate_node_invalid_args() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        assert!(GateNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .lookahead_ms(-1.0)
+            .build()
+            .is_err());
+    }
+}
@@ -1,2 +1,8 @@
 //! Effect node implementations - `effect`.
+pub mod bitcrusher;
+pub mod channel_gain;
 pub mod delay;
+pub mod fade;
+pub mod gate;
+pub mod pitch_shift;
+pub mod ring_mod;
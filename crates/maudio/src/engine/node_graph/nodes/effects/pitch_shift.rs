@@ -0,0 +1,239 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::{
+    audio::{
+        dsp::pitch_shifter::{PitchShifter, PitchShifterBuilder, MAX_SEMITONES},
+        sample_rate::SampleRate,
+    },
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult,
+};
+
+/// A node that shifts the pitch of an audio signal without changing its speed.
+///
+/// [`Sound::set_pitch`](crate::sound::Sound::set_pitch) resamples the sound, so raising the
+/// pitch also makes it play faster. Routing a sound through a `PitchShiftNode` instead
+/// keeps the timing intact, which is what voice changers and music transposition need.
+///
+/// The processing is done by [`PitchShifter`]. The shift can be changed at any time from
+/// the control thread and is picked up by the audio thread on the next processing
+/// callback. The window length is fixed when the node is built.
+///
+/// Use [`PitchShiftNodeBuilder`] to initialize
+pub struct PitchShiftNode {
+    node: Node<Effect<PitchShiftProcessor>>,
+    semitones: Arc<AtomicU32>,
+    channels: u32,
+    sample_rate: SampleRate,
+    latency: u32,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for PitchShiftNode {
+    type __PtrProvider = private_node::PitchShiftNodeProvider;
+}
+
+impl PitchShiftNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Returns the pitch shift in semitones.
+    pub fn semitones(&self) -> f32 {
+        f32::from_bits(self.semitones.load(Ordering::Relaxed))
+    }
+
+    /// Sets the pitch shift in semitones. Positive values raise the pitch.
+    ///
+    /// Clamped to ±[`MAX_SEMITONES`]. Non-finite values are ignored.
+    pub fn set_semitones(&mut self, semitones: f32) {
+        if !semitones.is_finite() {
+            return;
+        }
+        let semitones = semitones.clamp(-MAX_SEMITONES, MAX_SEMITONES);
+        self.semitones.store(semitones.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the average latency introduced by the shifter, in frames.
+    pub fn latency(&self) -> u32 {
+        self.latency
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+struct PitchShiftProcessor {
+    shifter: PitchShifter,
+    semitones: Arc<AtomicU32>,
+}
+
+impl EffectCallback for PitchShiftProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        let semitones = f32::from_bits(self.semitones.load(Ordering::Relaxed));
+        if semitones != self.shifter.semitones() {
+            self.shifter.set_semitones(semitones);
+        }
+
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        self.shifter.process_pcm_frames(frames_out, frames_in)?;
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`PitchShiftNode`]
+pub struct PitchShiftNodeBuilder<'a, N: AsNodeGraphPtr> {
+    shifter: PitchShifterBuilder,
+    channels: u32,
+    sample_rate: SampleRate,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> PitchShiftNodeBuilder<'a, N> {
+    /// Creates a builder with no shift and a 50 ms window.
+    pub fn new(node_graph: &'a N, channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            shifter: PitchShifterBuilder::new(channels, sample_rate),
+            channels,
+            sample_rate,
+            node_graph,
+        }
+    }
+
+    /// Sets the initial pitch shift in semitones.
+    pub fn semitones(&mut self, semitones: f32) -> &mut Self {
+        self.shifter.semitones(semitones);
+        self
+    }
+
+    /// Sets the window length in milliseconds. Must be between 5 and 500 ms.
+    pub fn window_ms(&mut self, ms: f32) -> &mut Self {
+        self.shifter.window_ms(ms);
+        self
+    }
+
+    pub fn build(&self) -> MaResult<PitchShiftNode> {
+        let shifter = self.shifter.build()?;
+        let latency = shifter.latency();
+        let semitones = Arc::new(AtomicU32::new(shifter.semitones().to_bits()));
+
+        let processor = PitchShiftProcessor {
+            shifter,
+            semitones: semitones.clone(),
+        };
+
+        let mut builder = NodeBuilder::effect();
+        builder
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels)
+            // Keeps the delay line draining after the input stops.
+            .continuous_processing();
+        let node = builder.build(self.node_graph, processor)?;
+
+        Ok(PitchShiftNode {
+            node,
+            semitones,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            latency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{
+            node_graph::nodes::{effects::pitch_shift::PitchShiftNodeBuilder, NodeOps},
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_pitch_shift_node_basic_init() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let node = PitchShiftNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+            .semitones(-5.0)
+            .window_ms(20.0)
+            .build()
+            .unwrap();
+
+        assert_eq!(node.channels(), 2);
+        assert_eq!(node.semitones(), -5.0);
+        assert_eq!(node.latency(), 480);
+        assert_eq!(node.as_node().in_bus_count(), 1);
+        assert_eq!(node.as_node().out_bus_count(), 1);
+    }
+
+    #[test]
+    fn test_pitch_shift_node_set_semitones() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut node = PitchShiftNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+
+        node.set_semitones(7.0);
+        assert_eq!(node.semitones(), 7.0);
+        node.set_semitones(100.0);
+        assert_eq!(node.semitones(), 24.0);
+        node.set_semitones(f32::NAN);
+        assert_eq!(node.semitones(), 24.0);
+
+        assert!(
+            PitchShiftNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+                .window_ms(0.0)
+                .build()
+                .is_err()
+        );
+    }
+}
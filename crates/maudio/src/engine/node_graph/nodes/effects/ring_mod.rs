@@ -0,0 +1,317 @@
+use std::{
+    f64::consts::TAU,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    audio::sample_rate::SampleRate,
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult, MaudioError,
+};
+
+/// A node that multiplies an audio signal by a sine wave carrier.
+///
+/// Ring modulation replaces each frequency in the input with the sum and difference of
+/// that frequency and the carrier's. Low carrier frequencies (below ~20 Hz) give a tremolo,
+/// higher ones give the metallic, bell-like tones used for robot voices and sci-fi effects.
+///
+/// The processing is done entirely in Rust inside the node callback, which makes this node
+/// a small, self-contained example of the custom node API. The carrier phase carries over
+/// between callbacks, so changing the frequency does not click. Parameters can be changed
+/// at any time from the control thread and are picked up on the next processing callback.
+///
+/// Use [`RingModNodeBuilder`] to initialize
+pub struct RingModNode {
+    node: Node<Effect<RingModProcessor>>,
+    params: Arc<RingModParams>,
+    channels: u32,
+    sample_rate: SampleRate,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for RingModNode {
+    type __PtrProvider = private_node::RingModNodeProvider;
+}
+
+impl RingModNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Returns the carrier frequency in Hz.
+    pub fn frequency(&self) -> f32 {
+        f32::from_bits(self.params.frequency.load(Ordering::Relaxed))
+    }
+
+    /// Sets the carrier frequency in Hz.
+    ///
+    /// Clamped to half the node's sample rate. Values that are not finite or are negative
+    /// are ignored.
+    pub fn set_frequency(&mut self, frequency: f32) {
+        if !frequency.is_finite() || frequency < 0.0 {
+            return;
+        }
+        let frequency = frequency.min(u32::from(self.sample_rate) as f32 / 2.0);
+        self.params
+            .frequency
+            .store(frequency.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the amount of modulated signal in the output.
+    pub fn mix(&self) -> f32 {
+        f32::from_bits(self.params.mix.load(Ordering::Relaxed))
+    }
+
+    /// Sets the amount of modulated signal in the output, from 0.0 (dry) to 1.0 (fully
+    /// modulated).
+    ///
+    /// Clamped to 0.0..=1.0. Non-finite values are ignored.
+    pub fn set_mix(&mut self, mix: f32) {
+        if !mix.is_finite() {
+            return;
+        }
+        self.params
+            .mix
+            .store(mix.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+struct RingModParams {
+    frequency: AtomicU32,
+    mix: AtomicU32,
+}
+
+struct RingModProcessor {
+    params: Arc<RingModParams>,
+    channels: usize,
+    sample_rate: f64,
+    phase: f64,
+}
+
+impl EffectCallback for RingModProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        let frequency = f32::from_bits(self.params.frequency.load(Ordering::Relaxed));
+        let mix = f32::from_bits(self.params.mix.load(Ordering::Relaxed));
+        let increment = TAU * frequency as f64 / self.sample_rate;
+
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+
+        for (frame_in, frame_out) in frames_in
+            .chunks_exact(self.channels)
+            .zip(frames_out.chunks_exact_mut(self.channels))
+        {
+            let gain = 1.0 - mix + mix * self.phase.sin() as f32;
+            for (out, sample) in frame_out.iter_mut().zip(frame_in) {
+                *out = sample * gain;
+            }
+            self.phase = (self.phase + increment) % TAU;
+        }
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`RingModNode`]
+pub struct RingModNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    sample_rate: SampleRate,
+    frequency: f32,
+    mix: f32,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> RingModNodeBuilder<'a, N> {
+    /// Creates a builder with a 440 Hz carrier and a fully modulated output.
+    pub fn new(node_graph: &'a N, channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            frequency: 440.0,
+            mix: 1.0,
+            node_graph,
+        }
+    }
+
+    /// Sets the initial carrier frequency in Hz. Must be between 0 and half the sample rate.
+    pub fn frequency(&mut self, frequency: f32) -> &mut Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Sets the initial amount of modulated signal. Must be between 0.0 and 1.0.
+    pub fn mix(&mut self, mix: f32) -> &mut Self {
+        self.mix = mix;
+        self
+    }
+
+    pub fn build(&self) -> MaResult<RingModNode> {
+        let sample_rate: u32 = self.sample_rate.into();
+        if self.channels == 0
+            || !self.frequency.is_finite()
+            || !(0.0..=sample_rate as f32 / 2.0).contains(&self.frequency)
+            || !(0.0..=1.0).contains(&self.mix)
+        {
+            return Err(MaudioError::from_ma_result(
+                maudio_sys::ffi::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+
+        let params = Arc::new(RingModParams {
+            frequency: AtomicU32::new(self.frequency.to_bits()),
+            mix: AtomicU32::new(self.mix.to_bits()),
+        });
+
+        let processor = RingModProcessor {
+            params: params.clone(),
+            channels: self.channels as usize,
+            sample_rate: sample_rate as f64,
+            phase: 0.0,
+        };
+
+        let mut builder = NodeBuilder::effect();
+        builder
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels);
+        let node = builder.build(self.node_graph, processor)?;
+
+        Ok(RingModNode {
+            node,
+            params,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{effects::ring_mod::RingModNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_ring_mod_node_basic_init() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let mut node = RingModNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+            .frequency(30.0)
+            .mix(0.5)
+            .build()
+            .unwrap();
+
+        assert_eq!(node.channels(), 2);
+        assert_eq!(node.frequency(), 30.0);
+        assert_eq!(node.mix(), 0.5);
+        assert_eq!(node.as_node().in_bus_count(), 1);
+
+        node.set_frequency(100_000.0);
+        assert_eq!(node.frequency(), 24000.0);
+        node.set_frequency(f32::NAN);
+        assert_eq!(node.frequency(), 24000.0);
+        node.set_mix(2.0);
+        assert_eq!(node.mix(), 1.0);
+
+        assert!(RingModNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .mix(1.5)
+            .build()
+            .is_err());
+        assert!(RingModNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .frequency(-1.0)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_ring_mod_node_modulates_dc() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let ring_mod = RingModNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .frequency(1000.0)
+            .build()
+            .unwrap();
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[1.0f32; 4096]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut ring_mod.as_node(), 0)
+            .unwrap();
+        ring_mod
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(960).unwrap();
+        let out = out.as_ref();
+
+        // A constant input comes out as the carrier itself, 48 frames per cycle.
+        for (i, s) in out.iter().enumerate().skip(1) {
+            let expected = (std::f64::consts::TAU * i as f64 / 48.0).sin() as f32;
+            assert!((s - expected).abs() < 1e-3, "frame {i}: {s} != {expected}");
+        }
+    }
+}
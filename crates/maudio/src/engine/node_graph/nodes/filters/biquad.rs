@@ -190,6 +190,7 @@ pub(crate) mod n_biquad_ffi {
 impl Drop for BiquadNode {
     fn drop(&mut self) {
         n_biquad_ffi::ma_biquad_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -3,7 +3,10 @@ use std::{mem::MaybeUninit, sync::Arc};
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{formats::Format, sample_rate::SampleRate},
+    audio::{
+        dsp::filters::biquad_filter::biquad_frequency_response, formats::Format,
+        sample_rate::SampleRate,
+    },
     engine::{
         node_graph::{
             nodes::{node_ffi, private_node::HiShelfNodeProvider, AsNodePtr, NodeRef},
@@ -46,6 +49,7 @@ pub struct HiShelfNode {
     // format is hard coded as ma_format_f32 in miniaudio `sys::ma_hishelf_node_config_init()`
     // but use value in inner.hishelf.format anyway inside new_with_cfg_alloc_internal()
     format: Format,
+    sample_rate: SampleRate,
 }
 
 unsafe impl Send for HiShelfNode {}
@@ -72,6 +76,9 @@ impl HiShelfNode {
         let alloc_cb: *const sys::ma_allocation_callbacks =
             alloc.clone().map_or(core::ptr::null(), |c| c.as_raw_ptr());
 
+        // Convert before init so an error can not leak the initialized node
+        let sample_rate: SampleRate = config.inner.hishelf.sampleRate.try_into()?;
+
         let mut mem: Box<std::mem::MaybeUninit<sys::ma_hishelf_node>> =
             Box::new(MaybeUninit::uninit());
 
@@ -95,6 +102,7 @@ impl HiShelfNode {
                 .format
                 .try_into()
                 .unwrap_or(Format::F32),
+            sample_rate,
         })
     }
 
@@ -137,7 +145,15 @@ impl HiShelfNode {
         }
 
         let params = HiShelfNodeParams::new(self, sample_rate, gain_db, shelf_slope, frequency);
-        n_hishelf_ffi::ma_hishelf_node_reinit(params.as_raw_ptr(), self)
+        n_hishelf_ffi::ma_hishelf_node_reinit(params.as_raw_ptr(), self)?;
+        self.sample_rate = sample_rate;
+        Ok(())
+    }
+
+    /// Returns the linear gain of the high shelf at each frequency in `freqs` (Hz).
+    pub fn frequency_response(&self, freqs: &[f32]) -> Vec<f32> {
+        let bq = unsafe { &(*self.to_raw()).hishelf.bq };
+        biquad_frequency_response(bq, self.sample_rate.into(), freqs)
     }
 
     /// Returns a **borrowed view** as a node in the engine's node graph.
@@ -211,6 +227,7 @@ pub(crate) mod n_hishelf_ffi {
 impl Drop for HiShelfNode {
     fn drop(&mut self) {
         n_hishelf_ffi::ma_hishelf_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -478,4 +495,18 @@ mod test {
         let res = node.reinit(SampleRate::Sr48000, 0.0, 0.5, f64::NAN);
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_hishelf_frequency_response_boosts_highs() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        let node = HiShelfNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000, 6.0, 1.0, 2000.0)
+            .build()
+            .unwrap();
+
+        let response = node.frequency_response(&[20.0, 20_000.0]);
+        assert!((response[0] - 1.0).abs() < 0.05);
+        assert!(20.0 * response[1].log10() > 5.0);
+    }
 }
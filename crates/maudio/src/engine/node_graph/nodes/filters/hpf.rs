@@ -189,6 +189,7 @@ pub(crate) mod n_hpf_ffi {
 impl Drop for HpfNode {
     fn drop(&mut self) {
         n_hpf_ffi::ma_hpf_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
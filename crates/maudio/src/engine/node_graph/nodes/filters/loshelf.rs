@@ -3,7 +3,10 @@ use std::{mem::MaybeUninit, sync::Arc};
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{formats::Format, sample_rate::SampleRate},
+    audio::{
+        dsp::filters::biquad_filter::biquad_frequency_response, formats::Format,
+        sample_rate::SampleRate,
+    },
     engine::{
         node_graph::{
             nodes::{node_ffi, private_node::LoShelfNodeProvider, AsNodePtr, NodeRef},
@@ -46,6 +49,7 @@ pub struct LoShelfNode {
     // format is hard coded as ma_format_f32 in miniaudio `sys::ma_loshelf_node_config_init()`
     // but use value in inner.loshelf.format anyway inside new_with_cfg_alloc_internal()
     format: Format,
+    sample_rate: SampleRate,
 }
 
 unsafe impl Send for LoShelfNode {}
@@ -72,6 +76,9 @@ impl LoShelfNode {
         let alloc_cb: *const sys::ma_allocation_callbacks =
             alloc.clone().map_or(core::ptr::null(), |c| c.as_raw_ptr());
 
+        // Convert before init so an error can not leak the initialized node
+        let sample_rate: SampleRate = config.inner.loshelf.sampleRate.try_into()?;
+
         let mut mem: Box<std::mem::MaybeUninit<sys::ma_loshelf_node>> =
             Box::new(MaybeUninit::uninit());
 
@@ -95,6 +102,7 @@ impl LoShelfNode {
                 .format
                 .try_into()
                 .unwrap_or(Format::F32),
+            sample_rate,
         })
     }
     /// Returns the owning engine, if any.
@@ -136,7 +144,15 @@ impl LoShelfNode {
         }
 
         let params = LoShelfNodeParams::new(self, sample_rate, gain_db, shelf_slope, frequency);
-        n_loshelf_ffi::ma_loshelf_node_reinit(params.as_raw_ptr(), self)
+        n_loshelf_ffi::ma_loshelf_node_reinit(params.as_raw_ptr(), self)?;
+        self.sample_rate = sample_rate;
+        Ok(())
+    }
+
+    /// Returns the linear gain of the low shelf at each frequency in `freqs` (Hz).
+    pub fn frequency_response(&self, freqs: &[f32]) -> Vec<f32> {
+        let bq = unsafe { &(*self.to_raw()).loshelf.bq };
+        biquad_frequency_response(bq, self.sample_rate.into(), freqs)
     }
 
     /// Returns a **borrowed view** as a node in the engine's node graph.
@@ -210,6 +226,7 @@ pub(crate) mod n_loshelf_ffi {
 impl Drop for LoShelfNode {
     fn drop(&mut self) {
         n_loshelf_ffi::ma_loshelf_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -476,4 +493,23 @@ mod test {
             .reinit(SampleRate::Sr48000, 0.0, 0.5, f64::NAN)
             .is_err());
     }
+
+    #[test]
+    fn test_loshelf_frequency_response_tracks_reinit() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        let mut node =
+            LoShelfNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000, 0.0, 1.0, 200.0)
+                .build()
+                .unwrap();
+
+        let flat = node.frequency_response(&[20.0, 10_000.0]);
+        assert!(flat.iter().all(|g| (g - 1.0).abs() < 0.01));
+
+        node.reinit(SampleRate::Sr44100, -12.0, 1.0, 200.0).unwrap();
+        let cut = node.frequency_response(&[20.0, 10_000.0]);
+        assert!(20.0 * cut[0].log10() < -10.0);
+        assert!((cut[1] - 1.0).abs() < 0.05);
+    }
 }
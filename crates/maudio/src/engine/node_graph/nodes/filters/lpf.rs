@@ -196,6 +196,7 @@ pub(crate) mod n_lpf_ffi {
 impl Drop for LpfNode {
     fn drop(&mut self) {
         n_lpf_ffi::ma_lpf_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
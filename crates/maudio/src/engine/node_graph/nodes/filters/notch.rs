@@ -3,7 +3,10 @@ use std::{mem::MaybeUninit, sync::Arc};
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{formats::Format, sample_rate::SampleRate},
+    audio::{
+        dsp::filters::biquad_filter::biquad_frequency_response, formats::Format,
+        sample_rate::SampleRate,
+    },
     engine::{
         node_graph::{
             nodes::{node_ffi, private_node::NotchNodeProvider, AsNodePtr, NodeRef},
@@ -73,6 +76,9 @@ impl NotchNode {
         let alloc_cb: *const sys::ma_allocation_callbacks =
             alloc.clone().map_or(core::ptr::null(), |c| c.as_raw_ptr());
 
+        // Convert before init so an error can not leak the initialized node
+        let sample_rate: SampleRate = config.inner.notch.sampleRate.try_into()?;
+
         let mut mem: Box<std::mem::MaybeUninit<sys::ma_notch_node>> =
             Box::new(MaybeUninit::uninit());
 
@@ -91,7 +97,7 @@ impl NotchNode {
             owner: private_node_graph::clone_owner(node_graph),
             format: config.inner.notch.format.try_into().unwrap_or(Format::F32),
             channels: config.inner.notch.channels,
-            sample_rate: config.inner.notch.sampleRate.try_into()?,
+            sample_rate,
         })
     }
 
@@ -133,6 +139,12 @@ impl NotchNode {
         n_notch_ffi::ma_notch_node_reinit(params.as_raw_ptr(), self)
     }
 
+    /// Returns the linear gain of the notch filter at each frequency in `freqs` (Hz).
+    pub fn frequency_response(&self, freqs: &[f32]) -> Vec<f32> {
+        let bq = unsafe { &(*self.to_raw()).notch.bq };
+        biquad_frequency_response(bq, self.sample_rate.into(), freqs)
+    }
+
     /// Returns a **borrowed view** as a node in the engine's node graph.
     ///
     /// ### What this is for
@@ -204,6 +216,7 @@ pub(crate) mod n_notch_ffi {
 impl Drop for NotchNode {
     fn drop(&mut self) {
         n_notch_ffi::ma_notch_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -384,4 +397,20 @@ mod test {
         // TODO: But is ok?
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn test_notch_frequency_response_attenuates_center() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        let node = NotchNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000, 1.0, 1000.0)
+            .build()
+            .unwrap();
+
+        let response = node.frequency_response(&[1000.0, 20.0, 20_000.0]);
+        assert_eq!(response.len(), 3);
+        assert!(response[0] < 0.01);
+        assert!((response[1] - 1.0).abs() < 0.05);
+        assert!((response[2] - 1.0).abs() < 0.05);
+    }
 }
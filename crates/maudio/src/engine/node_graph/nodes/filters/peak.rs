@@ -3,7 +3,10 @@ use std::{mem::MaybeUninit, sync::Arc};
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{formats::Format, sample_rate::SampleRate},
+    audio::{
+        dsp::filters::biquad_filter::biquad_frequency_response, formats::Format,
+        sample_rate::SampleRate,
+    },
     engine::{
         node_graph::{
             nodes::{node_ffi, private_node::PeakNodeProvider, AsNodePtr, NodeRef},
@@ -76,6 +79,9 @@ impl PeakNode {
         let alloc_cb: *const sys::ma_allocation_callbacks =
             alloc.clone().map_or(core::ptr::null(), |c| c.as_raw_ptr());
 
+        // Convert before init so an error can not leak the initialized node
+        let sample_rate: SampleRate = config.inner.peak.sampleRate.try_into()?;
+
         let mut mem: Box<std::mem::MaybeUninit<sys::ma_peak_node>> =
             Box::new(MaybeUninit::uninit());
 
@@ -89,7 +95,7 @@ impl PeakNode {
             owner: private_node_graph::clone_owner(node_graph),
             format: config.inner.peak.format.try_into().unwrap_or(Format::F32),
             channels: config.inner.peak.channels,
-            sample_rate: config.inner.peak.sampleRate.try_into()?,
+            sample_rate,
         })
     }
 
@@ -117,6 +123,12 @@ impl PeakNode {
         n_peak_ffi::ma_peak_node_reinit(params.as_raw_ptr(), self)
     }
 
+    /// Returns the linear gain of the peaking EQ at each frequency in `freqs` (Hz).
+    pub fn frequency_response(&self, freqs: &[f32]) -> Vec<f32> {
+        let bq = unsafe { &(*self.to_raw()).peak.bq };
+        biquad_frequency_response(bq, self.sample_rate.into(), freqs)
+    }
+
     /// Returns a **borrowed view** as a node in the engine's node graph.
     ///
     /// ### What this is for
@@ -185,6 +197,7 @@ pub(crate) mod n_peak_ffi {
 impl Drop for PeakNode {
     fn drop(&mut self) {
         n_peak_ffi::ma_peak_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -329,4 +342,19 @@ mod test {
 
         node.reinit(1.5, 1.0, 4000.0).unwrap();
     }
+
+    #[test]
+    fn test_peak_frequency_response_matches_gain() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        let node = PeakNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000, 6.0, 1.0, 1000.0)
+            .build()
+            .unwrap();
+
+        let response = node.frequency_response(&[1000.0, 10.0]);
+        let center_db = 20.0 * response[0].log10();
+        assert!((center_db - 6.0).abs() < 0.1);
+        assert!((response[1] - 1.0).abs() < 0.05);
+    }
 }
@@ -0,0 +1,296 @@
+//! Pass-through node that copies everything flowing through it to a ring buffer.
+//!
+//! A [`CaptureNode`] can be inserted anywhere in the node graph to get one submix, for
+//! example only the voice chat or only the music, instead of the whole engine output. The
+//! audio thread writes each processed block into a ring buffer, and the [`CaptureReader`]
+//! reads it back from another thread, to record it or stream it to a network encoder.
+//!
+//! [`SoundGroup::capture`](crate::sound::sound_group::SoundGroup::capture) inserts one after a
+//! sound group.
+//!
+//! Like [`OutputTap`](crate::engine::output_tap::OutputTap), frames are dropped and counted by
+//! [`CaptureReader::overrun_notifier`] when the reader falls behind.
+//!
+//! ```no_run
+//! # use maudio::engine::{Engine, node_graph::nodes::{NodeOps, routing::capture::CaptureNodeBuilder}};
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let node_graph = engine.as_node_graph();
+//! let (capture, mut reader) = CaptureNodeBuilder::new(&node_graph, 2).build()?;
+//! capture.as_node().attach_output(0, &mut engine.endpoint(), 0)?;
+//!
+//! // On the encoder thread
+//! let block = reader.read_available()?;
+//! # let _ = block;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    audio::formats::SampleBuffer,
+    data_source::sources::pcm_ring_buffer::{PcmRbRecv, PcmRbSend, PcmRingBuffer},
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    util::proc_notif::ProcFramesNotif,
+    ErrorKinds, MaResult, MaudioError,
+};
+
+/// A pass-through node copying its input to a [`CaptureReader`].
+///
+/// Use [`CaptureNodeBuilder`] to initialize.
+pub struct CaptureNode {
+    node: Node<Effect<CaptureProcessor>>,
+    channels: u32,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for CaptureNode {
+    type __PtrProvider = private_node::CaptureNodeProvider;
+}
+
+impl CaptureNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+/// Reading end of a [`CaptureNode`].
+pub struct CaptureReader {
+    rx: PcmRbRecv<f32>,
+    overruns: ProcFramesNotif,
+}
+
+impl CaptureReader {
+    /// Reads as many frames as are available into `dst`, as interleaved `f32` samples.
+    ///
+    /// Returns the number of frames read.
+    pub fn read(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        let channels = self.channels() as usize;
+        let mut frames_read = 0;
+        // A single read stops at the end of the ring buffer
+        loop {
+            let n = self.rx.read(&mut dst[frames_read * channels..])?;
+            if n == 0 {
+                break;
+            }
+            frames_read += n;
+        }
+        Ok(frames_read)
+    }
+
+    /// Reads every available frame into a new [`SampleBuffer`].
+    pub fn read_available(&mut self) -> MaResult<SampleBuffer<f32>> {
+        let channels = self.channels();
+        let available = self.available_frames() as usize;
+        let mut storage = SampleBuffer::<f32>::new_zeroed(available, channels)?;
+        let frames = self.read(&mut storage)?;
+        SampleBuffer::from_storage(storage, frames, channels)
+    }
+
+    /// Number of frames that can currently be read.
+    pub fn available_frames(&self) -> u32 {
+        self.rx.available_read()
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.rx.channels()
+    }
+
+    /// Returns a [`ProcFramesNotif`] counting the frames dropped because the reader was full.
+    pub fn overrun_notifier(&self) -> ProcFramesNotif {
+        self.overruns.clone()
+    }
+
+    /// Returns the receive end of the underlying ring buffer.
+    pub fn into_inner(self) -> PcmRbRecv<f32> {
+        self.rx
+    }
+}
+
+struct CaptureProcessor {
+    tx: PcmRbSend<f32>,
+    overruns: ProcFramesNotif,
+}
+
+impl CaptureProcessor {
+    fn capture(&mut self, frames_in: &[f32], channels: usize) {
+        let total = frames_in.len() / channels;
+        let mut written = 0;
+        // A single write stops at the end of the ring buffer
+        while written < total {
+            match self.tx.write(&frames_in[written * channels..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => written += n,
+            }
+        }
+        if written < total {
+            self.overruns.add_frames((total - written) as u64);
+        }
+    }
+}
+
+impl EffectCallback for CaptureProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        let len = frames_in.len().min(frames_out.len());
+        frames_out[..len].copy_from_slice(&frames_in[..len]);
+        self.capture(&frames_in[..len], self.tx.channels() as usize);
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`CaptureNode`]
+pub struct CaptureNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    capacity_frames: u32,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> CaptureNodeBuilder<'a, N> {
+    /// Creates a builder with a ring buffer holding 48000 frames, one second at 48 kHz.
+    pub fn new(node_graph: &'a N, channels: u32) -> Self {
+        Self {
+            channels,
+            capacity_frames: 48_000,
+            node_graph,
+        }
+    }
+
+    /// Sets the size of the ring buffer between the audio thread and the reader, in frames.
+    ///
+    /// Frames that do not fit are dropped.
+    pub fn capacity_frames(&mut self, frames: u32) -> &mut Self {
+        self.capacity_frames = frames;
+        self
+    }
+
+    pub fn build(&self) -> MaResult<(CaptureNode, CaptureReader)> {
+        if self.capacity_frames == 0 {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "capture buffer must hold at least one frame",
+            )));
+        }
+        let (tx, rx) = PcmRingBuffer::new_f32(self.capacity_frames, self.channels)?;
+        let overruns = ProcFramesNotif::default();
+        let processor = CaptureProcessor {
+            tx,
+            overruns: overruns.clone(),
+        };
+        let node = NodeBuilder::effect()
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels)
+            .build(self.node_graph, processor)?;
+
+        Ok((
+            CaptureNode {
+                node,
+                channels: self.channels,
+            },
+            CaptureReader { rx, overruns },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{routing::capture::CaptureNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_capture_node_copies_what_passes_through() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let (capture, mut reader) = CaptureNodeBuilder::new(&node_graph, 1)
+            .capacity_frames(64)
+            .build()
+            .unwrap();
+        capture
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 256]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut capture.as_node(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut out_reader = engine.try_acquire_reader().unwrap();
+        let out = out_reader.read_pcm_frames(48).unwrap();
+        let captured = reader.read_available().unwrap();
+        assert_eq!(captured.frames(), 48);
+        assert_eq!(captured.as_ref(), out.as_ref());
+
+        // The buffer only holds 64 frames
+        out_reader.read_pcm_frames(100).unwrap();
+        assert_eq!(reader.overrun_notifier().take_delta(), 36);
+        assert_eq!(reader.available_frames(), 64);
+    }
+
+    #[test]
+    fn test_capture_node_invalid_args() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        assert!(CaptureNodeBuilder::new(&node_graph, 2)
+            .capacity_frames(0)
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Pass-through node that measures the loudness of everything flowing through it.
+//!
+//! A [`LoudnessNode`] can be inserted anywhere in the node graph, for example before the
+//! endpoint to meter the mix. Audio is passed through unchanged and measured on the audio
+//! thread by a [`LoudnessMeter`]. The measurement is published at the end of each processing
+//! callback and can be read from any thread.
+//!
+//! ```no_run
+//! # use maudio::engine::{Engine, node_graph::nodes::{NodeOps, routing::loudness::LoudnessNodeBuilder}};
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let node_graph = engine.as_node_graph();
+//! let meter = LoudnessNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000).build()?;
+//!
+//! let sound = engine.new_sound_from_file(Path::new("music.ogg"))?;
+//! sound.as_node().attach_output(0, &mut meter.as_node(), 0)?;
+//! meter.as_node().attach_output(0, &mut engine.endpoint(), 0)?;
+//!
+//! // ...
+//!
+//! let loudness = meter.loudness();
+//! println!("{:.1} LUFS, {:.1} dBTP", loudness.short_term, loudness.true_peak);
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::{
+    audio::{
+        dsp::loudness::{Loudness, LoudnessMeter},
+        sample_rate::SampleRate,
+    },
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    MaResult,
+};
+
+/// A pass-through node measuring loudness with a [`LoudnessMeter`].
+///
+/// Use [`LoudnessNodeBuilder`] to initialize.
+pub struct LoudnessNode {
+    node: Node<Effect<LoudnessProcessor>>,
+    shared: Arc<LoudnessShared>,
+    channels: u32,
+    sample_rate: SampleRate,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for LoudnessNode {
+    type __PtrProvider = private_node::LoudnessNodeProvider;
+}
+
+impl LoudnessNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Returns the measurement at the end of the last processing callback.
+    pub fn loudness(&self) -> Loudness {
+        Loudness {
+            momentary: load_f64(&self.shared.momentary),
+            short_term: load_f64(&self.shared.short_term),
+            integrated: load_f64(&self.shared.integrated),
+            true_peak: load_f64(&self.shared.true_peak),
+        }
+    }
+
+    /// Starts a new measurement.
+    ///
+    /// The meter is reset by the audio thread on the next processing callback. Until then
+    /// [`LoudnessNode::loudness`] reports nothing measured.
+    pub fn reset(&self) {
+        self.shared.publish(&Loudness::default());
+        self.shared.reset.store(true, Ordering::Release);
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+}
+
+// Measurement published by the audio thread, and the reset request from the control thread.
+struct LoudnessShared {
+    momentary: AtomicU64,
+    short_term: AtomicU64,
+    integrated: AtomicU64,
+    true_peak: AtomicU64,
+    reset: AtomicBool,
+}
+
+impl LoudnessShared {
+    fn publish(&self, loudness: &Loudness) {
+        store_f64(&self.momentary, loudness.momentary);
+        store_f64(&self.short_term, loudness.short_term);
+        store_f64(&self.integrated, loudness.integrated);
+        store_f64(&self.true_peak, loudness.true_peak);
+    }
+}
+
+#[inline]
+fn load_f64(v: &AtomicU64) -> f64 {
+    f64::from_bits(v.load(Ordering::Relaxed))
+}
+
+#[inline]
+fn store_f64(v: &AtomicU64, value: f64) {
+    v.store(value.to_bits(), Ordering::Relaxed);
+}
+
+struct LoudnessProcessor {
+    meter: LoudnessMeter,
+    shared: Arc<LoudnessShared>,
+}
+
+impl EffectCallback for LoudnessProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        if self.shared.reset.swap(false, Ordering::Acquire) {
+            self.meter.reset();
+        }
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        let len = frames_in.len().min(frames_out.len());
+        frames_out[..len].copy_from_slice(&frames_in[..len]);
+        self.meter.process(&frames_in[..len])?;
+        self.shared.publish(&self.meter.loudness());
+        Ok(frames)
+    }
+}
+
+/// Builder for creating a [`LoudnessNode`]
+pub struct LoudnessNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    sample_rate: SampleRate,
+    channel_weights: Option<Vec<f32>>,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> LoudnessNodeBuilder<'a, N> {
+    /// `sample_rate` is the rate of the audio flowing through the node.
+    pub fn new(node_graph: &'a N, channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            channel_weights: None,
+            node_graph,
+        }
+    }
+
+    /// Sets the weight of each channel, see [`LoudnessMeter::set_channel_weights`].
+    pub fn channel_weights(&mut self, weights: &[f32]) -> &mut Self {
+        self.channel_weights = Some(weights.to_vec());
+        self
+    }
+
+    pub fn build(&self) -> MaResult<LoudnessNode> {
+        let mut meter = LoudnessMeter::new(self.channels, self.sample_rate)?;
+        if let Some(weights) = &self.channel_weights {
+            meter.set_channel_weights(weights)?;
+        }
+
+        let empty = Loudness::default();
+        let shared = Arc::new(LoudnessShared {
+            momentary: AtomicU64::new(empty.momentary.to_bits()),
+            short_term: AtomicU64::new(empty.short_term.to_bits()),
+            integrated: AtomicU64::new(empty.integrated.to_bits()),
+            true_peak: AtomicU64::new(empty.true_peak.to_bits()),
+            reset: AtomicBool::new(false),
+        });
+        let processor = LoudnessProcessor {
+            meter,
+            shared: shared.clone(),
+        };
+        let node = NodeBuilder::effect()
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels)
+            .build(self.node_graph, processor)?;
+
+        Ok(LoudnessNode {
+            node,
+            shared,
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::waveform::WaveFormBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{routing::loudness::LoudnessNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+            Engine,
+        },
+    };
+
+    #[test]
+    fn test_loudness_node_measures_what_passes_through() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let meter = LoudnessNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert_eq!(meter.loudness().momentary, f64::NEG_INFINITY);
+
+        let sine = WaveFormBuilder::new_sine(SampleRate::Sr48000, 997.0)
+            .amplitude(0.1)
+            .build_f32()
+            .unwrap();
+        let mut sound = engine.new_sound_from_source(&sine).unwrap();
+        sound.set_spatialization(false);
+        sound
+            .as_node()
+            .attach_output(0, &mut meter.as_node(), 0)
+            .unwrap();
+        meter
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(48_000).unwrap();
+        assert!(out.as_ref().iter().any(|s| *s != 0.0));
+        let loudness = meter.loudness();
+        assert!((loudness.momentary + 23.01).abs() < 0.1, "{loudness:?}");
+        assert!((loudness.integrated + 23.01).abs() < 0.1, "{loudness:?}");
+        assert_eq!(loudness.short_term, f64::NEG_INFINITY);
+
+        meter.reset();
+        assert_eq!(meter.loudness().integrated, f64::NEG_INFINITY);
+        reader.read_pcm_frames(4800).unwrap();
+        assert_eq!(meter.loudness().momentary, f64::NEG_INFINITY);
+        assert!(meter.loudness().true_peak > -21.0);
+    }
+
+    #[test]
+    fn test_loudness_node_invalid_args() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        assert!(
+            LoudnessNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+                .channel_weights(&[1.0])
+                .build()
+                .is_err()
+        );
+        let node = LoudnessNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert_eq!(node.channels(), 2);
+        assert_eq!(node.as_node().in_bus_count(), 1);
+    }
+}
@@ -1,2 +1,5 @@
-//! Routing node implementations - `splitter`.
+//! Routing node implementations - `capture`, `loudness`, `recorder`, `splitter`.
+pub mod capture;
+pub mod loudness;
+pub mod recorder;
 pub mod splitter;
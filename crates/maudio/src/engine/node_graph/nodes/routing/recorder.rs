@@ -0,0 +1,416 @@
+//! Pass-through node that records everything flowing through it to a WAV file.
+//!
+//! A [`RecorderNode`] can be inserted anywhere in the node graph, for example between two
+//! effects, to hear exactly what one stage of a chain produces. Audio is passed through
+//! unchanged and copied into a bounded queue on the audio thread. A background thread
+//! drains the queue and writes it to disk as 32-bit float WAV.
+//!
+//! The audio thread never waits for the disk. If the writer falls behind and the queue is
+//! full, new frames are dropped and counted by [`RecorderNode::overrun_notifier`].
+//!
+//! ```no_run
+//! # use maudio::engine::{Engine, node_graph::nodes::{NodeOps, routing::recorder::RecorderNodeBuilder}};
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let node_graph = engine.as_node_graph();
+//! let recorder = RecorderNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+//!     .build(Path::new("debug.wav"))?;
+//!
+//! let sound = engine.new_sound_from_file(Path::new("music.ogg"))?;
+//! sound.as_node().attach_output(0, &mut recorder.as_node(), 0)?;
+//! recorder.as_node().attach_output(0, &mut engine.endpoint(), 0)?;
+//!
+//! // ...
+//!
+//! let frames = recorder.finish()?;
+//! println!("recorded {frames} frames");
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{
+    audio::sample_rate::SampleRate,
+    data_source::sources::{
+        decoder::Fs,
+        pcm_ring_buffer::{PcmRbRecv, PcmRbSend, PcmRingBuffer},
+    },
+    encoder::{Encoder, EncoderBuilder, Wav},
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+            nodes::{private_node, AsNodePtr, Node, NodeRef},
+            AsNodeGraphPtr, NodeGraph, NodeGraphRef,
+        },
+        Engine,
+    },
+    util::proc_notif::ProcFramesNotif,
+    ErrorKinds, MaResult, MaudioError,
+};
+
+type WavEncoder = Encoder<f32, Wav, Fs>;
+
+// How often the writer thread checks the queue when it is empty.
+const WRITER_POLL: Duration = Duration::from_millis(5);
+// Largest block the writer thread moves from the queue to the encoder at once.
+const WRITER_BLOCK_FRAMES: u32 = 4096;
+
+/// A pass-through node that writes its input to a WAV file.
+///
+/// Use [`RecorderNodeBuilder`] to initialize.
+///
+/// Dropping the node stops the recording and waits for the queued frames to be written.
+/// Use [`RecorderNode::finish`] to do the same and get the result.
+pub struct RecorderNode {
+    node: Node<Effect<RecorderProcessor>>,
+    shared: Arc<RecorderShared>,
+    writer: Option<JoinHandle<MaResult<()>>>,
+    overruns: ProcFramesNotif,
+    path: PathBuf,
+    channels: u32,
+    sample_rate: SampleRate,
+}
+
+#[doc(hidden)]
+impl AsNodePtr for RecorderNode {
+    type __PtrProvider = private_node::RecorderNodeProvider;
+}
+
+impl RecorderNode {
+    /// Returns the owning engine, if any.
+    pub fn engine(&self) -> Option<Engine> {
+        self.node.engine()
+    }
+
+    /// Returns the owning node graph, if any.
+    pub fn node_graph(&self) -> Option<NodeGraph> {
+        self.node.node_graph()
+    }
+
+    /// Returns a reference to the node graph.
+    pub fn node_graph_ref(&self) -> NodeGraphRef {
+        self.node.node_graph_ref()
+    }
+
+    /// Path of the WAV file being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of frames written to the file so far.
+    pub fn frames_written(&self) -> u64 {
+        self.shared.frames_written.load(Ordering::Relaxed)
+    }
+
+    /// Returns a [`ProcFramesNotif`] counting the frames dropped because the queue was full.
+    pub fn overrun_notifier(&self) -> ProcFramesNotif {
+        self.overruns.clone()
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Stops the recording, writes the queued frames and closes the file.
+    ///
+    /// Returns the number of frames written. Audio that reaches the node after this
+    /// call is still passed through, but is no longer recorded.
+    pub fn finish(mut self) -> MaResult<u64> {
+        self.stop_writer()?;
+        Ok(self.frames_written())
+    }
+
+    /// Returns a **borrowed view** as a node in the engine's node graph.
+    ///
+    /// ### What this is for
+    ///
+    /// Use `as_node()` when you want to:
+    /// - connect this to other nodes (effects, mixers, splitters, etc.)
+    /// - insert into a custom routing graph
+    /// - query node-level state exposed by the graph
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.node.as_node()
+    }
+
+    fn stop_writer(&mut self) -> MaResult<()> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        self.shared.stop.store(true, Ordering::Release);
+        writer.join().map_err(|_| {
+            MaudioError::new_ma_error(ErrorKinds::Other("recorder writer thread panicked"))
+        })?
+    }
+}
+
+impl Drop for RecorderNode {
+    fn drop(&mut self) {
+        let _ = self.stop_writer();
+    }
+}
+
+// State shared between the node and the writer thread.
+#[derive(Default)]
+struct RecorderShared {
+    stop: AtomicBool,
+    frames_written: AtomicU64,
+}
+
+struct RecorderProcessor {
+    tx: PcmRbSend<f32>,
+    overruns: ProcFramesNotif,
+}
+
+impl RecorderProcessor {
+    fn record(&mut self, frames_in: &[f32], channels: usize) {
+        let total = frames_in.len() / channels;
+        let mut written = 0;
+        // A single write stops at the end of the ring buffer
+        while written < total {
+            match self.tx.write(&frames_in[written * channels..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => written += n,
+            }
+        }
+        if written < total {
+            self.overruns.add_frames((total - written) as u64);
+        }
+    }
+}
+
+impl EffectCallback for RecorderProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        let len = frames_in.len().min(frames_out.len());
+        frames_out[..len].copy_from_slice(&frames_in[..len]);
+        self.record(&frames_in[..len], self.tx.channels() as usize);
+        Ok(frames)
+    }
+}
+
+fn writer_loop(
+    mut rx: PcmRbRecv<f32>,
+    mut encoder: WavEncoder,
+    shared: Arc<RecorderShared>,
+) -> MaResult<()> {
+    let channels = rx.channels() as usize;
+    let block_frames = rx.buffer_size().min(WRITER_BLOCK_FRAMES) as usize;
+    let mut block = vec![0.0f32; block_frames * channels];
+    loop {
+        // Checked before reading, so everything queued before the stop is written
+        let stopping = shared.stop.load(Ordering::Acquire);
+        let frames = rx.read(&mut block)?;
+        if frames > 0 {
+            let written = encoder.write_pcm_frames(&block[..frames * channels])?;
+            shared.frames_written.fetch_add(written, Ordering::Relaxed);
+        } else if stopping {
+            return Ok(());
+        } else {
+            std::thread::sleep(WRITER_POLL);
+        }
+    }
+}
+
+/// Builder for creating a [`RecorderNode`]
+pub struct RecorderNodeBuilder<'a, N: AsNodeGraphPtr> {
+    channels: u32,
+    sample_rate: SampleRate,
+    queue_frames: u32,
+    node_graph: &'a N,
+}
+
+impl<'a, N: AsNodeGraphPtr> RecorderNodeBuilder<'a, N> {
+    /// Creates a builder with a queue holding one second of audio.
+    ///
+    /// `sample_rate` is the rate of the audio flowing through the node, and is written to
+    /// the file header.
+    pub fn new(node_graph: &'a N, channels: u32, sample_rate: SampleRate) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            queue_frames: sample_rate.into(),
+            node_graph,
+        }
+    }
+
+    /// Sets the size of the queue between the audio thread and the writer thread, in frames.
+    ///
+    /// Frames that do not fit in the queue are dropped.
+    pub fn queue_frames(&mut self, frames: u32) -> &mut Self {
+        self.queue_frames = frames;
+        self
+    }
+
+    /// Creates the WAV file at `path`, starts the writer thread and builds the node.
+    pub fn build(&self, path: &Path) -> MaResult<RecorderNode> {
+        if self.queue_frames == 0 {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "recorder queue must hold at least one frame",
+            )));
+        }
+        let encoder = EncoderBuilder::new_f32(self.channels, self.sample_rate)
+            .wav()
+            .build_path(path)?;
+        let (tx, rx) = PcmRingBuffer::new_f32(self.queue_frames, self.channels)?;
+        let overruns = ProcFramesNotif::default();
+
+        let processor = RecorderProcessor {
+            tx,
+            overruns: overruns.clone(),
+        };
+        let node = NodeBuilder::effect()
+            .set_in_channel_count(0, self.channels)
+            .set_out_channel_count(0, self.channels)
+            .build(self.node_graph, processor)?;
+
+        let shared = Arc::new(RecorderShared::default());
+        let writer_shared = shared.clone();
+        let writer = std::thread::Builder::new()
+            .name("maudio-recorder".into())
+            .spawn(move || writer_loop(rx, encoder, writer_shared))?;
+
+        Ok(RecorderNode {
+            node,
+            shared,
+            writer: Some(writer),
+            overruns,
+            path: path.to_path_buf(),
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::{
+            buffer::AudioBufferBuilder,
+            decoder::{DecoderBuilder, DecoderOps},
+        },
+        engine::{
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{routing::recorder::RecorderNodeBuilder, NodeOps},
+                NodeGraphOps,
+            },
+            Engine,
+        },
+        test_assets::temp_file::{unique_tmp_path, TempFileGuard},
+    };
+
+    #[test]
+    fn test_recorder_node_writes_what_passes_through() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let recorder = RecorderNodeBuilder::new(&node_graph, 2, SampleRate::Sr48000)
+            .build(guard.path())
+            .unwrap();
+        assert_eq!(recorder.path(), guard.path());
+
+        let samples: Vec<f32> = (0..512).map(|i| (i % 64) as f32 / 64.0).collect();
+        let buffer = AudioBufferBuilder::build_f32(2, &samples).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound
+            .as_node()
+            .attach_output(0, &mut recorder.as_node(), 0)
+            .unwrap();
+        recorder
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let expected = reader.read_pcm_frames(128).unwrap();
+        assert!(expected.as_ref().iter().any(|s| *s != 0.0));
+
+        let overruns = recorder.overrun_notifier();
+        assert_eq!(recorder.finish().unwrap(), 128);
+        assert_eq!(overruns.take_delta(), 0);
+
+        let mut dec = DecoderBuilder::new_f32(2, SampleRate::Sr48000)
+            .from_file(guard.path())
+            .unwrap();
+        let recorded = dec.read_pcm_frames(256).unwrap();
+        assert_eq!(recorded.as_ref(), expected.as_ref());
+    }
+
+    #[test]
+    fn test_recorder_node_drops_on_overflow() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let node_graph = engine.as_node_graph();
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let recorder = RecorderNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+            .queue_frames(64)
+            .build(guard.path())
+            .unwrap();
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 256]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound
+            .as_node()
+            .attach_output(0, &mut recorder.as_node(), 0)
+            .unwrap();
+        recorder
+            .as_node()
+            .attach_output(0, &mut node_graph.endpoint(), 0)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        // A single read of 200 frames can not fit in a 64 frame queue
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(200).unwrap();
+        // Audio is passed through even when it can not be recorded
+        assert!(out.as_ref()[1..].iter().all(|s| *s == 0.5));
+
+        let overruns = recorder.overrun_notifier();
+        let written = recorder.finish().unwrap();
+        assert!(written >= 64);
+        assert_eq!(written + overruns.take_delta(), 200);
+    }
+
+    #[test]
+    fn test_recorder_node_invalid_args() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        assert!(
+            RecorderNodeBuilder::new(&node_graph, 1, SampleRate::Sr48000)
+                .queue_frames(0)
+                .build(guard.path())
+                .is_err()
+        );
+    }
+}
@@ -183,6 +183,7 @@ pub(crate) mod n_splitter_ffi {
 impl Drop for SplitterNode {
     fn drop(&mut self) {
         n_splitter_ffi::ma_splitter_node_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -222,9 +223,12 @@ impl<'a, N: AsNodeGraphPtr + ?Sized> SplitterNodeBuilder<'a, N> {
 
 #[cfg(test)]
 mod test {
-    use crate::engine::{
-        node_graph::nodes::{routing::splitter::SplitterNodeBuilder, NodeOps, NodeState},
-        Engine,
+    use crate::{
+        engine::{
+            node_graph::nodes::{routing::splitter::SplitterNodeBuilder, NodeOps, NodeState},
+            Engine,
+        },
+        ErrorKinds,
     };
 
     #[test]
@@ -355,10 +359,10 @@ mod test {
         let mut b = splitter_b.as_node();
 
         // Attach A.out[0] -> B.in[0]
-        a.attach_output_bus(0, &mut b, 0).unwrap();
+        a.attach_output(0, &mut b, 0).unwrap();
 
         // Detach just that bus.
-        a.detach_output_bus(0).unwrap();
+        a.detach_output(0).unwrap();
 
         // And detach-all should be safe even if nothing is attached.
         a.detach_all_outputs().unwrap();
@@ -386,7 +390,7 @@ mod test {
         // the graph may mix them or the node may receive multiple connections depending
         // on miniaudio internals, but attach should succeed).
         for out_bus in 0..4 {
-            a.attach_output_bus(out_bus, &mut b, 0).unwrap();
+            a.attach_output(out_bus, &mut b, 0).unwrap();
         }
 
         a.detach_all_outputs().unwrap();
@@ -408,9 +412,12 @@ mod test {
 
         for (bus, &v) in vols.iter().enumerate() {
             node_ref.set_output_bus_volume(bus as u32, v).unwrap();
-            let got = node_ref.output_bus_volume(bus as u32);
+            let got = node_ref.output_bus_volume(bus as u32).unwrap();
             assert!((got - v).abs() < 1.0e-6);
         }
+
+        assert!(node_ref.set_output_bus_volume(3, 0.5).is_err());
+        assert!(node_ref.output_bus_volume(3).is_err());
     }
 
     #[test]
@@ -432,6 +439,29 @@ mod test {
         assert_eq!(node_ref.state().unwrap(), NodeState::Started);
     }
 
+    #[test]
+    fn test_splitter_scheduled_start_stop() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+        let splitter = SplitterNodeBuilder::new(&node_graph, 2).build().unwrap();
+        let mut node = splitter.as_node();
+
+        node.set_state(NodeState::Stopped).unwrap();
+        node.set_start_time_pcm(1000).unwrap();
+        node.set_stop_time_pcm(2000).unwrap();
+
+        assert_eq!(node.state().unwrap(), NodeState::Started);
+        assert_eq!(node.state_time(NodeState::Started), 1000);
+        assert_eq!(node.state_time(NodeState::Stopped), 2000);
+
+        assert_eq!(node.state_by_time(500).unwrap(), NodeState::Stopped);
+        assert_eq!(node.state_by_time(1500).unwrap(), NodeState::Started);
+        assert_eq!(node.state_by_time(2500).unwrap(), NodeState::Stopped);
+
+        node.set_stop_time_pcm(u64::MAX).unwrap();
+        assert_eq!(node.state_by_time(2500).unwrap(), NodeState::Started);
+    }
+
     #[test]
     fn test_splitter_invalid_attach_indices_is_err() {
         let engine = Engine::new_for_tests().unwrap();
@@ -451,9 +481,70 @@ mod test {
         let mut b = splitter_b.as_node();
 
         // output_bus index 999 should be out of range => Err
-        assert!(a.attach_output_bus(999, &mut b, 0).is_err());
+        assert!(a.attach_output(999, &mut b, 0).is_err());
 
         // input bus index 999 should be out of range => Err (splitter has 1 input bus)
-        assert!(a.attach_output_bus(0, &mut b, 999).is_err());
+        let err = a.attach_output(0, &mut b, 999).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Some(ErrorKinds::BusIndexOutOfRange {
+                bus: 999,
+                bus_count: 1,
+                ..
+            })
+        ));
+
+        assert!(a.detach_output(2).is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_splitter_deprecated_bus_methods_forward() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        let splitter_a = SplitterNodeBuilder::new(&node_graph, 2).build().unwrap();
+        let splitter_b = SplitterNodeBuilder::new(&node_graph, 2).build().unwrap();
+        let mut a = splitter_a.as_node();
+        let mut b = splitter_b.as_node();
+
+        a.attach_output_bus(0, &mut b, 0).unwrap();
+        a.detach_output_bus(0).unwrap();
+        // The checks of the new methods apply
+        assert!(a.attach_output_bus(5, &mut b, 0).is_err());
+    }
+
+    #[test]
+    fn test_splitter_attach_rejects_cycles() {
+        let engine = Engine::new_for_tests().unwrap();
+        let node_graph = engine.as_node_graph();
+
+        let splitter_a = SplitterNodeBuilder::new(&node_graph, 2).build().unwrap();
+        let splitter_b = SplitterNodeBuilder::new(&node_graph, 2).build().unwrap();
+        let splitter_c = SplitterNodeBuilder::new(&node_graph, 2).build().unwrap();
+
+        let mut a = splitter_a.as_node();
+        let mut b = splitter_b.as_node();
+        let mut c = splitter_c.as_node();
+
+        let is_cycle = |res: crate::MaResult<()>| {
+            matches!(res.unwrap_err().kind(), Some(ErrorKinds::NodeGraphCycle))
+        };
+
+        // A -> B -> C
+        a.attach_output(0, &mut b, 0).unwrap();
+        b.attach_output(0, &mut c, 0).unwrap();
+
+        // C -> A would close the loop
+        assert!(is_cycle(c.attach_output(0, &mut a, 0)));
+        // A node can not feed itself
+        let mut a2 = splitter_a.as_node();
+        assert!(is_cycle(a.attach_output(1, &mut a2, 0)));
+
+        // Once B -> C is removed, C -> A is fine
+        b.detach_output(0).unwrap();
+        c.attach_output(0, &mut a, 0).unwrap();
+        // ...but now B -> C would create C -> A -> B -> C
+        assert!(is_cycle(b.attach_output(0, &mut c, 0)));
     }
 }
@@ -9,9 +9,9 @@ use crate::{
             nodes::{
                 node_ffi,
                 private_node::{AttachedSourceNodeProvider, SourceNodeProvider},
-                AsNodePtr, NodeRef,
+                AsNodePtr, NodeOps, NodeRef,
             },
-            private_node_graph, AsNodeGraphPtr, GraphOwner, NodeGraph, NodeGraphRef,
+            private_node_graph, AsNodeGraphPtr, GraphOwner, NodeGraph, NodeGraphOps, NodeGraphRef,
         },
         AllocationCallbacks, Engine,
     },
@@ -95,6 +95,28 @@ impl<'a, S: AsSourcePtr> SourceNode<'a, S> {
         NodeRef::from_ptr(ptr)
     }
 
+    /// Returns `true` if the source loops back to its loop start when it reaches the end.
+    pub fn looping(&self) -> bool {
+        n_datasource_ffi::ma_data_source_node_is_looping(self)
+    }
+
+    /// Enables or disables looping of the source.
+    ///
+    /// This is the looping flag of the data source itself, so it is shared with anything else
+    /// reading the same source.
+    pub fn set_looping(&mut self, looping: bool) -> MaResult<()> {
+        n_datasource_ffi::ma_data_source_node_set_looping(self, looping)
+    }
+
+    /// Attaches the output of this node to the endpoint of its node graph.
+    ///
+    /// Shortcut for `attach_output(0, &mut graph.endpoint(), 0)`, the usual last step when
+    /// the source is played without effects.
+    pub fn attach_to_endpoint(&mut self) -> MaResult<()> {
+        let graph = self.node_graph_ref();
+        self.as_node().attach_output(0, &mut graph.endpoint(), 0)
+    }
+
     #[inline]
     fn alloc_cb_ptr(&self) -> *const sys::ma_allocation_callbacks {
         match &self.alloc_cb {
@@ -104,6 +126,16 @@ impl<'a, S: AsSourcePtr> SourceNode<'a, S> {
     }
 }
 
+impl<'a, S: AsSourcePtr<Format = f32>> SourceNode<'a, S> {
+    /// Creates a node that plays `source` into the node graph, with the default config.
+    ///
+    /// Same as `SourceNodeBuilder::new(node_graph, source).build()`. The node graph only
+    /// processes `f32` audio, so only `f32` sources are accepted.
+    pub fn from_source<N: AsNodeGraphPtr>(node_graph: &'a N, source: &'a S) -> MaResult<Self> {
+        SourceNodeBuilder::new(node_graph, source).build()
+    }
+}
+
 pub struct AttachedSourceNode<S: AsSourcePtr> {
     inner: *mut sys::ma_data_source_node,
     alloc_cb: Option<Arc<AllocationCallbacks>>,
@@ -172,7 +204,7 @@ impl<S: AsSourcePtr> AttachedSourceNode<S> {
         self.owner.graph().map(|g| NodeGraph { inner: g })
     }
 
-    /// Returns a refer            inner: g,h.
+    /// Returns a reference to the node graph.
     pub fn node_graph_ref(&self) -> NodeGraphRef {
         let ptr = node_ffi::ma_node_get_node_graph(self);
         NodeGraphRef {
@@ -188,6 +220,28 @@ impl<S: AsSourcePtr> AttachedSourceNode<S> {
         NodeRef::from_ptr(ptr)
     }
 
+    /// Returns `true` if the source loops back to its loop start when it reaches the end.
+    pub fn looping(&self) -> bool {
+        n_datasource_ffi::ma_data_source_node_is_looping(self)
+    }
+
+    /// Enables or disables looping of the source.
+    ///
+    /// This is the looping flag of the data source itself, so it is shared with anything else
+    /// reading the same source.
+    pub fn set_looping(&mut self, looping: bool) -> MaResult<()> {
+        n_datasource_ffi::ma_data_source_node_set_looping(self, looping)
+    }
+
+    /// Attaches the output of this node to the endpoint of its node graph.
+    ///
+    /// Shortcut for `attach_output(0, &mut graph.endpoint(), 0)`, the usual last step when
+    /// the source is played without effects.
+    pub fn attach_to_endpoint(&mut self) -> MaResult<()> {
+        let graph = self.node_graph_ref();
+        self.as_node().attach_output(0, &mut graph.endpoint(), 0)
+    }
+
     /// Retrieve a reference to the underlying source
     pub fn source(&self) -> &S {
         &self.source
@@ -213,13 +267,28 @@ impl<S: AsSourcePtr> AttachedSourceNode<S> {
     }
 }
 
+impl<S: AsSourcePtr<Format = f32>> AttachedSourceNode<S> {
+    /// Creates a node that owns and plays `source`, with the default config.
+    ///
+    /// Same as `AttachedSourceNodeBuilder::new(node_graph, source).build()`. Use
+    /// [`AttachedSourceNode::source_mut`] to change the source, for example the frequency of
+    /// a waveform, while the node plays.
+    pub fn from_source<N: AsNodeGraphPtr>(node_graph: &N, source: S) -> MaResult<Self> {
+        AttachedSourceNodeBuilder::new(node_graph, source).build()
+    }
+}
+
 pub(crate) mod n_datasource_ffi {
     use maudio_sys::ffi as sys;
 
     use crate::{
         data_source::AsSourcePtr,
         engine::node_graph::{
-            nodes::source::source_node::{AttachedSourceNode, SourceNode},
+            nodes::{
+                private_node,
+                source::source_node::{AttachedSourceNode, SourceNode},
+                AsNodePtr,
+            },
             private_node_graph, AsNodeGraphPtr,
         },
         Binding, MaResult, MaudioError,
@@ -250,6 +319,30 @@ pub(crate) mod n_datasource_ffi {
         }
     }
 
+    #[inline]
+    pub fn ma_data_source_node_set_looping<N: AsNodePtr + ?Sized>(
+        node: &mut N,
+        looping: bool,
+    ) -> MaResult<()> {
+        let res = unsafe {
+            sys::ma_data_source_node_set_looping(
+                private_node::node_ptr(node).cast::<sys::ma_data_source_node>(),
+                looping as u32,
+            )
+        };
+        MaudioError::check(res)
+    }
+
+    #[inline]
+    pub fn ma_data_source_node_is_looping<N: AsNodePtr + ?Sized>(node: &N) -> bool {
+        let res = unsafe {
+            sys::ma_data_source_node_is_looping(
+                private_node::node_ptr(node).cast::<sys::ma_data_source_node>(),
+            )
+        };
+        res == 1
+    }
+
     // If more functions for AttachedSourceNode get added, create the common trait
     #[inline]
     pub fn ma_attached_data_source_node_uninit<S: AsSourcePtr>(node: &mut AttachedSourceNode<S>) {
@@ -359,10 +452,22 @@ impl<'a, N: AsNodeGraphPtr, S: AsSourcePtr> AttachedSourceNodeBuilder<'a, N, S>
 #[cfg(test)]
 mod test {
     use crate::{
-        data_source::sources::buffer::AudioBufferBuilder,
+        audio::sample_rate::SampleRate,
+        data_source::sources::{
+            buffer::AudioBufferBuilder,
+            noise::{NoiseBuilder, NoiseType},
+            waveform::{WaveFormBuilder, WaveFormOps},
+        },
         engine::{
-            node_graph::nodes::source::source_node::{
-                AttachedSourceNodeBuilder, SourceNodeBuilder,
+            engine_builder::EngineBuilder,
+            node_graph::{
+                nodes::{
+                    source::source_node::{
+                        AttachedSourceNodeBuilder, SourceNode, SourceNodeBuilder,
+                    },
+                    NodeOps,
+                },
+                NodeGraphOps,
             },
             Engine,
         },
@@ -461,4 +566,98 @@ mod test {
         let buff = src_node.source();
         let _ = buff.length_pcm().unwrap();
     }
+
+    #[test]
+    fn source_node_test_from_source_plays_into_graph() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let graph = engine.as_node_graph();
+        let data: Vec<f32> = (0..64).map(|i| i as f32 / 64.0).collect();
+        let buf = AudioBufferBuilder::build_f32(1, &data).unwrap();
+
+        let node = SourceNode::from_source(&graph, &buf).unwrap();
+        node.as_node()
+            .attach_output(0, &mut graph.endpoint(), 0)
+            .unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(64).unwrap();
+        assert_eq!(out.as_ref(), data.as_slice());
+    }
+
+    #[test]
+    fn source_node_test_generators_into_node() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let graph = engine.as_node_graph();
+
+        let sine = WaveFormBuilder::new_sine(SampleRate::Sr48000, 1000.0)
+            .channels(1)
+            .amplitude(0.5)
+            .build_f32()
+            .unwrap();
+        let mut sine = sine.into_node(&graph).unwrap();
+        sine.as_node()
+            .attach_output(0, &mut graph.endpoint(), 0)
+            .unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(48).unwrap();
+        let peak = out.as_ref().iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.5).abs() < 1e-3, "peak {peak}");
+
+        // The owned generator can still be changed while it plays
+        sine.source_mut().set_amplitude(0.25).unwrap();
+        let out = reader.read_pcm_frames(48).unwrap();
+        let peak = out.as_ref().iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.25).abs() < 1e-3, "peak {peak}");
+        drop(sine);
+
+        let noise = NoiseBuilder::new(1, NoiseType::White, 0.5)
+            .build_f32()
+            .unwrap()
+            .into_node(&graph)
+            .unwrap();
+        noise
+            .as_node()
+            .attach_output(0, &mut graph.endpoint(), 0)
+            .unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref().iter().any(|s| *s != 0.0));
+        assert!(out.as_ref().iter().all(|s| s.abs() <= 0.5));
+    }
+
+    #[test]
+    fn source_node_test_looping_and_attach_to_endpoint() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let graph = engine.as_node_graph();
+        let data: Vec<f32> = (1..=16).map(|i| i as f32).collect();
+        let buf = AudioBufferBuilder::build_f32(1, &data).unwrap();
+
+        let mut node = AttachedSourceNodeBuilder::new(&graph, buf).build().unwrap();
+        assert!(!node.looping());
+        node.set_looping(true).unwrap();
+        assert!(node.looping());
+        assert!(node.as_source_ref().looping());
+        node.attach_to_endpoint().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(40).unwrap();
+        for (i, s) in out.as_ref().iter().enumerate() {
+            assert_eq!(*s, data[i % 16]);
+        }
+
+        // Plays the rest of the current pass, then stops
+        node.set_looping(false).unwrap();
+        let out = reader.read_pcm_frames(16).unwrap();
+        assert_eq!(&out.as_ref()[..8], &data[8..]);
+        assert!(out.as_ref()[8..].iter().all(|s| *s == 0.0));
+    }
 }
@@ -117,7 +117,7 @@ impl<'g, N: AsNodeGraphPtr> VoiceBuilder<'g, N> {
         // TODO: Check how many outputs it has. How will dsp nodes will be added later?
         // TODO: Add option to not connect it?
         // TODO: Keep track of the connected mixer output busses
-        mixer.attach_output_bus(0, &mut endpoint, 0)?;
+        mixer.attach_output(0, &mut endpoint, 0)?;
 
         let mut waves = Vec::with_capacity(self.wave_builders.len());
         let mut pulses = Vec::with_capacity(self.pulse_builders.len());
@@ -125,13 +125,13 @@ impl<'g, N: AsNodeGraphPtr> VoiceBuilder<'g, N> {
         for wave_builder in &mut self.wave_builders {
             let wave = wave_builder.build_f32()?;
             let mut attach = AttachedSourceNodeBuilder::new(graph_ref, wave).build()?;
-            attach.attach_output_bus(0, &mut mixer, 0)?;
+            attach.attach_output(0, &mut mixer, 0)?;
             waves.push(attach);
         }
         for pulse_builder in &mut self.pulse_builders {
             let pulse = pulse_builder.build_f32()?;
             let mut attach = AttachedSourceNodeBuilder::new(graph_ref, pulse).build()?;
-            attach.attach_output_bus(0, &mut mixer, 0)?;
+            attach.attach_output(0, &mut mixer, 0)?;
             pulses.push(attach);
         }
         Ok(VoiceStack {
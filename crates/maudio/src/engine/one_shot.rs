@@ -0,0 +1,241 @@
+//! Fire-and-forget playback of in-memory samples, behind [`Engine::play_buffer`].
+//!
+//! Each call copies the samples into an audio buffer and plays it with a sound that the
+//! engine owns. Like miniaudio's own `ma_engine_play_sound`, finished sounds are freed the
+//! next time a buffer is played, and the remaining ones when the engine is dropped.
+//!
+//! ```no_run
+//! # use maudio::engine::{Engine, one_shot::PlayOptions};
+//! # use maudio::data_source::sources::sweep::SweepBuilder;
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut sweep = SweepBuilder::new(1, SampleRate::Sr48000, 200.0, 800.0, 12_000).build()?;
+//! let samples = sweep.read_pcm_frames(12_000)?;
+//!
+//! engine.play_buffer(&samples, PlayOptions::new().volume(0.5))?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{mem::MaybeUninit, sync::Mutex};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate},
+    data_source::sources::buffer::{AudioBuffer, AudioBufferBuilder},
+    engine::Engine,
+    sound::{sound_ffi, sound_flags::SoundFlags},
+    MaResult,
+};
+
+/// How [`Engine::play_buffer`] plays the samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayOptions {
+    /// Linear volume, `1.0` by default.
+    pub volume: f32,
+    /// Pan between `-1.0` (left) and `1.0` (right), `0.0` by default.
+    pub pan: f32,
+    /// Pitch, `1.0` by default.
+    pub pitch: f32,
+    /// Sample rate of the samples. Defaults to the engine's sample rate.
+    pub sample_rate: Option<SampleRate>,
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pan: 0.0,
+            pitch: 1.0,
+            sample_rate: None,
+        }
+    }
+}
+
+impl PlayOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn volume(&mut self, volume: f32) -> &mut Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn pan(&mut self, pan: f32) -> &mut Self {
+        self.pan = pan;
+        self
+    }
+
+    pub fn pitch(&mut self, pitch: f32) -> &mut Self {
+        self.pitch = pitch;
+        self
+    }
+
+    /// Sample rate the samples were generated at, if it differs from the engine's.
+    pub fn sample_rate(&mut self, sample_rate: SampleRate) -> &mut Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+}
+
+// A sound playing its own copy of the samples.
+//
+// The `ma_sound` is kept raw rather than in a `Sound`, which would hold the engine alive
+// from inside the engine.
+struct OneShot {
+    sound: *mut sys::ma_sound,
+    // Read by the sound, dropped after it
+    _buffer: AudioBuffer<f32>,
+}
+
+impl OneShot {
+    fn ended(&self) -> bool {
+        unsafe { sys::ma_sound_at_end(self.sound) == 1 }
+    }
+}
+
+impl Drop for OneShot {
+    fn drop(&mut self) {
+        unsafe { sys::ma_sound_uninit(self.sound) };
+        drop(unsafe { Box::from_raw(self.sound) });
+    }
+}
+
+/// The sounds started with [`Engine::play_buffer`], owned by the engine.
+#[derive(Default)]
+pub(crate) struct OneShots {
+    playing: Mutex<Vec<OneShot>>,
+}
+
+impl OneShots {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<OneShot>> {
+        self.playing.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Frees the sounds that reached their end and returns how many are left.
+    pub(crate) fn reap(&self) -> usize {
+        let mut playing = self.lock();
+        playing.retain(|one_shot| !one_shot.ended());
+        playing.len()
+    }
+
+    /// Runs `f` with the sounds that are still owned, which are not freed until it returns.
+    pub(crate) fn with_sounds<R>(&self, f: impl FnOnce(&[*mut sys::ma_sound]) -> R) -> R {
+        let playing = self.lock();
+        let sounds = playing.iter().map(|s| s.sound).collect::<Vec<_>>();
+        f(&sounds)
+    }
+
+    /// Frees every sound. Must run before the engine is uninitialized.
+    pub(crate) fn clear(&self) {
+        let playing = core::mem::take(&mut *self.lock());
+        drop(playing);
+    }
+}
+
+pub(crate) fn play_buffer(
+    engine: &Engine,
+    samples: &SampleBuffer<f32>,
+    options: &PlayOptions,
+) -> MaResult<()> {
+    engine.0.one_shots.reap();
+    if samples.frames() == 0 {
+        return Ok(());
+    }
+
+    let sample_rate = match options.sample_rate {
+        Some(rate) => rate,
+        None => engine.sample_rate()?,
+    };
+    let buffer = AudioBufferBuilder::build_f32_with_sample_rate(
+        samples.channels(),
+        sample_rate,
+        samples.as_ref(),
+    )?;
+
+    let mut mem: Box<MaybeUninit<sys::ma_sound>> = Box::new(MaybeUninit::uninit());
+    sound_ffi::ma_sound_init_from_data_source(
+        engine,
+        &buffer,
+        SoundFlags::NO_SPATIALIZATION,
+        None,
+        mem.as_mut_ptr(),
+    )?;
+    let one_shot = OneShot {
+        sound: Box::into_raw(mem) as *mut sys::ma_sound,
+        _buffer: buffer,
+    };
+    unsafe {
+        sys::ma_sound_set_volume(one_shot.sound, options.volume);
+        sys::ma_sound_set_pan(one_shot.sound, options.pan);
+        sys::ma_sound_set_pitch(one_shot.sound, options.pitch * engine.0.sounds.time_scale());
+    }
+    let res = unsafe { sys::ma_sound_start(one_shot.sound) };
+    crate::MaudioError::check(res)?;
+
+    engine.0.one_shots.lock().push(one_shot);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::{formats::SampleBuffer, sample_rate::SampleRate},
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{engine_builder::EngineBuilder, one_shot::PlayOptions},
+    };
+
+    fn samples(channels: u32, data: &[f32]) -> SampleBuffer<f32> {
+        let mut buffer = AudioBufferBuilder::build_f32(channels, data).unwrap();
+        buffer
+            .read_pcm_frames(data.len() as u64 / channels as u64, false)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_play_buffer_plays_and_frees_the_sound() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+
+        engine
+            .play_buffer(&samples(1, &[0.5; 256]), PlayOptions::new().volume(0.5))
+            .unwrap();
+        assert_eq!(engine.playing_buffer_count(), 1);
+
+        let out = reader.read_pcm_frames(512).unwrap();
+        // Sounds start one frame late
+        assert!(out.as_ref()[1..256]
+            .iter()
+            .all(|s| (*s - 0.25).abs() < 1e-6));
+        assert!(out.as_ref()[256..].iter().all(|s| *s == 0.0));
+        assert_eq!(engine.playing_buffer_count(), 0);
+    }
+
+    #[test]
+    fn test_play_buffer_mixes_overlapping_buffers() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+
+        engine
+            .play_buffer(&samples(2, &[0.25; 512]), &PlayOptions::default())
+            .unwrap();
+        engine
+            .play_buffer(&samples(2, &[0.5; 512]), &PlayOptions::default())
+            .unwrap();
+        assert_eq!(engine.playing_buffer_count(), 2);
+
+        let out = reader.read_pcm_frames(64).unwrap();
+        assert!(out.as_ref()[2..].iter().all(|s| (*s - 0.75).abs() < 1e-6));
+        // Still playing when the engine is dropped
+        drop(reader);
+        drop(engine);
+    }
+}
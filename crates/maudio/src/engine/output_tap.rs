@@ -0,0 +1,159 @@
+//! Copy of the engine's final mixed output, readable from another thread.
+//!
+//! An [`OutputTap`] is created with
+//! [`EngineBuilder::with_output_tap`](crate::engine::engine_builder::EngineBuilder::with_output_tap).
+//! The engine's `onProcess` callback writes every processed block into a ring buffer,
+//! while the mix keeps going to the device as usual. The tap reads it back from any
+//! (single) thread, for example to write the mix to disk, stream it over the network
+//! or analyze it.
+//!
+//! ```no_run
+//! # use maudio::engine::engine_builder::EngineBuilder;
+//! # fn main() -> maudio::MaResult<()> {
+//! // Keep up to one second of audio at 48 kHz
+//! let (engine, mut tap) = EngineBuilder::new().with_output_tap(48_000)?;
+//!
+//! let mut block = vec![0.0f32; 1024 * tap.channels() as usize];
+//! loop {
+//!     let frames = tap.read(&mut block)?;
+//!     // Use `block[..frames * channels]`
+//!     # let _ = frames;
+//!     std::thread::sleep(std::time::Duration::from_millis(10));
+//! }
+//! # drop(engine);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    data_source::sources::pcm_ring_buffer::{PcmRbRecv, PcmRbSend},
+    engine::process_cb::EngineProcessCallback,
+    util::proc_notif::ProcFramesNotif,
+    MaResult,
+};
+
+/// Reading end of the engine output tap.
+///
+/// Frames are only written by the engine, so if the tap is not read fast enough the
+/// ring buffer fills up and new frames are dropped. The number of dropped frames is
+/// reported by [`OutputTap::overrun_notifier`].
+pub struct OutputTap {
+    rx: PcmRbRecv<f32>,
+    overruns: ProcFramesNotif,
+}
+
+impl OutputTap {
+    pub(crate) fn new(
+        tx: PcmRbSend<f32>,
+        rx: PcmRbRecv<f32>,
+    ) -> (Self, Box<EngineProcessCallback>) {
+        let overruns = ProcFramesNotif::default();
+        let writer = tap_writer(tx, overruns.clone());
+        (Self { rx, overruns }, writer)
+    }
+
+    /// Reads as many frames as are available into `dst`, as interleaved `f32` samples.
+    ///
+    /// Returns the number of frames read.
+    pub fn read(&mut self, dst: &mut [f32]) -> MaResult<usize> {
+        let channels = self.channels() as usize;
+        let mut frames_read = 0;
+        // A single read stops at the end of the ring buffer
+        loop {
+            let n = self.rx.read(&mut dst[frames_read * channels..])?;
+            if n == 0 {
+                break;
+            }
+            frames_read += n;
+        }
+        Ok(frames_read)
+    }
+
+    /// Number of frames that can currently be read.
+    pub fn available_frames(&self) -> u32 {
+        self.rx.available_read()
+    }
+
+    /// Channel count of the engine output.
+    pub fn channels(&self) -> u32 {
+        self.rx.channels()
+    }
+
+    /// Returns a [`ProcFramesNotif`] counting the frames dropped because the tap was full.
+    pub fn overrun_notifier(&self) -> ProcFramesNotif {
+        self.overruns.clone()
+    }
+
+    /// Returns the receive end of the underlying ring buffer.
+    pub fn into_inner(self) -> PcmRbRecv<f32> {
+        self.rx
+    }
+}
+
+fn tap_writer(mut tx: PcmRbSend<f32>, overruns: ProcFramesNotif) -> Box<EngineProcessCallback> {
+    Box::new(move |out: &mut [f32], channels: u32| {
+        let channels = channels as usize;
+        let total = out.len() / channels;
+        let mut written = 0;
+        while written < total {
+            match tx.write(&out[written * channels..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => written += n,
+            }
+        }
+        if written < total {
+            overruns.add_frames((total - written) as u64);
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate, data_source::sources::buffer::AudioBufferBuilder,
+        engine::engine_builder::EngineBuilder,
+    };
+
+    #[test]
+    fn test_output_tap_matches_engine_output() {
+        let (engine, mut tap) = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .with_output_tap(1024)
+            .unwrap();
+        assert_eq!(tap.channels(), 2);
+
+        let samples: Vec<f32> = (0..512).map(|i| (i % 64) as f32 / 64.0).collect();
+        let buffer = AudioBufferBuilder::build_f32(2, &samples).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let expected = reader.read_pcm_frames(128).unwrap();
+
+        assert_eq!(tap.available_frames(), 128);
+        let mut out = vec![0.0f32; 256 * 2];
+        assert_eq!(tap.read(&mut out).unwrap(), 128);
+        assert_eq!(&out[..256], expected.as_ref());
+        assert!(out[..256].iter().any(|s| *s != 0.0));
+    }
+
+    #[test]
+    fn test_output_tap_counts_overruns() {
+        let (engine, mut tap) = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .with_output_tap(64)
+            .unwrap();
+        let overruns = tap.overrun_notifier();
+
+        // The engine only processes while something is playing
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 256]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(100).unwrap();
+
+        assert_eq!(overruns.take_delta(), 36);
+        let mut out = vec![0.0f32; 100];
+        assert_eq!(tap.read(&mut out).unwrap(), 64);
+    }
+}
@@ -1,39 +1,73 @@
 //! Callback fired whenever the engine processes and outputs audio frames.
 use std::{
     cell::UnsafeCell,
-    panic::{catch_unwind, AssertUnwindSafe},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU32, Ordering},
         Arc,
     },
+    time::Instant,
 };
 
 use maudio_sys::ffi as sys;
 
-use crate::util::{device_notif::DeviceStateNotifier, proc_notif::ProcFramesNotif};
+use crate::{
+    engine::{
+        block_events::BlockEvents, engine_stats::StatsCounters,
+        node_graph::nodes::effects::channel_gain::ChannelGains,
+    },
+    util::{
+        callback_panic, device_notif::DeviceStateNotifier, proc_notif::ProcFramesNotif, rt_check,
+    },
+};
 
 #[derive(Default)]
 pub(crate) struct ProcessState {
     frames_processed: ProcFramesNotif,
-    channels: u32,
+    // 0 until the engine is initialized, when the config leaves it to the device
+    channels: AtomicU32,
     cb: UnsafeCell<Option<Box<EngineProcessCallback>>>,
     pub(crate) state_notif: DeviceStateNotifier,
     panic_flag: Arc<AtomicBool>,
     in_cb: AtomicBool,
+    stats: Option<Arc<StatsCounters>>,
+    // Set with `EngineBuilder::with_block_events`
+    blocks: Option<BlockEvents>,
+    // The engine's own device callback, when it is wrapped to time it
+    device_on_data: UnsafeCell<sys::ma_device_data_proc>,
+    // Set with `Engine::set_output_channel_gain`
+    pub(crate) output_gains: ChannelGains,
 }
 
 impl ProcessState {
-    pub(crate) fn new(channels: u32, cb: Option<Box<EngineProcessCallback>>) -> Self {
+    pub(crate) fn new(
+        channels: u32,
+        cb: Option<Box<EngineProcessCallback>>,
+        stats: Option<Arc<StatsCounters>>,
+        blocks: Option<BlockEvents>,
+    ) -> Self {
         ProcessState {
             frames_processed: ProcFramesNotif::default(),
-            channels,
+            channels: AtomicU32::new(channels),
             cb: UnsafeCell::new(cb),
             state_notif: DeviceStateNotifier::default(),
             panic_flag: Arc::new(AtomicBool::new(false)),
             in_cb: AtomicBool::new(false),
+            stats,
+            blocks,
+            device_on_data: UnsafeCell::new(None),
+            output_gains: ChannelGains::default(),
         }
     }
 
+    pub(crate) fn blocks(&self) -> Option<&BlockEvents> {
+        self.blocks.as_ref()
+    }
+
+    /// Returns `true` if the device callback must be wrapped to time each block.
+    pub(crate) fn times_blocks(&self) -> bool {
+        self.stats.is_some() || self.blocks.is_some()
+    }
+
     pub(crate) fn clone_proc_notif(&self) -> ProcFramesNotif {
         self.frames_processed.clone()
     }
@@ -46,6 +80,60 @@ impl ProcessState {
     pub(crate) fn clone_panic_flag(&self) -> Arc<AtomicBool> {
         self.panic_flag.clone()
     }
+
+    /// Sets the channel count of the engine, once it is known.
+    pub(crate) fn set_channels(&self, channels: u32) {
+        self.channels.store(channels, Ordering::Release);
+    }
+
+    /// Replaces the user callback.
+    ///
+    /// # Safety
+    /// The engine must not be processing audio, meaning it is not started and
+    /// no one is reading from it.
+    pub(crate) unsafe fn set_callback(&self, cb: Box<EngineProcessCallback>) {
+        *self.cb.get() = Some(cb);
+    }
+
+    /// Wraps the data callback of the engine's own device, to time each callback.
+    ///
+    /// # Safety
+    /// `device` must be the device owned by the engine using this state, and it must not
+    /// be started.
+    pub(crate) unsafe fn wrap_device_callback(&self, device: *mut sys::ma_device) {
+        *self.device_on_data.get() = (*device).onData;
+        (*device).onData = Some(timed_device_data_callback);
+    }
+}
+
+unsafe extern "C" fn timed_device_data_callback(
+    device: *mut sys::ma_device,
+    output: *mut core::ffi::c_void,
+    input: *const core::ffi::c_void,
+    frame_count: u32,
+) {
+    // The engine is the user data of the device it owns
+    let engine = (*device).pUserData as *mut sys::ma_engine;
+    if engine.is_null() {
+        return;
+    }
+    let state = (*engine).pProcessUserData as *const ProcessState;
+    if state.is_null() {
+        return;
+    }
+    let state = &*state;
+    let Some(on_data) = *state.device_on_data.get() else {
+        return;
+    };
+
+    if let Some(blocks) = &state.blocks {
+        blocks.block_started();
+    }
+    let start = Instant::now();
+    on_data(device, output, input, frame_count);
+    if let Some(stats) = &state.stats {
+        stats.record_block(start.elapsed(), frame_count, Some((*device).sampleRate));
+    }
 }
 
 // TODO: Maybe convert it to a generic as in the Device callback?
@@ -64,16 +152,30 @@ pub(crate) unsafe extern "C" fn on_process_callback(
 
     let ctx = unsafe { &*(user_data as *const ProcessState) };
 
-    if ctx.panic_flag.load(Ordering::Relaxed) {
-        // The callback is poisoned
-        return;
+    if let Some(stats) = &ctx.stats {
+        stats.add_frames(frame_count);
     }
 
     if frames_out.is_null() || frame_count == 0 {
         return;
     }
 
+    let channels = ctx.channels.load(Ordering::Acquire);
+    // Applied first, so the user callback and taps see the output as it is played
+    if let Some(len) = (frame_count as usize).checked_mul(channels as usize) {
+        let out = core::slice::from_raw_parts_mut(frames_out, len);
+        ctx.output_gains.apply(out, channels as usize);
+    }
+
+    if ctx.panic_flag.load(Ordering::Relaxed) {
+        // The callback is poisoned
+        return;
+    }
+
     ctx.frames_processed.add_frames(frame_count);
+    if let Some(blocks) = &ctx.blocks {
+        blocks.publish(frame_count as u32);
+    }
 
     if ctx
         .in_cb
@@ -84,9 +186,14 @@ pub(crate) unsafe extern "C" fn on_process_callback(
         return;
     }
 
-    let channels = ctx.channels as usize;
+    if channels == 0 {
+        // The engine is still being initialized
+        ctx.in_cb.store(false, Ordering::Release);
+        return;
+    }
     // Engine is alwaus f32, no need to adjust to vec storage units
-    let Some(slice_len) = (frame_count as usize).checked_mul(channels) else {
+    let Some(slice_len) = (frame_count as usize).checked_mul(channels as usize) else {
+        ctx.in_cb.store(false, Ordering::Release);
         return;
     };
 
@@ -95,9 +202,10 @@ pub(crate) unsafe extern "C" fn on_process_callback(
 
     let cb_slot = &mut *ctx.cb.get();
     if let Some(cb) = cb_slot.as_mut() {
-        let result = catch_unwind(AssertUnwindSafe(|| {
-            cb(out, ctx.channels);
-        }));
+        let _rt = rt_check::enter();
+        let result = callback_panic::guard(|| {
+            cb(out, channels);
+        });
 
         if result.is_err() {
             // Disable callback permanently after panic.
@@ -92,25 +92,43 @@ use crate::{
         formats::{Format, SampleBuffer},
         sample_rate::SampleRate,
     },
-    data_source::{AsSourcePtr, SharedSource},
+    data_source::{
+        data_source_ffi, sources::decoder::decoding_backend::RegisteredBackends, AsSourcePtr,
+        DataSourceOps, SharedSource,
+    },
     engine::resource::{
         rm_buffer::{ResourceManagerBuffer, ResourceManagerBufferBuilder},
         rm_builder::ResourceManagerBuilder,
+        rm_flags::RmFlags,
+        rm_jobs::{JobRunner, JobStatus, RmJob},
+        rm_progress::LoadProgress,
         rm_source::{ResourceManagerSource, ResourceManagerSourceBuilder},
         rm_source_flags::RmSourceFlags,
+        rm_stats::{Footprint, ResourceStats, RmMemoryStats},
         rm_stream::{ResourceManagerStream, ResourceManagerStreamBuilder},
     },
+    engine::{Engine, EngineInner},
     pcm_frames::{PcmFormat, PcmFormatInternal, S24Packed, S24},
+    sound::{sound_flags::SoundFlags, Sound},
     test_assets::wav_i16_le,
-    AsRawRef, Binding, MaResult, MaudioError,
+    util::jobs,
+    AsRawRef, Binding, ErrorContext, ErrorKinds, MaResult, MaudioError, ResultContext,
 };
 
+#[cfg(feature = "memmap2")]
+use crate::util::mapped_file::MappedFile;
+
 pub mod rm_buffer;
 pub mod rm_builder;
+pub mod rm_cache;
 pub mod rm_flags;
+pub mod rm_jobs;
 pub mod rm_notif;
+pub mod rm_progress;
+pub(crate) mod rm_registry;
 pub mod rm_source;
 pub mod rm_source_flags;
+pub mod rm_stats;
 pub mod rm_stream;
 
 /// An owned resource manager handle.
@@ -217,6 +235,8 @@ pub(crate) struct InnerResourceManager<F: PcmFormat> {
     inner: *mut sys::ma_resource_manager,
     #[allow(unused)]
     channels: Option<u32>,
+    // Custom decoding backends. Must outlive `inner`
+    _backends: Option<RegisteredBackends>,
     _format: PhantomData<F>,
 }
 
@@ -250,6 +270,8 @@ impl<F: PcmFormat> Binding for ResourceManager<F> {
 /// In practice, this is determined by how the original resource manager was created.
 pub struct ResourceManagerRef<'a, F: PcmFormat> {
     inner: *mut sys::ma_resource_manager,
+    // The engine owning the resource manager, see `EngineBuilder::resource_manager_ref`
+    engine: Arc<EngineInner>,
     _format: PhantomData<F>,
     _marker: PhantomData<&'a ()>,
 }
@@ -263,13 +285,18 @@ impl<F: PcmFormat> Binding for ResourceManagerRef<'_, F> {
 }
 
 impl<'a, F: PcmFormat> ResourceManagerRef<'a, F> {
-    pub(crate) fn from_ptr(ptr: *mut sys::ma_resource_manager) -> Self {
+    pub(crate) fn from_ptr(ptr: *mut sys::ma_resource_manager, engine: Arc<EngineInner>) -> Self {
         Self {
             inner: ptr,
+            engine,
             _format: PhantomData,
             _marker: PhantomData,
         }
     }
+
+    pub(crate) fn engine_inner(&self) -> &Arc<EngineInner> {
+        &self.engine
+    }
 }
 
 pub(crate) mod private_rm {
@@ -329,6 +356,9 @@ impl<F: PcmFormat> AsRmPtr for ResourceManagerRef<'_, F> {
 ///
 /// The guard may also hold ownership of the underlying bytes (for conversions done by maudio).
 ///
+/// Guards can be cloned, or acquired again by name with [`RmOps::acquire`], for example from
+/// another thread. The resource stays registered until the last guard for its name is dropped.
+///
 /// # Typical workflow
 ///
 /// 1. Register data with `register_*()`.
@@ -336,14 +366,29 @@ impl<F: PcmFormat> AsRmPtr for ResourceManagerRef<'_, F> {
 /// 3. Build buffers, streams, or sources from the guard.
 ///
 /// Dropping the guard unregisters the resource once it is no longer in active use.
-#[allow(dead_code)]
 pub struct ResourceGuard<'a, R: AsRmPtr + ?Sized> {
     rm: &'a R,
+    // Registry entry shared by the clones of this guard
+    id: u64,
     data_name: RegisteredDataType,
-    data_store: Option<Arc<[u8]>>,
     _data_marker: PhantomData<&'a [u8]>,
 }
 
+// Data kept alive by a `ResourceGuard` for as long as the resource manager may read it
+#[allow(dead_code)]
+pub(crate) enum DataStore {
+    // An `Arc<[T]>` of encoded bytes or decoded samples
+    Owned(Box<dyn core::any::Any + Send + Sync>),
+    #[cfg(feature = "memmap2")]
+    Mapped(MappedFile),
+}
+
+impl DataStore {
+    fn owned<T: Send + Sync + 'static>(data: Arc<[T]>) -> Self {
+        DataStore::Owned(Box::new(data))
+    }
+}
+
 // Builders for registered resources
 impl<'a, R: AsRmPtr> ResourceGuard<'a, R> {
     /// Builds a [`ResourceManagerBuffer`] from a previously registered file path or
@@ -473,51 +518,180 @@ impl<'a, R: AsRmPtr> ResourceGuard<'a, R> {
         }
         Ok(PendingResource::Ready { inner: resource })
     }
+
+    /// Creates a [`Sound`] playing the registered resource.
+    ///
+    /// The engine must use the same resource manager as the guard (see
+    /// [`EngineBuilder::resource_manager`](crate::engine::engine_builder::EngineBuilder::resource_manager)
+    /// and [`Engine::resource_manager`]).
+    ///
+    /// The sound keeps the data owned by the guard alive, so it can outlive the guard.
+    /// Data registered from a borrowed slice (`register_encoded`, `register_decoded_*`)
+    /// can not outlive its guard, and returns an error. Register it with one of the
+    /// `*_owned` methods instead.
+    ///
+    /// Registered data is always read from memory, so [`SoundFlags::STREAM`] is ignored for
+    /// it. Registered files can be streamed.
+    pub fn build_sound(&self, engine: &Engine, flags: SoundFlags) -> MaResult<Sound> {
+        let same_rm = engine.resource_manager().map_or(false, |rm| {
+            private_rm::rm_ptr(&rm) == private_rm::rm_ptr(self.rm)
+        });
+        if !same_rm {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "the engine does not use the resource manager of the guard",
+            ))
+            .with_context(ErrorContext::Resource(self.name().into_owned())));
+        }
+        let resource = rm_registry::shared_store(self.id)?;
+
+        let mut sound = match &self.data_name {
+            RegisteredDataType::RegisteredPath { path } => engine
+                .new_sound_with_file_internal(path, flags, None, None)
+                .with_path(path)?,
+            RegisteredDataType::RegisteredData { name } => {
+                let mut flags = flags;
+                flags.remove(SoundFlags::STREAM);
+                engine
+                    .new_sound_with_file_internal(Path::new(name), flags, None, None)
+                    .with_resource(name)?
+            }
+        };
+        sound.resource = resource;
+        Ok(sound)
+    }
+}
+
+impl<'a, R: AsRmPtr + ?Sized> ResourceGuard<'a, R> {
+    /// Returns the name (or file path) the resource was registered under.
+    pub fn name(&self) -> std::borrow::Cow<'_, str> {
+        self.data_name.name()
+    }
+
+    /// Returns a snapshot of the reference count and memory usage of this resource.
+    ///
+    /// The reference count includes the registration itself, plus one for every live
+    /// buffer or sound built from the resource.
+    ///
+    /// Returns `None` once the name was registered again with [`DuplicatePolicy::Replace`].
+    pub fn stats(&self) -> Option<ResourceStats> {
+        rm_registry::stats(self.id)
+    }
 }
 
 // Private methods
 impl<'a, R: AsRmPtr + ?Sized> ResourceGuard<'a, R> {
-    pub(crate) fn from_path(rm: &'a R, path: &Path) -> Self {
-        Self {
-            rm,
-            data_name: RegisteredDataType::RegisteredPath {
-                path: path.to_path_buf(),
-            },
-            data_store: None,
-            _data_marker: PhantomData,
-        }
+    /// Registers a file with `register`, following the [`DuplicatePolicy`] of `rm`.
+    pub(crate) fn from_path(
+        rm: &'a R,
+        path: &Path,
+        flags: RmSourceFlags,
+        register: impl FnOnce() -> MaResult<()>,
+    ) -> MaResult<Self> {
+        let data_name = RegisteredDataType::RegisteredPath {
+            path: path.to_path_buf(),
+        };
+        let footprint = || Footprint::file(rm, path, flags);
+        let id = rm_registry::register(rm, &data_name, None, false, footprint, register)?;
+        Ok(Self::from_registered(rm, id, data_name))
     }
 
-    pub(crate) fn from_data(rm: &'a R, name: &str, data: Option<Arc<[u8]>>) -> Self {
+    /// Registers data with `register`, following the [`DuplicatePolicy`] of `rm`.
+    ///
+    /// `data` is `None` when the bytes are borrowed from the caller.
+    pub(crate) fn from_data(
+        rm: &'a R,
+        name: &str,
+        data: Option<DataStore>,
+        footprint: Footprint,
+        register: impl FnOnce() -> MaResult<()>,
+    ) -> MaResult<Self> {
+        let data_name = RegisteredDataType::RegisteredData {
+            name: name.to_string(),
+        };
+        let borrowed = data.is_none();
+        let id = rm_registry::register(rm, &data_name, data, borrowed, || footprint, register)?;
+        Ok(Self::from_registered(rm, id, data_name))
+    }
+
+    // A guard for an entry already counted by the registry
+    fn from_registered(rm: &'a R, id: u64, data_name: RegisteredDataType) -> Self {
         Self {
             rm,
-            data_name: RegisteredDataType::RegisteredData {
-                name: name.to_string(),
-            },
-            data_store: data,
+            id,
+            data_name,
             _data_marker: PhantomData,
         }
     }
 }
 
+impl<'a, R: AsRmPtr + ?Sized> Clone for ResourceGuard<'a, R> {
+    /// Returns another guard for the same resource, which stays registered until every
+    /// guard is dropped.
+    fn clone(&self) -> Self {
+        rm_registry::add_guard(self.id);
+        Self::from_registered(self.rm, self.id, self.data_name.clone())
+    }
+}
+
 impl<R: AsRmPtr + ?Sized> Drop for ResourceGuard<'_, R> {
     fn drop(&mut self) {
-        match &self.data_name {
-            RegisteredDataType::RegisteredData { name } => {
-                let _ = resource_ffi::ma_resource_manager_unregister_data_internal(self.rm, name);
-            }
-            RegisteredDataType::RegisteredPath { path } => {
-                let _ = resource_ffi::ma_resource_manager_unregister_file_internal(self.rm, path);
-            }
-        }
+        rm_registry::release(self.rm, self.id);
     }
 }
 
-enum RegisteredDataType {
+// Identifies a resource manager in the registry
+pub(crate) fn rm_key<R: AsRmPtr + ?Sized>(rm: &R) -> usize {
+    private_rm::rm_ptr(rm) as usize
+}
+
+/// What registering a name (or file path) that is already registered does.
+///
+/// Set with [`ResourceManagerBuilder::duplicate_policy`](rm_builder::ResourceManagerBuilder::duplicate_policy)
+/// or [`RmOps::set_duplicate_policy`]. The policy applies to every `register_*()` method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Fail with [`ErrorKinds::ResourceAlreadyRegistered`].
+    Error,
+    /// Keep the existing data and return another guard for it. The new data is not used.
+    ///
+    /// This is how miniaudio handles duplicates, and the default.
+    #[default]
+    Reuse,
+    /// Unregister the existing data and register the new data in its place.
+    ///
+    /// Guards of the old data stay valid but no longer refer to the name: they are not
+    /// listed by [`RmOps::registered_names`] and do not unregister the new data when
+    /// dropped. Fails with [`ErrorKinds::InvalidOperation`] while buffers built from the old
+    /// data are alive, as miniaudio would keep using it for the name.
+    Replace,
+}
+
+#[derive(Clone)]
+pub(crate) enum RegisteredDataType {
     RegisteredPath { path: PathBuf },
     RegisteredData { name: String },
 }
 
+impl RegisteredDataType {
+    pub(crate) fn name(&self) -> std::borrow::Cow<'_, str> {
+        match self {
+            RegisteredDataType::RegisteredPath { path } => path.to_string_lossy(),
+            RegisteredDataType::RegisteredData { name } => std::borrow::Cow::Borrowed(name),
+        }
+    }
+
+    pub(crate) fn unregister<R: AsRmPtr + ?Sized>(&self, rm: &R) {
+        let _ = match self {
+            RegisteredDataType::RegisteredData { name } => {
+                resource_ffi::ma_resource_manager_unregister_data_internal(rm, name)
+            }
+            RegisteredDataType::RegisteredPath { path } => {
+                resource_ffi::ma_resource_manager_unregister_file_internal(rm, path)
+            }
+        };
+    }
+}
+
 /// Result of building a resource-manager data source.
 ///
 /// When asynchronous loading is enabled, the resource may not be ready
@@ -627,7 +801,7 @@ impl<B: AsAsyncSource> PendingResource<B> {
                         }
                         Err(e) if e.is_busy() => Ok(false),
                         Err(e) => {
-                            *self = PendingResource::Failed(e);
+                            *self = PendingResource::Failed(e.clone());
                             Err(e)
                         }
                     }
@@ -635,7 +809,20 @@ impl<B: AsAsyncSource> PendingResource<B> {
                     unreachable!()
                 }
             }
-            PendingResource::Failed(e) => Err(*e),
+            PendingResource::Failed(e) => Err(e.clone()),
+        }
+    }
+
+    /// Returns how far loading got, for resources built with [`RmSourceFlags::ASYNC`].
+    ///
+    /// Returns `None` if the load failed. See [`LoadProgress`].
+    pub fn load_progress(&self) -> Option<LoadProgress<'_>> {
+        match self {
+            PendingResource::Ready { inner } => Some(private_async_src::load_progress(inner)),
+            PendingResource::Pending { inner } => {
+                inner.as_ref().map(private_async_src::load_progress)
+            }
+            PendingResource::Failed(_) => None,
         }
     }
 
@@ -674,6 +861,7 @@ mod private_async_src {
 
     pub trait AsyncCheckProvider<T: ?Sized> {
         fn as_result_check(t: &T) -> MaResult<()>;
+        fn as_load_progress(t: &T) -> LoadProgress<'_>;
     }
 
     pub struct SourceCheckProvider;
@@ -684,23 +872,39 @@ mod private_async_src {
         fn as_result_check(t: &ResourceManagerSource<'a, R>) -> MaResult<()> {
             resource_ffi::ma_resource_manager_data_source_result(t)
         }
+
+        fn as_load_progress<'t>(t: &'t ResourceManagerSource<'a, R>) -> LoadProgress<'t> {
+            LoadProgress::from_data_source(t.to_raw())
+        }
     }
 
     impl<'a, R: AsRmPtr> AsyncCheckProvider<ResourceManagerBuffer<'a, R>> for BufferCheckProvider {
         fn as_result_check(t: &ResourceManagerBuffer<'a, R>) -> MaResult<()> {
             resource_ffi::ma_resource_manager_data_buffer_result(t)
         }
+
+        fn as_load_progress<'t>(t: &'t ResourceManagerBuffer<'a, R>) -> LoadProgress<'t> {
+            LoadProgress::from_buffer(t.to_raw())
+        }
     }
 
     impl<'a, R: AsRmPtr> AsyncCheckProvider<ResourceManagerStream<'a, R>> for StreamCheckProvider {
         fn as_result_check(t: &ResourceManagerStream<'a, R>) -> MaResult<()> {
             resource_ffi::ma_resource_manager_data_stream_result(t)
         }
+
+        fn as_load_progress<'t>(t: &'t ResourceManagerStream<'a, R>) -> LoadProgress<'t> {
+            LoadProgress::from_stream(t.to_raw())
+        }
     }
 
     pub fn result_check<T: AsAsyncSource + ?Sized>(t: &T) -> MaResult<()> {
         <T as AsAsyncSource>::__ResultProvider::as_result_check(t)
     }
+
+    pub fn load_progress<T: AsAsyncSource + ?Sized>(t: &T) -> LoadProgress<'_> {
+        <T as AsAsyncSource>::__ResultProvider::as_load_progress(t)
+    }
 }
 
 /// Unifies async result checks across sources.
@@ -745,8 +949,9 @@ pub trait RmOps: AsRmPtr {
             use crate::engine::cstring_from_path;
 
             let c_path = cstring_from_path(path)?;
-            resource_ffi::ma_resource_manager_register_file(self, c_path, flags)?;
-            Ok(ResourceGuard::from_path(self, path))
+            ResourceGuard::from_path(self, path, flags, || {
+                resource_ffi::ma_resource_manager_register_file(self, c_path, flags)
+            })
         }
 
         #[cfg(windows)]
@@ -755,8 +960,9 @@ pub trait RmOps: AsRmPtr {
 
             let c_path = wide_null_terminated(path);
 
-            resource_ffi::ma_resource_manager_register_file_w(self, &c_path, flags)?;
-            Ok(ResourceGuard::from_path(self, path))
+            ResourceGuard::from_path(self, path, flags, || {
+                resource_ffi::ma_resource_manager_register_file_w(self, &c_path, flags)
+            })
         }
 
         #[cfg(not(any(unix, windows)))]
@@ -782,15 +988,43 @@ pub trait RmOps: AsRmPtr {
         channels: u32,
         sample_rate: SampleRate,
     ) -> MaResult<ResourceGuard<'a, Self>> {
-        resource_ffi::ma_resource_manager_register_decoded_data_internal::<u8, Self>(
+        ResourceGuard::from_data(self, name, None, Footprint::decoded(data), || {
+            resource_ffi::ma_resource_manager_register_decoded_data_internal::<u8, Self>(
+                self,
+                name,
+                data,
+                Format::U8,
+                channels,
+                sample_rate,
+            )
+        })
+    }
+
+    /// Same as [`RmOps::register_decoded_u8`], but the guard owns `data`, so it does not
+    /// have to be kept alive separately.
+    fn register_decoded_u8_owned(
+        &self,
+        name: &str,
+        data: Arc<[u8]>,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<ResourceGuard<'_, Self>> {
+        ResourceGuard::from_data(
             self,
             name,
-            data,
-            Format::U8,
-            channels,
-            sample_rate,
-        )?;
-        Ok(ResourceGuard::from_data(self, name, None))
+            Some(DataStore::owned(data.clone())),
+            Footprint::decoded(&data),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_internal::<u8, Self>(
+                    self,
+                    name,
+                    &data,
+                    Format::U8,
+                    channels,
+                    sample_rate,
+                )
+            },
+        )
     }
 
     /// The [`RmSourceFlags`] used are:
@@ -812,15 +1046,43 @@ pub trait RmOps: AsRmPtr {
         channels: u32,
         sample_rate: SampleRate,
     ) -> MaResult<ResourceGuard<'a, Self>> {
-        resource_ffi::ma_resource_manager_register_decoded_data_internal::<i16, Self>(
+        ResourceGuard::from_data(self, name, None, Footprint::decoded(data), || {
+            resource_ffi::ma_resource_manager_register_decoded_data_internal::<i16, Self>(
+                self,
+                name,
+                data,
+                Format::S16,
+                channels,
+                sample_rate,
+            )
+        })
+    }
+
+    /// Same as [`RmOps::register_decoded_i16`], but the guard owns `data`, so it does not
+    /// have to be kept alive separately.
+    fn register_decoded_i16_owned(
+        &self,
+        name: &str,
+        data: Arc<[i16]>,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<ResourceGuard<'_, Self>> {
+        ResourceGuard::from_data(
             self,
             name,
-            data,
-            Format::S16,
-            channels,
-            sample_rate,
-        )?;
-        Ok(ResourceGuard::from_data(self, name, None))
+            Some(DataStore::owned(data.clone())),
+            Footprint::decoded(&data),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_internal::<i16, Self>(
+                    self,
+                    name,
+                    &data,
+                    Format::S16,
+                    channels,
+                    sample_rate,
+                )
+            },
+        )
     }
 
     /// The [`RmSourceFlags`] used are:
@@ -842,15 +1104,43 @@ pub trait RmOps: AsRmPtr {
         channels: u32,
         sample_rate: SampleRate,
     ) -> MaResult<ResourceGuard<'a, Self>> {
-        resource_ffi::ma_resource_manager_register_decoded_data_internal::<i32, Self>(
+        ResourceGuard::from_data(self, name, None, Footprint::decoded(data), || {
+            resource_ffi::ma_resource_manager_register_decoded_data_internal::<i32, Self>(
+                self,
+                name,
+                data,
+                Format::S32,
+                channels,
+                sample_rate,
+            )
+        })
+    }
+
+    /// Same as [`RmOps::register_decoded_i32`], but the guard owns `data`, so it does not
+    /// have to be kept alive separately.
+    fn register_decoded_i32_owned(
+        &self,
+        name: &str,
+        data: Arc<[i32]>,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<ResourceGuard<'_, Self>> {
+        ResourceGuard::from_data(
             self,
             name,
-            data,
-            Format::S32,
-            channels,
-            sample_rate,
-        )?;
-        Ok(ResourceGuard::from_data(self, name, None))
+            Some(DataStore::owned(data.clone())),
+            Footprint::decoded(&data),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_internal::<i32, Self>(
+                    self,
+                    name,
+                    &data,
+                    Format::S32,
+                    channels,
+                    sample_rate,
+                )
+            },
+        )
     }
 
     /// The [`RmSourceFlags`] used are:
@@ -872,15 +1162,43 @@ pub trait RmOps: AsRmPtr {
         channels: u32,
         sample_rate: SampleRate,
     ) -> MaResult<ResourceGuard<'a, Self>> {
-        resource_ffi::ma_resource_manager_register_decoded_data_internal::<S24Packed, Self>(
+        ResourceGuard::from_data(self, name, None, Footprint::decoded(data), || {
+            resource_ffi::ma_resource_manager_register_decoded_data_internal::<S24Packed, Self>(
+                self,
+                name,
+                data,
+                Format::S24Packed,
+                channels,
+                sample_rate,
+            )
+        })
+    }
+
+    /// Same as [`RmOps::register_decoded_s24_packed`], but the guard owns `data`, so it does not
+    /// have to be kept alive separately.
+    fn register_decoded_s24_packed_owned(
+        &self,
+        name: &str,
+        data: Arc<[u8]>,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<ResourceGuard<'_, Self>> {
+        ResourceGuard::from_data(
             self,
             name,
-            data,
-            Format::S24Packed,
-            channels,
-            sample_rate,
-        )?;
-        Ok(ResourceGuard::from_data(self, name, None))
+            Some(DataStore::owned(data.clone())),
+            Footprint::decoded(&data),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_internal::<S24Packed, Self>(
+                    self,
+                    name,
+                    &data,
+                    Format::S24Packed,
+                    channels,
+                    sample_rate,
+                )
+            },
+        )
     }
 
     /// The [`RmSourceFlags`] used are:
@@ -923,15 +1241,23 @@ pub trait RmOps: AsRmPtr {
             frames,
             channels as usize,
         )?;
-        resource_ffi::ma_resource_manager_register_decoded_data_internal::<S24Packed, Self>(
+        let dst: Arc<[u8]> = dst.into();
+        ResourceGuard::from_data(
             self,
             name,
-            &dst,
-            Format::S24Packed,
-            channels,
-            sample_rate,
-        )?;
-        Ok(ResourceGuard::from_data(self, name, Some(dst.into())))
+            Some(DataStore::owned(dst.clone())),
+            Footprint::decoded(&dst),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_internal::<S24Packed, Self>(
+                    self,
+                    name,
+                    &dst,
+                    Format::S24Packed,
+                    channels,
+                    sample_rate,
+                )
+            },
+        )
     }
 
     /// The [`RmSourceFlags`] used are:
@@ -953,15 +1279,43 @@ pub trait RmOps: AsRmPtr {
         channels: u32,
         sample_rate: SampleRate,
     ) -> MaResult<ResourceGuard<'a, Self>> {
-        resource_ffi::ma_resource_manager_register_decoded_data_internal::<f32, Self>(
+        ResourceGuard::from_data(self, name, None, Footprint::decoded(data), || {
+            resource_ffi::ma_resource_manager_register_decoded_data_internal::<f32, Self>(
+                self,
+                name,
+                data,
+                Format::F32,
+                channels,
+                sample_rate,
+            )
+        })
+    }
+
+    /// Same as [`RmOps::register_decoded_f32`], but the guard owns `data`, so it does not
+    /// have to be kept alive separately.
+    fn register_decoded_f32_owned(
+        &self,
+        name: &str,
+        data: Arc<[f32]>,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<ResourceGuard<'_, Self>> {
+        ResourceGuard::from_data(
             self,
             name,
-            data,
-            Format::F32,
-            channels,
-            sample_rate,
-        )?;
-        Ok(ResourceGuard::from_data(self, name, None))
+            Some(DataStore::owned(data.clone())),
+            Footprint::decoded(&data),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_internal::<f32, Self>(
+                    self,
+                    name,
+                    &data,
+                    Format::F32,
+                    channels,
+                    sample_rate,
+                )
+            },
+        )
     }
 
     /// Registers encoded/compressed audio bytes under a name.
@@ -974,8 +1328,258 @@ pub trait RmOps: AsRmPtr {
         name: &str,
         data: &'a [u8],
     ) -> MaResult<ResourceGuard<'a, Self>> {
-        resource_ffi::ma_resource_manager_register_encoded_data_internal(self, name, data)?;
-        Ok(ResourceGuard::from_data(self, name, None))
+        ResourceGuard::from_data(self, name, None, Footprint::encoded(data), || {
+            resource_ffi::ma_resource_manager_register_encoded_data_internal(self, name, data)
+        })
+    }
+
+    /// Registers encoded/compressed audio bytes under a name, without copying them.
+    ///
+    /// Same as [`RmOps::register_encoded`], but the guard owns `data`, so it does not have to
+    /// be kept alive separately. Clone the `Arc` to keep using the bytes elsewhere.
+    fn register_encoded_owned(
+        &self,
+        name: &str,
+        data: Arc<[u8]>,
+    ) -> MaResult<ResourceGuard<'_, Self>> {
+        ResourceGuard::from_data(
+            self,
+            name,
+            Some(DataStore::owned(data.clone())),
+            Footprint::encoded(&data),
+            || resource_ffi::ma_resource_manager_register_encoded_data_internal(self, name, &data),
+        )
+    }
+
+    /// Registers a memory-mapped encoded file under a name.
+    ///
+    /// Same as [`RmOps::register_encoded`], but the guard keeps a clone of the mapping alive
+    /// instead of borrowing the bytes, so the file is never read into memory as a whole.
+    #[cfg(feature = "memmap2")]
+    fn register_mapped<'a>(
+        &'a self,
+        name: &str,
+        file: &MappedFile,
+    ) -> MaResult<ResourceGuard<'a, Self>> {
+        ResourceGuard::from_data(
+            self,
+            name,
+            Some(DataStore::Mapped(file.clone())),
+            Footprint::encoded(file),
+            || resource_ffi::ma_resource_manager_register_encoded_data_internal(self, name, file),
+        )
+    }
+
+    /// Decodes the frames in `start_frame..end_frame` of a file and registers them as
+    /// decoded data under `name`.
+    ///
+    /// Only the requested slice is kept in memory, which is useful for long files where only
+    /// a short section is needed (e.g. one loop out of a longer recording). The file is not
+    /// registered itself, and the slice is decoded in the resource manager's format.
+    ///
+    /// The slice is a separate resource. Build buffers or sounds from it using `name`,
+    /// the same way as other registered data. If the file is shorter than `end_frame`,
+    /// the slice ends at the end of the file.
+    ///
+    /// Returns an error if `start_frame >= end_frame` or `start_frame` is past the end of the file.
+    fn register_file_range<'a>(
+        &'a self,
+        path: &Path,
+        name: &str,
+        start_frame: u64,
+        end_frame: u64,
+    ) -> MaResult<ResourceGuard<'a, Self>>
+    where
+        Self: Sized,
+    {
+        if start_frame >= end_frame {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+
+        let mut source = ResourceManagerBufferBuilder::new(self)
+            .file_path(path)
+            .build_internal()?;
+        let data_format = source.data_format()?;
+        let length = source.length_in_pcm_frames()?;
+        if start_frame >= length {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+
+        let frames = end_frame.min(length) - start_frame;
+        let bytes_per_frame = data_format.format.bytes_per_sample() * data_format.channels as usize;
+        let len = usize::try_from(frames)
+            .ok()
+            .and_then(|f| f.checked_mul(bytes_per_frame))
+            .ok_or(MaudioError::new_ma_error(
+                crate::ErrorKinds::IntegerOverflow {
+                    op: "range frames to bytes",
+                },
+            ))?;
+
+        let mut data = vec![0u8; len];
+        source.seek_to_pcm_frame(start_frame)?;
+        let frames_read = data_source_ffi::ma_data_source_read_pcm_frames_internal(
+            &mut source,
+            frames,
+            data.as_mut_ptr() as *mut core::ffi::c_void,
+        )?;
+        data.truncate(frames_read as usize * bytes_per_frame);
+        let data: Arc<[u8]> = data.into();
+
+        ResourceGuard::from_data(
+            self,
+            name,
+            Some(DataStore::owned(data.clone())),
+            Footprint::decoded(&data),
+            || {
+                resource_ffi::ma_resource_manager_register_decoded_data_named(
+                    self,
+                    name,
+                    data.as_ptr() as *const core::ffi::c_void,
+                    frames_read,
+                    data_format.format,
+                    data_format.channels,
+                    data_format.sample_rate,
+                )
+            },
+        )
+    }
+
+    /// Returns `true` if a live [`ResourceGuard`] holds `name`.
+    ///
+    /// Files are registered under their path, as returned by [`ResourceGuard::name`].
+    fn is_registered(&self, name: &str) -> bool {
+        rm_registry::is_registered(rm_key(self), name)
+    }
+
+    /// Returns the names of every resource registered through this resource manager, sorted.
+    ///
+    /// Resources registered from another handle to the same resource manager (for example
+    /// the [`ResourceManagerRef`] of an engine using it) are included.
+    fn registered_names(&self) -> Vec<String> {
+        rm_registry::names(rm_key(self))
+    }
+
+    /// Returns a new guard for a resource registered elsewhere, for example by an asset
+    /// loader on another thread.
+    ///
+    /// The resource stays registered until every guard for it is dropped, so systems can
+    /// build sources from it without knowing who registered it.
+    ///
+    /// Returns an error if nothing is registered under `name`, or if the resource was
+    /// registered from borrowed data (`register_encoded`, `register_decoded_*`). Borrowed
+    /// data can only be shared by cloning the original guard, which keeps the borrow.
+    fn acquire(&self, name: &str) -> MaResult<ResourceGuard<'_, Self>> {
+        let (id, data_name) = rm_registry::acquire(rm_key(self), name)?;
+        Ok(ResourceGuard::from_registered(self, id, data_name))
+    }
+
+    /// Sets what registering an already registered name does, see [`DuplicatePolicy`].
+    ///
+    /// The policy is shared by every handle to the resource manager.
+    fn set_duplicate_policy(&self, policy: DuplicatePolicy) {
+        rm_registry::set_policy(rm_key(self), policy);
+    }
+
+    /// Returns the [`DuplicatePolicy`] used when registering resources.
+    fn duplicate_policy(&self) -> DuplicatePolicy {
+        rm_registry::policy(rm_key(self))
+    }
+
+    /// Returns the [`RmFlags`] the resource manager was created with.
+    fn flags(&self) -> RmFlags {
+        resource_ffi::rm_flags(self)
+    }
+
+    /// Returns the number of job threads owned by the resource manager.
+    ///
+    /// When this is `0`, jobs must be processed manually, see [`RmOps::job_runner`].
+    fn job_thread_count(&self) -> u32 {
+        resource_ffi::rm_job_thread_count(self)
+    }
+
+    /// Returns the format that resources are decoded to.
+    ///
+    /// Returns an error if the resource manager keeps the native format of each file.
+    fn decoded_format(&self) -> MaResult<Format> {
+        resource_ffi::rm_decoded_format(self)
+    }
+
+    /// Returns `true` if the job queue was created with [`RmFlags::NON_BLOCKING`].
+    ///
+    /// In non-blocking mode, fetching a job from an empty queue returns immediately
+    /// instead of waiting for a job to be posted.
+    fn is_non_blocking(&self) -> bool {
+        self.flags().contains(RmFlags::NON_BLOCKING)
+    }
+
+    /// Takes the next job from the queue and processes it.
+    ///
+    /// Only useful when the resource manager was built with `job_thread_count(0)`.
+    /// Otherwise the internal job threads compete for the same jobs.
+    ///
+    /// Unless the resource manager is non-blocking, this waits until a job is available.
+    fn process_next_job(&self) -> MaResult<JobStatus> {
+        resource_ffi::ma_resource_manager_process_next_job(self)
+    }
+
+    /// Takes the next job from the queue without processing it.
+    ///
+    /// Returns `Ok(None)` if the queue is empty (non-blocking mode only) or if a quit
+    /// job was posted. The returned [`RmJob`] must be processed, see [`RmJob::process`].
+    ///
+    /// Unless the resource manager is non-blocking, this waits until a job is available.
+    fn next_job(&self) -> MaResult<Option<RmJob<'_, Self>>> {
+        let mut job: sys::ma_job = unsafe { std::mem::zeroed() };
+        match resource_ffi::ma_resource_manager_next_job(self, &mut job)? {
+            JobStatus::Processed => Ok(Some(RmJob::new(self, job))),
+            JobStatus::Empty | JobStatus::Quit => Ok(None),
+        }
+    }
+
+    /// Posts a quit job to the queue.
+    ///
+    /// Wakes up and stops any thread waiting on the queue, including a [`JobRunner`].
+    /// The quit job stays in the queue, so every consumer will see it.
+    fn post_job_quit(&self) -> MaResult<()> {
+        resource_ffi::ma_resource_manager_post_job_quit(self)
+    }
+
+    /// Posts `f` to the job queue, to be run on a job thread or by a [`JobRunner`].
+    ///
+    /// Never blocks. Jobs still in the queue when the resource manager is dropped never run,
+    /// and their closures are leaked. See [`util::jobs`](crate::util::jobs) for a queue
+    /// dedicated to user jobs.
+    fn post_custom_job<F: FnOnce() + Send + 'static>(&self, f: F) -> MaResult<()>
+    where
+        Self: Sized,
+    {
+        let mut job = jobs::custom_job(f);
+        let res = resource_ffi::ma_resource_manager_post_job(self, &job);
+        if res.is_err() {
+            unsafe { jobs::free_custom_job(&mut job) };
+        }
+        res
+    }
+
+    /// Returns a [`JobRunner`] used to pump resource manager jobs from a user thread.
+    fn job_runner(&self) -> JobRunner<'_, Self> {
+        JobRunner::new(self)
+    }
+
+    /// Takes a snapshot of every resource registered with the resource manager,
+    /// including their reference counts and memory usage.
+    ///
+    /// See [`rm_stats`](crate::engine::resource::rm_stats).
+    fn memory_stats(&self) -> RmMemoryStats {
+        RmMemoryStats {
+            resources: rm_registry::all_stats(rm_key(self)),
+        }
+    }
+
+    /// Returns the total bytes of audio data resident in memory.
+    fn resident_bytes(&self) -> u64 {
+        self.memory_stats().total_bytes()
     }
 }
 
@@ -983,15 +1587,24 @@ impl<F: PcmFormat> ResourceManager<F> {
     fn new_with_config(config: &ResourceManagerBuilder) -> MaResult<Self> {
         let mut mem: Box<MaybeUninit<sys::ma_resource_manager>> = Box::new(MaybeUninit::uninit());
 
-        resource_ffi::ma_resource_manager_init(config.as_raw_ptr(), mem.as_mut_ptr())?;
+        // The backends are owned by the resource manager, the builder can be reused
+        let mut backends = config.registered_backends()?;
+        let mut raw_config = *config.as_raw();
+        if let Some(backends) = backends.as_mut() {
+            backends.apply(&mut raw_config);
+        }
+
+        resource_ffi::ma_resource_manager_init(&raw_config, mem.as_mut_ptr())?;
 
         let inner: *mut sys::ma_resource_manager =
             Box::into_raw(mem) as *mut sys::ma_resource_manager;
+        rm_registry::set_policy(inner as usize, config.duplicate_policy);
 
         Ok(Self {
             inner: Arc::new(InnerResourceManager {
                 inner,
                 channels: None,
+                _backends: backends,
                 _format: PhantomData,
             }),
         })
@@ -1003,7 +1616,7 @@ pub(crate) mod resource_ffi {
 
     use crate::{
         audio::{
-            channels::Channel,
+            channels::ChannelLayout,
             formats::{Format, SampleBuffer},
             sample_rate::SampleRate,
         },
@@ -1011,6 +1624,8 @@ pub(crate) mod resource_ffi {
         engine::resource::{
             private_rm,
             rm_buffer::{ResourceManagerBuffer, ResourceManagerBufferBuilder},
+            rm_flags::RmFlags,
+            rm_jobs::JobStatus,
             rm_source::{ResourceManagerSource, ResourceManagerSourceBuilder},
             rm_source_flags::RmSourceFlags,
             rm_stream::{ResourceManagerStream, ResourceManagerStreamBuilder},
@@ -1122,6 +1737,27 @@ pub(crate) mod resource_ffi {
 
         let frame_count = (data_len / units_per_frame) as u64;
 
+        ma_resource_manager_register_decoded_data_named(
+            rm,
+            name,
+            data.as_ptr() as *const _,
+            frame_count,
+            format,
+            channels,
+            sample_rate,
+        )
+    }
+
+    // `data` must hold `frame_count` frames of `format` and outlive the registration
+    pub fn ma_resource_manager_register_decoded_data_named<R: AsRmPtr + ?Sized>(
+        rm: &R,
+        name: &str,
+        data: *const core::ffi::c_void,
+        frame_count: u64,
+        format: Format,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<()> {
         #[cfg(unix)]
         {
             let name = std::ffi::CString::new(name)
@@ -1129,7 +1765,7 @@ pub(crate) mod resource_ffi {
             ma_resource_manager_register_decoded_data(
                 rm,
                 name.as_ptr(),
-                data.as_ptr() as *const _,
+                data,
                 frame_count,
                 format,
                 channels,
@@ -1144,7 +1780,7 @@ pub(crate) mod resource_ffi {
             ma_resource_manager_register_decoded_data_w(
                 rm,
                 &name,
-                data.as_ptr() as *const _,
+                data,
                 frame_count,
                 format,
                 channels,
@@ -1765,9 +2401,7 @@ pub(crate) mod resource_ffi {
         };
         MaudioError::check(res)?;
         // Could cast when passing the ptr to miniaudio, but copying should be fine here
-        let mut channel_map: Vec<Channel> =
-            channel_map_raw.into_iter().map(Channel::from_raw).collect();
-        channel_map.truncate(channels as usize);
+        let channel_map = ChannelLayout::from_raw_count(&channel_map_raw, channels)?;
 
         Ok(DataFormat {
             format: format_raw.try_into()?,
@@ -2006,9 +2640,7 @@ pub(crate) mod resource_ffi {
         };
         MaudioError::check(res)?;
         // Could cast when passing the ptr to miniaudio, but copying should be fine here
-        let mut channel_map: Vec<Channel> =
-            channel_map_raw.into_iter().map(Channel::from_raw).collect();
-        channel_map.truncate(channels as usize);
+        let channel_map = ChannelLayout::from_raw_count(&channel_map_raw, channels)?;
 
         Ok(DataFormat {
             format: format_raw.try_into()?,
@@ -2102,7 +2734,7 @@ pub(crate) mod resource_ffi {
 
     // JOB MANAGEMENT
     #[allow(unused)]
-    pub fn ma_resource_manager_post_job<R: AsRmPtr>(
+    pub fn ma_resource_manager_post_job<R: AsRmPtr + ?Sized>(
         rm: &R,
         job: *const sys::ma_job,
     ) -> MaResult<()> {
@@ -2111,29 +2743,68 @@ pub(crate) mod resource_ffi {
     }
 
     #[inline]
-    #[allow(unused)]
-    pub fn ma_resource_manager_post_job_quit<R: AsRmPtr>(rm: &R) -> MaResult<()> {
+    pub fn ma_resource_manager_post_job_quit<R: AsRmPtr + ?Sized>(rm: &R) -> MaResult<()> {
         let res = unsafe { sys::ma_resource_manager_post_job_quit(private_rm::rm_ptr(rm)) };
         MaudioError::check(res)
     }
 
     #[inline]
-    #[allow(unused)]
-    pub fn ma_resource_manager_next_job<R: AsRmPtr>(rm: &R, job: *mut sys::ma_job) -> MaResult<()> {
+    pub fn ma_resource_manager_next_job<R: AsRmPtr + ?Sized>(
+        rm: &R,
+        job: *mut sys::ma_job,
+    ) -> MaResult<JobStatus> {
         let res = unsafe { sys::ma_resource_manager_next_job(private_rm::rm_ptr(rm), job) };
+        job_status(res)
+    }
+
+    #[inline]
+    pub fn ma_resource_manager_process_job<R: AsRmPtr + ?Sized>(
+        rm: &R,
+        job: *mut sys::ma_job,
+    ) -> MaResult<()> {
+        let res = unsafe { sys::ma_resource_manager_process_job(private_rm::rm_ptr(rm), job) };
         MaudioError::check(res)
     }
 
     #[inline]
-    #[allow(unused)]
-    pub fn ma_resource_manager_process_next_job<R: AsRmPtr>(rm: &R) -> MaResult<()> {
+    pub fn ma_resource_manager_process_next_job<R: AsRmPtr + ?Sized>(
+        rm: &R,
+    ) -> MaResult<JobStatus> {
         let res = unsafe { sys::ma_resource_manager_process_next_job(private_rm::rm_ptr(rm)) };
-        MaudioError::check(res)
+        job_status(res)
+    }
+
+    // MA_NO_DATA_AVAILABLE is only returned by a non-blocking queue with no jobs.
+    // MA_CANCELLED means a quit job was received.
+    #[inline]
+    fn job_status(res: sys::ma_result) -> MaResult<JobStatus> {
+        match res {
+            sys::ma_result_MA_SUCCESS => Ok(JobStatus::Processed),
+            sys::ma_result_MA_NO_DATA_AVAILABLE => Ok(JobStatus::Empty),
+            sys::ma_result_MA_CANCELLED => Ok(JobStatus::Quit),
+            _ => Err(MaudioError::from_ma_result(res)),
+        }
+    }
+
+    #[inline]
+    pub fn rm_flags<R: AsRmPtr + ?Sized>(rm: &R) -> RmFlags {
+        RmFlags::from_bits(unsafe { (*private_rm::rm_ptr(rm)).config.flags })
+    }
+
+    #[inline]
+    pub fn rm_job_thread_count<R: AsRmPtr + ?Sized>(rm: &R) -> u32 {
+        unsafe { (*private_rm::rm_ptr(rm)).config.jobThreadCount }
+    }
+
+    #[inline]
+    pub fn rm_decoded_format<R: AsRmPtr + ?Sized>(rm: &R) -> MaResult<Format> {
+        Format::try_from(unsafe { (*private_rm::rm_ptr(rm)).config.decodedFormat })
     }
 }
 
 impl<F: PcmFormat> Drop for InnerResourceManager<F> {
     fn drop(&mut self) {
+        rm_registry::forget(self.inner as usize);
         resource_ffi::ma_resource_manager_uninit(self);
         drop(unsafe { Box::from_raw(self.inner) });
     }
@@ -2151,16 +2822,21 @@ fn tiny_test_wav_mono(frames: usize) -> Vec<u8> {
 #[cfg(test)]
 mod test {
     use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::DataSourceOps,
         engine::resource::{
             rm_builder::ResourceManagerBuilder, rm_source::ResourceManagerSourceBuilder,
-            rm_source_flags::RmSourceFlags, tiny_test_wav_mono, RmOps,
+            rm_source_flags::RmSourceFlags, tiny_test_wav_mono, AsRmPtr, DuplicatePolicy,
+            ResourceGuard, RmOps,
         },
+        sound::sound_flags::SoundFlags,
         test_assets::{
             decoded_data::{
                 asset_interleaved_f32, asset_interleaved_i16, asset_interleaved_i32,
                 asset_interleaved_s24_i32, asset_interleaved_s24_packed_le, asset_interleaved_u8,
             },
             temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le,
         },
     };
 
@@ -2181,6 +2857,16 @@ mod test {
         drop(rm);
     }
 
+    #[test]
+    fn test_resource_man_decoded_format() {
+        use crate::audio::formats::Format;
+
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        assert_eq!(rm.decoded_format().unwrap(), Format::F32);
+        let rm = ResourceManagerBuilder::new().build_i16().unwrap();
+        assert_eq!(rm.decoded_format().unwrap(), Format::S16);
+    }
+
     #[test]
     fn test_resource_man_basic_register_file() {
         let rm = ResourceManagerBuilder::new().build_f32().unwrap();
@@ -2204,6 +2890,283 @@ mod test {
         let _src = guard.build_source(RmSourceFlags::NONE).unwrap();
     }
 
+    #[test]
+    fn test_resource_man_register_encoded_owned() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav: std::sync::Arc<[u8]> = tiny_test_wav_mono(20).into();
+        let guard = rm
+            .register_encoded_owned("test:owned", wav.clone())
+            .unwrap();
+        // The guard holds the only other reference
+        drop(wav);
+
+        let mut buf = guard
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .ok()
+            .unwrap();
+        assert_eq!(buf.read_pcm_frames(20).unwrap().frames(), 20);
+    }
+
+    #[test]
+    fn test_resource_man_registered_names() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav: std::sync::Arc<[u8]> = tiny_test_wav_mono(20).into();
+        let b = rm.register_encoded_owned("names:b", wav.clone()).unwrap();
+        let a = rm.register_encoded_owned("names:a", wav).unwrap();
+        assert!(rm.is_registered("names:a"));
+        assert!(!rm.is_registered("names:c"));
+        assert_eq!(rm.registered_names(), vec!["names:a", "names:b"]);
+
+        // Names are tracked per resource manager
+        let other = ResourceManagerBuilder::new().build_f32().unwrap();
+        assert!(other.registered_names().is_empty());
+
+        drop(a);
+        assert_eq!(rm.registered_names(), vec!["names:b"]);
+        drop(b);
+        assert!(rm.registered_names().is_empty());
+    }
+
+    #[test]
+    fn test_resource_man_acquire_from_another_thread() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav: std::sync::Arc<[u8]> = tiny_test_wav_mono(20).into();
+        let guard = rm.register_encoded_owned("shared:wav", wav).unwrap();
+
+        let handle = rm.clone();
+        let frames = std::thread::spawn(move || {
+            let guard = handle.acquire("shared:wav").unwrap();
+            let mut buf = guard
+                .build_buffer(RmSourceFlags::NONE)
+                .unwrap()
+                .into_ready()
+                .ok()
+                .unwrap();
+            buf.read_pcm_frames(20).unwrap().frames()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(frames, 20);
+
+        // The other thread's guard is gone, the original still holds the resource
+        assert!(rm.is_registered("shared:wav"));
+        drop(guard);
+        assert!(!rm.is_registered("shared:wav"));
+
+        let Err(err) = rm.acquire("shared:wav") else {
+            panic!("the resource was unregistered");
+        };
+        assert_eq!(
+            err.context(),
+            Some(&crate::ErrorContext::Resource("shared:wav".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_resource_man_clone_guard_keeps_resource() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav = tiny_test_wav_mono(20);
+        let guard = rm.register_encoded("borrowed:wav", &wav).unwrap();
+        // Borrowed data can not be acquired by name
+        assert!(rm.acquire("borrowed:wav").is_err());
+
+        let clone = guard.clone();
+        drop(guard);
+        assert!(rm.is_registered("borrowed:wav"));
+        let mut buf = clone
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .ok()
+            .unwrap();
+        assert_eq!(buf.read_pcm_frames(20).unwrap().frames(), 20);
+        drop(buf);
+        drop(clone);
+        assert!(!rm.is_registered("borrowed:wav"));
+    }
+
+    fn registered_frames<R: AsRmPtr>(guard: &ResourceGuard<'_, R>) -> u64 {
+        let buf = guard
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .ok()
+            .unwrap();
+        buf.length_in_pcm_frames().unwrap()
+    }
+
+    #[test]
+    fn test_resource_man_duplicate_policy_reuse() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        assert_eq!(rm.duplicate_policy(), DuplicatePolicy::Reuse);
+        let first = rm
+            .register_encoded_owned("dup:reuse", tiny_test_wav_mono(20).into())
+            .unwrap();
+        let second = rm
+            .register_encoded_owned("dup:reuse", tiny_test_wav_mono(40).into())
+            .unwrap();
+        // The first data is kept
+        assert_eq!(registered_frames(&second), 20);
+
+        drop(first);
+        assert!(rm.is_registered("dup:reuse"));
+        assert_eq!(registered_frames(&second), 20);
+        drop(second);
+        assert!(!rm.is_registered("dup:reuse"));
+    }
+
+    #[test]
+    fn test_resource_man_duplicate_policy_error() {
+        let rm = ResourceManagerBuilder::new()
+            .duplicate_policy(DuplicatePolicy::Error)
+            .build_f32()
+            .unwrap();
+        let wav = tiny_test_wav_mono(20);
+        let _guard = rm.register_encoded("dup:error", &wav).unwrap();
+        let Err(err) = rm.register_encoded("dup:error", &wav) else {
+            panic!("the duplicate was registered");
+        };
+        assert_eq!(
+            err.kind(),
+            Some(&crate::ErrorKinds::ResourceAlreadyRegistered {
+                name: "dup:error".to_owned()
+            })
+        );
+        // Other names are not affected
+        rm.register_encoded("dup:other", &wav).unwrap();
+    }
+
+    #[test]
+    fn test_resource_man_duplicate_policy_replace() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        rm.set_duplicate_policy(DuplicatePolicy::Replace);
+        assert_eq!(rm.duplicate_policy(), DuplicatePolicy::Replace);
+        let old = rm
+            .register_encoded_owned("dup:replace", tiny_test_wav_mono(20).into())
+            .unwrap();
+
+        // A live buffer still uses the old data
+        let buf = old
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .ok()
+            .unwrap();
+        assert!(rm
+            .register_encoded_owned("dup:replace", tiny_test_wav_mono(40).into())
+            .is_err());
+        drop(buf);
+
+        let new = rm
+            .register_encoded_owned("dup:replace", tiny_test_wav_mono(40).into())
+            .unwrap();
+        assert_eq!(registered_frames(&new), 40);
+        assert_eq!(rm.registered_names(), vec!["dup:replace"]);
+
+        // The old guard no longer owns the name
+        drop(old);
+        assert!(rm.is_registered("dup:replace"));
+        assert_eq!(registered_frames(&new), 40);
+        drop(new);
+        assert!(!rm.is_registered("dup:replace"));
+    }
+
+    #[test]
+    fn test_resource_man_build_sound_outlives_guard() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let engine = crate::engine::engine_builder::EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .resource_manager(&rm)
+            .build()
+            .unwrap();
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &[16384; 4096]);
+        let guard = rm.register_encoded_owned("sound:wav", wav.into()).unwrap();
+
+        let mut sound = guard.build_sound(&engine, SoundFlags::NONE).unwrap();
+        // The sound keeps the data alive
+        drop(guard);
+        assert!(!rm.is_registered("sound:wav"));
+
+        sound.set_spatialization(false);
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        assert!(out.as_ref()[1..].iter().all(|s| (*s - 0.5).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_resource_man_build_sound_errors() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let engine = crate::engine::engine_builder::EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .resource_manager(&rm)
+            .build()
+            .unwrap();
+        let wav = tiny_test_wav_mono(20);
+
+        // Borrowed data can not outlive its guard
+        let guard = rm.register_encoded("sound:borrowed", &wav).unwrap();
+        assert!(guard.build_sound(&engine, SoundFlags::NONE).is_err());
+
+        // The engine uses another resource manager
+        let other = ResourceManagerBuilder::new().build_f32().unwrap();
+        let guard = other
+            .register_encoded_owned("sound:other", wav.clone().into())
+            .unwrap();
+        let Err(err) = guard.build_sound(&engine, SoundFlags::NONE) else {
+            panic!("built a sound from another resource manager");
+        };
+        assert_eq!(
+            err.context(),
+            Some(&crate::ErrorContext::Resource("sound:other".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_resource_man_register_decoded_owned() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let data: std::sync::Arc<[f32]> = asset_interleaved_f32(2, 100, 1.0).into();
+        let guard = rm
+            .register_decoded_f32_owned(
+                "owned:f32",
+                data.clone(),
+                2,
+                crate::audio::sample_rate::SampleRate::Sr48000,
+            )
+            .unwrap();
+        let mut buf = guard
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .ok()
+            .unwrap();
+        assert_eq!(buf.read_pcm_frames(100).unwrap().as_ref(), &data[..]);
+
+        let data: std::sync::Arc<[i16]> = asset_interleaved_i16(2, 100, 1).into();
+        let guard = rm
+            .register_decoded_i16_owned(
+                "owned:i16",
+                data,
+                2,
+                crate::audio::sample_rate::SampleRate::Sr48000,
+            )
+            .unwrap();
+        let _src = guard.build_source(RmSourceFlags::NONE).unwrap();
+
+        // Length must be a whole number of frames
+        let odd: std::sync::Arc<[u8]> = vec![0u8; 5].into();
+        assert!(rm
+            .register_decoded_u8_owned(
+                "owned:u8",
+                odd,
+                2,
+                crate::audio::sample_rate::SampleRate::Sr48000
+            )
+            .is_err());
+    }
+
     #[test]
     fn test_resource_man_decoded_u8() {
         let rm = ResourceManagerBuilder::new().build_f32().unwrap();
@@ -2270,6 +3233,56 @@ mod test {
         drop(src);
     }
 
+    #[test]
+    fn test_resource_man_register_file_range() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        let guard = rm
+            .register_file_range(path_guard.path(), "slice", 100, 150)
+            .unwrap();
+        let mut buf = guard
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .unwrap();
+        assert_eq!(buf.length_in_pcm_frames().unwrap(), 50);
+
+        let frames = buf.read_pcm_frames(50).unwrap();
+        assert_eq!(frames.frames(), 50);
+        let expected = (100 * 300 % i16::MAX as i32) as f32 / 32768.0;
+        assert!((frames.as_ref()[0] - expected).abs() < 1e-4);
+        drop(buf);
+
+        // The end is clamped to the length of the file
+        let tail = rm
+            .register_file_range(path_guard.path(), "tail", 150, 1000)
+            .unwrap();
+        let buf = tail
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .unwrap();
+        assert_eq!(buf.length_in_pcm_frames().unwrap(), 50);
+    }
+
+    #[test]
+    fn test_resource_man_register_file_range_invalid() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        assert!(rm
+            .register_file_range(path_guard.path(), "empty", 50, 50)
+            .is_err());
+        assert!(rm
+            .register_file_range(path_guard.path(), "past_end", 200, 300)
+            .is_err());
+    }
+
     #[test]
     fn test_resource_man_decoded_s24_packed() {
         let rm = ResourceManagerBuilder::new().build_f32().unwrap();
@@ -6,8 +6,12 @@ use maudio_sys::ffi as sys;
 use crate::{
     data_source::{private_data_source, AsSourcePtr, DataSourceRef, SharedSource},
     engine::resource::{
-        resource_ffi, rm_notif::NotificationPipeline, rm_source::SourceBufSource,
-        rm_source_flags::RmSourceFlags, AsRmPtr, PendingResource,
+        resource_ffi, rm_key,
+        rm_notif::{LoadTarget, NotificationPipeline},
+        rm_registry::ResourceUse,
+        rm_source::{with_source_path, SourceBufSource},
+        rm_source_flags::RmSourceFlags,
+        AsRmPtr, PendingResource,
     },
     sound::sound_builder::OwnedPathBuf,
     AsRawRef, Binding, MaResult,
@@ -50,6 +54,8 @@ use crate::{
 pub struct ResourceManagerBuffer<'a, R: AsRmPtr + ?Sized> {
     inner: *mut sys::ma_resource_manager_data_buffer,
     pipeline_notif: Option<NotificationPipeline>,
+    // Set when the buffer reads a registered resource
+    resource_use: Option<ResourceUse>,
     _format: PhantomData<R::Format>,
     _marker: PhantomData<&'a R>,
 }
@@ -99,16 +105,38 @@ impl<'a, R: AsRmPtr> ResourceManagerBuffer<'a, R> {
         Ok(Self {
             inner,
             pipeline_notif: None, // config.pNotifications do not get carried over. PipeNotif will be lost.
+            resource_use: existing.resource_use.clone(),
             _format: PhantomData,
             _marker: PhantomData,
         })
     }
 
     fn new_with_config(config: &ResourceManagerBufferBuilder<'a, R>) -> MaResult<Self> {
+        Self::new_with_config_in(config.rm, config)
+    }
+
+    // The config only needs to outlive the init call. The buffer is tied to the resource manager.
+    pub(crate) fn new_with_config_in(
+        rm: &'a R,
+        config: &ResourceManagerBufferBuilder<'_, R>,
+    ) -> MaResult<Self> {
         let mut mem: Box<MaybeUninit<sys::ma_resource_manager_data_buffer>> =
             Box::new(MaybeUninit::uninit());
 
-        resource_ffi::ma_resource_manager_data_buffer_init_ex(config.rm, config, mem.as_mut_ptr())?;
+        let resource_use = config
+            .source
+            .path()
+            .and_then(|path| ResourceUse::new(rm_key(rm), &path.to_string_lossy()));
+        // Done closures and load handles read the result from the data buffer
+        if let Some(notif) = &config.pipeline_notif {
+            notif.attach(LoadTarget::Buffer(mem.as_ptr()));
+        }
+        let res =
+            resource_ffi::ma_resource_manager_data_buffer_init_ex(rm, config, mem.as_mut_ptr());
+        if let (Err(_), Some(notif)) = (&res, &config.pipeline_notif) {
+            notif.detach();
+        }
+        res?;
 
         let inner: *mut sys::ma_resource_manager_data_buffer =
             Box::into_raw(mem) as *mut sys::ma_resource_manager_data_buffer;
@@ -116,6 +144,7 @@ impl<'a, R: AsRmPtr> ResourceManagerBuffer<'a, R> {
         Ok(Self {
             inner,
             pipeline_notif: None,
+            resource_use,
             _format: PhantomData,
             _marker: PhantomData,
         })
@@ -232,16 +261,38 @@ impl<'a, R: AsRmPtr> ResourceManagerBufferBuilder<'a, R> {
 
     pub(crate) fn build_internal(&mut self) -> MaResult<ResourceManagerBuffer<'a, R>> {
         self.set_source()?;
-        ResourceManagerBuffer::<R>::new_with_config(self)
+        with_source_path(
+            ResourceManagerBuffer::<R>::new_with_config(self),
+            &self.source,
+        )
+    }
+
+    /// Builds a buffer tied to `rm` instead of the builder lifetime.
+    ///
+    /// `rm` must be the resource manager this builder was created with.
+    pub(crate) fn build_internal_in<'r>(
+        &mut self,
+        rm: &'r R,
+    ) -> MaResult<ResourceManagerBuffer<'r, R>> {
+        debug_assert!(core::ptr::eq(
+            crate::engine::resource::private_rm::rm_ptr(rm),
+            crate::engine::resource::private_rm::rm_ptr(self.rm)
+        ));
+        self.set_source()?;
+        with_source_path(
+            ResourceManagerBuffer::<R>::new_with_config_in(rm, self),
+            &self.source,
+        )
     }
 
     pub fn build(&mut self) -> MaResult<PendingResource<ResourceManagerBuffer<'a, R>>> {
         let mut buf = self.build_internal()?;
+        // Clone the pipeline notifications to prevent them from getting dropped.
+        // Async loads signal them later from a job thread.
+        buf.pipeline_notif = self.pipeline_notif.clone();
         if self.flags.intersects(RmSourceFlags::ASYNC) {
             return Ok(PendingResource::Pending { inner: Some(buf) });
         }
-        // Clone the pipeline notifications to prevent them from getting dropped
-        buf.pipeline_notif = self.pipeline_notif.clone();
         Ok(PendingResource::Ready { inner: buf })
     }
 
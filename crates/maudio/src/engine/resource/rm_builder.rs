@@ -3,9 +3,12 @@ use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{formats::Format, sample_rate::SampleRate},
-    engine::resource::{rm_flags::RmFlags, ResourceManager},
+    data_source::sources::decoder::decoding_backend::{
+        backend_vtable_fn, BackendVTableFn, DecodingBackend, RegisteredBackends,
+    },
+    engine::resource::{rm_flags::RmFlags, DuplicatePolicy, ResourceManager},
     pcm_frames::{S24Packed, S24},
-    AsRawRef, MaResult,
+    AsRawRef, ErrorKinds, MaResult, MaudioError,
 };
 
 /// At the end, you will set the sample format that audio decoded by
@@ -17,6 +20,8 @@ pub struct ResourceManagerBuilder {
     channels: Option<u32>,
     sample_rate: Option<SampleRate>,
     flags: RmFlags,
+    backends: Vec<BackendVTableFn>,
+    pub(crate) duplicate_policy: DuplicatePolicy,
 }
 
 impl AsRawRef for ResourceManagerBuilder {
@@ -31,13 +36,23 @@ impl ResourceManagerBuilder {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         let inner = unsafe { sys::ma_resource_manager_config_init() };
-        Self {
+        let mut builder = Self {
             inner,
             format: None,
             channels: None,
             sample_rate: None,
             flags: RmFlags::NONE,
+            backends: Vec::new(),
+            duplicate_policy: DuplicatePolicy::default(),
+        };
+        // Emscripten can only create threads when built with pthreads (atomics)
+        if cfg!(all(
+            target_os = "emscripten",
+            not(target_feature = "atomics")
+        )) {
+            builder.no_threading(true).job_thread_count(0);
         }
+        builder
     }
 
     /// Sets the number of channels that all audio decoded by this ResourceManager
@@ -101,11 +116,52 @@ impl ResourceManagerBuilder {
         self
     }
 
+    /// Sets the number of job threads created by the resource manager.
+    ///
+    /// With `0`, jobs posted by asynchronous loads are not processed automatically.
+    /// Use [`RmOps::job_runner`](crate::engine::resource::RmOps::job_runner) to process
+    /// them from your own thread.
     pub fn job_thread_count(&mut self, count: u32) -> &mut Self {
         self.inner.jobThreadCount = count;
         self
     }
 
+    /// Registers a custom [`DecodingBackend`] used to decode resources.
+    ///
+    /// Custom backends are tried before miniaudio's built-in decoders, in the order they
+    /// were registered. A backend must produce audio with the channel count and sample rate
+    /// set with [`channels`](Self::channels) and [`sample_rate`](Self::sample_rate). Both are
+    /// required when a backend is registered, otherwise building fails.
+    pub fn decoding_backend<B: DecodingBackend>(&mut self) -> &mut Self {
+        self.backends.push(backend_vtable_fn::<B>());
+        self
+    }
+
+    pub(crate) fn registered_backends(&self) -> MaResult<Option<RegisteredBackends>> {
+        if self.backends.is_empty() {
+            return Ok(None);
+        }
+        let (Some(channels), Some(sample_rate)) = (self.channels, self.sample_rate) else {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "Custom decoding backends require the channels and sample rate to be set.",
+            )));
+        };
+        Ok(Some(RegisteredBackends::new(
+            &self.backends,
+            channels,
+            sample_rate,
+        )))
+    }
+
+    /// Sets what registering an already registered name (or file path) does.
+    ///
+    /// Defaults to [`DuplicatePolicy::Reuse`]. Can be changed later with
+    /// [`RmOps::set_duplicate_policy`](crate::engine::resource::RmOps::set_duplicate_policy).
+    pub fn duplicate_policy(&mut self, policy: DuplicatePolicy) -> &mut Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
     pub fn build_u8(&mut self) -> MaResult<ResourceManager<u8>> {
         self.set_format(Format::U8);
         ResourceManager::<u8>::new_with_config(self)
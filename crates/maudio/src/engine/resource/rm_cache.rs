@@ -0,0 +1,315 @@
+//! Registered resources with least-recently-used eviction.
+use std::path::Path;
+
+use crate::{
+    engine::resource::{
+        rm_buffer::{ResourceManagerBuffer, ResourceManagerBufferBuilder},
+        rm_source_flags::RmSourceFlags,
+        AsRmPtr, PendingResource, ResourceGuard,
+    },
+    MaResult, MaudioError,
+};
+
+/// A collection of registered resources that can be unloaded under a memory budget.
+///
+/// A [`ResourceGuard`] keeps its resource registered (and its data in memory) for as long as
+/// it is alive. `ResourceCache` takes ownership of guards and tracks when each resource was
+/// last used, so that the least recently used ones can be unloaded when the total memory
+/// used by the cache goes over a budget.
+///
+/// Resources that are still in use by a buffer are never evicted. A resource is considered
+/// in use when its reference count (see [`ResourceGuard::stats`]) is higher than one, meaning
+/// something other than the registration itself is using it.
+///
+/// Use [`ResourceCache::build_buffer`] to create buffers from cached resources, so that the
+/// access is recorded. Buffers created by other means still count as references, but do not
+/// update the least recently used order.
+///
+/// ```no_run
+/// # use maudio::engine::resource::{rm_builder::ResourceManagerBuilder, rm_cache::ResourceCache,
+/// #     rm_source_flags::RmSourceFlags, RmOps};
+/// # fn main() -> maudio::MaResult<()> {
+/// # let rm = ResourceManagerBuilder::new().build_f32()?;
+/// # let path = std::path::Path::new("music.wav");
+/// let mut cache = ResourceCache::new(&rm);
+/// cache.insert(rm.register_file(path, RmSourceFlags::DECODE)?);
+///
+/// let buffer = cache.build_buffer("music.wav", RmSourceFlags::NONE)?;
+/// // ...
+/// drop(buffer);
+///
+/// // Keep at most 64 MiB of audio data registered
+/// let evicted = cache.evict_to_budget(64 * 1024 * 1024);
+/// # let _ = evicted;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ResourceCache<'a, R: AsRmPtr + ?Sized> {
+    rm: &'a R,
+    entries: Vec<CacheEntry<'a, R>>,
+    clock: u64,
+}
+
+struct CacheEntry<'a, R: AsRmPtr + ?Sized> {
+    name: String,
+    guard: ResourceGuard<'a, R>,
+    last_used: u64,
+}
+
+impl<'a, R: AsRmPtr + ?Sized> ResourceCache<'a, R> {
+    pub fn new(rm: &'a R) -> Self {
+        Self {
+            rm,
+            entries: Vec::new(),
+            clock: 0,
+        }
+    }
+
+    /// Adds a registered resource to the cache, marking it as the most recently used.
+    ///
+    /// If a resource with the same name is already cached, it is replaced and the
+    /// previous guard is returned.
+    pub fn insert(&mut self, guard: ResourceGuard<'a, R>) -> Option<ResourceGuard<'a, R>> {
+        let name = guard.name().into_owned();
+        let last_used = self.tick();
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.name == name) {
+            entry.last_used = last_used;
+            return Some(std::mem::replace(&mut entry.guard, guard));
+        }
+        self.entries.push(CacheEntry {
+            name,
+            guard,
+            last_used,
+        });
+        None
+    }
+
+    /// Removes a resource from the cache and returns its guard.
+    ///
+    /// The resource stays registered until the returned guard is dropped.
+    pub fn remove(&mut self, name: &str) -> Option<ResourceGuard<'a, R>> {
+        let idx = self.entries.iter().position(|e| e.name == name)?;
+        Some(self.entries.swap_remove(idx).guard)
+    }
+
+    /// Returns the guard for a cached resource, without marking it as used.
+    pub fn get(&self, name: &str) -> Option<&ResourceGuard<'a, R>> {
+        self.entries
+            .iter()
+            .find(|e| e.name == name)
+            .map(|e| &e.guard)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.name == name)
+    }
+
+    /// Marks a resource as the most recently used.
+    ///
+    /// Returns `false` if the resource is not cached.
+    pub fn touch(&mut self, name: &str) -> bool {
+        let last_used = self.tick();
+        match self.entries.iter_mut().find(|e| e.name == name) {
+            Some(entry) => {
+                entry.last_used = last_used;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Builds a [`ResourceManagerBuffer`] from a cached resource and marks it as the most recently used.
+    ///
+    /// See [`ResourceGuard::build_buffer`] for the flags.
+    pub fn build_buffer(
+        &mut self,
+        name: &str,
+        flags: RmSourceFlags,
+    ) -> MaResult<PendingResource<ResourceManagerBuffer<'a, R>>>
+    where
+        R: Sized,
+    {
+        if !self.touch(name) {
+            return Err(MaudioError::from_ma_result(
+                maudio_sys::ffi::ma_result_MA_DOES_NOT_EXIST,
+            ));
+        }
+        let mut flags = flags;
+        flags.remove(RmSourceFlags::STREAM);
+
+        let path = Path::new(name);
+        let mut builder = ResourceManagerBufferBuilder::new(self.rm);
+        builder.flags(flags).file_path(path);
+        let resource = builder.build_internal_in(self.rm)?;
+        if flags.intersects(RmSourceFlags::ASYNC) {
+            return Ok(PendingResource::Pending {
+                inner: Some(resource),
+            });
+        }
+        Ok(PendingResource::Ready { inner: resource })
+    }
+
+    /// Number of cached resources.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the total bytes of audio data held by the cached resources.
+    pub fn resident_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .filter_map(|e| e.guard.stats())
+            .map(|s| s.size_bytes)
+            .sum()
+    }
+
+    /// Unloads the least recently used resources that are not in use, until the
+    /// cached resources use at most `budget_bytes` of memory.
+    ///
+    /// Resources in use by a buffer are skipped, so the budget may not be reached.
+    /// Returns the names of the evicted resources, least recently used first.
+    pub fn evict_to_budget(&mut self, budget_bytes: u64) -> Vec<String> {
+        let mut order: Vec<(u64, String, u64, u32)> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let stats = e.guard.stats();
+                (
+                    e.last_used,
+                    e.name.clone(),
+                    stats.as_ref().map_or(0, |s| s.size_bytes),
+                    stats.as_ref().map_or(0, |s| s.ref_count),
+                )
+            })
+            .collect();
+
+        let mut total: u64 = order.iter().map(|(_, _, size, _)| size).sum();
+        order.sort_by_key(|(last_used, _, _, _)| *last_used);
+
+        let mut evicted = Vec::new();
+        for (_, name, size, ref_count) in order {
+            if total <= budget_bytes {
+                break;
+            }
+            if ref_count > 1 {
+                continue;
+            }
+            // Dropping the guard unregisters the resource
+            drop(self.remove(&name));
+            total = total.saturating_sub(size);
+            evicted.push(name);
+        }
+        evicted
+    }
+
+    #[inline]
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::resource::{
+            rm_builder::ResourceManagerBuilder, rm_cache::ResourceCache,
+            rm_source_flags::RmSourceFlags, RmOps,
+        },
+    };
+
+    #[test]
+    fn test_rm_cache_insert_and_resident_bytes() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let a = vec![0.0f32; 100];
+        let b = vec![0.0f32; 200];
+
+        let mut cache = ResourceCache::new(&rm);
+        cache.insert(
+            rm.register_decoded_f32("a", &a, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+        cache.insert(
+            rm.register_decoded_f32("b", &b, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains("a"));
+        assert_eq!(cache.resident_bytes(), 1200);
+        assert_eq!(rm.resident_bytes(), 1200);
+    }
+
+    #[test]
+    fn test_rm_cache_evicts_least_recently_used() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let a = vec![0.0f32; 100];
+        let b = vec![0.0f32; 100];
+        let c = vec![0.0f32; 100];
+
+        let mut cache = ResourceCache::new(&rm);
+        cache.insert(
+            rm.register_decoded_f32("a", &a, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+        cache.insert(
+            rm.register_decoded_f32("b", &b, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+        cache.insert(
+            rm.register_decoded_f32("c", &c, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+
+        assert!(cache.touch("a"));
+
+        let evicted = cache.evict_to_budget(800);
+        assert_eq!(evicted, vec!["b".to_string()]);
+        assert!(!cache.contains("b"));
+        assert_eq!(rm.resident_bytes(), 800);
+
+        let evicted = cache.evict_to_budget(0);
+        assert_eq!(evicted, vec!["c".to_string(), "a".to_string()]);
+        assert!(cache.is_empty());
+        assert!(rm.memory_stats().is_empty());
+    }
+
+    #[test]
+    fn test_rm_cache_skips_resources_in_use() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let a = vec![0.0f32; 100];
+        let b = vec![0.0f32; 100];
+
+        let mut cache = ResourceCache::new(&rm);
+        cache.insert(
+            rm.register_decoded_f32("a", &a, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+        cache.insert(
+            rm.register_decoded_f32("b", &b, 1, SampleRate::Sr48000)
+                .unwrap(),
+        );
+
+        let buf = cache.build_buffer("a", RmSourceFlags::NONE).unwrap();
+        // "a" is the most recently used, but "b" is evicted first anyway
+        let evicted = cache.evict_to_budget(0);
+        assert_eq!(evicted, vec!["b".to_string()]);
+        assert!(cache.contains("a"));
+
+        drop(buf);
+        let evicted = cache.evict_to_budget(0);
+        assert_eq!(evicted, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn test_rm_cache_build_buffer_missing_name() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let mut cache = ResourceCache::new(&rm);
+        assert!(cache.build_buffer("missing", RmSourceFlags::NONE).is_err());
+    }
+}
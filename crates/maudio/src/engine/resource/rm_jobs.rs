@@ -0,0 +1,290 @@
+//! Manual processing of resource manager jobs.
+//!
+//! By default, a resource manager owns one or more job threads that load and decode
+//! resources in the background. When it is built with `job_thread_count(0)`, no threads
+//! are created and the jobs stay in the queue until they are processed manually.
+//!
+//! This is useful when asynchronous loading should run on a thread pool owned by the
+//! application, or inside a game loop with a fixed time budget per frame.
+//!
+//! Combine it with [`ResourceManagerBuilder::non_blocking`](crate::engine::resource::rm_builder::ResourceManagerBuilder::non_blocking)
+//! so that fetching a job from an empty queue returns immediately instead of waiting.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use maudio::engine::resource::{rm_builder::ResourceManagerBuilder, RmOps};
+//! # fn main() -> maudio::MaResult<()> {
+//! let rm = ResourceManagerBuilder::new()
+//!     .job_thread_count(0)
+//!     .non_blocking(true)
+//!     .build_f32()?;
+//!
+//! // Once per frame
+//! let processed = rm.job_runner().run_for(Duration::from_millis(2))?;
+//! # let _ = processed;
+//! # Ok(())
+//! # }
+//! ```
+use std::time::{Duration, Instant};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::resource::{resource_ffi, AsRmPtr, RmOps},
+    MaResult,
+};
+
+/// Result of taking a job from the resource manager job queue, or from a
+/// [`JobQueue`](crate::util::jobs::JobQueue).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    /// A job was taken from the queue.
+    Processed,
+    /// The queue is empty. Only returned in non-blocking mode.
+    Empty,
+    /// A quit job was posted with [`RmOps::post_job_quit`] or
+    /// [`JobQueue::post_quit`](crate::util::jobs::JobQueue::post_quit).
+    Quit,
+}
+
+/// A job taken from the resource manager job queue.
+///
+/// Returned by [`RmOps::next_job`]. Loading a resource is split into several jobs,
+/// and each one must be processed for the resource to finish loading.
+///
+/// If the job is dropped without calling [`RmJob::process`], it is processed in `Drop`
+/// and any error is ignored. This prevents resources from being stuck in a loading state.
+pub struct RmJob<'a, R: AsRmPtr + ?Sized> {
+    rm: &'a R,
+    job: sys::ma_job,
+    processed: bool,
+}
+
+impl<'a, R: AsRmPtr + ?Sized> RmJob<'a, R> {
+    pub(crate) fn new(rm: &'a R, job: sys::ma_job) -> Self {
+        Self {
+            rm,
+            job,
+            processed: false,
+        }
+    }
+
+    /// Returns the raw miniaudio job type (`ma_job_type`).
+    pub fn job_type(&self) -> u16 {
+        unsafe { self.job.toc.breakup.code }
+    }
+
+    /// Processes the job.
+    pub fn process(mut self) -> MaResult<()> {
+        self.processed = true;
+        resource_ffi::ma_resource_manager_process_job(self.rm, &mut self.job)
+    }
+}
+
+impl<R: AsRmPtr + ?Sized> Drop for RmJob<'_, R> {
+    fn drop(&mut self) {
+        if !self.processed {
+            let _ = resource_ffi::ma_resource_manager_process_job(self.rm, &mut self.job);
+        }
+    }
+}
+
+/// Processes resource manager jobs from a user thread.
+///
+/// Created by [`RmOps::job_runner`]. Intended for resource managers built with
+/// `job_thread_count(0)`, where no job threads are owned by the resource manager.
+///
+/// `JobRunner` is also an [`Iterator`]. Each call to `next()` processes one job and
+/// yields its result. Iteration ends when the queue is empty (non-blocking mode only)
+/// or when a quit job is received.
+///
+/// ### Blocking mode
+///
+/// Unless the resource manager is non-blocking, taking a job from an empty queue
+/// waits until one is posted. In that mode, the runner only stops after
+/// [`RmOps::post_job_quit`] is called, which makes it suitable for a dedicated thread.
+pub struct JobRunner<'a, R: AsRmPtr + ?Sized> {
+    rm: &'a R,
+    quit: bool,
+}
+
+impl<'a, R: AsRmPtr + RmOps + ?Sized> JobRunner<'a, R> {
+    pub(crate) fn new(rm: &'a R) -> Self {
+        Self { rm, quit: false }
+    }
+
+    /// Returns `true` once a quit job has been received.
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Processes jobs until the queue is empty or a quit job is received.
+    ///
+    /// Returns the number of processed jobs. Stops at the first job that fails.
+    pub fn run_until_empty(&mut self) -> MaResult<usize> {
+        let mut count = 0;
+        for res in self.by_ref() {
+            res?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Processes jobs until `budget` has elapsed, the queue is empty, or a quit job is received.
+    ///
+    /// The budget is checked between jobs, so a single long job can exceed it.
+    /// Returns the number of processed jobs. Stops at the first job that fails.
+    pub fn run_for(&mut self, budget: Duration) -> MaResult<usize> {
+        let start = Instant::now();
+        let mut count = 0;
+        while start.elapsed() < budget {
+            match self.next() {
+                Some(res) => {
+                    res?;
+                    count += 1;
+                }
+                None => break,
+            }
+        }
+        Ok(count)
+    }
+}
+
+impl<'a, R: AsRmPtr + RmOps + ?Sized> Iterator for JobRunner<'a, R> {
+    type Item = MaResult<()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.quit {
+            return None;
+        }
+        match self.rm.process_next_job() {
+            Ok(JobStatus::Processed) => Some(Ok(())),
+            Ok(JobStatus::Empty) => None,
+            Ok(JobStatus::Quit) => {
+                self.quit = true;
+                None
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{
+        engine::resource::{
+            rm_buffer::ResourceManagerBufferBuilder, rm_builder::ResourceManagerBuilder,
+            rm_jobs::JobStatus, tiny_test_wav_mono, RmOps,
+        },
+        test_assets::temp_file::{unique_tmp_path, TempFileGuard},
+    };
+
+    #[test]
+    fn test_rm_jobs_flags_and_thread_count() {
+        let rm = ResourceManagerBuilder::new()
+            .job_thread_count(0)
+            .non_blocking(true)
+            .build_f32()
+            .unwrap();
+        assert_eq!(rm.job_thread_count(), 0);
+        assert!(rm.is_non_blocking());
+    }
+
+    #[test]
+    fn test_rm_jobs_empty_queue_returns_empty() {
+        let rm = ResourceManagerBuilder::new()
+            .job_thread_count(0)
+            .non_blocking(true)
+            .build_f32()
+            .unwrap();
+        assert_eq!(rm.process_next_job().unwrap(), JobStatus::Empty);
+        assert!(rm.next_job().unwrap().is_none());
+        assert_eq!(rm.job_runner().run_until_empty().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_rm_jobs_runner_completes_async_load() {
+        let rm = ResourceManagerBuilder::new()
+            .job_thread_count(0)
+            .non_blocking(true)
+            .build_f32()
+            .unwrap();
+
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        let mut pending = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(path_guard.path())
+            .async_load(true)
+            .build()
+            .unwrap();
+
+        let mut runner = rm.job_runner();
+        let mut ready = false;
+        for _ in 0..100 {
+            runner.run_for(Duration::from_millis(10)).unwrap();
+            if pending.poll_ready().unwrap() {
+                ready = true;
+                break;
+            }
+        }
+        assert!(ready);
+    }
+
+    #[test]
+    fn test_rm_jobs_next_job_process() {
+        let rm = ResourceManagerBuilder::new()
+            .job_thread_count(0)
+            .non_blocking(true)
+            .build_f32()
+            .unwrap();
+
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        let mut pending = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(path_guard.path())
+            .async_load(true)
+            .build()
+            .unwrap();
+
+        while let Some(job) = rm.next_job().unwrap() {
+            job.process().unwrap();
+        }
+        assert!(pending.poll_ready().unwrap());
+    }
+
+    #[test]
+    fn test_rm_jobs_quit_stops_runner() {
+        let rm = ResourceManagerBuilder::new()
+            .job_thread_count(0)
+            .non_blocking(true)
+            .build_f32()
+            .unwrap();
+
+        rm.post_job_quit().unwrap();
+        let mut runner = rm.job_runner();
+        assert!(runner.next().is_none());
+        assert!(runner.is_quit());
+        assert_eq!(rm.process_next_job().unwrap(), JobStatus::Quit);
+    }
+
+    #[test]
+    fn test_rm_jobs_custom_job_runs_on_runner() {
+        let rm = ResourceManagerBuilder::new()
+            .job_thread_count(0)
+            .non_blocking(true)
+            .build_f32()
+            .unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        rm.post_custom_job(move || tx.send(7).unwrap()).unwrap();
+        assert!(rx.try_recv().is_err());
+        assert_eq!(rm.job_runner().run_until_empty().unwrap(), 1);
+        assert_eq!(rx.try_recv().unwrap(), 7);
+    }
+}
@@ -1,10 +1,16 @@
 //! Event-based alternative to polling resource loading
 
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
 
 use maudio_sys::ffi as sys;
 
-use crate::{util::fence::Fence, AsRawRef, Binding};
+use crate::{
+    util::{callback_panic, fence::Fence},
+    AsRawRef, Binding, MaResult, MaudioError,
+};
 
 /// Asynchronous notification pipeline for resource-manager data sources.
 ///
@@ -48,6 +54,18 @@ use crate::{util::fence::Fence, AsRawRef, Binding};
 /// let buffer = pending.into_ready().unwrap();
 /// ```
 ///
+/// # Closures and load handles
+///
+/// Besides a [`Fence`], each stage can run closures (see
+/// [`NotificationPipelineBuilder::on_done`]) or signal a [`LoadHandle`] that can be
+/// polled or waited on from any thread. Closures run on the thread that completes the
+/// stage, which is usually one of the resource manager's job threads.
+///
+/// [`NotificationPipelineBuilder::on_done`] closures and [`LoadHandle`]s receive the load
+/// result. A [`Fence`] only reports that the stage has finished, call `poll_ready()` on the
+/// [`PendingResource`](crate::engine::resource::PendingResource) to find out whether the load
+/// succeeded.
+///
 /// # When to use
 ///
 /// Use a `NotificationPipeline` when:
@@ -58,6 +76,8 @@ use crate::{util::fence::Fence, AsRawRef, Binding};
 /// # Notes
 /// - Attaching a pipeline is optional.
 /// - The `NotificationPipeline` object is Send + Sync and cheap to clone and re-use.
+///   Closures run once, and the result comes from the last resource the pipeline was
+///   attached to, so only reuse a pipeline once the previous load is done.
 /// - Internally, this wraps `ma_resource_manager_pipeline_notifications`.
 #[derive(Clone)]
 pub struct NotificationPipeline {
@@ -66,13 +86,62 @@ pub struct NotificationPipeline {
 
 struct NotifPipeInner {
     inner: sys::ma_resource_manager_pipeline_notifications,
-    _init: Option<Fence>, // ref count. Keep alive
-    _done: Option<Fence>, // ref count. Keep alive
+    _init: Option<Fence>,                  // ref count. Keep alive
+    _done: Option<Fence>,                  // ref count. Keep alive
+    _init_notif: Option<Box<CustomNotif>>, // Keep alive. Pointer is held by miniaudio
+    _done_notif: Option<Box<CustomNotif>>, // Keep alive. Pointer is held by miniaudio
 }
 
 unsafe impl Send for NotifPipeInner {}
 unsafe impl Sync for NotifPipeInner {}
 
+impl NotificationPipeline {
+    // Called by the builders before init, the resource must stay allocated while it loads
+    pub(crate) fn attach(&self, target: LoadTarget) {
+        self.set_target(Some(target));
+    }
+
+    // Called when init fails, the resource is freed right after
+    pub(crate) fn detach(&self) {
+        self.set_target(None);
+    }
+
+    fn set_target(&self, target: Option<LoadTarget>) {
+        for notif in [&self.inner._init_notif, &self.inner._done_notif]
+            .into_iter()
+            .flatten()
+        {
+            *notif.state.target.lock().unwrap_or_else(|e| e.into_inner()) = target;
+        }
+    }
+}
+
+/// The resource a [`NotificationPipeline`] reads the load result from.
+#[derive(Clone, Copy)]
+pub(crate) enum LoadTarget {
+    Buffer(*const sys::ma_resource_manager_data_buffer),
+    Stream(*const sys::ma_resource_manager_data_stream),
+    Source(*const sys::ma_resource_manager_data_source),
+}
+
+impl LoadTarget {
+    // miniaudio sets the result before signaling the done stage
+    fn result(self) -> MaResult<()> {
+        let res = unsafe {
+            match self {
+                LoadTarget::Buffer(ptr) => sys::ma_resource_manager_data_buffer_result(ptr),
+                LoadTarget::Stream(ptr) => sys::ma_resource_manager_data_stream_result(ptr),
+                LoadTarget::Source(ptr) => sys::ma_resource_manager_data_source_result(ptr),
+            }
+        };
+        // Still loading when the init stage is signaled
+        if res == sys::ma_result_MA_BUSY {
+            return Ok(());
+        }
+        MaudioError::check(res)
+    }
+}
+
 impl AsRawRef for NotificationPipeline {
     type Raw = sys::ma_resource_manager_pipeline_notifications;
 
@@ -91,6 +160,8 @@ pub struct NotificationPipelineBuilder {
     inner: sys::ma_resource_manager_pipeline_notifications,
     init_fence: Option<Fence>,
     done_fence: Option<Fence>,
+    init_notif: Option<Box<CustomNotif>>,
+    done_notif: Option<Box<CustomNotif>>,
 }
 
 impl NotificationPipelineBuilder {
@@ -101,6 +172,8 @@ impl NotificationPipelineBuilder {
             inner,
             init_fence: None,
             done_fence: None,
+            init_notif: None,
+            done_notif: None,
         }
     }
 
@@ -122,27 +195,330 @@ impl NotificationPipelineBuilder {
         self
     }
 
+    /// Run `f` when initialization completes.
+    ///
+    /// The closure runs on the thread that completes the stage, usually a resource manager
+    /// job thread. Keep it short and do not block inside it.
+    pub fn on_init<F: FnOnce() + Send + 'static>(&mut self, f: F) -> &mut Self {
+        let notif = self.init_notif.get_or_insert_with(CustomNotif::new);
+        notif.push(Box::new(move |_| f()));
+        self.inner.init.pNotification = notif.as_notification_ptr();
+        self
+    }
+
+    /// Run `f` with the load result when the resource is fully loaded (or has failed to load).
+    ///
+    /// The closure runs on the thread that completes the stage, usually a resource manager
+    /// job thread. Keep it short and do not block inside it.
+    ///
+    /// Multiple closures can be attached. They run in the order they were added.
+    pub fn on_done<F: FnOnce(MaResult<()>) + Send + 'static>(&mut self, f: F) -> &mut Self {
+        let notif = self.done_notif.get_or_insert_with(CustomNotif::new);
+        notif.push(Box::new(f));
+        self.inner.done.pNotification = notif.as_notification_ptr();
+        self
+    }
+
+    /// Signal `handle` when initialization completes.
+    ///
+    /// The handle reports an error if the resource already failed to load.
+    pub fn init_with_handle(&mut self, handle: &LoadHandle) -> &mut Self {
+        let handle = handle.clone();
+        let notif = self.init_notif.get_or_insert_with(CustomNotif::new);
+        notif.push(Box::new(move |result| handle.signal(result)));
+        self.inner.init.pNotification = notif.as_notification_ptr();
+        self
+    }
+
+    /// Signal `handle` when the resource is fully loaded (or has failed to load).
+    pub fn done_with_handle(&mut self, handle: &LoadHandle) -> &mut Self {
+        let handle = handle.clone();
+        self.on_done(move |result| handle.signal(result))
+    }
+
     pub fn build(self) -> NotificationPipeline {
         NotificationPipeline {
             inner: Arc::new(NotifPipeInner {
                 inner: self.inner,
                 _init: self.init_fence,
                 _done: self.done_fence,
+                _init_notif: self.init_notif,
+                _done_notif: self.done_notif,
             }),
         }
     }
 }
 
-// Not implemented
+/// A pollable handle that is signaled when a resource loading stage completes.
+///
+/// Attach it to a pipeline with [`NotificationPipelineBuilder::done_with_handle`] (or
+/// [`NotificationPipelineBuilder::init_with_handle`]), then either poll it from a game loop
+/// with [`LoadHandle::is_done`] or block on it with [`LoadHandle::wait`]. Both
+/// [`LoadHandle::wait`] and [`LoadHandle::result`] report whether the load succeeded.
+///
+/// Unlike a [`Fence`], a `LoadHandle` never needs to be acquired or released manually.
+///
+/// `LoadHandle` is Send + Sync and cheap to clone. All clones observe the same state.
+///
+/// # Example
+///
+/// ```ignore
+/// # let rm = todo!();
+/// # let path = todo!();
+/// let handle = LoadHandle::new();
+///
+/// let mut notif = NotificationPipelineBuilder::new();
+/// notif.done_with_handle(&handle);
+///
+/// let mut pending = ResourceManagerBufferBuilder::new(&rm)
+///     .file_path(path)
+///     .async_load(true)
+///     .notification(notif.build())
+///     .build()?;
+///
+/// handle.wait()?; // Reports the load result
+/// let buffer = pending.into_ready().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct LoadHandle {
+    inner: Arc<LoadHandleInner>,
+}
+
+#[derive(Default)]
+struct LoadHandleInner {
+    result: Mutex<Option<MaResult<()>>>,
+    cvar: Condvar,
+}
+
+impl LoadHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the stage this handle is attached to has completed.
+    pub fn is_done(&self) -> bool {
+        self.result().is_some()
+    }
+
+    /// Returns the load result, or `None` if the stage has not completed yet.
+    pub fn result(&self) -> Option<MaResult<()>> {
+        self.inner
+            .result
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Blocks the current thread until the stage has completed, and returns the load result.
+    pub fn wait(&self) -> MaResult<()> {
+        let result = self.inner.result.lock().unwrap_or_else(|e| e.into_inner());
+        let result = self
+            .inner
+            .cvar
+            .wait_while(result, |result| result.is_none())
+            .unwrap_or_else(|e| e.into_inner());
+        result.clone().unwrap_or(Ok(()))
+    }
+
+    /// Blocks the current thread until the stage has completed or `timeout` has elapsed.
+    ///
+    /// Returns the load result, or `None` on timeout.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<MaResult<()>> {
+        let result = self.inner.result.lock().unwrap_or_else(|e| e.into_inner());
+        let (result, _) = self
+            .inner
+            .cvar
+            .wait_timeout_while(result, timeout, |result| result.is_none())
+            .unwrap_or_else(|e| e.into_inner());
+        result.clone()
+    }
+
+    fn signal(&self, load_result: MaResult<()>) {
+        let mut result = self.inner.result.lock().unwrap_or_else(|e| e.into_inner());
+        *result = Some(load_result);
+        self.inner.cvar.notify_all();
+    }
+}
+
+type NotifCallback = Box<dyn FnOnce(MaResult<()>) + Send + 'static>;
+
+// Custom ma_async_notification. miniaudio only sees the leading callbacks struct.
 #[repr(C)]
-#[allow(dead_code)]
 struct CustomNotif {
-    cb: sys::ma_async_notification,
-    state: *mut core::ffi::c_void,
+    cb: sys::ma_async_notification_callbacks,
+    state: State,
 }
 
-// Not implemented
-#[allow(dead_code)]
 struct State {
-    cb: Mutex<Option<Box<dyn FnOnce() + Send + 'static>>>,
+    cb: Mutex<Vec<NotifCallback>>,
+    target: Mutex<Option<LoadTarget>>,
+}
+
+impl CustomNotif {
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            cb: sys::ma_async_notification_callbacks {
+                onSignal: Some(on_signal_callback),
+            },
+            state: State {
+                cb: Mutex::new(Vec::new()),
+                target: Mutex::new(None),
+            },
+        })
+    }
+
+    fn push(&mut self, f: NotifCallback) {
+        self.state
+            .cb
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(f);
+    }
+
+    fn as_notification_ptr(&mut self) -> *mut sys::ma_async_notification {
+        self as *mut CustomNotif as *mut sys::ma_async_notification
+    }
+}
+
+unsafe extern "C" fn on_signal_callback(notification: *mut sys::ma_async_notification) {
+    if notification.is_null() {
+        return;
+    }
+    let notif = unsafe { &*(notification as *const CustomNotif) };
+    let callbacks = std::mem::take(&mut *notif.state.cb.lock().unwrap_or_else(|e| e.into_inner()));
+    if callbacks.is_empty() {
+        return;
+    }
+    let target = *notif.state.target.lock().unwrap_or_else(|e| e.into_inner());
+    let result = target.map_or(Ok(()), LoadTarget::result);
+    for f in callbacks {
+        let result = result.clone();
+        // Never unwind into miniaudio
+        let _ = callback_panic::guard(move || f(result));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use crate::{
+        engine::resource::{
+            rm_buffer::ResourceManagerBufferBuilder,
+            rm_builder::ResourceManagerBuilder,
+            rm_notif::{LoadHandle, NotificationPipelineBuilder},
+            rm_source::ResourceManagerSourceBuilder,
+            tiny_test_wav_mono,
+        },
+        test_assets::temp_file::{unique_tmp_path, TempFileGuard},
+    };
+
+    #[test]
+    fn test_rm_notif_load_handle_async_buffer() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let path = path_guard.path().to_path_buf();
+        std::fs::write(&path, &wav).unwrap();
+
+        let handle = LoadHandle::new();
+        let mut notif = NotificationPipelineBuilder::new();
+        notif.done_with_handle(&handle);
+
+        let mut pending = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(&path)
+            .async_load(true)
+            .notification(notif.build())
+            .build()
+            .unwrap();
+
+        assert!(handle.wait_timeout(Duration::from_secs(5)).unwrap().is_ok());
+        assert!(handle.is_done());
+        assert!(handle.wait().is_ok());
+        assert!(pending.poll_ready().unwrap());
+    }
+
+    #[test]
+    fn test_rm_notif_on_done_closures_run_once() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let path = path_guard.path().to_path_buf();
+        std::fs::write(&path, &wav).unwrap();
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let handle = LoadHandle::new();
+        let mut notif = NotificationPipelineBuilder::new();
+        let c1 = count.clone();
+        let c2 = count.clone();
+        notif
+            .on_done(move |result| {
+                assert!(result.is_ok());
+                c1.fetch_add(1, Ordering::SeqCst);
+            })
+            .on_done(move |result| {
+                assert!(result.is_ok());
+                c2.fetch_add(1, Ordering::SeqCst);
+            })
+            .done_with_handle(&handle);
+
+        let _pending = ResourceManagerSourceBuilder::new(&rm)
+            .file_path(&path)
+            .async_load(true)
+            .notification(notif.build())
+            .build()
+            .unwrap();
+
+        assert!(handle.wait_timeout(Duration::from_secs(5)).is_some());
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_rm_notif_reports_failed_load() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let path = path_guard.path().to_path_buf();
+        std::fs::write(&path, [0x5Au8; 256]).unwrap();
+
+        let failed = Arc::new(AtomicUsize::new(0));
+        let handle = LoadHandle::new();
+        let mut notif = NotificationPipelineBuilder::new();
+        let f = failed.clone();
+        notif
+            .on_done(move |result| {
+                if result.is_err() {
+                    f.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .done_with_handle(&handle);
+
+        let _pending = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(&path)
+            .async_load(true)
+            .notification(notif.build())
+            .build()
+            .unwrap();
+
+        let result = handle.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert!(result.is_err());
+        assert!(handle.wait().is_err());
+        assert_eq!(failed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_rm_notif_load_handle_times_out_when_not_attached() {
+        let handle = LoadHandle::new();
+        assert!(!handle.is_done());
+        assert!(handle.result().is_none());
+        assert!(handle.wait_timeout(Duration::from_millis(1)).is_none());
+    }
 }
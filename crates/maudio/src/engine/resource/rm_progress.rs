@@ -0,0 +1,318 @@
+//! Load progress of resources loaded with [`RmSourceFlags::ASYNC`].
+//!
+//! A [`Fence`](crate::util::fence::Fence) only tells whether a load has finished. A
+//! [`LoadProgress`] reads how far the job threads got, for loading screens that show a
+//! progress bar per asset.
+//!
+//! It is returned by [`PendingResource::load_progress`] and
+//! [`Sound::load_progress`](crate::sound::Sound::load_progress).
+//!
+//! [`RmSourceFlags::ASYNC`]: crate::engine::resource::rm_source_flags::RmSourceFlags::ASYNC
+//! [`PendingResource::load_progress`]: crate::engine::resource::PendingResource::load_progress
+use std::marker::PhantomData;
+
+use maudio_sys::ffi as sys;
+
+// miniaudio decodes streams one page at a time
+const STREAM_PAGE_MILLIS: u64 = 1000;
+
+/// Progress of a resource being loaded by the resource manager's job threads.
+///
+/// What counts as progress depends on how the resource is loaded:
+/// - Decoded buffers ([`RmSourceFlags::DECODE`]) count the frames decoded so far. The total
+///   is known once the decoder is initialized, unless the file does not report its length.
+/// - Encoded buffers only load the file into memory, which has no frame count. Only
+///   [`LoadProgress::is_loaded`] changes.
+/// - Streams ([`RmSourceFlags::STREAM`]) only decode ahead of the cursor. Their progress
+///   covers the two pages decoded before playback can start.
+///
+/// [`RmSourceFlags::DECODE`]: crate::engine::resource::rm_source_flags::RmSourceFlags::DECODE
+/// [`RmSourceFlags::STREAM`]: crate::engine::resource::rm_source_flags::RmSourceFlags::STREAM
+#[derive(Clone, Copy)]
+pub struct LoadProgress<'a> {
+    target: Target,
+    _marker: PhantomData<&'a ()>,
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+    Buffer(*const sys::ma_resource_manager_data_buffer),
+    Stream(*const sys::ma_resource_manager_data_stream),
+}
+
+impl LoadProgress<'_> {
+    /// Frames decoded so far.
+    pub fn decoded_frames(&self) -> u64 {
+        match self.target {
+            Target::Buffer(buffer) => match buffer_node(buffer) {
+                Some(node) => {
+                    let supply = unsafe { core::ptr::addr_of!((*node).data) };
+                    match supply_type(node) {
+                        sys::ma_resource_manager_data_supply_type_ma_resource_manager_data_supply_type_decoded => unsafe {
+                            read(core::ptr::addr_of!((*supply).backend.decoded.decodedFrameCount))
+                        },
+                        sys::ma_resource_manager_data_supply_type_ma_resource_manager_data_supply_type_decoded_paged => unsafe {
+                            read(core::ptr::addr_of!((*supply).backend.decodedPaged.decodedFrameCount))
+                        },
+                        _ => 0,
+                    }
+                }
+                None => 0,
+            },
+            Target::Stream(stream) => {
+                if !stream_decoder_ready(stream) {
+                    return 0;
+                }
+                (0..2)
+                    .filter(
+                        |i| unsafe { read(core::ptr::addr_of!((*stream).isPageValid[*i])) } != 0,
+                    )
+                    .map(
+                        |i| unsafe { read(core::ptr::addr_of!((*stream).pageFrameCount[i])) }
+                            as u64,
+                    )
+                    .sum()
+            }
+        }
+    }
+
+    /// Frames to decode in total, if known yet.
+    pub fn total_frames(&self) -> Option<u64> {
+        match self.target {
+            Target::Buffer(buffer) => {
+                let node = buffer_node(buffer)?;
+                if supply_type(node)
+                    != sys::ma_resource_manager_data_supply_type_ma_resource_manager_data_supply_type_decoded
+                {
+                    return None;
+                }
+                let total = unsafe {
+                    read(core::ptr::addr_of!(
+                        (*node).data.backend.decoded.totalFrameCount
+                    ))
+                };
+                Some(total)
+            }
+            Target::Stream(stream) => {
+                if !stream_decoder_ready(stream) {
+                    return None;
+                }
+                let (sample_rate, length) = unsafe {
+                    (
+                        read(core::ptr::addr_of!((*stream).decoder.outputSampleRate)) as u64,
+                        read(core::ptr::addr_of!((*stream).totalLengthInPCMFrames)),
+                    )
+                };
+                let pages = 2 * STREAM_PAGE_MILLIS * (sample_rate / 1000);
+                Some(if length > 0 { pages.min(length) } else { pages })
+            }
+        }
+    }
+
+    /// Returns the progress between `0.0` and `1.0`, or `None` while the total is unknown.
+    ///
+    /// Always `Some(1.0)` once the resource is loaded.
+    pub fn fraction(&self) -> Option<f32> {
+        if self.is_loaded() {
+            return Some(1.0);
+        }
+        let total = self.total_frames()?;
+        if total == 0 {
+            return Some(0.0);
+        }
+        Some((self.decoded_frames() as f64 / total as f64).min(1.0) as f32)
+    }
+
+    /// Returns `true` once loading has finished successfully.
+    pub fn is_loaded(&self) -> bool {
+        self.result() == sys::ma_result_MA_SUCCESS
+    }
+
+    /// Returns `true` if loading failed. The error is returned when the resource is used,
+    /// for example by [`PendingResource::poll_ready`](crate::engine::resource::PendingResource::poll_ready).
+    pub fn is_failed(&self) -> bool {
+        let result = self.result();
+        result != sys::ma_result_MA_SUCCESS && result != sys::ma_result_MA_BUSY
+    }
+}
+
+// Private methods
+impl LoadProgress<'_> {
+    pub(crate) fn from_buffer(buffer: *const sys::ma_resource_manager_data_buffer) -> Self {
+        Self {
+            target: Target::Buffer(buffer),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn from_stream(stream: *const sys::ma_resource_manager_data_stream) -> Self {
+        Self {
+            target: Target::Stream(stream),
+            _marker: PhantomData,
+        }
+    }
+
+    /// A resource manager data source is either a buffer or a stream.
+    pub(crate) fn from_data_source(source: *const sys::ma_resource_manager_data_source) -> Self {
+        let flags = unsafe { (*source).flags };
+        let stream = flags
+            & sys::ma_resource_manager_data_source_flags_MA_RESOURCE_MANAGER_DATA_SOURCE_FLAG_STREAM
+            != 0;
+        unsafe {
+            match stream {
+                true => Self::from_stream(core::ptr::addr_of!((*source).backend.stream)),
+                false => Self::from_buffer(core::ptr::addr_of!((*source).backend.buffer)),
+            }
+        }
+    }
+
+    fn result(&self) -> sys::ma_result {
+        match self.target {
+            Target::Buffer(buffer) => match buffer_node(buffer) {
+                Some(node) => unsafe { read(core::ptr::addr_of!((*node).result)) },
+                None => sys::ma_result_MA_BUSY,
+            },
+            Target::Stream(stream) => unsafe { read(core::ptr::addr_of!((*stream).result)) },
+        }
+    }
+}
+
+// The job threads write these fields while the application reads them
+unsafe fn read<T: Copy>(field: *const T) -> T {
+    core::ptr::read_volatile(field)
+}
+
+fn buffer_node(
+    buffer: *const sys::ma_resource_manager_data_buffer,
+) -> Option<*const sys::ma_resource_manager_data_buffer_node> {
+    let node = unsafe { read(core::ptr::addr_of!((*buffer).pNode)) };
+    (!node.is_null()).then_some(node as *const _)
+}
+
+fn supply_type(
+    node: *const sys::ma_resource_manager_data_buffer_node,
+) -> sys::ma_resource_manager_data_supply_type {
+    unsafe { read(core::ptr::addr_of!((*node).data.type_)) }
+}
+
+fn stream_decoder_ready(stream: *const sys::ma_resource_manager_data_stream) -> bool {
+    unsafe { read(core::ptr::addr_of!((*stream).isDecoderInitialized)) != 0 }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{
+            engine_builder::EngineBuilder,
+            resource::{
+                rm_buffer::ResourceManagerBufferBuilder, rm_builder::ResourceManagerBuilder,
+                rm_source_flags::RmSourceFlags, rm_stream::ResourceManagerStreamBuilder,
+            },
+        },
+        sound::sound_flags::SoundFlags,
+        test_assets::{
+            temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le,
+        },
+    };
+
+    fn wav_file(frames: usize) -> TempFileGuard {
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let samples: Vec<i16> = (0..frames).map(|i| (i % 1000) as i16).collect();
+        std::fs::write(guard.path(), wav_i16_le(1, SampleRate::Sr48000, &samples)).unwrap();
+        guard
+    }
+
+    #[test]
+    fn test_load_progress_decoded_buffer() {
+        let file = wav_file(4800);
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let buffer = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(file.path())
+            .flags(RmSourceFlags::DECODE)
+            .build()
+            .unwrap();
+
+        let progress = buffer.load_progress().unwrap();
+        assert!(progress.is_loaded());
+        assert!(!progress.is_failed());
+        assert_eq!(progress.total_frames(), Some(4800));
+        assert_eq!(progress.decoded_frames(), 4800);
+        assert_eq!(progress.fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn test_load_progress_async_buffer_reaches_total() {
+        let file = wav_file(48000 * 4);
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let mut buffer = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(file.path())
+            .flags(RmSourceFlags::DECODE | RmSourceFlags::ASYNC)
+            .build()
+            .unwrap();
+
+        let start = Instant::now();
+        let mut last = 0;
+        loop {
+            let progress = buffer.load_progress().unwrap();
+            let decoded = progress.decoded_frames();
+            assert!(decoded >= last);
+            last = decoded;
+            if progress.is_loaded() {
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "load timed out");
+            std::thread::sleep(Duration::from_micros(100));
+        }
+        assert!(buffer.poll_ready().unwrap());
+        let progress = buffer.load_progress().unwrap();
+        assert_eq!(progress.decoded_frames(), 48000 * 4);
+        assert_eq!(progress.total_frames(), Some(48000 * 4));
+    }
+
+    #[test]
+    fn test_load_progress_stream_prefill() {
+        let file = wav_file(20000);
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let stream = ResourceManagerStreamBuilder::new(&rm)
+            .file_path(file.path())
+            .build()
+            .unwrap();
+
+        // The file is shorter than the two pages decoded before playback
+        let progress = stream.load_progress().unwrap();
+        assert!(progress.is_loaded());
+        assert_eq!(progress.total_frames(), Some(20000));
+        assert_eq!(progress.decoded_frames(), 20000);
+    }
+
+    #[test]
+    fn test_load_progress_sound() {
+        let file = wav_file(48000);
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let sound = engine
+            .new_sound_from_file_with_flags(
+                file.path(),
+                SoundFlags::DECODE | SoundFlags::ASYNC,
+                None,
+            )
+            .unwrap();
+
+        let start = Instant::now();
+        while !sound.load_progress().unwrap().is_loaded() {
+            assert!(start.elapsed() < Duration::from_secs(5), "load timed out");
+            std::thread::sleep(Duration::from_micros(100));
+        }
+        assert_eq!(sound.load_progress().unwrap().decoded_frames(), 48000);
+
+        // Sounds playing a data source are not loaded by the resource manager
+        let sound = engine.new_sound_from_source(&sound.data_source()).unwrap();
+        assert!(sound.load_progress().is_none());
+    }
+}
@@ -0,0 +1,314 @@
+//! Names registered with each resource manager, behind `RmOps::registered_names`,
+//! `RmOps::acquire` and the [`DuplicatePolicy`].
+//!
+//! miniaudio only keeps a hash of each registered name, so the names are tracked here. Every
+//! [`ResourceGuard`](super::ResourceGuard) of a name shares one entry, and the resource is
+//! only unregistered from miniaudio once the last of them is dropped.
+//!
+//! Entries also count the buffers and sounds built from their name (see [`ResourceUse`]) and
+//! the size of their data, for `RmOps::memory_stats`.
+use std::sync::{Arc, Mutex};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::resource::{
+        rm_key,
+        rm_stats::{Footprint, LoadProbe, ResourceStats},
+        AsRmPtr, DataStore, DuplicatePolicy, RegisteredDataType,
+    },
+    ErrorContext, ErrorKinds, MaResult, MaudioError,
+};
+
+struct Entry {
+    rm: usize,
+    id: u64,
+    data: RegisteredDataType,
+    // Data owned on behalf of the guards, `None` for files and borrowed data. Shared with
+    // the sounds built from the guards, which may outlive them.
+    store: Option<Arc<DataStore>>,
+    // Borrowed data must not outlive the guard that registered it
+    borrowed: bool,
+    guards: usize,
+    // Number of times the name was registered with miniaudio. Zero once the entry was
+    // replaced, its guards no longer own the name.
+    registrations: usize,
+    // Buffers and sounds built from the name
+    users: usize,
+    footprint: Footprint,
+}
+
+impl Entry {
+    fn is(&self, rm: usize, name: &str) -> bool {
+        self.rm == rm && self.registrations > 0 && self.data.name() == name
+    }
+
+    fn stats(&mut self, finished: &mut Vec<LoadProbe>) -> ResourceStats {
+        finished.extend(self.footprint.update());
+        let ref_count = (self.registrations + self.users) as u32;
+        self.footprint
+            .stats(self.data.name().into_owned(), ref_count)
+    }
+}
+
+struct Registry {
+    entries: Vec<Entry>,
+    // Resource managers with a policy other than the default
+    policies: Vec<(usize, DuplicatePolicy)>,
+    next_id: u64,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    entries: Vec::new(),
+    policies: Vec::new(),
+    next_id: 0,
+});
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Registers `data` with `register` and returns the entry of the new guard.
+///
+/// The registry stays locked while `register` runs, so the policy is applied to the state
+/// the registration actually sees. `footprint` is only called for a new entry, after the
+/// registration succeeded.
+pub(crate) fn register<R: AsRmPtr + ?Sized>(
+    rm: &R,
+    data: &RegisteredDataType,
+    store: Option<DataStore>,
+    borrowed: bool,
+    footprint: impl FnOnce() -> Footprint,
+    register: impl FnOnce() -> MaResult<()>,
+) -> MaResult<u64> {
+    let key = rm_key(rm);
+    let name = data.name();
+    let mut registry = registry();
+    let policy = policy_of(&registry, key);
+
+    if let Some(entry) = registry.entries.iter_mut().find(|e| e.is(key, &name)) {
+        match policy {
+            DuplicatePolicy::Error => {
+                return Err(
+                    MaudioError::new_ma_error(ErrorKinds::ResourceAlreadyRegistered {
+                        name: name.into_owned(),
+                    })
+                    .with_context(ErrorContext::Resource(data.name().into_owned())),
+                );
+            }
+            DuplicatePolicy::Reuse => {
+                // miniaudio keeps the data of the first registration, `store` is not used
+                register()?;
+                entry.guards += 1;
+                entry.registrations += 1;
+                return Ok(entry.id);
+            }
+            DuplicatePolicy::Replace => {
+                if entry.users > 0 {
+                    return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                        "a resource can not be replaced while buffers built from it are alive",
+                    ))
+                    .with_context(ErrorContext::Resource(name.into_owned())));
+                }
+                // The probe holds on to the old file, which must be freed before the name is
+                // registered again. This waits for the job threads if it is still loading.
+                drop(entry.footprint.take_probe());
+                for _ in 0..entry.registrations {
+                    entry.data.unregister(rm);
+                }
+                // The old guards keep their data alive until they are dropped
+                entry.registrations = 0;
+            }
+        }
+    }
+
+    register()?;
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.entries.push(Entry {
+        rm: key,
+        id,
+        data: data.clone(),
+        store: store.map(Arc::new),
+        borrowed,
+        guards: 1,
+        registrations: 1,
+        users: 0,
+        footprint: footprint(),
+    });
+    Ok(id)
+}
+
+/// Adds a guard to the entry registered under `name`.
+///
+/// Fails if nothing is registered under `name`, or if the data is borrowed.
+pub(crate) fn acquire(rm: usize, name: &str) -> MaResult<(u64, RegisteredDataType)> {
+    let mut registry = registry();
+    let Some(entry) = registry.entries.iter_mut().find(|e| e.is(rm, name)) else {
+        return Err(
+            MaudioError::from_ma_result(sys::ma_result_MA_DOES_NOT_EXIST)
+                .with_context(ErrorContext::Resource(name.to_owned())),
+        );
+    };
+    if entry.borrowed {
+        return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+            "resources registered from borrowed data can not be acquired by name",
+        ))
+        .with_context(ErrorContext::Resource(name.to_owned())));
+    }
+    entry.guards += 1;
+    Ok((entry.id, entry.data.clone()))
+}
+
+/// Returns the data owned by an entry, for a sound that reads it.
+///
+/// Fails for borrowed data, which can not outlive its guard.
+pub(crate) fn shared_store(id: u64) -> MaResult<Option<Arc<DataStore>>> {
+    let registry = registry();
+    let Some(entry) = registry.entries.iter().find(|e| e.id == id) else {
+        return Ok(None);
+    };
+    if entry.borrowed {
+        return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+            "sounds can not be built from borrowed data, register it with a `*_owned` method",
+        ))
+        .with_context(ErrorContext::Resource(entry.data.name().into_owned())));
+    }
+    Ok(entry.store.clone())
+}
+
+/// Adds a guard to an entry, for a cloned guard.
+pub(crate) fn add_guard(id: u64) {
+    if let Some(entry) = registry().entries.iter_mut().find(|e| e.id == id) {
+        entry.guards += 1;
+    }
+}
+
+/// Drops a guard. The last guard unregisters the name once per registration, while the
+/// registry is still locked so that no other thread can acquire the name meanwhile.
+pub(crate) fn release<R: AsRmPtr + ?Sized>(rm: &R, id: u64) {
+    let mut registry = registry();
+    let Some(i) = registry.entries.iter().position(|e| e.id == id) else {
+        return;
+    };
+    let entry = &mut registry.entries[i];
+    entry.guards -= 1;
+    if entry.guards == 0 {
+        let mut entry = registry.entries.swap_remove(i);
+        let probe = entry.footprint.take_probe();
+        for _ in 0..entry.registrations {
+            entry.data.unregister(rm);
+        }
+        drop(registry);
+        // A file that is still loading is freed on the job threads
+        drop(probe);
+    }
+}
+
+/// Returns the stats of an entry, or `None` once it was replaced.
+pub(crate) fn stats(id: u64) -> Option<ResourceStats> {
+    let mut finished = Vec::new();
+    let mut registry = registry();
+    let stats = registry
+        .entries
+        .iter_mut()
+        .find(|e| e.id == id && e.registrations > 0)
+        .map(|e| e.stats(&mut finished));
+    drop(registry);
+    drop(finished);
+    stats
+}
+
+/// Returns the stats of every name registered with `rm`, sorted by name.
+pub(crate) fn all_stats(rm: usize) -> Vec<ResourceStats> {
+    let mut finished = Vec::new();
+    let mut registry = registry();
+    let mut stats: Vec<ResourceStats> = registry
+        .entries
+        .iter_mut()
+        .filter(|e| e.rm == rm && e.registrations > 0)
+        .map(|e| e.stats(&mut finished))
+        .collect();
+    drop(registry);
+    drop(finished);
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    stats
+}
+
+/// Counts a buffer or sound built from a registered name, for as long as it is alive.
+///
+/// miniaudio counts them too, but does not expose the count. A registered name is only
+/// considered in use by `DuplicatePolicy::Replace` and `ResourceCache` while it has users.
+pub(crate) struct ResourceUse {
+    id: u64,
+}
+
+impl ResourceUse {
+    /// Returns `None` if nothing is registered under `name`.
+    pub(crate) fn new(rm: usize, name: &str) -> Option<Self> {
+        let mut registry = registry();
+        let entry = registry.entries.iter_mut().find(|e| e.is(rm, name))?;
+        entry.users += 1;
+        Some(Self { id: entry.id })
+    }
+}
+
+impl Clone for ResourceUse {
+    fn clone(&self) -> Self {
+        if let Some(entry) = registry().entries.iter_mut().find(|e| e.id == self.id) {
+            entry.users += 1;
+        }
+        Self { id: self.id }
+    }
+}
+
+impl Drop for ResourceUse {
+    fn drop(&mut self) {
+        // The entry is gone if every guard was dropped before the user
+        if let Some(entry) = registry().entries.iter_mut().find(|e| e.id == self.id) {
+            entry.users -= 1;
+        }
+    }
+}
+
+pub(crate) fn is_registered(rm: usize, name: &str) -> bool {
+    registry().entries.iter().any(|e| e.is(rm, name))
+}
+
+pub(crate) fn names(rm: usize) -> Vec<String> {
+    let mut names: Vec<String> = registry()
+        .entries
+        .iter()
+        .filter(|e| e.rm == rm && e.registrations > 0)
+        .map(|e| e.data.name().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+fn policy_of(registry: &Registry, rm: usize) -> DuplicatePolicy {
+    registry
+        .policies
+        .iter()
+        .find(|(key, _)| *key == rm)
+        .map_or(DuplicatePolicy::default(), |(_, policy)| *policy)
+}
+
+pub(crate) fn policy(rm: usize) -> DuplicatePolicy {
+    policy_of(&registry(), rm)
+}
+
+pub(crate) fn set_policy(rm: usize, policy: DuplicatePolicy) {
+    let mut registry = registry();
+    registry.policies.retain(|(key, _)| *key != rm);
+    if policy != DuplicatePolicy::default() {
+        registry.policies.push((rm, policy));
+    }
+}
+
+/// Clears the state of a resource manager that is being uninitialized, so that a new one
+/// at the same address starts with the defaults.
+pub(crate) fn forget(rm: usize) {
+    let mut registry = registry();
+    registry.policies.retain(|(key, _)| *key != rm);
+}
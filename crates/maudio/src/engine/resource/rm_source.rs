@@ -6,11 +6,14 @@ use maudio_sys::ffi as sys;
 use crate::{
     data_source::{private_data_source, AsSourcePtr, DataSourceRef, SharedSource},
     engine::resource::{
-        resource_ffi, rm_notif::NotificationPipeline, rm_source_flags::RmSourceFlags, AsRmPtr,
-        PendingResource,
+        resource_ffi, rm_key,
+        rm_notif::{LoadTarget, NotificationPipeline},
+        rm_registry::ResourceUse,
+        rm_source_flags::RmSourceFlags,
+        AsRmPtr, PendingResource,
     },
     sound::sound_builder::OwnedPathBuf,
-    AsRawRef, Binding, MaResult,
+    AsRawRef, Binding, MaResult, ResultContext,
 };
 
 /// Resource-managed audio data source.
@@ -51,6 +54,8 @@ use crate::{
 pub struct ResourceManagerSource<'a, R: AsRmPtr + ?Sized> {
     inner: *mut sys::ma_resource_manager_data_source,
     pipeline_notif: Option<NotificationPipeline>,
+    // Set when the source buffers a registered resource. Streams read the file themselves.
+    resource_use: Option<ResourceUse>,
     _format: PhantomData<R::Format>,
     _marker: PhantomData<&'a R>,
 }
@@ -99,6 +104,7 @@ impl<'a, R: AsRmPtr + ?Sized> ResourceManagerSource<'a, R> {
         Ok(Self {
             inner,
             pipeline_notif: None, // config.pNotifications do not get carried over. PipeNotif will be lost.
+            resource_use: existing.resource_use.clone(),
             _format: PhantomData,
             _marker: PhantomData,
         })
@@ -108,13 +114,32 @@ impl<'a, R: AsRmPtr + ?Sized> ResourceManagerSource<'a, R> {
         let mut mem: Box<MaybeUninit<sys::ma_resource_manager_data_source>> =
             Box::new(MaybeUninit::uninit());
 
-        resource_ffi::ma_resource_manager_data_source_init_ex(config.rm, config, mem.as_mut_ptr())?;
+        let resource_use = match config.source.path() {
+            Some(path) if !config.flags.contains(RmSourceFlags::STREAM) => {
+                ResourceUse::new(rm_key(config.rm), &path.to_string_lossy())
+            }
+            _ => None,
+        };
+        // Done closures and load handles read the result from the data source
+        if let Some(notif) = &config.pipeline_notif {
+            notif.attach(LoadTarget::Source(mem.as_ptr()));
+        }
+        let res = resource_ffi::ma_resource_manager_data_source_init_ex(
+            config.rm,
+            config,
+            mem.as_mut_ptr(),
+        );
+        if let (Err(_), Some(notif)) = (&res, &config.pipeline_notif) {
+            notif.detach();
+        }
+        res?;
 
         let inner: *mut sys::ma_resource_manager_data_source =
             Box::into_raw(mem) as *mut sys::ma_resource_manager_data_source;
         Ok(Self {
             inner,
             pipeline_notif: None,
+            resource_use,
             _format: PhantomData,
             _marker: PhantomData,
         })
@@ -147,10 +172,28 @@ pub enum SourceBufSource<'a> {
     FileWide(&'a Path),
 }
 
+impl<'a> SourceBufSource<'a> {
+    pub(crate) fn path(&self) -> Option<&'a Path> {
+        match *self {
+            SourceBufSource::None => None,
+            #[cfg(unix)]
+            SourceBufSource::FileUtf8(p) => Some(p),
+            #[cfg(windows)]
+            SourceBufSource::FileWide(p) => Some(p),
+        }
+    }
+}
+
+/// Names the file in the error, if the builder loads from a path.
+pub(crate) fn with_source_path<T>(res: MaResult<T>, source: &SourceBufSource<'_>) -> MaResult<T> {
+    match source.path() {
+        Some(path) => res.with_path(path),
+        None => res,
+    }
+}
+
 // TODO:
 // initialSeekPointInPCMFrames;
-// rangeBegInPCMFrames;
-// rangeEndInPCMFrames;
 // loopPointBegInPCMFrames;
 // loopPointEndInPCMFrames;
 impl<'a, R: AsRmPtr> ResourceManagerSourceBuilder<'a, R> {
@@ -230,18 +273,55 @@ impl<'a, R: AsRmPtr> ResourceManagerSourceBuilder<'a, R> {
         self
     }
 
+    /// Streams the file from disk instead of loading it into memory.
+    ///
+    /// Sets [`RmSourceFlags::STREAM`]. Recommended for long files such as music.
+    pub fn streaming(&mut self, yes: bool) -> &mut Self {
+        self.set_flag(RmSourceFlags::STREAM, yes)
+    }
+
+    /// Fully decodes the file into memory when loading, instead of decoding while playing.
+    ///
+    /// Sets [`RmSourceFlags::DECODE`]. Recommended for short sounds that are played often.
+    /// When combined with [`streaming`](Self::streaming), the stream is decoded ahead of time in pages.
+    pub fn decoded(&mut self, yes: bool) -> &mut Self {
+        self.set_flag(RmSourceFlags::DECODE, yes)
+    }
+
+    /// Limits playback to the frames in `start_frame..end_frame`.
+    ///
+    /// Reading and seeking are relative to `start_frame`. The whole file is still loaded.
+    /// To keep only a slice of a long file in memory, use [`RmOps::register_file_range`](crate::engine::resource::RmOps::register_file_range).
+    pub fn range(&mut self, start_frame: u64, end_frame: u64) -> &mut Self {
+        self.inner.rangeBegInPCMFrames = start_frame;
+        self.inner.rangeEndInPCMFrames = end_frame;
+        self
+    }
+
+    fn set_flag(&mut self, flag: RmSourceFlags, yes: bool) -> &mut Self {
+        let mut flags = RmSourceFlags::from_bits(self.inner.flags);
+        flags.set(flag, yes);
+        self.inner.flags = flags.bits();
+        self.flags = flags;
+        self
+    }
+
     pub(crate) fn build_internal(&mut self) -> MaResult<ResourceManagerSource<'a, R>> {
         self.set_source()?;
-        ResourceManagerSource::<R>::new_with_config(self)
+        with_source_path(
+            ResourceManagerSource::<R>::new_with_config(self),
+            &self.source,
+        )
     }
 
     pub fn build(&mut self) -> MaResult<PendingResource<ResourceManagerSource<'a, R>>> {
         let mut buf = self.build_internal()?;
+        // Clone the pipeline notifications to prevent them from getting dropped.
+        // Async loads signal them later from a job thread.
+        buf.pipeline_notif = self.pipeline_notif.clone();
         if self.flags.intersects(RmSourceFlags::ASYNC) {
             return Ok(PendingResource::Pending { inner: Some(buf) });
         }
-        // Clone the pipeline notifications to prevent them from getting dropped
-        buf.pipeline_notif = self.pipeline_notif.clone();
         Ok(PendingResource::Ready { inner: buf })
     }
 
@@ -264,13 +344,48 @@ impl<'a, R: AsRmPtr + ?Sized> Drop for ResourceManagerSource<'a, R> {
 #[cfg(test)]
 mod test {
     use crate::{
+        data_source::DataSourceOps,
         engine::resource::{
             rm_builder::ResourceManagerBuilder, rm_source::ResourceManagerSourceBuilder,
-            tiny_test_wav_mono,
+            rm_source_flags::RmSourceFlags, tiny_test_wav_mono,
         },
         test_assets::temp_file::{unique_tmp_path, TempFileGuard},
     };
 
+    #[test]
+    fn test_res_man_data_source_builder_flag_helpers() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let mut builder = ResourceManagerSourceBuilder::new(&rm);
+        builder.streaming(true).decoded(true).async_load(true);
+        assert!(builder
+            .flags
+            .contains(RmSourceFlags::STREAM | RmSourceFlags::DECODE | RmSourceFlags::ASYNC));
+
+        builder.streaming(false).async_load(false);
+        assert_eq!(builder.flags, RmSourceFlags::DECODE);
+        assert_eq!(builder.inner.flags, RmSourceFlags::DECODE.bits());
+    }
+
+    #[test]
+    fn test_res_man_data_source_builder_range() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+
+        let wav = tiny_test_wav_mono(200);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        let source = ResourceManagerSourceBuilder::new(&rm)
+            .file_path(path_guard.path())
+            .decoded(true)
+            .range(50, 150)
+            .build()
+            .unwrap()
+            .into_ready()
+            .unwrap();
+        assert_eq!(source.range_in_pcm_frames(), 50..150);
+        assert_eq!(source.length_in_pcm_frames().unwrap(), 100);
+    }
+
     #[test]
     fn test_res_man_data_source_builder_basic_init() {
         let rm = ResourceManagerBuilder::new().build_f32().unwrap();
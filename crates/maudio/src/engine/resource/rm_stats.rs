@@ -0,0 +1,370 @@
+//! Memory accounting for the resources registered with the resource manager.
+//!
+//! Every resource registered with `register_*()` is tracked by maudio, along with the size of
+//! its data and the buffers and sounds built from it.
+//! [`RmOps::memory_stats`](crate::engine::resource::RmOps::memory_stats) takes a snapshot of
+//! them, which can be used to monitor how much registered audio data is resident in memory.
+//! See [`ResourceCache`](crate::engine::resource::rm_cache::ResourceCache) for unloading
+//! registered resources under a memory budget.
+//!
+//! Files loaded by path without being registered, and streams (`ResourceManagerStream`), are
+//! not included.
+use std::{mem::MaybeUninit, path::Path};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::formats::Format,
+    engine::resource::{private_rm, rm_source_flags::RmSourceFlags, AsRmPtr},
+};
+
+/// How the data of a resource is stored in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceDataKind {
+    /// Encoded (compressed) bytes, decoded on the fly when read.
+    Encoded,
+    /// Decoded PCM frames.
+    Decoded,
+}
+
+/// Snapshot of a single registered resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceStats {
+    /// The name (or file path) the resource was registered under.
+    pub name: String,
+    /// Number of registrations, buffers and sounds using this resource.
+    pub ref_count: u32,
+    /// Bytes of audio data held in memory.
+    ///
+    /// Encoded files count their size on disk. Decoded files are only counted once
+    /// loading has finished.
+    pub size_bytes: u64,
+    pub kind: ResourceDataKind,
+    /// `true` if the data was allocated by the resource manager.
+    ///
+    /// `false` for data registered with `register_*()`, which is owned by the application
+    /// or by the [`ResourceGuard`](crate::engine::resource::ResourceGuard).
+    pub owned_by_rm: bool,
+    /// `true` once loading has finished successfully.
+    pub loaded: bool,
+}
+
+/// Snapshot of all resources registered with the resource manager.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RmMemoryStats {
+    pub resources: Vec<ResourceStats>,
+}
+
+impl RmMemoryStats {
+    /// Total bytes of audio data resident in memory.
+    pub fn total_bytes(&self) -> u64 {
+        self.resources.iter().map(|r| r.size_bytes).sum()
+    }
+
+    /// Total bytes of decoded PCM data resident in memory.
+    pub fn decoded_bytes(&self) -> u64 {
+        self.resources
+            .iter()
+            .filter(|r| r.kind == ResourceDataKind::Decoded)
+            .map(|r| r.size_bytes)
+            .sum()
+    }
+
+    /// Number of registered resources.
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// Finds a resource by the name (or file path) it was registered under.
+    pub fn find(&self, name: &str) -> Option<&ResourceStats> {
+        self.resources.iter().find(|r| r.name == name)
+    }
+}
+
+/// Size of the data of a registered resource, recorded when it is registered.
+pub(crate) struct Footprint {
+    kind: ResourceDataKind,
+    size_bytes: u64,
+    owned_by_rm: bool,
+    loaded: bool,
+    // Open on a registered file until it has finished loading
+    probe: Option<LoadProbe>,
+}
+
+impl Footprint {
+    pub(crate) fn encoded(data: &[u8]) -> Self {
+        Self::registered(ResourceDataKind::Encoded, data.len() as u64)
+    }
+
+    pub(crate) fn decoded<T>(data: &[T]) -> Self {
+        Self::registered(
+            ResourceDataKind::Decoded,
+            std::mem::size_of_val(data) as u64,
+        )
+    }
+
+    fn registered(kind: ResourceDataKind, size_bytes: u64) -> Self {
+        Self {
+            kind,
+            size_bytes,
+            owned_by_rm: false,
+            loaded: true,
+            probe: None,
+        }
+    }
+
+    /// Footprint of a file that was just registered with `flags`.
+    ///
+    /// The resource manager reads encoded files into memory as they are, so their size is
+    /// known right away. Decoded files are measured once they are loaded.
+    pub(crate) fn file<R: AsRmPtr + ?Sized>(rm: &R, path: &Path, flags: RmSourceFlags) -> Self {
+        let (kind, size_bytes) = if flags.contains(RmSourceFlags::DECODE) {
+            (ResourceDataKind::Decoded, 0)
+        } else {
+            let len = std::fs::metadata(path).map_or(0, |m| m.len());
+            (ResourceDataKind::Encoded, len)
+        };
+        let mut footprint = Self {
+            kind,
+            size_bytes,
+            owned_by_rm: true,
+            loaded: false,
+            probe: LoadProbe::open(rm, path, flags & RmSourceFlags::ASYNC),
+        };
+        // Synchronous registrations are loaded already
+        drop(footprint.update());
+        footprint
+    }
+
+    /// Records the size of a file that has finished loading.
+    ///
+    /// Returns the probe once it is no longer needed. Dropping it may wait for the job
+    /// threads, so callers drop it after unlocking the registry.
+    pub(crate) fn update(&mut self) -> Option<LoadProbe> {
+        let result = self.probe.as_ref()?.result();
+        if result == sys::ma_result_MA_BUSY {
+            return None;
+        }
+        let probe = self.probe.take()?;
+        if result == sys::ma_result_MA_SUCCESS {
+            self.loaded = true;
+            if self.kind == ResourceDataKind::Decoded {
+                self.size_bytes = probe.decoded_bytes().unwrap_or(0);
+            }
+        }
+        Some(probe)
+    }
+
+    /// Takes the probe of a file that is still loading.
+    pub(crate) fn take_probe(&mut self) -> Option<LoadProbe> {
+        self.probe.take()
+    }
+
+    pub(crate) fn stats(&self, name: String, ref_count: u32) -> ResourceStats {
+        ResourceStats {
+            name,
+            ref_count,
+            size_bytes: self.size_bytes,
+            kind: self.kind,
+            owned_by_rm: self.owned_by_rm,
+            loaded: self.loaded,
+        }
+    }
+}
+
+/// A data buffer opened on a registered file, to tell when it has finished loading.
+///
+/// It only uses miniaudio's public data buffer API. The file is registered when the probe
+/// is opened, so opening it never loads the file a second time.
+pub(crate) struct LoadProbe(*mut sys::ma_resource_manager_data_buffer);
+
+// The probe is only passed to miniaudio's data buffer functions, which can be called from
+// any thread, and is never read while it is uninitialized.
+unsafe impl Send for LoadProbe {}
+
+impl LoadProbe {
+    fn open<R: AsRmPtr + ?Sized>(rm: &R, path: &Path, flags: RmSourceFlags) -> Option<Self> {
+        let mut mem: Box<MaybeUninit<sys::ma_resource_manager_data_buffer>> =
+            Box::new(MaybeUninit::uninit());
+
+        #[cfg(unix)]
+        let res = {
+            let path = crate::engine::cstring_from_path(path).ok()?;
+            unsafe {
+                sys::ma_resource_manager_data_buffer_init(
+                    private_rm::rm_ptr(rm),
+                    path.as_ptr(),
+                    flags.bits(),
+                    core::ptr::null(),
+                    mem.as_mut_ptr(),
+                )
+            }
+        };
+
+        #[cfg(windows)]
+        let res = {
+            let path = crate::engine::wide_null_terminated(path);
+            unsafe {
+                sys::ma_resource_manager_data_buffer_init_w(
+                    private_rm::rm_ptr(rm),
+                    path.as_ptr(),
+                    flags.bits(),
+                    core::ptr::null(),
+                    mem.as_mut_ptr(),
+                )
+            }
+        };
+
+        if res != sys::ma_result_MA_SUCCESS {
+            return None;
+        }
+        Some(Self(Box::into_raw(mem).cast()))
+    }
+
+    fn result(&self) -> sys::ma_result {
+        unsafe { sys::ma_resource_manager_data_buffer_result(self.0) }
+    }
+
+    fn decoded_bytes(&self) -> Option<u64> {
+        let mut format = sys::ma_format_ma_format_unknown;
+        let mut channels = 0u32;
+        let mut sample_rate = 0u32;
+        let res = unsafe {
+            sys::ma_resource_manager_data_buffer_get_data_format(
+                self.0,
+                &mut format,
+                &mut channels,
+                &mut sample_rate,
+                core::ptr::null_mut(),
+                0,
+            )
+        };
+        if res != sys::ma_result_MA_SUCCESS {
+            return None;
+        }
+
+        let mut frames = 0u64;
+        let res = unsafe {
+            sys::ma_resource_manager_data_buffer_get_length_in_pcm_frames(self.0, &mut frames)
+        };
+        if res != sys::ma_result_MA_SUCCESS {
+            return None;
+        }
+
+        let bytes_per_frame =
+            Format::try_from(format).ok()?.bytes_per_sample() as u64 * channels as u64;
+        Some(frames.saturating_mul(bytes_per_frame))
+    }
+}
+
+impl Drop for LoadProbe {
+    fn drop(&mut self) {
+        unsafe {
+            sys::ma_resource_manager_data_buffer_uninit(self.0);
+            drop(Box::from_raw(
+                self.0
+                    .cast::<MaybeUninit<sys::ma_resource_manager_data_buffer>>(),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::resource::{
+            rm_buffer::ResourceManagerBufferBuilder, rm_builder::ResourceManagerBuilder,
+            rm_source_flags::RmSourceFlags, rm_stats::ResourceDataKind, tiny_test_wav_mono, RmOps,
+        },
+        test_assets::temp_file::{unique_tmp_path, TempFileGuard},
+    };
+
+    #[test]
+    fn test_rm_stats_empty_manager() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let stats = rm.memory_stats();
+        assert!(stats.is_empty());
+        assert_eq!(stats.total_bytes(), 0);
+    }
+
+    #[test]
+    fn test_rm_stats_registered_decoded_data() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let data = vec![0.0f32; 2 * 100];
+        let guard = rm
+            .register_decoded_f32(
+                "stats_decoded",
+                &data,
+                2,
+                crate::audio::sample_rate::SampleRate::Sr48000,
+            )
+            .unwrap();
+
+        let stats = guard.stats().unwrap();
+        assert_eq!(stats.kind, ResourceDataKind::Decoded);
+        assert_eq!(stats.size_bytes, 100 * 2 * 4);
+        assert_eq!(stats.ref_count, 1);
+        assert!(!stats.owned_by_rm);
+        assert_eq!(rm.memory_stats().total_bytes(), 800);
+        assert_eq!(rm.resident_bytes(), 800);
+    }
+
+    #[test]
+    fn test_rm_stats_decoded_file_counts_buffers() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav = tiny_test_wav_mono(300);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        let guard = rm
+            .register_file(path_guard.path(), RmSourceFlags::DECODE)
+            .unwrap();
+        assert_eq!(guard.stats().unwrap().ref_count, 1);
+
+        let buf = ResourceManagerBufferBuilder::new(&rm)
+            .file_path(path_guard.path())
+            .build()
+            .unwrap();
+        let stats = guard.stats().unwrap();
+        assert_eq!(stats.ref_count, 2);
+        assert!(stats.owned_by_rm);
+        assert!(stats.loaded);
+        // Mono f32
+        assert_eq!(stats.size_bytes, 300 * 4);
+
+        drop(buf);
+        assert_eq!(guard.stats().unwrap().ref_count, 1);
+        drop(guard);
+        assert!(rm.memory_stats().is_empty());
+    }
+
+    #[test]
+    fn test_rm_stats_async_file_is_measured_once_loaded() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let wav = tiny_test_wav_mono(300);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(path_guard.path(), &wav).unwrap();
+
+        let guard = rm
+            .register_file(
+                path_guard.path(),
+                RmSourceFlags::DECODE | RmSourceFlags::ASYNC,
+            )
+            .unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while !guard.stats().unwrap().loaded {
+            assert!(std::time::Instant::now() < deadline, "load timed out");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        let stats = rm.memory_stats();
+        let resource = stats.find(&guard.name()).unwrap();
+        assert_eq!(resource.kind, ResourceDataKind::Decoded);
+        assert_eq!(resource.size_bytes, 300 * 4);
+        assert_eq!(resource.ref_count, 1);
+    }
+}
@@ -1,4 +1,17 @@
 //! Resource-managed streaming audio sources
+//!
+//! ## Read-ahead
+//!
+//! A stream decodes its file in pages of [`PAGE_SIZE_MILLIS`] milliseconds, on a job
+//! thread, and keeps [`PAGE_COUNT`] pages decoded. Playback reads one page while the other
+//! is refilled, so a page must be decoded faster than the other one plays. When the storage
+//! stalls for longer than that, the stream underruns and plays silence.
+//!
+//! miniaudio fixes the page size at compile time, through
+//! `MA_RESOURCE_MANAGER_PAGE_SIZE_IN_MILLISECONDS`, so it cannot be set per stream. Use
+//! [`page_size_frames`](ResourceManagerStream::page_size_frames) and
+//! [`read_ahead_frames`](ResourceManagerStream::read_ahead_frames) to check how much audio a
+//! stream buffers. Sounds that must survive longer stalls are better decoded fully into memory.
 use std::{marker::PhantomData, mem::MaybeUninit, path::Path};
 
 use maudio_sys::ffi as sys;
@@ -6,13 +19,22 @@ use maudio_sys::ffi as sys;
 use crate::{
     data_source::{private_data_source, AsSourcePtr, DataSourceRef, SharedSource},
     engine::resource::{
-        resource_ffi, rm_notif::NotificationPipeline, rm_source::SourceBufSource,
-        rm_source_flags::RmSourceFlags, AsRmPtr, PendingResource,
+        resource_ffi,
+        rm_notif::{LoadTarget, NotificationPipeline},
+        rm_source::{with_source_path, SourceBufSource},
+        rm_source_flags::RmSourceFlags,
+        AsRmPtr, PendingResource,
     },
     sound::sound_builder::OwnedPathBuf,
     AsRawRef, Binding, MaResult,
 };
 
+/// Length of a stream page in milliseconds. See the [module docs](self).
+pub const PAGE_SIZE_MILLIS: u32 = 1000;
+
+/// Number of pages a stream keeps decoded.
+pub const PAGE_COUNT: u32 = 2;
+
 /// Resource-managed streaming audio source.
 ///
 /// Wraps a miniaudio `ma_resource_manager_data_stream`.
@@ -72,6 +94,22 @@ impl<'a, R: AsRmPtr> ResourceManagerStream<'a, R> {
         let ptr = self.to_raw().cast::<sys::ma_data_source>();
         DataSourceRef::from_ptr(ptr)
     }
+
+    /// Returns the length of a page in frames, at the sample rate the stream decodes to.
+    ///
+    /// Returns `0` while an async stream is still loading.
+    pub fn page_size_frames(&self) -> u32 {
+        let sample_rate = unsafe { (*self.to_raw()).decoder.outputSampleRate };
+        // Same rounding as miniaudio
+        PAGE_SIZE_MILLIS * (sample_rate / 1000)
+    }
+
+    /// Returns the number of frames the stream keeps decoded ahead of playback.
+    ///
+    /// Returns `0` while an async stream is still loading.
+    pub fn read_ahead_frames(&self) -> u32 {
+        self.page_size_frames() * PAGE_COUNT
+    }
 }
 
 // private methods
@@ -80,7 +118,19 @@ impl<'a, R: AsRmPtr> ResourceManagerStream<'a, R> {
         let mut mem: Box<MaybeUninit<sys::ma_resource_manager_data_stream>> =
             Box::new(MaybeUninit::uninit());
 
-        resource_ffi::ma_resource_manager_data_stream_init_ex(config.rm, config, mem.as_mut_ptr())?;
+        // Done closures and load handles read the result from the data stream
+        if let Some(notif) = &config.pipeline_notif {
+            notif.attach(LoadTarget::Stream(mem.as_ptr()));
+        }
+        let res = resource_ffi::ma_resource_manager_data_stream_init_ex(
+            config.rm,
+            config,
+            mem.as_mut_ptr(),
+        );
+        if let (Err(_), Some(notif)) = (&res, &config.pipeline_notif) {
+            notif.detach();
+        }
+        res?;
 
         let inner: *mut sys::ma_resource_manager_data_stream =
             Box::into_raw(mem) as *mut sys::ma_resource_manager_data_stream;
@@ -94,6 +144,10 @@ impl<'a, R: AsRmPtr> ResourceManagerStream<'a, R> {
     }
 }
 
+/// Builder for a [`ResourceManagerStream`].
+///
+/// The page size, which sets how far ahead streams decode, is fixed by miniaudio. See the
+/// [module docs](self).
 pub struct ResourceManagerStreamBuilder<'a, R: AsRmPtr + ?Sized> {
     rm: &'a R,
     inner: sys::ma_resource_manager_data_source_config,
@@ -187,16 +241,20 @@ impl<'a, R: AsRmPtr> ResourceManagerStreamBuilder<'a, R> {
 
     pub(crate) fn build_internal(&mut self) -> MaResult<ResourceManagerStream<'a, R>> {
         self.set_source()?;
-        ResourceManagerStream::<R>::new_with_config(self)
+        with_source_path(
+            ResourceManagerStream::<R>::new_with_config(self),
+            &self.source,
+        )
     }
 
     pub fn build(&mut self) -> MaResult<PendingResource<ResourceManagerStream<'a, R>>> {
         let mut buf = self.build_internal()?;
+        // Clone the pipeline notifications to prevent them from getting dropped.
+        // Async loads signal them later from a job thread.
+        buf.pipeline_notif = self.pipeline_notif.clone();
         if self.flags.intersects(RmSourceFlags::ASYNC) {
             return Ok(PendingResource::Pending { inner: Some(buf) });
         }
-        // Clone the pipeline notifications to prevent them from getting dropped
-        buf.pipeline_notif = self.pipeline_notif.clone();
         Ok(PendingResource::Ready { inner: buf })
     }
 }
@@ -212,8 +270,9 @@ impl<'a, R: AsRmPtr + ?Sized> Drop for ResourceManagerStream<'a, R> {
 mod test {
     use crate::{
         engine::resource::{
-            rm_builder::ResourceManagerBuilder, rm_stream::ResourceManagerStreamBuilder,
-            tiny_test_wav_mono,
+            rm_builder::ResourceManagerBuilder,
+            rm_stream::{ResourceManagerStreamBuilder, PAGE_COUNT, PAGE_SIZE_MILLIS},
+            tiny_test_wav_mono, PendingResource,
         },
         test_assets::temp_file::{unique_tmp_path, TempFileGuard},
     };
@@ -232,4 +291,26 @@ mod test {
             .build()
             .unwrap();
     }
+
+    #[test]
+    fn test_res_man_data_source_stream_read_ahead() {
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+
+        let wav = tiny_test_wav_mono(20);
+        let path_guard = TempFileGuard::new(unique_tmp_path("wav"));
+        let path = path_guard.path().to_path_buf();
+        std::fs::write(&path, &wav).unwrap();
+
+        let stream = ResourceManagerStreamBuilder::new(&rm)
+            .file_path(&path)
+            .build()
+            .unwrap();
+        let PendingResource::Ready { inner: stream } = stream else {
+            panic!("a sync stream is ready once built");
+        };
+        let sample_rate = stream.as_source_ref().data_format().unwrap().sample_rate;
+        let expected = PAGE_SIZE_MILLIS * (u32::from(sample_rate) / 1000);
+        assert_eq!(stream.page_size_frames(), expected);
+        assert_eq!(stream.read_ahead_frames(), expected * PAGE_COUNT);
+    }
 }
@@ -0,0 +1,454 @@
+//! The sounds of an engine that are still alive, behind [`Engine::pause_all_except`],
+//! [`Engine::set_time_scale`] and [`Engine::set_start_policy`].
+//!
+//! [`Engine::pause_all_except`]: crate::engine::Engine::pause_all_except
+//! [`Engine::set_time_scale`]: crate::engine::Engine::set_time_scale
+//! [`Engine::set_start_policy`]: crate::engine::Engine::set_start_policy
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::{
+        node_graph::nodes::{node_ffi, NodeRef},
+        one_shot::OneShots,
+        start_policy::{Overflow, StartOutcome, StartPolicy},
+    },
+    sound::sound_group::SoundGroup,
+    util::rt_check,
+    MaResult, MaudioError,
+};
+
+// A `ma_sound` owned by a `Sound`, which unregisters it before freeing it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct SoundPtr(*mut sys::ma_sound);
+
+unsafe impl Send for SoundPtr {}
+
+struct RegistryState {
+    // Whether each sound ignores the time scale
+    live: HashMap<SoundPtr, bool>,
+    // Sounds stopped by `pause_except`, to start again in `resume`
+    paused: HashSet<SoundPtr>,
+    time_scale: f32,
+    policy: Option<StartPolicy>,
+    // Starts held back by `Overflow::Defer`, with their priority, in order of arrival
+    deferred: Vec<(SoundPtr, u8)>,
+}
+
+impl RegistryState {
+    // Factor between the pitch set by the user and the pitch given to miniaudio
+    fn pitch_factor(&self, sound: SoundPtr) -> f32 {
+        match self.live.get(&sound) {
+            Some(false) => self.time_scale,
+            _ => 1.0,
+        }
+    }
+}
+
+pub(crate) struct SoundRegistry {
+    state: Mutex<RegistryState>,
+}
+
+impl Default for SoundRegistry {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(RegistryState {
+                live: HashMap::new(),
+                paused: HashSet::new(),
+                time_scale: 1.0,
+                policy: None,
+                deferred: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl SoundRegistry {
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        rt_check::check("engine sound registry");
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn register(&self, sound: *mut sys::ma_sound) {
+        let mut state = self.lock();
+        state.live.insert(SoundPtr(sound), false);
+        if state.time_scale != 1.0 {
+            unsafe {
+                let pitch = sys::ma_sound_get_pitch(sound);
+                sys::ma_sound_set_pitch(sound, pitch * state.time_scale);
+            }
+        }
+    }
+
+    /// Removes a sound that is about to be freed.
+    pub(crate) fn unregister(&self, sound: *mut sys::ma_sound) {
+        let mut state = self.lock();
+        state.live.remove(&SoundPtr(sound));
+        state.paused.remove(&SoundPtr(sound));
+        state.deferred.retain(|(s, _)| s.0 != sound);
+    }
+
+    /// Called when a sound is stopped by hand, so `resume` and `start_deferred` leave it
+    /// stopped.
+    pub(crate) fn forget_paused(&self, sound: *mut sys::ma_sound) {
+        let mut state = self.lock();
+        state.paused.remove(&SoundPtr(sound));
+        state.deferred.retain(|(s, _)| s.0 != sound);
+    }
+
+    pub(crate) fn start_policy(&self) -> Option<StartPolicy> {
+        self.lock().policy
+    }
+
+    /// Sets the policy. Removing it, or switching to `Overflow::Drop`, drops the deferred starts.
+    pub(crate) fn set_start_policy(&self, policy: Option<StartPolicy>) {
+        let mut state = self.lock();
+        state.policy = policy;
+        if !matches!(policy, Some(p) if p.overflow == Overflow::Defer) {
+            state.deferred.clear();
+        }
+    }
+
+    /// Starts a sound with `start`, unless the policy holds it back.
+    pub(crate) fn start(
+        &self,
+        one_shots: &OneShots,
+        sound: *mut sys::ma_sound,
+        priority: u8,
+        start: impl FnOnce() -> MaResult<()>,
+    ) -> MaResult<StartOutcome> {
+        let mut state = self.lock();
+        let held_back = match state.policy {
+            Some(policy) if priority < policy.always_start_priority => {
+                let playing = unsafe { sys::ma_sound_is_playing(sound) } == 1;
+                let saturated = Self::playing_count(&state, one_shots) >= policy.max_playing;
+                (!playing && saturated).then_some(policy.overflow)
+            }
+            _ => None,
+        };
+        match held_back {
+            Some(Overflow::Defer) => {
+                if !state.deferred.iter().any(|(s, _)| s.0 == sound) {
+                    state.deferred.push((SoundPtr(sound), priority));
+                }
+                Ok(StartOutcome::Deferred)
+            }
+            Some(Overflow::Drop) => Ok(StartOutcome::Dropped),
+            None => {
+                state.deferred.retain(|(s, _)| s.0 != sound);
+                start()?;
+                Ok(StartOutcome::Started)
+            }
+        }
+    }
+
+    /// Starts deferred sounds while fewer than `max_playing` sounds are playing, and returns
+    /// how many were started.
+    pub(crate) fn start_deferred(&self, one_shots: &OneShots) -> MaResult<usize> {
+        let mut state = self.lock();
+        let Some(policy) = state.policy else {
+            return Ok(0);
+        };
+        let mut started = 0;
+        while !state.deferred.is_empty()
+            && Self::playing_count(&state, one_shots) < policy.max_playing
+        {
+            // Highest priority first, the oldest start among equals
+            let best = state
+                .deferred
+                .iter()
+                .enumerate()
+                .max_by(|(ia, (_, a)), (ib, (_, b))| a.cmp(b).then(ib.cmp(ia)))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            let (sound, _) = state.deferred.remove(best);
+            MaudioError::check(unsafe { sys::ma_sound_start(sound.0) })?;
+            started += 1;
+        }
+        Ok(started)
+    }
+
+    pub(crate) fn deferred_count(&self) -> usize {
+        self.lock().deferred.len()
+    }
+
+    fn playing_count(state: &RegistryState, one_shots: &OneShots) -> usize {
+        one_shots.with_sounds(|one_shots| {
+            state
+                .live
+                .keys()
+                .map(|s| s.0)
+                .chain(one_shots.iter().copied())
+                .filter(|s| unsafe { sys::ma_sound_is_playing(*s) } == 1)
+                .count()
+        })
+    }
+
+    pub(crate) fn pause_except(
+        &self,
+        one_shots: &OneShots,
+        exempt: &[&SoundGroup],
+    ) -> MaResult<usize> {
+        let mut state = self.lock();
+        one_shots.with_sounds(|one_shots| {
+            let playing = state
+                .live
+                .keys()
+                .copied()
+                .chain(one_shots.iter().map(|s| SoundPtr(*s)))
+                .filter(|s| unsafe { sys::ma_sound_is_playing(s.0) } == 1)
+                .collect::<Vec<_>>();
+
+            let mut paused = 0;
+            for sound in playing {
+                let node = NodeRef::from_ptr(sound.0.cast::<sys::ma_node>());
+                // Covers sounds in a child group of an exempt group, and inserted effects
+                if exempt
+                    .iter()
+                    .any(|g| node_ffi::node_feeds_into(&node, &g.as_node()))
+                {
+                    continue;
+                }
+                MaudioError::check(unsafe { sys::ma_sound_stop(sound.0) })?;
+                state.paused.insert(sound);
+                paused += 1;
+            }
+            Ok(paused)
+        })
+    }
+
+    pub(crate) fn resume(&self, one_shots: &OneShots) -> MaResult<usize> {
+        let mut state = self.lock();
+        let paused = core::mem::take(&mut state.paused);
+        one_shots.with_sounds(|one_shots| {
+            let mut resumed = 0;
+            for sound in paused {
+                if !state.live.contains_key(&sound) && !one_shots.contains(&sound.0) {
+                    continue;
+                }
+                // Started again by hand since
+                if unsafe { sys::ma_sound_is_playing(sound.0) } == 1 {
+                    continue;
+                }
+                MaudioError::check(unsafe { sys::ma_sound_start(sound.0) })?;
+                resumed += 1;
+            }
+            Ok(resumed)
+        })
+    }
+
+    pub(crate) fn paused_count(&self) -> usize {
+        self.lock().paused.len()
+    }
+
+    pub(crate) fn time_scale(&self) -> f32 {
+        self.lock().time_scale
+    }
+
+    /// Rescales the pitch of every sound that is not exempt, one-shots included.
+    pub(crate) fn set_time_scale(&self, one_shots: &OneShots, scale: f32) {
+        let mut state = self.lock();
+        let ratio = scale / state.time_scale;
+        state.time_scale = scale;
+        one_shots.with_sounds(|one_shots| {
+            let scaled = state
+                .live
+                .iter()
+                .filter(|(_, exempt)| !**exempt)
+                .map(|(s, _)| s.0)
+                .chain(one_shots.iter().copied());
+            for sound in scaled {
+                unsafe {
+                    let pitch = sys::ma_sound_get_pitch(sound);
+                    sys::ma_sound_set_pitch(sound, pitch * ratio);
+                }
+            }
+        });
+    }
+
+    /// Sets the pitch of a sound, before the time scale is applied.
+    pub(crate) fn set_pitch(&self, sound: *mut sys::ma_sound, pitch: f32) {
+        let state = self.lock();
+        let factor = state.pitch_factor(SoundPtr(sound));
+        unsafe { sys::ma_sound_set_pitch(sound, pitch * factor) };
+    }
+
+    /// Returns the pitch of a sound, before the time scale is applied.
+    pub(crate) fn pitch(&self, sound: *mut sys::ma_sound) -> f32 {
+        let state = self.lock();
+        let factor = state.pitch_factor(SoundPtr(sound));
+        unsafe { sys::ma_sound_get_pitch(sound) / factor }
+    }
+
+    pub(crate) fn is_time_scale_exempt(&self, sound: *mut sys::ma_sound) -> bool {
+        self.lock()
+            .live
+            .get(&SoundPtr(sound))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn set_time_scale_exempt(&self, sound: *mut sys::ma_sound, exempt: bool) {
+        let mut state = self.lock();
+        let pitch = {
+            let factor = state.pitch_factor(SoundPtr(sound));
+            unsafe { sys::ma_sound_get_pitch(sound) / factor }
+        };
+        if let Some(entry) = state.live.get_mut(&SoundPtr(sound)) {
+            *entry = exempt;
+        }
+        let factor = state.pitch_factor(SoundPtr(sound));
+        unsafe { sys::ma_sound_set_pitch(sound, pitch * factor) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::{formats::SampleBuffer, sample_rate::SampleRate},
+        data_source::sources::buffer::AudioBufferBuilder,
+        engine::{
+            engine_builder::EngineBuilder,
+            one_shot::PlayOptions,
+            start_policy::{Overflow, StartOutcome, StartPolicy},
+        },
+        sound::{sound_builder::SoundBuilder, sound_group::SoundGroupBuilder},
+    };
+
+    #[test]
+    fn test_pause_all_except_keeps_exempt_groups_playing() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let ui = engine.new_sound_group().unwrap();
+        let menu = SoundGroupBuilder::new(&engine)
+            .initial_attachment(&ui.as_node(), 0)
+            .build()
+            .unwrap();
+        let music_buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 48_000]).unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 48_000]).unwrap();
+
+        // Each sound reads its own buffer, so the cursors are independent
+        let mut music = engine.new_sound_from_source(&music_buffer).unwrap();
+        let mut click = SoundBuilder::new(&engine)
+            .data_source(&buffer)
+            .sound_group(&menu)
+            .build()
+            .unwrap();
+        let idle = engine.new_sound_from_source(&buffer).unwrap();
+        music.play_sound().unwrap();
+        click.play_sound().unwrap();
+        let samples = SampleBuffer::<f32>::new_zeroed(256, 1).unwrap();
+        let samples = SampleBuffer::from_storage(samples, 256, 1).unwrap();
+        engine.play_buffer(&samples, &PlayOptions::new()).unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(64).unwrap();
+        assert_eq!(engine.pause_all_except(&[&ui]).unwrap(), 2);
+        assert_eq!(engine.paused_sound_count(), 2);
+        assert!(!music.is_playing());
+        assert!(click.is_playing());
+        assert!(!idle.is_playing());
+
+        let cursor = music.cursor_pcm().unwrap();
+        reader.read_pcm_frames(64).unwrap();
+        assert_eq!(music.cursor_pcm().unwrap(), cursor);
+        assert_eq!(engine.playing_buffer_count(), 1);
+
+        assert_eq!(engine.resume_all().unwrap(), 2);
+        assert!(music.is_playing());
+        assert!(!idle.is_playing());
+        assert_eq!(engine.paused_sound_count(), 0);
+    }
+
+    #[test]
+    fn test_resume_all_leaves_sounds_changed_while_paused() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 48_000]).unwrap();
+        let mut stopped = engine.new_sound_from_source(&buffer).unwrap();
+        let mut restarted = engine.new_sound_from_source(&buffer).unwrap();
+        let mut dropped = engine.new_sound_from_source(&buffer).unwrap();
+        stopped.play_sound().unwrap();
+        restarted.play_sound().unwrap();
+        dropped.play_sound().unwrap();
+
+        assert_eq!(engine.pause_all().unwrap(), 3);
+        stopped.stop_sound().unwrap();
+        restarted.play_sound().unwrap();
+        drop(dropped);
+        assert_eq!(engine.paused_sound_count(), 1);
+
+        assert_eq!(engine.resume_all().unwrap(), 0);
+        assert!(!stopped.is_playing());
+        assert!(restarted.is_playing());
+    }
+
+    #[test]
+    fn test_start_policy_drops_low_priority_starts() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        engine.set_start_policy(Some(
+            StartPolicy::new(1, Overflow::Drop).always_start_priority(200),
+        ));
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 48_000]).unwrap();
+        let mut first = engine.new_sound_from_source(&buffer).unwrap();
+        let mut second = engine.new_sound_from_source(&buffer).unwrap();
+        let mut music = engine.new_sound_from_source(&buffer).unwrap();
+        music.set_priority(200);
+
+        assert_eq!(first.try_play_sound().unwrap(), StartOutcome::Started);
+        // Already playing
+        assert_eq!(first.try_play_sound().unwrap(), StartOutcome::Started);
+        assert_eq!(second.try_play_sound().unwrap(), StartOutcome::Dropped);
+        assert!(!second.is_playing());
+        assert_eq!(music.try_play_sound().unwrap(), StartOutcome::Started);
+        assert_eq!(engine.deferred_sound_count(), 0);
+
+        engine.set_start_policy(None);
+        assert_eq!(second.try_play_sound().unwrap(), StartOutcome::Started);
+    }
+
+    #[test]
+    fn test_start_policy_defers_until_a_slot_frees_up() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        engine.set_start_policy(Some(StartPolicy::new(1, Overflow::Defer)));
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 48_000]).unwrap();
+        let mut playing = engine.new_sound_from_source(&buffer).unwrap();
+        let mut low = engine.new_sound_from_source(&buffer).unwrap();
+        let mut high = engine.new_sound_from_source(&buffer).unwrap();
+        let mut cancelled = engine.new_sound_from_source(&buffer).unwrap();
+        high.set_priority(5);
+
+        playing.play_sound().unwrap();
+        assert_eq!(low.try_play_sound().unwrap(), StartOutcome::Deferred);
+        assert_eq!(high.try_play_sound().unwrap(), StartOutcome::Deferred);
+        assert_eq!(cancelled.try_play_sound().unwrap(), StartOutcome::Deferred);
+        cancelled.stop_sound().unwrap();
+        assert_eq!(engine.deferred_sound_count(), 2);
+        assert_eq!(engine.start_deferred_sounds().unwrap(), 0);
+
+        playing.stop_sound().unwrap();
+        assert_eq!(engine.start_deferred_sounds().unwrap(), 1);
+        assert!(high.is_playing());
+        assert!(!low.is_playing());
+
+        drop(high);
+        assert_eq!(engine.start_deferred_sounds().unwrap(), 1);
+        assert!(low.is_playing());
+        assert!(!cancelled.is_playing());
+        assert_eq!(engine.deferred_sound_count(), 0);
+    }
+}
@@ -0,0 +1,81 @@
+//! Limit on the number of playing sounds, behind [`Engine::set_start_policy`].
+//!
+//! Every started sound is mixed, so a burst of sounds (an explosion with a hundred debris
+//! impacts) costs as much as it has sounds, and the important ones get lost in the mix. With
+//! a [`StartPolicy`], [`Sound::try_play_sound`] holds back the starts of low priority sounds
+//! while the engine already plays `max_playing` sounds. Depending on the [`Overflow`] action
+//! they are dropped, or deferred and started by [`Engine::start_deferred_sounds`] once
+//! enough sounds stopped.
+//!
+//! Priorities are set with [`Sound::set_priority`]. Sounds at or above
+//! [`StartPolicy::always_start_priority`] are always started, so music and dialogue are never
+//! held back.
+//!
+//! ```no_run
+//! # use maudio::engine::{start_policy::{Overflow, StartOutcome, StartPolicy}, Engine};
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! engine.set_start_policy(Some(StartPolicy::new(32, Overflow::Drop)));
+//!
+//! let mut debris = engine.new_sound_from_file(Path::new("debris.wav"))?;
+//! debris.set_priority(10);
+//! if debris.try_play_sound()? == StartOutcome::Dropped {
+//!     // Too many sounds playing
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Engine::set_start_policy`]: crate::engine::Engine::set_start_policy
+//! [`Engine::start_deferred_sounds`]: crate::engine::Engine::start_deferred_sounds
+//! [`Sound::try_play_sound`]: crate::sound::Sound::try_play_sound
+//! [`Sound::set_priority`]: crate::sound::Sound::set_priority
+
+/// What happens to a start held back by a [`StartPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The sound is queued, and started by
+    /// [`Engine::start_deferred_sounds`](crate::engine::Engine::start_deferred_sounds) once
+    /// fewer sounds are playing. Higher priorities are started first, then older starts.
+    Defer,
+    /// The sound is not started.
+    Drop,
+}
+
+/// Limits the number of sounds playing at once. See the [module docs](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartPolicy {
+    /// Number of playing sounds from which low priority starts are held back.
+    pub max_playing: usize,
+    /// Sounds with a priority at or above this are always started. Defaults to `u8::MAX`.
+    pub always_start_priority: u8,
+    pub overflow: Overflow,
+}
+
+impl StartPolicy {
+    pub fn new(max_playing: usize, overflow: Overflow) -> Self {
+        Self {
+            max_playing,
+            always_start_priority: u8::MAX,
+            overflow,
+        }
+    }
+
+    /// Sets the priority at and above which sounds are always started.
+    pub fn always_start_priority(mut self, priority: u8) -> Self {
+        self.always_start_priority = priority;
+        self
+    }
+}
+
+/// Result of [`Sound::try_play_sound`](crate::sound::Sound::try_play_sound).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartOutcome {
+    /// The sound is playing.
+    Started,
+    /// The sound is queued, see [`Overflow::Defer`].
+    Deferred,
+    /// The sound was not started, see [`Overflow::Drop`].
+    Dropped,
+}
@@ -0,0 +1,489 @@
+//! A small polyphonic synthesizer driven by note events.
+//!
+//! A [`Synth`] owns a fixed number of voices. Each voice is a node in the engine's graph
+//! that reads a [`WaveForm`] and shapes it with an ADSR [`Envelope`]. Notes are started and
+//! released with MIDI style events, either right away with [`Synth::note_on`] and
+//! [`Synth::note_off`], or at a time on the engine clock with [`Synth::note_on_at`],
+//! [`Synth::note_off_at`] and [`Synth::play_note_at`], so short melodies and procedural sound
+//! effects can be sequenced ahead of time.
+//!
+//! The events are applied on the audio thread, at the exact frame they are scheduled for.
+//! When every voice is busy, a new note takes over the voice that started first.
+//!
+//! ```no_run
+//! # use maudio::audio::{dsp::envelope::Adsr, wave_shape::WaveFormType};
+//! # use maudio::engine::{Engine, synth::SynthBuilder};
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut synth = SynthBuilder::new(&engine)
+//!     .voices(4)
+//!     .wave_type(WaveFormType::Square)
+//!     .adsr(Adsr::new(2.0, 80.0, 0.4, 150.0))
+//!     .build()?;
+//!
+//! // A C major arpeggio, one note every 100 ms
+//! let start = engine.time_pcm();
+//! for (i, note) in [60, 64, 67, 72].into_iter().enumerate() {
+//!     synth.play_note_at(start + i as u64 * 4_800, note, 0.8, 4_000)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{
+        dsp::envelope::{Adsr, Envelope},
+        sample_rate::SampleRate,
+        wave_shape::WaveFormType,
+    },
+    data_source::sources::waveform::{WaveForm, WaveFormBuilder, WaveFormOps},
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Source, SourceCallback},
+            nodes::{
+                routing::splitter::{SplitterNode, SplitterNodeBuilder},
+                Node, NodeOps, NodeRef,
+            },
+        },
+        Engine,
+    },
+    Binding, MaResult, MaudioError,
+};
+
+/// Returns the frequency in Hz of a MIDI note number, with note 69 (A4) at 440 Hz.
+pub fn midi_to_frequency(note: u8) -> f64 {
+    440.0 * 2f64.powf((note as f64 - 69.0) / 12.0)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum VoiceEvent {
+    NoteOn { frequency: f64, velocity: f32 },
+    NoteOff,
+}
+
+// Events waiting for the audio thread, in time order
+#[derive(Default)]
+struct VoiceShared {
+    events: Mutex<Vec<(u64, VoiceEvent)>>,
+}
+
+impl VoiceShared {
+    fn lock(&self) -> MutexGuard<'_, Vec<(u64, VoiceEvent)>> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, time: u64, event: VoiceEvent) {
+        let mut events = self.lock();
+        // After the events scheduled for the same frame
+        let index = events.partition_point(|(t, _)| *t <= time);
+        events.insert(index, (time, event));
+    }
+}
+
+// The engine owned by the `Synth`, which outlives its voices.
+#[derive(Clone, Copy)]
+struct EnginePtr(*mut sys::ma_engine);
+
+unsafe impl Send for EnginePtr {}
+
+struct VoiceProcessor {
+    wave: WaveForm<f32>,
+    envelope: Envelope,
+    velocity: f32,
+    shared: Arc<VoiceShared>,
+    engine: EnginePtr,
+    channels: usize,
+    // Engine time at the start of the current processing callback, and the frames
+    // rendered since, for callbacks that split one engine period
+    period_start: u64,
+    rendered: u64,
+    pending: Vec<(u64, VoiceEvent)>,
+}
+
+impl VoiceProcessor {
+    fn apply(&mut self, event: VoiceEvent) {
+        match event {
+            VoiceEvent::NoteOn {
+                frequency,
+                velocity,
+            } => {
+                // Restart the cycle only if the voice was silent, so retriggers do not click
+                if self.envelope.is_idle() {
+                    let _ = self.wave.seek_to_pcm_frame(0);
+                }
+                let _ = self.wave.set_frequency(frequency);
+                self.velocity = velocity;
+                self.envelope.note_on();
+            }
+            VoiceEvent::NoteOff => self.envelope.note_off(),
+        }
+    }
+
+    fn render(&mut self, output: &mut [f32]) -> MaResult<()> {
+        if self.envelope.is_idle() {
+            output.fill(0.0);
+            return Ok(());
+        }
+        self.wave.read_pcm_frames_into(output)?;
+        self.envelope.process_in_place(output)?;
+        if self.velocity != 1.0 {
+            output.iter_mut().for_each(|s| *s *= self.velocity);
+        }
+        Ok(())
+    }
+}
+
+impl SourceCallback for VoiceProcessor {
+    fn on_audio(&mut self, output: &mut [f32]) -> MaResult<u32> {
+        let frames = (output.len() / self.channels) as u64;
+        let now = unsafe { sys::ma_engine_get_time_in_pcm_frames(self.engine.0) };
+        if now != self.period_start {
+            self.period_start = now;
+            self.rendered = 0;
+        }
+        let start = self.period_start + self.rendered;
+        self.rendered += frames;
+
+        // Retried on the next callback if the control thread holds the lock
+        if let Ok(mut events) = self.shared.events.try_lock() {
+            let due = events.partition_point(|(t, _)| *t < start + frames);
+            self.pending.extend(events.drain(..due));
+        }
+
+        let mut done = 0u64;
+        let mut index = 0;
+        while index < self.pending.len() {
+            let (time, event) = self.pending[index];
+            let offset = time.saturating_sub(start).max(done);
+            if offset > done {
+                let range = done as usize * self.channels..offset as usize * self.channels;
+                self.render(&mut output[range])?;
+                done = offset;
+            }
+            self.apply(event);
+            index += 1;
+        }
+        self.pending.clear();
+        self.render(&mut output[done as usize * self.channels..])?;
+        Ok(frames as u32)
+    }
+}
+
+// What the control thread knows about a voice, to pick one for a new note
+#[derive(Debug, Clone, Copy, Default)]
+struct VoiceSlot {
+    note: Option<u8>,
+    start: u64,
+    // Time of the note off, `None` while the note is held
+    release: Option<u64>,
+}
+
+/// A polyphonic synthesizer. See the [module docs](self).
+///
+/// Dropping the synth removes its voices from the graph.
+pub struct Synth {
+    voices: Vec<Node<Source<VoiceProcessor>>>,
+    shared: Vec<Arc<VoiceShared>>,
+    slots: Vec<VoiceSlot>,
+    mix: SplitterNode,
+    release_frames: u64,
+    sample_rate: SampleRate,
+    engine: Engine,
+}
+
+impl Synth {
+    /// Starts `note` now. `velocity` scales the volume of the note, from `0.0` to `1.0`.
+    pub fn note_on(&mut self, note: u8, velocity: f32) -> MaResult<()> {
+        let now = self.engine.time_pcm();
+        self.note_on_at(now, note, velocity)
+    }
+
+    /// Releases `note` now.
+    pub fn note_off(&mut self, note: u8) {
+        let now = self.engine.time_pcm();
+        self.note_off_at(now, note);
+    }
+
+    /// Starts `note` when the engine clock reaches `time_pcm`.
+    ///
+    /// Times in the past start the note on the next processing callback.
+    pub fn note_on_at(&mut self, time_pcm: u64, note: u8, velocity: f32) -> MaResult<()> {
+        if !velocity.is_finite() || velocity < 0.0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let index = self.pick_voice(time_pcm);
+        self.slots[index] = VoiceSlot {
+            note: Some(note),
+            start: time_pcm,
+            release: None,
+        };
+        self.shared[index].push(
+            time_pcm,
+            VoiceEvent::NoteOn {
+                frequency: midi_to_frequency(note),
+                velocity: velocity.min(1.0),
+            },
+        );
+        Ok(())
+    }
+
+    /// Releases `note` when the engine clock reaches `time_pcm`.
+    ///
+    /// Releases the most recent held `note` started before `time_pcm`. Does nothing if
+    /// there is none.
+    pub fn note_off_at(&mut self, time_pcm: u64, note: u8) {
+        let held = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.note == Some(note) && s.release.is_none() && s.start <= time_pcm)
+            .max_by_key(|(_, s)| s.start)
+            .map(|(i, _)| i);
+        if let Some(index) = held {
+            self.slots[index].release = Some(time_pcm);
+            self.shared[index].push(time_pcm, VoiceEvent::NoteOff);
+        }
+    }
+
+    /// Plays `note` from `time_pcm` for `duration_frames`, then releases it.
+    pub fn play_note_at(
+        &mut self,
+        time_pcm: u64,
+        note: u8,
+        velocity: f32,
+        duration_frames: u64,
+    ) -> MaResult<()> {
+        self.note_on_at(time_pcm, note, velocity)?;
+        self.note_off_at(time_pcm + duration_frames, note);
+        Ok(())
+    }
+
+    /// Releases every held note now.
+    pub fn all_notes_off(&mut self) {
+        let now = self.engine.time_pcm();
+        for (slot, shared) in self.slots.iter_mut().zip(&self.shared) {
+            if slot.note.is_some() && slot.release.is_none() {
+                slot.release = Some(now.max(slot.start));
+                shared.push(now.max(slot.start), VoiceEvent::NoteOff);
+            }
+        }
+    }
+
+    /// Returns the number of voices, the maximum number of notes sounding at once.
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Returns the number of voices playing or releasing a note at `time_pcm`, based on the
+    /// events scheduled so far.
+    pub fn active_voices_at(&self, time_pcm: u64) -> usize {
+        self.slots
+            .iter()
+            .filter(|s| s.note.is_some() && !self.is_free(s, time_pcm) && s.start <= time_pcm)
+            .count()
+    }
+
+    /// Sets the volume of the synth.
+    pub fn set_volume(&mut self, volume: f32) -> MaResult<()> {
+        self.mix.as_node().set_output_bus_volume(0, volume)
+    }
+
+    pub fn volume(&self) -> MaResult<f32> {
+        self.mix.as_node().output_bus_volume(0)
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Returns the node the voices are mixed into, to route the synth through effects.
+    ///
+    /// It is attached to the engine's endpoint when the synth is built.
+    pub fn as_node<'a>(&'a self) -> NodeRef<'a> {
+        self.mix.as_node()
+    }
+
+    fn is_free(&self, slot: &VoiceSlot, time_pcm: u64) -> bool {
+        match (slot.note, slot.release) {
+            (None, _) => true,
+            (Some(_), Some(release)) => release + self.release_frames <= time_pcm,
+            (Some(_), None) => false,
+        }
+    }
+
+    // A free voice, or the one that started first
+    fn pick_voice(&self, time_pcm: u64) -> usize {
+        let free = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| self.is_free(s, time_pcm))
+            .min_by_key(|(_, s)| s.release.unwrap_or(0))
+            .map(|(i, _)| i);
+        free.unwrap_or_else(|| {
+            self.slots
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, s)| s.start)
+                .map(|(i, _)| i)
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// Builder for creating a [`Synth`]
+pub struct SynthBuilder<'a> {
+    engine: &'a Engine,
+    voices: usize,
+    wave_type: WaveFormType,
+    amplitude: f64,
+    adsr: Adsr,
+}
+
+impl<'a> SynthBuilder<'a> {
+    /// Creates a builder for an 8 voice sine synth with an amplitude of `0.25` and the
+    /// default [`Adsr`].
+    pub fn new(engine: &'a Engine) -> Self {
+        Self {
+            engine,
+            voices: 8,
+            wave_type: WaveFormType::Sine,
+            amplitude: 0.25,
+            adsr: Adsr::default(),
+        }
+    }
+
+    /// Sets the number of voices, the maximum number of notes sounding at once.
+    pub fn voices(&mut self, voices: usize) -> &mut Self {
+        self.voices = voices;
+        self
+    }
+
+    pub fn wave_type(&mut self, wave_type: WaveFormType) -> &mut Self {
+        self.wave_type = wave_type;
+        self
+    }
+
+    /// Sets the amplitude of each voice at full velocity.
+    pub fn amplitude(&mut self, amplitude: f64) -> &mut Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    pub fn adsr(&mut self, adsr: Adsr) -> &mut Self {
+        self.adsr = adsr;
+        self
+    }
+
+    pub fn build(&self) -> MaResult<Synth> {
+        if self.voices == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let channels = self.engine.channels();
+        let sample_rate = self.engine.sample_rate()?;
+        let node_graph = self.engine.as_node_graph();
+
+        let mut mix = SplitterNodeBuilder::new(&node_graph, channels).build()?;
+        mix.attach_output(0, &mut self.engine.endpoint(), 0)?;
+
+        let mut voices = Vec::with_capacity(self.voices);
+        let mut shared = Vec::with_capacity(self.voices);
+        for _ in 0..self.voices {
+            let wave =
+                WaveFormBuilder::new(channels, sample_rate, self.wave_type, self.amplitude, 440.0)
+                    .build_f32()?;
+            let voice_shared = Arc::new(VoiceShared::default());
+            let processor = VoiceProcessor {
+                wave,
+                envelope: Envelope::new(channels, sample_rate, self.adsr)?,
+                velocity: 1.0,
+                shared: voice_shared.clone(),
+                engine: EnginePtr(self.engine.to_raw()),
+                channels: channels as usize,
+                period_start: u64::MAX,
+                rendered: 0,
+                pending: Vec::new(),
+            };
+            let node = NodeBuilder::source()
+                .output_channel_count(channels)
+                .build(&node_graph, processor)?;
+            node.as_node().attach_output(0, &mut mix, 0)?;
+            voices.push(node);
+            shared.push(voice_shared);
+        }
+
+        Ok(Synth {
+            voices,
+            slots: vec![VoiceSlot::default(); shared.len()],
+            shared,
+            mix,
+            release_frames: self.adsr.release_frames(sample_rate),
+            sample_rate,
+            engine: Engine(self.engine.0.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::{dsp::envelope::Adsr, sample_rate::SampleRate},
+        engine::{
+            synth::{midi_to_frequency, SynthBuilder},
+            test_engine::{self, TestEngine},
+        },
+    };
+
+    #[test]
+    fn test_midi_to_frequency() {
+        assert!((midi_to_frequency(69) - 440.0).abs() < 1e-9);
+        assert!((midi_to_frequency(81) - 880.0).abs() < 1e-9);
+        assert!((midi_to_frequency(60) - 261.625_565).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_synth_scheduled_notes() {
+        let mut test = TestEngine::new(1, SampleRate::Sr48000).unwrap();
+        let mut synth = SynthBuilder::new(test.engine())
+            .voices(2)
+            .amplitude(0.5)
+            .adsr(Adsr::new(0.0, 0.0, 1.0, 0.0))
+            .build()
+            .unwrap();
+        synth.play_note_at(1024, 69, 1.0, 1024).unwrap();
+
+        let out = test.advance(4096).unwrap();
+        let out = out.as_ref();
+        test_engine::assert_silent(&out[..1024]);
+        test_engine::assert_rms_in_range(&out[1024..2048], 0.3..=0.4);
+        test_engine::assert_silent(&out[2048..]);
+    }
+
+    #[test]
+    fn test_synth_voice_stealing() {
+        let test = TestEngine::new(1, SampleRate::Sr48000).unwrap();
+        let mut synth = SynthBuilder::new(test.engine()).voices(2).build().unwrap();
+        assert_eq!(synth.voice_count(), 2);
+
+        synth.note_on_at(0, 60, 1.0).unwrap();
+        synth.note_on_at(10, 64, 1.0).unwrap();
+        synth.note_on_at(20, 67, 1.0).unwrap();
+        assert_eq!(synth.active_voices_at(30), 2);
+        // The first note was stolen, so releasing it does nothing
+        synth.note_off_at(30, 60);
+        assert_eq!(
+            synth.slots.iter().filter(|s| s.release.is_some()).count(),
+            0
+        );
+
+        synth.note_off_at(30, 64);
+        let release = synth.release_frames;
+        assert_eq!(synth.active_voices_at(30 + release), 1);
+        assert!(synth.note_on_at(0, 60, -1.0).is_err());
+        assert!(SynthBuilder::new(test.engine()).voices(0).build().is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Deterministic engine for unit testing audio logic.
+//!
+//! [`TestEngine`] wraps an [`Engine`] without a device. Nothing is rendered until
+//! [`TestEngine::advance`] is called, which renders an exact number of frames and
+//! returns them. The engine clock only moves when the engine is advanced, so scheduled
+//! start and stop times, fades and other time based behavior are reproducible.
+//!
+//! The functions in this module inspect the captured output and panic with a
+//! descriptive message when an expectation is not met.
+//!
+//! ```
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use maudio::data_source::sources::buffer::AudioBufferBuilder;
+//! # use maudio::engine::test_engine::{self, TestEngine};
+//! # fn main() -> maudio::MaResult<()> {
+//! let mut test = TestEngine::new(1, SampleRate::Sr48000)?;
+//!
+//! let data = vec![0.5f32; 4800];
+//! let buffer = AudioBufferBuilder::build_f32(1, &data)?;
+//! let mut sound = test.engine().new_sound_from_source(&buffer)?;
+//! sound.set_start_time_pcm(1024);
+//! sound.play_sound()?;
+//!
+//! let out = test.advance(2048)?;
+//! test_engine::assert_silent(&out.as_ref()[..1024]);
+//! test_engine::assert_rms_in_range(&out.as_ref()[1100..], 0.4..=0.6);
+//! # Ok(())
+//! # }
+//! ```
+use std::ops::RangeInclusive;
+
+use crate::{
+    audio::{formats::SampleBuffer, sample_rate::SampleRate},
+    engine::{engine_builder::EngineBuilder, Engine},
+    MaResult,
+};
+
+/// An engine without a device that only renders when advanced.
+///
+/// Use [`TestEngine::engine`] to create sounds and nodes as usual.
+pub struct TestEngine {
+    engine: Engine,
+}
+
+impl TestEngine {
+    /// Creates an engine without a device, with the given output format.
+    pub fn new(channels: u32, sample_rate: SampleRate) -> MaResult<Self> {
+        let engine = EngineBuilder::new()
+            .no_device(channels, sample_rate)
+            .build()?;
+        Ok(Self { engine })
+    }
+
+    /// Returns the underlying engine.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Current engine time, in PCM frames.
+    pub fn time_pcm(&self) -> u64 {
+        self.engine.time_pcm()
+    }
+
+    /// Renders exactly `frames` frames and returns them.
+    ///
+    /// The engine clock is advanced by `frames`, even if nothing is playing.
+    /// See [`Engine::render_offline`] for how scheduled times are applied.
+    pub fn advance(&mut self, frames: u64) -> MaResult<SampleBuffer<f32>> {
+        let channels = self.engine.channels();
+        let mut data = Vec::with_capacity(frames as usize * channels as usize);
+        self.engine
+            .render_offline(frames, |block| data.extend_from_slice(block))?;
+        SampleBuffer::from_storage(data, frames as usize, channels)
+    }
+
+    /// Renders `frames` frames and discards them.
+    pub fn skip(&mut self, frames: u64) -> MaResult<()> {
+        self.engine.render_offline(frames, |_| {})
+    }
+}
+
+/// Root mean square of the samples, across all channels.
+///
+/// Returns `0.0` for an empty slice.
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|s| (*s as f64) * (*s as f64)).sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+/// Index of the first frame where any channel is not zero.
+pub fn first_non_zero_frame(samples: &[f32], channels: u32) -> Option<usize> {
+    let channels = channels.max(1) as usize;
+    samples
+        .iter()
+        .position(|s| *s != 0.0)
+        .map(|index| index / channels)
+}
+
+/// Panics if any sample is not zero.
+#[track_caller]
+pub fn assert_silent(samples: &[f32]) {
+    if let Some(index) = samples.iter().position(|s| *s != 0.0) {
+        panic!(
+            "expected silence, found {} at sample {index}",
+            samples[index]
+        );
+    }
+}
+
+/// Panics if the RMS of the samples is outside of `range`.
+#[track_caller]
+pub fn assert_rms_in_range(samples: &[f32], range: RangeInclusive<f32>) {
+    let value = rms(samples);
+    if !range.contains(&value) {
+        panic!("expected RMS in {range:?}, found {value}");
+    }
+}
+
+/// Panics if the first frame that is not silent is not `expected`.
+///
+/// Use `None` to expect a buffer that is completely silent.
+#[track_caller]
+pub fn assert_first_non_zero_frame(buffer: &SampleBuffer<f32>, expected: Option<usize>) {
+    let found = first_non_zero_frame(buffer.as_ref(), buffer.channels());
+    if found != expected {
+        panic!("expected first non-zero frame {expected:?}, found {found:?}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_source::sources::buffer::AudioBufferBuilder;
+
+    #[test]
+    fn test_test_engine_advance_is_exact() {
+        let mut test = TestEngine::new(2, SampleRate::Sr48000).unwrap();
+
+        let out = test.advance(300).unwrap();
+        assert_eq!(out.frames(), 300);
+        assert_eq!(out.channels(), 2);
+        assert_eq!(out.as_ref().len(), 600);
+        assert_eq!(test.time_pcm(), 300);
+        assert_silent(out.as_ref());
+        assert_first_non_zero_frame(&out, None);
+
+        test.skip(200).unwrap();
+        assert_eq!(test.time_pcm(), 500);
+    }
+
+    #[test]
+    fn test_test_engine_scheduled_sound() {
+        let mut test = TestEngine::new(1, SampleRate::Sr48000).unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 2000]).unwrap();
+        let mut sound = test.engine().new_sound_from_source(&buffer).unwrap();
+        sound.set_start_time_pcm(1024);
+        sound.play_sound().unwrap();
+
+        let out = test.advance(2048).unwrap();
+        let first = first_non_zero_frame(out.as_ref(), 1).unwrap();
+        assert!((1024..1030).contains(&first));
+        assert_silent(&out.as_ref()[..1024]);
+        assert_rms_in_range(&out.as_ref()[1100..], 0.45..=0.55);
+    }
+
+    #[test]
+    fn test_test_engine_helpers() {
+        assert_eq!(rms(&[]), 0.0);
+        assert!((rms(&[1.0, -1.0, 1.0, -1.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(first_non_zero_frame(&[0.0, 0.0, 0.0, 0.2], 2), Some(1));
+        assert_eq!(first_non_zero_frame(&[0.0; 4], 2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected silence")]
+    fn test_test_engine_assert_silent_panics() {
+        assert_silent(&[0.0, 0.1]);
+    }
+}
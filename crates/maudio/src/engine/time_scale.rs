@@ -0,0 +1,194 @@
+//! Engine-wide time scale, behind [`Engine::set_time_scale`].
+//!
+//! Slowing down the game for a slow-motion moment should slow down its audio the same way.
+//! [`Engine::set_time_scale`] multiplies the pitch of every sound by the scale, so they play
+//! slower and lower, and slows the engine clock down by the same factor, so sounds scheduled
+//! with [`Sound::set_start_time_pcm`](crate::sound::Sound::set_start_time_pcm) or
+//! [`Sound::set_stop_time_pcm`](crate::sound::Sound::set_stop_time_pcm) keep in step with
+//! the game.
+//!
+//! [`Sound::pitch`](crate::sound::Sound::pitch) and
+//! [`Sound::set_pitch`](crate::sound::Sound::set_pitch) keep working with the pitch of the
+//! sound before scaling. Sounds that should not be affected, such as UI sounds or music, can
+//! opt out with [`Sound::set_time_scale_exempt`](crate::sound::Sound::set_time_scale_exempt).
+//!
+//! The clock is slowed down on the audio thread, by a silent node that the engine attaches
+//! to its endpoint the first time the scale is changed.
+//!
+//! ```no_run
+//! # use maudio::engine::Engine;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut music = engine.new_sound_from_file(Path::new("music.ogg"))?;
+//! music.set_time_scale_exempt(true);
+//!
+//! // Bullet time
+//! engine.set_time_scale(0.5)?;
+//! // ...
+//! engine.set_time_scale(1.0)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Engine::set_time_scale`]: crate::engine::Engine::set_time_scale
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Source, SourceCallback},
+            nodes::{Node, NodeOps},
+        },
+        Engine,
+    },
+    Binding, MaResult,
+};
+
+// The `ma_engine` of the engine owning the driver, which drops it before it is freed.
+struct EnginePtr(*mut sys::ma_engine);
+
+unsafe impl Send for EnginePtr {}
+
+// Silent node attached to the endpoint, holding the engine clock back once per callback.
+struct ClockDriver {
+    engine: EnginePtr,
+    scale: Arc<AtomicU32>,
+    channels: usize,
+    // Frames the clock should be held back by and has not been yet
+    pending: f64,
+}
+
+impl SourceCallback for ClockDriver {
+    fn on_audio(&mut self, output: &mut [f32]) -> MaResult<u32> {
+        output.fill(0.0);
+        let frames = output.len() / self.channels;
+        let scale = f32::from_bits(self.scale.load(Ordering::Relaxed)) as f64;
+        // The endpoint adds the frames it read to the clock once it is done, after this block
+        self.pending += frames as f64 * (1.0 - scale);
+        let whole = self.pending.trunc();
+        if whole != 0.0 {
+            unsafe {
+                let time = sys::ma_engine_get_time_in_pcm_frames(self.engine.0);
+                // The clock cannot go below zero, the rest is applied once it has moved on
+                let applied = (whole as i64).min(time as i64);
+                sys::ma_engine_set_time_in_pcm_frames(
+                    self.engine.0,
+                    (time as i64 - applied) as u64,
+                );
+                self.pending -= applied as f64;
+            }
+        }
+        Ok(frames as u32)
+    }
+}
+
+/// The scale applied to the engine clock.
+pub(crate) struct ScaledClock {
+    scale: Arc<AtomicU32>,
+    driver: Mutex<Option<Node<Source<ClockDriver>>>>,
+}
+
+impl Default for ScaledClock {
+    fn default() -> Self {
+        Self {
+            scale: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            driver: Mutex::new(None),
+        }
+    }
+}
+
+impl ScaledClock {
+    pub(crate) fn set_scale(&self, engine: &Engine, scale: f32) -> MaResult<()> {
+        let mut driver = self.driver.lock().unwrap_or_else(|e| e.into_inner());
+        if driver.is_none() && scale != 1.0 {
+            let channels = engine.channels();
+            let processor = ClockDriver {
+                engine: EnginePtr(engine.to_raw()),
+                scale: self.scale.clone(),
+                channels: channels as usize,
+                pending: 0.0,
+            };
+            let node = NodeBuilder::source()
+                .output_channel_count(channels)
+                .build(&engine.as_node_graph(), processor)?;
+            node.as_node().attach_output(0, &mut engine.endpoint(), 0)?;
+            *driver = Some(node);
+        }
+        self.scale.store(scale.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Removes the driver node. Called before the engine is freed.
+    pub(crate) fn clear(&self) {
+        drop(self.driver.lock().unwrap_or_else(|e| e.into_inner()).take());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use maudio_sys::ffi as sys;
+
+    use crate::{
+        audio::sample_rate::SampleRate, data_source::sources::buffer::AudioBufferBuilder,
+        engine::engine_builder::EngineBuilder, Binding,
+    };
+
+    #[test]
+    fn test_time_scale_scales_pitch_and_clock() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.25f32; 4096]).unwrap();
+        let mut effect = engine.new_sound_from_source(&buffer).unwrap();
+        let mut music = engine.new_sound_from_source(&buffer).unwrap();
+        effect.set_pitch(1.5);
+        music.set_time_scale_exempt(true);
+
+        engine.set_time_scale(0.5).unwrap();
+        assert_eq!(engine.time_scale(), 0.5);
+        assert_eq!(effect.pitch(), 1.5);
+        assert_eq!(unsafe { sys::ma_sound_get_pitch(effect.to_raw()) }, 0.75);
+        assert_eq!(unsafe { sys::ma_sound_get_pitch(music.to_raw()) }, 1.0);
+
+        // Sounds created while scaled are scaled too
+        let late = engine.new_sound_from_source(&buffer).unwrap();
+        assert_eq!(late.pitch(), 1.0);
+        assert_eq!(unsafe { sys::ma_sound_get_pitch(late.to_raw()) }, 0.5);
+
+        music.set_time_scale_exempt(false);
+        assert_eq!(music.pitch(), 1.0);
+        assert_eq!(unsafe { sys::ma_sound_get_pitch(music.to_raw()) }, 0.5);
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        for _ in 0..4 {
+            reader.read_pcm_frames(1024).unwrap();
+        }
+        assert_eq!(engine.time_pcm(), 2048);
+
+        engine.set_time_scale(1.0).unwrap();
+        assert_eq!(unsafe { sys::ma_sound_get_pitch(effect.to_raw()) }, 1.5);
+        let start = engine.time_pcm();
+        reader.read_pcm_frames(1024).unwrap();
+        assert_eq!(engine.time_pcm() - start, 1024);
+    }
+
+    #[test]
+    fn test_time_scale_invalid_args() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        assert!(engine.set_time_scale(0.0).is_err());
+        assert!(engine.set_time_scale(-1.0).is_err());
+        assert!(engine.set_time_scale(f32::NAN).is_err());
+        assert_eq!(engine.time_scale(), 1.0);
+    }
+}
@@ -0,0 +1,362 @@
+//! Callbacks at positions on the engine timeline, behind [`Engine::schedule_callback_at_pcm`].
+//!
+//! Syncing visuals to audio by polling [`Engine::time_pcm`] is only as precise as the
+//! polling. Instead, a callback can be scheduled for an exact frame of the engine clock. The
+//! audio thread marks it as fired during the processing callback that reaches that frame,
+//! and hands it to the [`TimelineEvents`] receiver along with the frame. The callback itself
+//! runs on the thread that drains the receiver, usually once per game frame, so it can
+//! touch game state and never runs on the audio thread.
+//!
+//! The frames are checked on the audio thread, by a silent node that the engine attaches to
+//! its endpoint the first time a callback is scheduled.
+//!
+//! ```no_run
+//! # use maudio::engine::Engine;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let events = engine.timeline_events();
+//!
+//! // One second from now
+//! let beat = engine.time_pcm() + 48_000;
+//! engine.schedule_callback_at_pcm(beat, |frame| println!("beat at {frame}"))?;
+//!
+//! // In the game loop
+//! events.run_pending();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Engine::schedule_callback_at_pcm`]: crate::engine::Engine::schedule_callback_at_pcm
+//! [`Engine::time_pcm`]: crate::engine::Engine::time_pcm
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::{
+        node_graph::{
+            node_builder::NodeBuilder,
+            node_on_process::{Source, SourceCallback},
+            nodes::{Node, NodeOps},
+        },
+        Engine,
+    },
+    util::rt_check,
+    Binding, MaResult,
+};
+
+type TimelineCallback = Box<dyn FnOnce(u64) + Send>;
+
+/// Identifies a callback scheduled with
+/// [`Engine::schedule_callback_at_pcm`](crate::engine::Engine::schedule_callback_at_pcm).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+/// A callback whose frame the engine has reached.
+pub struct FiredCallback {
+    id: CallbackId,
+    frame: u64,
+    callback: TimelineCallback,
+}
+
+impl FiredCallback {
+    pub fn id(&self) -> CallbackId {
+        self.id
+    }
+
+    /// Returns the engine frame the callback fired at.
+    ///
+    /// This is the scheduled frame, or the first frame of the processing callback that
+    /// picked it up if it was scheduled in the past.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Runs the callback with the frame it fired at.
+    pub fn run(self) {
+        (self.callback)(self.frame)
+    }
+}
+
+impl std::fmt::Debug for FiredCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FiredCallback")
+            .field("id", &self.id)
+            .field("frame", &self.frame)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Scheduled {
+    id: CallbackId,
+    frame: u64,
+    callback: TimelineCallback,
+}
+
+#[derive(Default)]
+struct TimelineState {
+    // Sorted by frame, then by scheduling order
+    scheduled: VecDeque<Scheduled>,
+    fired: VecDeque<FiredCallback>,
+}
+
+// State shared between the control thread and the audio thread.
+// The audio thread never waits for the lock.
+#[derive(Default)]
+struct TimelineShared {
+    state: Mutex<TimelineState>,
+}
+
+impl TimelineShared {
+    fn lock(&self) -> MutexGuard<'_, TimelineState> {
+        rt_check::check("engine timeline");
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Fires the callbacks due before `end`
+    fn advance(&self, start: u64, end: u64) {
+        // Retried on the next callback if the control thread holds the lock
+        let Ok(mut state) = self.state.try_lock() else {
+            return;
+        };
+        while matches!(state.scheduled.front(), Some(s) if s.frame < end) {
+            if let Some(due) = state.scheduled.pop_front() {
+                state.fired.push_back(FiredCallback {
+                    id: due.id,
+                    frame: due.frame.max(start),
+                    callback: due.callback,
+                });
+            }
+        }
+    }
+}
+
+// The `ma_engine` of the engine owning the driver, which drops it before it is freed.
+struct EnginePtr(*mut sys::ma_engine);
+
+unsafe impl Send for EnginePtr {}
+
+// Silent node attached to the endpoint, so it runs once per processing callback.
+struct TimelineDriver {
+    shared: Arc<TimelineShared>,
+    engine: EnginePtr,
+    channels: usize,
+    // Engine time at the start of the current processing callback, and the frames
+    // rendered since, for callbacks that split one engine period
+    period_start: u64,
+    rendered: u64,
+}
+
+impl SourceCallback for TimelineDriver {
+    fn on_audio(&mut self, output: &mut [f32]) -> MaResult<u32> {
+        output.fill(0.0);
+        let frames = (output.len() / self.channels) as u64;
+        // The endpoint only adds the frames of this block to the clock once it is done
+        let now = unsafe { sys::ma_engine_get_time_in_pcm_frames(self.engine.0) };
+        if now != self.period_start {
+            self.period_start = now;
+            self.rendered = 0;
+        }
+        let start = self.period_start + self.rendered;
+        self.rendered += frames;
+        self.shared.advance(start, start + frames);
+        Ok(frames as u32)
+    }
+}
+
+/// Receives the callbacks scheduled with
+/// [`Engine::schedule_callback_at_pcm`](crate::engine::Engine::schedule_callback_at_pcm)
+/// once they fire. See the [module docs](self).
+///
+/// Every receiver of an engine drains the same queue. Cloning a `TimelineEvents` creates
+/// another handle to it.
+#[derive(Clone)]
+pub struct TimelineEvents {
+    shared: Arc<TimelineShared>,
+}
+
+impl TimelineEvents {
+    /// Takes the oldest fired callback, without running it.
+    pub fn try_recv(&self) -> Option<FiredCallback> {
+        self.shared.lock().fired.pop_front()
+    }
+
+    /// Takes every fired callback, oldest first, without running them.
+    pub fn drain(&self) -> Vec<FiredCallback> {
+        self.shared.lock().fired.drain(..).collect()
+    }
+
+    /// Runs every fired callback, oldest first, and returns how many ran.
+    ///
+    /// The callbacks run after the queue is unlocked, so they can schedule new callbacks.
+    pub fn run_pending(&self) -> usize {
+        let fired = self.drain();
+        let count = fired.len();
+        fired.into_iter().for_each(FiredCallback::run);
+        count
+    }
+
+    /// Returns the number of fired callbacks waiting to be received.
+    pub fn len(&self) -> usize {
+        self.shared.lock().fired.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The scheduled callbacks of an engine.
+#[derive(Default)]
+pub(crate) struct Timeline {
+    shared: Arc<TimelineShared>,
+    next_id: AtomicU64,
+    driver: Mutex<Option<Node<Source<TimelineDriver>>>>,
+}
+
+impl Timeline {
+    pub(crate) fn schedule(
+        &self,
+        engine: &Engine,
+        frame: u64,
+        callback: TimelineCallback,
+    ) -> MaResult<CallbackId> {
+        self.start_driver(engine)?;
+        let id = CallbackId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut state = self.shared.lock();
+        let index = state.scheduled.partition_point(|s| s.frame <= frame);
+        state.scheduled.insert(
+            index,
+            Scheduled {
+                id,
+                frame,
+                callback,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Removes a callback that has not fired yet. Returns `false` if it already fired.
+    pub(crate) fn cancel(&self, id: CallbackId) -> bool {
+        let mut state = self.shared.lock();
+        match state.scheduled.iter().position(|s| s.id == id) {
+            Some(index) => {
+                state.scheduled.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn scheduled_count(&self) -> usize {
+        self.shared.lock().scheduled.len()
+    }
+
+    pub(crate) fn events(&self) -> TimelineEvents {
+        TimelineEvents {
+            shared: self.shared.clone(),
+        }
+    }
+
+    fn start_driver(&self, engine: &Engine) -> MaResult<()> {
+        let mut driver = self.driver.lock().unwrap_or_else(|e| e.into_inner());
+        if driver.is_some() {
+            return Ok(());
+        }
+        let channels = engine.channels();
+        let processor = TimelineDriver {
+            shared: self.shared.clone(),
+            engine: EnginePtr(engine.to_raw()),
+            channels: channels as usize,
+            period_start: u64::MAX,
+            rendered: 0,
+        };
+        let node = NodeBuilder::source()
+            .output_channel_count(channels)
+            .build(&engine.as_node_graph(), processor)?;
+        node.as_node().attach_output(0, &mut engine.endpoint(), 0)?;
+        *driver = Some(node);
+        Ok(())
+    }
+
+    /// Removes the driver node and the callbacks that did not fire. Called before the engine
+    /// is freed.
+    pub(crate) fn clear(&self) {
+        drop(self.driver.lock().unwrap_or_else(|e| e.into_inner()).take());
+        self.shared.lock().scheduled.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{engine_builder::EngineBuilder, Engine},
+    };
+
+    fn engine() -> Engine {
+        EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_timeline_fires_at_exact_frame() {
+        let engine = engine();
+        let events = engine.timeline_events();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        for frame in [1500u64, 100, 700] {
+            let seen = seen.clone();
+            engine
+                .schedule_callback_at_pcm(frame, move |f| seen.lock().unwrap().push(f))
+                .unwrap();
+        }
+        assert_eq!(engine.scheduled_callback_count(), 3);
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(1024).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events.run_pending(), 2);
+        assert_eq!(*seen.lock().unwrap(), vec![100, 700]);
+
+        reader.read_pcm_frames(1024).unwrap();
+        let fired = events.try_recv().unwrap();
+        assert_eq!(fired.frame(), 1500);
+        fired.run();
+        assert!(events.is_empty());
+        assert_eq!(*seen.lock().unwrap(), vec![100, 700, 1500]);
+        assert_eq!(engine.scheduled_callback_count(), 0);
+    }
+
+    #[test]
+    fn test_timeline_cancel_and_past_frames() {
+        let engine = engine();
+        let events = engine.timeline_events();
+        let cancelled = engine.schedule_callback_at_pcm(600, |_| {}).unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(512).unwrap();
+        assert_eq!(engine.time_pcm(), 512);
+
+        let past = engine.schedule_callback_at_pcm(10, |_| {}).unwrap();
+        assert!(engine.cancel_scheduled_callback(cancelled));
+        assert!(!engine.cancel_scheduled_callback(cancelled));
+
+        reader.read_pcm_frames(512).unwrap();
+        let fired = events.drain();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].id(), past);
+        // Fired at the start of the next processing callback
+        assert_eq!(fired[0].frame(), 512);
+        assert!(!engine.cancel_scheduled_callback(past));
+    }
+}
@@ -84,6 +84,18 @@
 //!
 //! - Vorbis `.ogg` files can be decoded via miniaudio's decoding APIs.
 //!
+//! ## `memmap2`
+//! Adds [`util::mapped_file::MappedFile`], to decode files and register them with the resource
+//! manager through a memory mapping instead of reading them into memory first.
+//!
+//! ## `bevy`
+//! Adds the [`bevy`] module, with a plugin, components and systems that make `maudio` the
+//! audio backend of a [bevy](https://bevyengine.org) app.
+//!
+//! ## `capi`
+//! Adds the [`capi`] module, an `extern "C"` facade to create engines and play sounds from
+//! C, C++ or C# hosts.
+//!
 //! ## `generate-bindings`
 //! Generates bindings at build time using `bindgen`.
 //!
@@ -94,23 +106,36 @@
 
 pub mod audio;
 pub mod backend;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod context;
 pub mod data_source;
 pub mod device;
 pub mod encoder;
 pub mod engine;
 pub mod pcm_frames;
+mod result_code;
 pub mod sound;
 pub(crate) mod test_assets;
 pub mod util;
+#[cfg(target_os = "emscripten")]
+pub mod web;
 
 #[doc(hidden)]
 pub extern crate maudio_sys;
 
-use std::num::TryFromIntError;
+use std::{
+    num::TryFromIntError,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use maudio_sys::ffi as sys;
 
+pub use result_code::MaResultCode;
+
 /// IMPORTANT: type Raw must be a *mut pointer
 pub(crate) trait Binding: Sized {
     type Raw;
@@ -141,17 +166,13 @@ impl MaudioError {
         if res == sys::ma_result_MA_SUCCESS {
             Ok(())
         } else {
-            Err(MaudioError {
-                native: None,
-                ma_result: MaError(res as sys::ma_result),
-            })
+            Err(MaudioError::from_ma_result(res as sys::ma_result))
         }
     }
 
     /// Returns true if error is miniaudio error MA_RESULT_MA_BUSY
     pub fn is_busy(&self) -> bool {
-        let a = self.ma_result;
-        a.name() == "MA_BUSY"
+        self.code() == MaResultCode::Busy
     }
 
     /// Returns the wrapper-level error is present.
@@ -169,10 +190,34 @@ impl MaudioError {
         self.ma_result.0
     }
 
+    /// Returns the underlying miniaudio result code as a [`MaResultCode`].
+    ///
+    /// Wrapper-level errors (see [`MaudioError::kind`]) report [`MaResultCode::Error`].
+    pub fn code(&self) -> MaResultCode {
+        MaResultCode::from_raw(self.ma_result.0)
+    }
+
+    /// Returns what the error relates to, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        self.context.as_deref()
+    }
+
+    /// Attaches `context` to the error.
+    ///
+    /// If the error already has a context, the existing (more specific) one is kept.
+    pub fn with_context(mut self, context: ErrorContext) -> Self {
+        if self.context.is_none() {
+            self.context = Some(Box::new(context));
+        }
+        self
+    }
+
     fn from_ma_result(error: sys::ma_result) -> Self {
         Self {
             native: None,
             ma_result: MaError(error),
+            context: None,
+            source: None,
         }
     }
 
@@ -183,10 +228,33 @@ impl MaudioError {
         Self {
             native: Some(native),
             ma_result: MaError(sys::ma_result_MA_ERROR),
+            context: None,
+            source: None,
         }
     }
 }
 
+/// Attaches an [`ErrorContext`] to the error of a [`MaResult`].
+pub(crate) trait ResultContext<T> {
+    fn with_path(self, path: &Path) -> MaResult<T>;
+    fn with_resource(self, name: &str) -> MaResult<T>;
+    fn with_node(self, id: usize) -> MaResult<T>;
+}
+
+impl<T> ResultContext<T> for MaResult<T> {
+    fn with_path(self, path: &Path) -> MaResult<T> {
+        self.map_err(|e| e.with_context(ErrorContext::Path(path.to_path_buf())))
+    }
+
+    fn with_resource(self, name: &str) -> MaResult<T> {
+        self.map_err(|e| e.with_context(ErrorContext::Resource(name.to_owned())))
+    }
+
+    fn with_node(self, id: usize) -> MaResult<T> {
+        self.map_err(|e| e.with_context(ErrorContext::Node(id)))
+    }
+}
+
 impl PartialEq<MaError> for MaudioError {
     fn eq(&self, other: &MaError) -> bool {
         self.ma_result.0.eq(&other.0)
@@ -197,14 +265,27 @@ impl std::fmt::Display for MaudioError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.native {
             None => {
-                write!(f, "{}", self.ma_result)
+                write!(f, "{}", self.ma_result)?;
             }
             Some(kind) => {
                 write!(f, "{kind}.")?;
                 write!(f, " MA: ({})", self.ma_result)?;
-                Ok(())
             }
         }
+        if let Some(context) = &self.context {
+            write!(f, " [{context}]")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorContext::Path(path) => write!(f, "file: {}", path.display()),
+            ErrorContext::Resource(name) => write!(f, "resource: {name}"),
+            ErrorContext::Node(id) => write!(f, "node: {id:#x}"),
+        }
     }
 }
 
@@ -262,6 +343,14 @@ impl std::fmt::Display for ErrorKinds {
                 )
             }
             ErrorKinds::InvalidGraphState => write!(f, "invalid graph state"),
+            ErrorKinds::BusIndexOutOfRange {
+                context,
+                bus,
+                bus_count,
+            } => {
+                write!(f, "{context} {bus} is out of range (bus count {bus_count})")
+            }
+            ErrorKinds::NodeGraphCycle => write!(f, "attaching the node would create a cycle"),
             ErrorKinds::InvalidFormat => write!(f, "invalid format"),
             ErrorKinds::InvalidCString => write!(f, "invalid C string"),
             ErrorKinds::IoError { err } => write!(f, "IO error: {err}"),
@@ -270,6 +359,10 @@ impl std::fmt::Display for ErrorKinds {
             ErrorKinds::Other(error) => write!(f, "{error}",),
             ErrorKinds::NotImplemented => write!(f, "Not implemented"),
             ErrorKinds::ReaderExists => write!(f, "Reader already exists"),
+            ErrorKinds::CallbackPanicked => write!(f, "callback panicked"),
+            ErrorKinds::ResourceAlreadyRegistered { name } => {
+                write!(f, "a resource is already registered as {name:?}")
+            }
         }
     }
 }
@@ -288,83 +381,7 @@ impl std::fmt::Debug for MaError {
 
 impl MaError {
     pub fn name(self) -> &'static str {
-        match self.0 {
-            sys::ma_result_MA_ERROR => "MA_ERROR",
-            sys::ma_result_MA_INVALID_ARGS => "MA_INVALID_ARGS",
-            sys::ma_result_MA_INVALID_OPERATION => "MA_INVALID_OPERATION",
-            sys::ma_result_MA_OUT_OF_MEMORY => "MA_OUT_OF_MEMORY",
-            sys::ma_result_MA_OUT_OF_RANGE => "MA_OUT_OF_RANGE",
-            sys::ma_result_MA_ACCESS_DENIED => "MA_ACCESS_DENIED",
-            sys::ma_result_MA_DOES_NOT_EXIST => "MA_DOES_NOT_EXIST",
-            sys::ma_result_MA_ALREADY_EXISTS => "MA_ALREADY_EXISTS",
-            sys::ma_result_MA_TOO_MANY_OPEN_FILES => "MA_TOO_MANY_OPEN_FILES",
-            sys::ma_result_MA_INVALID_FILE => "MA_INVALID_FILE",
-            sys::ma_result_MA_TOO_BIG => "MA_TOO_BIG",
-            sys::ma_result_MA_PATH_TOO_LONG => "MA_PATH_TOO_LONG",
-            sys::ma_result_MA_NAME_TOO_LONG => "MA_NAME_TOO_LONG",
-            sys::ma_result_MA_NOT_DIRECTORY => "MA_NOT_DIRECTORY",
-            sys::ma_result_MA_IS_DIRECTORY => "MA_IS_DIRECTORY",
-            sys::ma_result_MA_DIRECTORY_NOT_EMPTY => "MA_DIRECTORY_NOT_EMPTY",
-            sys::ma_result_MA_AT_END => "MA_AT_END",
-            sys::ma_result_MA_NO_SPACE => "MA_NO_SPACE",
-            sys::ma_result_MA_BUSY => "MA_BUSY",
-            sys::ma_result_MA_IO_ERROR => "MA_IO_ERROR",
-            sys::ma_result_MA_INTERRUPT => "MA_INTERRUPT",
-            sys::ma_result_MA_UNAVAILABLE => "MA_UNAVAILABLE",
-            sys::ma_result_MA_ALREADY_IN_USE => "MA_ALREADY_IN_USE",
-            sys::ma_result_MA_BAD_ADDRESS => "MA_BAD_ADDRESS",
-            sys::ma_result_MA_BAD_SEEK => "MA_BAD_SEEK",
-            sys::ma_result_MA_BAD_PIPE => "MA_BAD_PIPE",
-            sys::ma_result_MA_DEADLOCK => "MA_DEADLOCK",
-            sys::ma_result_MA_TOO_MANY_LINKS => "MA_TOO_MANY_LINKS",
-            sys::ma_result_MA_NOT_IMPLEMENTED => "MA_NOT_IMPLEMENTED",
-            sys::ma_result_MA_NO_MESSAGE => "MA_NO_MESSAGE",
-            sys::ma_result_MA_BAD_MESSAGE => "MA_BAD_MESSAGE",
-            sys::ma_result_MA_NO_DATA_AVAILABLE => "MA_NO_DATA_AVAILABLE",
-            sys::ma_result_MA_INVALID_DATA => "MA_INVALID_DATA",
-            sys::ma_result_MA_TIMEOUT => "MA_TIMEOUT",
-            sys::ma_result_MA_NO_NETWORK => "MA_NO_NETWORK",
-            sys::ma_result_MA_NOT_UNIQUE => "MA_NOT_UNIQUE",
-            sys::ma_result_MA_NOT_SOCKET => "MA_NOT_SOCKET",
-            sys::ma_result_MA_NO_ADDRESS => "MA_NO_ADDRESS",
-            sys::ma_result_MA_BAD_PROTOCOL => "MA_BAD_PROTOCOL",
-            sys::ma_result_MA_PROTOCOL_UNAVAILABLE => "MA_PROTOCOL_UNAVAILABLE",
-            sys::ma_result_MA_PROTOCOL_NOT_SUPPORTED => "MA_PROTOCOL_NOT_SUPPORTED",
-            sys::ma_result_MA_PROTOCOL_FAMILY_NOT_SUPPORTED => "MA_PROTOCOL_FAMILY_NOT_SUPPORTED",
-            sys::ma_result_MA_ADDRESS_FAMILY_NOT_SUPPORTED => "MA_ADDRESS_FAMILY_NOT_SUPPORTED",
-            sys::ma_result_MA_SOCKET_NOT_SUPPORTED => "MA_SOCKET_NOT_SUPPORTED",
-            sys::ma_result_MA_CONNECTION_RESET => "MA_CONNECTION_RESET",
-            sys::ma_result_MA_ALREADY_CONNECTED => "MA_ALREADY_CONNECTED",
-            sys::ma_result_MA_NOT_CONNECTED => "MA_NOT_CONNECTED",
-            sys::ma_result_MA_CONNECTION_REFUSED => "MA_CONNECTION_REFUSED",
-            sys::ma_result_MA_NO_HOST => "MA_NO_HOST",
-            sys::ma_result_MA_IN_PROGRESS => "MA_IN_PROGRESS",
-            sys::ma_result_MA_CANCELLED => "MA_CANCELLED",
-            sys::ma_result_MA_MEMORY_ALREADY_MAPPED => "MemoryAlreadyMapped",
-            // General non-standard errors.
-            sys::ma_result_MA_CRC_MISMATCH => "MA_CRC_MISMATCH",
-            // General miniaudio-specific errors.
-            sys::ma_result_MA_FORMAT_NOT_SUPPORTED => "MA_FORMAT_NOT_SUPPORTED",
-            sys::ma_result_MA_DEVICE_TYPE_NOT_SUPPORTED => "MA_DEVICE_TYPE_NOT_SUPPORTED",
-            sys::ma_result_MA_SHARE_MODE_NOT_SUPPORTED => "MA_SHARE_MODE_NOT_SUPPORTED",
-            sys::ma_result_MA_NO_BACKEND => "MA_NO_BACKEND",
-            sys::ma_result_MA_NO_DEVICE => "MA_NO_DEVICE",
-            sys::ma_result_MA_API_NOT_FOUND => "MA_API_NOT_FOUND",
-            sys::ma_result_MA_INVALID_DEVICE_CONFIG => "MA_INVALID_DEVICE_CONFIG",
-            sys::ma_result_MA_LOOP => "MA_LOOP",
-            sys::ma_result_MA_BACKEND_NOT_ENABLED => "MA_BACKEND_NOT_ENABLED",
-            // State errors.
-            sys::ma_result_MA_DEVICE_NOT_INITIALIZED => "MA_DEVICE_NOT_INITIALIZED",
-            sys::ma_result_MA_DEVICE_ALREADY_INITIALIZED => "MA_DEVICE_ALREADY_INITIALIZED",
-            sys::ma_result_MA_DEVICE_NOT_STARTED => "MA_DEVICE_NOT_STARTED",
-            sys::ma_result_MA_DEVICE_NOT_STOPPED => "MA_DEVICE_NOT_STOPPED",
-            // Operation errors.
-            sys::ma_result_MA_FAILED_TO_INIT_BACKEND => "MA_FAILED_TO_INIT_BACKEND",
-            sys::ma_result_MA_FAILED_TO_OPEN_BACKEND_DEVICE => "MA_FAILED_TO_OPEN_BACKEND_DEVICE",
-            sys::ma_result_MA_FAILED_TO_START_BACKEND_DEVICE => "MA_FAILED_TO_START_BACKEND_DEVICE",
-            sys::ma_result_MA_FAILED_TO_STOP_BACKEND_DEVICE => "MA_FAILED_TO_STOP_BACKEND_DEVICE",
-            _ => "UNKNOWN_MA_ERROR",
-        }
+        MaResultCode::from_raw(self.0).name()
     }
 }
 
@@ -387,7 +404,7 @@ impl ErrorKinds {
 /// - Detecting arithmetic overflow
 ///
 /// Miniaudio-native errors are represented separately by `MA_RESULT`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum ErrorKinds {
     // Error converting a raw value to an enum variant
@@ -421,6 +438,13 @@ pub enum ErrorKinds {
     S24OverFlow,
     S24UnderFlow,
     InvalidGraphState,
+    BusIndexOutOfRange {
+        context: &'static str, // "output bus"
+        bus: u32,
+        bus_count: u32,
+    },
+    /// Attaching a node would create a cycle in the node graph
+    NodeGraphCycle,
     InvalidFormat,
     /// Error coverting Path to CString
     InvalidCString,
@@ -434,28 +458,68 @@ pub enum ErrorKinds {
     },
     NotImplemented,
     ReaderExists,
+    /// User code panicked inside a callback
+    CallbackPanicked,
+    /// A resource is already registered under this name (or file path), see
+    /// [`DuplicatePolicy`](crate::engine::resource::DuplicatePolicy)
+    ResourceAlreadyRegistered {
+        name: String,
+    },
 }
 
-impl std::error::Error for MaudioError {}
+impl std::error::Error for MaudioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.source {
+            Some(err) => Some(err.as_ref()),
+            None => match &self.native {
+                Some(ErrorKinds::IntegerError { err }) => Some(err),
+                _ => None,
+            },
+        }
+    }
+}
 
 impl From<std::io::Error> for MaudioError {
     fn from(value: std::io::Error) -> Self {
-        Self {
-            native: Some(ErrorKinds::IoError { err: value.kind() }),
-            ma_result: MaError(sys::ma_result_MA_ERROR),
-        }
+        let mut err = Self::new_ma_error(ErrorKinds::IoError { err: value.kind() });
+        err.source = Some(Arc::new(value));
+        err
     }
 }
 
 impl From<TryFromIntError> for MaudioError {
     fn from(value: TryFromIntError) -> Self {
-        Self {
-            native: Some(ErrorKinds::IntegerError { err: value }),
-            ma_result: MaError(sys::ma_result_MA_ERROR),
-        }
+        Self::new_ma_error(ErrorKinds::IntegerError { err: value })
+    }
+}
+
+impl From<std::ffi::NulError> for MaudioError {
+    fn from(_: std::ffi::NulError) -> Self {
+        Self::new_ma_error(ErrorKinds::InvalidCString)
     }
 }
 
+impl From<ErrorKinds> for MaudioError {
+    fn from(value: ErrorKinds) -> Self {
+        Self::new_ma_error(value)
+    }
+}
+
+/// What a [`MaudioError`] relates to.
+///
+/// Attached by the high-level APIs, so that an error like a failed decoder init
+/// names the file involved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorContext {
+    /// A file on disk
+    Path(PathBuf),
+    /// A resource or sound looked up by name
+    Resource(String),
+    /// A node in the node graph, see [`NodeOps::node_id`](crate::engine::node_graph::nodes::NodeOps::node_id)
+    Node(usize),
+}
+
 /// Error type returned by the maudio crate.
 ///
 /// `MaudioError` can originate from two sources:
@@ -468,17 +532,34 @@ impl From<TryFromIntError> for MaudioError {
 ///
 /// When `Some`, the error was produced by the wrapper and may include an
 /// associated miniaudio result for context. In this case, ma_result will be `MA_ERROR (-1)`.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+///
+/// Errors returned by the high-level APIs may carry an [`ErrorContext`], such as the
+/// path of the file that failed to load. Errors converted from [`std::io::Error`] keep
+/// the original error as their [`source`](std::error::Error::source).
+#[derive(Debug, Clone)]
 pub struct MaudioError {
     native: Option<ErrorKinds>,
     ma_result: MaError,
+    context: Option<Box<ErrorContext>>,
+    source: Option<Arc<std::io::Error>>,
 }
 
+// The source is not compared, io::Error has no equality
+impl PartialEq for MaudioError {
+    fn eq(&self, other: &Self) -> bool {
+        self.native == other.native
+            && self.ma_result == other.ma_result
+            && self.context == other.context
+    }
+}
+
+impl Eq for MaudioError {}
+
 pub type MaResult<T> = std::result::Result<T, MaudioError>;
 
 #[cfg(test)]
 mod test {
-    use crate::MaudioError;
+    use crate::{ErrorContext, MaudioError};
 
     #[test]
     fn test_maudioerror_is_busy() {
@@ -487,4 +568,19 @@ mod test {
         let err = MaudioError::from_ma_result(sys::ma_result_MA_BUSY);
         assert!(err.is_busy());
     }
+
+    #[test]
+    fn test_maudioerror_context_and_source() {
+        use std::error::Error;
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let err = MaudioError::from(io)
+            .with_context(ErrorContext::Path("a.wav".into()))
+            .with_context(ErrorContext::Resource("ignored".into()));
+
+        assert_eq!(err.context(), Some(&ErrorContext::Path("a.wav".into())));
+        assert!(err.to_string().ends_with("[file: a.wav]"));
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), "missing");
+    }
 }
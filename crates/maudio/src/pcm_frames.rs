@@ -1,4 +1,5 @@
 //! PCM format abstraction and utilities.
+use crate::audio::formats::Format;
 use crate::pcm_frames::private_pcm::PcmInterface;
 use crate::{ErrorKinds, MaResult, MaudioError};
 
@@ -918,6 +919,8 @@ pub trait PcmFormat {
     const STORE_SILENCE: Self::StorageUnit;
     /// Fills the Pcm buffer with silence. This is not always the same as `PcmFormat::PcmUnit::default()`
     const PCM_UNIT_SILENCE: Self::PcmUnit;
+    /// The miniaudio format of `StorageUnit`.
+    const FORMAT: Format;
 }
 
 impl PcmFormat for u8 {
@@ -930,6 +933,7 @@ impl PcmFormat for u8 {
     const DIRECT_READ: bool = true;
     const STORE_SILENCE: Self::StorageUnit = 128;
     const PCM_UNIT_SILENCE: Self::PcmUnit = 128;
+    const FORMAT: Format = Format::U8;
 }
 
 impl PcmFormat for i16 {
@@ -942,6 +946,7 @@ impl PcmFormat for i16 {
     const DIRECT_READ: bool = true;
     const STORE_SILENCE: Self::StorageUnit = 0;
     const PCM_UNIT_SILENCE: Self::PcmUnit = 0;
+    const FORMAT: Format = Format::S16;
 }
 
 impl PcmFormat for S24Packed {
@@ -954,6 +959,7 @@ impl PcmFormat for S24Packed {
     const DIRECT_READ: bool = true;
     const STORE_SILENCE: Self::StorageUnit = 0;
     const PCM_UNIT_SILENCE: Self::PcmUnit = 0;
+    const FORMAT: Format = Format::S24Packed;
 }
 
 impl PcmFormat for S24 {
@@ -966,6 +972,7 @@ impl PcmFormat for S24 {
     const DIRECT_READ: bool = false;
     const STORE_SILENCE: Self::StorageUnit = 0;
     const PCM_UNIT_SILENCE: Self::PcmUnit = 0;
+    const FORMAT: Format = Format::S24Packed;
 }
 
 impl PcmFormat for i32 {
@@ -978,6 +985,7 @@ impl PcmFormat for i32 {
     const DIRECT_READ: bool = true;
     const STORE_SILENCE: Self::StorageUnit = 0;
     const PCM_UNIT_SILENCE: Self::PcmUnit = 0;
+    const FORMAT: Format = Format::S32;
 }
 
 impl PcmFormat for f32 {
@@ -989,4 +997,5 @@ impl PcmFormat for f32 {
     const DIRECT_READ: bool = true;
     const STORE_SILENCE: Self::StorageUnit = 0.0;
     const PCM_UNIT_SILENCE: Self::PcmUnit = 0.0;
+    const FORMAT: Format = Format::F32;
 }
@@ -0,0 +1,161 @@
+//! Typed miniaudio result codes.
+use maudio_sys::ffi as sys;
+
+macro_rules! result_codes {
+    ($($variant:ident => $raw:ident,)*) => {
+        /// A miniaudio result code (`ma_result`).
+        ///
+        /// Returned by [`MaudioError::code`](crate::MaudioError::code), so errors can be
+        /// matched on without comparing against the raw `sys` constants:
+        ///
+        /// ```
+        /// # use maudio::{data_source::sources::decoder::Decoder, MaResultCode};
+        /// # use std::path::Path;
+        /// match Decoder::probe(Path::new("missing.wav")) {
+        ///     Ok(info) => println!("{} channels", info.channels),
+        ///     Err(err) if err.code() == MaResultCode::DoesNotExist => println!("not found"),
+        ///     Err(err) => println!("failed: {err}"),
+        /// }
+        /// ```
+        ///
+        /// Codes that are not known to this version of maudio are reported as
+        /// [`MaResultCode::Other`]. Use [`MaResultCode::from_raw`] instead of constructing
+        /// `Other` directly, so known codes always map to their own variant.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum MaResultCode {
+            $($variant,)*
+            /// A result code without a variant.
+            Other(i32),
+        }
+
+        impl MaResultCode {
+            /// Converts a raw `ma_result`.
+            pub fn from_raw(raw: sys::ma_result) -> Self {
+                match raw {
+                    $(sys::$raw => Self::$variant,)*
+                    other => Self::Other(other as i32),
+                }
+            }
+
+            /// Returns the raw `ma_result`.
+            pub fn to_raw(self) -> sys::ma_result {
+                match self {
+                    $(Self::$variant => sys::$raw,)*
+                    Self::Other(raw) => raw as sys::ma_result,
+                }
+            }
+
+            /// Name of the miniaudio constant, for example `"MA_DOES_NOT_EXIST"`.
+            pub fn name(self) -> &'static str {
+                const PREFIX: usize = "ma_result_".len();
+                match self {
+                    $(Self::$variant => &stringify!($raw)[PREFIX..],)*
+                    Self::Other(_) => "UNKNOWN_MA_ERROR",
+                }
+            }
+        }
+    };
+}
+
+result_codes! {
+    Success => ma_result_MA_SUCCESS,
+    Error => ma_result_MA_ERROR,
+    InvalidArgs => ma_result_MA_INVALID_ARGS,
+    InvalidOperation => ma_result_MA_INVALID_OPERATION,
+    OutOfMemory => ma_result_MA_OUT_OF_MEMORY,
+    OutOfRange => ma_result_MA_OUT_OF_RANGE,
+    AccessDenied => ma_result_MA_ACCESS_DENIED,
+    DoesNotExist => ma_result_MA_DOES_NOT_EXIST,
+    AlreadyExists => ma_result_MA_ALREADY_EXISTS,
+    TooManyOpenFiles => ma_result_MA_TOO_MANY_OPEN_FILES,
+    InvalidFile => ma_result_MA_INVALID_FILE,
+    TooBig => ma_result_MA_TOO_BIG,
+    PathTooLong => ma_result_MA_PATH_TOO_LONG,
+    NameTooLong => ma_result_MA_NAME_TOO_LONG,
+    NotDirectory => ma_result_MA_NOT_DIRECTORY,
+    IsDirectory => ma_result_MA_IS_DIRECTORY,
+    DirectoryNotEmpty => ma_result_MA_DIRECTORY_NOT_EMPTY,
+    AtEnd => ma_result_MA_AT_END,
+    NoSpace => ma_result_MA_NO_SPACE,
+    Busy => ma_result_MA_BUSY,
+    IoError => ma_result_MA_IO_ERROR,
+    Interrupt => ma_result_MA_INTERRUPT,
+    Unavailable => ma_result_MA_UNAVAILABLE,
+    AlreadyInUse => ma_result_MA_ALREADY_IN_USE,
+    BadAddress => ma_result_MA_BAD_ADDRESS,
+    BadSeek => ma_result_MA_BAD_SEEK,
+    BadPipe => ma_result_MA_BAD_PIPE,
+    Deadlock => ma_result_MA_DEADLOCK,
+    TooManyLinks => ma_result_MA_TOO_MANY_LINKS,
+    NotImplemented => ma_result_MA_NOT_IMPLEMENTED,
+    NoMessage => ma_result_MA_NO_MESSAGE,
+    BadMessage => ma_result_MA_BAD_MESSAGE,
+    NoDataAvailable => ma_result_MA_NO_DATA_AVAILABLE,
+    InvalidData => ma_result_MA_INVALID_DATA,
+    Timeout => ma_result_MA_TIMEOUT,
+    NoNetwork => ma_result_MA_NO_NETWORK,
+    NotUnique => ma_result_MA_NOT_UNIQUE,
+    NotSocket => ma_result_MA_NOT_SOCKET,
+    NoAddress => ma_result_MA_NO_ADDRESS,
+    BadProtocol => ma_result_MA_BAD_PROTOCOL,
+    ProtocolUnavailable => ma_result_MA_PROTOCOL_UNAVAILABLE,
+    ProtocolNotSupported => ma_result_MA_PROTOCOL_NOT_SUPPORTED,
+    ProtocolFamilyNotSupported => ma_result_MA_PROTOCOL_FAMILY_NOT_SUPPORTED,
+    AddressFamilyNotSupported => ma_result_MA_ADDRESS_FAMILY_NOT_SUPPORTED,
+    SocketNotSupported => ma_result_MA_SOCKET_NOT_SUPPORTED,
+    ConnectionReset => ma_result_MA_CONNECTION_RESET,
+    AlreadyConnected => ma_result_MA_ALREADY_CONNECTED,
+    NotConnected => ma_result_MA_NOT_CONNECTED,
+    ConnectionRefused => ma_result_MA_CONNECTION_REFUSED,
+    NoHost => ma_result_MA_NO_HOST,
+    InProgress => ma_result_MA_IN_PROGRESS,
+    Cancelled => ma_result_MA_CANCELLED,
+    MemoryAlreadyMapped => ma_result_MA_MEMORY_ALREADY_MAPPED,
+    // General non-standard errors.
+    CrcMismatch => ma_result_MA_CRC_MISMATCH,
+    // General miniaudio-specific errors.
+    FormatNotSupported => ma_result_MA_FORMAT_NOT_SUPPORTED,
+    DeviceTypeNotSupported => ma_result_MA_DEVICE_TYPE_NOT_SUPPORTED,
+    ShareModeNotSupported => ma_result_MA_SHARE_MODE_NOT_SUPPORTED,
+    NoBackend => ma_result_MA_NO_BACKEND,
+    NoDevice => ma_result_MA_NO_DEVICE,
+    ApiNotFound => ma_result_MA_API_NOT_FOUND,
+    InvalidDeviceConfig => ma_result_MA_INVALID_DEVICE_CONFIG,
+    Loop => ma_result_MA_LOOP,
+    BackendNotEnabled => ma_result_MA_BACKEND_NOT_ENABLED,
+    // State errors.
+    DeviceNotInitialized => ma_result_MA_DEVICE_NOT_INITIALIZED,
+    DeviceAlreadyInitialized => ma_result_MA_DEVICE_ALREADY_INITIALIZED,
+    DeviceNotStarted => ma_result_MA_DEVICE_NOT_STARTED,
+    DeviceNotStopped => ma_result_MA_DEVICE_NOT_STOPPED,
+    // Operation errors.
+    FailedToInitBackend => ma_result_MA_FAILED_TO_INIT_BACKEND,
+    FailedToOpenBackendDevice => ma_result_MA_FAILED_TO_OPEN_BACKEND_DEVICE,
+    FailedToStartBackendDevice => ma_result_MA_FAILED_TO_START_BACKEND_DEVICE,
+    FailedToStopBackendDevice => ma_result_MA_FAILED_TO_STOP_BACKEND_DEVICE,
+}
+
+impl std::fmt::Display for MaResultCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_result_code_round_trip() {
+        let code = MaResultCode::from_raw(sys::ma_result_MA_DOES_NOT_EXIST);
+        assert_eq!(code, MaResultCode::DoesNotExist);
+        assert_eq!(code.to_raw(), sys::ma_result_MA_DOES_NOT_EXIST);
+        assert_eq!(code.name(), "MA_DOES_NOT_EXIST");
+
+        let other = MaResultCode::from_raw(-12345);
+        assert_eq!(other, MaResultCode::Other(-12345));
+        assert_eq!(other.to_raw(), -12345);
+        assert_eq!(other.name(), "UNKNOWN_MA_ERROR");
+    }
+}
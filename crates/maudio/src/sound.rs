@@ -5,31 +5,66 @@ use std::{
     cell::Cell,
     marker::PhantomData,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{atomic::Ordering, Arc},
 };
 
 use maudio_sys::ffi as sys;
 
 use crate::{
     audio::{
+        dsp::shaped_fader::FadeCurve,
         math::vec3::Vec3,
-        pan::PanMode,
+        pan::{PanLaw, PanMode},
         spatial::{attenuation::AttenuationModel, cone::Cone, positioning::Positioning},
     },
-    data_source::{DataFormat, DataSourceRef},
+    data_source::{
+        sources::decoder::wav_metadata::{Marker, WavMetadata},
+        DataFormat, DataSourceRef,
+    },
     engine::{
-        node_graph::{nodes::NodeRef, GraphOwner, NodeGraphRef},
+        node_graph::{
+            nodes::{
+                effects::{
+                    fade::{FadeNode, FadeNodeBuilder},
+                    pitch_shift::{PitchShiftNode, PitchShiftNodeBuilder},
+                },
+                node_ffi, NodeOps, NodeRef,
+            },
+            GraphOwner, NodeGraphRef,
+        },
+        resource::{rm_progress::LoadProgress, rm_registry::ResourceUse, DataStore},
+        start_policy::StartOutcome,
         Engine, EngineInner,
     },
-    sound::{notifier::EndNotifier, sound_flags::SoundFlags, sound_group::SoundGroup},
-    util::fence::Fence,
-    Binding, MaResult, MaudioError,
+    sound::{
+        channel_mix::ChannelMix,
+        marker_tracker::MarkerTracker,
+        notifier::{EndNotifier, MarkerNotifier},
+        occlusion::Occlusion,
+        seamless_loop::SeamlessSource,
+        sound_flags::SoundFlags,
+        sound_group::SoundGroup,
+        stream_error::{StreamErrorNotifier, StreamErrorPolicy, StreamWatch},
+    },
+    util::{fence::Fence, rt_check},
+    Binding, ErrorKinds, MaResult, MaudioError,
 };
 
+mod channel_mix;
+mod marker_tracker;
 pub mod notifier;
+mod occlusion;
+mod reaper;
+mod seamless_loop;
+pub mod sound_bank;
 pub mod sound_builder;
 pub mod sound_flags;
 pub mod sound_group;
+pub mod sound_params;
+pub mod spatial_defaults;
+pub mod spatial_updater;
+pub mod stream_error;
+pub mod voice_manager;
 
 /// The initialization source for a sound.
 ///
@@ -48,27 +83,95 @@ impl SoundSource<'_> {
     pub(crate) fn is_valid(&self) -> bool {
         !matches!(self, Self::None)
     }
+
+    pub(crate) fn is_file(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Self::FileUtf8(_) => true,
+            #[cfg(windows)]
+            Self::FileWide(_) => true,
+            _ => false,
+        }
+    }
 }
 
 /// Engine-managed sound voice.
 ///
 /// A `Sound` is an engine-owned playback instance backed by a data source. It can be started,
 /// stopped, seeked, spatialized, and controlled (volume/pan/pitch).
+///
+/// ## Threads
+///
+/// `Sound` is `Send` but not `Sync`. It can be moved to another thread, for example into an
+/// ECS component or a game logic thread, and controlled from there. miniaudio reads the sound's
+/// parameters from the audio thread using atomics, so changes made from any thread are picked up
+/// on the next processed block. Only one thread can own a `Sound` at a time, so it cannot be
+/// modified from two threads at once. Share it with a `Mutex` if several threads need it.
 pub struct Sound {
-    inner: *mut sys::ma_sound,
+    inner: RawSound,
     _engine: Arc<EngineInner>,
     _not_sync: PhantomData<Cell<()>>,
     // Miniaudio stores only one ma_sound_end_proc and pUserData per ma_sound.
     // One end_notifier at a time will be ok
     _fence: Option<Fence>, // Ref count
     end_notifier: Option<EndNotifier>,
+    // Restores the pitch changed by `set_playback_rate_preserve_pitch`
+    time_stretch: Option<PitchShiftNode>,
+    // Applies the fades set with `set_fade_curve_pcm`
+    fade: Option<FadeNode>,
+    // Filter for `set_occlusion` and `set_obstruction`
+    occlusion: Option<Occlusion>,
+    // Applies the gains set with `set_channel_gain` and `set_pan_law`
+    channel_mix: Option<ChannelMix>,
+    // In-memory source played after `set_seamless_loop`
+    seamless_source: Option<SeamlessSource>,
+    // Read from the file the sound was loaded from, or set with `set_markers`
+    markers: Vec<Marker>,
+    // Node flagging the markers for `marker_notifier`
+    marker_tracker: Option<(MarkerTracker, MarkerNotifier)>,
+    // Used by the engine's start policy
+    priority: u8,
+    // End callback state for `set_stream_error_policy`
+    stream_watch: Option<Arc<StreamWatch>>,
+    // Registered data read by the sound, see `ResourceGuard::build_sound`
+    pub(crate) resource: Option<Arc<DataStore>>,
+    // Set when the sound reads a registered resource
+    pub(crate) resource_use: Option<ResourceUse>,
 }
 
+// The audio thread only reads the ma_sound through miniaudio's own synchronization,
+// and Sound is not Sync, so it is never accessed from two user threads at once.
+#[derive(Clone, Copy)]
+struct RawSound(*mut sys::ma_sound);
+
+unsafe impl Send for RawSound {}
+
+// Sound is Send because each of its fields is. Keep in sync with the fields above.
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<RawSound>();
+    assert_send::<Arc<EngineInner>>();
+    assert_send::<PhantomData<Cell<()>>>();
+    assert_send::<Option<Fence>>();
+    assert_send::<Option<EndNotifier>>();
+    assert_send::<Option<PitchShiftNode>>();
+    assert_send::<Option<FadeNode>>();
+    assert_send::<Option<Occlusion>>();
+    assert_send::<Option<ChannelMix>>();
+    assert_send::<Option<SeamlessSource>>();
+    assert_send::<Vec<Marker>>();
+    assert_send::<Option<(MarkerTracker, MarkerNotifier)>>();
+    assert_send::<u8>();
+    assert_send::<Option<Arc<StreamWatch>>>();
+    assert_send::<Option<Arc<DataStore>>>();
+    assert_send::<Option<ResourceUse>>();
+};
+
 impl Binding for Sound {
     type Raw = *mut sys::ma_sound;
 
     fn to_raw(&self) -> Self::Raw {
-        self.inner
+        self.inner.0
     }
 }
 
@@ -99,26 +202,87 @@ impl Sound {
         sound_ffi::ma_sound_get_data_source(self)
     }
 
+    /// Returns how far an asynchronous load got, for sounds created from a file or a
+    /// registered resource with [`SoundFlags::ASYNC`].
+    ///
+    /// Returns `None` for sounds created from a data source, which are not loaded by the
+    /// resource manager. See [`LoadProgress`].
+    pub fn load_progress(&self) -> Option<LoadProgress<'_>> {
+        let source = unsafe { (*self.to_raw()).pResourceManagerDataSource };
+        (!source.is_null()).then(|| LoadProgress::from_data_source(source))
+    }
+
     /// Starts playback.
+    ///
+    /// Follows the engine's [`StartPolicy`](crate::engine::start_policy::StartPolicy), if
+    /// any, so the sound might be deferred or not started. Use [`Sound::try_play_sound`] to
+    /// know which.
     pub fn play_sound(&mut self) -> MaResult<()> {
-        sound_ffi::ma_sound_start(self)
+        self.try_play_sound().map(|_| ())
+    }
+
+    /// Starts playback, unless the engine's
+    /// [`StartPolicy`](crate::engine::start_policy::StartPolicy) holds it back, and returns
+    /// what happened.
+    pub fn try_play_sound(&mut self) -> MaResult<StartOutcome> {
+        let engine = self._engine.clone();
+        engine
+            .sounds
+            .start(&engine.one_shots, self.inner.0, self.priority, || {
+                sound_ffi::ma_sound_start(self)
+            })
+    }
+
+    /// Returns the priority used by the engine's start policy.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// Sets the priority used by the engine's
+    /// [`StartPolicy`](crate::engine::start_policy::StartPolicy). Higher values win. The
+    /// default is `0`.
+    pub fn set_priority(&mut self, priority: u8) {
+        self.priority = priority;
     }
 
     /// Stops playback.
+    ///
+    /// A sound paused by [`Engine::pause_all_except`] is then not started again by
+    /// [`Engine::resume_all`].
     pub fn stop_sound(&mut self) -> MaResult<()> {
+        self._engine.sounds.forget_paused(self.inner.0);
         sound_ffi::ma_sound_stop(self)
     }
 
     /// Stops playback with a fade-out over `fade_frames` PCM frames.
     pub fn stop_at_with_fade_frames(&mut self, fade_frames: u64) -> MaResult<()> {
+        self._engine.sounds.forget_paused(self.inner.0);
         sound_ffi::ma_sound_stop_with_fade_in_pcm_frames(self, fade_frames)
     }
 
     /// Stops playback with a fade-out over `fade_milis` milliseconds.
     pub fn stop_at_with_fade_millis(&mut self, fade_milis: u64) -> MaResult<()> {
+        self._engine.sounds.forget_paused(self.inner.0);
         sound_ffi::ma_sound_stop_with_fade_in_milis(self, fade_milis)
     }
 
+    /// Fades the sound out over `fade_millis` milliseconds, then frees it.
+    ///
+    /// The sound is handed to a background thread that drops it once it stopped, so it does
+    /// not have to be kept alive and polled until the fade is over. It keeps its engine alive
+    /// until then. A sound that is not playing is freed right away.
+    ///
+    /// The fade only progresses while the engine is read, by its device or an
+    /// [`EngineReader`](crate::engine::EngineReader). If every other handle to the engine is
+    /// dropped first, the sound is freed without finishing the fade.
+    pub fn fade_out_and_destroy(mut self, fade_millis: u64) -> MaResult<()> {
+        if !self.is_playing() {
+            return Ok(());
+        }
+        self.stop_at_with_fade_millis(fade_millis)?;
+        reaper::free_when_stopped(self)
+    }
+
     /// Returns the sound volume.
     pub fn volume(&self) -> f32 {
         sound_ffi::ma_sound_get_volume(self)
@@ -131,12 +295,58 @@ impl Sound {
 
     /// Returns the pan value.
     pub fn pan(&self) -> f32 {
-        sound_ffi::ma_sound_get_pan(self)
+        match self.channel_mix.as_ref().and_then(|mix| mix.pan()) {
+            Some(pan) => pan,
+            None => sound_ffi::ma_sound_get_pan(self),
+        }
     }
 
     /// Sets the pan value.
     pub fn set_pan(&mut self, pan: f32) {
-        sound_ffi::ma_sound_set_pan(self, pan);
+        match &mut self.channel_mix {
+            // The gains of a pan law are always valid
+            Some(mix) if mix.pan_law().is_some() => {
+                let _ = mix.set_pan(pan);
+            }
+            _ => sound_ffi::ma_sound_set_pan(self, pan),
+        }
+    }
+
+    /// Returns the pan law set with [`Sound::set_pan_law`], or `None` if miniaudio pans the
+    /// sound.
+    pub fn pan_law(&self) -> Option<PanLaw> {
+        self.channel_mix.as_ref().and_then(|mix| mix.pan_law())
+    }
+
+    /// Replaces miniaudio's panning with `law`, or restores it with `None`.
+    ///
+    /// While a law is set, [`Sound::set_pan`] evaluates it and applies the resulting gains
+    /// to the left and right channels, on top of [`Sound::set_channel_gain`], and
+    /// [`Sound::set_pan_mode`] has no effect. The current pan is kept.
+    ///
+    /// Returns an error if the output of the sound is not stereo.
+    pub fn set_pan_law(&mut self, law: Option<PanLaw>) -> MaResult<()> {
+        let pan = self.pan();
+        match law {
+            Some(law) => {
+                if self.as_node().output_channels(0) != 2 {
+                    return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                        "pan laws need a stereo output",
+                    )));
+                }
+                self.channel_mix()?.set_pan_law(Some(law), pan)?;
+                // A centered sound is not changed by miniaudio's panner
+                sound_ffi::ma_sound_set_pan(self, 0.0);
+                Ok(())
+            }
+            None => {
+                if let Some(mix) = &mut self.channel_mix {
+                    mix.set_pan_law(None, 0.0)?;
+                    sound_ffi::ma_sound_set_pan(self, pan);
+                }
+                Ok(())
+            }
+        }
     }
 
     /// Returns the pan mode.
@@ -150,13 +360,61 @@ impl Sound {
     }
 
     /// Returns the pitch multiplier.
+    ///
+    /// This is the pitch set with [`Sound::set_pitch`], without the engine's
+    /// [time scale](Engine::set_time_scale).
     pub fn pitch(&self) -> f32 {
-        sound_ffi::ma_sound_get_pitch(self)
+        self._engine.sounds.pitch(self.inner.0)
     }
 
     /// Sets the pitch multiplier.
+    ///
+    /// Unless the sound is [exempt](Sound::set_time_scale_exempt), miniaudio plays it at
+    /// `pitch` multiplied by the engine's [time scale](Engine::set_time_scale).
     pub fn set_pitch(&mut self, pitch: f32) {
-        sound_ffi::ma_sound_set_pitch(self, pitch);
+        self._engine.sounds.set_pitch(self.inner.0, pitch);
+    }
+
+    /// Returns `true` if the sound ignores the engine's [time scale](Engine::set_time_scale).
+    pub fn is_time_scale_exempt(&self) -> bool {
+        self._engine.sounds.is_time_scale_exempt(self.inner.0)
+    }
+
+    /// Makes the sound ignore the engine's [time scale](Engine::set_time_scale), or follow it
+    /// again. Sounds follow it by default.
+    ///
+    /// The pitch set with [`Sound::set_pitch`] is kept.
+    pub fn set_time_scale_exempt(&mut self, exempt: bool) {
+        self._engine
+            .sounds
+            .set_time_scale_exempt(self.inner.0, exempt);
+    }
+
+    /// Changes the playback speed without changing the pitch.
+    ///
+    /// A `rate` of `2.0` plays twice as fast, `0.5` half as fast. The rate is clamped to
+    /// `0.25..=4.0`. Useful for audiobooks and podcasts, where [`Sound::set_pitch`] would
+    /// make voices sound higher or lower.
+    ///
+    /// The speed is changed with [`Sound::set_pitch`], and a
+    /// [`PitchShiftNode`] shifts the pitch back by the same ratio. The first time this is
+    /// called with a rate other than `1.0`, the node is inserted between the sound and the
+    /// node its output is attached to, so attach the sound where it should go first.
+    /// Calling [`Sound::set_pitch`] afterwards changes the speed again without updating
+    /// the pitch correction.
+    pub fn set_playback_rate_preserve_pitch(&mut self, rate: f32) -> MaResult<()> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let rate = rate.clamp(0.25, 4.0);
+        let semitones = -12.0 * rate.log2();
+        match &mut self.time_stretch {
+            Some(node) => node.set_semitones(semitones),
+            None if rate == 1.0 => {}
+            None => self.time_stretch = Some(self.insert_time_stretch(semitones)?),
+        }
+        self.set_pitch(rate);
+        Ok(())
     }
 
     /// Returns `true` if spatialization is enabled.
@@ -319,6 +577,65 @@ impl Sound {
         sound_ffi::ma_sound_set_directional_attenuation_factor(self, factor);
     }
 
+    /// Returns the occlusion amount, between `0.0` and `1.0`.
+    pub fn occlusion(&self) -> f32 {
+        self.occlusion.as_ref().map_or(0.0, |o| o.occlusion())
+    }
+
+    /// Sets how much the sound is blocked from the listener, for example by a wall.
+    ///
+    /// `amount` goes from `0.0` (clear line of sight) to `1.0` (fully occluded) and is
+    /// clamped to that range. Higher amounts lower the cutoff of a low-pass filter and
+    /// the volume of the sound.
+    ///
+    /// The filter is inserted between the sound and the node its output is attached to
+    /// the first time a non-zero amount is set, so attach the sound where it should go
+    /// first.
+    pub fn set_occlusion(&mut self, amount: f32) -> MaResult<()> {
+        let obstruction = self.obstruction();
+        self.set_occlusion_amounts(amount, obstruction)
+    }
+
+    /// Returns the obstruction amount, between `0.0` and `1.0`.
+    pub fn obstruction(&self) -> f32 {
+        self.occlusion.as_ref().map_or(0.0, |o| o.obstruction())
+    }
+
+    /// Sets how much the direct path to the listener is obstructed, for example by a pillar.
+    ///
+    /// Like [`Sound::set_occlusion`], but only muffles the sound without lowering its volume.
+    /// When both are set, the filter follows the larger amount.
+    pub fn set_obstruction(&mut self, amount: f32) -> MaResult<()> {
+        let occlusion = self.occlusion();
+        self.set_occlusion_amounts(occlusion, amount)
+    }
+
+    /// Returns the gain of output `channel`, or `None` if the sound has no such channel.
+    pub fn channel_gain(&self, channel: u32) -> Option<f32> {
+        match &self.channel_mix {
+            Some(mix) => mix.gain(channel),
+            None => (channel < self.as_node().output_channels(0)).then_some(1.0),
+        }
+    }
+
+    /// Sets the linear gain of one output channel, for example `0.0` on channel `0` to mute
+    /// the left channel of a stereo sound.
+    ///
+    /// Applied after panning and spatialization, on top of [`Sound::set_volume`]. The gains
+    /// are applied by a
+    /// [`ChannelGainNode`](crate::engine::node_graph::nodes::effects::channel_gain::ChannelGainNode)
+    /// inserted between the sound and the node its output is attached to the first time a
+    /// gain is set, so attach the sound where it should go first.
+    ///
+    /// Returns an error if the sound has no such channel, or if `gain` is negative or not
+    /// finite.
+    pub fn set_channel_gain(&mut self, channel: u32, gain: f32) -> MaResult<()> {
+        if channel >= self.as_node().output_channels(0) {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        self.channel_mix()?.set_gain(channel, gain)
+    }
+
     /// Schedules a fade from `vol_start` to `vol_end` over `fade_length_frames` PCM frames.
     pub fn set_fade_pcm(&mut self, vol_start: f32, vol_end: f32, fade_length_frames: u64) {
         sound_ffi::ma_sound_set_fade_in_pcm_frames(self, vol_start, vol_end, fade_length_frames);
@@ -368,6 +685,57 @@ impl Sound {
         sound_ffi::ma_sound_get_current_fade_volume(self)
     }
 
+    /// Schedules a fade from `vol_start` to `vol_end` over `fade_length_frames` PCM frames,
+    /// following `curve`.
+    ///
+    /// [`Sound::set_fade_pcm`] is always linear. This fade is applied by a
+    /// [`FadeNode`] instead, which is inserted between the sound and the node its output is
+    /// attached to the first time it is called, so attach the sound where it should go
+    /// first. The two fades are independent and their volumes multiply.
+    ///
+    /// A negative `vol_start` starts from the current volume of the curved fade.
+    pub fn set_fade_curve_pcm(
+        &mut self,
+        vol_start: f32,
+        vol_end: f32,
+        fade_length_frames: u64,
+        curve: FadeCurve,
+    ) -> MaResult<()> {
+        if self.fade.is_none() {
+            let graph = self.node_graph();
+            let node = FadeNodeBuilder::new(&graph, self.as_node().output_channels(0)).build()?;
+            self.insert_after(node.as_node())?;
+            self.fade = Some(node);
+        }
+        if let Some(fade) = &mut self.fade {
+            fade.set_fade_pcm(vol_start, vol_end, fade_length_frames, curve);
+        }
+        Ok(())
+    }
+
+    /// Schedules a fade from `vol_start` to `vol_end` over `fade_length_mili` milliseconds,
+    /// following `curve`.
+    ///
+    /// See [`Sound::set_fade_curve_pcm`].
+    pub fn set_fade_curve_mili(
+        &mut self,
+        vol_start: f32,
+        vol_end: f32,
+        fade_length_mili: u64,
+        curve: FadeCurve,
+    ) -> MaResult<()> {
+        let sample_rate: u32 = self.engine().sample_rate()?.into();
+        let frames = fade_length_mili.saturating_mul(sample_rate as u64) / 1000;
+        self.set_fade_curve_pcm(vol_start, vol_end, frames, curve)
+    }
+
+    /// Returns the volume of the fade set with [`Sound::set_fade_curve_pcm`].
+    ///
+    /// `1.0` if no curved fade was set.
+    pub fn current_fade_curve_volume(&self) -> f32 {
+        self.fade.as_ref().map_or(1.0, |fade| fade.current_volume())
+    }
+
     /// Sets the scheduled start time in PCM frames.
     pub fn set_start_time_pcm(&mut self, abs_time_frames: u64) {
         sound_ffi::ma_sound_set_start_time_in_pcm_frames(self, abs_time_frames);
@@ -435,6 +803,50 @@ impl Sound {
         sound_ffi::ma_sound_set_looping(self, looping);
     }
 
+    /// Crossfades the loop seam over `crossfade_frames` so that looping does not click.
+    ///
+    /// Looping jumps from the loop end straight back to the loop start, which clicks unless
+    /// the waveform happens to line up. This copies the sound's audio into memory and blends
+    /// the last `crossfade_frames` of the loop with the audio leading into the loop start.
+    /// If the loop starts fewer than `crossfade_frames` into the sound, the start of the loop
+    /// is faded in instead, and the loop start moves forward by `crossfade_frames`.
+    ///
+    /// The sound then plays from the copy. It keeps its settings, cursor, output attachment and
+    /// end callback, and looping is enabled. Changing the loop points afterwards does not move
+    /// the crossfade.
+    ///
+    /// The sound must be stopped and its length must be known, so streamed sounds are not
+    /// supported. `crossfade_frames` must be between 1 and half the loop length.
+    pub fn set_seamless_loop(&mut self, crossfade_frames: u64) -> MaResult<()> {
+        if self.is_playing() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "set_seamless_loop requires a stopped sound",
+            )));
+        }
+        let source = seamless_loop::crossfaded_source(self, crossfade_frames)?;
+        let mut replacement = self.engine().new_sound_with_source_internal(
+            SoundFlags::NO_DEFAULT_ATTACHMENT,
+            None,
+            &source,
+        )?;
+        seamless_loop::copy_state(self, &mut replacement)?;
+        if let Some((target, bus)) = node_ffi::output_attachment(&self.as_node(), 0) {
+            replacement
+                .as_node()
+                .attach_output(0, &mut NodeRef::from_ptr(target), bus)?;
+        }
+        self.install_end_callback(replacement.to_raw())?;
+
+        core::mem::swap(&mut self.inner, &mut replacement.inner);
+        if let Some((tracker, _)) = &self.marker_tracker {
+            tracker.set_sound(self.inner.0);
+        }
+        // Uninitializes the previous sound before the source it may be reading is dropped
+        drop(replacement);
+        self.seamless_source = Some(source);
+        Ok(())
+    }
+
     /// Returns `true` if playback has reached the end.
     pub fn ended(&self) -> bool {
         sound_ffi::ma_sound_at_end(self)
@@ -476,20 +888,93 @@ impl Sound {
     }
 
     pub fn set_end_callback(&mut self) -> MaResult<EndNotifier> {
-        let notifier = EndNotifier::new();
+        // The stream error policy owns the callback and sets the flag once the sound ends
+        let notifier = match &self.stream_watch {
+            Some(watch) => EndNotifier::from_flag(watch.end_flag()),
+            None => EndNotifier::new(),
+        };
         self.end_notifier = Some(notifier.clone());
+        self.install_end_callback(self.to_raw())?;
 
-        let user_data = notifier.as_user_data_ptr();
+        Ok(notifier)
+    }
 
-        let res = unsafe {
-            sys::ma_sound_set_end_callback(
-                self.to_raw(),
-                Some(crate::sound::notifier::on_end_callback),
-                user_data,
-            )
+    /// Sets what the sound does when reading its stream fails mid-playback, and returns a
+    /// handle reporting the failures.
+    ///
+    /// Without a policy, a streamed sound whose file can't be read any further stops early
+    /// without an error. Calling this again changes the policy and returns another handle to
+    /// the same notifications. An [`EndNotifier`] of the sound keeps working, and fires once
+    /// the sound really ends.
+    ///
+    /// See [`stream_error`](crate::sound::stream_error) for details.
+    pub fn set_stream_error_policy(
+        &mut self,
+        policy: StreamErrorPolicy,
+    ) -> MaResult<StreamErrorNotifier> {
+        if let Some(watch) = &self.stream_watch {
+            watch.set_policy(policy);
+            return Ok(watch.notifier());
+        }
+        let end_flag = match &self.end_notifier {
+            Some(notifier) => notifier.flag(),
+            None => EndNotifier::new().flag(),
         };
-        MaudioError::check(res)?;
+        let watch = Arc::new(StreamWatch::new(policy, end_flag));
+        let notifier = watch.notifier();
+        self.stream_watch = Some(watch);
+        self.install_end_callback(self.to_raw())?;
+        Ok(notifier)
+    }
+
+    /// Returns the policy set with [`Sound::set_stream_error_policy`], if any.
+    pub fn stream_error_policy(&self) -> Option<StreamErrorPolicy> {
+        self.stream_watch.as_ref().map(|watch| watch.policy())
+    }
 
+    /// Returns the markers of the sound, sorted by position in PCM frames.
+    ///
+    /// Sounds loaded from a WAV file start with the cue points stored in the file, converted
+    /// to the rate the sound is decoded at. See [`Decoder::markers`].
+    ///
+    /// [`Decoder::markers`]: crate::data_source::sources::decoder::Decoder::markers
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Replaces the markers of the sound. Positions are in PCM frames of the sound's cursor.
+    ///
+    /// Must be called before [`Sound::marker_notifier`], which watches the markers set at
+    /// the time it is first called.
+    pub fn set_markers(&mut self, mut markers: Vec<Marker>) -> MaResult<()> {
+        if self.marker_tracker.is_some() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "set_markers must be called before marker_notifier",
+            )));
+        }
+        markers.sort_by_key(|m| m.position);
+        self.markers = markers;
+        Ok(())
+    }
+
+    /// Returns a notifier flagging each marker the sound plays past.
+    ///
+    /// The first call inserts a pass-through node after the sound, which compares the cursor
+    /// between two processed blocks. Later calls return another handle to the same notifier.
+    ///
+    /// Crossing the loop end while looping reports the markers at the end of the loop and
+    /// at its start. Seeking while the sound is stopped does not report anything, but seeking
+    /// forward while it plays reports the markers that were skipped.
+    pub fn marker_notifier(&mut self) -> MaResult<MarkerNotifier> {
+        if let Some((_, notifier)) = &self.marker_tracker {
+            return Ok(notifier.clone());
+        }
+        let notifier = MarkerNotifier::new(self.markers.clone());
+        let graph = self.node_graph();
+        let channels = self.as_node().output_channels(0);
+        let tracker = MarkerTracker::new(&graph, channels, self.to_raw(), notifier.state())?;
+        self.insert_after(tracker.as_node())?;
+        self.marker_tracker = Some((tracker, notifier.clone()));
         Ok(notifier)
     }
 }
@@ -502,15 +987,67 @@ impl Sound {
         fence: Option<Fence>,
         end_notifier: Option<EndNotifier>,
     ) -> Self {
+        rt_check::check("creating a sound");
+        engine.sound_count.fetch_add(1, Ordering::Relaxed);
+        engine.sounds.register(inner);
         Sound {
-            inner,
+            inner: RawSound(inner),
             _engine: engine,
             _not_sync: PhantomData,
             _fence: fence,
             end_notifier,
+            time_stretch: None,
+            fade: None,
+            occlusion: None,
+            channel_mix: None,
+            seamless_source: None,
+            markers: Vec::new(),
+            marker_tracker: None,
+            priority: 0,
+            stream_watch: None,
+            resource: None,
+            resource_use: None,
         }
     }
 
+    pub(crate) fn engine_inner(&self) -> &Arc<EngineInner> {
+        &self._engine
+    }
+
+    // Points the end callback of `sound` at the stream error policy or the end notifier
+    fn install_end_callback(&self, sound: *mut sys::ma_sound) -> MaResult<()> {
+        let (callback, user_data): (sys::ma_sound_end_proc, _) =
+            match (&self.stream_watch, &self.end_notifier) {
+                (Some(watch), _) => (
+                    Some(crate::sound::stream_error::on_stream_end_callback),
+                    watch.as_user_data_ptr(),
+                ),
+                (None, Some(notifier)) => (
+                    Some(crate::sound::notifier::on_end_callback),
+                    notifier.as_user_data_ptr(),
+                ),
+                (None, None) => return Ok(()),
+            };
+        let res = unsafe { sys::ma_sound_set_end_callback(sound, callback, user_data) };
+        MaudioError::check(res)
+    }
+
+    // Keeps the markers and returns the metadata in frames of the sound's cursor.
+    // The resource manager decodes at the engine's rate unless told otherwise.
+    pub(crate) fn load_wav_metadata(&mut self, path: &Path) -> WavMetadata {
+        let sample_rate = match self.data_format() {
+            Ok(format) => format.sample_rate.into(),
+            Err(_) => self.engine().sample_rate_u32(),
+        };
+        let metadata = WavMetadata::read_file(path).rescaled(sample_rate);
+        self.markers = metadata.markers.clone();
+        metadata
+    }
+
+    pub(crate) fn copy_markers_from(&mut self, other: &Sound) {
+        self.markers = other.markers.clone();
+    }
+
     pub(crate) fn init_from_file_internal(
         sound: *mut sys::ma_sound,
         engine: &Engine,
@@ -537,14 +1074,78 @@ impl Sound {
         #[cfg(not(any(unix, windows)))]
         compile_error!("init_sound_from_file is only supported on unix and windows");
     }
+
+    // Routes the sound through a pitch shifter: sound -> shifter -> previous output
+    fn insert_time_stretch(&self, semitones: f32) -> MaResult<PitchShiftNode> {
+        let graph = self.node_graph();
+        let channels = self.as_node().output_channels(0);
+        let sample_rate = self.engine().sample_rate()?;
+        let shifter = PitchShiftNodeBuilder::new(&graph, channels, sample_rate)
+            .semitones(semitones)
+            .build()?;
+        self.insert_after(shifter.as_node())?;
+        Ok(shifter)
+    }
+
+    fn set_occlusion_amounts(&mut self, occlusion: f32, obstruction: f32) -> MaResult<()> {
+        if !occlusion.is_finite() || !obstruction.is_finite() {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let occlusion = occlusion.clamp(0.0, 1.0);
+        let obstruction = obstruction.clamp(0.0, 1.0);
+        if self.occlusion.is_none() {
+            if occlusion == 0.0 && obstruction == 0.0 {
+                return Ok(());
+            }
+            let graph = self.node_graph();
+            let channels = self.as_node().output_channels(0);
+            let filter = Occlusion::new(&graph, channels, self.engine().sample_rate()?)?;
+            self.insert_after(filter.node().as_node())?;
+            self.occlusion = Some(filter);
+        }
+        match &mut self.occlusion {
+            Some(filter) => filter.set_amounts(occlusion, obstruction),
+            None => Ok(()),
+        }
+    }
+
+    // Inserts the channel gains on first use
+    fn channel_mix(&mut self) -> MaResult<&mut ChannelMix> {
+        let mix = match self.channel_mix.take() {
+            Some(mix) => mix,
+            None => {
+                let graph = self.node_graph();
+                let channels = self.as_node().output_channels(0);
+                let mix = ChannelMix::new(&graph, channels)?;
+                self.insert_after(mix.node().as_node())?;
+                mix
+            }
+        };
+        Ok(self.channel_mix.insert(mix))
+    }
+
+    // Inserts `node` between the sound and the node its output is attached to
+    fn insert_after(&self, mut node: NodeRef<'_>) -> MaResult<()> {
+        let target = node_ffi::output_attachment(&self.as_node(), 0);
+        self.as_node().attach_output(0, &mut node, 0)?;
+        if let Some((target, bus)) = target {
+            node.attach_output(0, &mut NodeRef::from_ptr(target), bus)?;
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Sound {
     fn drop(&mut self) {
+        // The tracker reads the sound from the audio thread
+        drop(self.marker_tracker.take());
+        self._engine.sounds.unregister(self.to_raw());
         unsafe {
             sys::ma_sound_uninit(self.to_raw());
         }
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
+        self._engine.sound_count.fetch_sub(1, Ordering::Relaxed);
     }
 }
 
@@ -571,7 +1172,7 @@ pub fn sound_volume_linear_to_db(factor: f32) -> f32 {
 pub(crate) mod sound_ffi {
     use maudio_sys::ffi as sys;
 
-    use crate::audio::channels::Channel;
+    use crate::audio::channels::ChannelLayout;
     use crate::audio::math::vec3::Vec3;
     use crate::audio::pan::PanMode;
     use crate::audio::spatial::{
@@ -776,16 +1377,6 @@ pub(crate) mod sound_ffi {
         res.try_into()
     }
 
-    #[inline]
-    pub fn ma_sound_set_pitch(sound: &mut Sound, pitch: f32) {
-        unsafe { sys::ma_sound_set_pitch(sound.to_raw(), pitch) }
-    }
-
-    #[inline]
-    pub fn ma_sound_get_pitch(sound: &Sound) -> f32 {
-        unsafe { sys::ma_sound_get_pitch(sound.to_raw() as *const _) }
-    }
-
     #[inline]
     pub fn ma_sound_set_spatialization_enabled(sound: &mut Sound, enabled: bool) {
         let enabled = enabled as sys::ma_bool32;
@@ -1187,9 +1778,7 @@ pub(crate) mod sound_ffi {
         MaudioError::check(res)?;
 
         // Could cast when passing the ptr to miniaudio, but copying should be fine here
-        let mut channel_map: Vec<Channel> =
-            channel_map_raw.into_iter().map(Channel::from_raw).collect();
-        channel_map.truncate(channels as usize);
+        let channel_map = ChannelLayout::from_raw_count(&channel_map_raw, channels)?;
 
         Ok(DataFormat {
             format: format_raw.try_into()?,
@@ -1252,15 +1841,22 @@ pub(crate) mod sound_ffi {
 #[cfg(test)]
 mod test {
     use crate::{
+        audio::sample_rate::SampleRate,
         audio::{
+            dsp::shaped_fader::FadeCurve,
             math::vec3::Vec3,
-            pan::PanMode,
+            pan::{PanLaw, PanMode},
             spatial::{attenuation::AttenuationModel, cone::Cone, positioning::Positioning},
         },
         data_source::sources::buffer::AudioBufferBuilder,
-        engine::{node_graph::nodes::NodeOps, Engine},
+        engine::{engine_builder::EngineBuilder, node_graph::nodes::NodeOps, test_engine, Engine},
         sound::sound_builder::SoundBuilder,
+        test_assets::{
+            temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le, wav_with_metadata,
+        },
     };
+    use std::sync::atomic::Ordering;
 
     fn assert_f32_eq(a: f32, b: f32) {
         assert!(
@@ -1326,6 +1922,27 @@ mod test {
         let _ = sound.is_playing();
     }
 
+    #[test]
+    fn test_sound_control_from_other_thread() {
+        fn assert_send<T: Send>() {}
+        assert_send::<super::Sound>();
+
+        let engine = Engine::new_for_tests().unwrap();
+        let sound = engine.new_sound().unwrap();
+
+        let sound = std::thread::spawn(move || {
+            let mut sound = sound;
+            sound.set_volume(0.25);
+            sound.play_sound().unwrap();
+            sound
+        })
+        .join()
+        .unwrap();
+
+        assert_f32_eq(sound.volume(), 0.25);
+        drop(sound);
+    }
+
     #[test]
     fn test_sound_stop_with_fade_smoke() {
         let engine = Engine::new_for_tests().unwrap();
@@ -1337,6 +1954,67 @@ mod test {
         sound.stop_at_with_fade_millis(10).unwrap();
     }
 
+    #[test]
+    fn test_sound_fade_out_and_destroy() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 4800]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_looping(true);
+        sound.play_sound().unwrap();
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(256).unwrap();
+
+        sound.fade_out_and_destroy(10).unwrap();
+        assert_eq!(engine.0.sound_count.load(Ordering::Relaxed), 1);
+
+        // 10 ms is 480 frames. Read in small blocks, a sound whose stop time falls inside
+        // a block is not rendered for that block
+        let mut out = Vec::new();
+        for _ in 0..16 {
+            out.extend_from_slice(reader.read_pcm_frames(64).unwrap().as_ref());
+        }
+        assert!(out[0] > 0.4);
+        assert!(out[240] > 0.1 && out[240] < 0.4);
+        test_engine::assert_silent(&out[600..]);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while engine.0.sound_count.load(Ordering::Relaxed) != 0 {
+            assert!(std::time::Instant::now() < deadline, "sound was not freed");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        // Not playing, freed right away
+        let sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.fade_out_and_destroy(10).unwrap();
+        assert_eq!(engine.0.sound_count.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_sound_fade_out_and_destroy_frees_unread_engine() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 4800]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_looping(true);
+        sound.play_sound().unwrap();
+
+        // Nothing reads the engine, so the fade never ends
+        sound.fade_out_and_destroy(10).unwrap();
+        let weak = std::sync::Arc::downgrade(&engine.0);
+        drop(engine);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while weak.upgrade().is_some() {
+            assert!(std::time::Instant::now() < deadline, "engine was not freed");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
     #[test]
     fn test_sound_volume_roundtrip() {
         let engine = Engine::new_for_tests().unwrap();
@@ -1373,6 +2051,239 @@ mod test {
         assert_eq!(sound.pan_mode().unwrap(), PanMode::Balance);
     }
 
+    #[test]
+    fn test_sound_playback_rate_preserve_pitch() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let samples: Vec<f32> = (0..48_000)
+            .map(|i| (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48_000.0).sin() * 0.5)
+            .collect();
+        let buffer = AudioBufferBuilder::build_f32(1, &samples).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+
+        assert!(sound.set_playback_rate_preserve_pitch(0.0).is_err());
+        sound.set_playback_rate_preserve_pitch(2.0).unwrap();
+        assert_f32_eq(sound.pitch(), 2.0);
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(9600).unwrap();
+
+        // Twice as fast
+        let cursor = sound.cursor_pcm().unwrap();
+        assert!((19_000..=19_400).contains(&cursor), "cursor {cursor}");
+
+        // Same pitch: 220 Hz crosses zero 44 times in 0.1 s
+        let crossings = out.as_ref()[4800..]
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        assert!((40..=48).contains(&crossings), "crossings {crossings}");
+    }
+
+    #[test]
+    fn test_sound_fade_curve() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(1, &[1.0f32; 2048]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        assert_eq!(sound.current_fade_curve_volume(), 1.0);
+
+        sound
+            .set_fade_curve_pcm(1.0, 0.0, 480, FadeCurve::SCurve)
+            .unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(600).unwrap();
+        let out = out.as_ref();
+        assert!((out[239] - 0.5).abs() < 0.01);
+        assert!(out[480..].iter().all(|s| *s == 0.0));
+        assert_eq!(sound.current_fade_curve_volume(), 0.0);
+    }
+
+    #[test]
+    fn test_sound_occlusion_filters_and_attenuates() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        // 6 kHz tone
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| (2.0 * std::f32::consts::PI * 6000.0 * i as f32 / 48_000.0).sin() * 0.5)
+            .collect();
+        let buffer = AudioBufferBuilder::build_f32(1, &samples).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        assert_eq!(sound.occlusion(), 0.0);
+        assert!(sound.set_occlusion(f32::NAN).is_err());
+
+        sound.set_obstruction(0.5).unwrap();
+        sound.set_occlusion(2.0).unwrap();
+        assert_eq!(sound.occlusion(), 1.0);
+        assert_eq!(sound.obstruction(), 0.5);
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(2400).unwrap();
+        let occluded = test_engine::rms(&out.as_ref()[480..]);
+        assert!(occluded < 0.01, "rms {occluded}");
+
+        sound.set_occlusion(0.0).unwrap();
+        sound.set_obstruction(0.0).unwrap();
+        let out = reader.read_pcm_frames(2400).unwrap();
+        let clear = test_engine::rms(&out.as_ref()[480..]);
+        assert!(clear > 0.3, "rms {clear}");
+    }
+
+    #[test]
+    fn test_sound_channel_gain_mutes_one_channel() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(2, &[0.5f32; 4800]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        assert_eq!(sound.channel_gain(0), Some(1.0));
+        assert_eq!(sound.channel_gain(2), None);
+        assert!(sound.set_channel_gain(2, 0.0).is_err());
+
+        sound.set_channel_gain(0, 0.0).unwrap();
+        assert!(sound.set_channel_gain(1, -0.5).is_err());
+        assert_eq!(sound.channel_gain(0), Some(0.0));
+        assert_eq!(sound.channel_gain(1), Some(1.0));
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(512).unwrap();
+        for frame in out.as_ref().chunks_exact(2).skip(1) {
+            assert_eq!(frame[0], 0.0);
+            assert!((frame[1] - 0.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_sound_pan_law_replaces_miniaudio_panning() {
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let buffer = AudioBufferBuilder::build_f32(2, &[0.5f32; 4800]).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound.set_pan(0.5);
+        assert!(sound.pan_law().is_none());
+
+        sound.set_pan_law(Some(PanLaw::Linear)).unwrap();
+        assert!(matches!(sound.pan_law(), Some(PanLaw::Linear)));
+        assert_eq!(sound.pan(), 0.5);
+        sound.set_pan(-1.0);
+        sound.set_channel_gain(0, 0.5).unwrap();
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(256).unwrap();
+        for frame in out.as_ref().chunks_exact(2).skip(1) {
+            assert!((frame[0] - 0.25).abs() < 1e-5);
+            assert_eq!(frame[1], 0.0);
+        }
+
+        sound.set_pan_law(None).unwrap();
+        assert!(sound.pan_law().is_none());
+        assert_eq!(sound.pan(), -1.0);
+        assert_eq!(sound.channel_gain(0), Some(0.5));
+
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        assert!(sound.set_pan_law(Some(PanLaw::ConstantPower)).is_err());
+    }
+
+    #[test]
+    fn test_sound_seamless_loop_removes_the_seam() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        // A ramp jumps from 1.0 back to 0.0 at the loop seam.
+        let ramp: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+        let buffer = AudioBufferBuilder::build_f32(1, &ramp).unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.set_spatialization(false);
+        sound.set_volume(0.5);
+
+        assert!(sound.set_seamless_loop(0).is_err());
+        assert!(sound.set_seamless_loop(501).is_err());
+        sound.set_seamless_loop(100).unwrap();
+        assert!(sound.looping());
+        assert_f32_eq(sound.volume(), 0.5);
+        assert_eq!(sound.data_source().loop_point_in_pcm_frames(), 100..1000);
+
+        sound.play_sound().unwrap();
+        assert!(sound.set_seamless_loop(100).is_err());
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(3000).unwrap();
+        let out = out.as_ref();
+        let max_step = out[1..]
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_step < 0.02, "max step {max_step}");
+        // Never drops back to the start of the ramp.
+        assert!(out[1000..].iter().all(|s| *s > 0.04));
+    }
+
+    #[test]
+    fn test_sound_wav_markers_notify_when_crossed() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let wav = wav_with_metadata(
+            &wav_i16_le(1, SampleRate::Sr24000, &[1000i16; 4000]),
+            &[
+                (1, 50, Some("start")),
+                (2, 1000, None),
+                (3, 2500, Some("end")),
+            ],
+            &[],
+        );
+        let file = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(file.path(), wav).unwrap();
+
+        let mut sound = engine.new_sound_from_file(file.path()).unwrap();
+        sound.set_spatialization(false);
+        // Decoded at the engine's rate
+        let positions: Vec<u64> = sound.markers().iter().map(|m| m.position).collect();
+        assert_eq!(positions, [100, 2000, 5000]);
+
+        let notifier = sound.marker_notifier().unwrap();
+        assert!(sound.set_markers(Vec::new()).is_err());
+        assert!(!notifier.peek());
+        sound.play_sound().unwrap();
+
+        let mut reader = engine.try_acquire_reader().unwrap();
+        reader.read_pcm_frames(1024).unwrap();
+        let crossed = notifier.take();
+        assert_eq!(crossed.len(), 1);
+        assert_eq!(crossed[0].label.as_deref(), Some("start"));
+        assert!(!notifier.peek());
+
+        reader.read_pcm_frames(4096).unwrap();
+        let ids: Vec<u32> = notifier.take().iter().map(|m| m.id).collect();
+        assert_eq!(ids, [2, 3]);
+    }
+
     #[test]
     fn test_sound_pitch_roundtrip() {
         let engine = Engine::new_for_tests().unwrap();
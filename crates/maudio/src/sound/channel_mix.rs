@@ -0,0 +1,87 @@
+//! Per-channel gains behind `Sound::set_channel_gain` and `Sound::set_pan_law`.
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::pan::PanLaw,
+    engine::node_graph::{
+        nodes::effects::channel_gain::{ChannelGainNode, ChannelGainNodeBuilder},
+        AsNodeGraphPtr,
+    },
+    MaResult, MaudioError,
+};
+
+/// A [`ChannelGainNode`] inserted after a sound.
+///
+/// The node applies the gains set by the user multiplied by the gains of the pan law, if
+/// one replaces miniaudio's panning.
+pub(crate) struct ChannelMix {
+    node: ChannelGainNode,
+    gains: Vec<f32>,
+    pan: Option<(PanLaw, f32)>,
+}
+
+impl ChannelMix {
+    pub(crate) fn new<N: AsNodeGraphPtr>(node_graph: &N, channels: u32) -> MaResult<Self> {
+        let node = ChannelGainNodeBuilder::new(node_graph, channels).build()?;
+        Ok(Self {
+            node,
+            gains: vec![1.0; channels as usize],
+            pan: None,
+        })
+    }
+
+    pub(crate) fn node(&self) -> &ChannelGainNode {
+        &self.node
+    }
+
+    pub(crate) fn gain(&self, channel: u32) -> Option<f32> {
+        self.gains.get(channel as usize).copied()
+    }
+
+    pub(crate) fn set_gain(&mut self, channel: u32, gain: f32) -> MaResult<()> {
+        if channel as usize >= self.gains.len() || !gain.is_finite() || gain < 0.0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        self.gains[channel as usize] = gain;
+        self.refresh()
+    }
+
+    pub(crate) fn pan_law(&self) -> Option<PanLaw> {
+        self.pan.map(|(law, _)| law)
+    }
+
+    /// Returns the pan, while a pan law is set.
+    pub(crate) fn pan(&self) -> Option<f32> {
+        self.pan.map(|(_, pan)| pan)
+    }
+
+    pub(crate) fn set_pan_law(&mut self, law: Option<PanLaw>, pan: f32) -> MaResult<()> {
+        self.pan = law.map(|law| (law, pan));
+        self.refresh()
+    }
+
+    /// Does nothing unless a pan law is set.
+    pub(crate) fn set_pan(&mut self, pan: f32) -> MaResult<()> {
+        match &mut self.pan {
+            Some((_, current)) => *current = pan,
+            None => return Ok(()),
+        }
+        self.refresh()
+    }
+
+    fn refresh(&mut self) -> MaResult<()> {
+        let (left, right) = match self.pan {
+            Some((law, pan)) => law.gains(pan),
+            None => (1.0, 1.0),
+        };
+        for (channel, gain) in self.gains.iter().enumerate() {
+            let pan_gain = match channel {
+                0 => left,
+                1 => right,
+                _ => 1.0,
+            };
+            self.node.set_gain(channel as u32, gain * pan_gain)?;
+        }
+        Ok(())
+    }
+}
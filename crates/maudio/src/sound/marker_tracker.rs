@@ -0,0 +1,132 @@
+//! Pass-through node behind `Sound::marker_notifier` that flags the markers the sound plays past.
+use std::sync::{
+    atomic::{AtomicPtr, Ordering},
+    Arc,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::node_graph::{
+        node_builder::NodeBuilder,
+        node_on_process::{Effect, EffectCallback, InputBusses, OutputBusses},
+        nodes::{Node, NodeRef},
+        AsNodeGraphPtr,
+    },
+    sound::notifier::MarkerState,
+    MaResult,
+};
+
+/// A node inserted after a sound that compares the sound's cursor between two callbacks.
+///
+/// The node runs after the sound in the graph, so the cursor it reads already includes the
+/// frames of the current block.
+pub(crate) struct MarkerTracker {
+    node: Node<Effect<MarkerProcessor>>,
+    sound: Arc<AtomicPtr<sys::ma_sound>>,
+}
+
+impl MarkerTracker {
+    pub(crate) fn new<N: AsNodeGraphPtr>(
+        node_graph: &N,
+        channels: u32,
+        sound: *mut sys::ma_sound,
+        state: Arc<MarkerState>,
+    ) -> MaResult<Self> {
+        let mut last_cursor = 0;
+        unsafe { sys::ma_sound_get_cursor_in_pcm_frames(sound, &mut last_cursor) };
+        let sound = Arc::new(AtomicPtr::new(sound));
+        let processor = MarkerProcessor {
+            sound: sound.clone(),
+            state,
+            last_cursor,
+        };
+        let mut builder = NodeBuilder::effect();
+        builder
+            .set_in_channel_count(0, channels)
+            .set_out_channel_count(0, channels);
+        let node = builder.build(node_graph, processor)?;
+        Ok(Self { node, sound })
+    }
+
+    pub(crate) fn as_node(&self) -> NodeRef<'_> {
+        self.node.as_node()
+    }
+
+    /// Points the tracker at another `ma_sound`, used when a sound swaps its inner sound.
+    pub(crate) fn set_sound(&self, sound: *mut sys::ma_sound) {
+        self.sound.store(sound, Ordering::Release);
+    }
+}
+
+struct MarkerProcessor {
+    sound: Arc<AtomicPtr<sys::ma_sound>>,
+    state: Arc<MarkerState>,
+    last_cursor: u64,
+}
+
+impl MarkerProcessor {
+    fn track(&mut self) {
+        let sound = self.sound.load(Ordering::Acquire);
+        if sound.is_null() {
+            return;
+        }
+        let mut cursor = 0;
+        let res = unsafe { sys::ma_sound_get_cursor_in_pcm_frames(sound, &mut cursor) };
+        if res != sys::ma_result_MA_SUCCESS {
+            return;
+        }
+        let from = core::mem::replace(&mut self.last_cursor, cursor);
+        // Seeks while stopped are not crossings. A sound that just reached its end is
+        // already stopped, but still played the last block.
+        let moving =
+            unsafe { sys::ma_sound_is_playing(sound) == 1 || sys::ma_sound_at_end(sound) == 1 };
+        if !moving {
+            return;
+        }
+
+        if cursor >= from {
+            self.fire_range(from, cursor);
+        } else if unsafe { sys::ma_sound_is_looping(sound) } == 1 {
+            // Wrapped around: the end of the loop, then its start
+            let (mut loop_begin, mut loop_end) = (0, 0);
+            unsafe {
+                sys::ma_data_source_get_loop_point_in_pcm_frames(
+                    sys::ma_sound_get_data_source(sound),
+                    &mut loop_begin,
+                    &mut loop_end,
+                );
+            }
+            self.fire_range(from, loop_end.max(from));
+            self.fire_range(loop_begin.min(cursor), cursor);
+        }
+    }
+
+    // Flags the markers in `from..to`
+    fn fire_range(&self, from: u64, to: u64) {
+        for (i, position) in self.state.positions().enumerate() {
+            if (from..to).contains(&position) {
+                self.state.fire(i);
+            }
+        }
+    }
+}
+
+impl EffectCallback for MarkerProcessor {
+    fn on_audio(&mut self, input: &InputBusses, output: &mut OutputBusses) -> MaResult<u32> {
+        self.track();
+
+        let Some(frames) = input.frame_count(0) else {
+            if let Some(out) = output.get_mut_bus(0) {
+                out.fill(0.0);
+            }
+            return Ok(output.frame_count(0).unwrap_or(0));
+        };
+        let (Some(frames_in), Some(frames_out)) = (input.get_bus(0), output.get_mut_bus(0)) else {
+            return Ok(0);
+        };
+        let len = frames_in.len().min(frames_out.len());
+        frames_out[..len].copy_from_slice(&frames_in[..len]);
+        Ok(frames)
+    }
+}
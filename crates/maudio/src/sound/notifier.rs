@@ -1,4 +1,4 @@
-//! Notification for when a sound reaches the end.
+//! Notifications for when a sound reaches the end or crosses a marker.
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
@@ -6,6 +6,8 @@ use std::sync::{
 
 use maudio_sys::ffi as sys;
 
+use crate::data_source::sources::decoder::wav_metadata::Marker;
+
 /// A lightweight notification handle that becomes `true` when a sound finishes playback.
 ///
 /// The audio thread sets the flag when playback ends. You can then:
@@ -65,11 +67,100 @@ impl EndNotifier {
         }
     }
 
+    pub(crate) fn from_flag(flag: Arc<AtomicBool>) -> Self {
+        Self { flag }
+    }
+
+    pub(crate) fn flag(&self) -> Arc<AtomicBool> {
+        self.flag.clone()
+    }
+
     pub(crate) fn as_user_data_ptr(&self) -> *mut core::ffi::c_void {
         std::sync::Arc::as_ptr(&self.flag) as *mut core::ffi::c_void
     }
 }
 
+/// A notification handle for the markers a sound plays past.
+///
+/// Created with [`Sound::marker_notifier()`](crate::sound::Sound::marker_notifier()). The audio
+/// thread flags each marker the sound's cursor moves past. Markers are flags, not a queue: a
+/// marker crossed several times between two calls to [`take()`](MarkerNotifier::take()) is
+/// reported once.
+///
+/// Cloning a `MarkerNotifier` creates another handle to the same flags.
+#[derive(Clone)]
+pub struct MarkerNotifier {
+    state: Arc<MarkerState>,
+}
+
+pub(crate) struct MarkerState {
+    markers: Vec<Marker>,
+    fired: Vec<AtomicBool>,
+}
+
+impl MarkerState {
+    pub(crate) fn positions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.markers.iter().map(|m| m.position)
+    }
+
+    pub(crate) fn fire(&self, index: usize) {
+        self.fired[index].store(true, Ordering::Relaxed);
+    }
+}
+
+impl MarkerNotifier {
+    pub(crate) fn new(markers: Vec<Marker>) -> Self {
+        let fired = markers.iter().map(|_| AtomicBool::new(false)).collect();
+        Self {
+            state: Arc::new(MarkerState { markers, fired }),
+        }
+    }
+
+    pub(crate) fn state(&self) -> Arc<MarkerState> {
+        self.state.clone()
+    }
+
+    /// Returns the markers watched by this notifier, sorted by position.
+    pub fn markers(&self) -> &[Marker] {
+        &self.state.markers
+    }
+
+    /// Returns `true` if any marker has been crossed since the last [`MarkerNotifier::take()`].
+    ///
+    /// This does **not** clear the notifications.
+    #[inline]
+    pub fn peek(&self) -> bool {
+        self.state.fired.iter().any(|f| f.load(Ordering::Relaxed))
+    }
+
+    /// Consumes the notifications and returns the markers that were crossed, sorted by
+    /// position.
+    pub fn take(&self) -> Vec<Marker> {
+        self.state
+            .markers
+            .iter()
+            .zip(&self.state.fired)
+            .filter(|(_, fired)| fired.swap(false, Ordering::Relaxed))
+            .map(|(marker, _)| marker.clone())
+            .collect()
+    }
+
+    /// Clears all pending notifications.
+    #[inline]
+    pub fn clear(&self) {
+        for fired in &self.state.fired {
+            fired.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Calls `f` for each marker crossed since the last call, consuming the notifications.
+    pub fn take_with<F: FnMut(&Marker)>(&self, mut f: F) {
+        for marker in self.take() {
+            f(&marker);
+        }
+    }
+}
+
 pub(crate) unsafe extern "C" fn on_end_callback(
     user_data: *mut core::ffi::c_void,
     _sound: *mut sys::ma_sound,
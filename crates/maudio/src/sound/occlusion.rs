@@ -0,0 +1,85 @@
+//! Low-pass filter and gain behind `Sound::set_occlusion` and `Sound::set_obstruction`.
+use crate::{
+    audio::sample_rate::SampleRate,
+    engine::node_graph::{
+        nodes::{
+            filters::lpf::{LpfNode, LpfNodeBuilder},
+            NodeOps,
+        },
+        AsNodeGraphPtr,
+    },
+    MaResult,
+};
+
+// Cutoff at an amount of 0, clamped below Nyquist for low sample rates.
+const OPEN_CUTOFF_HZ: f64 = 20_000.0;
+// Cutoff at an amount of 1.
+const CLOSED_CUTOFF_HZ: f64 = 300.0;
+// Attenuation of a fully occluded sound.
+const OCCLUSION_ATTENUATION_DB: f32 = 18.0;
+
+/// A second order low-pass node inserted after a sound.
+///
+/// Both amounts close the filter, the larger one wins. Occlusion (a wall between the
+/// listener and the sound) also lowers the volume, obstruction (something partly in the
+/// way) only muffles the sound.
+pub(crate) struct Occlusion {
+    node: LpfNode,
+    sample_rate: SampleRate,
+    occlusion: f32,
+    obstruction: f32,
+}
+
+impl Occlusion {
+    pub(crate) fn new<N: AsNodeGraphPtr + ?Sized>(
+        node_graph: &N,
+        channels: u32,
+        sample_rate: SampleRate,
+    ) -> MaResult<Self> {
+        let node = LpfNodeBuilder::new(
+            node_graph,
+            channels,
+            sample_rate,
+            open_cutoff(sample_rate),
+            2,
+        )
+        .build()?;
+        Ok(Self {
+            node,
+            sample_rate,
+            occlusion: 0.0,
+            obstruction: 0.0,
+        })
+    }
+
+    pub(crate) fn node(&self) -> &LpfNode {
+        &self.node
+    }
+
+    pub(crate) fn occlusion(&self) -> f32 {
+        self.occlusion
+    }
+
+    pub(crate) fn obstruction(&self) -> f32 {
+        self.obstruction
+    }
+
+    pub(crate) fn set_amounts(&mut self, occlusion: f32, obstruction: f32) -> MaResult<()> {
+        self.occlusion = occlusion;
+        self.obstruction = obstruction;
+
+        // Interpolated on a log scale, so equal steps sound like equal changes
+        let amount = occlusion.max(obstruction) as f64;
+        let open = open_cutoff(self.sample_rate);
+        let cutoff = open * (CLOSED_CUTOFF_HZ / open).powf(amount);
+        self.node.reinit(self.sample_rate, cutoff)?;
+
+        let gain = 10f32.powf(-OCCLUSION_ATTENUATION_DB * occlusion / 20.0);
+        self.node.set_output_bus_volume(0, gain)
+    }
+}
+
+fn open_cutoff(sample_rate: SampleRate) -> f64 {
+    let sample_rate: u32 = sample_rate.into();
+    OPEN_CUTOFF_HZ.min(sample_rate as f64 * 0.45)
+}
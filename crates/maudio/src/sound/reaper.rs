@@ -0,0 +1,90 @@
+//! Background thread behind `Sound::fade_out_and_destroy`, freeing sounds once they stopped.
+//!
+//! The sounds keep their engine alive while they fade out, so they are owned by a thread
+//! rather than by the engine itself. The thread is started when the first sound is handed
+//! over, and exits once every sound it owns has been freed.
+//!
+//! A fade only ends when the engine's time advances. Sounds whose engine has no handle left
+//! outside the reaper are freed without waiting, since nothing can read the engine anymore.
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{sound::Sound, ErrorKinds, MaResult, MaudioError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+struct Reaper {
+    sounds: Vec<Sound>,
+    running: bool,
+}
+
+static REAPER: Mutex<Reaper> = Mutex::new(Reaper {
+    sounds: Vec::new(),
+    running: false,
+});
+
+fn reaper() -> std::sync::MutexGuard<'static, Reaper> {
+    REAPER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Frees `sound` on the reaper thread once it is no longer playing.
+pub(crate) fn free_when_stopped(sound: Sound) -> MaResult<()> {
+    let mut state = reaper();
+    state.sounds.push(sound);
+    if state.running {
+        return Ok(());
+    }
+    let spawned = std::thread::Builder::new()
+        .name("maudio-reaper".into())
+        .spawn(run);
+    match spawned {
+        Ok(_) => {
+            state.running = true;
+            Ok(())
+        }
+        Err(_) => {
+            // Freed right away, cutting the fade short
+            let sound = state.sounds.pop();
+            drop(state);
+            drop(sound);
+            Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "failed to spawn the reaper thread",
+            )))
+        }
+    }
+}
+
+fn run() {
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let (stopped, done) = {
+            let mut state = reaper();
+            let sounds = std::mem::take(&mut state.sounds);
+            let free: Vec<bool> = sounds
+                .iter()
+                .map(|s| !s.is_playing() || orphaned(s, &sounds))
+                .collect();
+            let (stopped, playing): (Vec<_>, Vec<_>) =
+                sounds.into_iter().zip(free).partition(|(_, free)| *free);
+            state.sounds = playing.into_iter().map(|(s, _)| s).collect();
+            state.running = !state.sounds.is_empty();
+            (stopped, !state.running)
+        };
+        // Freed outside the lock, the last sound of an engine frees the engine too
+        drop(stopped);
+        if done {
+            return;
+        }
+    }
+}
+
+// True if the engine of `sound` is only kept alive by sounds owned by the reaper
+fn orphaned(sound: &Sound, sounds: &[Sound]) -> bool {
+    let owned = sounds
+        .iter()
+        .filter(|s| Arc::ptr_eq(&s._engine, &sound._engine))
+        .count();
+    Arc::strong_count(&sound._engine) <= owned
+}
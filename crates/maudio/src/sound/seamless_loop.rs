@@ -0,0 +1,149 @@
+//! In-memory copy of a sound with a crossfaded loop seam, behind `Sound::set_seamless_loop`.
+use std::f32::consts::FRAC_PI_2;
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    audio::{formats::Format, sample_rate::SampleRate},
+    data_source::{data_source_builder::DataSourceBuilder, data_source_ffi, DataSource},
+    sound::{sound_ffi, Sound},
+    ErrorKinds, MaResult, MaudioError,
+};
+
+pub(crate) type SeamlessSource = DataSource<f32, Vec<f32>>;
+
+/// Reads the whole sound into memory and crossfades the last `crossfade_frames` of its loop
+/// region into the audio that leads into the loop start.
+///
+/// Returns the new source with the loop points already set. The sound's cursor and looping
+/// flag are restored once the audio has been read.
+pub(crate) fn crossfaded_source(sound: &Sound, crossfade_frames: u64) -> MaResult<SeamlessSource> {
+    let mut source = sound.data_source();
+    let (format, channels, sample_rate, channel_map) =
+        data_source_ffi::ma_data_source_get_data_format_raw(&source)?;
+    if Format::try_from(format)? != Format::F32 {
+        return Err(MaudioError::new_ma_error(ErrorKinds::InvalidFormat));
+    }
+    // A source without a sample rate plays at the engine's rate.
+    let sample_rate = match sample_rate {
+        0 => sound.engine().sample_rate()?,
+        sr => SampleRate::try_from(sr)?,
+    };
+    let length = source.length_in_pcm_frames()?;
+    let loop_points = source.loop_point_in_pcm_frames();
+    let (begin, end) = (loop_points.start, loop_points.end.min(length));
+    if crossfade_frames == 0 || begin >= end || crossfade_frames > (end - begin) / 2 {
+        return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+    }
+
+    let cursor = source.cursor_in_pcm_frames()?;
+    let looping = source.looping();
+    // A looping source would wrap around at the loop end instead of reading to the end.
+    data_source_ffi::ma_data_source_set_looping(&mut source, false)?;
+    let read = data_source_ffi::ma_data_source_seek_to_pcm_frame(&mut source, 0).and_then(|_| {
+        data_source_ffi::ma_data_source_read_pcm_frames::<f32, _>(&mut source, length, channels)
+    });
+    data_source_ffi::ma_data_source_seek_to_pcm_frame(&mut source, cursor)?;
+    data_source_ffi::ma_data_source_set_looping(&mut source, looping)?;
+    let read = read?;
+
+    if (read.frames() as u64) < end {
+        return Err(MaudioError::new_ma_error(
+            ErrorKinds::ReadExceedsAvailability {
+                available: read.frames(),
+                read: end as usize,
+            },
+        ));
+    }
+    let mut data = read.data;
+    let loop_begin = crossfade_seam(
+        &mut data,
+        channels as usize,
+        begin as usize,
+        end as usize,
+        crossfade_frames as usize,
+    );
+
+    let mut seamless = DataSourceBuilder::new(channels, sample_rate)
+        .channel_map(channel_map)
+        .build_f32(data)?;
+    seamless.set_loop_point_in_pcm_frames(loop_begin as u64, end)?;
+    Ok(seamless)
+}
+
+/// Copies the playback and spatialization settings of `from` onto `to`.
+pub(crate) fn copy_state(from: &Sound, to: &mut Sound) -> MaResult<()> {
+    to.set_volume(from.volume());
+    to.set_pan(from.pan());
+    to.set_pan_mode(from.pan_mode()?);
+    to.set_time_scale_exempt(from.is_time_scale_exempt());
+    to.set_pitch(from.pitch());
+    to.set_spatialization(from.spatialization());
+    to.set_pinned_listener(from.pinned_listener());
+    to.set_positioning(from.positioning()?);
+    to.set_position(from.position());
+    to.set_direction(from.direction());
+    to.set_velocity(from.velocity());
+    to.set_attenuation(from.attenuation()?);
+    to.set_rolloff(from.rolloff());
+    to.set_min_gain(from.min_gain());
+    to.set_max_gain(from.max_gain());
+    to.set_min_distance(from.min_distance());
+    to.set_max_distance(from.max_distance());
+    to.set_cone(from.cone());
+    to.set_doppler_factor(from.doppler_factor());
+    to.set_directional_attenuation(sound_ffi::ma_sound_get_directional_attenuation_factor(from));
+    to.set_looping(true);
+    to.seek_to_frame(from.cursor_pcm()?)
+}
+
+/// Blends `fade` frames at the end of the loop `begin..end` with the frames leading into
+/// `begin`, so that jumping back to the loop start continues the audio that was faded in.
+///
+/// When there are fewer than `fade` frames before `begin`, the start of the loop is faded in
+/// instead and the loop start moves past it. Returns the new loop start.
+fn crossfade_seam(
+    data: &mut [f32],
+    channels: usize,
+    begin: usize,
+    end: usize,
+    fade: usize,
+) -> usize {
+    let (lead_in, loop_begin) = if begin >= fade {
+        (begin - fade, begin)
+    } else {
+        (begin, begin + fade)
+    };
+    for i in 0..fade {
+        // Equal power, ends fully on the lead-in so the jump lands on the next frame.
+        let t = (i + 1) as f32 / fade as f32 * FRAC_PI_2;
+        let (fade_in, fade_out) = (t.sin(), t.cos());
+        let tail = (end - fade + i) * channels;
+        let head = (lead_in + i) * channels;
+        for c in 0..channels {
+            data[tail + c] = data[tail + c] * fade_out + data[head + c] * fade_in;
+        }
+    }
+    loop_begin
+}
+
+#[cfg(test)]
+mod test {
+    use super::crossfade_seam;
+
+    #[test]
+    fn test_crossfade_seam_lead_in_and_loop_start() {
+        // Enough audio before the loop start: the loop points stay the same.
+        let mut data: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        assert_eq!(crossfade_seam(&mut data, 1, 8, 20, 4), 8);
+        assert!((data[19] - 7.0).abs() < 1e-5);
+        assert_eq!(data[15], 15.0);
+
+        // Loop from the start: the start of the loop is faded in and skipped afterwards.
+        let mut data: Vec<f32> = vec![1.0; 10].into_iter().chain(vec![0.0; 10]).collect();
+        assert_eq!(crossfade_seam(&mut data, 2, 0, 10, 2), 2);
+        assert!(data[16] > 0.5 && data[16] < 1.0);
+        assert!((data[18] - 1.0).abs() < 1e-6);
+        assert!((data[19] - 1.0).abs() < 1e-6);
+    }
+}
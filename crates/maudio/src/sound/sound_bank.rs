@@ -0,0 +1,473 @@
+//! Named sound events with randomized variations.
+//!
+//! A [`SoundBank`] maps event names (e.g. `"footstep_grass"`) to a [`SoundEvent`]: one or more
+//! assets, plus volume and pitch ranges. Every time an event is played, one of its assets is
+//! selected and a random volume and pitch are picked from the ranges, so repeated sounds do not
+//! all sound the same.
+//!
+//! Assets are file paths or names of data registered with the engine's resource manager.
+//! They are loaded and decoded when the bank is built.
+//!
+//! ```no_run
+//! # use maudio::engine::Engine;
+//! # use maudio::sound::sound_bank::{Selection, SoundBankBuilder, SoundEvent};
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut bank = SoundBankBuilder::new(&engine)
+//!     .event(
+//!         "footstep_grass",
+//!         SoundEvent::new(["grass_1.wav", "grass_2.wav", "grass_3.wav"])
+//!             .selection(Selection::RoundRobin)
+//!             .volume_range(0.8, 1.0)
+//!             .pitch_range(0.95, 1.05),
+//!     )
+//!     .event("jump", SoundEvent::new(["jump.wav"]).pitch_range(0.9, 1.1))
+//!     .build()?;
+//!
+//! bank.play("footstep_grass")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Serde
+//!
+//! With the `serde` feature, [`SoundBankDef`] and [`SoundEvent`] can be deserialized, so that
+//! banks can be defined in data files. Build the bank with [`SoundBankBuilder::from_def`].
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::Engine,
+    sound::{sound_flags::SoundFlags, Sound},
+    ErrorContext, ErrorKinds, MaResult, MaudioError,
+};
+
+/// How the asset of an event is selected each time it is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Selection {
+    /// A random asset. The same asset is not selected twice in a row, unless it is the only one.
+    #[default]
+    Random,
+    /// Each asset in order, starting over after the last one.
+    RoundRobin,
+}
+
+/// Definition of a sound event: the assets and how they are varied.
+///
+/// The volume and pitch ranges are inclusive. Both default to `1.0..=1.0` (no variation).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SoundEvent {
+    pub assets: Vec<String>,
+    pub selection: Selection,
+    pub volume: (f32, f32),
+    pub pitch: (f32, f32),
+}
+
+impl Default for SoundEvent {
+    fn default() -> Self {
+        Self {
+            assets: Vec::new(),
+            selection: Selection::Random,
+            volume: (1.0, 1.0),
+            pitch: (1.0, 1.0),
+        }
+    }
+}
+
+impl SoundEvent {
+    pub fn new<I, S>(assets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            assets: assets.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn selection(&mut self, selection: Selection) -> &mut Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Linear volume range. Must be `0.0 <= min <= max`.
+    pub fn volume_range(&mut self, min: f32, max: f32) -> &mut Self {
+        self.volume = (min, max);
+        self
+    }
+
+    /// Pitch range. Must be `0.0 < min <= max`.
+    pub fn pitch_range(&mut self, min: f32, max: f32) -> &mut Self {
+        self.pitch = (min, max);
+        self
+    }
+
+    fn validate(&self) -> MaResult<()> {
+        if self.assets.is_empty() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "sound event has no assets",
+            )));
+        }
+        let (min_vol, max_vol) = self.volume;
+        let (min_pitch, max_pitch) = self.pitch;
+        // Written to also reject NaN
+        let volume_ok = min_vol >= 0.0 && min_vol <= max_vol;
+        let pitch_ok = min_pitch > 0.0 && min_pitch <= max_pitch;
+        if !volume_ok || !pitch_ok {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        Ok(())
+    }
+}
+
+/// The events of a [`SoundBank`], by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SoundBankDef {
+    pub events: BTreeMap<String, SoundEvent>,
+}
+
+/// Builder for a [`SoundBank`].
+pub struct SoundBankBuilder<'a> {
+    engine: &'a Engine,
+    def: SoundBankDef,
+    seed: Option<u64>,
+}
+
+impl<'a> SoundBankBuilder<'a> {
+    pub fn new(engine: &'a Engine) -> Self {
+        Self::from_def(engine, SoundBankDef::default())
+    }
+
+    /// Starts from an existing definition, e.g. one loaded from a data file.
+    pub fn from_def(engine: &'a Engine, def: SoundBankDef) -> Self {
+        Self {
+            engine,
+            def,
+            seed: None,
+        }
+    }
+
+    /// Adds an event. An event with the same name is replaced.
+    pub fn event(&mut self, name: &str, event: &SoundEvent) -> &mut Self {
+        self.def.events.insert(name.to_string(), event.clone());
+        self
+    }
+
+    /// Seed for the random selection and variations. Useful for reproducible output.
+    ///
+    /// By default, the seed is taken from the system time.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn def(&self) -> &SoundBankDef {
+        &self.def
+    }
+
+    /// Loads every asset and builds the bank.
+    ///
+    /// Returns an error if an event has no assets, has invalid ranges, or an asset can not be loaded.
+    pub fn build(&self) -> MaResult<SoundBank> {
+        let mut events = HashMap::with_capacity(self.def.events.len());
+        for (name, event) in &self.def.events {
+            event.validate()?;
+            let templates = event
+                .assets
+                .iter()
+                .map(|asset| {
+                    self.engine.new_sound_with_file_internal(
+                        Path::new(asset),
+                        SoundFlags::DECODE,
+                        None,
+                        None,
+                    )
+                })
+                .collect::<MaResult<Vec<_>>>()?;
+            events.insert(
+                name.clone(),
+                BankEvent {
+                    def: event.clone(),
+                    templates,
+                    last: None,
+                },
+            );
+        }
+
+        let seed = self.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        Ok(SoundBank {
+            engine: Engine(self.engine.0.clone()),
+            events,
+            playing: Vec::new(),
+            rng: XorShift::new(seed),
+        })
+    }
+}
+
+/// A set of named sound events. See the [module docs](self).
+///
+/// Sounds started with [`SoundBank::play`] are owned by the bank until they end.
+/// Dropping the bank stops them.
+pub struct SoundBank {
+    engine: Engine,
+    events: HashMap<String, BankEvent>,
+    playing: Vec<Sound>,
+    rng: XorShift,
+}
+
+struct BankEvent {
+    def: SoundEvent,
+    // Fully loaded sounds, copied for every instance
+    templates: Vec<Sound>,
+    last: Option<usize>,
+}
+
+impl SoundBank {
+    /// Plays an event and forgets about it.
+    ///
+    /// Returns an error if no event is called `name`.
+    pub fn play(&mut self, name: &str) -> MaResult<()> {
+        self.playing.retain(|s| !s.ended());
+        let mut sound = self.new_sound(name)?;
+        sound.play_sound()?;
+        self.playing.push(sound);
+        Ok(())
+    }
+
+    /// Creates a sound for an event, with the variations applied, without starting it.
+    ///
+    /// Use this to position the sound or control it after it starts.
+    pub fn new_sound(&mut self, name: &str) -> MaResult<Sound> {
+        let event = self.events.get_mut(name).ok_or_else(|| {
+            MaudioError::from_ma_result(sys::ma_result_MA_DOES_NOT_EXIST)
+                .with_context(ErrorContext::Resource(name.to_owned()))
+        })?;
+
+        let count = event.templates.len();
+        let index = match (event.def.selection, event.last) {
+            (Selection::RoundRobin, Some(last)) => (last + 1) % count,
+            (Selection::RoundRobin, None) => 0,
+            (Selection::Random, Some(last)) if count > 1 => {
+                // Pick among the others, so the same asset is not repeated
+                let pick = self.rng.next_below(count - 1);
+                if pick >= last {
+                    pick + 1
+                } else {
+                    pick
+                }
+            }
+            (Selection::Random, _) => self.rng.next_below(count),
+        };
+        event.last = Some(index);
+
+        let mut sound = self
+            .engine
+            .clone_sound(&event.templates[index], SoundFlags::NONE)?;
+        let (min_vol, max_vol) = event.def.volume;
+        let (min_pitch, max_pitch) = event.def.pitch;
+        sound.set_volume(self.rng.next_range(min_vol, max_vol));
+        sound.set_pitch(self.rng.next_range(min_pitch, max_pitch));
+        Ok(sound)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.events.contains_key(name)
+    }
+
+    /// Returns the names of the events.
+    pub fn event_names(&self) -> impl Iterator<Item = &str> {
+        self.events.keys().map(String::as_str)
+    }
+
+    /// Number of sounds started with [`SoundBank::play`] that are still playing.
+    pub fn playing_count(&mut self) -> usize {
+        self.playing.retain(|s| !s.ended());
+        self.playing.len()
+    }
+
+    /// Stops every sound started with [`SoundBank::play`].
+    pub fn stop_all(&mut self) {
+        for mut sound in self.playing.drain(..) {
+            let _ = sound.stop_sound();
+        }
+    }
+}
+
+// xorshift64*. Only used for variations, so quality is not a concern
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15 | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Uniform in [min, max]
+    fn next_range(&mut self, min: f32, max: f32) -> f32 {
+        let t = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * t
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{resource::RmOps, Engine},
+        sound::sound_bank::{Selection, SoundBankBuilder, SoundEvent},
+        test_assets::wav_i16_le,
+    };
+
+    fn wav(len: usize) -> Vec<u8> {
+        let samples: Vec<i16> = (0..len).map(|i| (i % 1000) as i16).collect();
+        wav_i16_le(1, SampleRate::Sr44100, &samples)
+    }
+
+    #[test]
+    fn test_sound_bank_round_robin_and_ranges() {
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let (a, b) = (wav(100), wav(200));
+        let _ga = rm.register_encoded("bank_a.wav", &a).unwrap();
+        let _gb = rm.register_encoded("bank_b.wav", &b).unwrap();
+
+        let mut bank = SoundBankBuilder::new(&engine)
+            .event(
+                "step",
+                SoundEvent::new(["bank_a.wav", "bank_b.wav"])
+                    .selection(Selection::RoundRobin)
+                    .volume_range(0.5, 0.7)
+                    .pitch_range(0.9, 1.1),
+            )
+            .seed(7)
+            .build()
+            .unwrap();
+        assert!(bank.contains("step"));
+
+        let lengths: Vec<u64> = (0..4)
+            .map(|_| bank.new_sound("step").unwrap().length_pcm().unwrap())
+            .collect();
+        // Lengths are in engine frames, so only compare the order
+        assert!(lengths[0] < lengths[1]);
+        assert_eq!(lengths[0], lengths[2]);
+        assert_eq!(lengths[1], lengths[3]);
+
+        for _ in 0..20 {
+            let sound = bank.new_sound("step").unwrap();
+            assert!((0.5..=0.7).contains(&sound.volume()));
+            assert!((0.9..=1.1).contains(&sound.pitch()));
+        }
+
+        bank.play("step").unwrap();
+        assert!(bank.play("missing").is_err());
+        bank.stop_all();
+        assert_eq!(bank.playing_count(), 0);
+    }
+
+    #[test]
+    fn test_sound_bank_random_does_not_repeat() {
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let (a, b) = (wav(100), wav(200));
+        let _ga = rm.register_encoded("bank_rand_a.wav", &a).unwrap();
+        let _gb = rm.register_encoded("bank_rand_b.wav", &b).unwrap();
+
+        let mut bank = SoundBankBuilder::new(&engine)
+            .event(
+                "hit",
+                &SoundEvent::new(["bank_rand_a.wav", "bank_rand_b.wav"]),
+            )
+            .seed(42)
+            .build()
+            .unwrap();
+
+        let mut prev = bank.new_sound("hit").unwrap().length_pcm().unwrap();
+        for _ in 0..10 {
+            let len = bank.new_sound("hit").unwrap().length_pcm().unwrap();
+            assert_ne!(len, prev);
+            prev = len;
+        }
+    }
+
+    #[test]
+    fn test_sound_bank_build_validation() {
+        let engine = Engine::new_for_tests().unwrap();
+        let empty: [&str; 0] = [];
+        assert!(SoundBankBuilder::new(&engine)
+            .event("empty", &SoundEvent::new(empty))
+            .build()
+            .is_err());
+
+        let rm = engine.resource_manager().unwrap();
+        let a = wav(100);
+        let _ga = rm.register_encoded("bank_valid.wav", &a).unwrap();
+        assert!(SoundBankBuilder::new(&engine)
+            .event(
+                "bad_volume",
+                SoundEvent::new(["bank_valid.wav"]).volume_range(1.0, 0.5)
+            )
+            .build()
+            .is_err());
+        assert!(SoundBankBuilder::new(&engine)
+            .event(
+                "bad_pitch",
+                SoundEvent::new(["bank_valid.wav"]).pitch_range(0.0, 1.0)
+            )
+            .build()
+            .is_err());
+        assert!(SoundBankBuilder::new(&engine)
+            .event("missing", &SoundEvent::new(["bank_not_registered.wav"]))
+            .build()
+            .is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sound_bank_def_from_json() {
+        use crate::sound::sound_bank::SoundBankDef;
+
+        let json = r#"{
+            "events": {
+                "step": { "assets": ["bank_json.wav"], "selection": "RoundRobin", "pitch": [0.9, 1.1] }
+            }
+        }"#;
+        let def: SoundBankDef = serde_json::from_str(json).unwrap();
+        let step = &def.events["step"];
+        assert_eq!(step.selection, Selection::RoundRobin);
+        assert_eq!(step.volume, (1.0, 1.0));
+        assert_eq!(step.pitch, (0.9, 1.1));
+
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let a = wav(100);
+        let _ga = rm.register_encoded("bank_json.wav", &a).unwrap();
+        let mut bank = SoundBankBuilder::from_def(&engine, def).build().unwrap();
+        assert!(bank.new_sound("step").is_ok());
+    }
+}
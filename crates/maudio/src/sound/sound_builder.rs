@@ -25,6 +25,10 @@
 //! [`SoundBuilder::start_playing`] will call `sound.play_sound()` after initialization,
 //! but only when a real source is set. It is rejected for [`SoundBuilder::no_source`].
 //!
+//! ## Loudness normalization
+//! [`SoundBuilder::normalize_loudness`] sets the volume of a file-based sound so that it plays
+//! at a target loudness. Each file is measured once and the result is cached by the engine.
+//!
 //! ## End notifications
 //! [`SoundBuilder::with_end_notifier`] builds the sound and returns an [`EndNotifier`]
 //! that becomes `true` once the sound reaches the end callback.
@@ -33,8 +37,15 @@ use std::path::Path;
 use maudio_sys::ffi as sys;
 
 use crate::{
-    audio::{channels::MonoExpansionMode, math::vec3::Vec3},
-    data_source::{private_data_source, AsSourcePtr, DataSourceRef},
+    audio::{
+        channels::MonoExpansionMode,
+        math::vec3::Vec3,
+        spatial::{attenuation::AttenuationModel, cone::Cone},
+    },
+    data_source::{
+        data_source_ffi, private_data_source, sources::decoder::wav_metadata::WavMetadata,
+        AsSourcePtr, DataSourceRef,
+    },
     engine::{
         node_graph::nodes::{private_node, AsNodePtr},
         Engine,
@@ -43,7 +54,7 @@ use crate::{
         notifier::EndNotifier, sound_flags::SoundFlags, sound_group::SoundGroup, Sound, SoundSource,
     },
     util::fence::Fence,
-    AsRawRef, Binding, MaResult,
+    AsRawRef, Binding, MaResult, ResultContext,
 };
 
 /// Builder for constructing a [`Sound`]
@@ -116,13 +127,17 @@ impl<'a, 'b> AsRawRef for SoundBuilder<'a, 'b> {
 
 #[derive(Default)]
 pub(crate) struct SoundState {
+    pub(crate) attenuation: Option<AttenuationModel>,
     pub(crate) min_distance: Option<f32>,
     pub(crate) max_distance: Option<f32>,
     pub(crate) rolloff: Option<f32>,
     pub(crate) position: Option<Vec3>,
     pub(crate) velocity: Option<Vec3>,
     pub(crate) direction: Option<Vec3>,
+    pub(crate) cone: Option<Cone>,
     pub(crate) start_playing: bool,
+    pub(crate) file_loop_points: bool,
+    pub(crate) normalize_lufs: Option<f32>,
 }
 
 // Keeps the ptr to the path alive
@@ -167,6 +182,12 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
     }
 
     fn start_sound(&mut self) -> MaResult<Sound> {
+        self.check_ranges()?;
+        if self.sound_state.normalize_lufs.is_some() && !self.source.is_file() {
+            return Err(crate::MaudioError::from_ma_result(
+                sys::ma_result_MA_INVALID_ARGS,
+            ));
+        }
         if let Some(fence) = self.fence.clone() {
             self.inner.pDoneFence = fence.to_raw()
         };
@@ -178,12 +199,41 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
                         sys::ma_result_MA_INVALID_ARGS,
                     ));
                 }
-                self.engine.new_sound_with_config_internal(Some(self))?
+                let sound = self.engine.new_sound_with_config_internal(Some(self))?;
+                // miniaudio only applies the initial seek point to sounds loaded from a file
+                let seek_point = self.inner.initialSeekPointInPCMFrames;
+                if seek_point > 0 {
+                    data_source_ffi::ma_data_source_seek_to_pcm_frame(
+                        &mut sound.data_source(),
+                        seek_point,
+                    )?;
+                }
+                sound
             }
             #[cfg(unix)]
-            SoundSource::FileUtf8(_) => self.engine.new_sound_with_config_internal(Some(self))?,
+            SoundSource::FileUtf8(ref path) => {
+                let mut sound = self
+                    .engine
+                    .new_sound_with_config_internal(Some(self))
+                    .with_path(path)?;
+                sound.resource_use = self.engine.resource_use(path, self.flags);
+                let metadata = sound.load_wav_metadata(path);
+                self.apply_file_loop(&mut sound, &metadata)?;
+                self.apply_normalization(&mut sound, path)?;
+                sound
+            }
             #[cfg(windows)]
-            SoundSource::FileWide(_) => self.engine.new_sound_with_config_internal(Some(self))?,
+            SoundSource::FileWide(ref path) => {
+                let mut sound = self
+                    .engine
+                    .new_sound_with_config_internal(Some(self))
+                    .with_path(path)?;
+                sound.resource_use = self.engine.resource_use(path, self.flags);
+                let metadata = sound.load_wav_metadata(path);
+                self.apply_file_loop(&mut sound, &metadata)?;
+                self.apply_normalization(&mut sound, path)?;
+                sound
+            }
             SoundSource::None => {
                 self.check_flags_without_source()?;
 
@@ -257,6 +307,11 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
         self
     }
 
+    /// Attaches the sound to `group` instead of the engine's main output.
+    ///
+    /// The sound starts with the group's
+    /// [`spatial_defaults`](SoundGroup::spatial_defaults). Spatial settings set on this
+    /// builder override them.
     pub fn sound_group(&mut self, group: &'b SoundGroup) -> &mut Self {
         self.inner.pInitialAttachment = private_node::node_ptr(&group.as_node());
         self.group = Some(group);
@@ -381,6 +436,10 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
     /// Sets the initial seek position, in PCM frames.
     ///
     /// Playback starts from this frame instead of the beginning.
+    /// The position is relative to the start of the range, if one is set.
+    ///
+    /// Unlike calling [`Sound::seek_to_frame`] after the sound is built, the seek is done
+    /// before the sound can start playing.
     pub fn seek_point_frames(&mut self, pcm_frames: u64) -> &mut Self {
         self.inner.initialSeekPointInPCMFrames = pcm_frames;
         self
@@ -429,6 +488,8 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
     }
 
     /// Convenience method for calling [`Self::range_begin_frames`] and [`Self::range_end_frames`] in the same call
+    ///
+    /// Building the sound fails if `begin > end`.
     pub fn range_frames(&mut self, begin: u64, end: u64) -> &mut Self {
         self.inner.rangeBegInPCMFrames = begin;
         self.inner.rangeEndInPCMFrames = end;
@@ -445,6 +506,8 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
     }
 
     /// Convenience method for calling [`Self::loop_begin_frames`] and [`Self::loop_end_frames`] in the same call
+    ///
+    /// Loop points are relative to the start of the range. Building the sound fails if `begin > end`.
     pub fn loop_frames(&mut self, begin: u64, end: u64) -> &mut Self {
         self.inner.loopPointBegInPCMFrames = begin;
         self.inner.loopPointEndInPCMFrames = end;
@@ -460,6 +523,45 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
         self
     }
 
+    /// Starts playback at `frame`, relative to the start of the range.
+    ///
+    /// Same as [`Self::seek_point_frames`]. The seek is applied when the sound is
+    /// initialized, so it can not race with [`Sound::play_sound`].
+    pub fn start_at_frame(&mut self, frame: u64) -> &mut Self {
+        self.seek_point_frames(frame)
+    }
+
+    /// Limits playback to the frames from `start` to `end`.
+    ///
+    /// Same as [`Self::range_frames`], applied when the sound is initialized.
+    /// Building the sound fails if `start > end`.
+    pub fn range_pcm(&mut self, start: u64, end: u64) -> &mut Self {
+        self.range_frames(start, end)
+    }
+
+    /// Sets the loop points, relative to the start of the range.
+    ///
+    /// Same as [`Self::loop_frames`], applied when the sound is initialized.
+    /// Building the sound fails if `begin > end`.
+    pub fn loop_points_pcm(&mut self, begin: u64, end: u64) -> &mut Self {
+        self.loop_frames(begin, end)
+    }
+
+    /// Uses the first loop stored in the `smpl` chunk of a WAV file as the loop points.
+    ///
+    /// Samplers and music tools save loop regions in this chunk. The loop is converted to
+    /// the rate the sound is decoded at. Loop points set with [`Self::loop_frames`] or
+    /// [`Self::loop_millis`] take precedence, and files without a loop keep the default loop
+    /// points. Looping itself still has to be enabled, see [`Self::looping`].
+    ///
+    /// Only applies to sounds loaded from a file. See also [`SampleLoop`].
+    ///
+    /// [`SampleLoop`]: crate::data_source::sources::decoder::wav_metadata::SampleLoop
+    pub fn file_loop_points(&mut self, yes: bool) -> &mut Self {
+        self.sound_state.file_loop_points = yes;
+        self
+    }
+
     /// Equivalent to adding [SoundFlags::LOOPING]
     ///
     /// Does not modify any other existing flags
@@ -521,6 +623,14 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
         self
     }
 
+    /// Sets the attenuation model of the newly created sound
+    ///
+    /// Equivalent to calling [`Sound::set_attenuation`]
+    pub fn attenuation(&mut self, model: AttenuationModel) -> &mut Self {
+        self.sound_state.attenuation = Some(model);
+        self
+    }
+
     /// Sets the `min_distance` field on the newly created sound
     ///
     /// Equivalent to calling [`Sound::set_min_distance`]
@@ -569,6 +679,30 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
         self
     }
 
+    /// Sets the directional cone of the newly created sound
+    ///
+    /// Equivalent to calling [`Sound::set_cone`]
+    pub fn cone(&mut self, cone: Cone) -> &mut Self {
+        self.sound_state.cone = Some(cone);
+        self
+    }
+
+    /// Sets the volume so the sound plays at `target_lufs`, for example `-14.0` for music
+    /// or `-23.0` for broadcast (EBU R128).
+    ///
+    /// Only valid for sounds loaded from a file. The first sound loaded from a file decodes
+    /// it once to measure its integrated loudness, which blocks the build also for
+    /// [`SoundFlags::ASYNC`] sounds. The result is cached by the engine, see
+    /// [`Engine::cached_loudness`]. Quiet files are not turned up past a true peak of
+    /// -1 dBTP, and silent files are left as they are.
+    ///
+    /// The normalization replaces the initial volume. Calling [`Sound::set_volume`] later
+    /// replaces the normalization.
+    pub fn normalize_loudness(&mut self, target_lufs: f32) -> &mut Self {
+        self.sound_state.normalize_lufs = Some(target_lufs);
+        self
+    }
+
     /// Equivalent to calling [`Sound::play_sound()`] after sound is initialized
     pub fn start_playing(&mut self, yes: bool) -> &mut Self {
         self.sound_state.start_playing = yes;
@@ -595,6 +729,13 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
     }
 
     fn configure_sound(&self, sound: &mut Sound) {
+        // Group defaults first, so the settings on the builder override them.
+        if let Some(group) = self.group {
+            group.spatial_defaults().apply_to(sound);
+        }
+        if let Some(model) = self.sound_state.attenuation {
+            sound.set_attenuation(model);
+        }
         if let Some(min_d) = self.sound_state.min_distance {
             sound.set_min_distance(min_d)
         };
@@ -613,6 +754,46 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
         if let Some(d) = self.sound_state.direction {
             sound.set_direction(d);
         }
+        if let Some(cone) = self.sound_state.cone {
+            sound.set_cone(cone);
+        }
+    }
+
+    fn apply_file_loop(&self, sound: &mut Sound, metadata: &WavMetadata) -> MaResult<()> {
+        let loop_set = self.inner.loopPointBegInPCMFrames != 0
+            || self.inner.loopPointEndInPCMFrames != u64::MAX;
+        if !self.sound_state.file_loop_points || loop_set {
+            return Ok(());
+        }
+        match metadata.loops.first() {
+            Some(l) => sound
+                .data_source()
+                .set_loop_point_in_pcm_frames(l.start, l.end),
+            None => Ok(()),
+        }
+    }
+
+    fn apply_normalization(&self, sound: &mut Sound, path: &Path) -> MaResult<()> {
+        let Some(target) = self.sound_state.normalize_lufs else {
+            return Ok(());
+        };
+        let gain_db = self.engine.0.loudness_cache.gain_db(path, target)?;
+        sound.set_volume(crate::sound::sound_volume_db_to_linear(gain_db));
+        Ok(())
+    }
+
+    /// The range and loop points are applied by miniaudio when the sound is initialized,
+    /// which ignores invalid values. Reject them here instead.
+    fn check_ranges(&self) -> MaResult<()> {
+        let cfg = &self.inner;
+        if cfg.rangeBegInPCMFrames > cfg.rangeEndInPCMFrames
+            || cfg.loopPointBegInPCMFrames > cfg.loopPointEndInPCMFrames
+        {
+            return Err(crate::MaudioError::from_ma_result(
+                sys::ma_result_MA_INVALID_ARGS,
+            ));
+        }
+        Ok(())
     }
 
     /// Some flags don't make sense without a source.
@@ -681,11 +862,201 @@ impl<'a, 'b> SoundBuilder<'a, 'b> {
 
 #[cfg(test)]
 mod test {
-    use crate::engine::Engine;
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::{data_source_ffi, sources::buffer::AudioBufferBuilder},
+        engine::{engine_builder::EngineBuilder, Engine},
+        sound::sound_builder::SoundBuilder,
+        test_assets::{
+            temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le, wav_with_metadata,
+        },
+    };
 
     #[test]
     fn sound_builder_test_basic() {
         let engine = Engine::new_for_tests().unwrap();
         let _sound = engine.sound_config().channels_in(1).build().unwrap();
     }
+
+    #[test]
+    fn sound_builder_test_range_loop_and_seek_applied_at_init() {
+        let engine = Engine::new_for_tests().unwrap();
+        let data = vec![0.0f32; 2 * 64];
+        let buf = AudioBufferBuilder::build_f32(2, &data).unwrap();
+        let src = buf.as_source_ref();
+
+        let sound = SoundBuilder::new(&engine)
+            .data_source(&src)
+            .range_frames(8, 40)
+            .loop_frames(4, 16)
+            .seek_point_frames(6)
+            .build()
+            .unwrap();
+
+        let ds = sound.data_source();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_range_in_pcm_frames(&ds),
+            8..40
+        );
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&ds),
+            4..16
+        );
+        assert_eq!(sound.cursor_pcm().unwrap(), 6);
+        assert_eq!(sound.length_pcm().unwrap(), 32);
+    }
+
+    #[test]
+    fn sound_builder_test_file_loop_points() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let wav = wav_with_metadata(
+            &wav_i16_le(1, SampleRate::Sr24000, &[0i16; 1000]),
+            &[],
+            &[(1, 100, 399), (2, 500, 899)],
+        );
+        let file = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(file.path(), wav).unwrap();
+
+        // The first loop, at the engine's rate
+        let sound = SoundBuilder::new(&engine)
+            .file_path(file.path())
+            .file_loop_points(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&sound.data_source()),
+            200..800
+        );
+
+        // Explicit loop points win
+        let sound = SoundBuilder::new(&engine)
+            .file_path(file.path())
+            .file_loop_points(true)
+            .loop_frames(10, 20)
+            .build()
+            .unwrap();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&sound.data_source()),
+            10..20
+        );
+
+        // Opt-in only
+        let sound = SoundBuilder::new(&engine)
+            .file_path(file.path())
+            .build()
+            .unwrap();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&sound.data_source()),
+            0..u64::MAX
+        );
+    }
+
+    #[test]
+    fn sound_builder_test_rejects_inverted_range_and_loop() {
+        let engine = Engine::new_for_tests().unwrap();
+        let data = vec![0.0f32; 2 * 64];
+        let buf = AudioBufferBuilder::build_f32(2, &data).unwrap();
+        let src = buf.as_source_ref();
+
+        assert!(SoundBuilder::new(&engine)
+            .data_source(&src)
+            .range_frames(40, 8)
+            .build()
+            .is_err());
+        assert!(SoundBuilder::new(&engine)
+            .data_source(&src)
+            .loop_frames(16, 4)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn sound_builder_test_normalize_loudness() {
+        let engine = Engine::new_for_tests().unwrap();
+        // 997 Hz at -20 dBFS, which is -23 LUFS
+        let samples: Vec<i16> = (0..48_000)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * 997.0 * i as f32 / 48_000.0;
+                (phase.sin() * 3277.0) as i16
+            })
+            .collect();
+        let file = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(file.path(), wav_i16_le(1, SampleRate::Sr48000, &samples)).unwrap();
+        assert!(engine.cached_loudness(file.path()).is_none());
+
+        let sound = SoundBuilder::new(&engine)
+            .file_path(file.path())
+            .normalize_loudness(-20.0)
+            .build()
+            .unwrap();
+        let measured = engine.cached_loudness(file.path()).unwrap();
+        assert!((measured.integrated + 23.0).abs() < 0.1);
+        let gain_db = crate::sound::sound_volume_linear_to_db(sound.volume());
+        assert!((gain_db as f64 - (-20.0 - measured.integrated)).abs() < 0.01);
+
+        // Turned up only to a true peak of -1 dBTP
+        let sound = SoundBuilder::new(&engine)
+            .file_path(file.path())
+            .normalize_loudness(0.0)
+            .build()
+            .unwrap();
+        let gain_db = crate::sound::sound_volume_linear_to_db(sound.volume());
+        assert!((gain_db as f64 - (-1.0 - measured.true_peak)).abs() < 0.01);
+
+        engine.clear_loudness_cache();
+        assert!(engine.cached_loudness(file.path()).is_none());
+
+        let buf = AudioBufferBuilder::build_f32(1, &[0.0f32; 64]).unwrap();
+        assert!(SoundBuilder::new(&engine)
+            .data_source(&buf)
+            .normalize_loudness(-14.0)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn sound_builder_test_start_range_and_loop_points_pcm() {
+        let engine = Engine::new_for_tests().unwrap();
+        let data: Vec<f32> = (0..64).map(|i| i as f32).collect();
+        let buf = AudioBufferBuilder::build_f32(1, &data).unwrap();
+        let src = buf.as_source_ref();
+
+        let sound = SoundBuilder::new(&engine)
+            .data_source(&src)
+            .range_pcm(10, 50)
+            .loop_points_pcm(2, 20)
+            .start_at_frame(5)
+            .build()
+            .unwrap();
+
+        let mut ds = sound.data_source();
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_range_in_pcm_frames(&ds),
+            10..50
+        );
+        assert_eq!(
+            data_source_ffi::ma_data_source_get_loop_point_in_pcm_frames(&ds),
+            2..20
+        );
+        assert_eq!(sound.cursor_pcm().unwrap(), 5);
+        // Playback starts 5 frames into the range
+        let read =
+            data_source_ffi::ma_data_source_read_pcm_frames::<f32, _>(&mut ds, 3, 1).unwrap();
+        assert_eq!(read.as_ref(), &[15.0, 16.0, 17.0]);
+
+        assert!(SoundBuilder::new(&engine)
+            .data_source(&src)
+            .range_pcm(50, 10)
+            .build()
+            .is_err());
+        assert!(SoundBuilder::new(&engine)
+            .data_source(&src)
+            .loop_points_pcm(20, 2)
+            .build()
+            .is_err());
+    }
 }
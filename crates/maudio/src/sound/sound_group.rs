@@ -10,17 +10,28 @@ use crate::{
         spatial::{attenuation::AttenuationModel, cone::Cone, positioning::Positioning},
     },
     engine::{
-        node_graph::nodes::{private_node, AsNodePtr, NodeRef},
+        mix_snapshot,
+        node_graph::nodes::{
+            node_ffi, private_node,
+            routing::capture::{CaptureNode, CaptureNodeBuilder, CaptureReader},
+            AsNodePtr, NodeOps, NodeRef,
+        },
         Engine, EngineInner,
     },
-    sound::{sound_builder::SoundState, sound_flags::SoundFlags},
-    AsRawRef, Binding, MaResult,
+    sound::{
+        sound_builder::SoundState, sound_flags::SoundFlags, spatial_defaults::SpatialDefaults,
+    },
+    AsRawRef, Binding, ErrorKinds, MaResult, MaudioError,
 };
 
 pub struct SoundGroup {
     inner: *mut sys::ma_sound_group,
     _not_sync: PhantomData<Cell<()>>,
     _engine: Arc<EngineInner>,
+    spatial_defaults: SpatialDefaults,
+    capture: Option<CaptureNode>,
+    // Identifies the group in a `MixSnapshot`
+    id: u64,
 }
 
 impl Binding for SoundGroup {
@@ -250,6 +261,78 @@ impl SoundGroup {
         s_group_ffi::ma_sound_group_get_time_in_pcm_frames(self)
     }
 
+    /// Returns the spatialization settings that new sounds in this group start with.
+    pub fn spatial_defaults(&self) -> &SpatialDefaults {
+        &self.spatial_defaults
+    }
+
+    /// Sets the spatialization settings that new sounds in this group start with.
+    ///
+    /// These apply to the sounds, not to the group itself. Only sounds created after this
+    /// call are affected. See [`SpatialDefaults`].
+    pub fn set_spatial_defaults(&mut self, defaults: SpatialDefaults) {
+        self.spatial_defaults = defaults;
+    }
+
+    /// Starts copying the group's submix to a [`CaptureReader`].
+    ///
+    /// A [`CaptureNode`] is inserted between the group and whatever it is attached to, so
+    /// the mix keeps playing as before. The reader gets the group's output after its volume,
+    /// pan and effects, for example to record or stream only the voice chat or only the
+    /// music. `capacity_frames` is the size of the ring buffer between the audio thread and
+    /// the reader. See [`CaptureNodeBuilder`].
+    ///
+    /// Returns an error if the group is already being captured.
+    pub fn capture(&mut self, capacity_frames: u32) -> MaResult<CaptureReader> {
+        if self.capture.is_some() {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "sound group is already being captured",
+            )));
+        }
+        let engine = Engine(self._engine.clone());
+        let channels = self.as_node().output_channels(0);
+        let (capture, reader) = CaptureNodeBuilder::new(&engine.as_node_graph(), channels)
+            .capacity_frames(capacity_frames)
+            .build()?;
+
+        let target = node_ffi::output_attachment(&self.as_node(), 0);
+        self.as_node().attach_output(0, &mut capture.as_node(), 0)?;
+        if let Some((target, bus)) = target {
+            capture
+                .as_node()
+                .attach_output(0, &mut NodeRef::from_ptr(target), bus)?;
+        }
+        self.capture = Some(capture);
+        Ok(reader)
+    }
+
+    /// Returns `true` if the group is being captured.
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Stops the capture started with [`SoundGroup::capture`].
+    ///
+    /// The group is attached back to the node the capture was attached to. The reader keeps
+    /// the frames that were not read yet. Does nothing if the group is not being captured.
+    pub fn stop_capture(&mut self) -> MaResult<()> {
+        let Some(capture) = self.capture.take() else {
+            return Ok(());
+        };
+        match node_ffi::output_attachment(&capture.as_node(), 0) {
+            Some((target, bus)) => {
+                self.as_node()
+                    .attach_output(0, &mut NodeRef::from_ptr(target), bus)?;
+            }
+            None => self.as_node().detach_output(0)?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
     // Safe to cast as ma_node in version 0.11.23
     pub fn as_node(&self) -> NodeRef<'_> {
         assert!(!self.to_raw().is_null());
@@ -260,7 +343,9 @@ impl SoundGroup {
 
 impl Drop for SoundGroup {
     fn drop(&mut self) {
+        self._engine.mix_snapshots.unregister(self);
         s_group_ffi::ma_sound_group_uninit(self);
+        crate::engine::node_graph::node_bypass::release(self.to_raw().cast());
         drop(unsafe { Box::from_raw(self.to_raw()) });
     }
 }
@@ -660,6 +745,7 @@ pub struct SoundGroupBuilder<'a> {
     inner: sys::ma_sound_group_config,
     engine: &'a Engine,
     state: SoundState,
+    spatial_defaults: SpatialDefaults,
 }
 
 impl AsRawRef for SoundGroupBuilder<'_> {
@@ -677,12 +763,14 @@ impl<'a> SoundGroupBuilder<'a> {
             inner,
             engine,
             state: SoundState::default(),
+            spatial_defaults: SpatialDefaults::default(),
         }
     }
 
     pub fn build(&self) -> MaResult<SoundGroup> {
         let mut group = self.new_sound_group(self.engine.0.clone())?;
         self.configure_sound_group(&mut group);
+        self.engine.0.mix_snapshots.register(&group);
         Ok(group)
     }
 
@@ -774,6 +862,14 @@ impl<'a> SoundGroupBuilder<'a> {
         self
     }
 
+    /// Sets the spatialization settings that new sounds in the group start with.
+    ///
+    /// Equivalent to calling [`SoundGroup::set_spatial_defaults`]
+    pub fn spatial_defaults(&mut self, defaults: SpatialDefaults) -> &mut Self {
+        self.spatial_defaults = defaults;
+        self
+    }
+
     /// Sets the `position` field on the newly created sound
     ///
     /// Equivalent to calling [`SoundGroup::set_position`]
@@ -838,6 +934,9 @@ impl<'a> SoundGroupBuilder<'a> {
             inner,
             _not_sync: PhantomData,
             _engine: engine,
+            spatial_defaults: self.spatial_defaults,
+            capture: None,
+            id: mix_snapshot::next_group_id(),
         })
     }
 }
@@ -848,9 +947,15 @@ mod test {
         audio::{
             math::vec3::Vec3,
             pan::PanMode,
+            sample_rate::SampleRate,
             spatial::{attenuation::AttenuationModel, cone::Cone, positioning::Positioning},
         },
+        data_source::sources::buffer::AudioBufferBuilder,
         engine::{engine_builder::EngineBuilder, Engine},
+        sound::{
+            sound_builder::SoundBuilder, sound_group::SoundGroupBuilder,
+            spatial_defaults::SpatialDefaults,
+        },
     };
 
     fn approx_eq(a: f32, b: f32, eps: f32) -> bool {
@@ -1154,4 +1259,117 @@ mod test {
         let _is_playing = s_group.is_playing();
         let _t = s_group.time_pcm();
     }
+
+    #[test]
+    fn test_sound_group_spatial_defaults_inherited_and_overridden() {
+        let engine = Engine::new_for_tests().unwrap();
+        let defaults = SpatialDefaults {
+            attenuation: Some(AttenuationModel::Linear),
+            min_distance: Some(2.0),
+            max_distance: Some(40.0),
+            rolloff: Some(0.5),
+            cone: Some(Cone::from_degrees(90.0, 180.0, 0.25)),
+        };
+        let s_group = SoundGroupBuilder::new(&engine)
+            .spatial_defaults(defaults)
+            .build()
+            .unwrap();
+        assert_eq!(*s_group.spatial_defaults(), defaults);
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.0f32; 64]).unwrap();
+        let sound = SoundBuilder::new(&engine)
+            .data_source(&buffer)
+            .sound_group(&s_group)
+            .build()
+            .unwrap();
+        assert_eq!(sound.attenuation().unwrap(), AttenuationModel::Linear);
+        assert_approx_eq(sound.min_distance(), 2.0, 1e-6);
+        assert_approx_eq(sound.max_distance(), 40.0, 1e-6);
+        assert_approx_eq(sound.rolloff(), 0.5, 1e-6);
+        assert_approx_eq(sound.cone().outer_gain, 0.25, 1e-6);
+
+        let sound = SoundBuilder::new(&engine)
+            .data_source(&buffer)
+            .sound_group(&s_group)
+            .attenuation(AttenuationModel::Exponential)
+            .max_distance(10.0)
+            .build()
+            .unwrap();
+        assert_eq!(sound.attenuation().unwrap(), AttenuationModel::Exponential);
+        assert_approx_eq(sound.max_distance(), 10.0, 1e-6);
+        assert_approx_eq(sound.min_distance(), 2.0, 1e-6);
+    }
+
+    #[test]
+    fn test_sound_group_spatial_defaults_only_apply_to_new_sounds() {
+        let engine = Engine::new_for_tests().unwrap();
+        let mut s_group = engine.new_sound_group().unwrap();
+        assert!(s_group.spatial_defaults().is_empty());
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.0f32; 64]).unwrap();
+        let before = SoundBuilder::new(&engine)
+            .data_source(&buffer)
+            .sound_group(&s_group)
+            .build()
+            .unwrap();
+        let default_rolloff = before.rolloff();
+
+        s_group.set_spatial_defaults(SpatialDefaults {
+            rolloff: Some(3.0),
+            ..Default::default()
+        });
+        let after = SoundBuilder::new(&engine)
+            .data_source(&buffer)
+            .sound_group(&s_group)
+            .build()
+            .unwrap();
+
+        assert_approx_eq(before.rolloff(), default_rolloff, 1e-6);
+        assert_approx_eq(after.rolloff(), 3.0, 1e-6);
+    }
+
+    #[test]
+    fn test_sound_group_capture_submix() {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let mut s_group = engine.new_sound_group().unwrap();
+        s_group.set_spatialization(false);
+        s_group.set_volume(0.5);
+
+        let buffer = AudioBufferBuilder::build_f32(1, &[0.5f32; 512]).unwrap();
+        let mut sound = SoundBuilder::new(&engine)
+            .data_source(&buffer)
+            .sound_group(&s_group)
+            .build()
+            .unwrap();
+        sound.set_spatialization(false);
+
+        let mut capture = s_group.capture(1024).unwrap();
+        assert!(s_group.is_capturing());
+        assert!(s_group.capture(1024).is_err());
+        assert_eq!(capture.channels(), 1);
+        sound.play_sound().unwrap();
+
+        // The mix still reaches the endpoint through the capture node
+        let mut reader = engine.try_acquire_reader().unwrap();
+        let out = reader.read_pcm_frames(128).unwrap();
+        assert!(out.as_ref().iter().any(|s| *s != 0.0));
+        let captured = capture.read_available().unwrap();
+        assert_eq!(captured.frames(), 128);
+        assert_eq!(captured.as_ref(), out.as_ref());
+
+        s_group.stop_capture().unwrap();
+        assert!(!s_group.is_capturing());
+        let out = reader.read_pcm_frames(128).unwrap();
+        assert!(out.as_ref().iter().any(|s| *s != 0.0));
+        assert_eq!(capture.available_frames(), 0);
+
+        // Stopping again does nothing, and a new capture can start
+        s_group.stop_capture().unwrap();
+        let mut capture = s_group.capture(1024).unwrap();
+        reader.read_pcm_frames(64).unwrap();
+        assert_eq!(capture.read_available().unwrap().frames(), 64);
+    }
 }
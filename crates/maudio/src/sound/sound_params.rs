@@ -0,0 +1,140 @@
+//! Several sound parameters applied in a single call.
+//!
+//! Games often update volume, pan, pitch and the spatial state of hundreds of emitters every
+//! frame. [`SoundParams`] collects the changes for one sound, [`Sound::apply`] applies them,
+//! and [`Engine::apply_batch`](crate::engine::Engine::apply_batch) applies many of them in one
+//! pass. Fields left as `None` are not touched, so no FFI call is made for them.
+//!
+//! ```no_run
+//! # use maudio::audio::math::vec3::Vec3;
+//! # use maudio::engine::Engine;
+//! # use maudio::sound::sound_params::SoundParams;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut sound = engine.new_sound()?;
+//!
+//! sound.apply(&SoundParams {
+//!     volume: Some(0.5),
+//!     position: Some(Vec3::new(1.0, 0.0, -3.0)),
+//!     ..Default::default()
+//! });
+//! # Ok(())
+//! # }
+//! ```
+use crate::{audio::math::vec3::Vec3, sound::Sound};
+
+/// Parameters to change on a [`Sound`]. `None` leaves a parameter unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SoundParams {
+    /// See [`Sound::set_volume`].
+    pub volume: Option<f32>,
+    /// See [`Sound::set_pan`].
+    pub pan: Option<f32>,
+    /// See [`Sound::set_pitch`].
+    pub pitch: Option<f32>,
+    /// See [`Sound::set_position`].
+    pub position: Option<Vec3>,
+    /// See [`Sound::set_velocity`].
+    pub velocity: Option<Vec3>,
+    /// See [`Sound::set_direction`].
+    pub direction: Option<Vec3>,
+}
+
+impl SoundParams {
+    /// Reads every parameter from `sound`.
+    pub fn from_sound(sound: &Sound) -> Self {
+        Self {
+            volume: Some(sound.volume()),
+            pan: Some(sound.pan()),
+            pitch: Some(sound.pitch()),
+            position: Some(sound.position()),
+            velocity: Some(sound.velocity()),
+            direction: Some(sound.direction()),
+        }
+    }
+
+    /// Returns `true` if no parameter is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Sound {
+    /// Applies every parameter that is set in `params`.
+    pub fn apply(&mut self, params: &SoundParams) {
+        if let Some(volume) = params.volume {
+            self.set_volume(volume);
+        }
+        if let Some(pan) = params.pan {
+            self.set_pan(pan);
+        }
+        if let Some(pitch) = params.pitch {
+            self.set_pitch(pitch);
+        }
+        if let Some(position) = params.position {
+            self.set_position(position);
+        }
+        if let Some(velocity) = params.velocity {
+            self.set_velocity(velocity);
+        }
+        if let Some(direction) = params.direction {
+            self.set_direction(direction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::math::vec3::Vec3, engine::Engine, sound::sound_params::SoundParams, ErrorKinds,
+    };
+
+    #[test]
+    fn test_sound_params_apply() {
+        let engine = Engine::new_for_tests().unwrap();
+        let mut sound = engine.new_sound().unwrap();
+        let before = SoundParams::from_sound(&sound);
+
+        let params = SoundParams {
+            volume: Some(0.25),
+            position: Some(Vec3::new(1.0, 2.0, 3.0)),
+            ..Default::default()
+        };
+        assert!(!params.is_empty());
+        sound.apply(&params);
+
+        let after = SoundParams::from_sound(&sound);
+        assert_eq!(after.volume, Some(0.25));
+        assert_eq!(after.position, Some(Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(after.pan, before.pan);
+        assert_eq!(after.pitch, before.pitch);
+        assert!(SoundParams::default().is_empty());
+    }
+
+    #[test]
+    fn test_engine_apply_batch() {
+        let engine = Engine::new_for_tests().unwrap();
+        let mut a = engine.new_sound().unwrap();
+        let mut b = engine.new_sound().unwrap();
+        let pan = SoundParams {
+            pan: Some(-0.5),
+            ..Default::default()
+        };
+        let pitch = SoundParams {
+            pitch: Some(1.5),
+            ..Default::default()
+        };
+
+        engine
+            .apply_batch([(&mut a, &pan), (&mut b, &pitch)])
+            .unwrap();
+        assert_eq!(a.pan(), -0.5);
+        assert_eq!(b.pitch(), 1.5);
+
+        let other = Engine::new_for_tests().unwrap();
+        let mut foreign = other.new_sound().unwrap();
+        let err = engine.apply_batch([(&mut foreign, &pan)]).unwrap_err();
+        assert!(matches!(err.kind(), Some(ErrorKinds::InvalidOperation(_))));
+        assert_ne!(foreign.pan(), -0.5);
+    }
+}
@@ -0,0 +1,80 @@
+//! Spatialization settings shared by every sound in a [`SoundGroup`].
+//!
+//! Emitters of the same kind (footsteps, gunshots, ambience) usually share the same
+//! attenuation model, distances and cone. Instead of setting them on every sound,
+//! set them once on the group with [`SoundGroup::set_spatial_defaults`]. Sounds created in
+//! the group start with these settings, and anything set on the [`SoundBuilder`] overrides
+//! them. Fields left as `None` keep the miniaudio default.
+//!
+//! The defaults are only applied when a sound is initialized. Changing them later does not
+//! affect sounds that already exist.
+//!
+//! ```no_run
+//! # use maudio::audio::spatial::attenuation::AttenuationModel;
+//! # use maudio::engine::Engine;
+//! # use maudio::sound::{sound_builder::SoundBuilder, spatial_defaults::SpatialDefaults};
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut footsteps = engine.new_sound_group()?;
+//! footsteps.set_spatial_defaults(SpatialDefaults {
+//!     attenuation: Some(AttenuationModel::Linear),
+//!     max_distance: Some(30.0),
+//!     ..Default::default()
+//! });
+//!
+//! // Starts with linear attenuation, but fades out over 10 units instead of 30.
+//! let step = SoundBuilder::new(&engine)
+//!     .sound_group(&footsteps)
+//!     .max_distance(10.0)
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SoundGroup`]: crate::sound::sound_group::SoundGroup
+//! [`SoundGroup::set_spatial_defaults`]: crate::sound::sound_group::SoundGroup::set_spatial_defaults
+//! [`SoundBuilder`]: crate::sound::sound_builder::SoundBuilder
+use crate::{
+    audio::spatial::{attenuation::AttenuationModel, cone::Cone},
+    sound::Sound,
+};
+
+/// Spatialization settings applied to new sounds. `None` keeps the miniaudio default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpatialDefaults {
+    /// See [`Sound::set_attenuation`].
+    pub attenuation: Option<AttenuationModel>,
+    /// See [`Sound::set_min_distance`].
+    pub min_distance: Option<f32>,
+    /// See [`Sound::set_max_distance`].
+    pub max_distance: Option<f32>,
+    /// See [`Sound::set_rolloff`].
+    pub rolloff: Option<f32>,
+    /// See [`Sound::set_cone`].
+    pub cone: Option<Cone>,
+}
+
+impl SpatialDefaults {
+    /// Returns `true` if no setting is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub(crate) fn apply_to(&self, sound: &mut Sound) {
+        if let Some(model) = self.attenuation {
+            sound.set_attenuation(model);
+        }
+        if let Some(min_d) = self.min_distance {
+            sound.set_min_distance(min_d);
+        }
+        if let Some(max_d) = self.max_distance {
+            sound.set_max_distance(max_d);
+        }
+        if let Some(r) = self.rolloff {
+            sound.set_rolloff(r);
+        }
+        if let Some(cone) = self.cone {
+            sound.set_cone(cone);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Updates the position and velocity of many sounds at once.
+//!
+//! A [`SpatialUpdater`] owns a set of [`Sound`]s, each attached to a transform provider:
+//! a closure returning the current position and velocity of whatever the sound follows,
+//! such as a game entity. Calling [`SpatialUpdater::update`] once per frame polls every
+//! provider and pushes the new values to miniaudio.
+//!
+//! The sounds are stored contiguously and the last values sent to miniaudio are cached,
+//! so sounds whose entity did not move cost no FFI call at all. In busy scenes with many
+//! static emitters, this saves most of the per-sound calls made when setting positions
+//! by hand every frame.
+//!
+//! ```no_run
+//! # use std::sync::{Arc, Mutex};
+//! # use maudio::audio::math::vec3::Vec3;
+//! # use maudio::engine::Engine;
+//! # use maudio::sound::spatial_updater::SpatialUpdater;
+//! # fn main() -> maudio::MaResult<()> {
+//! # let path = std::path::Path::new("engine_loop.wav");
+//! let engine = Engine::new()?;
+//! let mut updater = SpatialUpdater::new();
+//!
+//! // Position of a car, written by the game logic
+//! let car = Arc::new(Mutex::new(Vec3::new(0.0, 0.0, -10.0)));
+//!
+//! let mut sound = engine.new_sound_from_file(path)?;
+//! sound.play_sound()?;
+//! let transform = car.clone();
+//! let id = updater.attach(sound, move || {
+//!     let position = *transform.lock().unwrap();
+//!     (position, Vec3::new(0.0, 0.0, 5.0))
+//! });
+//!
+//! // Once per frame
+//! updater.update();
+//! # let _ = id;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{audio::math::vec3::Vec3, sound::Sound};
+
+/// Identifies a sound in a [`SpatialUpdater`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpatialId(u64);
+
+type TransformProvider = Box<dyn FnMut() -> (Vec3, Vec3) + Send>;
+
+/// Keeps sounds at the position of the entities they follow. See the [module docs](self).
+#[derive(Default)]
+pub struct SpatialUpdater {
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+struct Entry {
+    id: SpatialId,
+    sound: Sound,
+    provider: TransformProvider,
+    position: Option<Vec3>,
+    velocity: Option<Vec3>,
+}
+
+impl SpatialUpdater {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sound that follows the transform returned by `provider`.
+    ///
+    /// `provider` returns `(position, velocity)`. It is called on every
+    /// [`SpatialUpdater::update`], on the thread calling it.
+    pub fn attach<P>(&mut self, sound: Sound, provider: P) -> SpatialId
+    where
+        P: FnMut() -> (Vec3, Vec3) + Send + 'static,
+    {
+        let id = SpatialId(self.next_id);
+        self.next_id += 1;
+        self.entries.push(Entry {
+            id,
+            sound,
+            provider: Box::new(provider),
+            position: None,
+            velocity: None,
+        });
+        id
+    }
+
+    /// Removes a sound and returns it. The sound keeps its last position and velocity.
+    pub fn detach(&mut self, id: SpatialId) -> Option<Sound> {
+        let idx = self.entries.iter().position(|e| e.id == id)?;
+        Some(self.entries.swap_remove(idx).sound)
+    }
+
+    /// Returns `true` if the sound exists.
+    pub fn contains(&self, id: SpatialId) -> bool {
+        self.entry(id).is_some()
+    }
+
+    pub fn sound(&self, id: SpatialId) -> Option<&Sound> {
+        self.entry(id).map(|e| &e.sound)
+    }
+
+    /// Returns a sound.
+    ///
+    /// A position or velocity set directly on the sound is overwritten on the next
+    /// [`SpatialUpdater::update`] only if the provider returns a different value.
+    pub fn sound_mut(&mut self, id: SpatialId) -> Option<&mut Sound> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .map(|e| &mut e.sound)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Polls every provider and updates the sounds whose transform changed.
+    ///
+    /// Returns the number of sounds that were updated.
+    pub fn update(&mut self) -> usize {
+        let mut updated = 0;
+        for entry in &mut self.entries {
+            let (position, velocity) = (entry.provider)();
+            let mut changed = false;
+            if entry.position != Some(position) {
+                entry.sound.set_position(position);
+                entry.position = Some(position);
+                changed = true;
+            }
+            if entry.velocity != Some(velocity) {
+                entry.sound.set_velocity(velocity);
+                entry.velocity = Some(velocity);
+                changed = true;
+            }
+            updated += changed as usize;
+        }
+        updated
+    }
+
+    /// Removes the sounds for which `f` returns `false`, for example sounds that finished
+    /// playing.
+    pub fn retain<F: FnMut(&Sound) -> bool>(&mut self, mut f: F) {
+        self.entries.retain(|e| f(&e.sound));
+    }
+
+    fn entry(&self, id: SpatialId) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{audio::math::vec3::Vec3, engine::Engine, sound::spatial_updater::SpatialUpdater};
+
+    #[test]
+    fn test_spatial_updater_follows_provider() {
+        let engine = Engine::new_for_tests().unwrap();
+        let mut updater = SpatialUpdater::new();
+
+        let target = Arc::new(Mutex::new(Vec3::new(1.0, 2.0, 3.0)));
+        let transform = target.clone();
+        let moving = updater.attach(engine.new_sound().unwrap(), move || {
+            (*transform.lock().unwrap(), Vec3::new(0.0, 0.0, 1.0))
+        });
+        let fixed = updater.attach(engine.new_sound().unwrap(), || {
+            (Vec3::new(-5.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0))
+        });
+        assert_eq!(updater.len(), 2);
+
+        assert_eq!(updater.update(), 2);
+        assert_eq!(
+            updater.sound(moving).unwrap().position(),
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(
+            updater.sound(moving).unwrap().velocity(),
+            Vec3::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            updater.sound(fixed).unwrap().position(),
+            Vec3::new(-5.0, 0.0, 0.0)
+        );
+
+        // Nothing moved
+        assert_eq!(updater.update(), 0);
+
+        *target.lock().unwrap() = Vec3::new(4.0, 0.0, 0.0);
+        assert_eq!(updater.update(), 1);
+        assert_eq!(
+            updater.sound(moving).unwrap().position(),
+            Vec3::new(4.0, 0.0, 0.0)
+        );
+
+        let sound = updater.detach(moving).unwrap();
+        assert_eq!(sound.position(), Vec3::new(4.0, 0.0, 0.0));
+        assert!(!updater.contains(moving));
+        assert!(updater.detach(moving).is_none());
+
+        updater.retain(|_| false);
+        assert!(updater.is_empty());
+    }
+}
@@ -0,0 +1,437 @@
+//! Recovery from read failures of streamed sounds, behind [`Sound::set_stream_error_policy`].
+//!
+//! A streamed sound decodes its file while it plays. When reading the file fails part way, for
+//! example because a network share dropped or the file was truncated, miniaudio ends the sound
+//! as if the file were shorter, without reporting anything. A [`StreamErrorPolicy`] tells
+//! these early ends apart from the real end of the sound, by comparing the cursor with the
+//! length read from the file's header, and decides what happens next.
+//!
+//! Failures are reported through the [`StreamErrorNotifier`] returned by
+//! [`Sound::set_stream_error_policy`]. The sound's [`EndNotifier`] only fires once the sound
+//! really stops, after the policy gave up or the sound played to its end.
+//!
+//! ```no_run
+//! # use maudio::engine::Engine;
+//! # use maudio::sound::{sound_flags::SoundFlags, stream_error::StreamErrorPolicy};
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let engine = Engine::new()?;
+//! let mut music = engine.new_sound_from_file_with_flags(Path::new("music.wav"), SoundFlags::STREAM, None)?;
+//! let errors = music.set_stream_error_policy(StreamErrorPolicy::Retry {
+//!     attempts: 3,
+//!     backoff_millis: 250,
+//! })?;
+//! music.play_sound()?;
+//!
+//! // Later, once per frame
+//! if let Some(error) = errors.take() {
+//!     eprintln!("stream failed at frame {} of {}", error.cursor, error.length);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Sound::set_stream_error_policy`]: crate::sound::Sound::set_stream_error_policy
+//! [`EndNotifier`]: crate::sound::notifier::EndNotifier
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering},
+    Arc,
+};
+
+use maudio_sys::ffi as sys;
+
+/// What a streamed sound does when reading its file fails mid-playback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamErrorPolicy {
+    /// The sound stops at the failure. This is what miniaudio does on its own.
+    #[default]
+    End,
+    /// The sound plays silence for the rest of its length, then ends. Keeps sounds that are
+    /// synchronized with something else, like a cutscene, on time.
+    Silence,
+    /// The sound pauses and reads again from where it failed, up to `attempts` times. The
+    /// first retry waits `backoff_millis`, and every following retry waits twice as long. A
+    /// retry that gets past the failure resets the attempts. The sound ends once they run out.
+    Retry { attempts: u32, backoff_millis: u32 },
+}
+
+/// A read failure reported by a [`StreamErrorNotifier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamError {
+    /// Cursor of the sound when the stream ended early, in PCM frames.
+    pub cursor: u64,
+    /// Length of the sound, in PCM frames.
+    pub length: u64,
+    /// Number of failures since the last [`StreamErrorNotifier::take`], including retries
+    /// that failed again.
+    pub count: u32,
+}
+
+/// A notification handle for the read failures of a streamed sound.
+///
+/// Created with [`Sound::set_stream_error_policy`](crate::sound::Sound::set_stream_error_policy).
+/// The audio thread records each failure. Like [`EndNotifier`](crate::sound::notifier::EndNotifier),
+/// it is polled: several failures between two calls to [`take()`](StreamErrorNotifier::take())
+/// are reported once, with the position of the latest.
+///
+/// Cloning a `StreamErrorNotifier` creates another handle to the same notifications.
+#[derive(Clone)]
+pub struct StreamErrorNotifier {
+    watch: Arc<StreamWatch>,
+}
+
+impl StreamErrorNotifier {
+    /// Returns `true` if a failure happened since the last [`StreamErrorNotifier::take()`].
+    ///
+    /// This does **not** clear the notification.
+    #[inline]
+    pub fn peek(&self) -> bool {
+        self.watch.failures.load(Ordering::Acquire) > 0
+    }
+
+    /// Consumes the notification and returns the latest failure, if any.
+    pub fn take(&self) -> Option<StreamError> {
+        let count = self.watch.failures.swap(0, Ordering::Acquire);
+        if count == 0 {
+            return None;
+        }
+        Some(StreamError {
+            cursor: self.watch.cursor.load(Ordering::Relaxed),
+            length: self.watch.length.load(Ordering::Relaxed),
+            count,
+        })
+    }
+
+    /// Returns `true` if the policy gave up and the sound stopped before its end.
+    pub fn gave_up(&self) -> bool {
+        self.watch.gave_up.load(Ordering::Relaxed)
+    }
+}
+
+const POLICY_END: u8 = 0;
+const POLICY_SILENCE: u8 = 1;
+const POLICY_RETRY: u8 = 2;
+
+/// State shared with the end callback of a sound that has a [`StreamErrorPolicy`].
+///
+/// The sound keeps it alive for as long as the callback is installed. The policy is stored in
+/// atomics so it can be changed without replacing the callback's user data.
+pub(crate) struct StreamWatch {
+    policy: AtomicU8,
+    attempts: AtomicU32,
+    backoff_millis: AtomicU32,
+    // Flag of the sound's EndNotifier, set once the sound really ends
+    end: Arc<AtomicBool>,
+    // Retries made since the sound last got past a failure
+    retries: AtomicU32,
+    failures: AtomicU32,
+    cursor: AtomicU64,
+    length: AtomicU64,
+    gave_up: AtomicBool,
+}
+
+impl StreamWatch {
+    pub(crate) fn new(policy: StreamErrorPolicy, end: Arc<AtomicBool>) -> Self {
+        let watch = Self {
+            policy: AtomicU8::new(POLICY_END),
+            attempts: AtomicU32::new(0),
+            backoff_millis: AtomicU32::new(0),
+            end,
+            retries: AtomicU32::new(0),
+            failures: AtomicU32::new(0),
+            cursor: AtomicU64::new(0),
+            length: AtomicU64::new(0),
+            gave_up: AtomicBool::new(false),
+        };
+        watch.set_policy(policy);
+        watch
+    }
+
+    pub(crate) fn notifier(self: &Arc<Self>) -> StreamErrorNotifier {
+        StreamErrorNotifier {
+            watch: self.clone(),
+        }
+    }
+
+    pub(crate) fn end_flag(&self) -> Arc<AtomicBool> {
+        self.end.clone()
+    }
+
+    pub(crate) fn policy(&self) -> StreamErrorPolicy {
+        match self.policy.load(Ordering::Relaxed) {
+            POLICY_SILENCE => StreamErrorPolicy::Silence,
+            POLICY_RETRY => StreamErrorPolicy::Retry {
+                attempts: self.attempts.load(Ordering::Relaxed),
+                backoff_millis: self.backoff_millis.load(Ordering::Relaxed),
+            },
+            _ => StreamErrorPolicy::End,
+        }
+    }
+
+    pub(crate) fn set_policy(&self, policy: StreamErrorPolicy) {
+        let kind = match policy {
+            StreamErrorPolicy::End => POLICY_END,
+            StreamErrorPolicy::Silence => POLICY_SILENCE,
+            StreamErrorPolicy::Retry {
+                attempts,
+                backoff_millis,
+            } => {
+                self.attempts.store(attempts, Ordering::Relaxed);
+                self.backoff_millis.store(backoff_millis, Ordering::Relaxed);
+                POLICY_RETRY
+            }
+        };
+        self.policy.store(kind, Ordering::Relaxed);
+    }
+
+    pub(crate) fn as_user_data_ptr(self: &Arc<Self>) -> *mut core::ffi::c_void {
+        Arc::as_ptr(self) as *mut core::ffi::c_void
+    }
+
+    fn report(&self, cursor: u64, length: u64) {
+        // Getting past the previous failure means the last retry worked
+        if cursor > self.cursor.load(Ordering::Relaxed) {
+            self.retries.store(0, Ordering::Relaxed);
+        }
+        self.cursor.store(cursor, Ordering::Relaxed);
+        self.length.store(length, Ordering::Relaxed);
+        self.failures.fetch_add(1, Ordering::Release);
+    }
+
+    fn end(&self, failed: bool) {
+        self.retries.store(0, Ordering::Relaxed);
+        self.gave_up.store(failed, Ordering::Relaxed);
+        self.end.store(true, Ordering::Relaxed);
+    }
+}
+
+// Returns the cursor and length of the sound's data source, if the length is known.
+unsafe fn source_position(sound: *mut sys::ma_sound) -> Option<(u64, u64, u32)> {
+    let source = sys::ma_sound_get_data_source(sound);
+    if source.is_null() {
+        return None;
+    }
+    let mut cursor = 0;
+    let mut length = 0;
+    let mut sample_rate = 0;
+    if sys::ma_data_source_get_cursor_in_pcm_frames(source, &mut cursor)
+        != sys::ma_result_MA_SUCCESS
+        || sys::ma_data_source_get_length_in_pcm_frames(source, &mut length)
+            != sys::ma_result_MA_SUCCESS
+        || sys::ma_data_source_get_data_format(
+            source,
+            core::ptr::null_mut(),
+            core::ptr::null_mut(),
+            &mut sample_rate,
+            core::ptr::null_mut(),
+            0,
+        ) != sys::ma_result_MA_SUCCESS
+    {
+        return None;
+    }
+    Some((cursor, length, sample_rate))
+}
+
+/// End callback installed by `Sound::set_stream_error_policy`.
+///
+/// Runs on the audio thread, right after the sound's data source reported its end and before
+/// miniaudio stops the sound on the next processed block. Clearing the end flag keeps the sound
+/// playing, and a start time in the future pauses it until then.
+pub(crate) unsafe extern "C" fn on_stream_end_callback(
+    user_data: *mut core::ffi::c_void,
+    sound: *mut sys::ma_sound,
+) {
+    if user_data.is_null() || sound.is_null() {
+        return;
+    }
+    let watch = &*(user_data as *const StreamWatch);
+
+    let Some((cursor, length, source_rate)) = source_position(sound) else {
+        watch.end(false);
+        return;
+    };
+    // Unknown lengths can't tell a failure from the end
+    if length == 0 || cursor >= length {
+        watch.end(false);
+        return;
+    }
+    watch.report(cursor, length);
+
+    let engine = sys::ma_sound_get_engine(sound);
+    let engine_rate = sys::ma_engine_get_sample_rate(engine);
+    // Sources without a sample rate play at the engine's rate
+    let source_rate = if source_rate == 0 {
+        engine_rate
+    } else {
+        source_rate
+    };
+
+    let (seek_to, delay_frames) = match watch.policy() {
+        StreamErrorPolicy::End => {
+            watch.end(true);
+            return;
+        }
+        StreamErrorPolicy::Silence => {
+            let missing = (length - cursor) * engine_rate as u64 / source_rate as u64;
+            (length, missing)
+        }
+        StreamErrorPolicy::Retry {
+            attempts,
+            backoff_millis,
+        } => {
+            let retry = watch.retries.fetch_add(1, Ordering::Relaxed);
+            if retry >= attempts {
+                watch.end(true);
+                return;
+            }
+            let backoff = backoff_millis as u64 * engine_rate as u64 / 1000;
+            // Streams ignore a seek to their own cursor, which would not read the file again.
+            // Replaying a single frame is not audible.
+            let seek_to = if cursor > 0 { cursor - 1 } else { 1 };
+            (seek_to, backoff.saturating_mul(1 << retry.min(16)))
+        }
+    };
+
+    sys::ma_sound_seek_to_pcm_frame(sound, seek_to);
+    let now = sys::ma_engine_get_time_in_pcm_frames(engine);
+    sys::ma_sound_set_start_time_in_pcm_frames(sound, now + delay_frames);
+    // The sound is not stopped while its end flag is clear
+    let at_end = &*(core::ptr::addr_of!((*sound).atEnd) as *const AtomicU32);
+    at_end.store(0, Ordering::Release);
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        audio::sample_rate::SampleRate,
+        engine::{engine_builder::EngineBuilder, Engine},
+        sound::{sound_flags::SoundFlags, stream_error::StreamErrorPolicy, Sound},
+        test_assets::{
+            temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le,
+        },
+    };
+
+    const FRAMES: usize = 48000 * 3;
+    // Past the two pages decoded when the stream is initialized
+    const READABLE: usize = 48000 * 5 / 2;
+    const HEADER: usize = 44;
+
+    fn truncated_stream(engine: &Engine, path: &Path, wav: &[u8]) -> Sound {
+        std::fs::write(path, wav).unwrap();
+        let sound = engine
+            .new_sound_from_file_with_flags(path, SoundFlags::STREAM, None)
+            .unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.set_len((HEADER + READABLE * 2) as u64).unwrap();
+        sound
+    }
+
+    // Pages load on a job thread, so reads may come back silent until they are ready
+    fn play_until(engine: &Engine, mut done: impl FnMut() -> bool) {
+        let mut reader = engine.try_acquire_reader().unwrap();
+        for _ in 0..10_000 {
+            if done() {
+                return;
+            }
+            reader.read_pcm_frames(4800).unwrap();
+            std::thread::sleep(std::time::Duration::from_micros(200));
+        }
+        panic!("the sound did not finish");
+    }
+
+    fn setup() -> (Engine, TempFileGuard, Vec<u8>) {
+        let engine = EngineBuilder::new()
+            .no_device(1, SampleRate::Sr48000)
+            .build()
+            .unwrap();
+        let wav = wav_i16_le(1, SampleRate::Sr48000, &vec![8000i16; FRAMES]);
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        (engine, guard, wav)
+    }
+
+    #[test]
+    fn test_stream_error_end_reports_failure() {
+        let (engine, guard, wav) = setup();
+        let mut sound = truncated_stream(&engine, guard.path(), &wav);
+        let end = sound.set_end_callback().unwrap();
+        let errors = sound
+            .set_stream_error_policy(StreamErrorPolicy::End)
+            .unwrap();
+        assert_eq!(sound.stream_error_policy(), Some(StreamErrorPolicy::End));
+        sound.play_sound().unwrap();
+
+        play_until(&engine, || end.peek());
+        let error = errors.take().unwrap();
+        assert_eq!(error.cursor, READABLE as u64);
+        assert_eq!(error.length, FRAMES as u64);
+        assert!(errors.gave_up());
+        assert!(errors.take().is_none());
+    }
+
+    #[test]
+    fn test_stream_error_silence_keeps_length() {
+        let (engine, guard, wav) = setup();
+        let mut sound = truncated_stream(&engine, guard.path(), &wav);
+        let errors = sound
+            .set_stream_error_policy(StreamErrorPolicy::Silence)
+            .unwrap();
+        let end = sound.set_end_callback().unwrap();
+        sound.play_sound().unwrap();
+
+        play_until(&engine, || end.peek());
+        assert!(errors.take().is_some());
+        assert!(!errors.gave_up());
+        assert!(engine.time_pcm() >= FRAMES as u64);
+    }
+
+    #[test]
+    fn test_stream_error_retry_recovers() {
+        let (engine, guard, wav) = setup();
+        let mut sound = truncated_stream(&engine, guard.path(), &wav);
+        let errors = sound
+            .set_stream_error_policy(StreamErrorPolicy::Retry {
+                attempts: 3,
+                backoff_millis: 100,
+            })
+            .unwrap();
+        let end = sound.set_end_callback().unwrap();
+        sound.play_sound().unwrap();
+
+        play_until(&engine, || errors.peek());
+        assert!(sound.is_playing());
+        // The storage comes back before the retry
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(guard.path())
+            .unwrap();
+        std::io::Write::write_all(&mut file, &wav).unwrap();
+        play_until(&engine, || end.peek());
+
+        let error = errors.take().unwrap();
+        assert_eq!(error.cursor, READABLE as u64);
+        assert_eq!(error.count, 1);
+        assert!(!errors.gave_up());
+        assert_eq!(sound.cursor_pcm().unwrap(), FRAMES as u64);
+    }
+
+    #[test]
+    fn test_stream_error_retry_gives_up() {
+        let (engine, guard, wav) = setup();
+        let mut sound = truncated_stream(&engine, guard.path(), &wav);
+        let errors = sound
+            .set_stream_error_policy(StreamErrorPolicy::Retry {
+                attempts: 2,
+                backoff_millis: 10,
+            })
+            .unwrap();
+        let end = sound.set_end_callback().unwrap();
+        sound.play_sound().unwrap();
+
+        play_until(&engine, || end.peek());
+        assert_eq!(errors.take().unwrap().count, 3);
+        assert!(errors.gave_up());
+    }
+}
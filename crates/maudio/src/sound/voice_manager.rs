@@ -0,0 +1,518 @@
+//! Limits the number of audible sounds.
+//!
+//! A [`VoiceManager`] owns a set of [`Sound`]s (voices) and keeps at most `max_audible` of them
+//! playing at once. When there are more voices than that, the least important ones become
+//! *virtual*: they are stopped, but their playback position keeps advancing with the engine
+//! clock. When an audible slot frees up, a virtual voice resumes from where it would have been,
+//! as if it had been playing all along.
+//!
+//! ## Choosing which voices are audible
+//!
+//! Voices are ranked by:
+//! 1. Priority. Higher values win.
+//! 2. Distance to the listener, for voices with the same priority. Closer voices win.
+//!    Non spatialized sounds have a distance of `0.0`.
+//! 3. Age, for voices with the same priority and distance. Older voices win.
+//!
+//! The ranking is recomputed by [`VoiceManager::update`], which should be called regularly
+//! (e.g. once per game frame) so that moving sounds and listeners are taken into account.
+//!
+//! ```no_run
+//! # use maudio::audio::math::vec3::Vec3;
+//! # use maudio::engine::Engine;
+//! # use maudio::sound::voice_manager::VoiceManager;
+//! # fn main() -> maudio::MaResult<()> {
+//! # let path = std::path::Path::new("footstep.wav");
+//! let engine = Engine::new()?;
+//! let mut voices = VoiceManager::new(&engine, 32);
+//!
+//! let mut sound = engine.new_sound_from_file(path)?;
+//! sound.set_position(Vec3::new(4.0, 0.0, -2.0));
+//! let id = voices.play(sound, 10)?;
+//!
+//! // Once per frame
+//! voices.update()?;
+//! if voices.is_virtual(id) {
+//!     // Not audible right now
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::cmp::Ordering;
+
+use crate::{
+    audio::{math::vec3::Vec3, spatial::positioning::Positioning},
+    engine::Engine,
+    sound::{sound_params::SoundParams, Sound},
+    MaResult,
+};
+
+/// Identifies a voice in a [`VoiceManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+/// Keeps the number of audible sounds under a limit. See the [module docs](self).
+pub struct VoiceManager {
+    engine: Engine,
+    max_audible: usize,
+    voices: Vec<Voice>,
+    next_id: u64,
+}
+
+struct Voice {
+    id: VoiceId,
+    sound: Sound,
+    priority: u8,
+    // Engine time and sound cursor when the voice became virtual
+    virtual_since: Option<VirtualState>,
+}
+
+#[derive(Clone, Copy)]
+struct VirtualState {
+    engine_time: u64,
+    cursor: Option<u64>,
+}
+
+impl VoiceManager {
+    /// Creates a voice manager keeping at most `max_audible` voices playing at once.
+    pub fn new(engine: &Engine, max_audible: usize) -> Self {
+        Self {
+            engine: Engine(engine.0.clone()),
+            max_audible,
+            voices: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn max_audible(&self) -> usize {
+        self.max_audible
+    }
+
+    /// Changes the limit of audible voices. Applied on the next [`VoiceManager::update`].
+    pub fn set_max_audible(&mut self, max_audible: usize) {
+        self.max_audible = max_audible;
+    }
+
+    /// Adds a sound and starts playing it, unless more important voices use every audible slot.
+    ///
+    /// In that case, the sound starts as a virtual voice. Playing a sound can also
+    /// make a less important voice virtual.
+    pub fn play(&mut self, sound: Sound, priority: u8) -> MaResult<VoiceId> {
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        // A voice starting virtual advances from where the sound starts playing
+        let cursor = sound.cursor_pcm().unwrap_or(0);
+        self.voices.push(Voice {
+            id,
+            sound,
+            priority,
+            virtual_since: Some(VirtualState {
+                engine_time: self.engine.time_pcm(),
+                cursor: Some(cursor),
+            }),
+        });
+        self.update()?;
+        Ok(id)
+    }
+
+    /// Removes a voice and returns its sound, stopped.
+    pub fn stop(&mut self, id: VoiceId) -> Option<Sound> {
+        let idx = self.voices.iter().position(|v| v.id == id)?;
+        let mut sound = self.voices.remove(idx).sound;
+        let _ = sound.stop_sound();
+        Some(sound)
+    }
+
+    /// Removes and stops every voice.
+    pub fn stop_all(&mut self) {
+        for mut voice in self.voices.drain(..) {
+            let _ = voice.sound.stop_sound();
+        }
+    }
+
+    /// Changes the priority of a voice. Applied on the next [`VoiceManager::update`].
+    ///
+    /// Returns `false` if the voice does not exist.
+    pub fn set_priority(&mut self, id: VoiceId, priority: u8) -> bool {
+        match self.voice_mut(id) {
+            Some(voice) => {
+                voice.priority = priority;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn priority(&self, id: VoiceId) -> Option<u8> {
+        self.voice(id).map(|v| v.priority)
+    }
+
+    /// Returns `true` if the voice exists.
+    ///
+    /// Voices are removed once their sound reaches the end.
+    pub fn contains(&self, id: VoiceId) -> bool {
+        self.voice(id).is_some()
+    }
+
+    /// Returns `true` if the voice exists and is virtual.
+    pub fn is_virtual(&self, id: VoiceId) -> bool {
+        self.voice(id).map_or(false, |v| v.virtual_since.is_some())
+    }
+
+    /// Returns the sound of a voice.
+    pub fn sound(&self, id: VoiceId) -> Option<&Sound> {
+        self.voice(id).map(|v| &v.sound)
+    }
+
+    /// Returns the sound of a voice.
+    ///
+    /// Starting or stopping the sound directly is not tracked by the voice manager.
+    /// Use [`VoiceManager::stop`] to remove a voice instead.
+    pub fn sound_mut(&mut self, id: VoiceId) -> Option<&mut Sound> {
+        self.voice_mut(id).map(|v| &mut v.sound)
+    }
+
+    /// Applies parameters to many voices in one pass.
+    ///
+    /// Voices that do not exist are skipped. Returns the number of voices updated.
+    pub fn apply_batch(&mut self, batch: &[(VoiceId, SoundParams)]) -> usize {
+        let mut applied = 0;
+        for (id, params) in batch {
+            if let Some(sound) = self.sound_mut(*id) {
+                sound.apply(params);
+                applied += 1;
+            }
+        }
+        applied
+    }
+
+    /// Number of voices, audible and virtual.
+    pub fn len(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+
+    /// Number of voices currently playing.
+    pub fn audible_count(&self) -> usize {
+        self.voices
+            .iter()
+            .filter(|v| v.virtual_since.is_none())
+            .count()
+    }
+
+    /// Number of virtual voices.
+    pub fn virtual_count(&self) -> usize {
+        self.voices.len() - self.audible_count()
+    }
+
+    /// Removes finished voices, ranks the remaining ones, and makes them audible or virtual
+    /// according to the limit.
+    pub fn update(&mut self) -> MaResult<()> {
+        let now = self.engine.time_pcm();
+        let engine_rate = self.engine.sample_rate_u32();
+
+        // Remove voices that reached the end, either while playing or while virtual
+        let mut i = 0;
+        while i < self.voices.len() {
+            let voice = &self.voices[i];
+            let finished = match voice.virtual_since {
+                None => voice.sound.ended(),
+                Some(state) => virtual_cursor(&voice.sound, state, now, engine_rate)
+                    .map_or(false, |c| c.is_none()),
+            };
+            if finished {
+                self.voices.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        let mut ranked: Vec<(usize, u8, f32)> = self
+            .voices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, v.priority, self.distance_to_listener(&v.sound)))
+            .collect();
+        // Voices are stored oldest first, and the sort is stable
+        ranked.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+        });
+
+        for (rank, &(idx, _, _)) in ranked.iter().enumerate() {
+            let voice = &mut self.voices[idx];
+            let audible = rank < self.max_audible;
+            match (audible, voice.virtual_since) {
+                (true, Some(state)) => {
+                    if let Some(cursor) = virtual_cursor(&voice.sound, state, now, engine_rate)? {
+                        voice.sound.seek_to_frame(cursor)?;
+                    }
+                    voice.sound.play_sound()?;
+                    voice.virtual_since = None;
+                }
+                (false, None) => {
+                    voice.sound.stop_sound()?;
+                    voice.virtual_since = Some(VirtualState {
+                        engine_time: now,
+                        cursor: voice.sound.cursor_pcm().ok(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn voice(&self, id: VoiceId) -> Option<&Voice> {
+        self.voices.iter().find(|v| v.id == id)
+    }
+
+    fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|v| v.id == id)
+    }
+
+    fn distance_to_listener(&self, sound: &Sound) -> f32 {
+        if !sound.spatialization() {
+            return 0.0;
+        }
+        let position = sound.position();
+        let listener = match sound.positioning() {
+            Ok(Positioning::Relative) => Vec3::new(0.0, 0.0, 0.0),
+            _ => match self.engine.listener(sound.listener()) {
+                Ok(listener) => listener.position(),
+                Err(_) => return 0.0,
+            },
+        };
+        let (dx, dy, dz) = (
+            position.x - listener.x,
+            position.y - listener.y,
+            position.z - listener.z,
+        );
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+}
+
+impl Drop for VoiceManager {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+// Where a virtual voice would be if it had kept playing.
+// Returns `Ok(None)` if it would have reached the end. If the cursor is unknown
+// (a sound that does not report one), it resumes where it is.
+fn virtual_cursor(
+    sound: &Sound,
+    state: VirtualState,
+    now: u64,
+    engine_rate: u32,
+) -> MaResult<Option<u64>> {
+    let Some(cursor) = state.cursor else {
+        return Ok(Some(sound.cursor_pcm().unwrap_or(0)));
+    };
+    let elapsed = now.saturating_sub(state.engine_time);
+    let sound_rate: u32 = sound.data_format()?.sample_rate.into();
+    let advanced = if engine_rate == 0 {
+        elapsed
+    } else {
+        (elapsed as f64 * sound_rate as f64 / engine_rate as f64 * sound.pitch() as f64) as u64
+    };
+    let target = cursor.saturating_add(advanced);
+
+    let length = match sound.length_pcm() {
+        Ok(length) if length > 0 => length,
+        _ => return Ok(Some(target)),
+    };
+    if target < length {
+        Ok(Some(target))
+    } else if sound.looping() {
+        Ok(Some(target % length))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::{math::vec3::Vec3, sample_rate::SampleRate},
+        engine::{resource::RmOps, Engine},
+        sound::{sound_flags::SoundFlags, sound_params::SoundParams, voice_manager::VoiceManager},
+        test_assets::wav_i16_le,
+    };
+
+    fn one_second_wav() -> Vec<u8> {
+        let samples: Vec<i16> = (0..44100).map(|i| (i % 1000) as i16).collect();
+        wav_i16_le(1, SampleRate::Sr44100, &samples)
+    }
+
+    #[test]
+    fn test_voice_manager_limits_by_priority() {
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let wav = one_second_wav();
+        let _guard = rm.register_encoded("voice_prio.wav", &wav).unwrap();
+        let new_sound = || {
+            engine
+                .new_sound_from_registered("voice_prio.wav", SoundFlags::DECODE)
+                .unwrap()
+        };
+
+        let mut voices = VoiceManager::new(&engine, 2);
+        let low = voices.play(new_sound(), 1).unwrap();
+        let mid = voices.play(new_sound(), 5).unwrap();
+        assert!(!voices.is_virtual(low));
+
+        let high = voices.play(new_sound(), 9).unwrap();
+        assert!(voices.is_virtual(low));
+        assert!(!voices.is_virtual(mid));
+        assert!(!voices.is_virtual(high));
+        assert!(!voices.sound(low).unwrap().is_playing());
+        assert_eq!(voices.audible_count(), 2);
+        assert_eq!(voices.virtual_count(), 1);
+
+        voices.stop(high).unwrap();
+        voices.update().unwrap();
+        assert!(!voices.is_virtual(low));
+        assert!(voices.sound(low).unwrap().is_playing());
+        assert!(!voices.contains(high));
+
+        let quiet = SoundParams {
+            volume: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(voices.apply_batch(&[(low, quiet), (high, quiet)]), 1);
+        assert_eq!(voices.sound(low).unwrap().volume(), 0.5);
+    }
+
+    #[test]
+    fn test_voice_manager_steals_farthest_voice() {
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let wav = one_second_wav();
+        let _guard = rm.register_encoded("voice_dist.wav", &wav).unwrap();
+        engine
+            .listener(0)
+            .unwrap()
+            .set_position(Vec3::new(0.0, 0.0, 0.0));
+
+        let mut voices = VoiceManager::new(&engine, 1);
+        let mut far = engine
+            .new_sound_from_registered("voice_dist.wav", SoundFlags::DECODE)
+            .unwrap();
+        far.set_position(Vec3::new(50.0, 0.0, 0.0));
+        let far = voices.play(far, 3).unwrap();
+        assert!(!voices.is_virtual(far));
+
+        let mut near = engine
+            .new_sound_from_registered("voice_dist.wav", SoundFlags::DECODE)
+            .unwrap();
+        near.set_position(Vec3::new(1.0, 0.0, 0.0));
+        let near = voices.play(near, 3).unwrap();
+        assert!(voices.is_virtual(far));
+        assert!(!voices.is_virtual(near));
+
+        // Moving the near sound away swaps them on the next update
+        voices
+            .sound_mut(near)
+            .unwrap()
+            .set_position(Vec3::new(100.0, 0.0, 0.0));
+        voices.update().unwrap();
+        assert!(!voices.is_virtual(far));
+        assert!(voices.is_virtual(near));
+    }
+
+    #[test]
+    fn test_voice_manager_virtual_voice_advances() {
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let wav = one_second_wav();
+        let _guard = rm.register_encoded("voice_virtual.wav", &wav).unwrap();
+
+        let mut voices = VoiceManager::new(&engine, 1);
+        let background = voices
+            .play(
+                engine
+                    .new_sound_from_registered("voice_virtual.wav", SoundFlags::DECODE)
+                    .unwrap(),
+                1,
+            )
+            .unwrap();
+        let important = voices
+            .play(
+                engine
+                    .new_sound_from_registered("voice_virtual.wav", SoundFlags::DECODE)
+                    .unwrap(),
+                2,
+            )
+            .unwrap();
+        assert!(voices.is_virtual(background));
+        let cursor_before = voices.sound(background).unwrap().cursor_pcm().unwrap();
+
+        let engine_rate = engine.sample_rate_u32() as u64;
+        engine.set_time_pcm(engine.time_pcm() + engine_rate / 10);
+        voices.stop(important).unwrap();
+        voices.update().unwrap();
+
+        assert!(!voices.is_virtual(background));
+        let cursor_after = voices.sound(background).unwrap().cursor_pcm().unwrap();
+        assert!(cursor_after >= cursor_before + 44100 / 10);
+
+        // A non looping virtual voice past its end is removed
+        let filler = voices
+            .play(
+                engine
+                    .new_sound_from_registered("voice_virtual.wav", SoundFlags::DECODE)
+                    .unwrap(),
+                2,
+            )
+            .unwrap();
+        assert!(voices.is_virtual(background));
+        engine.set_time_pcm(engine.time_pcm() + engine_rate * 2);
+        voices.stop(filler).unwrap();
+        voices.update().unwrap();
+        assert!(!voices.contains(background));
+        assert!(voices.is_empty());
+    }
+
+    #[test]
+    fn test_voice_manager_voice_starting_virtual_advances_and_expires() {
+        let engine = Engine::new_for_tests().unwrap();
+        let rm = engine.resource_manager().unwrap();
+        let wav = one_second_wav();
+        let _guard = rm
+            .register_encoded("voice_start_virtual.wav", &wav)
+            .unwrap();
+        let new_sound = || {
+            engine
+                .new_sound_from_registered("voice_start_virtual.wav", SoundFlags::DECODE)
+                .unwrap()
+        };
+
+        let mut voices = VoiceManager::new(&engine, 1);
+        let important = voices.play(new_sound(), 2).unwrap();
+        let background = voices.play(new_sound(), 1).unwrap();
+        assert!(voices.is_virtual(background));
+        assert_eq!(voices.sound(background).unwrap().cursor_pcm().unwrap(), 0);
+
+        let engine_rate = engine.sample_rate_u32() as u64;
+        engine.set_time_pcm(engine.time_pcm() + engine_rate / 10);
+        voices.stop(important).unwrap();
+        voices.update().unwrap();
+        assert!(!voices.is_virtual(background));
+        let cursor = voices.sound(background).unwrap().cursor_pcm().unwrap();
+        assert!(cursor >= 44100 / 10);
+
+        // A voice that never became audible expires once past its end
+        let filler = voices.play(new_sound(), 2).unwrap();
+        let late = voices.play(new_sound(), 1).unwrap();
+        assert!(voices.is_virtual(late));
+        engine.set_time_pcm(engine.time_pcm() + engine_rate * 2);
+        voices.update().unwrap();
+        assert!(!voices.contains(late));
+        assert!(voices.contains(filler));
+    }
+}
@@ -199,3 +199,78 @@ pub(crate) fn wav_i16_le(
 
     out
 }
+
+/// Appends `cue `, `LIST`/`adtl` and `smpl` chunks to a WAV file built by [`wav_i16_le`].
+///
+/// `cues` are `(id, frame, label)` and `loops` are `(id, first frame, last frame)`.
+pub(crate) fn wav_with_metadata(
+    wav: &[u8],
+    cues: &[(u32, u32, Option<&str>)],
+    loops: &[(u32, u32, u32)],
+) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+        out.extend_from_slice(id);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+
+    let mut out = wav.to_vec();
+    if !cues.is_empty() {
+        let mut body = (cues.len() as u32).to_le_bytes().to_vec();
+        for (id, frame, _) in cues {
+            body.extend_from_slice(&id.to_le_bytes());
+            body.extend_from_slice(&frame.to_le_bytes());
+            body.extend_from_slice(b"data");
+            body.extend_from_slice(&[0; 8]);
+            body.extend_from_slice(&frame.to_le_bytes());
+        }
+        chunk(&mut out, b"cue ", &body);
+    }
+    if cues.iter().any(|(_, _, label)| label.is_some()) {
+        let mut body = b"adtl".to_vec();
+        for (id, _, label) in cues {
+            if let Some(label) = label {
+                let mut labl = id.to_le_bytes().to_vec();
+                labl.extend_from_slice(label.as_bytes());
+                labl.push(0);
+                chunk(&mut body, b"labl", &labl);
+            }
+        }
+        chunk(&mut out, b"LIST", &body);
+    }
+    if !loops.is_empty() {
+        let mut body = vec![0; 28];
+        body.extend_from_slice(&(loops.len() as u32).to_le_bytes());
+        body.extend_from_slice(&[0; 4]);
+        for (id, first, last) in loops {
+            body.extend_from_slice(&id.to_le_bytes());
+            body.extend_from_slice(&0u32.to_le_bytes());
+            body.extend_from_slice(&first.to_le_bytes());
+            body.extend_from_slice(&last.to_le_bytes());
+            body.extend_from_slice(&[0; 8]);
+        }
+        chunk(&mut out, b"smpl", &body);
+    }
+
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    out
+}
+
+/// Build a silent mono MP3 stream of `mp3_frames` frames of 1152 samples at 44100 Hz.
+///
+/// Each frame is an MPEG-1 Layer III header (128 kbit/s, no CRC) followed by zeroed side
+/// info and main data, which decodes to silence.
+pub(crate) fn silent_mp3(mp3_frames: usize) -> Vec<u8> {
+    // 144 * 128000 / 44100, without padding
+    const FRAME_BYTES: usize = 417;
+    let mut out = Vec::with_capacity(mp3_frames * FRAME_BYTES);
+    for _ in 0..mp3_frames {
+        out.extend_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+        out.resize(out.len() + FRAME_BYTES - 4, 0);
+    }
+    out
+}
@@ -0,0 +1,97 @@
+//! What happens when user code panics inside a callback.
+//!
+//! miniaudio calls back into Rust from its own threads (data callbacks, custom nodes,
+//! data sources, decoder backends, readers and writers, notifications). Unwinding out of
+//! one of these callbacks and into C is undefined behavior, so every callback that runs
+//! user code is wrapped in [`std::panic::catch_unwind`].
+//!
+//! The [`CallbackPanicPolicy`] decides what happens after a panic is caught:
+//!
+//! - [`CallbackPanicPolicy::Mute`] (the default) outputs silence or returns an error to
+//!   miniaudio, and flags the callback. Callbacks that run on the audio thread are
+//!   poisoned and no longer run user code. Errors reported back to the caller use
+//!   [`ErrorKinds::CallbackPanicked`].
+//! - [`CallbackPanicPolicy::Abort`] aborts the process, for applications that prefer to
+//!   fail loudly.
+//!
+//! ```
+//! # use maudio::util::callback_panic::{self, CallbackPanicPolicy};
+//! callback_panic::set_policy(CallbackPanicPolicy::Abort);
+//! assert_eq!(callback_panic::policy(), CallbackPanicPolicy::Abort);
+//! # callback_panic::set_policy(CallbackPanicPolicy::Mute);
+//! ```
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+use crate::{ErrorKinds, MaResult, MaudioError};
+
+static POLICY: AtomicU8 = AtomicU8::new(CallbackPanicPolicy::Mute as u8);
+static PANIC_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// What to do after a panic is caught in a callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum CallbackPanicPolicy {
+    /// Output silence or report an error to miniaudio, and flag the callback.
+    #[default]
+    Mute,
+    /// Abort the process.
+    Abort,
+}
+
+/// Sets the policy used by all callbacks in the process.
+pub fn set_policy(policy: CallbackPanicPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Returns the current policy.
+pub fn policy() -> CallbackPanicPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        1 => CallbackPanicPolicy::Abort,
+        _ => CallbackPanicPolicy::Mute,
+    }
+}
+
+/// Number of panics caught in callbacks since the process started.
+pub fn panic_count() -> u64 {
+    PANIC_COUNT.load(Ordering::Relaxed)
+}
+
+/// Runs `f`, catching a panic according to the current policy.
+///
+/// Returns [`ErrorKinds::CallbackPanicked`] if `f` panicked and the policy is
+/// [`CallbackPanicPolicy::Mute`].
+pub(crate) fn guard<R>(f: impl FnOnce() -> R) -> MaResult<R> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => Ok(value),
+        Err(_) => {
+            PANIC_COUNT.fetch_add(1, Ordering::Relaxed);
+            if policy() == CallbackPanicPolicy::Abort {
+                std::process::abort();
+            }
+            Err(MaudioError::new_ma_error(ErrorKinds::CallbackPanicked))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_callback_panic_guard_mutes() {
+        let before = panic_count();
+        assert_eq!(guard(|| 3).unwrap(), 3);
+
+        let err = guard(|| -> u32 { panic!("user code") }).unwrap_err();
+        assert_eq!(err.kind(), Some(&ErrorKinds::CallbackPanicked));
+        assert!(panic_count() > before);
+    }
+
+    #[test]
+    fn test_callback_panic_default_policy() {
+        assert_eq!(CallbackPanicPolicy::default(), CallbackPanicPolicy::Mute);
+    }
+}
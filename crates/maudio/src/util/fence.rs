@@ -22,7 +22,11 @@
 //! # Ok(())
 //! # }
 //! ```
-use std::{mem::MaybeUninit, sync::Arc};
+use std::{
+    mem::MaybeUninit,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use maudio_sys::ffi as sys;
 
@@ -109,9 +113,47 @@ impl Fence {
     pub fn wait(&self) -> MaResult<()> {
         fence_ffi::ma_fence_wait(self.clone())
     }
+
+    /// Blocks the current thread until the fence is released, or `timeout` has passed.
+    ///
+    /// Returns `true` if the fence was released. Unlike [`Fence::wait()`], this polls the
+    /// fence with short sleeps, so it may return up to a millisecond after the release.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut sleep = Duration::from_micros(50);
+        loop {
+            if self.is_released() {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            std::thread::sleep(sleep.min(deadline - now));
+            sleep = (sleep * 2).min(Duration::from_millis(1));
+        }
+    }
+
+    /// Returns `true` if no [`FenceGuard`] (or asynchronous load) holds the fence.
+    pub fn is_released(&self) -> bool {
+        fence_ffi::ma_fence_counter(self) == 0
+    }
+
+    /// Blocks the current thread until every fence in `fences` is released.
+    ///
+    /// Useful when many sounds are loaded asynchronously, each with its own fence. Use
+    /// [`Fence::is_released`] on each fence to show progress while waiting.
+    pub fn wait_all(fences: &[&Fence]) -> MaResult<()> {
+        for fence in fences {
+            fence.wait()?;
+        }
+        Ok(())
+    }
 }
 
 pub(crate) mod fence_ffi {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
     use maudio_sys::ffi as sys;
 
     use crate::{util::fence::Fence, Binding, MaResult, MaudioError};
@@ -121,9 +163,9 @@ pub(crate) mod fence_ffi {
         MaudioError::check(res)
     }
 
-    pub fn ma_fence_uninit(fence: Fence) {
+    pub fn ma_fence_uninit(fence: *mut sys::ma_fence) {
         unsafe {
-            sys::ma_fence_uninit(fence.to_raw());
+            sys::ma_fence_uninit(fence);
         }
     }
 
@@ -141,12 +183,19 @@ pub(crate) mod fence_ffi {
         let res = unsafe { sys::ma_fence_wait(fence.to_raw()) };
         MaudioError::check(res)
     }
+
+    // Guards on other threads acquire and release the fence, so the counter is read atomically
+    pub fn ma_fence_counter(fence: &Fence) -> u32 {
+        let counter = unsafe { core::ptr::addr_of_mut!((*fence.to_raw()).counter) };
+        unsafe { (*counter.cast::<AtomicU32>()).load(Ordering::Acquire) }
+    }
 }
 
-impl Drop for Fence {
+// Uninitialized once the last clone of the `Fence` is dropped
+impl Drop for FenceInner {
     fn drop(&mut self) {
-        fence_ffi::ma_fence_uninit(self.clone());
-        drop(unsafe { Box::from_raw(self.to_raw()) });
+        fence_ffi::ma_fence_uninit(self.inner);
+        drop(unsafe { Box::from_raw(self.inner) });
     }
 }
 
@@ -157,3 +206,48 @@ impl Drop for FenceGuard {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::util::fence::Fence;
+
+    #[test]
+    fn test_fence_wait_timeout() {
+        let fence = Fence::new().unwrap();
+        assert!(fence.wait_timeout(Duration::ZERO));
+
+        let guard = fence.acquire().unwrap();
+        assert!(!fence.is_released());
+        assert!(!fence.wait_timeout(Duration::from_millis(5)));
+
+        let clone = fence.clone();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            drop(guard);
+        });
+        assert!(clone.wait_timeout(Duration::from_secs(5)));
+        assert!(fence.is_released());
+        releaser.join().unwrap();
+    }
+
+    #[test]
+    fn test_fence_wait_all() {
+        let fences: Vec<Fence> = (0..3).map(|_| Fence::new().unwrap()).collect();
+        let guards: Vec<_> = fences.iter().map(|f| f.acquire().unwrap()).collect();
+
+        let releaser = std::thread::spawn(move || {
+            for guard in guards {
+                std::thread::sleep(Duration::from_millis(2));
+                drop(guard);
+            }
+        });
+        let refs: Vec<&Fence> = fences.iter().collect();
+        Fence::wait_all(&refs).unwrap();
+        assert!(fences.iter().all(|f| f.is_released()));
+        releaser.join().unwrap();
+
+        Fence::wait_all(&[]).unwrap();
+    }
+}
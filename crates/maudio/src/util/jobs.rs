@@ -0,0 +1,524 @@
+//! A job queue running user jobs on miniaudio's lock-free queue.
+//!
+//! The resource manager schedules its loading and decoding work as jobs on an `ma_job_queue`.
+//! [`JobQueue`] exposes the same queue for user jobs, so decode or DSP work can be handed
+//! to worker threads without a lock in the way: posting a job never blocks, and can be
+//! done from any thread.
+//!
+//! Jobs are closures posted with [`JobQueue::post`]. They are run by
+//! [`JobQueue::spawn_workers`], which starts threads processing the queue, or pumped
+//! manually with [`JobQueue::process_next`], [`JobQueue::run_until_empty`] and
+//! [`JobQueue::run_for`], like the jobs of a resource manager in
+//! [`rm_jobs`](crate::engine::resource::rm_jobs).
+//!
+//! ```no_run
+//! # use maudio::util::jobs::JobQueueBuilder;
+//! # fn main() -> maudio::MaResult<()> {
+//! let queue = JobQueueBuilder::new().capacity(256).build()?;
+//! let workers = queue.spawn_workers(4)?;
+//!
+//! for track in ["a.mp3", "b.mp3"] {
+//!     queue.post(move || {
+//!         // Decode `track` and hand the result over
+//!         println!("decoding {track}");
+//!     })?;
+//! }
+//!
+//! // Waits for the workers to finish the posted jobs
+//! workers.join();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Jobs can also be posted on the queue of a resource manager with
+//! [`RmOps::post_custom_job`](crate::engine::resource::RmOps::post_custom_job), where they
+//! run on its job threads.
+use std::{
+    mem::MaybeUninit,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    engine::resource::rm_jobs::JobStatus, util::callback_panic, ErrorKinds, MaResult, MaudioError,
+};
+
+/// Capacity of a queue by default, the same as the resource manager's queue.
+pub const DEFAULT_CAPACITY: u32 = 1024;
+
+// How long a worker on a non-blocking queue sleeps when the queue is empty
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+type JobFn = Box<dyn FnOnce() + Send + 'static>;
+
+/// Builds a [`JobQueue`].
+pub struct JobQueueBuilder {
+    capacity: u32,
+    non_blocking: bool,
+}
+
+impl Default for JobQueueBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobQueueBuilder {
+    pub fn new() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            non_blocking: false,
+        }
+    }
+
+    /// Maximum number of jobs waiting in the queue. Posting to a full queue fails.
+    pub fn capacity(&mut self, capacity: u32) -> &mut Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Taking a job from an empty queue returns [`JobStatus::Empty`] instead of waiting.
+    pub fn non_blocking(&mut self, yes: bool) -> &mut Self {
+        self.non_blocking = yes;
+        self
+    }
+
+    pub fn build(&self) -> MaResult<JobQueue> {
+        if self.capacity == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let flags = if self.non_blocking {
+            sys::ma_job_queue_flags_MA_JOB_QUEUE_FLAG_NON_BLOCKING
+        } else {
+            0
+        };
+        let config = unsafe { sys::ma_job_queue_config_init(flags, self.capacity) };
+
+        let mut mem: Box<MaybeUninit<sys::ma_job_queue>> = Box::new(MaybeUninit::uninit());
+        let res = unsafe { sys::ma_job_queue_init(&config, std::ptr::null(), mem.as_mut_ptr()) };
+        MaudioError::check(res)?;
+        let queue = Box::into_raw(mem) as *mut sys::ma_job_queue;
+
+        Ok(JobQueue {
+            inner: Arc::new(QueueInner {
+                queue,
+                non_blocking: self.non_blocking,
+                pending: AtomicUsize::new(0),
+            }),
+        })
+    }
+}
+
+/// A queue of user jobs. See the [module docs](self).
+///
+/// Cloning the queue is cheap, and the clones share their jobs.
+#[derive(Clone)]
+pub struct JobQueue {
+    inner: Arc<QueueInner>,
+}
+
+struct QueueInner {
+    queue: *mut sys::ma_job_queue,
+    non_blocking: bool,
+    // Custom jobs posted and not taken yet, freed when the queue is dropped
+    pending: AtomicUsize,
+}
+
+// The queue is lock-free and can be used from any thread
+unsafe impl Send for QueueInner {}
+unsafe impl Sync for QueueInner {}
+
+impl Drop for QueueInner {
+    fn drop(&mut self) {
+        // Free the closures of the jobs that never ran. The quit job is posted again each
+        // time it is taken, but custom jobs are never behind an empty queue.
+        while self.pending.load(Ordering::Relaxed) > 0 {
+            let mut job: sys::ma_job = unsafe { std::mem::zeroed() };
+            let res = unsafe { sys::ma_job_queue_next(self.queue, &mut job) };
+            match res {
+                sys::ma_result_MA_SUCCESS => {
+                    if is_custom(&job) {
+                        self.pending.fetch_sub(1, Ordering::Relaxed);
+                        unsafe { free_custom_job(&mut job) };
+                    }
+                }
+                sys::ma_result_MA_CANCELLED => {}
+                _ => break,
+            }
+        }
+        unsafe {
+            sys::ma_job_queue_uninit(self.queue, std::ptr::null());
+            drop(Box::from_raw(
+                self.queue as *mut MaybeUninit<sys::ma_job_queue>,
+            ));
+        }
+    }
+}
+
+impl JobQueue {
+    /// Creates a blocking queue with the default capacity.
+    pub fn new() -> MaResult<Self> {
+        JobQueueBuilder::new().build()
+    }
+
+    /// Posts `f` to the queue, to be run by the next thread taking a job.
+    ///
+    /// Never blocks. Fails if the queue is full.
+    pub fn post<F: FnOnce() + Send + 'static>(&self, f: F) -> MaResult<()> {
+        let mut job = custom_job(f);
+        self.inner.pending.fetch_add(1, Ordering::Relaxed);
+        let res = unsafe { sys::ma_job_queue_post(self.inner.queue, &job) };
+        if let Err(e) = MaudioError::check(res) {
+            self.inner.pending.fetch_sub(1, Ordering::Relaxed);
+            unsafe { free_custom_job(&mut job) };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Posts a quit job to the queue.
+    ///
+    /// Wakes up and stops any thread waiting on the queue, including the workers.
+    /// The quit job stays in the queue, so every consumer will see it.
+    pub fn post_quit(&self) -> MaResult<()> {
+        let job = unsafe { sys::ma_job_init(sys::ma_job_type_MA_JOB_TYPE_QUIT as u16) };
+        let res = unsafe { sys::ma_job_queue_post(self.inner.queue, &job) };
+        MaudioError::check(res)
+    }
+
+    /// Takes the next job from the queue without running it.
+    ///
+    /// Returns `Ok(None)` if the queue is empty (non-blocking mode only) or if a quit
+    /// job was posted. Unless the queue is non-blocking, this waits until a job is available.
+    pub fn next_job(&self) -> MaResult<Option<Job>> {
+        let mut job: sys::ma_job = unsafe { std::mem::zeroed() };
+        let res = unsafe { sys::ma_job_queue_next(self.inner.queue, &mut job) };
+        match job_status(res)? {
+            JobStatus::Processed => {
+                if is_custom(&job) {
+                    self.inner.pending.fetch_sub(1, Ordering::Relaxed);
+                }
+                Ok(Some(Job {
+                    job,
+                    processed: false,
+                }))
+            }
+            JobStatus::Empty | JobStatus::Quit => Ok(None),
+        }
+    }
+
+    /// Takes the next job from the queue and runs it.
+    ///
+    /// A job that panicked returns [`ErrorKinds::CallbackPanicked`](crate::ErrorKinds::CallbackPanicked).
+    pub fn process_next(&self) -> MaResult<JobStatus> {
+        let mut job: sys::ma_job = unsafe { std::mem::zeroed() };
+        let res = unsafe { sys::ma_job_queue_next(self.inner.queue, &mut job) };
+        let status = job_status(res)?;
+        if status == JobStatus::Processed {
+            if is_custom(&job) {
+                self.inner.pending.fetch_sub(1, Ordering::Relaxed);
+            }
+            process(&mut job)?;
+        }
+        Ok(status)
+    }
+
+    /// Runs jobs until the queue is empty or a quit job is received.
+    ///
+    /// Returns the number of jobs run. Stops at the first job that fails.
+    pub fn run_until_empty(&self) -> MaResult<usize> {
+        let mut count = 0;
+        while self.process_next()? == JobStatus::Processed {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Runs jobs until `budget` has elapsed, the queue is empty, or a quit job is received.
+    ///
+    /// The budget is checked between jobs, so a single long job can exceed it.
+    /// Returns the number of jobs run. Stops at the first job that fails.
+    pub fn run_for(&self, budget: Duration) -> MaResult<usize> {
+        let start = Instant::now();
+        let mut count = 0;
+        while start.elapsed() < budget {
+            if self.process_next()? != JobStatus::Processed {
+                break;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Starts `count` threads running the jobs of the queue until a quit job is received.
+    ///
+    /// Workers keep running after a job fails or panics. On a non-blocking queue, idle
+    /// workers poll the queue instead of waiting on it.
+    pub fn spawn_workers(&self, count: usize) -> MaResult<JobWorkers> {
+        if count == 0 {
+            return Err(MaudioError::from_ma_result(sys::ma_result_MA_INVALID_ARGS));
+        }
+        let mut handles = Vec::with_capacity(count);
+        for i in 0..count {
+            let queue = self.clone();
+            let handle = thread::Builder::new()
+                .name(format!("maudio-job-{i}"))
+                .spawn(move || queue.worker_loop());
+            match handle {
+                Ok(handle) => handles.push(handle),
+                Err(_) => {
+                    // Stop the workers already started
+                    drop(JobWorkers {
+                        queue: self.clone(),
+                        handles,
+                    });
+                    return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                        "failed to spawn a job worker thread",
+                    )));
+                }
+            }
+        }
+        Ok(JobWorkers {
+            queue: self.clone(),
+            handles,
+        })
+    }
+
+    pub fn is_non_blocking(&self) -> bool {
+        self.inner.non_blocking
+    }
+
+    /// Number of jobs posted with [`JobQueue::post`] and not taken from the queue yet.
+    pub fn pending(&self) -> usize {
+        self.inner.pending.load(Ordering::Relaxed)
+    }
+
+    fn worker_loop(&self) {
+        loop {
+            match self.process_next() {
+                Ok(JobStatus::Quit) => break,
+                Ok(JobStatus::Empty) => thread::sleep(IDLE_SLEEP),
+                // Failed jobs are reported by the panic count, not by the worker
+                Ok(JobStatus::Processed) | Err(_) => {}
+            }
+        }
+    }
+}
+
+/// A job taken from a [`JobQueue`].
+///
+/// If the job is dropped without calling [`Job::process`], it is processed in `Drop`
+/// and any error is ignored, so the resources held by the job are released.
+pub struct Job {
+    job: sys::ma_job,
+    processed: bool,
+}
+
+impl Job {
+    /// Returns the raw miniaudio job type (`ma_job_type`).
+    pub fn job_type(&self) -> u16 {
+        unsafe { self.job.toc.breakup.code }
+    }
+
+    /// Runs the job.
+    pub fn process(mut self) -> MaResult<()> {
+        self.processed = true;
+        process(&mut self.job)
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if !self.processed {
+            let _ = process(&mut self.job);
+        }
+    }
+}
+
+/// Worker threads started by [`JobQueue::spawn_workers`].
+///
+/// Dropping the workers posts a quit job and waits for the threads, like [`JobWorkers::join`].
+pub struct JobWorkers {
+    queue: JobQueue,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl JobWorkers {
+    /// Number of worker threads.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Posts a quit job and waits for the workers to exit.
+    ///
+    /// The quit job is behind the jobs already posted, so they are all run first.
+    pub fn join(self) {
+        drop(self);
+    }
+}
+
+impl Drop for JobWorkers {
+    fn drop(&mut self) {
+        if self.handles.is_empty() {
+            return;
+        }
+        // A full queue is drained by the workers, retry until the quit job fits
+        while self.queue.post_quit().is_err() {
+            thread::sleep(IDLE_SLEEP);
+        }
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Creates a custom job running `f`. The job owns `f` until it is processed or freed.
+pub(crate) fn custom_job<F: FnOnce() + Send + 'static>(f: F) -> sys::ma_job {
+    let boxed: Box<JobFn> = Box::new(Box::new(f));
+    let mut job = unsafe { sys::ma_job_init(sys::ma_job_type_MA_JOB_TYPE_CUSTOM as u16) };
+    // Writing union fields is safe, only reading them is not
+    job.data.custom.proc_ = Some(custom_job_proc);
+    job.data.custom.data0 = Box::into_raw(boxed) as usize as u64;
+    job.data.custom.data1 = 0;
+    job
+}
+
+/// Frees the closure of a custom job that will not run.
+///
+/// # Safety
+/// `job` must come from [`custom_job`] and must not have been processed.
+pub(crate) unsafe fn free_custom_job(job: &mut sys::ma_job) {
+    let data = job.data.custom.data0 as usize as *mut JobFn;
+    if !data.is_null() {
+        job.data.custom.data0 = 0;
+        drop(Box::from_raw(data));
+    }
+}
+
+fn is_custom(job: &sys::ma_job) -> bool {
+    // Only `JobQueue::post` posts custom jobs
+    let code = unsafe { job.toc.breakup.code };
+    code as u32 == sys::ma_job_type_MA_JOB_TYPE_CUSTOM
+}
+
+fn process(job: &mut sys::ma_job) -> MaResult<()> {
+    let res = unsafe { sys::ma_job_process(job) };
+    if res == sys::ma_result_MA_ERROR {
+        return Err(MaudioError::new_ma_error(ErrorKinds::CallbackPanicked));
+    }
+    MaudioError::check(res)
+}
+
+// MA_NO_DATA_AVAILABLE is only returned by a non-blocking queue with no jobs.
+// MA_CANCELLED means a quit job was received.
+fn job_status(res: sys::ma_result) -> MaResult<JobStatus> {
+    match res {
+        sys::ma_result_MA_SUCCESS => Ok(JobStatus::Processed),
+        sys::ma_result_MA_NO_DATA_AVAILABLE => Ok(JobStatus::Empty),
+        sys::ma_result_MA_CANCELLED => Ok(JobStatus::Quit),
+        _ => Err(MaudioError::from_ma_result(res)),
+    }
+}
+
+unsafe extern "C" fn custom_job_proc(job: *mut sys::ma_job) -> sys::ma_result {
+    if job.is_null() {
+        return sys::ma_result_MA_INVALID_ARGS;
+    }
+    let data = (*job).data.custom.data0 as usize as *mut JobFn;
+    if data.is_null() {
+        return sys::ma_result_MA_INVALID_ARGS;
+    }
+    // The closure is only run once, even if the job is processed again
+    (*job).data.custom.data0 = 0;
+    let f = Box::from_raw(data);
+    match callback_panic::guard(f) {
+        Ok(()) => sys::ma_result_MA_SUCCESS,
+        // Reported as `CallbackPanicked` by `process`
+        Err(_) => sys::ma_result_MA_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_jobs_non_blocking_queue_runs_posted_jobs() {
+        let queue = JobQueueBuilder::new()
+            .capacity(8)
+            .non_blocking(true)
+            .build()
+            .unwrap();
+        assert_eq!(queue.process_next().unwrap(), JobStatus::Empty);
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..3 {
+            let counter = counter.clone();
+            queue
+                .post(move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })
+                .unwrap();
+        }
+        assert_eq!(queue.pending(), 3);
+
+        let job = queue.next_job().unwrap().unwrap();
+        assert_eq!(job.job_type(), sys::ma_job_type_MA_JOB_TYPE_CUSTOM as u16);
+        job.process().unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+        // Dropped jobs still run
+        drop(queue.next_job().unwrap());
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+
+        assert_eq!(queue.run_until_empty().unwrap(), 1);
+        assert_eq!(counter.load(Ordering::Relaxed), 3);
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn test_jobs_panicking_job_and_unrun_jobs() {
+        let queue = JobQueueBuilder::new().non_blocking(true).build().unwrap();
+        queue.post(|| panic!("job panicked")).unwrap();
+        assert!(queue.process_next().is_err());
+
+        // Jobs left in the queue are freed with it
+        let counter = Arc::new(AtomicUsize::new(0));
+        let held = counter.clone();
+        queue.post(move || drop(held)).unwrap();
+        queue.post_quit().unwrap();
+        assert_eq!(Arc::strong_count(&counter), 2);
+        drop(queue);
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn test_jobs_workers_run_jobs() {
+        let queue = JobQueue::new().unwrap();
+        let workers = queue.spawn_workers(2).unwrap();
+        assert_eq!(workers.len(), 2);
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..100 {
+            let counter = counter.clone();
+            queue
+                .post(move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })
+                .unwrap();
+        }
+        workers.join();
+        assert_eq!(counter.load(Ordering::Relaxed), 100);
+        assert!(queue.spawn_workers(0).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Memory-mapped audio files, behind the `memmap2` feature.
+//!
+//! Decoding a file from memory usually means reading the whole file into a `Vec<u8>` first.
+//! For large assets, like long uncompressed WAV files, that copy stays in RAM next to the
+//! decoded audio. A [`MappedFile`] maps the file instead, so the operating system pages it
+//! in as the decoder reads it, and can drop those pages again under memory pressure.
+//!
+//! A mapping can be shared: decoders and the resource manager hold a clone, and the file is
+//! unmapped once the last one is dropped.
+//!
+//! ```no_run
+//! # use maudio::util::mapped_file::MappedFile;
+//! # use maudio::data_source::sources::decoder::DecoderBuilder;
+//! # use maudio::engine::resource::{RmOps, rm_builder::ResourceManagerBuilder};
+//! # use maudio::audio::sample_rate::SampleRate;
+//! # use std::path::Path;
+//! # fn main() -> maudio::MaResult<()> {
+//! let file = MappedFile::open(Path::new("ambience.wav"))?;
+//!
+//! let decoder = DecoderBuilder::new_f32(2, SampleRate::Sr48000).from_mapped(&file)?;
+//!
+//! let rm = ResourceManagerBuilder::new().build_f32()?;
+//! let guard = rm.register_mapped("ambience", &file)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Without the feature, memory the application mapped itself can be passed as a
+//! `&'static [u8]` to [`DecoderBuilder::from_memory`] and [`RmOps::register_encoded`].
+//!
+//! The mapping assumes the file is not modified or truncated while it is mapped. Doing so
+//! from another process is undefined behavior, as with any memory-mapped file.
+//!
+//! [`DecoderBuilder::from_memory`]: crate::data_source::sources::decoder::DecoderBuilder::from_memory
+//! [`RmOps::register_encoded`]: crate::engine::resource::RmOps::register_encoded
+use std::{fs::File, ops::Deref, path::Path, sync::Arc};
+
+use crate::{MaResult, MaudioError, ResultContext};
+
+/// A read-only memory mapping of a file, cheap to clone.
+#[derive(Clone)]
+pub struct MappedFile {
+    map: Arc<memmap2::Mmap>,
+}
+
+impl MappedFile {
+    /// Maps the whole file at `path`.
+    pub fn open(path: &Path) -> MaResult<Self> {
+        let map = File::open(path)
+            .and_then(|file| {
+                // SAFETY: the mapping is read-only. See the module docs about modifying the
+                // file while it is mapped.
+                unsafe { memmap2::Mmap::map(&file) }
+            })
+            .map_err(MaudioError::from)
+            .with_path(path)?;
+        Ok(Self { map: Arc::new(map) })
+    }
+
+    /// Size of the file in bytes.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl core::fmt::Debug for MappedFile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MappedFile")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::{
+            sources::decoder::{DecoderBuilder, DecoderOps},
+            DataSourceOps,
+        },
+        engine::resource::{
+            rm_builder::ResourceManagerBuilder, rm_source_flags::RmSourceFlags, RmOps,
+        },
+        test_assets::{
+            temp_file::{unique_tmp_path, TempFileGuard},
+            wav_i16_le,
+        },
+        util::mapped_file::MappedFile,
+    };
+
+    fn mapped_wav(samples: &[i16]) -> (TempFileGuard, MappedFile) {
+        let guard = TempFileGuard::new(unique_tmp_path("wav"));
+        std::fs::write(guard.path(), wav_i16_le(1, SampleRate::Sr48000, samples)).unwrap();
+        let file = MappedFile::open(guard.path()).unwrap();
+        (guard, file)
+    }
+
+    #[test]
+    fn test_mapped_file_decoder_reads_the_mapping() {
+        let samples: Vec<i16> = (0..256).map(|i| i * 64).collect();
+        let (_guard, file) = mapped_wav(&samples);
+        assert_eq!(&file[0..4], b"RIFF");
+
+        let mut decoder = DecoderBuilder::new_f32(1, SampleRate::Sr48000)
+            .from_mapped(&file)
+            .unwrap();
+        // The decoder keeps its own clone of the mapping
+        drop(file);
+        assert_eq!(decoder.length_pcm().unwrap(), 256);
+        let out = decoder.read_pcm_frames(256).unwrap();
+        let expected: Vec<f32> = samples.iter().map(|s| *s as f32 / 32768.0).collect();
+        assert_eq!(out.as_ref(), expected.as_slice());
+    }
+
+    #[test]
+    fn test_mapped_file_register_with_resource_manager() {
+        let (_guard, file) = mapped_wav(&[1000; 64]);
+        let rm = ResourceManagerBuilder::new().build_f32().unwrap();
+        let guard = rm.register_mapped("mapped:wav", &file).unwrap();
+        drop(file);
+
+        let mut buffer = guard
+            .build_buffer(RmSourceFlags::NONE)
+            .unwrap()
+            .into_ready()
+            .ok()
+            .unwrap();
+        let out = buffer.read_pcm_frames(64).unwrap();
+        assert_eq!(out.frames(), 64);
+        assert!(out.as_ref().iter().all(|s| *s == 1000.0 / 32768.0));
+    }
+
+    #[test]
+    fn test_mapped_file_missing_path() {
+        let path = unique_tmp_path("wav");
+        let err = MappedFile::open(&path).unwrap_err();
+        assert_eq!(err.context(), Some(&crate::ErrorContext::Path(path)));
+    }
+}
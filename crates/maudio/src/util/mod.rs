@@ -1,3 +1,9 @@
+pub mod callback_panic;
 pub mod device_notif;
 pub mod fence;
+pub mod jobs;
+#[cfg(feature = "memmap2")]
+pub mod mapped_file;
 pub mod proc_notif;
+pub mod rt_check;
+pub mod watchdog;
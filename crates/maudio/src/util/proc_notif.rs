@@ -41,6 +41,12 @@ impl ProcFramesNotif {
             .fetch_add(frames, Ordering::Relaxed);
     }
 
+    // Total frames processed. Does not move the cursor used by `take_delta`.
+    #[inline]
+    pub(crate) fn frames_processed(&self) -> u64 {
+        self.inner.frames_processed.load(Ordering::Relaxed)
+    }
+
     /// Returns `true` if the process notification has been triggered (edge-triggered).
     ///
     /// This is a non-consuming check. It does not update the internal cursor used by
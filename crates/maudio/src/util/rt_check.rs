@@ -0,0 +1,191 @@
+//! Debug checks that code running on the audio thread stays real-time safe.
+//!
+//! Callbacks running on the audio thread must not allocate, lock or block, or the audio
+//! glitches whenever they take too long. Nothing stops a callback from calling a `maudio`
+//! function that does, so in debug builds the wrapper tracks when user code runs inside an
+//! audio callback, and reports the calls that are known to allocate or lock:
+//!
+//! - functions returning a new [`SampleBuffer`](crate::audio::formats::SampleBuffer), like
+//!   the `read_pcm_frames` methods of the engine reader and the sources,
+//! - creating sounds,
+//! - engine functions taking a lock shared with other threads, like
+//!   [`Engine::pause_all_except`](crate::engine::Engine::pause_all_except),
+//!   [`Engine::schedule_callback_at_pcm`](crate::engine::Engine::schedule_callback_at_pcm)
+//!   and the mixer snapshots,
+//! - [`BufferPool`](crate::audio::buffer_pool::BufferPool) and
+//!   [`Engine::subscribe_blocks`](crate::engine::Engine::subscribe_blocks).
+//!
+//! The audio callbacks are the ones installed with
+//! [`EngineBuilder::with_realtime_callback`](crate::engine::engine_builder::EngineBuilder::with_realtime_callback),
+//! the data callbacks of devices, and the `process_frames` method of custom nodes.
+//!
+//! The checks are compiled out of release builds. Setters that only store a value read by
+//! the audio thread, like `Sound::set_volume`, `Sound::set_pan` or `Sound::set_pitch`, and
+//! reads into caller provided memory (`read_pcm_frames_into`, or `read_pcm_frames_into_buffer`
+//! with a buffer that is already large enough) are real-time safe and never reported.
+//!
+//! What happens on a violation is set with [`set_action`]. The default,
+//! [`RtViolationAction::Panic`], panics in the callback, which is then handled by the
+//! [`callback_panic`](crate::util::callback_panic) policy.
+//!
+//! ```
+//! # use maudio::util::rt_check::{self, RtViolationAction};
+//! rt_check::set_action(RtViolationAction::Log);
+//! assert_eq!(rt_check::action(), RtViolationAction::Log);
+//! # rt_check::set_action(RtViolationAction::Panic);
+//! ```
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+static ACTION: AtomicU8 = AtomicU8::new(RtViolationAction::Panic as u8);
+static VIOLATIONS: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(debug_assertions)]
+thread_local! {
+    // Number of nested audio callbacks running on this thread
+    static DEPTH: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+}
+
+/// What to do when a function that is not real-time safe is called from an audio callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum RtViolationAction {
+    /// Panic, naming the function.
+    #[default]
+    Panic,
+    /// Print the function to stderr and continue.
+    Log,
+    /// Only count the violation, see [`violation_count`].
+    Ignore,
+}
+
+/// Sets the action used by all threads in the process.
+pub fn set_action(action: RtViolationAction) {
+    ACTION.store(action as u8, Ordering::Relaxed);
+}
+
+/// Returns the current action.
+pub fn action() -> RtViolationAction {
+    match ACTION.load(Ordering::Relaxed) {
+        1 => RtViolationAction::Log,
+        2 => RtViolationAction::Ignore,
+        _ => RtViolationAction::Panic,
+    }
+}
+
+/// Number of violations since the process started. Always `0` in release builds.
+pub fn violation_count() -> u64 {
+    VIOLATIONS.load(Ordering::Relaxed)
+}
+
+/// Returns `true` while the current thread runs user code inside an audio callback.
+///
+/// Always `false` in release builds.
+pub fn in_audio_callback() -> bool {
+    #[cfg(debug_assertions)]
+    {
+        DEPTH.with(|depth| depth.get() > 0)
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        false
+    }
+}
+
+/// Marks the current thread as running an audio callback until dropped.
+pub(crate) struct RtScope(());
+
+impl Drop for RtScope {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+    }
+}
+
+/// Enters an audio callback. Keep the returned scope alive while user code runs.
+#[inline]
+pub(crate) fn enter() -> RtScope {
+    #[cfg(debug_assertions)]
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+    RtScope(())
+}
+
+/// Reports `op` if it is called from an audio callback.
+#[inline]
+pub(crate) fn check(op: &'static str) {
+    #[cfg(debug_assertions)]
+    if in_audio_callback() {
+        violation(op);
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = op;
+}
+
+#[cfg(debug_assertions)]
+#[cold]
+fn violation(op: &'static str) {
+    VIOLATIONS.fetch_add(1, Ordering::Relaxed);
+    match action() {
+        RtViolationAction::Panic => {
+            panic!("{op} is not real-time safe and was called from an audio callback")
+        }
+        RtViolationAction::Log => {
+            eprintln!("maudio: {op} is not real-time safe and was called from an audio callback")
+        }
+        RtViolationAction::Ignore => {}
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod test {
+    use super::*;
+    use crate::{
+        audio::sample_rate::SampleRate,
+        data_source::sources::waveform::{WaveFormBuilder, WaveFormOps},
+        engine::engine_builder::EngineBuilder,
+    };
+
+    #[test]
+    fn test_rt_check_scope_nesting() {
+        assert!(!in_audio_callback());
+        let outer = enter();
+        let inner = enter();
+        drop(inner);
+        assert!(in_audio_callback());
+        drop(outer);
+        assert!(!in_audio_callback());
+
+        // Outside of a callback nothing is reported, and the default action does not panic
+        check("test op");
+    }
+
+    #[test]
+    fn test_rt_check_flags_allocating_read_in_realtime_callback() {
+        let mut wave = WaveFormBuilder::new_sine(SampleRate::Sr48000, 440.0)
+            .channels(2)
+            .build_f32()
+            .unwrap();
+        let engine = EngineBuilder::new()
+            .no_device(2, SampleRate::Sr48000)
+            .with_realtime_callback(move |_out, _channels| {
+                // Allocates a new buffer on every block
+                let _ = wave.read_pcm_frames(64);
+            })
+            .unwrap();
+        let buffer =
+            crate::data_source::sources::buffer::AudioBufferBuilder::build_f32(2, &[0.5f32; 1024])
+                .unwrap();
+        let mut sound = engine.new_sound_from_source(&buffer).unwrap();
+        sound.play_sound().unwrap();
+
+        let before = violation_count();
+        engine
+            .try_acquire_reader()
+            .unwrap()
+            .read_pcm_frames(256)
+            .unwrap();
+        assert!(violation_count() > before);
+        // The default action panics, which poisons the callback
+        assert!(engine.data_callback_panicked());
+        assert!(!in_audio_callback());
+    }
+}
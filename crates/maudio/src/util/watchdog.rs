@@ -0,0 +1,580 @@
+//! Watchdog that detects a stalled audio callback.
+//!
+//! A driver or backend can hang without reporting an error, leaving a started device that
+//! never calls its data callback again. For kiosk or other long-running applications this
+//! means silence until the process is restarted.
+//!
+//! A [`Watchdog`] runs on its own thread and watches the frames processed by the callback.
+//! If the device is started but no frames are processed for a number of periods, a stall is
+//! recorded and, depending on the [`StallAction`], the device is restarted.
+//!
+//! A hung driver can also block `ma_device_stop`, so restarts run on a separate detached
+//! thread. The watchdog keeps polling while a restart is in progress and never waits for it,
+//! including when it is dropped.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use maudio::engine::engine_builder::EngineBuilder;
+//! # use maudio::util::watchdog::{StallAction, WatchdogBuilder};
+//! # fn main() -> maudio::MaResult<()> {
+//! // The watchdog needs the frames processed notifier
+//! let engine = EngineBuilder::new().with_process_notifier()?;
+//!
+//! let watchdog = WatchdogBuilder::new()
+//!     .periods(16)
+//!     .action(StallAction::Restart)
+//!     .watch_engine(&engine)?;
+//!
+//! loop {
+//!     if watchdog.take_stall() {
+//!         println!("audio callback stalled, device restarted");
+//!     }
+//!     std::thread::sleep(std::time::Duration::from_millis(100));
+//! }
+//! # }
+//! ```
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Weak,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use maudio_sys::ffi as sys;
+
+use crate::{
+    device::{device_ffi, Device, DeviceInner, DeviceRef},
+    engine::{Engine, EngineInner},
+    pcm_frames::PcmFormat,
+    util::proc_notif::ProcFramesNotif,
+    Binding, ErrorKinds, MaResult, MaudioError,
+};
+
+// Used when the device does not report a period size.
+const FALLBACK_PERIOD: Duration = Duration::from_millis(10);
+
+/// What the [`Watchdog`] does when it detects a stall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallAction {
+    /// Only record the stall. See [`Watchdog::take_stall`].
+    Notify,
+    /// Record the stall, then stop and start the device on a separate thread.
+    ///
+    /// If the callback is still stalled after another timeout, the restart is attempted again,
+    /// unless the previous attempt has not returned yet.
+    Restart,
+}
+
+/// Builder for a [`Watchdog`].
+#[derive(Debug, Clone)]
+pub struct WatchdogBuilder {
+    periods: u32,
+    action: StallAction,
+    poll_interval: Option<Duration>,
+}
+
+impl Default for WatchdogBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WatchdogBuilder {
+    /// Creates a builder that reports a stall after 8 periods without a callback,
+    /// with [`StallAction::Notify`].
+    pub fn new() -> Self {
+        Self {
+            periods: 8,
+            action: StallAction::Notify,
+            poll_interval: None,
+        }
+    }
+
+    /// Sets how many device periods can pass without the callback firing before a stall is reported.
+    ///
+    /// Values below 2 are clamped to 2.
+    pub fn periods(&mut self, periods: u32) -> &mut Self {
+        self.periods = periods.max(2);
+        self
+    }
+
+    pub fn action(&mut self, action: StallAction) -> &mut Self {
+        self.action = action;
+        self
+    }
+
+    /// Sets how often the watchdog thread checks the callback.
+    ///
+    /// By default, it checks four times per timeout.
+    pub fn poll_interval(&mut self, interval: Duration) -> &mut Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Starts a watchdog for the device owned by `engine`.
+    ///
+    /// The engine must have a device and a frames processed notifier, see
+    /// [`EngineBuilder::with_process_notifier`](crate::engine::engine_builder::EngineBuilder::with_process_notifier).
+    ///
+    /// The watchdog does not keep the engine alive. It stops on its own once the engine is dropped.
+    pub fn watch_engine(&self, engine: &Engine) -> MaResult<Watchdog> {
+        let Some(notif) = engine.get_data_notifier() else {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "watchdog requires an engine built with a process notifier",
+            )));
+        };
+        let Some(device) = engine.device() else {
+            return Err(MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                "watchdog requires an engine with a device",
+            )));
+        };
+        let period = period_duration(&device);
+        let target = EngineTarget(Arc::downgrade(&engine.0));
+        self.spawn(target, notif, period)
+    }
+
+    /// Starts a watchdog for `device`.
+    ///
+    /// The watchdog does not keep the device alive. It stops on its own once the device is dropped.
+    pub fn watch_device<F: PcmFormat + 'static>(&self, device: &Device<F>) -> MaResult<Watchdog> {
+        let period = period_duration(&DeviceRef::from_ptr(device.to_raw()));
+        let target = DeviceTarget(Arc::downgrade(&device.inner));
+        self.spawn(target, device.get_callback_notifier(), period)
+    }
+
+    fn spawn<T: WatchdogTarget>(
+        &self,
+        target: T,
+        notif: ProcFramesNotif,
+        period: Duration,
+    ) -> MaResult<Watchdog> {
+        let timeout = period.saturating_mul(self.periods);
+        let poll_interval = self.poll_interval.unwrap_or(timeout / 4);
+        let action = self.action;
+        let shared = Arc::new(WatchdogShared::default());
+
+        let thread_shared = shared.clone();
+        let target = Arc::new(target);
+        let thread = std::thread::Builder::new()
+            .name("maudio-watchdog".into())
+            .spawn(move || {
+                let mut detector = StallDetector::new(timeout, notif.frames_processed());
+                while !thread_shared.quit.load(Ordering::Acquire) {
+                    std::thread::park_timeout(poll_interval);
+                    let Some(started) = target.is_started() else {
+                        // The device is gone
+                        break;
+                    };
+                    let now = Instant::now();
+                    match detector.poll(now, notif.frames_processed(), started) {
+                        StallEvent::None => {}
+                        StallEvent::Recovered => {
+                            thread_shared.stalled.store(false, Ordering::Relaxed);
+                        }
+                        StallEvent::Stalled { first } => {
+                            if first {
+                                thread_shared.stalled.store(true, Ordering::Relaxed);
+                                thread_shared.pending.store(true, Ordering::Relaxed);
+                                thread_shared.stalls.fetch_add(1, Ordering::Relaxed);
+                            }
+                            if action == StallAction::Restart {
+                                spawn_restart(&target, &thread_shared);
+                                detector.rearm(Instant::now());
+                            }
+                        }
+                    }
+                }
+            })
+            .map_err(|_| {
+                MaudioError::new_ma_error(ErrorKinds::InvalidOperation(
+                    "failed to spawn the watchdog thread",
+                ))
+            })?;
+        Ok(Watchdog {
+            shared,
+            timeout,
+            thread: Some(thread),
+        })
+    }
+}
+
+// Restarts `target` on a detached thread, unless a previous restart is still running.
+fn spawn_restart<T: WatchdogTarget>(target: &Arc<T>, shared: &Arc<WatchdogShared>) {
+    if shared.restarting.swap(true, Ordering::AcqRel) {
+        // Most likely stuck in the driver, a second attempt would block as well
+        return;
+    }
+    shared.restarts.fetch_add(1, Ordering::Relaxed);
+    let target = target.clone();
+    let restart_shared = shared.clone();
+    let spawned = std::thread::Builder::new()
+        .name("maudio-watchdog-restart".into())
+        .spawn(move || {
+            let _ = target.restart();
+            restart_shared.restarting.store(false, Ordering::Release);
+        });
+    if spawned.is_err() {
+        shared.restarting.store(false, Ordering::Release);
+    }
+}
+
+/// Monitors an audio callback from a background thread.
+///
+/// Created by [`WatchdogBuilder`]. The thread is stopped and joined when the watchdog is dropped.
+/// A restart still in progress is not waited for, see [`StallAction::Restart`].
+///
+/// Stall notifications are accumulated like the other notifiers in [`util`](crate::util): several
+/// stalls before a call to [`Watchdog::take_stall`] are reported once. Use
+/// [`Watchdog::stall_count`] for the total.
+pub struct Watchdog {
+    shared: Arc<WatchdogShared>,
+    timeout: Duration,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct WatchdogShared {
+    quit: AtomicBool,
+    stalled: AtomicBool,
+    pending: AtomicBool,
+    stalls: AtomicU64,
+    restarts: AtomicU64,
+    restarting: AtomicBool,
+}
+
+impl Watchdog {
+    /// Returns `true` if a stall was detected since the last call, and clears it.
+    pub fn take_stall(&self) -> bool {
+        self.shared.pending.swap(false, Ordering::Relaxed)
+    }
+
+    /// Returns `true` while the callback is stalled.
+    ///
+    /// Cleared as soon as the callback processes frames again, or the device is stopped.
+    pub fn is_stalled(&self) -> bool {
+        self.shared.stalled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of stalls detected.
+    pub fn stall_count(&self) -> u64 {
+        self.shared.stalls.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of restarts attempted.
+    pub fn restart_count(&self) -> u64 {
+        self.shared.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` while a restart is in progress.
+    ///
+    /// Stays `true` if the driver never returns from stopping or starting the device.
+    pub fn is_restarting(&self) -> bool {
+        self.shared.restarting.load(Ordering::Acquire)
+    }
+
+    /// Returns the time without processed frames after which a stall is reported.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns `true` if the watchdog thread is still running.
+    ///
+    /// The thread exits on its own once the watched engine or device is dropped.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().map_or(false, |t| !t.is_finished())
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.quit.store(true, Ordering::Release);
+        // The watchdog thread never restarts the device itself, so this does not wait on the driver
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+// The watchdog thread only holds weak references, so it never keeps the device alive.
+// Shared with the restart thread.
+trait WatchdogTarget: Send + Sync + 'static {
+    /// Returns `None` once the target has been dropped.
+    fn is_started(&self) -> Option<bool>;
+    fn restart(&self) -> MaResult<()>;
+}
+
+struct EngineTarget(Weak<EngineInner>);
+
+impl WatchdogTarget for EngineTarget {
+    fn is_started(&self) -> Option<bool> {
+        let engine = Engine(self.0.upgrade()?);
+        let device = engine.device()?;
+        Some(device_ffi::ma_device_is_started(&device))
+    }
+
+    fn restart(&self) -> MaResult<()> {
+        let Some(inner) = self.0.upgrade() else {
+            return Ok(());
+        };
+        let engine = Engine(inner);
+        engine.stop()?;
+        engine.start()
+    }
+}
+
+struct DeviceTarget<F: PcmFormat>(Weak<DeviceInner<F>>);
+
+impl<F: PcmFormat + 'static> WatchdogTarget for DeviceTarget<F> {
+    fn is_started(&self) -> Option<bool> {
+        let inner = self.0.upgrade()?;
+        let device = DeviceRef::from_ptr(inner.to_raw());
+        Some(device_ffi::ma_device_is_started(&device))
+    }
+
+    fn restart(&self) -> MaResult<()> {
+        let Some(inner) = self.0.upgrade() else {
+            return Ok(());
+        };
+        let device = DeviceRef::from_ptr(inner.to_raw());
+        device_ffi::ma_device_stop(&device)?;
+        device_ffi::ma_device_start(&device)
+    }
+}
+
+fn period_duration(device: &DeviceRef<'_>) -> Duration {
+    let raw = unsafe { &*device.to_raw() };
+    let (frames, sample_rate) = if raw.type_ == sys::ma_device_type_ma_device_type_capture
+        || raw.type_ == sys::ma_device_type_ma_device_type_loopback
+    {
+        (
+            raw.capture.internalPeriodSizeInFrames,
+            raw.capture.internalSampleRate,
+        )
+    } else {
+        (
+            raw.playback.internalPeriodSizeInFrames,
+            raw.playback.internalSampleRate,
+        )
+    };
+    if frames == 0 || sample_rate == 0 {
+        return FALLBACK_PERIOD;
+    }
+    Duration::from_secs_f64(frames as f64 / sample_rate as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StallEvent {
+    None,
+    Recovered,
+    Stalled { first: bool },
+}
+
+// Tracks the time since the callback last made progress.
+struct StallDetector {
+    timeout: Duration,
+    last_frames: u64,
+    last_progress: Option<Instant>,
+    stalled: bool,
+}
+
+impl StallDetector {
+    fn new(timeout: Duration, frames: u64) -> Self {
+        Self {
+            timeout,
+            last_frames: frames,
+            last_progress: None,
+            stalled: false,
+        }
+    }
+
+    fn poll(&mut self, now: Instant, frames: u64, started: bool) -> StallEvent {
+        let progressed = frames != self.last_frames;
+        self.last_frames = frames;
+        if !started || progressed {
+            // A stopped device is not expected to call back
+            self.last_progress = if started { Some(now) } else { None };
+            if self.stalled {
+                self.stalled = false;
+                return StallEvent::Recovered;
+            }
+            return StallEvent::None;
+        }
+
+        let since = *self.last_progress.get_or_insert(now);
+        if now.duration_since(since) < self.timeout {
+            return StallEvent::None;
+        }
+        let first = !self.stalled;
+        self.stalled = true;
+        StallEvent::Stalled { first }
+    }
+
+    // Restarts the timeout, e.g. after restarting the device.
+    fn rearm(&mut self, now: Instant) {
+        self.last_progress = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        util::{
+            proc_notif::ProcFramesNotif,
+            watchdog::{StallAction, StallDetector, StallEvent, WatchdogBuilder, WatchdogTarget},
+        },
+        MaResult,
+    };
+
+    #[test]
+    fn test_watchdog_detector_reports_stall_once() {
+        let timeout = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut detector = StallDetector::new(timeout, 0);
+
+        assert_eq!(detector.poll(start, 0, true), StallEvent::None);
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(50), 0, true),
+            StallEvent::None
+        );
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(100), 0, true),
+            StallEvent::Stalled { first: true }
+        );
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(150), 0, true),
+            StallEvent::Stalled { first: false }
+        );
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(160), 512, true),
+            StallEvent::Recovered
+        );
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(200), 512, true),
+            StallEvent::None
+        );
+    }
+
+    #[test]
+    fn test_watchdog_detector_ignores_stopped_device() {
+        let timeout = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut detector = StallDetector::new(timeout, 0);
+
+        assert_eq!(detector.poll(start, 0, false), StallEvent::None);
+        assert_eq!(
+            detector.poll(start + Duration::from_secs(10), 0, false),
+            StallEvent::None
+        );
+        // The timeout starts when the device is seen as started
+        assert_eq!(
+            detector.poll(start + Duration::from_secs(11), 0, true),
+            StallEvent::None
+        );
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(11_050), 0, true),
+            StallEvent::None
+        );
+    }
+
+    #[test]
+    fn test_watchdog_detector_rearm() {
+        let timeout = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut detector = StallDetector::new(timeout, 0);
+
+        detector.poll(start, 0, true);
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(100), 0, true),
+            StallEvent::Stalled { first: true }
+        );
+        detector.rearm(start + Duration::from_millis(100));
+        assert_eq!(
+            detector.poll(start + Duration::from_millis(150), 0, true),
+            StallEvent::None
+        );
+    }
+
+    // Started, never calls back, and hangs in restart like a stuck driver
+    struct HungTarget(std::sync::Mutex<std::sync::mpsc::Receiver<()>>);
+
+    impl WatchdogTarget for HungTarget {
+        fn is_started(&self) -> Option<bool> {
+            Some(true)
+        }
+
+        fn restart(&self) -> MaResult<()> {
+            let _ = self.0.lock().unwrap().recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_watchdog_drop_does_not_wait_for_hung_restart() {
+        let (release, hang) = std::sync::mpsc::channel();
+        let target = HungTarget(std::sync::Mutex::new(hang));
+        let watchdog = WatchdogBuilder::new()
+            .periods(2)
+            .action(StallAction::Restart)
+            .poll_interval(Duration::from_millis(1))
+            .spawn(target, ProcFramesNotif::default(), Duration::from_millis(5))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watchdog.is_restarting() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(watchdog.is_restarting());
+        // Further timeouts do not stack restarts behind the hung one
+        std::thread::sleep(watchdog.timeout() * 4);
+        assert_eq!(watchdog.restart_count(), 1);
+        assert!(watchdog.stall_count() >= 1);
+        assert!(watchdog.is_running());
+
+        let dropped = Instant::now();
+        drop(watchdog);
+        assert!(dropped.elapsed() < Duration::from_secs(1));
+        let _ = release.send(());
+    }
+
+    #[test]
+    fn test_watchdog_engine_requires_process_notifier() {
+        use crate::engine::Engine;
+
+        let engine = Engine::new_for_tests().unwrap();
+        assert!(WatchdogBuilder::new().watch_engine(&engine).is_err());
+    }
+
+    #[cfg(not(feature = "ci-tests"))]
+    #[test]
+    fn test_watchdog_device_running_callback_is_not_stalled() {
+        use crate::device::device_builder::{DeviceBuilder, DeviceBuilderOps};
+
+        let mut device = DeviceBuilder::playback()
+            .f32()
+            .playback_channels(2)
+            .with_callback(|_a, b| {
+                b.fill(0.0);
+            })
+            .unwrap();
+        let watchdog = WatchdogBuilder::new()
+            .periods(50)
+            .watch_device(&device)
+            .unwrap();
+        assert!(watchdog.is_running());
+
+        device.device_start().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!watchdog.is_stalled());
+        device.device_stop().unwrap();
+
+        drop(device);
+        std::thread::sleep(watchdog.timeout() / 2);
+        assert_eq!(watchdog.stall_count(), 0);
+    }
+}
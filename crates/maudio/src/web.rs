@@ -0,0 +1,28 @@
+//! Helpers for the Web Audio backend, when targeting `wasm32-unknown-emscripten`.
+//!
+//! Browsers create the `AudioContext` in a suspended state and only allow it to be resumed
+//! from a user gesture. miniaudio listens for the first `click` or `touchend` event and resumes
+//! every started device at that point. Once the context is running, a
+//! [`DeviceNotificationType::Unlocked`](crate::util::device_notif::DeviceNotificationType::Unlocked)
+//! notification is sent, which can be observed with
+//! [`EngineBuilder::state_notifier`](crate::engine::engine_builder::EngineBuilder::state_notifier).
+//!
+//! The engine must be started before the gesture happens. If the application has its own
+//! input handling (for example a key press), call [`unlock`] from that handler instead.
+//!
+//! Without pthreads the resource manager has no job threads. Its jobs are processed by the
+//! engine while it renders, so prefer decoding sounds at load time over asynchronous loading.
+use std::os::raw::c_char;
+
+extern "C" {
+    fn emscripten_run_script(script: *const c_char);
+}
+
+/// Resumes the `AudioContext` of every started device.
+///
+/// Must be called from inside a user gesture event handler, otherwise the browser
+/// keeps the context suspended.
+pub fn unlock() {
+    let script = b"miniaudio.unlock();\0";
+    unsafe { emscripten_run_script(script.as_ptr() as *const c_char) };
+}